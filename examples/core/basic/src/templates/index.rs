@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{Html, RenderFnResultWithCause, SsrNode, Template};
 use sycamore::prelude::{view, Scope, View};
 
@@ -29,10 +31,7 @@ pub fn head(cx: Scope, _props: IndexPageState) -> View<SsrNode> {
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(
-    _path: String,
-    _locale: String,
-) -> RenderFnResultWithCause<IndexPageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<IndexPageState> {
     Ok(IndexPageState {
         greeting: "Hello World!".to_string(),
     })
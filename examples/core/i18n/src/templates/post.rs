@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{link, RenderFnResult, RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Html, Scope, View};
 
@@ -32,15 +34,12 @@ pub fn get_template<G: Html>() -> Template<G> {
 }
 
 #[perseus::build_state]
-pub async fn get_static_props(
-    path: String,
-    _locale: String,
-) -> RenderFnResultWithCause<PostPageState> {
+pub async fn get_static_props(info: StateGeneratorInfo) -> RenderFnResultWithCause<PostPageState> {
     // This is just an example
-    let title = urlencoding::decode(&path).unwrap();
+    let title = urlencoding::decode(&info.path).unwrap();
     let content = format!(
         "This is a post entitled '{}'. Its original slug was '{}'.",
-        title, path
+        title, info.path
     );
 
     Ok(PostPageState {
@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{Html, RenderFnResultWithCause, Template};
 use sycamore::prelude::*;
 
@@ -98,10 +100,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(
-    _path: String,
-    _locale: String,
-) -> RenderFnResultWithCause<IndexProps> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<IndexProps> {
     Ok(IndexProps {
         username: "".to_string(),
     })
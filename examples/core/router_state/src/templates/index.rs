@@ -14,6 +14,7 @@ pub fn router_state_page<G: Html>(cx: Scope) -> View<G> {
         RouterLoadState::Loading {
             template_name,
             path,
+            ..
         } => format!("Loading {} (template: {}).", path, template_name),
         RouterLoadState::Server => "We're on the server.".to_string(),
     });
@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{Html, RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Scope, SsrNode, View};
 
@@ -34,10 +36,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(
-    _path: String,
-    _locale: String,
-) -> RenderFnResultWithCause<IndexPageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<IndexPageState> {
     Ok(IndexPageState {
         username: "".to_string(),
     })
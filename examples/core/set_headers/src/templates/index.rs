@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{Html, RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Scope, SsrNode, View};
 
@@ -25,23 +27,27 @@ pub fn get_template<G: Html>() -> Template<G> {
         .template(index_page)
         .head(head)
         .build_state_fn(get_build_state)
-        .set_headers_fn(set_headers)
+        .set_headers_fn_with_info(set_headers)
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         greeting: "Hello World!".to_string(),
     })
 }
 
-// For legacy reasons, this takes an `Option<T>`, but, if you're generating
-// state, it will always be here In v0.4.0, this will be updated to take just
-// your page's state (if it has any) Unfortunately, this return type does have
-// to be fully qualified, or you have to import it with a server-only
-// target-gate
+// This can take your page's state (if it has any) and/or information about the
+// page, like its path and locale, and the query string of the request (which
+// has to be given as `perseus::template::PageInfo` for the macro to recognize
+// it, and means the function goes to `.set_headers_fn_with_info()`).
+// Unfortunately, this return type does have to be fully qualified, or you have
+// to import it with a server-only target-gate
 #[perseus::set_headers]
-pub fn set_headers(state: PageState) -> perseus::http::header::HeaderMap {
+pub fn set_headers(
+    state: PageState,
+    info: perseus::template::PageInfo,
+) -> perseus::http::header::HeaderMap {
     // These imports are only available on the server-side, which this function is
     // automatically gated to
     use perseus::http::header::{HeaderMap, HeaderName};
@@ -51,5 +57,13 @@ pub fn set_headers(state: PageState) -> perseus::http::header::HeaderMap {
         HeaderName::from_lowercase(b"x-greeting").unwrap(),
         state.greeting.parse().unwrap(),
     );
+    // Apps that don't use i18n render everything in the dummy `xx-XX` locale,
+    // which isn't a real language
+    if info.locale != "xx-XX" {
+        map.insert(
+            HeaderName::from_lowercase(b"content-language").unwrap(),
+            info.locale.parse().unwrap(),
+        );
+    }
     map
 }
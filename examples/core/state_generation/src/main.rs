@@ -14,4 +14,6 @@ pub fn main<G: Html>() -> PerseusApp<G> {
         .template(crate::templates::revalidation_and_incremental_generation::get_template)
         .template(crate::templates::amalgamation::get_template)
         .error_pages(crate::error_pages::get_error_pages)
+        // There's no landing page in this example, so we'll send people to the first one
+        .index_redirect("/build_state")
 }
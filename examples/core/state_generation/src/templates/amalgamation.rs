@@ -1,6 +1,6 @@
-#[cfg(not(target_arch = "wasm32"))]
-use perseus::Request;
 use perseus::{RenderFnResultWithCause, Template};
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::{Request, StateGeneratorInfo};
 use sycamore::prelude::{view, Html, Scope, View};
 
 #[perseus::make_rx(PageStateRx)]
@@ -27,10 +27,11 @@ pub fn get_template<G: Html>() -> Template<G> {
 
 #[perseus::amalgamate_states]
 pub async fn amalgamate_states(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
     build_state: PageState,
     req_state: PageState,
+    // This could be used to only use the request state for certain users, for example
+    _req: Request,
 ) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         message: format!(
@@ -41,7 +42,7 @@ pub async fn amalgamate_states(
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         message: "Hello from the build process!".to_string(),
     })
@@ -49,8 +50,7 @@ pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWi
 
 #[perseus::request_state]
 pub async fn get_request_state(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
     _req: Request,
 ) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{RenderFnResult, RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Html, Scope, View};
 
@@ -32,11 +34,11 @@ pub fn get_template<G: Html>() -> Template<G> {
 // `build_paths` followed by the spcific path we're building for (as exported
 // from `get_build_paths`)
 #[perseus::build_state]
-pub async fn get_build_state(path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
-    let title = path.clone();
+pub async fn get_build_state(info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
+    let title = info.path.clone();
     let content = format!(
         "This is a post entitled '{}'. Its original slug was '{}'.",
-        &title, &path
+        &title, &info.path
     );
 
     Ok(PageState { title, content })
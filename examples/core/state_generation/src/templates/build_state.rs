@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Html, Scope, View};
 
@@ -24,7 +26,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 // Note that this function is asynchronous, so we can do work like fetching from
 // a server or the like here (see the `demo/fetching` example)
 #[perseus::build_state]
-pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         greeting: "Hello World!".to_string(),
     })
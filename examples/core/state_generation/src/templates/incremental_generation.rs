@@ -1,6 +1,8 @@
 // This is exactly the same as the build paths example except for a few lines
 // and some names
 
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{blame_err, RenderFnResult, RenderFnResultWithCause, Template};
 use sycamore::prelude::{view, Html, Scope, View};
 
@@ -40,20 +42,20 @@ pub fn get_template<G: Html>() -> Template<G> {
 // `incremental_generation` followed by the spcific path we're building for (as
 // exported from `get_build_paths`)
 #[perseus::build_state]
-pub async fn get_build_state(path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     // This path is illegal, and can't be rendered
     // Because we're using incremental generation, we could gte literally anything
     // as the `path`
-    if path == "incremental_generation/tests" {
+    if info.path == "incremental_generation/tests" {
         // This tells Perseus to return an error that's the client's fault, with the
         // HTTP status code 404 (not found) and the message 'illegal page'
         // You could return this error manually, but this is more convenient
         blame_err!(client, 404, "illegal page");
     }
-    let title = path.clone();
+    let title = info.path.clone();
     let content = format!(
         "This is a post entitled '{}'. Its original slug was '{}'.",
-        &title, &path
+        &title, &info.path
     );
 
     Ok(PageState { title, content })
@@ -1,6 +1,6 @@
-#[cfg(not(target_arch = "wasm32"))]
-use perseus::Request;
 use perseus::{RenderFnResultWithCause, Template};
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::{Request, StateGeneratorInfo};
 use sycamore::prelude::{view, Html, Scope, View};
 
 #[perseus::make_rx(PageStateRx)]
@@ -27,8 +27,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 
 #[perseus::request_state]
 pub async fn get_request_state(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
     // Unlike in build state, in request state we get access to the information that the user sent
     // with their HTTP request IN this example, we extract the browser's reporting of their IP
     // address and display it to them
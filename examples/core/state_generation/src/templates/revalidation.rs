@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{RenderFnResultWithCause, Template};
 use std::time::Duration;
 use sycamore::prelude::{view, Html, Scope, View};
@@ -30,7 +32,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 
 // This will get the system time when the app was built
 #[perseus::build_state]
-pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         time: format!("{:?}", std::time::SystemTime::now()),
     })
@@ -41,8 +43,7 @@ pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWi
 // to check if we should actually revalidate a page
 #[perseus::should_revalidate]
 pub async fn should_revalidate(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
     _req: perseus::Request,
 ) -> RenderFnResultWithCause<bool> {
     // For simplicity's sake, this will always say we should revalidate, but you
@@ -1,6 +1,8 @@
 // This page exists mostly for testing revalidation together with incremental
 // generation (because the two work in complex ways together)
 
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{RenderFnResult, RenderFnResultWithCause, Template};
 use std::time::Duration;
 use sycamore::prelude::{view, Html, Scope, View};
@@ -38,7 +40,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 
 // This will get the system time when the app was built
 #[perseus::build_state]
-pub async fn get_build_state(_path: String, _locale: String) -> RenderFnResultWithCause<PageState> {
+pub async fn get_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<PageState> {
     Ok(PageState {
         time: format!("{:?}", std::time::SystemTime::now()),
     })
@@ -54,8 +56,7 @@ pub async fn get_build_paths() -> RenderFnResult<Vec<String>> {
 // to check if we should actually revalidate a page
 #[perseus::should_revalidate]
 pub async fn should_revalidate(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
     _req: perseus::Request,
 ) -> RenderFnResultWithCause<bool> {
     // For simplicity's sake, this will always say we should revalidate, but you
@@ -2,10 +2,15 @@ use crate::initial_load::initial_load;
 use crate::page_data::page_data;
 use crate::translations::translations;
 use actix_files::{Files, NamedFile};
-use actix_web::{web, HttpRequest};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
 use perseus::{
     i18n::TranslationsManager,
-    server::{get_render_cfg, ServerOptions, ServerProps},
+    server::{
+        get_render_cfg, get_static_alias_mount, get_static_file, prepare_maintenance_page,
+        warm_up_incremental, ServerOptions, ServerProps, MAINTENANCE_ENDPOINT,
+    },
     stores::MutableStore,
 };
 
@@ -18,17 +23,48 @@ async fn wasm_bundle(opts: web::Data<ServerOptions>) -> std::io::Result<NamedFil
 async fn wasm_js_bundle(opts: web::Data<ServerOptions>) -> std::io::Result<NamedFile> {
     NamedFile::open(&opts.wasm_js_bundle)
 }
-async fn static_alias(
+/// Serves the static directory and static aliases, which all go through this
+/// handler so that the app's static content options are respected.
+async fn static_content(
     opts: web::Data<ServerOptions>,
     req: HttpRequest,
-) -> std::io::Result<NamedFile> {
-    let filename = opts.static_aliases.get(req.path());
-    let filename = match filename {
-        Some(filename) => filename,
-        // If the path doesn't exist, then the alias is not found
+) -> std::io::Result<HttpResponse> {
+    let file = match get_static_file(&opts, req.path()) {
+        Some(file) => file,
         None => return Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
     };
-    NamedFile::open(filename)
+    let content_type = opts.static_content.get_content_type(&file);
+    let req_headers = req.headers().keys().map(|name| name.as_str());
+    let mut res = match opts.static_content.read_small_file(&file, req_headers) {
+        Some(contents) => HttpResponse::Ok().body(contents),
+        // This streams the file, handling conditional and range requests for us
+        None => NamedFile::open(&file)?.into_response(&req),
+    };
+    if let Ok(content_type) = HeaderValue::from_str(&content_type) {
+        res.headers_mut().insert(header::CONTENT_TYPE, content_type);
+    }
+
+    Ok(res)
+}
+async fn maintenance(
+    opts: web::Data<ServerOptions>,
+    req: HttpRequest,
+    body: String,
+) -> HttpResponse {
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok());
+    let status = opts.maintenance.handle_toggle(authorization, &body);
+    HttpResponse::build(StatusCode::from_u16(status).unwrap()).finish()
+}
+async fn robots_txt(opts: web::Data<ServerOptions>) -> HttpResponse {
+    match &opts.robots_txt {
+        Some(robots_txt) => HttpResponse::Ok()
+            .content_type("text/plain")
+            .body(robots_txt.to_string()),
+        None => HttpResponse::NotFound().finish(),
+    }
 }
 
 /// Configures an existing Actix Web app for Perseus. This returns a function
@@ -54,6 +90,25 @@ pub async fn configurer<M: MutableStore + 'static, T: TranslationsManager + 'sta
         .get_build_state()
         .await
         .expect("Couldn't generate global state.");
+    // Pre-render any incrementally generated pages the user wants ready immediately
+    warm_up_incremental(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
+    prepare_maintenance_page(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
 
     move |cfg: &mut web::ServiceConfig| {
         cfg
@@ -89,16 +144,32 @@ pub async fn configurer<M: MutableStore + 'static, T: TranslationsManager + 'sta
             // This allows gettting JS interop snippets (including ones that are supposedly
             // 'inlined') These won't change, so they can be set as a filesystem
             // dependency safely
-            .service(Files::new("/.perseus/snippets", &opts.snippets));
-        // Now we add support for any static content the user wants to provide
-        if let Some(static_dir) = &opts.static_dir {
-            cfg.service(Files::new("/.perseus/static", static_dir));
+            .service(Files::new("/.perseus/snippets", &opts.snippets))
+            // This lets maintenance mode be toggled while the server is running
+            .route(MAINTENANCE_ENDPOINT, web::post().to(maintenance));
+        // Now we add support for any static content the user wants to provide (this and
+        // static aliases all go through the same handler, which works out what to
+        // serve)
+        if opts.static_dir.is_some() {
+            cfg.route("/.perseus/static/{path:.*}", web::get().to(static_content));
+        }
+        // Serve the generated `robots.txt` file if there is one
+        if opts.robots_txt.is_some() {
+            cfg.route("/robots.txt", web::get().to(robots_txt));
         }
         // And finally add in aliases for static content as necessary
-        for (url, _static_path) in opts.static_aliases.iter() {
-            // This handler indexes the path of the request in `opts.static_aliases` to
-            // figure out what to serve
-            cfg.route(url, web::get().to(static_alias));
+        for url in opts.static_aliases.keys() {
+            match get_static_alias_mount(url) {
+                Some(mount) => {
+                    cfg.route(
+                        &format!("{}/{{path:.*}}", mount),
+                        web::get().to(static_content),
+                    );
+                }
+                None => {
+                    cfg.route(url, web::get().to(static_content));
+                }
+            }
         }
         // For everything else, we'll serve the app shell directly
         // This has to be done AFTER everything else, because it will match anything
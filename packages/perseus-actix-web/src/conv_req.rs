@@ -1,5 +1,5 @@
 use crate::errors::*;
-use perseus::{HttpRequest, Request};
+use perseus::{server::RemoteAddr, HttpRequest, Request};
 
 /// Converts an Actix Web request into an `http::request`.
 pub fn convert_req(raw: &actix_web::HttpRequest) -> Result<Request, Error> {
@@ -9,7 +9,7 @@ pub fn convert_req(raw: &actix_web::HttpRequest) -> Result<Request, Error> {
         builder = builder.header(name, val);
     }
 
-    builder
+    let mut req = builder
         .uri(raw.uri())
         .method(raw.method())
         .version(raw.version())
@@ -17,5 +17,10 @@ pub fn convert_req(raw: &actix_web::HttpRequest) -> Result<Request, Error> {
         // Any custom data should therefore be sent in headers (if you're doing that, consider a
         // dedicated API)
         .body(())
-        .map_err(|err| Error::RequestConversionFailed { source: err })
+        .map_err(|err| Error::RequestConversionFailed { source: err })?;
+    if let Some(remote_addr) = raw.peer_addr() {
+        req.extensions_mut().insert(RemoteAddr(remote_addr));
+    }
+
+    Ok(req)
 }
@@ -0,0 +1,18 @@
+use actix_web::{error::ErrorForbidden, http::header::COOKIE, HttpRequest};
+use perseus::csrf::CSRF_HEADER;
+
+/// Checks that the given request has a valid CSRF token if it has an unsafe
+/// method (e.g. `POST`), returning a `403 Forbidden` error if it doesn't. This
+/// should be called at the start of any mutating handlers you add to your
+/// server, and it requires CSRF protection to have been enabled on your
+/// `PerseusApp`, so that users are actually given tokens.
+pub fn verify_csrf(req: &HttpRequest) -> Result<(), actix_web::Error> {
+    let headers = req.headers();
+    let cookie_headers = headers
+        .get_all(COOKIE)
+        .filter_map(|header| header.to_str().ok());
+    let token = headers
+        .get(CSRF_HEADER)
+        .and_then(|header| header.to_str().ok());
+    perseus::csrf::verify_csrf(req.method().as_str(), cookie_headers, token).map_err(ErrorForbidden)
+}
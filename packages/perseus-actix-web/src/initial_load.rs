@@ -1,19 +1,29 @@
 use crate::conv_req::convert_req;
-use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use actix_web::{
+    http::{
+        header::{HeaderValue, CACHE_CONTROL, SET_COOKIE, VARY},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse,
+};
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    csrf::get_or_mint_csrf_token,
+    errors::{err_to_redirect, err_to_status_code, ServerError},
+    experiments::{assign_variants, get_variants},
     i18n::{TranslationsManager, Translator},
-    router::{match_route_atomic, RouteInfoAtomic, RouteVerdictAtomic},
+    router::{
+        get_index_redirect, match_route_atomic, RenderCfg, RouteInfoAtomic, RouteVerdictAtomic,
+    },
     server::{
-        build_error_page, get_page_for_template, get_path_slice, GetPageProps, HtmlShell,
-        ServerOptions,
+        attach_request_context, build_error_page, build_server_error_page, get_page_for_template,
+        get_path_slice, GetPageProps, HtmlShell, ServerOptions,
     },
     stores::{ImmutableStore, MutableStore},
-    utils::get_path_prefix_server,
+    template::PageInfo,
+    utils::{get_path_prefix_server, is_private_response, PRIVATE_CACHE_CONTROL},
     ErrorPages, SsrNode,
 };
-use std::collections::HashMap;
 use std::rc::Rc;
 
 /// Builds on the internal Perseus primitives to provide a utility function that
@@ -36,26 +46,66 @@ fn return_error_page(
 /// The handler for calls to any actual pages (first-time visits), which will
 /// render the appropriate HTML and then interpolate it into the app shell.
 #[allow(clippy::too_many_arguments)]
-pub async fn initial_load<M: MutableStore, T: TranslationsManager>(
+pub async fn initial_load<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     req: HttpRequest,
     opts: web::Data<ServerOptions>,
     html_shell: web::Data<HtmlShell>,
-    render_cfg: web::Data<HashMap<String, String>>,
+    render_cfg: web::Data<RenderCfg>,
     immutable_store: web::Data<ImmutableStore>,
     mutable_store: web::Data<M>,
     translations_manager: web::Data<T>,
     global_state: web::Data<Option<String>>,
 ) -> HttpResponse {
+    // In maintenance mode, every page is replaced by the maintenance page
+    if let Some(maintenance_page) = opts.maintenance.get_page() {
+        return HttpResponse::ServiceUnavailable()
+            .content_type("text/html")
+            .insert_header(("Retry-After", opts.maintenance.retry_after()))
+            .body(maintenance_page);
+    }
     let templates = &opts.templates_map;
     let error_pages = &opts.error_pages;
     let path = req.path();
     let path_slice = get_path_slice(path);
+    // In development, error pages should use the latest translations
+    opts.error_translations
+        .refresh(path, translations_manager.get_ref())
+        .await;
     // Create a closure to make returning error pages easier (most have the same
-    // data)
+    // data, and they should be in the locale the user requested, if possible)
     let html_err = |status: u16, err: &str| {
-        return return_error_page(path, status, err, None, error_pages, html_shell.get_ref());
+        let translator = opts.error_translations.get_translator(path);
+        return return_error_page(
+            path,
+            status,
+            err,
+            translator,
+            error_pages,
+            html_shell.get_ref(),
+        );
+    };
+    // Errors from rendering pages might have error pages of their own
+    let server_err = |err: &ServerError| {
+        let translator = opts.error_translations.get_translator(path);
+        let html =
+            build_server_error_page(path, err, translator, error_pages, html_shell.get_ref());
+        HttpResponse::build(StatusCode::from_u16(err_to_status_code(err)).unwrap())
+            .content_type("text/html")
+            .body(html)
     };
 
+    // If this is the root of the app and it redirects elsewhere, we can send the
+    // user straight there
+    if let Some(to) = opts
+        .index_redirect
+        .as_ref()
+        .and_then(|index_redirect| get_index_redirect(&path_slice, &opts.locales, index_redirect))
+    {
+        return HttpResponse::Found()
+            .insert_header(("Location", format!("{}/{}", get_path_prefix_server(), to)))
+            .finish();
+    }
+
     // Run the routing algorithms on the path to figure out which template we need
     let verdict = match_route_atomic(&path_slice, render_cfg.get_ref(), templates, &opts.locales);
     match verdict {
@@ -70,50 +120,135 @@ pub async fn initial_load<M: MutableStore, T: TranslationsManager>(
             // We need to turn the Actix Web request into one acceptable for Perseus (uses
             // `http` internally)
             let http_req = convert_req(&req);
-            let http_req = match http_req {
+            let mut http_req = match http_req {
                 Ok(http_req) => http_req,
                 // If this fails, the client request is malformed, so it's a 400
                 Err(err) => {
                     return html_err(400, &fmt_err(&err));
                 }
             };
-            // Actually render the page as we would if this weren't an initial load
-            let page_data = get_page_for_template(
-                GetPageProps {
-                    raw_path: &path,
-                    locale: &locale,
-                    was_incremental_match,
-                    req: http_req,
-                    global_state: &global_state,
-                    immutable_store: immutable_store.get_ref(),
-                    mutable_store: mutable_store.get_ref(),
-                    translations_manager: translations_manager.get_ref(),
-                },
-                template,
-            )
-            .await;
-            let page_data = match page_data {
-                Ok(page_data) => page_data,
-                // We parse the error to return an appropriate status code
-                Err(err) => {
-                    return html_err(err_to_status_code(&err), &fmt_err(&err));
-                }
+            attach_request_context(&mut http_req, &opts);
+            // Work out which A/B experiment variants this user should get
+            let set_cookie = assign_variants(&opts.experiments, &mut http_req);
+            let variants = get_variants(&http_req);
+            // Give the user a CSRF token if they don't have one (and it's enabled)
+            let csrf = if opts.csrf_protection {
+                Some(get_or_mint_csrf_token(&http_req))
+            } else {
+                None
+            };
+            // Load the user's session, if sessions are enabled (if this fails, we still
+            // need to set the other cookies, so the error page is sent below)
+            let session = match &opts.sessions {
+                Some(sessions) => sessions.attach(&mut http_req).await.map(Some),
+                None => Ok(None),
             };
+            let info = PageInfo::for_request(&path, &locale, &http_req);
+            // Every outcome of rendering (including errors and redirects) needs the session
+            // saved and cookies set (e.g. after logging a user in), so this block only
+            // produces the response, and that's all done once it's finished
+            let mut http_res = async {
+                let session = match &session {
+                    Ok(session) => session,
+                    Err(err) => return html_err(500, &fmt_err(err)),
+                };
+                // Actually render the page as we would if this weren't an initial load
+                let page_data = get_page_for_template(
+                    GetPageProps {
+                        raw_path: &path,
+                        locale: &locale,
+                        was_incremental_match,
+                        req: http_req,
+                        global_state: &global_state,
+                        immutable_store: immutable_store.get_ref(),
+                        mutable_store: mutable_store.get_ref(),
+                        translations_manager: translations_manager.get_ref(),
+                        plugins: &opts.plugins,
+                        incremental_warmup: &opts.incremental_warmup,
+                        templates: &opts.templates_map,
+                        error_reporter: &opts.error_reporter,
+                        state_fn_timeout: opts.state_fn_timeout,
+                        build_redirects: &opts.build_redirects,
+                    },
+                    template,
+                )
+                .await;
+                match page_data {
+                    Ok(page_data) => {
+                        let mut final_html = html_shell
+                            .get_ref()
+                            .clone()
+                            .page_data(&page_data, &global_state)
+                            .variants(&variants);
+                        if let Some(session) = session {
+                            final_html = final_html.session(&session.snapshot());
+                        }
+                        if let Some((token, _)) = &csrf {
+                            final_html = final_html.csrf_token(token);
+                        }
+
+                        let mut http_res = HttpResponse::Ok();
+                        http_res.content_type("text/html");
+                        // Generate and add HTTP headers
+                        for (key, val) in template.get_headers(page_data.state, info) {
+                            http_res.insert_header((key.unwrap(), val));
+                        }
+
+                        http_res.body(final_html.to_string())
+                    }
+                    // We parse the error to return an appropriate status code (or redirect)
+                    Err(err) => match err_to_redirect(&err) {
+                        // `Redirect::to` validates these, but a bad one still shouldn't panic
+                        // (Actix Web will send a 500 itself if the location is invalid)
+                        Some(redirect) => match StatusCode::from_u16(redirect.status()) {
+                            Ok(status) => HttpResponse::build(status)
+                                .insert_header(("Location", redirect.location()))
+                                .finish(),
+                            Err(_) => html_err(500, "invalid redirect"),
+                        },
+                        None => server_err(&err),
+                    },
+                }
+            }
+            .await;
 
-            let final_html = html_shell
-                .get_ref()
-                .clone()
-                .page_data(&page_data, &global_state)
-                .to_string();
+            // Save any changes made to the session while rendering
+            let session = session.ok().flatten();
+            let session_cookie = match (&opts.sessions, &session) {
+                (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                    Ok(set_cookie) => set_cookie,
+                    Err(err) => {
+                        http_res = html_err(500, &fmt_err(&err));
+                        None
+                    }
+                },
+                _ => None,
+            };
+            // Anything that depends on the session, embeds a CSRF token, or embeds the user's
+            // experiment variants mustn't be cached for other users
+            let private = is_private_response(
+                session.as_ref(),
+                session_cookie.is_some(),
+                &variants,
+                csrf.is_some(),
+            );
 
-            let mut http_res = HttpResponse::Ok();
-            http_res.content_type("text/html");
-            // Generate and add HTTP headers
-            for (key, val) in template.get_headers(page_data.state) {
-                http_res.insert_header((key.unwrap(), val));
+            let csrf_cookie = csrf.and_then(|(_, csrf_cookie)| csrf_cookie);
+            let cookies = [set_cookie, csrf_cookie, session_cookie];
+            let headers = http_res.headers_mut();
+            for cookie in cookies.into_iter().flatten() {
+                // We generated all these cookies, so they'll be valid header values
+                headers.append(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
+            }
+            if private {
+                headers.insert(
+                    CACHE_CONTROL,
+                    HeaderValue::from_static(PRIVATE_CACHE_CONTROL),
+                );
+                headers.append(VARY, HeaderValue::from_static("Cookie"));
             }
 
-            http_res.body(final_html)
+            http_res
         }
         // For locale detection, we don't know the user's locale, so there's not much we can do
         // except send down the app shell, which will do the rest and fetch from `.perseus/page/...`
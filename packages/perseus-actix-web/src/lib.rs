@@ -10,6 +10,7 @@ documentation, and this should mostly be used as a secondary reference source. Y
 
 mod configurer;
 mod conv_req;
+mod csrf;
 #[cfg(feature = "dflt-server")]
 mod dflt_server;
 pub mod errors;
@@ -18,6 +19,7 @@ mod page_data;
 mod translations;
 
 pub use crate::configurer::configurer;
+pub use crate::csrf::verify_csrf;
 #[cfg(feature = "dflt-server")]
 pub use dflt_server::dflt_server;
 pub use perseus::server::ServerOptions;
@@ -1,11 +1,23 @@
 use crate::conv_req::convert_req;
-use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use actix_web::{
+    http::{
+        header::{HeaderValue, CACHE_CONTROL, SET_COOKIE, VARY},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse,
+};
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    errors::{err_to_body, err_to_redirect, err_to_status_code},
+    experiments::{assign_variants, get_variants},
     i18n::TranslationsManager,
-    server::{get_page_for_template, GetPageProps, ServerOptions},
+    server::{
+        attach_request_context, get_page_for_template, get_redirect_page_data, set_req_query,
+        GetPageProps, ServerOptions,
+    },
     stores::{ImmutableStore, MutableStore},
+    template::PageInfo,
+    utils::{is_private_response, PRIVATE_CACHE_CONTROL},
 };
 use serde::Deserialize;
 
@@ -13,12 +25,14 @@ use serde::Deserialize;
 pub struct PageDataReq {
     pub template_name: String,
     pub was_incremental_match: bool,
+    #[serde(default)]
+    pub query: String,
 }
 
 /// The handler for calls to `.perseus/page/*`. This will manage returning
 /// errors and the like.
 #[allow(clippy::too_many_arguments)]
-pub async fn page_data<M: MutableStore, T: TranslationsManager>(
+pub async fn page_data<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     req: HttpRequest,
     opts: web::Data<ServerOptions>,
     immutable_store: web::Data<ImmutableStore>,
@@ -27,11 +41,18 @@ pub async fn page_data<M: MutableStore, T: TranslationsManager>(
     global_state: web::Data<Option<String>>,
     web::Query(query_params): web::Query<PageDataReq>,
 ) -> HttpResponse {
+    // Nothing can be loaded in maintenance mode
+    if opts.maintenance.is_enabled() {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header(("Retry-After", opts.maintenance.retry_after()))
+            .body("the server is down for maintenance");
+    }
     let templates = &opts.templates_map;
     let locale = req.match_info().query("locale");
     let PageDataReq {
         template_name,
         was_incremental_match,
+        query,
     } = query_params;
     // Check if the locale is supported
     if opts.locales.is_supported(locale) {
@@ -39,7 +60,7 @@ pub async fn page_data<M: MutableStore, T: TranslationsManager>(
         // We need to turn the Actix Web request into one acceptable for Perseus (uses
         // `http` internally)
         let http_req = convert_req(&req);
-        let http_req = match http_req {
+        let mut http_req = match http_req {
             Ok(http_req) => http_req,
             // If this fails, the client request is malformed, so it's a 400
             Err(err) => {
@@ -56,37 +77,101 @@ pub async fn page_data<M: MutableStore, T: TranslationsManager>(
                 return HttpResponse::InternalServerError().body("template not found".to_string());
             }
         };
-        let page_data = get_page_for_template(
-            GetPageProps {
-                raw_path: path,
-                locale,
-                was_incremental_match,
-                req: http_req,
-                global_state: &global_state,
-                immutable_store: immutable_store.get_ref(),
-                mutable_store: mutable_store.get_ref(),
-                translations_manager: translations_manager.get_ref(),
-            },
-            template,
-        )
+        attach_request_context(&mut http_req, &opts);
+        let set_cookie = assign_variants(&opts.experiments, &mut http_req);
+        let variants = get_variants(&http_req);
+        // Load the user's session, if sessions are enabled (if this fails, we still need
+        // to set the variants cookie, so the error is sent below)
+        let session = match &opts.sessions {
+            Some(sessions) => sessions.attach(&mut http_req).await.map(Some),
+            None => Ok(None),
+        };
+        let http_req = set_req_query(http_req, &query);
+        let info = PageInfo::for_request(path, locale, &http_req);
+        // Every outcome of rendering (including errors and redirects) needs the session
+        // saved and cookies set (e.g. after logging a user in), so this block only
+        // produces the response, and that's all done once it's finished
+        let mut http_res = async {
+            if let Err(err) = &session {
+                return HttpResponse::InternalServerError().body(fmt_err(err));
+            }
+            let page_data = get_page_for_template(
+                GetPageProps {
+                    raw_path: path,
+                    locale,
+                    was_incremental_match,
+                    req: http_req,
+                    global_state: &global_state,
+                    immutable_store: immutable_store.get_ref(),
+                    mutable_store: mutable_store.get_ref(),
+                    translations_manager: translations_manager.get_ref(),
+                    plugins: &opts.plugins,
+                    incremental_warmup: &opts.incremental_warmup,
+                    templates: &opts.templates_map,
+                    error_reporter: &opts.error_reporter,
+                    state_fn_timeout: opts.state_fn_timeout,
+                    build_redirects: &opts.build_redirects,
+                },
+                template,
+            )
+            .await;
+            match page_data {
+                Ok(page_data) => {
+                    let mut http_res = HttpResponse::Ok();
+                    http_res.content_type("text/html");
+                    // Generate and add HTTP headers
+                    for (key, val) in template.get_headers(page_data.state.clone(), info) {
+                        http_res.insert_header((key.unwrap(), val));
+                    }
+                    http_res.body(serde_json::to_string(&page_data).unwrap())
+                }
+                // The app will navigate to wherever the page redirects to
+                Err(err) if err_to_redirect(&err).is_some() => HttpResponse::Ok()
+                    .content_type("text/html")
+                    .body(get_redirect_page_data(err_to_redirect(&err).unwrap())),
+                // We parse the error to return an appropriate status code
+                Err(err) => {
+                    // The body tells the app what kind of error this is
+                    let (content_type, body) = err_to_body(path, &err);
+                    HttpResponse::build(StatusCode::from_u16(err_to_status_code(&err)).unwrap())
+                        .content_type(content_type)
+                        .body(body)
+                }
+            }
+        }
         .await;
-        match page_data {
-            Ok(page_data) => {
-                let mut http_res = HttpResponse::Ok();
-                http_res.content_type("text/html");
-                // Generate and add HTTP headers
-                for (key, val) in template.get_headers(page_data.state.clone()) {
-                    http_res.insert_header((key.unwrap(), val));
+
+        // Save any changes made to the session while rendering
+        let session = session.ok().flatten();
+        let session_cookie = match (&opts.sessions, &session) {
+            (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                Ok(set_cookie) => set_cookie,
+                Err(err) => {
+                    http_res = HttpResponse::InternalServerError().body(fmt_err(&err));
+                    None
                 }
+            },
+            _ => None,
+        };
+        // Anything that depends on the session or the user's experiment variants mustn't be
+        // cached for other users
+        let private =
+            is_private_response(session.as_ref(), session_cookie.is_some(), &variants, false);
 
-                http_res.body(serde_json::to_string(&page_data).unwrap())
-            }
-            // We parse the error to return an appropriate status code
-            Err(err) => {
-                HttpResponse::build(StatusCode::from_u16(err_to_status_code(&err)).unwrap())
-                    .body(fmt_err(&err))
-            }
+        let headers = http_res.headers_mut();
+        for cookie in [set_cookie, session_cookie].into_iter().flatten() {
+            // We generated all these cookies, so they'll be valid header values
+            headers.append(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
         }
+        if private {
+            headers.insert(
+                CACHE_CONTROL,
+                HeaderValue::from_static(PRIVATE_CACHE_CONTROL),
+            );
+            headers.append(VARY, HeaderValue::from_static("Cookie"));
+        }
+
+        http_res
     } else {
         HttpResponse::NotFound().body("locale not supported".to_string())
     }
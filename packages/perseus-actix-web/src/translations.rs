@@ -1,12 +1,15 @@
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
 use fmterr::fmt_err;
 use perseus::i18n::TranslationsManager;
-use perseus::server::ServerOptions;
+use perseus::server::{etag_matches, get_etag, ServerOptions};
 
 /// The handler for calls to `.perseus/translations/{locale}`. This will manage
 /// returning errors and the like. THe JSON body returned from this does NOT
 /// include the `locale` key, just a `HashMap<String, String>` of the
 /// translations themselves.
+///
+/// Responses have ETags, so the browser will only download translations again
+/// if they've changed.
 pub async fn translations<T: TranslationsManager>(
     req: HttpRequest,
     opts: web::Data<ServerOptions>,
@@ -25,7 +28,22 @@ pub async fn translations<T: TranslationsManager>(
             Err(err) => return HttpResponse::InternalServerError().body(fmt_err(&err)),
         };
 
-        HttpResponse::Ok().body(translations)
+        let etag = get_etag(&translations);
+        let if_none_match = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|val| val.to_str().ok());
+        if etag_matches(if_none_match, &etag) {
+            return HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag))
+                .finish();
+        }
+
+        HttpResponse::Ok()
+            // The browser should always check that the translations haven't changed
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .insert_header((header::ETAG, etag))
+            .body(translations)
     } else {
         HttpResponse::NotFound().body("locale not supported".to_string())
     }
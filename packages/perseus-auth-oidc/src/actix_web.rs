@@ -0,0 +1,62 @@
+use crate::client::{handle, Action, AuthRequest, OidcClient};
+use ::actix_web::{http::header, web, HttpRequest, HttpResponse};
+use actix_http::StatusCode;
+use perseus::{csrf::CSRF_HEADER, session::Sessions};
+
+/// The state the routes need.
+struct OidcState {
+    client: OidcClient,
+    sessions: Sessions,
+}
+
+async fn route(req: HttpRequest, state: web::Data<OidcState>, action: Action) -> HttpResponse {
+    let auth_req = AuthRequest {
+        method: req.method().as_str(),
+        cookie_headers: req
+            .headers()
+            .get_all(header::COOKIE)
+            .filter_map(|header| header.to_str().ok())
+            .collect(),
+        csrf_token: req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|token| token.to_str().ok()),
+        query: req.query_string(),
+    };
+    let res = handle(&state.client, &state.sessions, action, auth_req).await;
+
+    let mut http_res = HttpResponse::build(StatusCode::from_u16(res.status).unwrap());
+    if let Some(location) = res.location {
+        http_res.insert_header((header::LOCATION, location));
+    }
+    if let Some(set_cookie) = res.set_cookie {
+        http_res.insert_header((header::SET_COOKIE, set_cookie));
+    }
+    http_res.body(res.body)
+}
+async fn login(req: HttpRequest, state: web::Data<OidcState>) -> HttpResponse {
+    route(req, state, Action::Login).await
+}
+async fn callback(req: HttpRequest, state: web::Data<OidcState>) -> HttpResponse {
+    route(req, state, Action::Callback).await
+}
+async fn logout(req: HttpRequest, state: web::Data<OidcState>) -> HttpResponse {
+    route(req, state, Action::Logout).await
+}
+
+/// Configures an existing Actix Web app with the login, callback, and logout
+/// routes for the given client. This should be configured before Perseus'
+/// own routes, and the given sessions should be the same as those given to
+/// your `PerseusApp`.
+pub fn oidc_configurer(
+    client: OidcClient,
+    sessions: Sessions,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg: &mut web::ServiceConfig| {
+        let base_path = client.base_path().to_string();
+        cfg.app_data(web::Data::new(OidcState { client, sessions }))
+            .route(&format!("{}/login", base_path), web::get().to(login))
+            .route(&format!("{}/callback", base_path), web::get().to(callback))
+            .route(&format!("{}/logout", base_path), web::post().to(logout));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::client::{handle, Action, AuthRequest, OidcClient};
+use ::axum::{
+    extract::RawQuery,
+    http::{header, HeaderMap, Method, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use perseus::{csrf::CSRF_HEADER, session::Sessions};
+
+/// Gets a `Router` with the login, callback, and logout routes for the given
+/// client, which should be merged into your server's router before Perseus'
+/// own routes. The given sessions should be the same as those given to your
+/// `PerseusApp`.
+pub fn oidc_router(client: OidcClient, sessions: Sessions) -> Router {
+    let route = |action: Action| {
+        let client = client.clone();
+        let sessions = sessions.clone();
+        move |method: Method, headers: HeaderMap, RawQuery(query): RawQuery| async move {
+            let query = query.unwrap_or_default();
+            let req = AuthRequest {
+                method: method.as_str(),
+                cookie_headers: headers
+                    .get_all(header::COOKIE)
+                    .iter()
+                    .filter_map(|header| header.to_str().ok())
+                    .collect(),
+                csrf_token: headers
+                    .get(CSRF_HEADER)
+                    .and_then(|token| token.to_str().ok()),
+                query: &query,
+            };
+            let res = handle(&client, &sessions, action, req).await;
+
+            let mut header_map = HeaderMap::new();
+            for (name, val) in [
+                (header::LOCATION, res.location),
+                (header::SET_COOKIE, res.set_cookie),
+            ] {
+                if let Some(val) = val {
+                    match val.parse() {
+                        Ok(val) => {
+                            header_map.insert(name, val);
+                        }
+                        Err(_) => {
+                            return (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                HeaderMap::new(),
+                                format!("invalid value for '{}' header", name),
+                            )
+                        }
+                    }
+                }
+            }
+            (
+                StatusCode::from_u16(res.status).unwrap(),
+                header_map,
+                res.body,
+            )
+        }
+    };
+
+    let base_path = client.base_path();
+    Router::new()
+        .route(&format!("{}/login", base_path), get(route(Action::Login)))
+        .route(
+            &format!("{}/callback", base_path),
+            get(route(Action::Callback)),
+        )
+        .route(
+            &format!("{}/logout", base_path),
+            post(route(Action::Logout)),
+        )
+}
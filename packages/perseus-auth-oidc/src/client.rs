@@ -0,0 +1,782 @@
+use crate::errors::OidcError;
+use crate::identity::{Identity, Tokens, IDENTITY_KEY, TOKENS_KEY};
+use perseus::csrf::constant_time_eq;
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+use perseus::csrf::verify_csrf;
+use perseus::session::SessionHandle;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The session key that a login in progress is stored under.
+const PENDING_KEY: &str = "__perseus_oidc_pending";
+
+/// The configuration for logging users in with an OpenID Connect provider.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// The issuer URL of the provider (e.g. `https://accounts.google.com`).
+    issuer: String,
+    /// The ID of this app as a client of the provider.
+    client_id: String,
+    /// The secret of this app as a client of the provider, if it has one.
+    client_secret: Option<String>,
+    /// The full URL the provider should redirect users back to after they've
+    /// logged in.
+    redirect_url: String,
+    /// The scopes to request.
+    scopes: Vec<String>,
+    /// The path the login, callback, and logout routes will be mounted under.
+    base_path: String,
+}
+impl OidcConfig {
+    /// Creates a new configuration for the provider with the given issuer URL
+    /// (which will be used to discover its endpoints), as the client with the
+    /// given ID. The redirect URL must be the full URL of the callback route
+    /// (e.g. `https://example.com/auth/callback`), and it must be registered
+    /// with the provider. By default, the `openid`, `profile`, and `email`
+    /// scopes will be requested.
+    pub fn new(issuer: &str, client_id: &str, redirect_url: &str) -> Self {
+        Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            redirect_url: redirect_url.to_string(),
+            scopes: vec![
+                "openid".to_string(),
+                "profile".to_string(),
+                "email".to_string(),
+            ],
+            base_path: "/auth".to_string(),
+        }
+    }
+    /// Sets the client secret, which confidential clients should use. PKCE is
+    /// always used, so public clients don't need one.
+    pub fn client_secret(mut self, val: &str) -> Self {
+        self.client_secret = Some(val.to_string());
+        self
+    }
+    /// Adds a scope to request (e.g. `offline_access` to get a refresh token).
+    pub fn scope(mut self, val: &str) -> Self {
+        if !self.scopes.iter().any(|scope| scope == val) {
+            self.scopes.push(val.to_string());
+        }
+        self
+    }
+    /// Sets the path the login, callback, and logout routes will be mounted
+    /// under (`/auth` by default). The callback route will be at
+    /// `<path>/callback`, which should match the redirect URL.
+    pub fn base_path(mut self, val: &str) -> Self {
+        self.base_path = format!("/{}", val.trim_matches('/'));
+        self
+    }
+}
+
+/// The endpoints of an OpenID Connect provider, from its discovery document.
+#[derive(Deserialize, Debug, Clone)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+/// A login that's in progress, which is kept in the user's session between
+/// their being sent to the provider and their coming back.
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    verifier: String,
+    return_to: String,
+}
+
+/// The response of the provider's token endpoint.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// The routes this integration provides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+pub(crate) enum Action {
+    Login,
+    Callback,
+    Logout,
+}
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+impl Action {
+    /// Gets the HTTP method this route accepts. Logging out changes the user's
+    /// state, so it needs to be a `POST` (which can be checked for CSRF).
+    pub(crate) fn method(&self) -> &'static str {
+        match self {
+            Self::Login | Self::Callback => "GET",
+            Self::Logout => "POST",
+        }
+    }
+}
+
+/// A client for an OpenID Connect provider, which can log users in and out.
+/// This can be cloned freely.
+#[derive(Debug, Clone)]
+pub struct OidcClient {
+    config: Arc<OidcConfig>,
+    provider: Arc<ProviderMetadata>,
+    http: reqwest::Client,
+}
+impl OidcClient {
+    /// Creates a new client by fetching the provider's discovery document
+    /// (from `<issuer>/.well-known/openid-configuration`). This should be
+    /// done once when your server starts.
+    pub async fn discover(config: OidcConfig) -> Result<Self, OidcError> {
+        let http = reqwest::Client::new();
+        let url = format!("{}/.well-known/openid-configuration", config.issuer);
+        let provider = async {
+            http.get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<ProviderMetadata>()
+                .await
+        }
+        .await
+        .map_err(|err| OidcError::DiscoveryFailed {
+            url: url.clone(),
+            source: err,
+        })?;
+        if provider.issuer.trim_end_matches('/') != config.issuer {
+            return Err(OidcError::IssuerMismatch {
+                expected: config.issuer,
+                found: provider.issuer,
+            });
+        }
+
+        Ok(Self {
+            config: Arc::new(config),
+            provider: Arc::new(provider),
+            http,
+        })
+    }
+    /// Gets the path the login, callback, and logout routes are mounted under.
+    pub fn base_path(&self) -> &str {
+        &self.config.base_path
+    }
+    /// Works out which of this integration's routes the given path is for, if
+    /// any.
+    #[cfg(feature = "warp")]
+    pub(crate) fn action_for(&self, path: &str) -> Option<Action> {
+        let action = path.strip_prefix(self.base_path())?;
+        match action {
+            "/login" => Some(Action::Login),
+            "/callback" => Some(Action::Callback),
+            "/logout" => Some(Action::Logout),
+            _ => None,
+        }
+    }
+    /// Starts logging the user with the given session in, returning the URL of
+    /// the provider that they should be redirected to. Once they've logged in,
+    /// they'll be redirected back to `return_to`, which must be a path on
+    /// this site (anything else will be replaced with `/`, to prevent open
+    /// redirects).
+    pub fn login(
+        &self,
+        session: &SessionHandle,
+        return_to: Option<&str>,
+    ) -> Result<String, OidcError> {
+        let verifier = random_string(32)?;
+        let pending = PendingLogin {
+            state: random_string(16)?,
+            nonce: random_string(16)?,
+            verifier: verifier.clone(),
+            return_to: sanitize_return_to(return_to),
+        };
+        // The challenge is the SHA-256 hash of the verifier (the `S256` method)
+        let challenge =
+            base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+
+        let params = [
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", &self.config.redirect_url),
+            ("scope", &self.config.scopes.join(" ")),
+            ("state", &pending.state),
+            ("nonce", &pending.nonce),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ]
+        .iter()
+        .map(|(key, val)| format!("{}={}", key, urlencoding::encode(val)))
+        .collect::<Vec<_>>()
+        .join("&");
+        let separator = if self.provider.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        session
+            .lock()
+            .insert(PENDING_KEY, &pending)
+            .map_err(|err| OidcError::SessionWriteFailed { source: err })?;
+
+        Ok(format!(
+            "{}{}{}",
+            self.provider.authorization_endpoint, separator, params
+        ))
+    }
+    /// Finishes logging the user with the given session in, given the query
+    /// string the provider redirected them back with. This will exchange the
+    /// authorization code for tokens, and store them and the user's
+    /// [`Identity`] in their session, which will be given a new ID. This
+    /// returns the path the user should be redirected to.
+    ///
+    /// The login in progress is removed from the session whether or not this
+    /// succeeds, so the session should be committed even if this fails.
+    pub async fn callback(
+        &self,
+        session: &SessionHandle,
+        query: &str,
+    ) -> Result<String, OidcError> {
+        let params = parse_query(query);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, val)| val.as_str())
+        };
+        if let Some(error) = param("error") {
+            return Err(OidcError::Provider {
+                error: error.to_string(),
+                description: param("error_description")
+                    .unwrap_or("no description")
+                    .to_string(),
+            });
+        }
+        let code = param("code").ok_or(OidcError::MissingParam {
+            param: "code".to_string(),
+        })?;
+        let state = param("state").ok_or(OidcError::MissingParam {
+            param: "state".to_string(),
+        })?;
+
+        // Each login can only be completed once
+        let pending = {
+            let mut session = session.lock();
+            let pending = session.get::<PendingLogin>(PENDING_KEY);
+            session.remove(PENDING_KEY);
+            pending.ok_or(OidcError::NoPendingLogin)?
+        };
+        if !constant_time_eq(pending.state.as_bytes(), state.as_bytes()) {
+            return Err(OidcError::StateMismatch);
+        }
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_url),
+            ("client_id", &self.config.client_id),
+            ("code_verifier", &pending.verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let res = async {
+            self.http
+                .post(&self.provider.token_endpoint)
+                .form(&form)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<TokenResponse>()
+                .await
+        }
+        .await
+        .map_err(|err| OidcError::TokenExchangeFailed { source: err })?;
+        let id_token = res.id_token.ok_or(OidcError::MissingIdToken)?;
+        let identity = self.validate_id_token(&id_token, &pending.nonce)?;
+        let tokens = Tokens {
+            access_token: res.access_token,
+            refresh_token: res.refresh_token,
+            id_token,
+            expires_at: res.expires_in.map(|expires_in| unix_now() + expires_in),
+        };
+
+        let mut session = session.lock();
+        // The user's privileges have changed, so their session needs a new ID
+        // (otherwise an attacker who planted an ID in their browser could use
+        // it to act as them)
+        session.regenerate_id();
+        session
+            .insert(IDENTITY_KEY, &identity)
+            .and_then(|_| session.insert(TOKENS_KEY, &tokens))
+            .map_err(|err| OidcError::SessionWriteFailed { source: err })?;
+
+        Ok(pending.return_to)
+    }
+    /// Logs the user with the given session out, by destroying their session.
+    /// Note that this won't log them out of the provider itself.
+    ///
+    /// Since this changes the user's state, it should only ever be done in
+    /// response to a request that's been checked for CSRF (the logout route
+    /// this integration provides only accepts `POST` requests with a valid
+    /// CSRF token).
+    pub fn logout(&self, session: &SessionHandle) {
+        session.lock().destroy();
+    }
+    /// Parses the claims of the given ID token, and checks that it was issued
+    /// for this client by the expected provider for this login.
+    ///
+    /// The token's signature isn't checked, since it came straight from the
+    /// provider's token endpoint over TLS (which the OpenID Connect spec allows
+    /// to stand in for signature validation).
+    fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<Identity, OidcError> {
+        let payload = id_token
+            .split('.')
+            .nth(1)
+            .ok_or(OidcError::MalformedIdToken)?;
+        let payload = base64::decode_config(payload.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .map_err(|_| OidcError::MalformedIdToken)?;
+        let claims = serde_json::from_slice::<serde_json::Map<String, Value>>(&payload)
+            .map_err(|_| OidcError::MalformedIdToken)?;
+        let invalid = |claim: &str| OidcError::InvalidClaim {
+            claim: claim.to_string(),
+        };
+
+        let issuer = claims.get("iss").and_then(|iss| iss.as_str());
+        if issuer.map(|iss| iss.trim_end_matches('/')) != Some(&self.config.issuer) {
+            return Err(invalid("iss"));
+        }
+        let client_id = Value::String(self.config.client_id.clone());
+        let audience_ok = match claims.get("aud") {
+            Some(Value::Array(auds)) => auds.contains(&client_id),
+            Some(aud) => *aud == client_id,
+            None => false,
+        };
+        if !audience_ok {
+            return Err(invalid("aud"));
+        }
+        match claims.get("exp").and_then(|exp| exp.as_u64()) {
+            Some(exp) if exp > unix_now() => (),
+            _ => return Err(invalid("exp")),
+        };
+        if claims.get("nonce").and_then(|nonce| nonce.as_str()) != Some(nonce) {
+            return Err(invalid("nonce"));
+        }
+
+        let claim = |name: &str| {
+            claims
+                .get(name)
+                .and_then(|val| val.as_str())
+                .map(|val| val.to_string())
+        };
+        Ok(Identity {
+            subject: claim("sub").ok_or_else(|| invalid("sub"))?,
+            email: claim("email"),
+            name: claim("name"),
+            claims: claims.clone(),
+        })
+    }
+}
+
+/// The parts of a request to one of this integration's routes that it needs,
+/// which each server integration extracts from its own format.
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+pub(crate) struct AuthRequest<'a> {
+    pub(crate) method: &'a str,
+    pub(crate) cookie_headers: Vec<&'a str>,
+    /// The value of the CSRF token header, if there was one.
+    pub(crate) csrf_token: Option<&'a str>,
+    pub(crate) query: &'a str,
+}
+
+/// A response from one of this integration's routes, which each server
+/// integration converts into its own format.
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+#[derive(Debug)]
+pub(crate) struct AuthResponse {
+    pub(crate) status: u16,
+    pub(crate) location: Option<String>,
+    pub(crate) set_cookie: Option<String>,
+    pub(crate) body: String,
+}
+
+/// Handles a request to one of this integration's routes.
+#[cfg(any(feature = "actix-web", feature = "warp", feature = "axum", test))]
+pub(crate) async fn handle(
+    client: &OidcClient,
+    sessions: &perseus::session::Sessions,
+    action: Action,
+    req: AuthRequest<'_>,
+) -> AuthResponse {
+    let error = |err: OidcError| AuthResponse {
+        status: crate::errors::err_to_status_code(&err),
+        location: None,
+        set_cookie: None,
+        body: fmterr::fmt_err(&err),
+    };
+
+    if !req.method.eq_ignore_ascii_case(action.method()) {
+        return error(OidcError::MethodNotAllowed {
+            method: req.method.to_string(),
+        });
+    }
+    if action == Action::Logout {
+        let cookie_headers = req.cookie_headers.iter().copied();
+        if let Err(err) = verify_csrf(req.method, cookie_headers, req.csrf_token) {
+            return error(err.into());
+        }
+    }
+
+    let session = match sessions.load(req.cookie_headers).await {
+        Ok(session) => session,
+        Err(err) => return error(err.into()),
+    };
+    let location = match action {
+        Action::Login => {
+            let return_to = parse_query(req.query)
+                .into_iter()
+                .find(|(key, _)| key == "return_to")
+                .map(|(_, val)| val);
+            client.login(&session, return_to.as_deref())
+        }
+        Action::Callback => client.callback(&session, req.query).await,
+        Action::Logout => {
+            client.logout(&session);
+            Ok("/".to_string())
+        }
+    };
+    // This is done even if the action failed, so that a failed login can't be
+    // completed later
+    let set_cookie = match sessions.commit(&session).await {
+        Ok(set_cookie) => set_cookie,
+        Err(err) => return error(err.into()),
+    };
+
+    match location {
+        Ok(location) => AuthResponse {
+            status: 302,
+            location: Some(location),
+            set_cookie,
+            body: String::new(),
+        },
+        Err(err) => AuthResponse {
+            set_cookie,
+            ..error(err)
+        },
+    }
+}
+
+/// Makes sure the given path to return to after logging in is a path on this
+/// site (that can be put in a `Location` header).
+fn sanitize_return_to(return_to: Option<&str>) -> String {
+    match return_to {
+        // `//example.com` and `/\example.com` are treated as other sites by browsers
+        Some(path)
+            if path.starts_with('/')
+                && !path.starts_with("//")
+                && !path.starts_with("/\\")
+                && !path.chars().any(|c| c.is_control()) =>
+        {
+            path.to_string()
+        }
+        _ => "/".to_string(),
+    }
+}
+
+/// Parses a query string into decoded key-value pairs.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = urlencoding::decode(&key.replace('+', " "))
+                .ok()?
+                .to_string();
+            let val = urlencoding::decode(&val.replace('+', " "))
+                .ok()?
+                .to_string();
+            Some((key, val))
+        })
+        .collect()
+}
+
+/// Generates a cryptographically secure random string from the given number of
+/// bytes.
+fn random_string(len: usize) -> Result<String, OidcError> {
+    let mut bytes = vec![0; len];
+    getrandom::getrandom(&mut bytes).map_err(|err| OidcError::RandomFailed { source: err })?;
+    Ok(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD))
+}
+
+/// Gets the current UNIX timestamp, in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use perseus::csrf::CSRF_COOKIE;
+    use perseus::session::{MemorySessionStore, Sessions};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const ISSUER: &str = "https://provider.example.com";
+    const CLIENT_ID: &str = "perseus-test";
+
+    /// Creates a client for a fake provider, whose token endpoint is at the
+    /// given URL.
+    fn test_client(token_endpoint: String) -> OidcClient {
+        OidcClient {
+            config: Arc::new(OidcConfig::new(
+                ISSUER,
+                CLIENT_ID,
+                "https://example.com/auth/callback",
+            )),
+            provider: Arc::new(ProviderMetadata {
+                issuer: ISSUER.to_string(),
+                authorization_endpoint: format!("{}/authorize", ISSUER),
+                token_endpoint,
+            }),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Answers the next request to the given listener with the given JSON,
+    /// as the provider's token endpoint would.
+    fn serve_once(listener: TcpListener, body: String) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            // The request needs to be read in full before we respond
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                if let Some((name, val)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = val.trim().parse().unwrap();
+                    }
+                }
+            }
+            reader.read_exact(&mut vec![0; content_length]).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        })
+    }
+
+    /// Creates a token response with an ID token for the login with the given
+    /// nonce. The token is unsigned, since signatures aren't checked.
+    fn token_response(nonce: &str) -> String {
+        let encode = |val: Value| base64::encode_config(val.to_string(), base64::URL_SAFE_NO_PAD);
+        let id_token = format!(
+            "{}.{}.",
+            encode(serde_json::json!({ "alg": "none" })),
+            encode(serde_json::json!({
+                "iss": ISSUER,
+                "aud": CLIENT_ID,
+                "exp": unix_now() + 60,
+                "nonce": nonce,
+                "sub": "user-1",
+            }))
+        );
+        serde_json::json!({ "access_token": "access", "id_token": id_token }).to_string()
+    }
+
+    /// Makes a request to the given route with the given cookies.
+    async fn request(
+        client: &OidcClient,
+        sessions: &Sessions,
+        action: Action,
+        cookie_headers: Vec<&str>,
+        csrf_token: Option<&str>,
+        query: &str,
+    ) -> AuthResponse {
+        let req = AuthRequest {
+            method: action.method(),
+            cookie_headers,
+            csrf_token,
+            query,
+        };
+        handle(client, sessions, action, req).await
+    }
+
+    /// Gets the session cookie set by the given response.
+    fn session_cookie(res: &AuthResponse) -> String {
+        let set_cookie = res.set_cookie.as_ref().expect("no session cookie was set");
+        set_cookie.split(';').next().unwrap().to_string()
+    }
+
+    /// Gets the value of the given parameter in the query string of the given
+    /// redirect response.
+    fn location_param(res: &AuthResponse, name: &str) -> String {
+        let location = res.location.as_ref().unwrap();
+        parse_query(location.split_once('?').unwrap().1)
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, val)| val)
+            .unwrap()
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn callback_logs_in_with_new_session() {
+        runtime().block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = test_client(format!("http://{}/token", listener.local_addr().unwrap()));
+
+            let res = request(
+                &client,
+                &sessions,
+                Action::Login,
+                Vec::new(),
+                None,
+                "return_to=%2Fdashboard",
+            )
+            .await;
+            assert_eq!(res.status, 302);
+            let login_cookie = session_cookie(&res);
+            let state = location_param(&res, "state");
+            let server = serve_once(listener, token_response(&location_param(&res, "nonce")));
+
+            let res = request(
+                &client,
+                &sessions,
+                Action::Callback,
+                vec![&login_cookie],
+                None,
+                &format!("code=abc&state={}", state),
+            )
+            .await;
+            server.join().unwrap();
+            assert_eq!(res.status, 302, "{}", res.body);
+            assert_eq!(res.location.as_deref(), Some("/dashboard"));
+
+            // The user should have a new session, and the old one should be gone
+            let cookie = session_cookie(&res);
+            assert_ne!(cookie, login_cookie);
+            let session = sessions.load(Some(cookie.as_str())).await.unwrap();
+            let identity = session.lock().get::<Identity>(IDENTITY_KEY).unwrap();
+            assert_eq!(identity.subject, "user-1");
+            let old_session = sessions.load(Some(login_cookie.as_str())).await.unwrap();
+            assert!(old_session.lock().get::<Identity>(IDENTITY_KEY).is_none());
+        });
+    }
+
+    #[test]
+    fn mismatched_state_ends_login() {
+        runtime().block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            // The token endpoint should never be reached
+            let client = test_client("http://127.0.0.1:9/token".to_string());
+
+            let res = request(&client, &sessions, Action::Login, Vec::new(), None, "").await;
+            let cookie = session_cookie(&res);
+            let state = location_param(&res, "state");
+
+            let res = request(
+                &client,
+                &sessions,
+                Action::Callback,
+                vec![&cookie],
+                None,
+                "code=abc&state=forged",
+            )
+            .await;
+            assert_eq!(res.status, 400);
+            assert!(res.body.contains("didn't match"), "{}", res.body);
+            // The login in progress should have been removed, even though the callback
+            // failed
+            let res = request(
+                &client,
+                &sessions,
+                Action::Callback,
+                vec![&cookie],
+                None,
+                &format!("code=abc&state={}", state),
+            )
+            .await;
+            assert_eq!(res.status, 400);
+            assert!(
+                res.body.contains("no login was in progress"),
+                "{}",
+                res.body
+            );
+        });
+    }
+
+    #[test]
+    fn logout_needs_post_with_csrf_token() {
+        runtime().block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let client = test_client("http://127.0.0.1:9/token".to_string());
+            let res = request(&client, &sessions, Action::Login, Vec::new(), None, "").await;
+            let cookie = session_cookie(&res);
+            let token = "0123456789abcdef0123456789abcdef";
+            let csrf_cookie = format!("{}={}", CSRF_COOKIE, token);
+
+            let req = AuthRequest {
+                method: "GET",
+                cookie_headers: vec![&cookie, &csrf_cookie],
+                csrf_token: Some(token),
+                query: "",
+            };
+            let res = handle(&client, &sessions, Action::Logout, req).await;
+            assert_eq!(res.status, 405);
+
+            let cookies = vec![cookie.as_str(), csrf_cookie.as_str()];
+            let res = request(
+                &client,
+                &sessions,
+                Action::Logout,
+                cookies.clone(),
+                None,
+                "",
+            )
+            .await;
+            assert_eq!(res.status, 403);
+            assert!(res.set_cookie.is_none());
+
+            let res = request(&client, &sessions, Action::Logout, cookies, Some(token), "").await;
+            assert_eq!(res.status, 302);
+            assert_eq!(res.location.as_deref(), Some("/"));
+            assert!(res.set_cookie.unwrap().contains("Max-Age=0"));
+        });
+    }
+
+    #[test]
+    fn return_to_must_be_local() {
+        assert_eq!(
+            sanitize_return_to(Some("/dashboard?tab=1")),
+            "/dashboard?tab=1"
+        );
+        assert_eq!(sanitize_return_to(Some("https://evil.com")), "/");
+        assert_eq!(sanitize_return_to(Some("//evil.com")), "/");
+        assert_eq!(sanitize_return_to(Some("/\\evil.com")), "/");
+        assert_eq!(sanitize_return_to(Some("/\r\nSet-Cookie: a=b")), "/");
+        assert_eq!(sanitize_return_to(None), "/");
+    }
+}
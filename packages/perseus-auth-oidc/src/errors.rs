@@ -0,0 +1,69 @@
+#![allow(missing_docs)]
+use thiserror::Error;
+
+/// Errors that can occur while logging a user in with OpenID Connect.
+#[derive(Error, Debug)]
+pub enum OidcError {
+    #[error("couldn't fetch openid connect discovery document from '{url}'")]
+    DiscoveryFailed {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("discovery document was for issuer '{found}', but '{expected}' was configured")]
+    IssuerMismatch { expected: String, found: String },
+    #[error("identity provider returned error '{error}' ({description})")]
+    Provider { error: String, description: String },
+    #[error("callback was missing the '{param}' parameter")]
+    MissingParam { param: String },
+    #[error("no login was in progress for this session (it may have expired, or the user may have used another browser)")]
+    NoPendingLogin,
+    #[error("callback state didn't match the state of the login in progress (this may have been a forged request)")]
+    StateMismatch,
+    #[error("couldn't exchange authorization code for tokens")]
+    TokenExchangeFailed {
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("token response had no id token (was the 'openid' scope requested?)")]
+    MissingIdToken,
+    #[error("id token was malformed")]
+    MalformedIdToken,
+    #[error("id token claim '{claim}' was invalid")]
+    InvalidClaim { claim: String },
+    #[error("couldn't store login data in session")]
+    SessionWriteFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("couldn't generate random data for login")]
+    RandomFailed {
+        #[source]
+        source: getrandom::Error,
+    },
+    #[error("this route doesn't accept '{method}' requests")]
+    MethodNotAllowed { method: String },
+    #[error(transparent)]
+    Csrf(#[from] perseus::errors::CsrfError),
+    #[error(transparent)]
+    StoreError(#[from] perseus::errors::StoreError),
+}
+
+/// Converts an OpenID Connect error into an HTTP status code.
+pub fn err_to_status_code(err: &OidcError) -> u16 {
+    match err {
+        // These are the user's problem (or an attacker's)
+        OidcError::Provider { .. }
+        | OidcError::MissingParam { .. }
+        | OidcError::NoPendingLogin
+        | OidcError::StateMismatch => 400,
+        OidcError::Csrf(_) => 403,
+        OidcError::MethodNotAllowed { .. } => 405,
+        // These are the identity provider's problem
+        OidcError::TokenExchangeFailed { .. }
+        | OidcError::MissingIdToken
+        | OidcError::MalformedIdToken
+        | OidcError::InvalidClaim { .. } => 502,
+        _ => 500,
+    }
+}
@@ -0,0 +1,52 @@
+use perseus::{session::get_session, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The session key that the user's identity is stored under.
+pub(crate) const IDENTITY_KEY: &str = "__perseus_oidc_identity";
+/// The session key that the user's tokens are stored under.
+pub(crate) const TOKENS_KEY: &str = "__perseus_oidc_tokens";
+
+/// The identity of a logged-in user, from the claims of their ID token.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// The user's unique identifier at the provider (the `sub` claim).
+    pub subject: String,
+    /// The user's email address, if the provider gave it.
+    pub email: Option<String>,
+    /// The user's full name, if the provider gave it.
+    pub name: Option<String>,
+    /// All the claims of the user's ID token.
+    pub claims: serde_json::Map<String, Value>,
+}
+
+/// The tokens the provider issued when the user logged in. These are only ever
+/// kept on the server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Tokens {
+    /// The access token, which can be used to call APIs on the user's behalf.
+    pub access_token: String,
+    /// The refresh token, if one was issued (usually only with the
+    /// `offline_access` scope).
+    pub refresh_token: Option<String>,
+    /// The raw ID token.
+    pub id_token: String,
+    /// When the access token expires, as a UNIX timestamp, if the provider
+    /// said.
+    pub expires_at: Option<u64>,
+}
+
+/// Gets the identity of the user who made the given request, if they're logged
+/// in. This is designed to be used in request-state functions, and it requires
+/// sessions to have been enabled on your `PerseusApp` (with the same
+/// [`Sessions`](perseus::session::Sessions) given to this integration's
+/// routes).
+pub fn get_identity(req: &Request) -> Option<Identity> {
+    get_session(req)?.lock().get(IDENTITY_KEY)
+}
+
+/// Gets the tokens of the user who made the given request, if they're logged
+/// in. See [`get_identity`] for details.
+pub fn get_tokens(req: &Request) -> Option<Tokens> {
+    get_session(req)?.lock().get(TOKENS_KEY)
+}
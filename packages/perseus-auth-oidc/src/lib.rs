@@ -0,0 +1,28 @@
+/*!
+This is the API documentation for the `perseus-auth-oidc` package, which lets Perseus apps log users in with any OAuth2/OpenID Connect provider (e.g. Google, Auth0, or Keycloak). Note that Perseus mostly uses [the book](https://arctic-hen7.github.io/perseus/en-US) for
+documentation, and this should mostly be used as a secondary reference source. You can also find full usage examples [here](https://github.com/arctic-hen7/perseus/tree/main/examples).
+
+This builds on Perseus' inbuilt sessions, so you'll need to enable those on your `PerseusApp` first. Then, create an [`OidcClient`] when your server starts, and mount its routes on your server with the function for your server integration (enabled by the `actix-web`, `warp`, and `axum` features). Users can then be sent to `/auth/login?return_to=/some/page` to log in (this uses the authorization code flow with PKCE), which will give them a new session ID once they've logged in. To log out, send a `POST` request to `/auth/logout` with a CSRF token (e.g. with `perseus::csrf::csrf_fetch()`), which means you'll need to enable `PerseusApp::csrf_protection()` too. Once they've logged in, their [`Identity`] will be available to your request-state functions through [`get_identity`].
+*/
+
+#![deny(missing_docs)]
+
+#[cfg(feature = "actix-web")]
+mod actix_web;
+#[cfg(feature = "axum")]
+mod axum;
+mod client;
+/// Errors that can occur while logging users in.
+pub mod errors;
+mod identity;
+#[cfg(feature = "warp")]
+mod warp;
+
+#[cfg(feature = "actix-web")]
+pub use crate::actix_web::oidc_configurer;
+#[cfg(feature = "axum")]
+pub use crate::axum::oidc_router;
+pub use crate::client::{OidcClient, OidcConfig};
+pub use crate::identity::{get_identity, get_tokens, Identity, Tokens};
+#[cfg(feature = "warp")]
+pub use crate::warp::oidc_routes;
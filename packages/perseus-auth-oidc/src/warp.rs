@@ -0,0 +1,65 @@
+use crate::client::{handle, AuthRequest, OidcClient};
+use ::warp::{http::Response, path::FullPath, Filter, Rejection, Reply};
+use perseus::{
+    csrf::CSRF_HEADER,
+    http::{header, HeaderMap, Method},
+    session::Sessions,
+};
+
+/// Gets a Warp filter with the login, callback, and logout routes for the
+/// given client, which should be combined with your server's other routes
+/// before Perseus' own routes. The given sessions should be the same as those
+/// given to your `PerseusApp`.
+pub fn oidc_routes(
+    client: OidcClient,
+    sessions: Sessions,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    ::warp::get()
+        .or(::warp::post())
+        .unify()
+        .and(::warp::method())
+        .and(::warp::path::full())
+        // Warp doesn't permit empty query strings without this extra config
+        .and(::warp::query::raw().or_else(|_| async move { Ok::<_, Rejection>((String::new(),)) }))
+        .and(::warp::header::headers_cloned())
+        .and_then(
+            move |method: Method, path: FullPath, query: String, headers: HeaderMap| {
+                let client = client.clone();
+                let sessions = sessions.clone();
+                async move {
+                    let action = client
+                        .action_for(path.as_str())
+                        .ok_or_else(::warp::reject::not_found)?;
+                    let req = AuthRequest {
+                        method: method.as_str(),
+                        cookie_headers: headers
+                            .get_all(header::COOKIE)
+                            .iter()
+                            .filter_map(|header| header.to_str().ok())
+                            .collect(),
+                        csrf_token: headers
+                            .get(CSRF_HEADER)
+                            .and_then(|token| token.to_str().ok()),
+                        query: &query,
+                    };
+                    let res = handle(&client, &sessions, action, req).await;
+
+                    let mut http_res = Response::builder().status(res.status);
+                    if let Some(location) = res.location {
+                        http_res = http_res.header("Location", location);
+                    }
+                    if let Some(set_cookie) = res.set_cookie {
+                        http_res = http_res.header("Set-Cookie", set_cookie);
+                    }
+                    // This will only fail if one of the headers was invalid
+                    let http_res = http_res.body(res.body).unwrap_or_else(|_| {
+                        Response::builder()
+                            .status(500)
+                            .body("invalid response header".to_string())
+                            .unwrap()
+                    });
+                    Ok::<_, Rejection>(http_res)
+                }
+            },
+        )
+}
@@ -0,0 +1,20 @@
+use axum::{body::Body, extract::ConnectInfo};
+use perseus::{server::RemoteAddr, Request};
+use std::net::SocketAddr;
+
+/// Converts an Axum request into one palatable for Perseus, which doesn't have
+/// the body attached. If the server was set up to provide connection
+/// information (as the default server is), the client's address will be
+/// recorded too.
+pub fn convert_req(http_req: perseus::http::Request<Body>) -> Request {
+    let mut req = Request::from_parts(http_req.into_parts().0, ());
+    let remote_addr = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| RemoteAddr(*addr));
+    if let Some(remote_addr) = remote_addr {
+        req.extensions_mut().insert(remote_addr);
+    }
+
+    req
+}
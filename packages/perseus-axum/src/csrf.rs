@@ -0,0 +1,27 @@
+use axum::{
+    http::{header::COOKIE, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use perseus::csrf::{verify_csrf, CSRF_HEADER};
+
+/// An Axum middleware that rejects any requests with unsafe methods (e.g.
+/// `POST`) that don't have a valid CSRF token with a `403 Forbidden`. This
+/// should be added to any mutating routes you add to your server with
+/// `axum::middleware::from_fn(csrf_middleware)`, and it requires CSRF
+/// protection to have been enabled on your `PerseusApp`, so that users are
+/// actually given tokens.
+pub async fn csrf_middleware<B>(req: Request<B>, next: Next<B>) -> Result<Response, StatusCode> {
+    let headers = req.headers();
+    let cookie_headers = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok());
+    let token = headers
+        .get(CSRF_HEADER)
+        .and_then(|header| header.to_str().ok());
+    match verify_csrf(req.method().as_str(), cookie_headers, token) {
+        Ok(_) => Ok(next.run(req).await),
+        Err(_) => Err(StatusCode::FORBIDDEN),
+    }
+}
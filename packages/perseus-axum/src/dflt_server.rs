@@ -16,7 +16,8 @@ pub async fn dflt_server<M: MutableStore + 'static, T: TranslationsManager + 'st
         .expect("Invalid address provided to bind to.");
     let app = get_router(props).await;
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        // We need the client's address for request contexts
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
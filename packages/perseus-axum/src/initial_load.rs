@@ -1,22 +1,28 @@
+use crate::conv_req::convert_req;
 use axum::{
     body::Body,
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::Html,
 };
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    csrf::get_or_mint_csrf_token,
+    errors::{err_to_redirect, err_to_status_code, ServerError},
+    experiments::{assign_variants, get_variants},
     i18n::{TranslationsManager, Translator},
-    router::{match_route_atomic, RouteInfoAtomic, RouteVerdictAtomic},
+    router::{
+        get_index_redirect, match_route_atomic, RenderCfg, RouteInfoAtomic, RouteVerdictAtomic,
+    },
     server::{
-        build_error_page, get_page_for_template, get_path_slice, GetPageProps, HtmlShell,
-        ServerOptions,
+        attach_request_context, build_error_page, build_server_error_page, get_page_for_template,
+        get_path_slice, GetPageProps, HtmlShell, ServerOptions,
     },
     stores::{ImmutableStore, MutableStore},
-    utils::get_path_prefix_server,
-    ErrorPages, Request, SsrNode,
+    template::PageInfo,
+    utils::{get_path_prefix_server, is_private_response, make_private},
+    ErrorPages, SsrNode,
 };
-use std::{collections::HashMap, rc::Rc, sync::Arc};
+use std::{rc::Rc, sync::Arc};
 
 /// Builds on the internal Perseus primitives to provide a utility function that
 /// returns a `Response` automatically.
@@ -40,28 +46,82 @@ fn return_error_page(
 /// The handler for calls to any actual pages (first-time visits), which will
 /// render the appropriate HTML and then interpolate it into the app shell.
 #[allow(clippy::too_many_arguments)] // As for `page_data_handler`, we don't have a choice
-pub async fn initial_load_handler<M: MutableStore, T: TranslationsManager>(
+pub async fn initial_load_handler<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     http_req: perseus::http::Request<Body>,
     opts: Arc<ServerOptions>,
     html_shell: Arc<HtmlShell>,
-    render_cfg: Arc<HashMap<String, String>>,
+    render_cfg: Arc<RenderCfg>,
     immutable_store: Arc<ImmutableStore>,
     mutable_store: Arc<M>,
     translations_manager: Arc<T>,
     global_state: Arc<Option<String>>,
 ) -> (StatusCode, HeaderMap, Html<String>) {
+    // In maintenance mode, every page is replaced by the maintenance page
+    if let Some(maintenance_page) = opts.maintenance.get_page() {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            header::RETRY_AFTER,
+            opts.maintenance.retry_after().parse().unwrap(),
+        );
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            header_map,
+            Html(maintenance_page),
+        );
+    }
     let path = http_req.uri().path().to_string();
-    let http_req = Request::from_parts(http_req.into_parts().0, ());
+    let mut http_req = convert_req(http_req);
+    attach_request_context(&mut http_req, &opts);
 
     let templates = &opts.templates_map;
     let error_pages = &opts.error_pages;
     let path_slice = get_path_slice(&path);
+    // In development, error pages should use the latest translations
+    opts.error_translations
+        .refresh(&path, translations_manager.as_ref())
+        .await;
     // Create a closure to make returning error pages easier (most have the same
-    // data)
+    // data, and they should be in the locale the user requested, if possible)
     let html_err = |status: u16, err: &str| {
-        return return_error_page(&path, status, err, None, error_pages, html_shell.as_ref());
+        let translator = opts.error_translations.get_translator(&path);
+        return return_error_page(
+            &path,
+            status,
+            err,
+            translator,
+            error_pages,
+            html_shell.as_ref(),
+        );
+    };
+    // Errors from rendering pages might have error pages of their own
+    let server_err = |err: &ServerError| {
+        let translator = opts.error_translations.get_translator(&path);
+        let html =
+            build_server_error_page(&path, err, translator, error_pages, html_shell.as_ref());
+        (
+            StatusCode::from_u16(err_to_status_code(err)).unwrap(),
+            HeaderMap::new(),
+            Html(html),
+        )
     };
 
+    // If this is the root of the app and it redirects elsewhere, we can send the
+    // user straight there
+    if let Some(to) = opts
+        .index_redirect
+        .as_ref()
+        .and_then(|index_redirect| get_index_redirect(&path_slice, &opts.locales, index_redirect))
+    {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            header::LOCATION,
+            format!("{}/{}", get_path_prefix_server(), to)
+                .parse()
+                .unwrap(),
+        );
+        return (StatusCode::FOUND, header_map, Html(String::new()));
+    }
+
     // Run the routing algorithms on the path to figure out which template we need
     let verdict = match_route_atomic(&path_slice, render_cfg.as_ref(), templates, &opts.locales);
     match verdict {
@@ -73,43 +133,123 @@ pub async fn initial_load_handler<M: MutableStore, T: TranslationsManager>(
             locale,
             was_incremental_match,
         }) => {
-            // Actually render the page as we would if this weren't an initial load
-            let page_data = get_page_for_template(
-                GetPageProps::<M, T> {
-                    raw_path: &path,
-                    locale: &locale,
-                    was_incremental_match,
-                    req: http_req,
-                    global_state: &global_state,
-                    immutable_store: &immutable_store,
-                    mutable_store: &mutable_store,
-                    translations_manager: &translations_manager,
-                },
-                template,
-            )
-            .await;
-            let page_data = match page_data {
-                Ok(page_data) => page_data,
-                // We parse the error to return an appropriate status code
-                Err(err) => {
-                    return html_err(err_to_status_code(&err), &fmt_err(&err));
-                }
+            // Work out which A/B experiment variants this user should get
+            let set_cookie = assign_variants(&opts.experiments, &mut http_req);
+            let variants = get_variants(&http_req);
+            // Give the user a CSRF token if they don't have one (and it's enabled)
+            let csrf = if opts.csrf_protection {
+                Some(get_or_mint_csrf_token(&http_req))
+            } else {
+                None
             };
+            // Load the user's session, if sessions are enabled (if this fails, we still
+            // need to set the other cookies, so the error page is sent below)
+            let session = match &opts.sessions {
+                Some(sessions) => sessions.attach(&mut http_req).await.map(Some),
+                None => Ok(None),
+            };
+            let info = PageInfo::for_request(&path, &locale, &http_req);
+            // Every outcome of rendering (including errors and redirects) needs the session
+            // saved and cookies set (e.g. after logging a user in), so this block only
+            // produces the response, and that's all done once it's finished
+            let (mut status, mut header_map, mut final_html) = async {
+                let session = match &session {
+                    Ok(session) => session,
+                    Err(err) => return html_err(500, &fmt_err(err)),
+                };
+                // Actually render the page as we would if this weren't an initial load
+                let page_data = get_page_for_template(
+                    GetPageProps::<M, T> {
+                        raw_path: &path,
+                        locale: &locale,
+                        was_incremental_match,
+                        req: http_req,
+                        global_state: &global_state,
+                        immutable_store: &immutable_store,
+                        mutable_store: &mutable_store,
+                        translations_manager: &translations_manager,
+                        plugins: &opts.plugins,
+                        incremental_warmup: &opts.incremental_warmup,
+                        templates: &opts.templates_map,
+                        error_reporter: &opts.error_reporter,
+                        state_fn_timeout: opts.state_fn_timeout,
+                        build_redirects: &opts.build_redirects,
+                    },
+                    template,
+                )
+                .await;
+                let mut header_map = HeaderMap::new();
+                match page_data {
+                    Ok(page_data) => {
+                        let mut final_html = html_shell
+                            .as_ref()
+                            .clone()
+                            .page_data(&page_data, &global_state)
+                            .variants(&variants);
+                        if let Some(session) = session {
+                            final_html = final_html.session(&session.snapshot());
+                        }
+                        if let Some((token, _)) = &csrf {
+                            final_html = final_html.csrf_token(token);
+                        }
+
+                        // Generate and add HTTP headers
+                        for (key, val) in template.get_headers(page_data.state, info) {
+                            header_map.insert(key.unwrap(), val);
+                        }
 
-            let final_html = html_shell
-                .as_ref()
-                .clone()
-                .page_data(&page_data, &global_state)
-                .to_string();
+                        (StatusCode::OK, header_map, Html(final_html.to_string()))
+                    }
+                    // We parse the error to return an appropriate status code (or redirect)
+                    Err(err) => match err_to_redirect(&err) {
+                        // `Redirect::to` validates these, but a bad one still shouldn't panic
+                        Some(redirect) => match (
+                            StatusCode::from_u16(redirect.status()),
+                            HeaderValue::from_str(redirect.location()),
+                        ) {
+                            (Ok(status), Ok(location)) => {
+                                header_map.insert(header::LOCATION, location);
+                                (status, header_map, Html(String::new()))
+                            }
+                            _ => html_err(500, "invalid redirect"),
+                        },
+                        None => server_err(&err),
+                    },
+                }
+            }
+            .await;
 
-            // http_res.content_type("text/html");
-            // Generate and add HTTP headers
-            let mut header_map = HeaderMap::new();
-            for (key, val) in template.get_headers(page_data.state) {
-                header_map.insert(key.unwrap(), val);
+            // Save any changes made to the session while rendering
+            let session = session.ok().flatten();
+            let session_cookie = match (&opts.sessions, &session) {
+                (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                    Ok(set_cookie) => set_cookie,
+                    Err(err) => {
+                        (status, header_map, final_html) = html_err(500, &fmt_err(&err));
+                        None
+                    }
+                },
+                _ => None,
+            };
+            // Anything that depends on the session, embeds a CSRF token, or embeds the user's
+            // experiment variants mustn't be cached for other users
+            let private = is_private_response(
+                session.as_ref(),
+                session_cookie.is_some(),
+                &variants,
+                csrf.is_some(),
+            );
+
+            let csrf_cookie = csrf.and_then(|(_, csrf_cookie)| csrf_cookie);
+            let cookies = [set_cookie, csrf_cookie, session_cookie];
+            for cookie in cookies.into_iter().flatten() {
+                header_map.append(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+            if private {
+                make_private(&mut header_map);
             }
 
-            (StatusCode::OK, header_map, Html(final_html))
+            (status, header_map, final_html)
         }
         // For locale detection, we don't know the user's locale, so there's not much we can do
         // except send down the app shell, which will do the rest and fetch from `.perseus/page/...`
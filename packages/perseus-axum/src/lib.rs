@@ -8,15 +8,17 @@ documentation, and this should mostly be used as a secondary reference source. Y
 
 #![deny(missing_docs)]
 
-// This integration doesn't need to convert request types, because we can get
-// them straight out of Axum and then just delete the bodies
+mod conv_req;
+mod csrf;
 #[cfg(feature = "dflt-server")]
 mod dflt_server;
 mod initial_load;
 mod page_data;
 mod router;
+mod static_content;
 mod translations;
 
+pub use crate::csrf::csrf_middleware;
 pub use crate::router::get_router;
 #[cfg(feature = "dflt-server")]
 pub use dflt_server::dflt_server;
@@ -1,15 +1,21 @@
+use crate::conv_req::convert_req;
 use axum::{
     body::Body,
     extract::{Path, Query},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
 };
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    errors::{err_to_body, err_to_redirect, err_to_status_code},
+    experiments::{assign_variants, get_variants},
     i18n::TranslationsManager,
-    server::{get_page_for_template, GetPageProps, ServerOptions},
+    server::{
+        attach_request_context, get_page_for_template, get_redirect_page_data, set_req_query,
+        GetPageProps, ServerOptions,
+    },
     stores::{ImmutableStore, MutableStore},
-    Request,
+    template::PageInfo,
+    utils::{is_private_response, make_private},
 };
 use serde::Deserialize;
 use std::sync::Arc;
@@ -21,15 +27,18 @@ use std::sync::Arc;
 pub struct PageDataReq {
     pub template_name: String,
     pub was_incremental_match: bool,
+    #[serde(default)]
+    pub query: String,
 }
 
 #[allow(clippy::too_many_arguments)] // Because of how Axum extractors work, we don't exactly have a choice
-pub async fn page_handler<M: MutableStore, T: TranslationsManager>(
+pub async fn page_handler<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     Path(path_parts): Path<Vec<String>>, /* From this, we can extract the locale and the path
                                           * tail (the page path, which *does* have slashes) */
     Query(PageDataReq {
         template_name,
         was_incremental_match,
+        query,
     }): Query<PageDataReq>,
     // This works without any conversion because Axum allows us to directly get an `http::Request`
     // out!
@@ -40,6 +49,19 @@ pub async fn page_handler<M: MutableStore, T: TranslationsManager>(
     translations_manager: Arc<T>,
     global_state: Arc<Option<String>>,
 ) -> (StatusCode, HeaderMap, String) {
+    // Nothing can be loaded in maintenance mode
+    if opts.maintenance.is_enabled() {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            header::RETRY_AFTER,
+            opts.maintenance.retry_after().parse().unwrap(),
+        );
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            header_map,
+            "the server is down for maintenance".to_string(),
+        );
+    }
     // Separate the locale from the rest of the page name
     let locale = &path_parts[0];
     let path = path_parts[1..]
@@ -72,41 +94,115 @@ pub async fn page_handler<M: MutableStore, T: TranslationsManager>(
         };
         // Convert the request into one palatable for Perseus (which doesn't have the
         // body attached)
-        let http_req = Request::from_parts(http_req.into_parts().0, ());
-        let page_data = get_page_for_template(
-            GetPageProps::<M, T> {
-                raw_path: path,
-                locale,
-                was_incremental_match,
-                req: http_req,
-                global_state: &global_state,
-                immutable_store: &immutable_store,
-                mutable_store: &mutable_store,
-                translations_manager: &translations_manager,
-            },
-            template,
-        )
-        .await;
-        match page_data {
-            Ok(page_data) => {
-                // http_res.content_type("text/html");
-                // Generate and add HTTP headers
-                let mut header_map = HeaderMap::new();
-                for (key, val) in template.get_headers(page_data.state.clone()) {
-                    header_map.insert(key.unwrap(), val);
+        let mut http_req = convert_req(http_req);
+        attach_request_context(&mut http_req, &opts);
+        let set_cookie = assign_variants(&opts.experiments, &mut http_req);
+        let variants = get_variants(&http_req);
+        // Load the user's session, if sessions are enabled (if this fails, we still need
+        // to set the variants cookie, so the error is sent below)
+        let session = match &opts.sessions {
+            Some(sessions) => sessions.attach(&mut http_req).await.map(Some),
+            None => Ok(None),
+        };
+        let http_req = set_req_query(http_req, &query);
+        let info = PageInfo::for_request(path, locale, &http_req);
+        // Every outcome of rendering (including errors and redirects) needs the session
+        // saved and cookies set (e.g. after logging a user in), so this block only
+        // produces the response, and that's all done once it's finished
+        let (mut status, mut header_map, mut body) = async {
+            if let Err(err) = &session {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    HeaderMap::new(),
+                    fmt_err(err),
+                );
+            }
+            let page_data = get_page_for_template(
+                GetPageProps::<M, T> {
+                    raw_path: path,
+                    locale,
+                    was_incremental_match,
+                    req: http_req,
+                    global_state: &global_state,
+                    immutable_store: &immutable_store,
+                    mutable_store: &mutable_store,
+                    translations_manager: &translations_manager,
+                    plugins: &opts.plugins,
+                    incremental_warmup: &opts.incremental_warmup,
+                    templates: &opts.templates_map,
+                    error_reporter: &opts.error_reporter,
+                    state_fn_timeout: opts.state_fn_timeout,
+                    build_redirects: &opts.build_redirects,
+                },
+                template,
+            )
+            .await;
+            let mut header_map = HeaderMap::new();
+            match page_data {
+                Ok(page_data) => {
+                    // Generate and add HTTP headers
+                    for (key, val) in template.get_headers(page_data.state.clone(), info) {
+                        header_map.insert(key.unwrap(), val);
+                    }
+                    (
+                        StatusCode::OK,
+                        header_map,
+                        serde_json::to_string(&page_data).unwrap(),
+                    )
                 }
+                // The app will navigate to wherever the page redirects to
+                Err(err) if err_to_redirect(&err).is_some() => (
+                    StatusCode::OK,
+                    header_map,
+                    get_redirect_page_data(err_to_redirect(&err).unwrap()),
+                ),
+                // We parse the error to return an appropriate status code
+                Err(err) => {
+                    // The body tells the app what kind of error this is
+                    let (content_type, body) = err_to_body(path, &err);
+                    header_map.insert(
+                        header::CONTENT_TYPE,
+                        header::HeaderValue::from_static(content_type),
+                    );
+                    (
+                        StatusCode::from_u16(err_to_status_code(&err)).unwrap(),
+                        header_map,
+                        body,
+                    )
+                }
+            }
+        }
+        .await;
 
-                let page_data_str = serde_json::to_string(&page_data).unwrap();
+        // Save any changes made to the session while rendering
+        let session = session.ok().flatten();
+        let session_cookie = match (&opts.sessions, &session) {
+            (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                Ok(set_cookie) => set_cookie,
+                Err(err) => {
+                    (status, header_map, body) = (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        HeaderMap::new(),
+                        fmt_err(&err),
+                    );
+                    None
+                }
+            },
+            _ => None,
+        };
+        // Anything that depends on the session or the user's experiment variants mustn't be
+        // cached for other users
+        let private =
+            is_private_response(session.as_ref(), session_cookie.is_some(), &variants, false);
 
-                (StatusCode::OK, header_map, page_data_str)
-            }
-            // We parse the error to return an appropriate status code
-            Err(err) => (
-                StatusCode::from_u16(err_to_status_code(&err)).unwrap(),
-                HeaderMap::new(),
-                fmt_err(&err),
-            ),
+        for cookie in [set_cookie, session_cookie].into_iter().flatten() {
+            header_map.append(header::SET_COOKIE, cookie.parse().unwrap());
         }
+        if private {
+            make_private(&mut header_map);
+        }
+
+        (status, header_map, body)
     } else {
         (
             StatusCode::NOT_FOUND,
@@ -1,14 +1,18 @@
 use crate::initial_load::initial_load_handler;
 use crate::page_data::page_handler;
+use crate::static_content::static_content_handler;
 use crate::translations::translations_handler;
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::{get, get_service},
+    routing::{get, get_service, post},
     Router,
 };
 use closure::closure;
-use perseus::server::{get_render_cfg, ServerProps};
+use perseus::server::{
+    get_render_cfg, get_static_alias_mount, prepare_maintenance_page, warm_up_incremental,
+    ServerProps, MAINTENANCE_ENDPOINT,
+};
 use perseus::{i18n::TranslationsManager, stores::MutableStore};
 use std::sync::Arc;
 use tower_http::services::{ServeDir, ServeFile};
@@ -33,6 +37,25 @@ pub async fn get_router<M: MutableStore + 'static, T: TranslationsManager + 'sta
         .get_build_state()
         .await
         .expect("Couldn't generate global state.");
+    // Pre-render any incrementally generated pages the user wants ready immediately
+    warm_up_incremental(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
+    prepare_maintenance_page(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
 
     let immutable_store = Arc::new(immutable_store);
     let mutable_store = Arc::new(mutable_store);
@@ -41,9 +64,6 @@ pub async fn get_router<M: MutableStore + 'static, T: TranslationsManager + 'sta
     let render_cfg = Arc::new(render_cfg);
     let global_state = Arc::new(global_state);
 
-    let static_dir = opts.static_dir.clone();
-    let static_aliases = opts.static_aliases.clone();
-
     let router = Router::new()
         .route(
             "/.perseus/bundle.js",
@@ -65,7 +85,7 @@ pub async fn get_router<M: MutableStore + 'static, T: TranslationsManager + 'sta
     let mut router = router
         .route(
             "/.perseus/translations/:locale",
-            get(closure!(clone opts, clone translations_manager, |path| translations_handler::<T>(path, opts, translations_manager))),
+            get(closure!(clone opts, clone translations_manager, |path, headers| translations_handler::<T>(path, headers, opts, translations_manager))),
         )
         .route("/.perseus/page/:locale/*tail", get(
             closure!(
@@ -87,20 +107,39 @@ pub async fn get_router<M: MutableStore + 'static, T: TranslationsManager + 'sta
                     )
             )
         ));
+    // The static content directory and static aliases are all served by the same
+    // handler, which works out what to serve
+    let static_content =
+        get(closure!(clone opts, |http_req| static_content_handler(http_req, opts)));
     // Only add the static content directory route if such a directory is being used
-    if let Some(static_dir) = static_dir {
-        router = router.nest(
-            "/.perseus/static",
-            get_service(ServeDir::new(static_dir)).handle_error(handle_fs_error),
-        )
+    if opts.static_dir.is_some() {
+        router = router.route("/.perseus/static/*path", static_content.clone());
+    }
+    for url in opts.static_aliases.keys() {
+        router = match get_static_alias_mount(url) {
+            Some(mount) => router.route(&format!("{}/*path", mount), static_content.clone()),
+            None => router.route(url, static_content.clone()), /* This comes with a leading
+                                                                * forward slash! */
+        };
     }
-    // Now add support for serving static aliases
-    for (url, static_path) in static_aliases.iter() {
-        // Note that `static_path` is already relative to the right place
-        // (`.perseus/server/`)
+    // Let maintenance mode be toggled while the server is running
+    router = router.route(
+        MAINTENANCE_ENDPOINT,
+        post(
+            closure!(clone opts, |headers: HeaderMap, body: String| async move {
+                let authorization = headers
+                    .get(header::AUTHORIZATION)
+                    .and_then(|header| header.to_str().ok());
+                let status = opts.maintenance.handle_toggle(authorization, &body);
+                StatusCode::from_u16(status).unwrap()
+            }),
+        ),
+    );
+    // Serve the generated `robots.txt` file if there is one
+    if let Some(robots_txt) = opts.robots_txt.clone() {
         router = router.route(
-            url, // This comes with a leading forward slash!
-            get_service(ServeFile::new(static_path)).handle_error(handle_fs_error),
+            "/robots.txt",
+            get(|| async move { ([(header::CONTENT_TYPE, "text/plain")], robots_txt) }),
         );
     }
     // And add the fallback for initial loads
@@ -0,0 +1,37 @@
+use axum::{
+    body::{boxed, Body, BoxBody},
+    http::{header, HeaderValue, Request, Response, StatusCode},
+    response::IntoResponse,
+};
+use perseus::server::{get_static_file, ServerOptions};
+use std::sync::Arc;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// Serves the static directory and static aliases, which all go through this
+/// handler so that the app's static content options are respected.
+pub async fn static_content_handler(
+    http_req: Request<Body>,
+    opts: Arc<ServerOptions>,
+) -> Response<BoxBody> {
+    let file = match get_static_file(&opts, http_req.uri().path()) {
+        Some(file) => file,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let content_type = opts.static_content.get_content_type(&file);
+    let req_headers = http_req.headers().keys().map(|name| name.as_str());
+    if let Some(contents) = opts.static_content.read_small_file(&file, req_headers) {
+        return ([(header::CONTENT_TYPE, content_type)], contents).into_response();
+    }
+    // This streams the file, handling conditional and range requests for us
+    match ServeFile::new(&file).oneshot(http_req).await {
+        Ok(res) => {
+            let mut res = res.map(boxed);
+            if let Ok(content_type) = HeaderValue::from_str(&content_type) {
+                res.headers_mut().insert(header::CONTENT_TYPE, content_type);
+            }
+            res
+        }
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Couldn't serve file.").into_response(),
+    }
+}
@@ -1,13 +1,24 @@
-use axum::{extract::Path, http::StatusCode};
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
 use fmterr::fmt_err;
-use perseus::{i18n::TranslationsManager, server::ServerOptions};
+use perseus::{
+    i18n::TranslationsManager,
+    server::{etag_matches, get_etag, ServerOptions},
+};
 use std::sync::Arc;
 
+/// The handler for calls to `.perseus/translations/:locale`. Responses have
+/// ETags, so the browser will only download translations again if they've
+/// changed.
 pub async fn translations_handler<T: TranslationsManager>(
     Path(locale): Path<String>,
+    headers: HeaderMap,
     opts: Arc<ServerOptions>,
     translations_manager: Arc<T>,
-) -> (StatusCode, String) {
+) -> Response {
     // Check if the locale is supported
     if opts.locales.is_supported(&locale) {
         // We know that the locale is supported, so any failure to get translations is a
@@ -17,11 +28,27 @@ pub async fn translations_handler<T: TranslationsManager>(
             .await;
         let translations = match translations {
             Ok(translations) => translations,
-            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, fmt_err(&err)),
+            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, fmt_err(&err)).into_response(),
         };
+        let etag = get_etag(&translations);
+        let if_none_match = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|val| val.to_str().ok());
+        if etag_matches(if_none_match, &etag) {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+        }
 
-        (StatusCode::OK, translations)
+        (
+            StatusCode::OK,
+            [
+                // The browser should always check that the translations haven't changed
+                (header::CACHE_CONTROL, "no-cache".to_string()),
+                (header::ETAG, etag),
+            ],
+            translations,
+        )
+            .into_response()
     } else {
-        (StatusCode::NOT_FOUND, "locale not supported".to_string())
+        (StatusCode::NOT_FOUND, "locale not supported".to_string()).into_response()
     }
 }
@@ -0,0 +1,460 @@
+use crate::errors::*;
+use crate::parse::{AnalyzeOpts, Opts};
+use crate::{get_dist_dir, get_user_crate_name};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wasmparser::{BinaryReaderError, Name, NameSectionReader, Parser, Payload, TypeRef};
+
+/// The file the last analysis is stored in (in `dist/`), so the next one can be
+/// compared with it.
+static ANALYSIS_FILE: &str = "analysis.json";
+/// Crates that are almost always only needed on the engine-side, and that
+/// usually end up in the Wasm bundle by mistake.
+static SERVER_CRATES: [&str; 18] = [
+    "actix_http",
+    "actix_web",
+    "axum",
+    "diesel",
+    "hyper",
+    "lettre",
+    "mio",
+    "mongodb",
+    "native_tls",
+    "openssl",
+    "postgres",
+    "redis",
+    "ring",
+    "rocket",
+    "rustls",
+    "sqlx",
+    "tokio_postgres",
+    "warp",
+];
+/// The size below which a server-side crate in the Wasm bundle won't be warned
+/// about, since it's probably just a few shared types.
+const SERVER_CRATE_THRESHOLD: u64 = 1024;
+
+/// The sizes of the parts of a Wasm bundle, in bytes.
+#[derive(Serialize, Deserialize, Default)]
+struct Analysis {
+    /// The total size of the bundle.
+    total: u64,
+    /// The size of the code from each crate, along with the size of the
+    /// non-code sections (in square brackets).
+    crates: HashMap<String, u64>,
+    /// The size of the code from each template module in the app (i.e. each
+    /// module in `templates/`).
+    templates: HashMap<String, u64>,
+}
+
+/// Analyzes the size of the user's last Wasm build, showing which crates and
+/// templates contribute the most to it, and how that's changed since the last
+/// analysis. Returns an exit code.
+pub fn analyze(
+    dir: PathBuf,
+    opts: &AnalyzeOpts,
+    global_opts: &Opts,
+) -> Result<i32, ExecutionError> {
+    let crate_name = get_user_crate_name(&dir)?;
+    let dist = get_dist_dir(&dir, global_opts);
+    // This has to be analyzed before post-processing, since `wasm-opt` strips the
+    // names of functions
+    let wasm_path = dist.join(format!(
+        "target_wasm/wasm32-unknown-unknown/{profile}/{crate_name}.wasm",
+        profile = if opts.release { "release" } else { "debug" },
+        crate_name = crate_name
+    ));
+    // We'll show this relative to the project if we can
+    let wasm_path_str = wasm_path
+        .strip_prefix(&dir)
+        .unwrap_or(&wasm_path)
+        .to_string_lossy()
+        .to_string();
+    let wasm = fs::read(&wasm_path).map_err(|err| ExecutionError::ReadWasmForAnalysisFailed {
+        path: wasm_path_str.clone(),
+        source: err,
+    })?;
+    let sections = parse_wasm(&wasm).map_err(|err| ExecutionError::ParseWasmForAnalysisFailed {
+        path: wasm_path_str.clone(),
+        source: err,
+    })?;
+    let analysis = analyze_sections(sections, &crate_name.replace('-', "_"));
+
+    let previous = fs::read_to_string(dist.join(ANALYSIS_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Analysis>(&contents).ok());
+    let previous = previous.as_ref();
+
+    print!("Analyzed '{}' ({}", wasm_path_str, fmt_size(analysis.total));
+    if let Some(previous) = previous {
+        print!(", {}", fmt_diff(analysis.total, Some(previous.total)));
+    }
+    println!(")");
+    if let Ok(metadata) = fs::metadata(dist.join("pkg/perseus_engine_bg.wasm")) {
+        println!(
+            "The last post-processed bundle is {}.",
+            fmt_size(metadata.len())
+        );
+    }
+    println!();
+    println!("Largest crates:");
+    print_top(
+        &analysis.crates,
+        previous.map(|previous| &previous.crates),
+        analysis.total,
+        opts.top,
+    );
+    if !analysis.templates.is_empty() {
+        println!();
+        println!("Largest templates:");
+        print_top(
+            &analysis.templates,
+            previous.map(|previous| &previous.templates),
+            analysis.total,
+            opts.top,
+        );
+    }
+
+    let warnings = get_server_crate_warnings(&analysis);
+    if !warnings.is_empty() {
+        println!();
+        println!("Possible engine-side crates:");
+        for warning in warnings {
+            println!("  {}", warning);
+        }
+    }
+
+    // If this fails, the next analysis just won't have anything to compare with
+    if let Ok(contents) = serde_json::to_string(&analysis) {
+        let _ = fs::write(dist.join(ANALYSIS_FILE), contents);
+    }
+
+    Ok(0)
+}
+
+/// Checks the Wasm artifact at the given path for crates that are usually only
+/// used on the engine-side, returning a warning for each one found. If the
+/// artifact can't be read, there won't be any warnings.
+pub fn check_server_crates(wasm_path: &Path, crate_name: &str) -> Vec<String> {
+    let sections = match fs::read(wasm_path)
+        .ok()
+        .and_then(|wasm| parse_wasm(&wasm).ok())
+    {
+        Some(sections) => sections,
+        None => return Vec::new(),
+    };
+    get_server_crate_warnings(&analyze_sections(sections, &crate_name.replace('-', "_")))
+}
+
+/// Gets a warning for each crate in the given analysis that's usually only used
+/// on the engine-side.
+fn get_server_crate_warnings(analysis: &Analysis) -> Vec<String> {
+    let mut warnings = SERVER_CRATES
+        .iter()
+        .filter_map(|krate| {
+            let size = *analysis.crates.get(*krate)?;
+            (size >= SERVER_CRATE_THRESHOLD).then(|| {
+                format!(
+                    "'{}' adds {} to your Wasm bundle, but it's usually only used on the engine-side (if that's true here, move it to `[target.'cfg(not(target_arch = \"wasm32\"))'.dependencies]` in your `Cargo.toml` and gate the code that uses it behind `#[cfg(not(target_arch = \"wasm32\"))]`)",
+                    krate,
+                    fmt_size(size)
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    warnings.sort();
+    warnings
+}
+
+/// Prints the given number of the largest entries from the given sizes, along
+/// with their share of the total and how they've changed since the previous
+/// analysis, if there was one.
+fn print_top(
+    sizes: &HashMap<String, u64>,
+    previous: Option<&HashMap<String, u64>>,
+    total: u64,
+    top: usize,
+) {
+    let mut sizes = sizes.iter().collect::<Vec<_>>();
+    sizes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let width = sizes
+        .iter()
+        .take(top)
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, size) in sizes.iter().take(top) {
+        print!(
+            "  {:width$}  {:>10}  {:>5.1}%",
+            name,
+            fmt_size(**size),
+            **size as f64 / total.max(1) as f64 * 100.0,
+            width = width
+        );
+        if let Some(previous) = previous {
+            print!("  {}", fmt_diff(**size, previous.get(*name).copied()));
+        }
+        println!();
+    }
+    if sizes.len() > top {
+        println!("  ...and {} more", sizes.len() - top);
+    }
+}
+
+/// Formats the given number of bytes for humans.
+fn fmt_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.2} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Formats the change from the given previous size to the given current one.
+fn fmt_diff(current: u64, previous: Option<u64>) -> String {
+    match previous {
+        None => "new".to_string(),
+        Some(previous) if previous == current => "unchanged".to_string(),
+        Some(previous) if current > previous => format!("+{}", fmt_size(current - previous)),
+        Some(previous) => format!("-{}", fmt_size(previous - current)),
+    }
+}
+
+/// The parts of a Wasm module that are relevant to its size.
+struct Sections {
+    /// The size of each function body, by function index.
+    functions: Vec<(u32, u64)>,
+    /// The names of functions, by function index.
+    names: HashMap<u32, String>,
+    /// The size of the data section.
+    data: u64,
+    /// The size of the DWARF debugging information.
+    debug: u64,
+    /// The size of any other custom sections.
+    custom: u64,
+    /// The size of everything else.
+    other: u64,
+}
+
+/// Groups the code in the given sections by crate and template.
+fn analyze_sections(sections: Sections, crate_name: &str) -> Analysis {
+    let mut analysis = Analysis::default();
+    let templates_prefix = format!("{}::templates::", crate_name);
+    for (idx, size) in sections.functions {
+        analysis.total += size;
+        let name = match sections.names.get(&idx) {
+            Some(name) => format!("{:#}", rustc_demangle::demangle(name)),
+            None => {
+                *analysis
+                    .crates
+                    .entry("[unnamed functions]".to_string())
+                    .or_default() += size;
+                continue;
+            }
+        };
+        // Trait implementations are attributed to the implementing type
+        let path = name.trim_start_matches(|c| matches!(c, '<' | '&' | '*' | '[' | '('));
+        let path = path
+            .trim_start_matches("mut ")
+            .trim_start_matches("const ")
+            .trim_start_matches("dyn ");
+        let krate = match path.split_once("::") {
+            Some((krate, _)) if krate.chars().all(|c| c.is_alphanumeric() || c == '_') => krate,
+            _ => "[other functions]",
+        };
+        *analysis.crates.entry(krate.to_string()).or_default() += size;
+
+        if let Some(rest) = path.strip_prefix(&templates_prefix) {
+            let module = rest.split("::").next().unwrap_or(rest);
+            *analysis
+                .templates
+                .entry(format!("templates::{}", module))
+                .or_default() += size;
+        }
+    }
+    for (name, size) in [
+        ("[data]", sections.data),
+        ("[debug info]", sections.debug),
+        ("[custom sections]", sections.custom),
+        ("[other sections]", sections.other),
+    ] {
+        if size > 0 {
+            analysis.total += size;
+            analysis.crates.insert(name.to_string(), size);
+        }
+    }
+
+    analysis
+}
+
+/// Parses the parts of the given Wasm module that are relevant to its size.
+fn parse_wasm(wasm: &[u8]) -> Result<Sections, BinaryReaderError> {
+    let mut sections = Sections {
+        functions: Vec::new(),
+        names: HashMap::new(),
+        data: 0,
+        debug: 0,
+        custom: 0,
+        other: 0,
+    };
+    let mut imported_functions = 0;
+    let mut defined_functions = 0;
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            // Imported functions come before defined ones in the function index space
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                sections.functions.push((
+                    imported_functions + defined_functions,
+                    body.range().len() as u64,
+                ));
+                defined_functions += 1;
+            }
+            Payload::DataSection(data) => sections.data += data.range().len() as u64,
+            Payload::CustomSection(custom) => {
+                if custom.name().starts_with(".debug_") {
+                    sections.debug += custom.range().len() as u64;
+                } else {
+                    sections.custom += custom.range().len() as u64;
+                }
+                if custom.name() == "name" {
+                    parse_names(custom.data(), custom.data_offset(), &mut sections.names)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    // Everything else (including the headers of the sections we've counted) is
+    // attributed to the module's structure
+    let counted = sections.functions.iter().map(|(_, size)| size).sum::<u64>()
+        + sections.data
+        + sections.debug
+        + sections.custom;
+    sections.other = (wasm.len() as u64).saturating_sub(counted);
+
+    Ok(sections)
+}
+
+/// Parses the function names from the given contents of a `name` custom
+/// section, which start at the given offset in the module.
+fn parse_names(
+    data: &[u8],
+    offset: usize,
+    names: &mut HashMap<u32, String>,
+) -> Result<(), BinaryReaderError> {
+    for subsection in NameSectionReader::new(data, offset) {
+        // We only care about function names
+        if let Name::Function(map) = subsection? {
+            for naming in map {
+                let naming = naming?;
+                names.insert(naming.index, naming.name.to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a section with the given ID and contents (which must be less
+    /// than 128 bytes long).
+    fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+        let mut section = vec![id, contents.len() as u8];
+        section.extend_from_slice(contents);
+        section
+    }
+    /// Encodes a name (which must be less than 128 bytes long).
+    fn name(name: &str) -> Vec<u8> {
+        let mut encoded = vec![name.len() as u8];
+        encoded.extend_from_slice(name.as_bytes());
+        encoded
+    }
+    /// Builds a module with one imported function, two defined functions
+    /// (with names), and a data segment. If `names` is given, it'll be used as
+    /// the contents of the function names subsection.
+    fn module(names: Option<Vec<u8>>) -> Vec<u8> {
+        let names = names.unwrap_or_else(|| {
+            let mut names = vec![2, 1];
+            names.extend(name("app::templates::index::get_template"));
+            names.push(2);
+            names.extend(name("serde::de::deserialize"));
+            names
+        });
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        // One function type, `() -> ()`
+        module.extend(section(1, &[1, 0x60, 0, 0]));
+        let mut imports = vec![1];
+        imports.extend(name("env"));
+        imports.extend(name("f"));
+        imports.extend([0, 0]);
+        module.extend(section(2, &imports));
+        module.extend(section(3, &[2, 0, 0]));
+        // Two bodies, one with just `end`, and the other with two `nop`s too
+        module.extend(section(10, &[2, 2, 0, 0x0b, 4, 0, 0x01, 0x01, 0x0b]));
+        // One passive data segment
+        module.extend(section(11, &[1, 1, 3, b'a', b'b', b'c']));
+        let mut name_section = name("name");
+        name_section.push(1);
+        name_section.push(names.len() as u8);
+        name_section.extend(names);
+        module.extend(section(0, &name_section));
+
+        module
+    }
+
+    #[test]
+    fn parses_sizes_and_names() {
+        let wasm = module(None);
+        let sections = parse_wasm(&wasm).unwrap();
+        // Imported functions take the first indices
+        assert_eq!(sections.functions, vec![(1, 2), (2, 4)]);
+        assert_eq!(
+            sections.names.get(&1).map(|name| name.as_str()),
+            Some("app::templates::index::get_template")
+        );
+        assert_eq!(sections.data, 6);
+        assert_eq!(sections.debug, 0);
+        // Everything should be accounted for
+        assert_eq!(
+            2 + 4 + sections.data + sections.custom + sections.other,
+            wasm.len() as u64
+        );
+
+        let analysis = analyze_sections(sections, "app");
+        assert_eq!(analysis.total, wasm.len() as u64);
+        assert_eq!(analysis.crates["app"], 2);
+        assert_eq!(analysis.crates["serde"], 4);
+        assert_eq!(analysis.templates["templates::index"], 2);
+    }
+    #[test]
+    fn rejects_malformed_modules() {
+        let wasm = module(None);
+        // Truncated in the middle of the code section
+        let code_start = wasm.iter().position(|byte| *byte == 10).unwrap();
+        assert!(parse_wasm(&wasm[..code_start + 4]).is_err());
+        // Truncated in the middle of the name section
+        assert!(parse_wasm(&wasm[..wasm.len() - 3]).is_err());
+        // Not Wasm at all
+        assert!(parse_wasm(b"\x7fELF\x02\x01\x01\0").is_err());
+        assert!(parse_wasm(b"").is_err());
+        // A section that claims to be longer than the module
+        let mut overlong = b"\0asm\x01\0\0\0".to_vec();
+        overlong.extend([11, 100, 0]);
+        assert!(parse_wasm(&overlong).is_err());
+        // A names subsection that claims to have more names than it does
+        assert!(parse_wasm(&module(Some(vec![5, 1, 1, b'a']))).is_err());
+    }
+}
@@ -5,13 +5,15 @@ use fmterr::fmt_err;
 use notify::{recommended_watcher, RecursiveMode, Watcher};
 use perseus_cli::parse::{ExportOpts, ServeOpts, SnoopSubcommand};
 use perseus_cli::{
-    build, check_env, delete_artifacts, deploy, export, init, new,
+    analyze, build, check, check_deployment, check_env, clean, delete_artifacts, deploy,
+    deploy_docker, export, init, new,
     parse::{Opts, Subcommand},
-    serve, serve_exported, tinker,
+    regenerate_static, serve, serve_exported, tinker, tools, verify,
 };
 use perseus_cli::{
-    create_dist, delete_dist, errors::*, export_error_page, order_reload, run_reload_server,
-    snoop_build, snoop_server, snoop_wasm_build, Tools,
+    create_dist, delete_dist, errors::*, export_error_page, find_free_port, get_dist_dir,
+    is_loopback, note_port_change, order_reload, run_reload_server, snoop_build, snoop_server,
+    snoop_wasm_build, write_deployment_manifest, Tools, BROWSER_OPENED_MARKER,
 };
 use std::env;
 use std::path::{Path, PathBuf};
@@ -52,7 +54,12 @@ async fn real_main() -> i32 {
             // delete the tools directory to *try* to avoid corruptions)
             if matches!(err, Error::InstallError(_)) {
                 // We'll try to delete *both* the local one and the system-wide cache
-                if let Err(err) = delete_artifacts(dir.clone(), "tools") {
+                // The options will have been parsed successfully if we got this far
+                let dist = match Opts::try_parse() {
+                    Ok(opts) => get_dist_dir(&dir, &opts),
+                    Err(_) => dir.join("dist"),
+                };
+                if let Err(err) = delete_artifacts(dist, "tools") {
                     eprintln!("{}", fmt_err(&err));
                 }
                 if let Some(dirs) = ProjectDirs::from("", "perseus", "perseus_cli") {
@@ -78,6 +85,9 @@ async fn real_main() -> i32 {
 enum Event {
     // Sent if we should restart the child process
     Reload,
+    // Sent if only the app's translations have changed, in which case we can
+    // regenerate its static artifacts without restarting the server
+    TranslationsChanged,
     // Sent if we should terminate the child process
     Terminate,
 }
@@ -90,7 +100,15 @@ enum Event {
 // will panic
 async fn core(dir: PathBuf) -> Result<i32, Error> {
     // Parse the CLI options with `clap`
-    let opts = Opts::parse();
+    let mut opts = Opts::parse();
+    // If we're being run by a watching parent process, it might have had to put the
+    // reload server on a different port
+    if let Some(port) = env::var("PERSEUS_RELOAD_SERVER_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+    {
+        opts.reload_server_port = port;
+    }
 
     // Warn the user if they're using the CLI single-threaded mode
     if opts.sequential {
@@ -134,6 +152,10 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
             })
             .expect("couldn't set handlers to gracefully terminate process");
 
+            // If the browser was opened in a previous session, it should be opened again
+            let dist = get_dist_dir(&dir, &opts);
+            let _ = std::fs::remove_file(dist.join(BROWSER_OPENED_MARKER));
+
             // Set up a browser reloading server
             // We provide an option for the user to disable this
             let Opts {
@@ -141,6 +163,20 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
                 reload_server_port,
                 ..
             } = opts.clone();
+            // Devices reaching the app at a public host will need to reach the reload
+            // server too
+            let reload_server_host = match &opts.subcmd {
+                Subcommand::Serve(ServeOpts {
+                    public_host: Some(_),
+                    ..
+                }) if is_loopback(&reload_server_host) => "0.0.0.0".to_string(),
+                _ => reload_server_host,
+            };
+            // If the port is taken, we'll use the next free one, and the child processes
+            // will be told about it (which will pass it through to the browser)
+            let reload_server_port = find_free_port(&reload_server_host, reload_server_port)?;
+            note_port_change("reload server", opts.reload_server_port, reload_server_port);
+            let reload_server_port_str = reload_server_port.to_string();
             if !opts.no_browser_reload {
                 tokio::task::spawn(async move {
                     run_reload_server(reload_server_host, reload_server_port).await;
@@ -162,22 +198,46 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
             // reliable)
             args.remove(0);
 
+            // Translations are read at runtime, so, when we're serving, changes to them
+            // alone don't need the server to be restarted (exports need to be redone from
+            // scratch anyway)
+            let is_serve = matches!(opts.subcmd, Subcommand::Serve(_));
+            let watch_dir = dir.clone();
             // Set up a watcher
-            let mut watcher = recommended_watcher(move |_| {
+            let mut watcher = recommended_watcher(move |res: notify::Result<notify::Event>| {
+                let translations_only = match res {
+                    Ok(ev) => {
+                        is_serve
+                            && !ev.paths.is_empty()
+                            && ev.paths.iter().all(|path| {
+                                path.strip_prefix(&watch_dir)
+                                    .or_else(|_| path.strip_prefix("."))
+                                    .unwrap_or(path)
+                                    .starts_with("translations")
+                            })
+                    }
+                    Err(_) => false,
+                };
+                let ev = if translations_only {
+                    Event::TranslationsChanged
+                } else {
+                    Event::Reload
+                };
                 // If this fails, the watcher channel was completely disconnected, which should
                 // never happen (it's in a loop)
-                tx_fs.send(Event::Reload).unwrap();
+                tx_fs.send(ev).unwrap();
             })
             .map_err(|err| WatchError::WatcherSetupFailed { source: err })?;
             // Watch the current directory
             for entry in std::fs::read_dir(".")
                 .map_err(|err| WatchError::ReadCurrentDirFailed { source: err })?
             {
-                // We want to exclude `target/` and `dist`, otherwise we should watch everything
+                // We want to exclude `target/` and `dist/` (wherever that is), otherwise we
+                // should watch everything
                 let entry = entry.map_err(|err| WatchError::ReadDirEntryFailed { source: err })?;
                 let name = entry.file_name();
                 if name != "target"
-                    && name != "dist"
+                    && !dist.starts_with(dir.join(&name))
                     && name != ".git"
                     && name != "target_engine"
                     && name != "target_wasm"
@@ -210,7 +270,9 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
             let child = child
                 .args(&args)
                 .env("PERSEUS_WATCHING_PROHIBITED", "true")
-                .env("PERSEUS_USE_RELOAD_SERVER", "true"); // This is for internal use ONLY
+                .env("PERSEUS_USE_RELOAD_SERVER", "true") // This is for internal use ONLY
+                .env("PERSEUS_RELOAD_SERVER_HOST", &opts.reload_server_host)
+                .env("PERSEUS_RELOAD_SERVER_PORT", &reload_server_port_str);
             #[cfg(debug_assertions)]
             let child = child.env_remove("TEST_EXAMPLE"); // We want to use the current directory in development
             let mut child = child
@@ -230,13 +292,34 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
                         let child_l = child_l
                             .args(&args)
                             .env("PERSEUS_WATCHING_PROHIBITED", "true")
-                            .env("PERSEUS_USE_RELOAD_SERVER", "true"); // This is for internal use ONLY
+                            .env("PERSEUS_USE_RELOAD_SERVER", "true") // This is for internal use ONLY
+                            .env("PERSEUS_RELOAD_SERVER_HOST", &opts.reload_server_host)
+                            .env("PERSEUS_RELOAD_SERVER_PORT", &reload_server_port_str);
                         #[cfg(debug_assertions)]
                         let child_l = child_l.env_remove("TEST_EXAMPLE"); // We want to use the current directory in development
                         child = child_l
                             .group_spawn()
                             .map_err(|err| WatchError::SpawnSelfFailed { source: err })?;
                     }
+                    Ok(Event::TranslationsChanged) => {
+                        // The server can keep running, we just need the static artifacts to be
+                        // regenerated with the new translations (which a separate process will
+                        // do, before telling any connected browsers to reload)
+                        let mut regen = Command::new(&bin_name);
+                        let regen = regen
+                            .args(&args)
+                            .env("PERSEUS_WATCHING_PROHIBITED", "true")
+                            .env("PERSEUS_USE_RELOAD_SERVER", "true") // This is for internal use ONLY
+                            .env("PERSEUS_RELOAD_SERVER_HOST", &opts.reload_server_host)
+                            .env("PERSEUS_RELOAD_SERVER_PORT", &reload_server_port_str)
+                            .env("PERSEUS_TRANSLATIONS_ONLY", "true"); // This is for internal use ONLY
+                        #[cfg(debug_assertions)]
+                        let regen = regen.env_remove("TEST_EXAMPLE"); // We want to use the current directory in development
+                                                                      // Any errors will have been printed by the process itself
+                        let _ = regen
+                            .status()
+                            .map_err(|err| WatchError::SpawnSelfFailed { source: err })?;
+                    }
                     Ok(Event::Terminate) => {
                         // This means the user is trying to stop the process
                         // We have to manually terminate the process group, because it's a process
@@ -257,21 +340,23 @@ async fn core(dir: PathBuf) -> Result<i32, Error> {
 }
 
 async fn core_watch(dir: PathBuf, opts: Opts) -> Result<i32, Error> {
-    // We install the tools for every command except `new`, `init`, and `clean`
+    let dist = get_dist_dir(&dir, &opts);
+    // We install the tools for every command except `new`, `init`, `clean`, and
+    // `tools`
     let exit_code = match opts.subcmd {
         Subcommand::Build(ref build_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             // Delete old build artifacts
-            delete_artifacts(dir.clone(), "static")?;
+            delete_artifacts(dist.clone(), "static")?;
             build(dir, build_opts, &tools, &opts)?
         }
         Subcommand::Export(ref export_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             // Delete old build/export artifacts
-            delete_artifacts(dir.clone(), "static")?;
-            delete_artifacts(dir.clone(), "exported")?;
+            delete_artifacts(dist.clone(), "static")?;
+            delete_artifacts(dist.clone(), "exported")?;
             let exit_code = export(dir.clone(), export_opts, &tools, &opts)?;
             if exit_code != 0 {
                 return Ok(exit_code);
@@ -279,61 +364,78 @@ async fn core_watch(dir: PathBuf, opts: Opts) -> Result<i32, Error> {
             if export_opts.serve {
                 // Tell any connected browsers to reload
                 order_reload(opts.reload_server_host.to_string(), opts.reload_server_port);
-                serve_exported(dir, export_opts.host.to_string(), export_opts.port).await;
+                serve_exported(dist, export_opts.host.to_string(), export_opts.port).await?;
             }
             0
         }
+        // If only the app's translations have changed, the server will still be running
+        Subcommand::Serve(ref serve_opts) if env::var("PERSEUS_TRANSLATIONS_ONLY").is_ok() => {
+            let tools = Tools::new(&dir, &opts).await?;
+            let exit_code = regenerate_static(dir, serve_opts.release, &tools, &opts)?;
+            if exit_code == 0 {
+                // Tell any connected browsers to reload
+                order_reload(opts.reload_server_host.to_string(), opts.reload_server_port);
+            }
+            exit_code
+        }
         Subcommand::Serve(ref serve_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             if !serve_opts.no_build {
-                delete_artifacts(dir.clone(), "static")?;
+                delete_artifacts(dist.clone(), "static")?;
             }
             // This orders reloads internally
             let (exit_code, _server_path) = serve(dir, serve_opts, &tools, &opts)?;
             exit_code
         }
         Subcommand::Test(ref test_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             // This will be used by the subcrates
             env::set_var("PERSEUS_TESTING", "true");
             // Delete old build artifacts if `--no-build` wasn't specified
             if !test_opts.no_build {
-                delete_artifacts(dir.clone(), "static")?;
+                delete_artifacts(dist.clone(), "static")?;
             }
             let (exit_code, _server_path) = serve(dir, test_opts, &tools, &opts)?;
             exit_code
         }
-        Subcommand::Clean => {
-            delete_dist(dir)?;
-            // Warn the user that the next run will be quite a bit slower
-            eprintln!(
-                "[NOTE]: Build artifacts have been deleted, the next run will take some time."
-            );
-            0
+        Subcommand::Clean(ref clean_opts) => clean(dir, clean_opts, &opts)?,
+        Subcommand::Analyze(ref analyze_opts) => analyze(dir, analyze_opts, &opts)?,
+        // Container deployments are built entirely inside the container
+        Subcommand::Deploy(ref deploy_opts) if deploy_opts.docker => {
+            deploy_docker(dir, deploy_opts, &opts)?
         }
         Subcommand::Deploy(ref deploy_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
-            delete_artifacts(dir.clone(), "static")?;
-            delete_artifacts(dir.clone(), "exported")?;
-            delete_artifacts(dir.clone(), "pkg")?;
-            deploy(dir, deploy_opts, &tools, &opts)?
+            delete_artifacts(dist.clone(), "static")?;
+            delete_artifacts(dist.clone(), "exported")?;
+            delete_artifacts(dist.clone(), "pkg")?;
+            let mut exit_code = deploy(dir.clone(), deploy_opts, &tools, &opts)?;
+            if exit_code == 0 && deploy_opts.check {
+                exit_code = check_deployment(dir, deploy_opts).await?;
+            }
+            // Checking starts the server, which can add files to the output, so this has
+            // to come last
+            if exit_code == 0 {
+                write_deployment_manifest(deploy_opts)?;
+            }
+            exit_code
         }
         Subcommand::Tinker(ref tinker_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             // Unless we've been told not to, we start with a blank slate
             // This will remove old tinkerings and eliminate any possible corruptions (which
             // are very likely with tinkering!)
             if !tinker_opts.no_clean {
-                delete_dist(dir.clone())?;
+                delete_dist(dist.clone())?;
             }
             tinker(dir, &tools, &opts)?
         }
         Subcommand::Snoop(ref snoop_subcmd) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             match snoop_subcmd {
                 SnoopSubcommand::Build => snoop_build(dir, &tools, &opts)?,
@@ -343,11 +445,18 @@ async fn core_watch(dir: PathBuf, opts: Opts) -> Result<i32, Error> {
                 }
             }
         }
+        Subcommand::Check(ref check_opts) => {
+            create_dist(&dist)?;
+            let tools = Tools::new(&dir, &opts).await?;
+            check(dir, check_opts, &tools, &opts)?
+        }
+        Subcommand::Verify(ref verify_opts) => verify(verify_opts)?,
         Subcommand::ExportErrorPage(ref eep_opts) => {
-            create_dist(&dir)?;
+            create_dist(&dist)?;
             let tools = Tools::new(&dir, &opts).await?;
             export_error_page(dir, eep_opts, &tools, &opts)?
         }
+        Subcommand::Tools(ref tools_subcmd) => tools(&dir, tools_subcmd, &opts).await?,
         Subcommand::New(ref new_opts) => new(dir, new_opts, &opts)?,
         Subcommand::Init(ref init_opts) => init(dir, init_opts)?,
     };
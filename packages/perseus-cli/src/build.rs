@@ -1,11 +1,15 @@
-use crate::cmd::{cfg_spinner, run_stage};
+use crate::analyze::check_server_crates;
+use crate::cmd::{cfg_spinner, print_warning, quote_path, run_stage_with_progress};
+use crate::deterministic::{get_source_date_epoch, get_wasm_rustflags};
+use crate::dev_wasm::DevWasmOpts;
 use crate::install::Tools;
 use crate::parse::{BuildOpts, Opts};
 use crate::thread::{spawn_thread, ThreadHandle};
-use crate::{errors::*, get_user_crate_name};
+use crate::wasm_cache::run_wasm_stage;
+use crate::{errors::*, get_dist_dir, get_user_crate_name};
 use console::{style, Emoji};
 use indicatif::{MultiProgress, ProgressBar};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Emojis for stages
 static GENERATING: Emoji<'_, '_> = Emoji("🔨", "");
@@ -46,13 +50,15 @@ pub fn build_internal(
     let Opts {
         wasm_release_rustflags,
         cargo_engine_args,
-        cargo_browser_args,
-        wasm_bindgen_args,
+        mut cargo_browser_args,
+        mut wasm_bindgen_args,
         wasm_opt_args,
+        deterministic,
         ..
     } = global_opts.clone();
 
     let crate_name = get_user_crate_name(&dir)?;
+    let dist = get_dist_dir(&dir, global_opts);
     // Static generation message
     let sg_msg = format!(
         "{} {} Generating your app",
@@ -76,22 +82,34 @@ pub fn build_internal(
     let wb_spinner = cfg_spinner(wb_spinner, &wb_msg);
     let wb_dir = dir;
     let cargo_engine_exec = tools.cargo_engine.clone();
+    let engine_envs = get_engine_envs(&sg_dir, global_opts);
+    let dev_wasm = if is_release {
+        DevWasmOpts::default()
+    } else {
+        DevWasmOpts::get(&sg_dir, global_opts)?
+    };
+    dev_wasm.apply_args(&mut cargo_browser_args, &mut wasm_bindgen_args);
+    let wasm_rustflags = get_wasm_rustflags(
+        &sg_dir,
+        is_release,
+        deterministic,
+        &wasm_release_rustflags,
+        dev_wasm.rustflags().as_deref(),
+    );
+    let wasm_envs = dev_wasm.envs();
     let sg_thread = spawn_thread(
         move || {
-            handle_exit_code!(run_stage(
-                vec![&format!(
+            handle_exit_code!(run_stage_with_progress(
+                &format!(
                     "{} run {} {}",
                     cargo_engine_exec,
                     if is_release { "--release" } else { "" },
                     cargo_engine_args
-                )],
+                ),
                 &sg_dir,
                 &sg_spinner,
                 &sg_msg,
-                vec![
-                    ("PERSEUS_ENGINE_OPERATION", "build"),
-                    ("CARGO_TARGET_DIR", "dist/target_engine")
-                ]
+                engine_envs.iter().map(|(k, v)| (*k, v.as_str())).collect()
             )?);
 
             Ok(0)
@@ -100,46 +118,61 @@ pub fn build_internal(
     );
     let wb_thread = spawn_thread(
         move || {
-            let mut cmds = vec![
-            // Build the Wasm artifact first (and we know where it will end up, since we're setting the target directory)
-            format!(
+            let profile = if is_release { "release" } else { "debug" };
+            // We know where the Wasm artifact will end up, since we're setting the target
+            // directory
+            let target_wasm = dist.join("target_wasm");
+            let wasm_path = target_wasm.join(format!(
+                "wasm32-unknown-unknown/{profile}/{crate_name}.wasm",
+                profile = profile,
+                crate_name = crate_name
+            ));
+            let pkg = dist.join("pkg");
+            let build_cmd = format!(
                 "{} build --target wasm32-unknown-unknown {} {}",
                 tools.cargo_browser,
                 if is_release { "--release" } else { "" },
                 cargo_browser_args
-            ),
-            // NOTE The `wasm-bindgen` version has to be *identical* to the dependency version
-            format!(
-                "{cmd} ./dist/target_wasm/wasm32-unknown-unknown/{profile}/{crate_name}.wasm --out-dir dist/pkg --out-name perseus_engine --target web {args}",
+            );
+            let mut post_cmds = vec![
+                // NOTE The `wasm-bindgen` version has to be *identical* to the dependency version
+                format!(
+                "{cmd} {wasm_path} --out-dir {pkg} --out-name perseus_engine --target web {args}",
                 cmd=tools.wasm_bindgen,
-                profile={ if is_release { "release" } else { "debug" } },
+                wasm_path=quote_path(&wasm_path),
+                pkg=quote_path(&pkg),
                 args=wasm_bindgen_args,
-                crate_name=crate_name
-            )
-        ];
+            ),
+            ];
             // If we're building for release, then we should run `wasm-opt`
             if is_release {
-                cmds.push(format!(
-                "{cmd} -Oz ./dist/pkg/perseus_engine_bg.wasm -o ./dist/pkg/perseus_engine_bg.wasm {args}",
-                cmd=tools.wasm_opt,
-                args=wasm_opt_args
-            ));
+                post_cmds.push(format!(
+                    "{cmd} -Oz {bundle} -o {bundle} {args}",
+                    cmd = tools.wasm_opt,
+                    bundle = quote_path(&pkg.join("perseus_engine_bg.wasm")),
+                    args = wasm_opt_args
+                ));
+            }
+            let target_wasm = target_wasm.to_string_lossy();
+            let mut envs = vec![("CARGO_TARGET_DIR", target_wasm.as_ref())];
+            if let Some(rustflags) = &wasm_rustflags {
+                envs.push(("RUSTFLAGS", rustflags));
             }
-            let cmds = cmds.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-            handle_exit_code!(run_stage(
-                cmds,
+            envs.extend(wasm_envs);
+            // Post-processing will be skipped if the Wasm artifact hasn't changed
+            handle_exit_code!(run_wasm_stage(
+                &build_cmd,
+                post_cmds,
+                &wasm_path,
+                &pkg,
                 &wb_dir,
                 &wb_spinner,
                 &wb_msg,
-                if is_release {
-                    vec![
-                        ("CARGO_TARGET_DIR", "dist/target_wasm"),
-                        ("RUSTFLAGS", &wasm_release_rustflags),
-                    ]
-                } else {
-                    vec![("CARGO_TARGET_DIR", "dist/target_wasm")]
-                }
+                envs
             )?);
+            for warning in check_server_crates(&wasm_path, &crate_name) {
+                print_warning(&wb_spinner, &warning);
+            }
 
             Ok(0)
         },
@@ -149,6 +182,81 @@ pub fn build_internal(
     Ok((sg_thread, wb_thread))
 }
 
+/// Gets the environment variables the engine should be run with to generate the
+/// app's static artifacts.
+fn get_engine_envs(dir: &Path, global_opts: &Opts) -> Vec<(&'static str, String)> {
+    let dist = get_dist_dir(dir, global_opts);
+    let mut engine_envs = vec![
+        ("PERSEUS_ENGINE_OPERATION", "build".to_string()),
+        ("PERSEUS_DIST_DIR", dist.to_string_lossy().to_string()),
+        (
+            "CARGO_TARGET_DIR",
+            dist.join("target_engine").to_string_lossy().to_string(),
+        ),
+    ];
+    // Deterministic builds pin the time the engine thinks it is, and let the app
+    // know that it should seed any randomness
+    if global_opts.deterministic {
+        engine_envs.push(("PERSEUS_DETERMINISTIC", "1".to_string()));
+        engine_envs.push((
+            "SOURCE_DATE_EPOCH",
+            get_source_date_epoch(dir, &global_opts.git_path),
+        ));
+    }
+    engine_envs.extend(get_build_envs(global_opts));
+
+    engine_envs
+}
+
+/// Gets the environment variables that tell the engine which of the app's
+/// locales to build, and which profile to build it with, if the user has
+/// specified those.
+pub(crate) fn get_build_envs(global_opts: &Opts) -> Vec<(&'static str, String)> {
+    let mut envs = Vec::new();
+    if !global_opts.locales.is_empty() {
+        envs.push(("PERSEUS_LOCALES", global_opts.locales.join(",")));
+    }
+    if let Some(profile) = &global_opts.profile {
+        envs.push(("PERSEUS_PROFILE", profile.to_string()));
+    }
+
+    envs
+}
+
+/// Regenerates the app's static artifacts without building it to Wasm, which is
+/// all that's needed when only its translations have changed, since they're
+/// read at runtime. (Cargo will still recompile the engine, since the `t!`
+/// macro tracks the translations so that its checks are rerun.) Returns an
+/// exit code.
+pub fn regenerate_static(
+    dir: PathBuf,
+    is_release: bool,
+    tools: &Tools,
+    global_opts: &Opts,
+) -> Result<i32, ExecutionError> {
+    let msg = format!(
+        "{} {} Regenerating your app with new translations",
+        style("[1/1]").bold().dim(),
+        GENERATING
+    );
+    let spinner = cfg_spinner(ProgressBar::new_spinner(), &msg);
+    let engine_envs = get_engine_envs(&dir, global_opts);
+    handle_exit_code!(run_stage_with_progress(
+        &format!(
+            "{} run {} {}",
+            tools.cargo_engine,
+            if is_release { "--release" } else { "" },
+            global_opts.cargo_engine_args
+        ),
+        &dir,
+        &spinner,
+        &msg,
+        engine_envs.iter().map(|(k, v)| (*k, v.as_str())).collect()
+    )?);
+
+    Ok(0)
+}
+
 /// Builds the subcrates to get a directory that we can serve. Returns an exit
 /// code.
 pub fn build(
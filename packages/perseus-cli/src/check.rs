@@ -0,0 +1,35 @@
+use crate::cmd::run_cmd_directly;
+use crate::errors::ExecutionError;
+use crate::get_dist_dir;
+use crate::install::Tools;
+use crate::parse::{CheckOpts, Opts};
+use std::path::PathBuf;
+
+/// Checks the app's configuration for problems (like duplicate template paths,
+/// or i18n without any translations) without building it, reporting all of
+/// them at once.
+pub fn check(
+    dir: PathBuf,
+    opts: &CheckOpts,
+    tools: &Tools,
+    global_opts: &Opts,
+) -> Result<i32, ExecutionError> {
+    let dist = get_dist_dir(&dir, global_opts);
+    run_cmd_directly(
+        format!(
+            "{} run {} {}",
+            tools.cargo_engine,
+            if opts.release { "--release" } else { "" },
+            global_opts.cargo_engine_args,
+        ),
+        &dir,
+        vec![
+            ("PERSEUS_ENGINE_OPERATION", "validate"),
+            ("PERSEUS_DIST_DIR", &dist.to_string_lossy()),
+            (
+                "CARGO_TARGET_DIR",
+                &dist.join("target_engine").to_string_lossy(),
+            ),
+        ],
+    )
+}
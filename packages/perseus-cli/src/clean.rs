@@ -0,0 +1,98 @@
+use crate::errors::*;
+use crate::get_dist_dir;
+use crate::install::get_tools_dir;
+use crate::parse::{CleanOpts, Opts};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Removes the build artifacts specified by the given options, printing a
+/// summary of how much space was reclaimed. If no specific artifacts are
+/// given, this will remove the entire `dist/` directory (but not the CLI's
+/// installed tools, unless they're explicitly requested).
+pub fn clean(dir: PathBuf, clean_opts: &CleanOpts, global_opts: &Opts) -> Result<i32, Error> {
+    let dist = get_dist_dir(&dir, global_opts);
+    let clean_all =
+        !clean_opts.cache && !clean_opts.targets && !clean_opts.exported && !clean_opts.tools;
+
+    // Work out everything we'll delete before doing anything
+    let mut targets = Vec::new();
+    if clean_all {
+        targets.push(dist.clone());
+    } else {
+        if clean_opts.cache {
+            targets.push(dist.join("mutable"));
+        }
+        if clean_opts.targets && dist.exists() {
+            // There's one of these for each target and profile (e.g. `target_engine`)
+            let entries =
+                fs::read_dir(&dist).map_err(|err| ExecutionError::RemoveArtifactsFailed {
+                    target: dist.to_str().map(|s| s.to_string()),
+                    source: err,
+                })?;
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with("target_") {
+                    targets.push(entry.path());
+                }
+            }
+        }
+        if clean_opts.exported {
+            targets.push(dist.join("exported"));
+        }
+    }
+    if clean_opts.tools {
+        targets.push(get_tools_dir(&dist, global_opts.no_system_tools_cache)?);
+    }
+
+    let mut reclaimed = 0;
+    for target in targets {
+        if !target.exists() {
+            continue;
+        }
+        let size = fs_extra::dir::get_size(&target).unwrap_or(0);
+        fs::remove_dir_all(&target).map_err(|err| ExecutionError::RemoveArtifactsFailed {
+            target: target.to_str().map(|s| s.to_string()),
+            source: err,
+        })?;
+        reclaimed += size;
+        println!(
+            "Removed {} ({})",
+            display_path(&dir, &target),
+            fmt_size(size)
+        );
+    }
+    println!("Reclaimed {} in total.", fmt_size(reclaimed));
+
+    // Warn the user that the next run will be quite a bit slower if we deleted
+    // Cargo's artifacts
+    if clean_all || clean_opts.targets {
+        eprintln!("[NOTE]: Build artifacts have been deleted, the next run will take some time.");
+    }
+
+    Ok(0)
+}
+
+/// Gets a path to display to the user, relative to the project if possible.
+fn display_path(dir: &Path, target: &Path) -> String {
+    target
+        .strip_prefix(dir)
+        .unwrap_or(target)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Formats the given number of bytes for humans.
+fn fmt_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
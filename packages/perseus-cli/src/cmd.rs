@@ -1,14 +1,46 @@
 use crate::errors::*;
-use console::Emoji;
+use console::{style, Emoji};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io::Write;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
 use std::process::{Command, Stdio};
+use std::time::Instant;
 
 // Some useful emojis
 pub static SUCCESS: Emoji<'_, '_> = Emoji("✅", "success!");
 pub static FAILURE: Emoji<'_, '_> = Emoji("❌", "failed!");
 
+/// The prefix of the lines the engine prints to `stdout` to report the progress
+/// of a build (this must match `BUILD_PROGRESS_PREFIX` in Perseus).
+static BUILD_PROGRESS_PREFIX: &str = "__PERSEUS_BUILD_PROGRESS__ ";
+/// The prefix of the lines the engine prints to `stdout` to report warnings
+/// about a build (this must match `BUILD_WARNING_PREFIX` in Perseus).
+static BUILD_WARNING_PREFIX: &str = "__PERSEUS_BUILD_WARNING__ ";
+
+/// An update on the progress of a build from the engine. This only has the
+/// fields of Perseus' `BuildProgress` that we need.
+#[derive(Deserialize)]
+struct BuildProgress {
+    done: u64,
+    total: u64,
+}
+
+/// Quotes the given path so that it can be interpolated into a command run by
+/// [`run_cmd`] or [`run_cmd_directly`] without the shell splitting it on spaces
+/// or interpreting any other characters in it (the dist directory can be
+/// anywhere the user likes).
+pub fn quote_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    // Both `sh` and PowerShell take everything in single quotes literally, except
+    // for single quotes themselves, which have to be escaped differently
+    #[cfg(unix)]
+    let escaped = path.replace('\'', r"'\''");
+    #[cfg(windows)]
+    let escaped = path.replace('\'', "''");
+    format!("'{}'", escaped)
+}
+
 /// Runs the given command conveniently, returning the exit code. Notably, this
 /// parses the given command by separating it on spaces. Returns the command's
 /// output and the exit code.
@@ -61,9 +93,13 @@ pub fn run_cmd(
     ))
 }
 
+/// Gets the style for spinners.
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::default_spinner().tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+}
 /// Creates a new spinner.
 pub fn cfg_spinner(spinner: ProgressBar, message: &str) -> ProgressBar {
-    spinner.set_style(ProgressStyle::default_spinner().tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "));
+    spinner.set_style(spinner_style());
     spinner.set_message(format!("{}...", &message));
     // Tick the spinner every 50 milliseconds
     spinner.enable_steady_tick(50);
@@ -78,6 +114,17 @@ pub fn succeed_spinner(spinner: &ProgressBar, message: &str) {
 pub fn fail_spinner(spinner: &ProgressBar, message: &str) {
     spinner.finish_with_message(format!("{}...{}", message, FAILURE));
 }
+/// Prints the given warning above the given spinner (and any others it's
+/// being shown with).
+pub fn print_warning(spinner: &ProgressBar, warning: &str) {
+    let warning = format!("{} {}", style("warning:").yellow().bold(), warning);
+    // Hidden spinners won't print anything
+    if spinner.is_hidden() {
+        eprintln!("{}", warning);
+    } else {
+        spinner.println(warning);
+    }
+}
 
 /// Runs a series of commands. Returns the last command's output and an
 /// appropriate exit code (0 if everything worked, otherwise th exit code of the
@@ -111,6 +158,137 @@ pub fn run_stage(
     Ok((last_output.0, last_output.1, 0))
 }
 
+/// Runs the given engine command as a stage, like [`run_stage`], but asks the
+/// engine to report its progress in building the app, turning the given
+/// spinner into a progress bar as it does. Any other output will be handled
+/// as usual. Once the stage has finished, the number of pages generated, and
+/// how quickly they were generated, will be shown.
+pub fn run_stage_with_progress(
+    cmd: &str,
+    target: &Path,
+    spinner: &ProgressBar,
+    message: &str,
+    mut envs: Vec<(&str, &str)>,
+) -> Result<(String, String, i32), ExecutionError> {
+    #[cfg(unix)]
+    let shell_exec = "sh";
+    #[cfg(windows)]
+    let shell_exec = "powershell";
+    #[cfg(unix)]
+    let shell_param = "-c";
+    #[cfg(windows)]
+    let shell_param = "-command";
+
+    envs.push(("PERSEUS_BUILD_PROGRESS", "1"));
+    let mut child = Command::new(shell_exec)
+        .args([shell_param, cmd])
+        .envs(envs)
+        .current_dir(target)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| ExecutionError::CmdExecFailed {
+            cmd: cmd.to_string(),
+            source: err,
+        })?;
+    // We have to read `stderr` at the same time as `stdout`, otherwise the command
+    // could block on writing to it
+    let mut stderr_pipe = child.stderr.take().unwrap();
+    let stderr_thread = std::thread::spawn(move || {
+        let mut stderr = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut stderr);
+        stderr
+    });
+
+    let mut stdout = Vec::new();
+    let mut is_bar = false;
+    // When the first page was generated, and how many have been generated since
+    let mut started = None;
+    let mut done = 0;
+    for line in BufReader::new(child.stdout.take().unwrap()).split(b'\n') {
+        let line = line.map_err(|err| ExecutionError::CmdExecFailed {
+            cmd: cmd.to_string(),
+            source: err,
+        })?;
+        let line_str = std::str::from_utf8(&line).ok();
+        let warning = line_str
+            .and_then(|line| line.strip_prefix(BUILD_WARNING_PREFIX))
+            .and_then(|warning| serde_json::from_str::<String>(warning).ok());
+        if let Some(warning) = warning {
+            print_warning(spinner, &warning);
+            continue;
+        }
+        let progress = line_str
+            .and_then(|line| line.strip_prefix(BUILD_PROGRESS_PREFIX))
+            .and_then(|progress| serde_json::from_str::<BuildProgress>(progress).ok());
+        match progress {
+            Some(progress) => {
+                if !is_bar {
+                    spinner.set_style(
+                        ProgressStyle::default_bar()
+                            .template("{msg} [{bar:30}] {pos}/{len} ({eta})")
+                            .progress_chars("=> "),
+                    );
+                    is_bar = true;
+                    started = Some(Instant::now());
+                }
+                spinner.set_length(progress.total);
+                spinner.set_position(progress.done);
+                done = progress.done;
+            }
+            None => {
+                stdout.extend(line);
+                stdout.push(b'\n');
+            }
+        }
+    }
+    let status = child.wait().map_err(|err| ExecutionError::CmdExecFailed {
+        cmd: cmd.to_string(),
+        source: err,
+    })?;
+    let stderr = stderr_thread.join().unwrap_or_default();
+    // The finished spinner should look like all the others
+    if is_bar {
+        spinner.set_style(spinner_style());
+    }
+
+    let exit_code = match status.code() {
+        Some(exit_code) => exit_code,
+        None if status.success() => 0,
+        None => 1,
+    };
+    // This mirrors `run_cmd`
+    if exit_code != 0 {
+        if !stderr.is_empty() {
+            fail_spinner(spinner, message);
+            std::io::stderr().write_all(&stdout).unwrap();
+            std::io::stderr().write_all(&stderr).unwrap();
+        }
+        return Ok((
+            String::from_utf8_lossy(&stdout).to_string(),
+            String::from_utf8_lossy(&stderr).to_string(),
+            1,
+        ));
+    }
+    match started {
+        Some(started) => {
+            let secs = started.elapsed().as_secs_f64();
+            let per_sec = if secs > 0.0 { done as f64 / secs } else { 0.0 };
+            spinner.finish_with_message(format!(
+                "{}...{} ({} pages, {:.0}/s)",
+                message, SUCCESS, done, per_sec
+            ));
+        }
+        None => succeed_spinner(spinner, message),
+    }
+
+    Ok((
+        String::from_utf8_lossy(&stdout).to_string(),
+        String::from_utf8_lossy(&stderr).to_string(),
+        0,
+    ))
+}
+
 /// Runs a command directly, piping its output and errors to the streams of this
 /// program. This allows the user to investigate the innards of Perseus, or just
 /// see their own `dbg!` calls. This will return the exit code of the command,
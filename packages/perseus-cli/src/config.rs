@@ -0,0 +1,94 @@
+use crate::errors::ConfigError;
+use crate::install::ToolsConfig;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// The last `Perseus.toml` that was read, along with where it was and when it
+/// was last modified, so that the many parts of the CLI that need it don't
+/// each have to read and parse it again (unless it changes).
+static LAST_READ: Mutex<Option<(PathBuf, Option<SystemTime>, PerseusToml)>> = Mutex::new(None);
+
+/// The contents of a project's `Perseus.toml`, which configures the CLI. This
+/// file is optional, and so is every section in it.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct PerseusToml {
+    /// The path prefixes that the development server should proxy to backends,
+    /// from the `[proxy]` section (e.g. `"/api" = "http://localhost:4000"`).
+    #[serde(default)]
+    pub proxy: HashMap<String, String>,
+    /// The tool versions pinned in the `[tools]` section.
+    #[serde(default)]
+    pub tools: ToolsConfig,
+}
+impl PerseusToml {
+    /// Reads the `Perseus.toml` file in the given project directory. If there
+    /// isn't one, the default configuration will be returned.
+    pub fn read(dir: &Path) -> Result<Self, ConfigError> {
+        let path = dir.join("Perseus.toml");
+        let modified = match fs::metadata(&path) {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(ConfigError::ReadFailed { source: err }),
+        };
+
+        let mut last_read = LAST_READ.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((last_path, last_modified, config)) = &*last_read {
+            if *last_path == path && modified.is_some() && *last_modified == modified {
+                return Ok(config.clone());
+            }
+        }
+        let contents =
+            fs::read_to_string(&path).map_err(|err| ConfigError::ReadFailed { source: err })?;
+        let config = Self::parse(&contents)?;
+        *last_read = Some((path, modified, config.clone()));
+
+        Ok(config)
+    }
+    /// Parses the given contents of a `Perseus.toml` file.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|err| ConfigError::ParseFailed { source: err })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::ToolPin;
+
+    #[test]
+    fn parses_every_section() {
+        let config = PerseusToml::parse(
+            r#"
+[proxy]
+"/api" = "http://localhost:4000"
+
+[tools]
+wasm-bindgen = "0.2.83"
+binaryen = { version = "110", path = "vendor/binaryen.tar.gz" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.proxy["/api"], "http://localhost:4000");
+        assert!(matches!(config.tools.wasm_bindgen, Some(ToolPin::Version(v)) if v == "0.2.83"));
+        assert!(
+            matches!(config.tools.wasm_opt, Some(ToolPin::Vendored { version, .. }) if version == "110")
+        );
+    }
+    #[test]
+    fn sections_are_optional() {
+        let config = PerseusToml::parse("").unwrap();
+        assert!(config.proxy.is_empty());
+        assert!(config.tools.wasm_bindgen.is_none());
+    }
+    #[test]
+    fn reports_invalid_config() {
+        assert!(matches!(
+            PerseusToml::parse("[proxy]\n\"/api\" = 4000"),
+            Err(ConfigError::ParseFailed { .. })
+        ));
+    }
+}
@@ -1,13 +1,25 @@
 use crate::errors::*;
 use crate::export;
+use crate::get_dist_dir;
 use crate::install::Tools;
+use crate::manifest::{write_manifest, MANIFEST_FILE, SIGNATURE_FILE};
 use crate::parse::Opts;
 use crate::parse::{DeployOpts, ExportOpts, ServeOpts};
 use crate::serve;
 use fs_extra::copy_items;
 use fs_extra::dir::{copy as copy_dir, CopyOptions};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How many times the server will be polled when checking a deployment before
+/// we give up on it starting.
+const CHECK_SERVER_MAX_ATTEMPTS: usize = 120;
+/// How long to wait between polls of the server when checking a deployment.
+const CHECK_SERVER_RETRY_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Deploys the user's app to the `pkg/` directory (can be changed with
 /// `-o/--output`). This will build everything for release and then put it all
@@ -24,7 +36,7 @@ pub fn deploy(
     let exit_code = if opts.export_static {
         deploy_export(dir, opts.output.to_string(), tools, global_opts)?
     } else {
-        deploy_full(dir, opts.output.to_string(), tools, global_opts)?
+        deploy_full(dir, opts, tools, global_opts)?
     };
 
     Ok(exit_code)
@@ -35,10 +47,11 @@ pub fn deploy(
 /// subcommands.
 fn deploy_full(
     dir: PathBuf,
-    output: String,
+    opts: &DeployOpts,
     tools: &Tools,
     global_opts: &Opts,
 ) -> Result<i32, Error> {
+    let output = opts.output.to_string();
     // Build everything for production, not running the server
     let (serve_exit_code, server_path) = serve(
         dir.clone(),
@@ -53,6 +66,12 @@ fn deploy_full(
             // here
             host: "127.0.0.1".to_string(),
             port: 8080,
+            open: false,
+            qr: false,
+            public_host: None,
+            proxy: Vec::new(),
+            target: opts.target.clone(),
+            linker: opts.linker.clone(),
         },
         tools,
         global_opts,
@@ -79,11 +98,9 @@ fn deploy_full(
             }
             .into());
         }
-        // Copy in the server executable
-        #[cfg(target_os = "windows")]
-        let to = output_path.join("server.exe");
-        #[cfg(not(target_os = "windows"))]
-        let to = output_path.join("server");
+        // Copy in the server executable (which might have been cross-compiled for a
+        // different platform)
+        let to = output_path.join(server_name(opts));
 
         if let Err(err) = fs::copy(&server_path, &to) {
             return Err(DeployError::MoveAssetFailed {
@@ -117,13 +134,15 @@ fn deploy_full(
                 .into());
             }
         }
-        // Create the `dist/` directory in the output directory
+        // Create the `dist/` directory in the output directory (the packaged server
+        // always uses `dist/`, wherever the artifacts were built)
         if let Err(err) = fs::create_dir(&output_path.join("dist")) {
             return Err(DeployError::CreateDistDirFailed { source: err }.into());
         }
         // Copy in the different parts of the `dist/` directory that we need (they all
         // have to exist)
-        let from = dir.join("dist/static");
+        let dist = get_dist_dir(&dir, global_opts);
+        let from = dist.join("static");
         if let Err(err) = copy_dir(&from, &output_path.join("dist"), &CopyOptions::new()) {
             return Err(DeployError::MoveDirFailed {
                 to: output,
@@ -132,7 +151,7 @@ fn deploy_full(
             }
             .into());
         }
-        let from = dir.join("dist/pkg");
+        let from = dist.join("pkg");
         if let Err(err) = copy_dir(&from, &output_path.join("dist"), &CopyOptions::new()) {
             return Err(DeployError::MoveDirFailed {
                 to: output,
@@ -141,7 +160,7 @@ fn deploy_full(
             }
             .into());
         }
-        let from = dir.join("dist/render_conf.json");
+        let from = dist.join("render_conf.json");
         if let Err(err) = fs::copy(&from, &output_path.join("dist/render_conf.json")) {
             return Err(DeployError::MoveAssetFailed {
                 to: output,
@@ -161,6 +180,20 @@ fn deploy_full(
     }
 }
 
+/// Gets the name the server executable should have in the output directory,
+/// which depends on the platform it was built for.
+fn server_name(opts: &DeployOpts) -> &'static str {
+    let for_windows = match &opts.target {
+        Some(target) => target.contains("windows"),
+        None => cfg!(target_os = "windows"),
+    };
+    if for_windows {
+        "server.exe"
+    } else {
+        "server"
+    }
+}
+
 /// Uses static exporting to deploy the user's app. This can return any kind of
 /// error because deploying involves working with other subcommands.
 fn deploy_export(
@@ -188,7 +221,7 @@ fn deploy_export(
     }
     // That subcommand produces a self-contained static site at `dist/exported/`
     // Just copy that out to the output directory
-    let from = dir.join("dist/exported");
+    let from = get_dist_dir(&dir, global_opts).join("exported");
     let output_path = PathBuf::from(&output);
     // Delete the output directory if it exists and recreate it
     if output_path.exists() {
@@ -251,3 +284,160 @@ fn deploy_export(
 
     Ok(0)
 }
+
+/// Checks the output of a deployment, making sure everything the server needs
+/// has been packaged, and then making sure it actually starts up and serves the
+/// app's index page. For exported apps, this just checks that there's an index
+/// page. This should be run after `deploy()` has succeeded.
+pub async fn check_deployment(dir: PathBuf, opts: &DeployOpts) -> Result<i32, Error> {
+    let output_path = PathBuf::from(&opts.output);
+    let mut problems = Vec::new();
+    if opts.export_static {
+        if !output_path.join("index.html").is_file() {
+            problems.push(
+                "there's no `index.html` (does your app have a template for the `/` route?)"
+                    .to_string(),
+            );
+        }
+        return finish_check(&opts.output, problems);
+    }
+
+    let server_path = output_path.join(server_name(opts));
+    if !server_path.is_file() {
+        problems.push(format!(
+            "the server executable `{}` is missing",
+            server_name(opts)
+        ));
+    }
+    for required in ["dist/render_conf.json", "dist/pkg", "dist/static"] {
+        if !output_path.join(required).exists() {
+            problems.push(format!("`{}` is missing", required));
+        }
+    }
+    // Anything the project has needs to have been copied in
+    for optional in ["static", "translations"] {
+        if dir.join(optional).exists() && !output_path.join(optional).exists() {
+            problems.push(format!(
+                "your project has a `{}/` directory, but it wasn't copied in",
+                optional
+            ));
+        }
+    }
+    if !problems.is_empty() {
+        return finish_check(&opts.output, problems);
+    }
+    // We can't run a server built for another platform
+    if let Some(target) = &opts.target {
+        println!("The server was cross-compiled for `{}`, so it can't be started here. You can check it on the platform you're deploying to by running it with the `PERSEUS_ENGINE_OPERATION` environment variable set to `check`.", target);
+        return finish_check(&opts.output, problems);
+    }
+
+    // Have the engine check everything from the perspective of the server itself
+    // (we need an absolute path for the executable, since we're changing directory)
+    let server_path = fs::canonicalize(&server_path).unwrap_or(server_path);
+    let server_path_str = server_path.to_string_lossy().to_string();
+    let check_output = Command::new(&server_path)
+        .current_dir(&output_path)
+        .env("PERSEUS_ENGINE_OPERATION", "check")
+        // The packaged server always uses its own `dist/`
+        .env_remove("PERSEUS_DIST_DIR")
+        .output()
+        .map_err(|err| ExecutionError::CmdExecFailed {
+            cmd: server_path_str.clone(),
+            source: err,
+        })?;
+    if !check_output.status.success() {
+        std::io::stderr().write_all(&check_output.stderr).unwrap();
+        return Ok(check_output.status.code().unwrap_or(1));
+    }
+
+    // Now actually start the server on a free port and make sure it can serve the
+    // index page
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|err| DeployError::FindFreePortFailed { source: err })?;
+    let mut child = Command::new(&server_path)
+        .current_dir(&output_path)
+        .env("PERSEUS_ENGINE_OPERATION", "serve")
+        .env_remove("PERSEUS_DIST_DIR")
+        .env("PERSEUS_HOST", "127.0.0.1")
+        .env("PERSEUS_PORT", port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| ExecutionError::CmdExecFailed {
+            cmd: server_path_str,
+            source: err,
+        })?;
+    let url = format!("http://127.0.0.1:{}/", port);
+    let mut attempts = 0;
+    let problem = loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            let mut stderr = String::new();
+            if let Some(mut child_stderr) = child.stderr.take() {
+                let _ = child_stderr.read_to_string(&mut stderr);
+            }
+            break Some(format!(
+                "the server exited with {} before it could serve anything: {}",
+                status,
+                stderr.trim()
+            ));
+        }
+        match reqwest::get(&url).await {
+            Ok(res) if res.status().is_success() => break None,
+            Ok(res) => {
+                break Some(format!(
+                    "the server responded to a request for `/` with status {}",
+                    res.status()
+                ))
+            }
+            // The server probably hasn't started listening yet
+            Err(_) if attempts < CHECK_SERVER_MAX_ATTEMPTS => {
+                attempts += 1;
+                tokio::time::sleep(CHECK_SERVER_RETRY_INTERVAL).await;
+            }
+            Err(err) => break Some(format!("couldn't request `/` from the server: {}", err)),
+        }
+    };
+    let _ = child.kill();
+    let _ = child.wait();
+    problems.extend(problem);
+
+    finish_check(&opts.output, problems)
+}
+
+/// Writes a manifest of the deployment's output (and signs it), if that was
+/// requested. This should be run after the deployment has been checked, since
+/// checking starts the server, which can add files to the output.
+pub fn write_deployment_manifest(opts: &DeployOpts) -> Result<(), Error> {
+    if !opts.manifest && opts.sign_key.is_none() {
+        return Ok(());
+    }
+    write_manifest(Path::new(&opts.output), opts.sign_key.as_deref())?;
+    if opts.sign_key.is_some() {
+        println!("Wrote a manifest of your deployment to '{}', and signed it in '{}'. You can check the uploaded files with `perseus verify <dir> --public-key <key>`.", MANIFEST_FILE, SIGNATURE_FILE);
+    } else {
+        println!("Wrote a manifest of your deployment to '{}'. You can check the uploaded files with `perseus verify <dir>`.", MANIFEST_FILE);
+    }
+
+    Ok(())
+}
+
+/// Reports the result of checking a deployment.
+fn finish_check(output: &str, problems: Vec<String>) -> Result<i32, Error> {
+    if problems.is_empty() {
+        println!("Your deployment in '{}' looks good!", output);
+        Ok(0)
+    } else {
+        Err(DeployError::CheckFailed {
+            output: output.to_string(),
+            problems: problems
+                .iter()
+                .map(|problem| format!("- {}", problem))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+        .into())
+    }
+}
@@ -0,0 +1,55 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Gets the timestamp (in seconds since the Unix epoch) that a deterministic
+/// build should treat as the current time. This follows the
+/// [`SOURCE_DATE_EPOCH`](https://reproducible-builds.org/specs/source-date-epoch/)
+/// convention, and, if that isn't set, the time of the last Git commit will be
+/// used. If that can't be worked out either, the Unix epoch itself will be
+/// used.
+pub fn get_source_date_epoch(dir: &Path, git_path: &str) -> String {
+    if let Ok(epoch) = env::var("SOURCE_DATE_EPOCH") {
+        if epoch.trim().parse::<i64>().is_ok() {
+            return epoch.trim().to_string();
+        }
+    }
+
+    let last_commit = Command::new(git_path)
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|stdout| stdout.trim().to_string())
+        .filter(|stdout| stdout.parse::<i64>().is_ok());
+    last_commit.unwrap_or_else(|| "0".to_string())
+}
+
+/// Gets the `RUSTFLAGS` that should be used for a Wasm build, if any. In
+/// deterministic builds, this will remap the absolute path of the project so
+/// that it doesn't end up embedded in the bundle (e.g. in panic messages).
+pub fn get_wasm_rustflags(
+    dir: &Path,
+    is_release: bool,
+    deterministic: bool,
+    release_rustflags: &str,
+    dev_rustflags: Option<&str>,
+) -> Option<String> {
+    let mut rustflags = Vec::new();
+    if is_release {
+        rustflags.push(release_rustflags.to_string());
+    } else if let Some(dev_rustflags) = dev_rustflags {
+        rustflags.push(dev_rustflags.to_string());
+    }
+    if deterministic {
+        rustflags.push(format!("--remap-path-prefix={}=.", dir.to_string_lossy()));
+    }
+
+    if rustflags.is_empty() {
+        None
+    } else {
+        Some(rustflags.join(" "))
+    }
+}
@@ -0,0 +1,116 @@
+use qrcode::render::unicode;
+use qrcode::QrCode;
+use std::env;
+use std::fs;
+use std::net::{IpAddr, TcpStream, UdpSocket};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// The file in `dist/` that records that we've already opened the browser,
+/// so that we don't open it again every time the app is rebuilt while
+/// watching. The watching process removes this when it starts up.
+pub const BROWSER_OPENED_MARKER: &str = "browser_opened";
+/// How many times we'll check if the server is listening before giving up on
+/// opening the browser.
+const OPEN_MAX_ATTEMPTS: usize = 100;
+/// How long to wait between checks of whether or not the server is listening.
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Checks if the given host is a loopback address, which devices on the local
+/// network won't be able to reach.
+pub fn is_loopback(host: &str) -> bool {
+    host == "localhost"
+        || host
+            .parse::<IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false)
+}
+
+/// Gets the address of this machine on the local network, if it has one. This
+/// doesn't actually send anything, connecting a UDP socket just makes the OS
+/// pick the interface it would route through.
+fn get_lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_unspecified() || ip.is_loopback() {
+        None
+    } else {
+        Some(ip)
+    }
+}
+
+/// Prints a QR code of the URL the app can be reached at from other devices on
+/// the local network, or a warning if there isn't one. If the user has told us
+/// what host other devices should use, that will be used instead of trying to
+/// work it out.
+pub fn print_lan_qr(public_host: Option<&str>, port: u16) {
+    let host = match public_host {
+        Some(host) => host.to_string(),
+        None => match get_lan_ip() {
+            Some(ip) => ip.to_string(),
+            None => {
+                println!("Couldn't work out this machine's address on your local network, so no QR code can be generated (are you connected to one? you can also provide it with `--public-host`).");
+                return;
+            }
+        },
+    };
+    let url = format!("http://{}:{}", host, port);
+    // This can only fail if the data is too long, which a URL like this won't be
+    let code = match QrCode::new(url.as_bytes()) {
+        Ok(code) => code,
+        Err(_) => return,
+    };
+    // Terminals are usually light-on-dark, so we invert the colors to keep the code
+    // scannable
+    let rendered = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    println!(
+        "\nScan this to open your app on another device on your network (<{}>):\n\n{}\n",
+        url, rendered
+    );
+}
+
+/// Opens the given address in the user's default browser once the server
+/// starts listening there. This happens in a separate thread, so it won't block
+/// the server. This will only open the browser once per watching session, so
+/// it's safe to call on every rebuild. This needs the `dist/` directory to keep
+/// track of that.
+pub fn open_when_ready(dist: &Path, host: &str, port: u16) {
+    // We'll be run as a child of the watching process if we're watching, and that
+    // will restart us on every change
+    if env::var("PERSEUS_WATCHING_PROHIBITED").is_ok() {
+        let marker = dist.join(BROWSER_OPENED_MARKER);
+        if marker.exists() {
+            return;
+        }
+        // There's no harm in opening the browser again if we can't write this
+        let _ = fs::write(&marker, "");
+    }
+
+    // Browsers can't open an unspecified address
+    let host = if host == "0.0.0.0" || host == "::" {
+        "localhost".to_string()
+    } else {
+        host.to_string()
+    };
+    thread::spawn(move || {
+        for _ in 0..OPEN_MAX_ATTEMPTS {
+            if TcpStream::connect((host.as_str(), port)).is_ok() {
+                let url = format!("http://{}:{}", host, port);
+                if open::that(&url).is_err() {
+                    println!(
+                        "Couldn't open your browser, you can see your app at <{}>.",
+                        url
+                    );
+                }
+                return;
+            }
+            thread::sleep(OPEN_RETRY_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,93 @@
+use crate::errors::ExecutionError;
+use crate::parse::Opts;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Controls for how the app is built to Wasm in development (these have no
+/// effect on release builds). These can be set in the `[dev]` section of
+/// `Perseus.toml`, or with the corresponding `--wasm-dev-*` flags, which will
+/// turn them on if the configuration file doesn't.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct DevWasmOpts {
+    /// Whether or not to build with `opt-level=1`, which makes the bundle much
+    /// faster at runtime, at the cost of slightly slower builds.
+    #[serde(default)]
+    pub optimize: bool,
+    /// Whether or not to enable Perseus' `hydrate` feature, even if the app
+    /// doesn't normally.
+    #[serde(default)]
+    pub hydrate: bool,
+    /// Whether or not to keep debug symbols in the bundle through
+    /// `wasm-bindgen`, for better stack traces in the browser.
+    #[serde(default)]
+    pub keep_debug: bool,
+    /// Whether or not to emit DWARF debugging information in the bundle (which
+    /// browser tools can use to map it back to your code), and to report
+    /// panics with symbolicated stack traces. This implies `keep_debug`.
+    #[serde(default)]
+    pub source_maps: bool,
+}
+impl DevWasmOpts {
+    /// Gets the development Wasm settings from `Perseus.toml` in the given
+    /// directory (if it exists) and the given arguments.
+    pub fn get(dir: &Path, global_opts: &Opts) -> Result<Self, ExecutionError> {
+        #[derive(Deserialize)]
+        struct PerseusToml {
+            #[serde(default)]
+            dev: DevWasmOpts,
+        }
+
+        let path = dir.join("Perseus.toml");
+        let mut opts = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|err| ExecutionError::ReadDevConfigFailed { source: err })?;
+            let config: PerseusToml = toml::from_str(&contents)
+                .map_err(|err| ExecutionError::ParseDevConfigFailed { source: err })?;
+            config.dev
+        } else {
+            Self::default()
+        };
+        opts.optimize |= global_opts.wasm_dev_optimize;
+        opts.hydrate |= global_opts.wasm_dev_hydrate;
+        opts.keep_debug |= global_opts.wasm_dev_keep_debug;
+        opts.source_maps |= global_opts.wasm_dev_source_maps;
+
+        Ok(opts)
+    }
+    /// Gets any extra `RUSTFLAGS` for a development Wasm build.
+    pub fn rustflags(&self) -> Option<String> {
+        let mut rustflags = Vec::new();
+        if self.optimize {
+            rustflags.push("-C opt-level=1");
+        }
+        if self.source_maps {
+            rustflags.push("-C debuginfo=2");
+        }
+
+        if rustflags.is_empty() {
+            None
+        } else {
+            Some(rustflags.join(" "))
+        }
+    }
+    /// Gets any extra environment variables for a development Wasm build.
+    /// Perseus reads these at compile-time.
+    pub fn envs(&self) -> Vec<(&'static str, &'static str)> {
+        if self.source_maps {
+            vec![("PERSEUS_WASM_SOURCE_MAPS", "1")]
+        } else {
+            Vec::new()
+        }
+    }
+    /// Adds any extra arguments for `cargo` and `wasm-bindgen` in a development
+    /// Wasm build to the given ones.
+    pub fn apply_args(&self, cargo_browser_args: &mut String, wasm_bindgen_args: &mut String) {
+        if self.hydrate {
+            cargo_browser_args.push_str(" --features perseus/hydrate");
+        }
+        if self.keep_debug || self.source_maps {
+            wasm_bindgen_args.push_str(" --keep-debug");
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::cmd::run_cmd_directly;
+use crate::errors::*;
+use crate::parse::{DeployOpts, Opts};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The image the app is built in. This should use the same Debian release as
+/// `RUNTIME_IMAGE`, otherwise the server might be linked against a newer
+/// version of glibc than the runtime image has.
+const BUILDER_IMAGE: &str = "rust:1-bookworm";
+/// The image the packaged app is run in.
+const RUNTIME_IMAGE: &str = "debian:bookworm-slim";
+/// The port the server listens on inside the container.
+const CONTAINER_PORT: u16 = 8080;
+
+/// Creates a multi-stage `Dockerfile` that builds the user's app with this
+/// version of the CLI, and then copies the packaged app (the server binary,
+/// `dist/`, `static/`, and `translations/`) into a minimal runtime image.
+fn make_dockerfile() -> String {
+    format!(
+        r#"# This file was generated by `perseus deploy --docker`, build it from the root of your project with
+# `docker build -f pkg/Dockerfile .` (or wherever you put your output directory)
+
+FROM {builder} AS build
+RUN rustup target add wasm32-unknown-unknown
+RUN cargo install perseus-cli --version {cli_version} --locked
+WORKDIR /app
+COPY . .
+RUN perseus deploy --output /pkg
+
+FROM {runtime}
+WORKDIR /app
+COPY --from=build /pkg/ .
+# The server finds its assets relative to its own location, so it only needs to be told where to listen
+ENV PERSEUS_HOST=0.0.0.0
+ENV PERSEUS_PORT={port}
+EXPOSE {port}
+CMD ["/app/server"]
+"#,
+        builder = BUILDER_IMAGE,
+        runtime = RUNTIME_IMAGE,
+        cli_version = env!("CARGO_PKG_VERSION"),
+        port = CONTAINER_PORT,
+    )
+}
+
+/// Creates an ignore file for the `Dockerfile`, so that local build artifacts
+/// aren't sent to the builder. This is picked up by BuildKit because it sits
+/// next to the `Dockerfile`, so we don't have to touch the user's own
+/// `.dockerignore`. The output directory and the directory for build
+/// artifacts are only ignored if they're inside the project.
+fn make_dockerignore(output: &str, out_dir: &str) -> String {
+    let mut ignore = "target/\n".to_string();
+    for dir in [out_dir, output] {
+        if !Path::new(dir).is_absolute() {
+            ignore.push_str(dir.trim_start_matches("./").trim_end_matches('/'));
+            ignore.push_str("/\n");
+        }
+    }
+
+    ignore
+}
+
+/// Deploys the user's app as a container image, by emitting a `Dockerfile`
+/// into the output directory that builds and packages everything inside the
+/// container. Nothing is built locally unless the user asked us to build the
+/// image with a particular builder, in which case we'll run that from the root
+/// of their project.
+pub fn deploy_docker(dir: PathBuf, opts: &DeployOpts, global_opts: &Opts) -> Result<i32, Error> {
+    let output = opts.output.to_string();
+    let output_path = dir.join(&output);
+    // Delete the output directory if it exists and recreate it
+    if output_path.exists() {
+        if let Err(err) = fs::remove_dir_all(&output_path) {
+            return Err(DeployError::ReplaceOutputDirFailed {
+                path: output,
+                source: err,
+            }
+            .into());
+        }
+    }
+    if let Err(err) = fs::create_dir_all(&output_path) {
+        return Err(DeployError::ReplaceOutputDirFailed {
+            path: output,
+            source: err,
+        }
+        .into());
+    }
+
+    let dockerfile_path = output_path.join("Dockerfile");
+    let ignore_path = output_path.join("Dockerfile.dockerignore");
+    for (path, contents) in [
+        (&dockerfile_path, make_dockerfile()),
+        (
+            &ignore_path,
+            make_dockerignore(&output, &global_opts.out_dir),
+        ),
+    ] {
+        if let Err(err) = fs::write(path, contents) {
+            return Err(DeployError::WriteDockerfileFailed {
+                path: path.to_string_lossy().to_string(),
+                source: err,
+            }
+            .into());
+        }
+    }
+
+    if let Some(builder) = &opts.docker_builder {
+        let exit_code = run_cmd_directly(
+            format!(
+                "{} build -f {} -t {} .",
+                builder,
+                dockerfile_path.to_string_lossy(),
+                opts.docker_tag
+            ),
+            &dir,
+            vec![("DOCKER_BUILDKIT", "1")],
+        )?;
+        if exit_code != 0 {
+            return Ok(exit_code);
+        }
+
+        println!();
+        println!("Deployment complete 🚀! Your app has been built into the image '{}', which will serve it on port {}.", &opts.docker_tag, CONTAINER_PORT);
+    } else {
+        println!();
+        println!("Deployment complete 🚀! A `Dockerfile` for your app is now available in '{}'. You can build it by running `docker build -f {} .` from the root of your project.", &output, dockerfile_path.strip_prefix(&dir).unwrap_or(&dockerfile_path).to_string_lossy());
+    }
+
+    Ok(0)
+}
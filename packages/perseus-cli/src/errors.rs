@@ -26,6 +26,8 @@ pub enum Error {
     #[error(transparent)]
     DeployError(#[from] DeployError),
     #[error(transparent)]
+    ManifestError(#[from] ManifestError),
+    #[error(transparent)]
     WatchError(#[from] WatchError),
     #[error(transparent)]
     InitError(#[from] InitError),
@@ -33,6 +35,23 @@ pub enum Error {
     NewError(#[from] NewError),
     #[error(transparent)]
     InstallError(#[from] InstallError),
+    #[error(transparent)]
+    TinkerError(#[from] TinkerError),
+}
+
+/// Errors that can occur while reading `Perseus.toml`.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("couldn't read `Perseus.toml`")]
+    ReadFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse `Perseus.toml`")]
+    ParseFailed {
+        #[source]
+        source: toml::de::Error,
+    },
 }
 
 /// Errors that can occur while attempting to execute a Perseus app with
@@ -45,6 +64,10 @@ pub enum ExecutionError {
         #[source]
         source: std::io::Error,
     },
+    #[error("couldn't install the `{target}` target for cross-compiling the server (is it a valid target triple?)")]
+    CrossTargetAddFailed { target: String, code: i32 },
+    #[error("the server was cross-compiled for `{target}`, so it can't be run here (try adding `--no-run`)")]
+    CrossCompiledServerNotRunnable { target: String },
     #[error("couldn't execute command because it prematurely stopped reporting (if this persists, please report it as a bug)")]
     NextStdoutLineNone,
     #[error("couldn't get path to server executable (if this persists, please report it as a bug, especially if you've just updated `cargo`)")]
@@ -62,6 +85,8 @@ pub enum ExecutionError {
         #[source]
         source: std::io::Error,
     },
+    #[error("couldn't find a free port to listen on at '{host}', starting from port {port} (try setting a different port)")]
+    NoFreePort { host: String, port: u16 },
     #[error("failed to wait on thread (please report this as a bug if it persists)")]
     ThreadWaitFailed,
     #[error("value in `PORT` environment variable couldn't be parsed as a number")]
@@ -69,6 +94,41 @@ pub enum ExecutionError {
         #[source]
         source: std::num::ParseIntError,
     },
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+    #[error(
+        "invalid proxy route '{route}' (expected something like `/api=http://localhost:4000`)"
+    )]
+    InvalidProxyRoute { route: String },
+    #[error("couldn't resolve '{host}:{port}' to start the development proxy on")]
+    ResolveProxyAddrFailed {
+        host: String,
+        port: u16,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't read Wasm build at '{path}' to analyze it (have you built your app yet?)")]
+    ReadWasmForAnalysisFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse Wasm build at '{path}' to analyze it (try rebuilding your app)")]
+    ParseWasmForAnalysisFailed {
+        path: String,
+        #[source]
+        source: wasmparser::BinaryReaderError,
+    },
+    #[error("couldn't read `Perseus.toml` to determine development Wasm settings")]
+    ReadDevConfigFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse `[dev]` section of `Perseus.toml`")]
+    ParseDevConfigFailed {
+        #[source]
+        source: toml::de::Error,
+    },
     #[error("couldn't parse `Cargo.toml` (are you running in the right directory?)")]
     GetManifestFailed {
         #[source]
@@ -146,6 +206,133 @@ pub enum DeployError {
         #[source]
         source: std::io::Error,
     },
+    #[error("the deployment in '{output}' isn't ready to be served:\n{problems}")]
+    CheckFailed { output: String, problems: String },
+    #[error("couldn't find a free port to check the deployed server on")]
+    FindFreePortFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't write '{path}' for container deployment")]
+    WriteDockerfileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Errors that can occur while creating or verifying the manifest of a
+/// deployment.
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    #[error("couldn't read directory '{path}' while hashing deployment files")]
+    ReadDirFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't hash file '{path}' for the deployment manifest")]
+    HashFileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't serialize deployment manifest")]
+    SerializeManifestFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("couldn't write '{path}' for the deployment manifest")]
+    WriteFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't read key '{path}'")]
+    ReadKeyFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse key '{path}' (it should be in PEM format)")]
+    InvalidKey { path: String },
+    #[error("key '{path}' isn't an Ed25519 key, which is the only kind supported for signing manifests (you can generate one with `openssl genpkey -algorithm ed25519 -out key.pem`)")]
+    UnsupportedKey { path: String },
+    #[error("'{path}' isn't valid unicode, so it can't be put in the deployment manifest")]
+    NonUtf8Path { path: String },
+    #[error("'{path}' is a symlink, which can't be put in the deployment manifest (copy the file it points to instead)")]
+    SymlinkFound { path: String },
+    #[error("couldn't read manifest '{path}' (did you deploy with `--manifest`?)")]
+    ReadManifestFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't read manifest signature '{path}' (did you deploy with `--sign-key`?)")]
+    ReadSignatureFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse deployment manifest (has it been modified?)")]
+    ParseManifestFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("the manifest uses the hashing algorithm '{algorithm}', which isn't supported by this version of the cli")]
+    UnsupportedAlgorithm { algorithm: String },
+    #[error("the signature of the manifest in '{dir}' isn't valid for the given public key (the manifest may have been tampered with)")]
+    BadSignature { dir: String },
+    #[error("the deployment in '{dir}' doesn't match its manifest:\n{problems}")]
+    VerifyFailed { dir: String, problems: String },
+}
+
+/// Errors that can occur while applying the structured edits declared by
+/// plugins in `perseus tinker`.
+#[derive(Error, Debug)]
+pub enum TinkerError {
+    #[error("couldn't read file '{path}' while applying tinker edits")]
+    ReadFileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't write file '{path}' while applying tinker edits")]
+    WriteFileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse tinker edits from the engine (try running `perseus tinker` again)")]
+    ParseEditsFailed {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("plugin '{first_plugin}' requires version '{first_version}' of dependency '{name}', but plugin '{second_plugin}' requires version '{second_version}'")]
+    ConflictingDependency {
+        name: String,
+        first_plugin: String,
+        first_version: String,
+        second_plugin: String,
+        second_version: String,
+    },
+    #[error("couldn't enable features on your `perseus` dependency, which must be declared under `[dependencies]` (with either a version or a table)")]
+    PerseusDependencyNotEditable,
+    #[error("couldn't parse `Cargo.toml` to apply plugin edits")]
+    ParseManifestFailed {
+        #[source]
+        source: toml_edit::TomlError,
+    },
+    #[error(
+        "couldn't add plugin dependencies to `{section}` in `Cargo.toml`, since it isn't a table"
+    )]
+    ManifestSectionNotEditable { section: String },
+    #[error("plugin '{plugin}' tried to inject code into '{file}', which isn't a relative path inside your project")]
+    UnsafeSnippetPath { plugin: String, file: String },
+    #[error("plugin '{plugin}' tried to enable the feature '{feature}' on `perseus`, which doesn't exist (try updating the CLI or the plugin)")]
+    UnknownPerseusFeature { plugin: String, feature: String },
+    #[error("couldn't find injection point '{point}' in '{file}' (add a `// perseus-tinker: {point}` comment where the plugin's code should go)")]
+    InjectionPointNotFound { file: String, point: String },
 }
 
 #[derive(Error, Debug)]
@@ -309,4 +496,32 @@ pub enum InstallError {
     },
     #[error("directory found in `dist/tools/` with invalid name (running `perseus clean` should resolve this)")]
     InvalidToolsDirName { name: String },
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+    #[error("couldn't read `Perseus.toml` to update pinned tool versions")]
+    ReadToolsConfigFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't parse `Perseus.toml` to update pinned tool versions")]
+    ParseToolsConfigForUpdateFailed {
+        #[source]
+        source: toml_edit::TomlError,
+    },
+    #[error("couldn't update pinned tool versions, since `tools` in `Perseus.toml` isn't a table")]
+    ToolsConfigNotEditable,
+    #[error("couldn't write pinned tool versions to `Perseus.toml`")]
+    WriteToolsConfigFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't open vendored archive for '{tool}' at '{path}'")]
+    VendoredToolArchiveUnavailable {
+        tool: String,
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("vendored archive for '{tool}' at '{path}' should contain exactly one directory (like the tool's GitHub releases)")]
+    VendoredToolArchiveInvalid { tool: String, path: String },
 }
@@ -1,8 +1,13 @@
-use crate::cmd::{cfg_spinner, run_stage};
+use crate::analyze::check_server_crates;
+use crate::build::get_build_envs;
+use crate::cmd::{cfg_spinner, print_warning, quote_path, run_stage_with_progress};
+use crate::deterministic::{get_source_date_epoch, get_wasm_rustflags};
+use crate::dev_wasm::DevWasmOpts;
 use crate::install::Tools;
 use crate::parse::{ExportOpts, Opts};
 use crate::thread::{spawn_thread, ThreadHandle};
-use crate::{errors::*, get_user_crate_name};
+use crate::wasm_cache::run_wasm_stage;
+use crate::{errors::*, get_dist_dir, get_user_crate_name};
 use console::{style, Emoji};
 use indicatif::{MultiProgress, ProgressBar};
 use std::fs;
@@ -37,30 +42,28 @@ macro_rules! copy_file {
     };
 }
 
-/// Finalizes the export by copying assets. This is very different from the
-/// finalization process of normal building.
+/// Finalizes the export by copying assets, given the `dist/` directory. This
+/// is very different from the finalization process of normal building.
 pub fn finalize_export(target: &Path) -> Result<(), ExportError> {
     // Copy files over (the directory structure should already exist from exporting
     // the pages)
     copy_file!(
-        "dist/pkg/perseus_engine.js",
-        "dist/exported/.perseus/bundle.js",
+        "pkg/perseus_engine.js",
+        "exported/.perseus/bundle.js",
         target
     );
     copy_file!(
-        "dist/pkg/perseus_engine_bg.wasm",
-        "dist/exported/.perseus/bundle.wasm",
+        "pkg/perseus_engine_bg.wasm",
+        "exported/.perseus/bundle.wasm",
         target
     );
-    // Copy any JS snippets over (if the directory doesn't exist though, don't do
-    // anything) This takes a target of the `dist/` directory, and then extends
-    // on that
-    fn copy_snippets(ext: &str, parent: &Path) -> Result<(), ExportError> {
-        // We read from the parent directory (`.perseus`), extended with `ext`
-        if let Ok(snippets) = fs::read_dir(&parent.join(ext)) {
+    // Copy any JS snippets over, preserving their directory structure (if the
+    // directory doesn't exist though, don't do anything)
+    fn copy_snippets(from: &Path, to: &Path) -> Result<(), ExportError> {
+        if let Ok(snippets) = fs::read_dir(from) {
             for file in snippets {
-                let path = match file {
-                    Ok(file) => file.path(),
+                let file = match file {
+                    Ok(file) => file,
                     Err(err) => {
                         return Err(ExportError::MoveAssetFailed {
                             from: "js snippet".to_string(),
@@ -69,50 +72,33 @@ pub fn finalize_export(target: &Path) -> Result<(), ExportError> {
                         })
                     }
                 };
+                let path = file.path();
+                let dest = to.join(file.file_name());
                 // Recurse on any directories and copy any files
                 if path.is_dir() {
-                    // We continue to pass on the parent, but we add the filename of this directory
-                    // to the extension
-                    copy_snippets(
-                        &format!("{}/{}", ext, path.file_name().unwrap().to_str().unwrap()),
-                        parent,
-                    )?;
+                    copy_snippets(&path, &dest)?;
                 } else {
-                    // `ext` holds the folder structure of this file, which we'll preserve
-                    // We must remove the prefix though (which is hardcoded in the initial
-                    // invocation of this function)
-                    let dir_tree = ext.strip_prefix("dist/pkg/snippets").unwrap();
-                    // This is to avoid `//`
-                    let dir_tree = if dir_tree.is_empty() {
-                        String::new()
-                    } else if dir_tree.starts_with('/') {
-                        dir_tree.to_string()
-                    } else {
-                        format!("/{}", dir_tree)
-                    };
-                    let filename = path.file_name().unwrap().to_str().unwrap();
-                    let final_dir_tree =
-                        parent.join(format!("dist/exported/.perseus/snippets{}", dir_tree));
-                    let path_to_copy_to = parent.join(&format!(
-                        "dist/exported/.perseus/snippets{}/{}",
-                        dir_tree, filename
-                    ));
                     // Create the directory structure needed for this
-                    if let Err(err) = fs::create_dir_all(&final_dir_tree) {
+                    if let Err(err) = fs::create_dir_all(to) {
                         return Err(ExportError::DirStructureCreationFailed { source: err });
                     }
-                    copy_file!(
-                        path.to_str().unwrap(),
-                        path_to_copy_to.to_str().unwrap(),
-                        parent
-                    );
+                    if let Err(err) = fs::copy(&path, &dest) {
+                        return Err(ExportError::MoveAssetFailed {
+                            from: path.to_string_lossy().to_string(),
+                            to: dest.to_string_lossy().to_string(),
+                            source: err,
+                        });
+                    }
                 }
             }
         }
 
         Ok(())
     }
-    copy_snippets("dist/pkg/snippets", target)?;
+    copy_snippets(
+        &target.join("pkg").join("snippets"),
+        &target.join("exported").join(".perseus").join("snippets"),
+    )?;
 
     Ok(())
 }
@@ -139,14 +125,17 @@ pub fn export_internal(
 > {
     let tools = tools.clone();
     let Opts {
-        cargo_browser_args,
+        mut cargo_browser_args,
         cargo_engine_args,
-        wasm_bindgen_args,
+        mut wasm_bindgen_args,
         wasm_opt_args,
         wasm_release_rustflags,
+        git_path,
+        deterministic,
         ..
     } = global_opts.clone();
     let crate_name = get_user_crate_name(&dir)?;
+    let dist = get_dist_dir(&dir, global_opts);
 
     // Exporting pages message
     let ep_msg = format!(
@@ -171,22 +160,51 @@ pub fn export_internal(
     let wb_spinner = cfg_spinner(wb_spinner, &wb_msg);
     let wb_target = dir;
     let cargo_engine_exec = tools.cargo_engine.clone();
+    let mut engine_envs = vec![
+        ("PERSEUS_ENGINE_OPERATION", "export".to_string()),
+        ("PERSEUS_DIST_DIR", dist.to_string_lossy().to_string()),
+        (
+            "CARGO_TARGET_DIR",
+            dist.join("target_engine").to_string_lossy().to_string(),
+        ),
+    ];
+    // Deterministic builds pin the time the engine thinks it is, and let the app
+    // know that it should seed any randomness
+    if deterministic {
+        engine_envs.push(("PERSEUS_DETERMINISTIC", "1".to_string()));
+        engine_envs.push((
+            "SOURCE_DATE_EPOCH",
+            get_source_date_epoch(&ep_target, &git_path),
+        ));
+    }
+    engine_envs.extend(get_build_envs(global_opts));
+    let dev_wasm = if is_release {
+        DevWasmOpts::default()
+    } else {
+        DevWasmOpts::get(&ep_target, global_opts)?
+    };
+    dev_wasm.apply_args(&mut cargo_browser_args, &mut wasm_bindgen_args);
+    let wasm_rustflags = get_wasm_rustflags(
+        &ep_target,
+        is_release,
+        deterministic,
+        &wasm_release_rustflags,
+        dev_wasm.rustflags().as_deref(),
+    );
+    let wasm_envs = dev_wasm.envs();
     let ep_thread = spawn_thread(
         move || {
-            handle_exit_code!(run_stage(
-                vec![&format!(
+            handle_exit_code!(run_stage_with_progress(
+                &format!(
                     "{} run {} {}",
                     cargo_engine_exec,
                     if is_release { "--release" } else { "" },
                     cargo_engine_args
-                )],
+                ),
                 &ep_target,
                 &ep_spinner,
                 &ep_msg,
-                vec![
-                    ("PERSEUS_ENGINE_OPERATION", "export"),
-                    ("CARGO_TARGET_DIR", "dist/target_engine")
-                ]
+                engine_envs.iter().map(|(k, v)| (*k, v.as_str())).collect()
             )?);
 
             Ok(0)
@@ -195,46 +213,61 @@ pub fn export_internal(
     );
     let wb_thread = spawn_thread(
         move || {
-            let mut cmds = vec![
-            // Build the Wasm artifact first (and we know where it will end up, since we're setting the target directory)
-            format!(
+            let profile = if is_release { "release" } else { "debug" };
+            // We know where the Wasm artifact will end up, since we're setting the target
+            // directory
+            let target_wasm = dist.join("target_wasm");
+            let wasm_path = target_wasm.join(format!(
+                "wasm32-unknown-unknown/{profile}/{crate_name}.wasm",
+                profile = profile,
+                crate_name = crate_name
+            ));
+            let pkg = dist.join("pkg");
+            let build_cmd = format!(
                 "{} build --target wasm32-unknown-unknown {} {}",
                 tools.cargo_browser,
                 if is_release { "--release" } else { "" },
                 cargo_browser_args
-            ),
-            // NOTE The `wasm-bindgen` version has to be *identical* to the dependency version
-            format!(
-                "{cmd} ./dist/target_wasm/wasm32-unknown-unknown/{profile}/{crate_name}.wasm --out-dir dist/pkg --out-name perseus_engine --target web {args}",
+            );
+            let mut post_cmds = vec![
+                // NOTE The `wasm-bindgen` version has to be *identical* to the dependency version
+                format!(
+                "{cmd} {wasm_path} --out-dir {pkg} --out-name perseus_engine --target web {args}",
                 cmd=tools.wasm_bindgen,
-                profile={ if is_release { "release" } else { "debug" } },
+                wasm_path=quote_path(&wasm_path),
+                pkg=quote_path(&pkg),
                 args=wasm_bindgen_args,
-                crate_name=crate_name
-            )
-        ];
+            ),
+            ];
             // If we're building for release, then we should run `wasm-opt`
             if is_release {
-                cmds.push(format!(
-                "{cmd} -Oz ./dist/pkg/perseus_engine_bg.wasm -o ./dist/pkg/perseus_engine_bg.wasm {args}",
-                cmd=tools.wasm_opt,
-                args=wasm_opt_args
-            ));
+                post_cmds.push(format!(
+                    "{cmd} -Oz {bundle} -o {bundle} {args}",
+                    cmd = tools.wasm_opt,
+                    bundle = quote_path(&pkg.join("perseus_engine_bg.wasm")),
+                    args = wasm_opt_args
+                ));
+            }
+            let target_wasm = target_wasm.to_string_lossy();
+            let mut envs = vec![("CARGO_TARGET_DIR", target_wasm.as_ref())];
+            if let Some(rustflags) = &wasm_rustflags {
+                envs.push(("RUSTFLAGS", rustflags));
             }
-            let cmds = cmds.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-            handle_exit_code!(run_stage(
-                cmds,
+            envs.extend(wasm_envs);
+            // Post-processing will be skipped if the Wasm artifact hasn't changed
+            handle_exit_code!(run_wasm_stage(
+                &build_cmd,
+                post_cmds,
+                &wasm_path,
+                &pkg,
                 &wb_target,
                 &wb_spinner,
                 &wb_msg,
-                if is_release {
-                    vec![
-                        ("CARGO_TARGET_DIR", "dist/target_wasm"),
-                        ("RUSTFLAGS", &wasm_release_rustflags),
-                    ]
-                } else {
-                    vec![("CARGO_TARGET_DIR", "dist/target_wasm")]
-                }
+                envs
             )?);
+            for warning in check_server_crates(&wasm_path, &crate_name) {
+                print_warning(&wb_spinner, &warning);
+            }
 
             Ok(0)
         },
@@ -278,7 +311,7 @@ pub fn export(
     }
 
     // And now we can run the finalization stage
-    finalize_export(&dir)?;
+    finalize_export(&get_dist_dir(&dir, global_opts))?;
 
     // We've handled errors in the component threads, so the exit code is now zero
     Ok(0)
@@ -1,5 +1,6 @@
 use crate::cmd::run_cmd_directly;
 use crate::errors::ExecutionError;
+use crate::get_dist_dir;
 use crate::install::Tools;
 use crate::parse::{ExportErrorPageOpts, Opts};
 use std::path::PathBuf;
@@ -12,6 +13,7 @@ pub fn export_error_page(
     tools: &Tools,
     global_opts: &Opts,
 ) -> Result<i32, ExecutionError> {
+    let dist = get_dist_dir(&dir, global_opts);
     run_cmd_directly(
         format!(
             "{} run {} -- {} {}",
@@ -24,7 +26,11 @@ pub fn export_error_page(
         &dir,
         vec![
             ("PERSEUS_ENGINE_OPERATION", "export_error_page"),
-            ("CARGO_TARGET_DIR", "dist/target_engine"),
+            ("PERSEUS_DIST_DIR", &dist.to_string_lossy()),
+            (
+                "CARGO_TARGET_DIR",
+                &dist.join("target_engine").to_string_lossy(),
+            ),
         ],
     )
 }
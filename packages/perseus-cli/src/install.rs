@@ -1,5 +1,7 @@
 use crate::cmd::{cfg_spinner, fail_spinner, succeed_spinner};
+use crate::config::PerseusToml;
 use crate::errors::*;
+use crate::get_dist_dir;
 use crate::parse::Opts;
 use console::Emoji;
 use directories::ProjectDirs;
@@ -7,6 +9,7 @@ use flate2::read::GzDecoder;
 use futures::future::try_join;
 use indicatif::ProgressBar;
 use reqwest::Client;
+use serde::Deserialize;
 use std::borrow::BorrowMut;
 use std::fs;
 use std::fs::File;
@@ -27,11 +30,12 @@ static INSTALLING: Emoji<'_, '_> = Emoji("📥", "");
 // where a tool can be found, we'll use that no matter what.
 
 /// Gets the directory to store tools in. This will preferentially use the
-/// system-wide cache, falling back to a local version.
+/// system-wide cache, falling back to a local version in the given `dist/`
+/// directory.
 ///
 /// If the user specifies that we're running on CI, we'll use the local version
 /// regardless.
-pub fn get_tools_dir(project: &Path, no_system_cache: bool) -> Result<PathBuf, InstallError> {
+pub fn get_tools_dir(dist: &Path, no_system_cache: bool) -> Result<PathBuf, InstallError> {
     match ProjectDirs::from("", "perseus", "perseus_cli") {
         Some(dirs) if !no_system_cache => {
             let target = dirs.cache_dir().join("tools");
@@ -43,7 +47,7 @@ pub fn get_tools_dir(project: &Path, no_system_cache: bool) -> Result<PathBuf, I
                     Ok(target)
                 } else {
                     // Failed, so we'll resort to the local cache
-                    let target = project.join("dist/tools");
+                    let target = dist.join("tools");
                     if !target.exists() {
                         // If this fails, we have no recourse, so we'll have to fail
                         fs::create_dir_all(&target)
@@ -55,7 +59,7 @@ pub fn get_tools_dir(project: &Path, no_system_cache: bool) -> Result<PathBuf, I
             }
         }
         _ => {
-            let target = project.join("dist/tools");
+            let target = dist.join("tools");
             if !target.exists() {
                 // If this fails, we have no recourse, so we'll have to fail
                 fs::create_dir_all(&target)
@@ -67,6 +71,61 @@ pub fn get_tools_dir(project: &Path, no_system_cache: bool) -> Result<PathBuf, I
     }
 }
 
+/// The tool versions pinned in the `[tools]` section of a project's
+/// `Perseus.toml`, which allows builds to be reproducible (e.g. on CI).
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct ToolsConfig {
+    /// The pinned version of `wasm-bindgen`.
+    #[serde(rename = "wasm-bindgen")]
+    pub wasm_bindgen: Option<ToolPin>,
+    /// The pinned version of `wasm-opt` (which is a version of Binaryen).
+    #[serde(rename = "wasm-opt", alias = "binaryen")]
+    pub wasm_opt: Option<ToolPin>,
+}
+impl ToolsConfig {
+    /// Reads the tools configuration from the `Perseus.toml` file in the given
+    /// project directory. If there's no such file, or if it has no `[tools]`
+    /// section, nothing will be pinned.
+    pub fn from_project(dir: &Path) -> Result<Self, InstallError> {
+        Ok(PerseusToml::read(dir)?.tools)
+    }
+    /// Gets the pin for the given tool, if there is one.
+    pub fn get(&self, tool_type: &ToolType) -> Option<&ToolPin> {
+        match tool_type {
+            ToolType::WasmBindgen => self.wasm_bindgen.as_ref(),
+            ToolType::WasmOpt => self.wasm_opt.as_ref(),
+        }
+    }
+}
+
+/// A pinned version of a tool, which may either be a plain version string, or
+/// a table that also specifies a local archive to install the tool from (for
+/// offline installs).
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ToolPin {
+    /// Just a version (e.g. `"0.2.83"`), which will be downloaded if it's not
+    /// already installed.
+    Version(String),
+    /// A version, along with the path (relative to the project) to a vendored
+    /// archive of the tool, in the same format as its GitHub releases.
+    Vendored {
+        /// The version of the tool.
+        version: String,
+        /// The path to the archive to install the tool from.
+        path: PathBuf,
+    },
+}
+impl ToolPin {
+    /// Gets the version this pins the tool to.
+    pub fn version(&self) -> &str {
+        match self {
+            Self::Version(version) => version,
+            Self::Vendored { version, .. } => version,
+        }
+    }
+}
+
 /// A representation of the paths to all the external tools we need.
 /// This includes `cargo`, simply for convenience, even though it's not
 /// actually independently installed.
@@ -89,18 +148,26 @@ impl Tools {
     ///
     /// If tools are installed, this will create a CLI spinner automatically.
     pub async fn new(dir: &Path, global_opts: &Opts) -> Result<Self, InstallError> {
-        let target = get_tools_dir(dir, global_opts.no_system_tools_cache)?;
+        let target = get_tools_dir(
+            &get_dist_dir(dir, global_opts),
+            global_opts.no_system_tools_cache,
+        )?;
+        let config = ToolsConfig::from_project(dir)?;
 
         // Instantiate the tools
-        let wasm_bindgen = Tool::new(
+        let wasm_bindgen = Tool::new_with_config(
             ToolType::WasmBindgen,
             &global_opts.wasm_bindgen_path,
             &global_opts.wasm_bindgen_version,
+            dir,
+            &config,
         );
-        let wasm_opt = Tool::new(
+        let wasm_opt = Tool::new_with_config(
             ToolType::WasmOpt,
             &global_opts.wasm_opt_path,
             &global_opts.wasm_opt_version,
+            dir,
+            &config,
         );
 
         // Get the statuses of all the tools
@@ -164,6 +231,9 @@ pub struct Tool {
     /// A specific version number provided by the user. By default, the latest
     /// version is used.
     pub user_given_version: Option<String>,
+    /// A local archive to install the tool from, instead of downloading it.
+    /// This will only be used if the user has also given a version.
+    pub vendored_archive: Option<PathBuf>,
     /// The path to the binary within the directory that is extracted from the
     /// downloaded archive.
     pub final_path: String,
@@ -193,19 +263,45 @@ impl Tool {
             name: tool_type.name(),
             user_given_path: user_given_path.to_owned(),
             user_given_version: user_given_version.to_owned(),
+            vendored_archive: None,
             final_path,
             gh_repo: tool_type.gh_repo(),
             extracted_dir_name: tool_type.extracted_dir_name(),
             tool_type,
         }
     }
+    /// Creates a new instance of this `struct`, using any version pinned in the
+    /// given tools configuration from the given project. Anything the user
+    /// has given directly will take precedence over the configuration.
+    pub fn new_with_config(
+        tool_type: ToolType,
+        user_given_path: &Option<String>,
+        user_given_version: &Option<String>,
+        dir: &Path,
+        config: &ToolsConfig,
+    ) -> Self {
+        let pin = config.get(&tool_type).cloned();
+        let mut tool = Self::new(tool_type, user_given_path, user_given_version);
+        if tool.user_given_version.is_none() {
+            match pin {
+                Some(ToolPin::Version(version)) => tool.user_given_version = Some(version),
+                Some(ToolPin::Vendored { version, path }) => {
+                    tool.user_given_version = Some(version);
+                    tool.vendored_archive = Some(dir.join(path));
+                }
+                None => (),
+            }
+        }
+
+        tool
+    }
     /// Gets the name of the artifact to download based on the tool data and the
     /// version to download. Note that the version provided here entirely
     /// overrides anything the user might have provided.
     ///
     /// If no precompiled binary is expected to be available for the current
     /// platform, this will return `None`.
-    fn get_artifact_name(&self, version: &str) -> Option<String> {
+    pub fn get_artifact_name(&self, version: &str) -> Option<String> {
         match &self.tool_type {
             // --- `wasm-bindgen` ---
             // Linux
@@ -255,7 +351,7 @@ impl Tool {
     /// available, and the same if it returns `ToolStatus::NeedsLatestInstall`.
     pub fn get_status(&self, target: &Path) -> Result<ToolStatus, InstallError> {
         // The status information will be incomplete from this first pass
-        let initial_status = {
+        let initial_status: Result<ToolStatus, InstallError> = {
             // If there's a directory that matches with a given user version, we'll use it.
             // If not, we'll use the latest version. Only if there are no
             // installed versions available will this return `None`, or if the user wants a
@@ -286,27 +382,8 @@ impl Tool {
                 } else {
                     // We have no further information from the user, so we'll use the latest version
                     // that's installed, or we'll install the latest version.
-                    // Either way, we need to know what we've got installed already by walking the
-                    // directory.
-                    let mut versions: Vec<String> = Vec::new();
-                    for entry in fs::read_dir(target)
-                        .map_err(|err| InstallError::ReadToolsDirFailed { source: err })?
-                    {
-                        let entry = entry
-                            .map_err(|err| InstallError::ReadToolsDirFailed { source: err })?;
-                        let dir_name = entry.file_name().to_string_lossy().to_string();
-                        if dir_name.starts_with(&self.name) {
-                            let dir_name_ref = dir_name.to_string();
-                            // Valid directory names are of the form `<tool-name>-<tool-version>`
-                            let version = dir_name
-                                .strip_prefix(&format!("{}-", self.name))
-                                .ok_or(InstallError::InvalidToolsDirName { name: dir_name_ref })?;
-                            versions.push(version.to_string());
-                        }
-                    }
-                    // Now order those from most recent to least recent
-                    versions.sort();
-                    let versions = versions.into_iter().rev().collect::<Vec<String>>();
+                    // Either way, we need to know what we've got installed already.
+                    let versions = self.get_installed_versions(target)?;
                     // If there are any at all, pick the first one
                     if !versions.is_empty() {
                         let latest_available_version = &versions[0];
@@ -326,13 +403,22 @@ impl Tool {
                     }
                 }
             }
-        }?;
+        };
+        let initial_status = initial_status?;
         // If we're considering installing something, we should make sure that there are
         // actually precompiled binaries available for this platform (if there
         // aren't, then we'll try to fall back on anything the user has installed
         // locally, and if they have nothing, an error will be returned)
         match initial_status {
             ToolStatus::Available(path) => Ok(ToolStatus::Available(path)),
+            // If we have a local archive, we don't need precompiled binaries to be available for
+            // download
+            ToolStatus::NeedsInstall { version, .. } if self.vendored_archive.is_some() => {
+                Ok(ToolStatus::NeedsInstall {
+                    version,
+                    artifact_name: String::new(),
+                })
+            }
             ToolStatus::NeedsInstall { version, .. } => {
                 // This will be `None` if there are no precompiled binaries available
                 let artifact_name = self.get_artifact_name(&version);
@@ -375,6 +461,30 @@ impl Tool {
             }
         }
     }
+    /// Gets the versions of this tool that are installed in the given tools
+    /// directory, from most recent to least recent.
+    pub fn get_installed_versions(&self, target: &Path) -> Result<Vec<String>, InstallError> {
+        // We need to walk the directory to find these
+        let mut versions: Vec<String> = Vec::new();
+        for entry in
+            fs::read_dir(target).map_err(|err| InstallError::ReadToolsDirFailed { source: err })?
+        {
+            let entry = entry.map_err(|err| InstallError::ReadToolsDirFailed { source: err })?;
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if dir_name.starts_with(&self.name) {
+                let dir_name_ref = dir_name.to_string();
+                // Valid directory names are of the form `<tool-name>-<tool-version>`
+                let version = dir_name
+                    .strip_prefix(&format!("{}-", self.name))
+                    .ok_or(InstallError::InvalidToolsDirName { name: dir_name_ref })?;
+                versions.push(version.to_string());
+            }
+        }
+        // Now order those from most recent to least recent
+        versions.sort();
+
+        Ok(versions.into_iter().rev().collect())
+    }
     /// Gets the latest version for this tool from its GitHub repository. One
     /// should only bother executing this if we know there are precompiled
     /// binaries for this platform.
@@ -419,6 +529,9 @@ impl Tool {
         // Do a sanity check to prevent installing something that already exists
         match status {
             ToolStatus::Available(path) => Ok(path),
+            ToolStatus::NeedsInstall { version, .. } if self.vendored_archive.is_some() => {
+                self.install_vendored(&version, target)
+            }
             ToolStatus::NeedsInstall {
                 version,
                 artifact_name,
@@ -471,9 +584,60 @@ impl Tool {
             Ok(self.name.to_string())
         }
     }
+    /// Installs the given version of the tool from the user's vendored archive,
+    /// returning the path to the final binary. This works entirely offline.
+    fn install_vendored(&self, version: &str, target: &Path) -> Result<String, InstallError> {
+        // This is only called when we know we have an archive
+        let archive_path = self.vendored_archive.as_ref().unwrap();
+        let dir_path = target.join(format!("{}-{}", self.name, version));
+        // We don't know what the archive will extract to (especially for custom
+        // builds), so we extract it into a temporary directory first
+        let tmp_path = target.join(format!(".{}-{}-vendored", self.name, version));
+        if tmp_path.exists() {
+            fs::remove_dir_all(&tmp_path)
+                .map_err(|err| InstallError::CreateToolExtractDestFailed { source: err })?;
+        }
+
+        let tar_gz = File::open(archive_path).map_err(|err| {
+            InstallError::VendoredToolArchiveUnavailable {
+                tool: self.name.to_string(),
+                path: archive_path.to_string_lossy().to_string(),
+                source: err,
+            }
+        })?;
+        let mut archive = Archive::new(GzDecoder::new(tar_gz));
+        archive
+            .unpack(&tmp_path)
+            .map_err(|err| InstallError::ToolExtractFailed {
+                source: err,
+                tool: self.name.to_string(),
+            })?;
+        // The archive should contain a single directory, like the GitHub releases do
+        let mut entries = fs::read_dir(&tmp_path)
+            .map_err(|err| InstallError::ReadToolsDirFailed { source: err })?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect::<Vec<PathBuf>>();
+        if entries.len() != 1 || !entries[0].is_dir() {
+            return Err(InstallError::VendoredToolArchiveInvalid {
+                tool: self.name.to_string(),
+                path: archive_path.to_string_lossy().to_string(),
+            });
+        }
+        fs::rename(entries.remove(0), &dir_path)
+            .map_err(|err| InstallError::DirRenameFailed { source: err })?;
+        fs::remove_dir_all(&tmp_path)
+            .map_err(|err| InstallError::ArchiveDeletionFailed { source: err })?;
+
+        Ok(dir_path
+            .join(&self.final_path)
+            .to_str()
+            .unwrap()
+            .to_string())
+    }
     /// Installs the given version of the tool, returning the path to the final
     /// binary.
-    async fn install_version(
+    pub async fn install_version(
         &self,
         version: &str,
         artifact_name: &str,
@@ -15,49 +15,84 @@ the documentation you'd like to see on this front!
 
 #![deny(missing_docs)]
 
+mod analyze;
 mod build;
+mod check;
+mod clean;
 mod cmd;
+mod config;
 mod deploy;
+mod deterministic;
+mod dev_url;
+mod dev_wasm;
+mod docker;
 pub mod errors;
 mod export;
 mod export_error_page;
 mod init;
 mod install;
+mod manifest;
 /// Parsing utilities for arguments.
 pub mod parse;
+mod ports;
 mod prepare;
+mod proxy;
 mod reload_server;
 mod serve;
 mod serve_exported;
 mod snoop;
 mod thread;
 mod tinker;
+mod tinker_edits;
+mod toml_doc;
+mod tools;
+mod wasm_cache;
 
 use errors::*;
+use parse::Opts;
 use std::path::PathBuf;
 use std::{fs, path::Path};
 
 /// The current version of the CLI, extracted from the crate version.
 pub const PERSEUS_VERSION: &str = env!("CARGO_PKG_VERSION");
-pub use build::build;
-pub use deploy::deploy;
+pub use analyze::analyze;
+pub use build::{build, regenerate_static};
+pub use check::check;
+pub use clean::clean;
+pub use config::PerseusToml;
+pub use deploy::{check_deployment, deploy, write_deployment_manifest};
+pub use dev_url::{is_loopback, BROWSER_OPENED_MARKER};
+pub use docker::deploy_docker;
 pub use export::export;
 pub use export_error_page::export_error_page;
 pub use init::{init, new};
-pub use install::{get_tools_dir, Tools};
+pub use install::{get_tools_dir, ToolPin, Tools, ToolsConfig};
+pub use manifest::verify;
+pub use ports::{find_free_port, note_port_change};
 pub use prepare::check_env;
 pub use reload_server::{order_reload, run_reload_server};
 pub use serve::serve;
 pub use serve_exported::serve_exported;
 pub use snoop::{snoop_build, snoop_server, snoop_wasm_build};
 pub use tinker::tinker;
+pub use tools::tools;
 
-/// Creates the `dist/` directory in the project root, which is necessary
-/// for Cargo to be able to put its build artifacts in there.
-pub fn create_dist(dir: &Path) -> Result<(), ExecutionError> {
-    let target = dir.join("dist");
-    if !target.exists() {
-        fs::create_dir(target).map_err(|err| ExecutionError::CreateDistFailed { source: err })?;
+/// Gets the directory that everything the CLI generates will be put in, given
+/// the project directory. This is `dist/` in the project root by default, but
+/// it can be changed with `--out-dir` (relative paths are resolved from the
+/// project root). Everything that works with build artifacts should go through
+/// this.
+pub fn get_dist_dir(dir: &Path, global_opts: &Opts) -> PathBuf {
+    dir.join(&global_opts.out_dir)
+}
+
+/// Creates the `dist/` directory (or wherever `--out-dir` says it should be),
+/// which is necessary for Cargo to be able to put its build artifacts in
+/// there.
+pub fn create_dist(dist: &Path) -> Result<(), ExecutionError> {
+    if !dist.exists() {
+        // The output directory might be somewhere that doesn't exist yet
+        fs::create_dir_all(dist).map_err(|err| ExecutionError::CreateDistFailed { source: err })?;
     }
     Ok(())
 }
@@ -65,12 +100,11 @@ pub fn create_dist(dir: &Path) -> Result<(), ExecutionError> {
 /// Deletes the entire `dist/` directory. Notably, this is where we keep
 /// several Cargo artifacts, so this means the next build will be much
 /// slower.
-pub fn delete_dist(dir: PathBuf) -> Result<(), ExecutionError> {
-    let target = dir.join("dist");
-    if target.exists() {
-        if let Err(err) = fs::remove_dir_all(&target) {
+pub fn delete_dist(dist: PathBuf) -> Result<(), ExecutionError> {
+    if dist.exists() {
+        if let Err(err) = fs::remove_dir_all(&dist) {
             return Err(ExecutionError::RemoveArtifactsFailed {
-                target: target.to_str().map(|s| s.to_string()),
+                target: dist.to_str().map(|s| s.to_string()),
                 source: err,
             });
         }
@@ -79,11 +113,10 @@ pub fn delete_dist(dir: PathBuf) -> Result<(), ExecutionError> {
     Ok(())
 }
 
-/// Deletes build artifacts in `dist/static` or `dist/pkg` and replaces the
-/// directory.
-pub fn delete_artifacts(dir: PathBuf, dir_to_remove: &str) -> Result<(), ExecutionError> {
-    let mut target = dir;
-    target.extend(["dist", dir_to_remove]);
+/// Deletes build artifacts in `dist/static` or `dist/pkg` (given the `dist/`
+/// directory) and replaces the directory.
+pub fn delete_artifacts(dist: PathBuf, dir_to_remove: &str) -> Result<(), ExecutionError> {
+    let target = dist.join(dir_to_remove);
     // We'll only delete the directory if it exists, otherwise we're fine
     if target.exists() {
         if let Err(err) = fs::remove_dir_all(&target) {
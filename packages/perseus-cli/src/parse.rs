@@ -39,6 +39,23 @@ pub struct Opts {
         global = true
     )]
     pub wasm_release_rustflags: String,
+    /// Build for Wasm with `opt-level=1` in development, for a faster app at
+    /// the cost of slightly slower builds (also settable in `Perseus.toml`)
+    #[clap(long, global = true)]
+    pub wasm_dev_optimize: bool,
+    /// Enable Perseus' `hydrate` feature for Wasm builds in development (also
+    /// settable in `Perseus.toml`)
+    #[clap(long, global = true)]
+    pub wasm_dev_hydrate: bool,
+    /// Keep debug symbols in Wasm builds in development, for better stack
+    /// traces (also settable in `Perseus.toml`)
+    #[clap(long, global = true)]
+    pub wasm_dev_keep_debug: bool,
+    /// Emit debugging information in Wasm builds in development, and report
+    /// panics in the browser with symbolicated stack traces (also settable in
+    /// `Perseus.toml`)
+    #[clap(long, global = true)]
+    pub wasm_dev_source_maps: bool,
     /// Any arguments to `cargo` when building for the engine-side
     #[clap(long, default_value = "", global = true)]
     pub cargo_engine_args: String,
@@ -57,8 +74,14 @@ pub struct Opts {
     /// The host for the reload server (you should almost never change this)
     #[clap(long, default_value = "localhost", global = true)]
     pub reload_server_host: String,
-    /// The port for the reload server (you should almost never change this)
-    #[clap(long, default_value = "3100", global = true)]
+    /// The port for the reload server (if it's taken, the next free port will
+    /// be used)
+    #[clap(
+        long,
+        visible_alias = "reload-port",
+        default_value = "3100",
+        global = true
+    )]
     pub reload_server_port: u16,
     /// If this is set, commands will be run sequentially rather than in
     /// parallel (slows down operations, but reduces memory usage)
@@ -79,6 +102,28 @@ pub struct Opts {
     /// should set this for CI)
     #[clap(long, global = true)]
     pub no_system_tools_cache: bool,
+    /// Makes builds and exports reproducible: build-time timestamps will be
+    /// pinned to `SOURCE_DATE_EPOCH` (or the time of the last Git commit),
+    /// `is_deterministic_build()` will be `true` in your app, and
+    /// local paths will be kept out of the Wasm bundle
+    #[clap(long, global = true)]
+    pub deterministic: bool,
+    /// The directory to put everything the CLI generates in, rather than
+    /// `dist/` (relative paths are resolved from the root of your project,
+    /// and you'll need to give the same directory to every command)
+    #[clap(long, default_value = "dist", global = true)]
+    pub out_dir: String,
+    /// Only build and export the given locales (comma-separated, e.g.
+    /// `en-US,fr-FR`), rather than all the ones your app supports (your app's
+    /// default locale will always be built)
+    #[clap(long, visible_alias = "locale", value_delimiter = ',', global = true)]
+    pub locales: Vec<String>,
+    /// The profile to build your app with, which your state generation
+    /// functions can use to tell preview and production builds apart
+    /// (defaults to `preview` in development and `production` in release
+    /// builds)
+    #[clap(long, value_parser = ["preview", "production"], global = true)]
+    pub profile: Option<String>,
 }
 
 #[derive(Parser, Clone)]
@@ -89,17 +134,41 @@ pub enum Subcommand {
     Serve(ServeOpts),
     /// Serves your app as `perseus serve` does, but puts it in testing mode
     Test(ServeOpts),
-    /// Removes build artifacts in the `dist/` directory
-    Clean,
+    Clean(CleanOpts),
     Deploy(DeployOpts),
     Tinker(TinkerOpts),
+    Analyze(AnalyzeOpts),
+    Check(CheckOpts),
+    Verify(VerifyOpts),
     /// Runs one of the underlying commands that builds your app, allowing you
     /// to see more detailed logs
     #[clap(subcommand)]
     Snoop(SnoopSubcommand),
+    /// Manages the external tools used to build your app (`wasm-bindgen` and
+    /// `wasm-opt`), which can be pinned in `Perseus.toml`
+    #[clap(subcommand)]
+    Tools(ToolsSubcommand),
     New(NewOpts),
     Init(InitOpts),
 }
+/// Removes build artifacts in the `dist/` directory (everything, unless you
+/// specify what to remove)
+#[derive(Parser, Clone)]
+pub struct CleanOpts {
+    /// Remove pages cached by revalidation and incremental generation
+    /// (`dist/mutable/`)
+    #[clap(long)]
+    pub cache: bool,
+    /// Remove Cargo's build artifacts (`dist/target_*/`)
+    #[clap(long)]
+    pub targets: bool,
+    /// Remove the exported app (`dist/exported/`)
+    #[clap(long)]
+    pub exported: bool,
+    /// Remove the tools the CLI has installed (e.g. `wasm-bindgen`)
+    #[clap(long)]
+    pub tools: bool,
+}
 /// Builds your app
 #[derive(Parser, Clone)]
 pub struct BuildOpts {
@@ -171,6 +240,57 @@ pub struct ServeOpts {
     /// The port to host your exported app on
     #[clap(long, default_value = "8080")]
     pub port: u16,
+    /// Open your app in your default browser once it's been built (this will
+    /// only happen once if you're watching for changes)
+    #[clap(long)]
+    pub open: bool,
+    /// Print a QR code of your app's address on your local network, for
+    /// testing on other devices (if the host is a loopback address, your app
+    /// will be served on all network interfaces instead)
+    #[clap(long)]
+    pub qr: bool,
+    /// Proxies requests for paths under the given prefix to a backend (e.g.
+    /// `/api=http://localhost:4000`), which avoids CORS issues when developing
+    /// against a separate API (these can also be set in the `[proxy]` section
+    /// of `Perseus.toml`)
+    #[clap(long)]
+    pub proxy: Vec<String>,
+    /// The host other devices will reach your app at (e.g. your machine's
+    /// address on your local network), which will be used for live reloading
+    /// and absolute asset URLs (if the host is a loopback address, your app
+    /// and the reload server will be served on all network interfaces
+    /// instead)
+    #[clap(long)]
+    pub public_host: Option<String>,
+    /// Cross-compile the server for the given target triple (e.g.
+    /// `x86_64-unknown-linux-musl`), which requires `--no-run`
+    #[clap(long)]
+    pub target: Option<String>,
+    /// The linker to use when cross-compiling the server (e.g.
+    /// `x86_64-linux-musl-gcc`), which will also be used as the C compiler
+    /// for any build scripts
+    #[clap(long, requires = "target")]
+    pub linker: Option<String>,
+}
+/// Analyzes the size of your app's last Wasm build, showing the largest crates
+/// and templates, and how they've changed since the last analysis
+#[derive(Parser, Clone)]
+pub struct AnalyzeOpts {
+    /// Analyze the last release build, rather than the last development build
+    #[clap(long)]
+    pub release: bool,
+    /// How many of the largest crates and templates to show
+    #[clap(long, default_value = "10")]
+    pub top: usize,
+}
+/// Checks your app's configuration for problems, like duplicate template
+/// paths or missing translations, without building it
+#[derive(Parser, Clone)]
+pub struct CheckOpts {
+    /// Check the configuration as it would be in production (e.g. making sure
+    /// you've set your own error pages)
+    #[clap(long)]
+    pub release: bool,
 }
 /// Packages your app for deployment
 #[derive(Parser, Clone)]
@@ -181,6 +301,57 @@ pub struct DeployOpts {
     /// Export you app to purely static files (see `export`)
     #[clap(short, long)]
     pub export_static: bool,
+    /// Cross-compile the server for the given target triple (e.g.
+    /// `x86_64-unknown-linux-musl`), so you can deploy to a different platform
+    /// from the one you're building on
+    #[clap(long, conflicts_with = "export_static")]
+    pub target: Option<String>,
+    /// The linker to use when cross-compiling the server (e.g.
+    /// `x86_64-linux-musl-gcc`), which will also be used as the C compiler
+    /// for any build scripts
+    #[clap(long, requires = "target")]
+    pub linker: Option<String>,
+    /// Emit a multi-stage `Dockerfile` into the output directory that builds
+    /// and packages your app inside a container, rather than building it here
+    /// (build it from your project's root with `docker build -f
+    /// pkg/Dockerfile .`)
+    #[clap(long, conflicts_with_all = &["export_static", "target"])]
+    pub docker: bool,
+    /// Build an image from the emitted `Dockerfile` straight away using the
+    /// given builder (e.g. `docker` or `podman`)
+    #[clap(long, requires = "docker")]
+    pub docker_builder: Option<String>,
+    /// The tag to give the image built with `--docker-builder`
+    #[clap(long, default_value = "perseus-app")]
+    pub docker_tag: String,
+    /// Check the packaged output once it's been deployed, making sure
+    /// everything the server needs is there and that it starts up and serves
+    /// your app properly (the server won't be started if it was
+    /// cross-compiled)
+    #[clap(long, conflicts_with = "docker")]
+    pub check: bool,
+    /// Write a manifest of every file in the output (with their SHA-256
+    /// hashes) to `perseus-manifest.json`, which can be checked with `perseus
+    /// verify` after uploading
+    #[clap(long, conflicts_with = "docker")]
+    pub manifest: bool,
+    /// Sign the manifest with the Ed25519 private key at the given path (in
+    /// PEM format), writing the signature to `perseus-manifest.json.sig`
+    /// (implies `--manifest`)
+    #[clap(long, conflicts_with = "docker")]
+    pub sign_key: Option<String>,
+}
+/// Verifies a deployment against the manifest written by `perseus deploy
+/// --manifest`, making sure no files have been changed, added, or removed
+#[derive(Parser, Clone)]
+pub struct VerifyOpts {
+    /// The directory the deployment is in
+    #[clap(default_value = "pkg")]
+    pub dir: String,
+    /// Check the manifest's signature with the Ed25519 public key at the given
+    /// path (in PEM format)
+    #[clap(long)]
+    pub public_key: Option<String>,
 }
 /// Runs the `tinker` action of plugins, which lets them modify the Perseus
 /// engine
@@ -227,6 +398,17 @@ pub enum SnoopSubcommand {
     Serve(SnoopServeOpts),
 }
 
+#[derive(Parser, Clone)]
+pub enum ToolsSubcommand {
+    /// Lists the pinned and installed versions of each tool
+    List,
+    /// Installs the latest version of each tool and pins it in `Perseus.toml`
+    Update,
+    /// Checks that the pinned version of each tool is installed and working
+    /// (useful on CI)
+    Verify,
+}
+
 #[derive(Parser, Clone)]
 pub struct SnoopServeOpts {
     /// Where to host your exported app
@@ -235,4 +417,8 @@ pub struct SnoopServeOpts {
     /// The port to host your exported app on
     #[clap(long, default_value = "8080")]
     pub port: u16,
+    /// Log every page the server renders, along with the rendering strategies
+    /// used and how long was spent reading from stores and rendering it
+    #[clap(long)]
+    pub request_log: bool,
 }
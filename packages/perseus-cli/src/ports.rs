@@ -0,0 +1,36 @@
+use crate::errors::ExecutionError;
+use std::net::TcpListener;
+
+/// The number of ports after the requested one that will be tried before we
+/// give up on finding a free one.
+const MAX_PORT_ATTEMPTS: u16 = 100;
+
+/// Finds a port that can be listened on at the given host, starting with the
+/// given port and moving upwards if it's already taken. Note that there's
+/// nothing to stop another process from taking the port we return before we
+/// get to use it, but this is only for development servers, so that doesn't
+/// matter much.
+pub fn find_free_port(host: &str, port: u16) -> Result<u16, ExecutionError> {
+    for candidate in port..=port.saturating_add(MAX_PORT_ATTEMPTS) {
+        // The listener will be dropped immediately, freeing the port again
+        if TcpListener::bind((host, candidate)).is_ok() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(ExecutionError::NoFreePort {
+        host: host.to_string(),
+        port,
+    })
+}
+
+/// Prints a note that the given port was taken, and so a different one is being
+/// used, if that's the case.
+pub fn note_port_change(what: &str, requested: u16, chosen: u16) {
+    if requested != chosen {
+        println!(
+            "Note: port {} is already in use, so the {} will use port {} instead.",
+            requested, what, chosen
+        );
+    }
+}
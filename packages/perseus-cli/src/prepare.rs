@@ -68,3 +68,42 @@ pub fn check_env(global_opts: &Opts) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Makes sure the given target is installed with `rustup` so that the server
+/// can be cross-compiled for it. Like the check for `wasm32-unknown-unknown`,
+/// this won't do anything if the user isn't using `rustup`.
+pub fn add_cross_target(target: &str, global_opts: &Opts) -> Result<(), ExecutionError> {
+    #[cfg(unix)]
+    let shell_exec = "sh";
+    #[cfg(windows)]
+    let shell_exec = "powershell";
+    #[cfg(unix)]
+    let shell_param = "-c";
+    #[cfg(windows)]
+    let shell_param = "-command";
+
+    let rustup_cmd = global_opts.rustup_path.to_string() + " target list --installed";
+    let rustup_res = Command::new(shell_exec)
+        .args([shell_param, &rustup_cmd])
+        .output();
+    if let Ok(rustup_res) = rustup_res {
+        if rustup_res.status.success() {
+            let stdout = String::from_utf8_lossy(&rustup_res.stdout);
+            if !stdout.lines().any(|line| line.trim() == target) {
+                let exit_code = run_cmd_directly(
+                    format!("{} target add {}", global_opts.rustup_path, target),
+                    &PathBuf::from("."),
+                    vec![],
+                )?;
+                if exit_code != 0 {
+                    return Err(ExecutionError::CrossTargetAddFailed {
+                        target: target.to_string(),
+                        code: exit_code,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
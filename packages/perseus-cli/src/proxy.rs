@@ -0,0 +1,200 @@
+use crate::config::PerseusToml;
+use crate::errors::ExecutionError;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use warp::http::{HeaderMap, Method, Response, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::path::FullPath;
+use warp::Filter;
+
+/// Headers that only apply to a single connection, which shouldn't be
+/// forwarded by a proxy.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// A path prefix that should be proxied to a backend by the development
+/// server, rather than being handled by the app.
+#[derive(Clone, Debug)]
+pub struct ProxyRoute {
+    /// The path prefix to match (e.g. `/api`). This will only match whole
+    /// path segments, so `/api` won't match `/apiary`.
+    pub prefix: String,
+    /// The backend to forward requests to (e.g. `http://localhost:4000`). The
+    /// full path of the request will be appended to this.
+    pub target: String,
+}
+impl ProxyRoute {
+    /// Parses a proxy route of the form `/api=http://localhost:4000`.
+    fn parse(route: &str) -> Result<Self, ExecutionError> {
+        match route.split_once('=') {
+            Some((prefix, target)) if prefix.starts_with('/') && !target.is_empty() => {
+                Ok(Self::new(prefix, target))
+            }
+            _ => Err(ExecutionError::InvalidProxyRoute {
+                route: route.to_string(),
+            }),
+        }
+    }
+    /// Creates a new proxy route, normalizing trailing slashes.
+    fn new(prefix: &str, target: &str) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            target: target.trim_end_matches('/').to_string(),
+        }
+    }
+    /// Checks if this route should handle the given path.
+    fn matches(&self, path: &str) -> bool {
+        match path.strip_prefix(&self.prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+/// Gets the proxy routes for the development server, from both the `[proxy]`
+/// section of `Perseus.toml` and the given `--proxy` arguments. If both
+/// specify the same prefix, the argument will win.
+pub fn get_proxy_routes(dir: &Path, args: &[String]) -> Result<Vec<ProxyRoute>, ExecutionError> {
+    let mut routes = Vec::new();
+    for (prefix, target) in PerseusToml::read(dir)?.proxy {
+        routes.push(ProxyRoute::parse(&format!("{}={}", prefix, target))?);
+    }
+    for arg in args {
+        let route = ProxyRoute::parse(arg)?;
+        routes.retain(|other: &ProxyRoute| other.prefix != route.prefix);
+        routes.push(route);
+    }
+    // The most specific prefix should be matched first
+    routes.sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()));
+
+    Ok(routes)
+}
+
+/// Resolves the address the development proxy should listen on from the host
+/// and port the user gave, which may be a hostname (like `localhost`) or an
+/// IPv6 address.
+pub fn resolve_proxy_addr(host: &str, port: u16) -> Result<SocketAddr, ExecutionError> {
+    let make_err = |source| ExecutionError::ResolveProxyAddrFailed {
+        host: host.to_string(),
+        port,
+        source,
+    };
+    // IPv6 addresses might be given in brackets (as they would be in a URL)
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (host, port)
+        .to_socket_addrs()
+        .map_err(make_err)?
+        .next()
+        .ok_or_else(|| {
+            make_err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "host resolved to no addresses",
+            ))
+        })
+}
+
+/// Runs a reverse proxy at the given address, which will forward requests
+/// matching any of the given routes to their backends, and everything else to
+/// the app's server on the given port. This is only designed for development,
+/// and it doesn't support WebSockets.
+pub async fn run_proxy(addr: SocketAddr, app_port: u16, routes: Vec<ProxyRoute>) {
+    let client = reqwest::Client::builder()
+        // Redirects should go back to the browser
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let routes = Arc::new(routes);
+    let app = format!("http://127.0.0.1:{}", app_port);
+
+    let proxy = warp::method()
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .then(
+            move |method: Method,
+                  path: FullPath,
+                  query: String,
+                  headers: HeaderMap,
+                  body: Bytes| {
+                let client = client.clone();
+                let routes = Arc::clone(&routes);
+                let app = app.clone();
+                async move {
+                    let base = match routes.iter().find(|route| route.matches(path.as_str())) {
+                        Some(route) => &route.target,
+                        None => &app,
+                    };
+                    let url = if query.is_empty() {
+                        format!("{}{}", base, path.as_str())
+                    } else {
+                        format!("{}{}?{}", base, path.as_str(), query)
+                    };
+                    forward(&client, method, &url, headers, body).await
+                }
+            },
+        );
+
+    warp::serve(proxy).run(addr).await
+}
+
+/// Forwards a single request to the given URL, returning the response (or a
+/// `502 Bad Gateway` if the backend couldn't be reached).
+async fn forward(
+    client: &reqwest::Client,
+    method: Method,
+    url: &str,
+    mut headers: HeaderMap,
+    body: Bytes,
+) -> Response<Bytes> {
+    // The client will set the right host for the backend
+    headers.remove("host");
+    for header in HOP_BY_HOP_HEADERS {
+        headers.remove(*header);
+    }
+    let res = client
+        .request(method, url)
+        .headers(headers)
+        .body(body)
+        .send()
+        .await;
+    let res = match res {
+        Ok(res) => res,
+        Err(err) => {
+            return Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Bytes::from(format!(
+                    "couldn't proxy request to '{}': {}",
+                    url, err
+                )))
+                .unwrap()
+        }
+    };
+
+    let mut builder = Response::builder().status(res.status());
+    for (name, value) in res.headers() {
+        if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            builder = builder.header(name, value);
+        }
+    }
+    let body = match res.bytes().await {
+        Ok(body) => body,
+        Err(err) => {
+            return Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Bytes::from(format!(
+                    "couldn't read proxied response from '{}': {}",
+                    url, err
+                )))
+                .unwrap()
+        }
+    };
+
+    builder.body(body).unwrap()
+}
@@ -1,14 +1,18 @@
 use crate::build::build_internal;
 use crate::cmd::{cfg_spinner, run_stage};
+use crate::dev_url::{is_loopback, open_when_ready, print_lan_qr};
 use crate::install::Tools;
 use crate::parse::{Opts, ServeOpts};
+use crate::ports::{find_free_port, note_port_change};
+use crate::prepare::add_cross_target;
+use crate::proxy::{get_proxy_routes, resolve_proxy_addr, run_proxy};
 use crate::thread::{spawn_thread, ThreadHandle};
-use crate::{errors::*, order_reload};
+use crate::{errors::*, get_dist_dir, order_reload};
 use console::{style, Emoji};
 use indicatif::{MultiProgress, ProgressBar};
 use std::env;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 
@@ -38,7 +42,7 @@ fn build_server(
     spinners: &MultiProgress,
     did_build: bool,
     exec: Arc<Mutex<String>>,
-    is_release: bool,
+    opts: &ServeOpts,
     tools: &Tools,
     global_opts: &Opts,
 ) -> Result<
@@ -49,6 +53,31 @@ fn build_server(
     let Opts {
         cargo_engine_args, ..
     } = global_opts.clone();
+    let is_release = opts.release;
+    let cross_target = opts.target.clone();
+
+    // If we're cross-compiling, Cargo (and any build scripts that compile C code)
+    // will need to know which linker to use
+    let mut envs = vec![(
+        "CARGO_TARGET_DIR".to_string(),
+        get_dist_dir(&dir, global_opts)
+            .join("target_engine")
+            .to_string_lossy()
+            .to_string(),
+    )];
+    if let (Some(target), Some(linker)) = (&opts.target, &opts.linker) {
+        envs.push((
+            format!(
+                "CARGO_TARGET_{}_LINKER",
+                target.to_uppercase().replace('-', "_")
+            ),
+            linker.to_string(),
+        ));
+        envs.push((
+            format!("CC_{}", target.replace('-', "_")),
+            linker.to_string(),
+        ));
+    }
     let num_steps = match did_build {
         true => 4,
         false => 2,
@@ -75,15 +104,21 @@ fn build_server(
                 vec![&format!(
                     // This sets Cargo to tell us everything, including the executable path to the
                     // server
-                    "{} build --message-format json {} {}",
+                    "{} build --message-format json {} {} {}",
                     tools.cargo_engine,
                     if is_release { "--release" } else { "" },
+                    match &cross_target {
+                        Some(target) => format!("--target {}", target),
+                        None => String::new(),
+                    },
                     cargo_engine_args
                 )],
                 &sb_target,
                 &sb_spinner,
                 &sb_msg,
-                vec![("CARGO_TARGET_DIR", "dist/target_engine")]
+                envs.iter()
+                    .map(|(key, val)| (key.as_str(), val.as_str()))
+                    .collect()
             )?);
 
             let msgs: Vec<&str> = stdout.trim().split('\n').collect();
@@ -132,7 +167,10 @@ fn build_server(
 fn run_server(
     exec: Arc<Mutex<String>>,
     dir: PathBuf,
+    dist: &Path,
     did_build: bool,
+    opts: &ServeOpts,
+    (host, port): (&str, u16),
 ) -> Result<i32, ExecutionError> {
     let num_steps = match did_build {
         true => 4,
@@ -155,6 +193,8 @@ fn run_server(
         .current_dir(&dir)
         // This needs to be provided in development, but not in production
         .env("PERSEUS_ENGINE_OPERATION", "serve")
+        // The server needs to know where the build artifacts are
+        .env("PERSEUS_DIST_DIR", dist)
         // We should be able to access outputs in case there's an error
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -163,13 +203,6 @@ fn run_server(
             cmd: server_exec_path,
             source: err,
         })?;
-    // Figure out what host/port the app will be live on (these have been set by the
-    // system)
-    let host = env::var("PERSEUS_HOST").unwrap_or_else(|_| "localhost".to_string());
-    let port = env::var("PERSEUS_PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse::<u16>()
-        .map_err(|err| ExecutionError::PortNotNumber { source: err })?;
     // Give the user a nice informational message
     println!(
         "  {} {} Your app is now live on <http://{host}:{port}>! To change this, re-run this command with different settings for `--host` and `--port`.",
@@ -178,6 +211,12 @@ fn run_server(
         host=host,
         port=port
     );
+    if opts.qr {
+        print_lan_qr(opts.public_host.as_deref(), port);
+    }
+    if opts.open {
+        open_when_ready(dist, host, port);
+    }
 
     // Wait on the child process to finish (which it shouldn't unless there's an
     // error), then perform error handling
@@ -211,14 +250,55 @@ pub fn serve(
     tools: &Tools,
     global_opts: &Opts,
 ) -> Result<(i32, Option<String>), ExecutionError> {
-    // Set the environment variables for the host and port
-    // NOTE Another part of this code depends on setting these in this way
-    env::set_var("PERSEUS_HOST", &opts.host);
-    env::set_var("PERSEUS_PORT", opts.port.to_string());
-
     let spinners = MultiProgress::new();
     let did_build = !opts.no_build;
     let should_run = !opts.no_run;
+    // Other devices can't reach a loopback address
+    let for_other_devices = opts.qr || opts.public_host.is_some();
+    let host = if should_run && for_other_devices && is_loopback(&opts.host) {
+        println!("Note: your app will be served on all network interfaces so that it can be reached from other devices.");
+        "0.0.0.0".to_string()
+    } else {
+        opts.host.to_string()
+    };
+    // If the port is taken, we'll fall back to the next free one (but only if we're
+    // actually going to run the server)
+    let port = if should_run {
+        let port = find_free_port(&host, opts.port)?;
+        note_port_change("server", opts.port, port);
+        port
+    } else {
+        opts.port
+    };
+    // If we're proxying some paths to backends, the app's server will sit behind
+    // the proxy on an internal port
+    let proxy_routes = if should_run {
+        get_proxy_routes(&dir, &opts.proxy)?
+    } else {
+        Vec::new()
+    };
+    let (app_host, app_port) = if proxy_routes.is_empty() {
+        (host.clone(), port)
+    } else {
+        let app_host = "127.0.0.1".to_string();
+        let app_port = find_free_port(&app_host, port.saturating_add(1))?;
+        (app_host, app_port)
+    };
+    // Set the environment variables for the host and port
+    env::set_var("PERSEUS_HOST", &app_host);
+    env::set_var("PERSEUS_PORT", app_port.to_string());
+    if let Some(public_host) = &opts.public_host {
+        env::set_var("PERSEUS_PUBLIC_HOST", public_host);
+    }
+    // A server built for another platform almost certainly can't be run here
+    if let Some(target) = &opts.target {
+        if should_run {
+            return Err(ExecutionError::CrossCompiledServerNotRunnable {
+                target: target.to_string(),
+            });
+        }
+        add_cross_target(target, global_opts)?;
+    }
     // We need to have a way of knowing what the executable path to the server is
     let exec = Arc::new(Mutex::new(String::new()));
     // We can begin building the server in a thread without having to deal with the
@@ -228,7 +308,7 @@ pub fn serve(
         &spinners,
         did_build,
         Arc::clone(&exec),
-        opts.release,
+        opts,
         tools,
         global_opts,
     )?;
@@ -264,7 +344,19 @@ pub fn serve(
 
     // Now actually run that executable path if we should
     if should_run {
-        let exit_code = run_server(Arc::clone(&exec), dir, did_build)?;
+        if !proxy_routes.is_empty() {
+            let addr = resolve_proxy_addr(&host, port)?;
+            tokio::task::spawn(run_proxy(addr, app_port, proxy_routes));
+        }
+        let dist = get_dist_dir(&dir, global_opts);
+        let exit_code = run_server(
+            Arc::clone(&exec),
+            dir,
+            &dist,
+            did_build,
+            opts,
+            (&host, port),
+        )?;
         Ok((exit_code, None))
     } else {
         // The user doesn't want to run the server, so we'll give them the executable
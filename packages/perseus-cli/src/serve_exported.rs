@@ -1,3 +1,5 @@
+use crate::errors::ExecutionError;
+use crate::ports::{find_free_port, note_port_change};
 use console::Emoji;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -5,9 +7,11 @@ use warp::Filter;
 
 static SERVING: Emoji<'_, '_> = Emoji("🛰️ ", "");
 
-/// Serves an exported app, assuming it's already been exported.
-pub async fn serve_exported(dir: PathBuf, host: String, port: u16) {
-    let dir = dir.join("dist/exported");
+/// Serves an exported app, assuming it's already been exported, given the
+/// `dist/` directory. If the given port is taken, the next free one will be
+/// used.
+pub async fn serve_exported(dist: PathBuf, host: String, port: u16) -> Result<(), ExecutionError> {
+    let dir = dist.join("exported");
     // We actually don't have to worry about HTML file extensions at all
     let files = warp::any().and(warp::fs::dir(dir));
     // Parse `localhost` into `127.0.0.1` (picky Rust `std`)
@@ -16,6 +20,9 @@ pub async fn serve_exported(dir: PathBuf, host: String, port: u16) {
     } else {
         host
     };
+    let requested_port = port;
+    let port = find_free_port(&host, requested_port)?;
+    note_port_change("exported app", requested_port, port);
     // Parse the host and port into an address
     let addr: SocketAddr = format!("{}:{}", host, port).parse().unwrap();
     // Notify the user that we're serving their files
@@ -26,5 +33,6 @@ pub async fn serve_exported(dir: PathBuf, host: String, port: u16) {
         port = port
     );
 
-    warp::serve(files).run(addr).await
+    warp::serve(files).run(addr).await;
+    Ok(())
 }
@@ -1,13 +1,15 @@
-use crate::cmd::run_cmd_directly;
+use crate::cmd::{quote_path, run_cmd_directly};
+use crate::dev_wasm::DevWasmOpts;
 use crate::install::Tools;
 use crate::parse::{Opts, SnoopServeOpts};
-use crate::{errors::*, get_user_crate_name};
+use crate::{errors::*, get_dist_dir, get_user_crate_name};
 use std::path::PathBuf;
 
 /// Runs static generation processes directly so the user can see detailed logs.
 /// This is commonly used for allowing users to see `dbg!` and the like in their
 /// builder functions.
 pub fn snoop_build(dir: PathBuf, tools: &Tools, global_opts: &Opts) -> Result<i32, ExecutionError> {
+    let dist = get_dist_dir(&dir, global_opts);
     run_cmd_directly(
         format!(
             "{} run {}",
@@ -16,7 +18,11 @@ pub fn snoop_build(dir: PathBuf, tools: &Tools, global_opts: &Opts) -> Result<i3
         &dir,
         vec![
             ("PERSEUS_ENGINE_OPERATION", "build"),
-            ("CARGO_TARGET_DIR", "dist/target_engine"),
+            ("PERSEUS_DIST_DIR", &dist.to_string_lossy()),
+            (
+                "CARGO_TARGET_DIR",
+                &dist.join("target_engine").to_string_lossy(),
+            ),
         ],
     )
 }
@@ -30,28 +36,44 @@ pub fn snoop_wasm_build(
     global_opts: &Opts,
 ) -> Result<i32, ExecutionError> {
     let crate_name = get_user_crate_name(&dir)?;
+    let dev_wasm = DevWasmOpts::get(&dir, global_opts)?;
+    let mut cargo_browser_args = global_opts.cargo_browser_args.clone();
+    let mut wasm_bindgen_args = global_opts.wasm_bindgen_args.clone();
+    dev_wasm.apply_args(&mut cargo_browser_args, &mut wasm_bindgen_args);
+    let rustflags = dev_wasm.rustflags();
+    let dist = get_dist_dir(&dir, global_opts);
+    let target_wasm = dist.join("target_wasm").to_string_lossy().to_string();
+    let mut envs = vec![("CARGO_TARGET_DIR", target_wasm.as_str())];
+    if let Some(rustflags) = &rustflags {
+        envs.push(("RUSTFLAGS", rustflags));
+    }
+    envs.extend(dev_wasm.envs());
 
     println!("[NOTE]: You should expect unused code warnings here! Don't worry about them, they're just a product of the target-gating.");
     let exit_code = run_cmd_directly(
         format!(
             "{} build --target wasm32-unknown-unknown {}",
-            tools.cargo_browser, global_opts.cargo_browser_args
+            tools.cargo_browser, cargo_browser_args
         ),
         &dir,
-        vec![("CARGO_TARGET_DIR", "dist/target_wasm")],
+        envs.clone(),
     )?;
     if exit_code != 0 {
         return Ok(exit_code);
     }
     run_cmd_directly(
         format!(
-            "{cmd} ./dist/target_wasm/wasm32-unknown-unknown/debug/{crate_name}.wasm --out-dir dist/pkg --out-name perseus_engine --target web {args}",
-            cmd=tools.wasm_bindgen,
-            args=global_opts.wasm_bindgen_args,
-            crate_name=crate_name
+            "{cmd} {wasm_path} --out-dir {pkg} --out-name perseus_engine --target web {args}",
+            cmd = tools.wasm_bindgen,
+            args = wasm_bindgen_args,
+            wasm_path = quote_path(&dist.join(format!(
+                "target_wasm/wasm32-unknown-unknown/debug/{}.wasm",
+                crate_name
+            ))),
+            pkg = quote_path(&dist.join("pkg")),
         ),
         &dir,
-        vec![("CARGO_TARGET_DIR", "dist/target_wasm")],
+        envs,
     )
 }
 
@@ -63,19 +85,28 @@ pub fn snoop_server(
     tools: &Tools,
     global_opts: &Opts,
 ) -> Result<i32, ExecutionError> {
+    let dist = get_dist_dir(&dir, global_opts);
+    let mut envs = vec![
+        // Unlike the `serve` command, we're both building and running here, so we provide the
+        // operation
+        ("PERSEUS_ENGINE_OPERATION", "serve".to_string()),
+        ("PERSEUS_DIST_DIR", dist.to_string_lossy().to_string()),
+        (
+            "CARGO_TARGET_DIR",
+            dist.join("target_engine").to_string_lossy().to_string(),
+        ),
+        ("PERSEUS_HOST", opts.host.to_string()),
+        ("PERSEUS_PORT", opts.port.to_string()),
+    ];
+    if opts.request_log {
+        envs.push(("PERSEUS_REQUEST_LOG", "true".to_string()));
+    }
     run_cmd_directly(
         format!(
             "{} run {}",
             tools.cargo_engine, global_opts.cargo_engine_args
         ),
         &dir,
-        vec![
-            ("PERSEUS_ENGINE_OPERATION", "serve"),
-            ("CARGO_TARGET_DIR", "dist/target_engine"),
-            ("PERSEUS_HOST", &opts.host),
-            ("PERSEUS_PORT", &opts.port.to_string()),
-        ], /* Unlike the `serve` command, we're both
-            * building and running here, so we provide
-            * the operation */
+        envs.iter().map(|(key, val)| (*key, val.as_str())).collect(),
     )
 }
@@ -1,8 +1,10 @@
 use crate::cmd::{cfg_spinner, run_stage};
 use crate::errors::*;
+use crate::get_dist_dir;
 use crate::install::Tools;
 use crate::parse::Opts;
 use crate::thread::{spawn_thread, ThreadHandle};
+use crate::tinker_edits::apply_tinker_edits;
 use console::{style, Emoji};
 use indicatif::{MultiProgress, ProgressBar};
 use std::path::PathBuf;
@@ -40,6 +42,7 @@ pub fn tinker_internal(
     let Opts {
         cargo_engine_args, ..
     } = global_opts.clone();
+    let dist = get_dist_dir(&dir, global_opts);
 
     // Tinkering message
     let tk_msg = format!(
@@ -62,7 +65,11 @@ pub fn tinker_internal(
                 &tk_msg,
                 vec![
                     ("PERSEUS_ENGINE_OPERATION", "tinker"),
-                    ("CARGO_TARGET_DIR", "dist/target_engine")
+                    ("PERSEUS_DIST_DIR", &dist.to_string_lossy()),
+                    (
+                        "CARGO_TARGET_DIR",
+                        &dist.join("target_engine").to_string_lossy()
+                    )
                 ]
             )?);
 
@@ -80,13 +87,16 @@ pub fn tinker_internal(
 pub fn tinker(dir: PathBuf, tools: &Tools, global_opts: &Opts) -> Result<i32, Error> {
     let spinners = MultiProgress::new();
 
-    let tk_thread = tinker_internal(dir, &spinners, 1, tools, global_opts)?;
+    let tk_thread = tinker_internal(dir.clone(), &spinners, 1, tools, global_opts)?;
     let tk_res = tk_thread
         .join()
         .map_err(|_| ExecutionError::ThreadWaitFailed)??;
     if tk_res != 0 {
         return Ok(tk_res);
     }
+    // Now apply the structured edits plugins declared (the engine can't do this
+    // itself, since it would be modifying its own code)
+    apply_tinker_edits(&dir, &get_dist_dir(&dir, global_opts))?;
 
     // We've handled errors in the component threads, so the exit code is now zero
     Ok(0)
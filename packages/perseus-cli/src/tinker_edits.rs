@@ -0,0 +1,531 @@
+use crate::errors::TinkerError;
+use crate::toml_doc::{extend_str_array, table_mut};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path};
+use toml_edit::{value, Array, Document, InlineTable};
+
+/// The features of the `perseus` crate that plugins can ask to be enabled.
+/// This has to be kept in sync with the core crate's manifest (which is
+/// checked by a test).
+const PERSEUS_FEATURES: &[&str] = &[
+    "client-helpers",
+    "data-sources",
+    "dflt-engine",
+    "formatting",
+    "hsr",
+    "hydrate",
+    "idb-freezing",
+    "live-reload",
+    "macros",
+    "preload-wasm-on-redirect",
+    "redis-store",
+    "syntax-highlighting",
+    "translator-fluent",
+    "translator-lightweight",
+    "wasm2js",
+];
+
+// These mirror the types plugins use to declare their edits in the core crate
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+enum TinkerTarget {
+    Both,
+    Engine,
+    Browser,
+}
+impl TinkerTarget {
+    /// Gets the path of keys to the manifest table for dependencies in this
+    /// target. These are the ones used in the default Perseus project
+    /// template.
+    fn path(&self) -> &'static [&'static str] {
+        match self {
+            Self::Both => &["dependencies"],
+            Self::Engine => &[
+                "target",
+                "cfg(not(target_arch = \"wasm32\"))",
+                "dependencies",
+            ],
+            Self::Browser => &["target", "cfg(target_arch = \"wasm32\")", "dependencies"],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TinkerDependency {
+    name: String,
+    version: String,
+    features: Vec<String>,
+    target: TinkerTarget,
+}
+
+#[derive(Deserialize)]
+struct TinkerSnippet {
+    file: String,
+    point: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct TinkerEdits {
+    dependencies: Vec<TinkerDependency>,
+    perseus_features: Vec<String>,
+    snippets: Vec<TinkerSnippet>,
+}
+
+/// A dependency merged from the declarations of every plugin that requires it.
+struct MergedDependency {
+    version: String,
+    features: BTreeSet<String>,
+    /// The first plugin that declared this dependency, for error messages.
+    plugin: String,
+}
+
+/// Validates and applies the structured edits declared by plugins in the
+/// `tinker_edits` action, which the engine will have written to
+/// `tinker_edits.json` in the given `dist/` directory. Edits that have already
+/// been applied will be skipped, so this is idempotent. If the engine didn't
+/// write any edits, this will do nothing.
+pub fn apply_tinker_edits(dir: &Path, dist: &Path) -> Result<(), TinkerError> {
+    let edits_path = dist.join("tinker_edits.json");
+    if !edits_path.exists() {
+        return Ok(());
+    }
+    let edits = fs::read_to_string(&edits_path).map_err(|err| TinkerError::ReadFileFailed {
+        path: edits_path.to_string_lossy().to_string(),
+        source: err,
+    })?;
+    // This is keyed by plugin name, and will be ordered alphabetically
+    let edits: BTreeMap<String, TinkerEdits> = serde_json::from_str(&edits)
+        .map_err(|err| TinkerError::ParseEditsFailed { source: err })?;
+
+    // Merge everything the plugins want to do, making sure they don't conflict
+    let mut dependencies: BTreeMap<(TinkerTarget, String), MergedDependency> = BTreeMap::new();
+    let mut perseus_features = BTreeSet::new();
+    let mut snippets: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for (plugin, edits) in edits {
+        for dep in edits.dependencies {
+            match dependencies.get_mut(&(dep.target, dep.name.clone())) {
+                Some(existing) if existing.version != dep.version => {
+                    return Err(TinkerError::ConflictingDependency {
+                        name: dep.name,
+                        first_plugin: existing.plugin.to_string(),
+                        first_version: existing.version.to_string(),
+                        second_plugin: plugin,
+                        second_version: dep.version,
+                    })
+                }
+                Some(existing) => existing.features.extend(dep.features),
+                None => {
+                    dependencies.insert(
+                        (dep.target, dep.name),
+                        MergedDependency {
+                            version: dep.version,
+                            features: dep.features.into_iter().collect(),
+                            plugin: plugin.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+        for feature in edits.perseus_features {
+            if !PERSEUS_FEATURES.contains(&feature.as_str()) {
+                return Err(TinkerError::UnknownPerseusFeature { plugin, feature });
+            }
+            perseus_features.insert(feature);
+        }
+        for snippet in edits.snippets {
+            // Plugins can only ever edit files inside the project
+            if !is_safe_project_path(&snippet.file) {
+                return Err(TinkerError::UnsafeSnippetPath {
+                    plugin,
+                    file: snippet.file,
+                });
+            }
+            snippets
+                .entry((snippet.file, snippet.point))
+                .or_default()
+                .push(snippet.content);
+        }
+    }
+
+    // Apply the changes to the manifest
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest =
+        fs::read_to_string(&manifest_path).map_err(|err| TinkerError::ReadFileFailed {
+            path: manifest_path.to_string_lossy().to_string(),
+            source: err,
+        })?;
+    let mut doc = manifest
+        .parse::<Document>()
+        .map_err(|err| TinkerError::ParseManifestFailed { source: err })?;
+    for ((target, name), dep) in dependencies {
+        add_dependency(&mut doc, target.path(), &name, &dep)?;
+    }
+    if !perseus_features.is_empty() {
+        enable_perseus_features(&mut doc, &perseus_features)?;
+    }
+    let new_manifest = doc.to_string();
+    if new_manifest != manifest {
+        fs::write(&manifest_path, new_manifest).map_err(|err| TinkerError::WriteFileFailed {
+            path: manifest_path.to_string_lossy().to_string(),
+            source: err,
+        })?;
+    }
+
+    // Inject the snippets
+    for ((file, point), contents) in snippets {
+        inject_snippets(dir, &file, &point, &contents)?;
+    }
+
+    Ok(())
+}
+
+/// Adds the given dependency to the manifest table at the given path, creating
+/// the table if necessary. If the user already depends on the crate there,
+/// this will do nothing, so the user's declaration always takes precedence.
+fn add_dependency(
+    manifest: &mut Document,
+    path: &[&str],
+    name: &str,
+    dep: &MergedDependency,
+) -> Result<(), TinkerError> {
+    let table =
+        table_mut(manifest, path).ok_or_else(|| TinkerError::ManifestSectionNotEditable {
+            section: path.join("."),
+        })?;
+    if table.contains_key(name) {
+        return Ok(());
+    }
+
+    let item = if dep.features.is_empty() {
+        value(dep.version.as_str())
+    } else {
+        let mut dep_table = InlineTable::new();
+        dep_table.insert("version", dep.version.as_str().into());
+        dep_table.insert(
+            "features",
+            Array::from_iter(dep.features.iter().map(|feature| feature.as_str())).into(),
+        );
+        value(dep_table)
+    };
+    table.insert(name, item);
+
+    Ok(())
+}
+
+/// Enables the given features on the user's `perseus` dependency, keeping any
+/// they've already enabled.
+fn enable_perseus_features(
+    manifest: &mut Document,
+    features: &BTreeSet<String>,
+) -> Result<(), TinkerError> {
+    let perseus = manifest
+        .get_mut("dependencies")
+        .and_then(|deps| deps.get_mut("perseus"))
+        .ok_or(TinkerError::PerseusDependencyNotEditable)?;
+    // A plain version string has to become a table before it can have features
+    if let Some(version) = perseus.as_str() {
+        let mut dep_table = InlineTable::new();
+        dep_table.insert("version", version.into());
+        *perseus = value(dep_table);
+    }
+    let changed = extend_str_array(
+        perseus
+            .as_table_like_mut()
+            .ok_or(TinkerError::PerseusDependencyNotEditable)?,
+        "features",
+        features.iter().map(|feature| feature.as_str()),
+    )
+    .ok_or(TinkerError::PerseusDependencyNotEditable)?;
+    // Keys added to inline tables otherwise pick up odd spacing
+    if let (true, Some(perseus)) = (changed, perseus.as_inline_table_mut()) {
+        perseus.fmt();
+    }
+
+    Ok(())
+}
+
+/// Checks if the given path is a relative path inside the project directory,
+/// without any `..`s or anything else that could make it point elsewhere.
+fn is_safe_project_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.contains(['\\', ':'])
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Injects the given snippets (in order) after the given injection point in
+/// the given file. Snippets that are already directly after the injection
+/// point (after any snippets before them) will be skipped, so this can be run
+/// repeatedly. The file's line endings will be preserved.
+fn inject_snippets(
+    dir: &Path,
+    file: &str,
+    point: &str,
+    contents: &[String],
+) -> Result<(), TinkerError> {
+    let path = dir.join(file);
+    let code = fs::read_to_string(&path).map_err(|err| TinkerError::ReadFileFailed {
+        path: file.to_string(),
+        source: err,
+    })?;
+    let marker = format!("perseus-tinker: {}", point);
+    let marker_idx = code
+        .lines()
+        .position(|line| {
+            line.trim()
+                .strip_prefix("//")
+                .map(|comment| comment.trim() == marker)
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| TinkerError::InjectionPointNotFound {
+            file: file.to_string(),
+            point: point.to_string(),
+        })?;
+
+    let mut lines: Vec<&str> = code.lines().collect();
+    let mut changed = false;
+    // Where the next snippet should be
+    let mut idx = marker_idx + 1;
+    for content in contents {
+        let snippet: Vec<&str> = content.lines().collect();
+        let is_present = lines
+            .get(idx..idx + snippet.len())
+            .map(|existing| {
+                existing
+                    .iter()
+                    .map(|line| line.trim())
+                    .eq(snippet.iter().map(|line| line.trim()))
+            })
+            .unwrap_or(false);
+        if !is_present {
+            lines.splice(idx..idx, snippet.iter().copied());
+            changed = true;
+        }
+        idx += snippet.len();
+    }
+    if !changed {
+        return Ok(());
+    }
+
+    let line_ending = if code.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut new_code = lines.join(line_ending);
+    if code.ends_with('\n') {
+        new_code.push_str(line_ending);
+    }
+    fs::write(&path, new_code).map_err(|err| TinkerError::WriteFileFailed {
+        path: file.to_string(),
+        source: err,
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(features: &[&str]) -> BTreeSet<String> {
+        features.iter().map(|feature| feature.to_string()).collect()
+    }
+
+    #[test]
+    fn only_allows_paths_inside_project() {
+        assert!(is_safe_project_path("src/main.rs"));
+        assert!(is_safe_project_path("src/templates/index.rs"));
+        assert!(!is_safe_project_path(""));
+        assert!(!is_safe_project_path("/etc/passwd"));
+        assert!(!is_safe_project_path("../other/src/main.rs"));
+        assert!(!is_safe_project_path("src/../../main.rs"));
+        assert!(!is_safe_project_path("./src/main.rs"));
+        assert!(!is_safe_project_path("src\\..\\..\\main.rs"));
+        assert!(!is_safe_project_path("C:/main.rs"));
+    }
+    #[test]
+    fn rejects_unsafe_edits() {
+        let tmp = assert_fs::TempDir::new().unwrap();
+        let dir = tmp.path();
+        let dist = dir.join("dist");
+        fs::create_dir_all(&dist).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[dependencies]\nperseus = \"0.4\"\n",
+        )
+        .unwrap();
+
+        let edits = |snippet_file: &str, feature: &str| {
+            serde_json::json!({
+                "test-plugin": {
+                    "dependencies": [],
+                    "perseus_features": [feature],
+                    "snippets": [{ "file": snippet_file, "point": "imports", "content": "use foo;" }]
+                }
+            })
+            .to_string()
+        };
+        fs::write(
+            dist.join("tinker_edits.json"),
+            edits("../main.rs", "hydrate"),
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_tinker_edits(dir, &dist),
+            Err(TinkerError::UnsafeSnippetPath { file, .. }) if file == "../main.rs"
+        ));
+        fs::write(
+            dist.join("tinker_edits.json"),
+            edits("src/main.rs", "not-a-feature"),
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_tinker_edits(dir, &dist),
+            Err(TinkerError::UnknownPerseusFeature { feature, .. }) if feature == "not-a-feature"
+        ));
+        // Nothing should have been changed
+        assert_eq!(
+            fs::read_to_string(dir.join("Cargo.toml")).unwrap(),
+            "[dependencies]\nperseus = \"0.4\"\n"
+        );
+    }
+    #[test]
+    fn injects_snippets_once() {
+        let tmp = assert_fs::TempDir::new().unwrap();
+        let dir = tmp.path();
+        let contents = vec![
+            "use foo;".to_string(),
+            "fn bar() {\n    foo();\n}\n".to_string(),
+        ];
+        // The snippets are mentioned elsewhere, but not at the injection point
+        fs::write(
+            dir.join("main.rs"),
+            "// use foo;\n    // perseus-tinker: imports\nfn main() {}\n",
+        )
+        .unwrap();
+        inject_snippets(dir, "main.rs", "imports", &contents).unwrap();
+        let injected = "// use foo;\n    // perseus-tinker: imports\nuse foo;\nfn bar() {\n    foo();\n}\nfn main() {}\n";
+        assert_eq!(fs::read_to_string(dir.join("main.rs")).unwrap(), injected);
+        inject_snippets(dir, "main.rs", "imports", &contents).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("main.rs")).unwrap(), injected);
+    }
+    #[test]
+    fn injects_snippets_with_file_line_endings() {
+        let tmp = assert_fs::TempDir::new().unwrap();
+        let dir = tmp.path();
+        let contents = vec!["use foo;".to_string()];
+        fs::write(
+            dir.join("main.rs"),
+            "// perseus-tinker: imports\r\nfn main() {}\r\n",
+        )
+        .unwrap();
+        inject_snippets(dir, "main.rs", "imports", &contents).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("main.rs")).unwrap(),
+            "// perseus-tinker: imports\r\nuse foo;\r\nfn main() {}\r\n"
+        );
+
+        fs::write(dir.join("lib.rs"), "// perseus-tinker: imports").unwrap();
+        inject_snippets(dir, "lib.rs", "imports", &contents).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("lib.rs")).unwrap(),
+            "// perseus-tinker: imports\nuse foo;"
+        );
+    }
+    #[test]
+    fn known_features_match_core_crate() {
+        let manifest = include_str!("../../perseus/Cargo.toml")
+            .parse::<Document>()
+            .unwrap();
+        let mut features = manifest["features"]
+            .as_table()
+            .unwrap()
+            .iter()
+            .map(|(feature, _)| feature)
+            .filter(|feature| *feature != "default")
+            .collect::<Vec<_>>();
+        features.sort_unstable();
+        assert_eq!(features, PERSEUS_FEATURES);
+    }
+    #[test]
+    fn enables_features_on_version_string() {
+        let mut doc = "[dependencies]\nperseus = \"0.4\"\n"
+            .parse::<Document>()
+            .unwrap();
+        enable_perseus_features(&mut doc, &features(&["hydrate"])).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[dependencies]\nperseus = { version = \"0.4\", features = [\"hydrate\"] }\n"
+        );
+    }
+    #[test]
+    fn enables_features_without_touching_default_features() {
+        let mut doc = "[dependencies]\nperseus = { version = \"0.4\", default-features = false, features = [ \"macros\" ] } # Comment\n"
+            .parse::<Document>()
+            .unwrap();
+        enable_perseus_features(&mut doc, &features(&["hydrate", "macros"])).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[dependencies]\nperseus = { version = \"0.4\", default-features = false, features = [\"macros\", \"hydrate\"] } # Comment\n"
+        );
+
+        let mut doc = "[dependencies]\nperseus = { version = \"0.4\", default-features = false }\n"
+            .parse::<Document>()
+            .unwrap();
+        enable_perseus_features(&mut doc, &features(&["hydrate"])).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[dependencies]\nperseus = { version = \"0.4\", default-features = false, features = [\"hydrate\"] }\n"
+        );
+    }
+    #[test]
+    fn enables_features_in_dependency_table() {
+        let mut doc = "[dependencies.perseus]\nversion = \"0.4\"\n"
+            .parse::<Document>()
+            .unwrap();
+        enable_perseus_features(&mut doc, &features(&["hydrate"])).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[dependencies.perseus]\nversion = \"0.4\"\nfeatures = [\"hydrate\"]\n"
+        );
+
+        let mut doc = "[dependencies]\nsycamore = \"0.8\"\n"
+            .parse::<Document>()
+            .unwrap();
+        assert!(matches!(
+            enable_perseus_features(&mut doc, &features(&["hydrate"])),
+            Err(TinkerError::PerseusDependencyNotEditable)
+        ));
+    }
+    #[test]
+    fn adds_dependencies_without_overriding_the_user() {
+        let mut doc = "[dependencies]\nserde = \"1.0.100\"\n"
+            .parse::<Document>()
+            .unwrap();
+        let dep = |version: &str, features: &[&str]| MergedDependency {
+            version: version.to_string(),
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            plugin: "test-plugin".to_string(),
+        };
+        add_dependency(
+            &mut doc,
+            TinkerTarget::Both.path(),
+            "serde",
+            &dep("1", &["derive"]),
+        )
+        .unwrap();
+        add_dependency(
+            &mut doc,
+            TinkerTarget::Engine.path(),
+            "tokio",
+            &dep("1", &["fs", "macros"]),
+        )
+        .unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[dependencies]\nserde = \"1.0.100\"\n\n[target.\"cfg(not(target_arch = \\\"wasm32\\\"))\".dependencies]\ntokio = { version = \"1\", features = [\"fs\", \"macros\"] }\n"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use toml_edit::{Array, Document, Item, Table, TableLike, Value};
+
+// Any TOML files in the user's project (like `Cargo.toml` and `Perseus.toml`)
+// should be edited through these, so that their formatting and comments are
+// preserved, and so that we edit actual keys rather than anything that happens
+// to look like them
+
+/// Gets the table at the given path of keys in the given document, creating
+/// it (and any tables above it) if it doesn't exist. Tables created above the
+/// target won't get their own headers (e.g. creating `target.x.dependencies`
+/// will only add a `[target.x.dependencies]` header). If something at that
+/// path already exists, but isn't a table, this will return `None`.
+pub fn table_mut<'a>(doc: &'a mut Document, path: &[&str]) -> Option<&'a mut dyn TableLike> {
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for (idx, key) in path.iter().enumerate() {
+        let is_last = idx == path.len() - 1;
+        table = table
+            .entry(key)
+            .or_insert_with(|| {
+                let mut new_table = Table::new();
+                new_table.set_implicit(!is_last);
+                Item::Table(new_table)
+            })
+            .as_table_like_mut()?;
+    }
+
+    Some(table)
+}
+
+/// Adds the given strings to the array at the given key of the given table,
+/// creating the array if it doesn't exist, and skipping any strings that are
+/// already in it. This returns whether or not anything was added, or `None`
+/// if the key exists but isn't an array.
+pub fn extend_str_array<'a>(
+    table: &mut dyn TableLike,
+    key: &str,
+    vals: impl IntoIterator<Item = &'a str>,
+) -> Option<bool> {
+    let array = table
+        .entry(key)
+        .or_insert_with(|| Item::Value(Value::Array(Array::new())))
+        .as_array_mut()?;
+
+    let mut changed = false;
+    for val in vals {
+        if !array.iter().any(|existing| existing.as_str() == Some(val)) {
+            array.push(val);
+            changed = true;
+        }
+    }
+    // Otherwise, new values will pick up odd spacing from the old last value
+    if changed {
+        array.fmt();
+    }
+
+    Some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_nested_tables_without_extra_headers() {
+        let mut doc = "[package]\nname = \"app\"\n".parse::<Document>().unwrap();
+        let deps = table_mut(&mut doc, &["target", "cfg(unix)", "dependencies"]).unwrap();
+        deps.insert("serde", toml_edit::value("1"));
+        assert_eq!(
+            doc.to_string(),
+            "[package]\nname = \"app\"\n\n[target.\"cfg(unix)\".dependencies]\nserde = \"1\"\n"
+        );
+    }
+    #[test]
+    fn finds_existing_tables() {
+        let mut doc = "# Pins\n[tools] # Comment\nwasm-bindgen = \"0.2.83\"\n"
+            .parse::<Document>()
+            .unwrap();
+        let tools = table_mut(&mut doc, &["tools"]).unwrap();
+        tools.insert("wasm-bindgen", toml_edit::value("0.2.84"));
+        assert_eq!(
+            doc.to_string(),
+            "# Pins\n[tools] # Comment\nwasm-bindgen = \"0.2.84\"\n"
+        );
+        // Something that isn't a table
+        let mut doc = "tools = 5\n".parse::<Document>().unwrap();
+        assert!(table_mut(&mut doc, &["tools"]).is_none());
+    }
+    #[test]
+    fn extends_arrays_without_duplicates() {
+        let mut doc = "dep = { default-features = false, features = [ \"a\" ] }\n"
+            .parse::<Document>()
+            .unwrap();
+        let dep = doc["dep"].as_table_like_mut().unwrap();
+        assert_eq!(extend_str_array(dep, "features", ["a", "b"]), Some(true));
+        assert_eq!(extend_str_array(dep, "features", ["b"]), Some(false));
+        assert_eq!(extend_str_array(dep, "default-features", ["c"]), None);
+        assert_eq!(
+            doc.to_string(),
+            "dep = { default-features = false, features = [\"a\", \"b\"] }\n"
+        );
+    }
+}
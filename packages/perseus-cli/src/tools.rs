@@ -0,0 +1,297 @@
+use crate::errors::*;
+use crate::get_dist_dir;
+use crate::install::{get_tools_dir, Tool, ToolStatus, ToolType, ToolsConfig};
+use crate::parse::{Opts, ToolsSubcommand};
+use crate::toml_doc::table_mut;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use toml_edit::{value, Document};
+
+/// Runs one of the subcommands for managing the CLI's external tools,
+/// returning an exit code.
+pub async fn tools(dir: &Path, subcmd: &ToolsSubcommand, global_opts: &Opts) -> Result<i32, Error> {
+    let target = get_tools_dir(
+        &get_dist_dir(dir, global_opts),
+        global_opts.no_system_tools_cache,
+    )?;
+    let config = ToolsConfig::from_project(dir)?;
+    let tools = [
+        Tool::new_with_config(
+            ToolType::WasmBindgen,
+            &global_opts.wasm_bindgen_path,
+            &global_opts.wasm_bindgen_version,
+            dir,
+            &config,
+        ),
+        Tool::new_with_config(
+            ToolType::WasmOpt,
+            &global_opts.wasm_opt_path,
+            &global_opts.wasm_opt_version,
+            dir,
+            &config,
+        ),
+    ];
+
+    let exit_code = match subcmd {
+        ToolsSubcommand::List => {
+            list_tools(&tools, &target)?;
+            0
+        }
+        ToolsSubcommand::Update => {
+            update_tools(dir, &tools, &target).await?;
+            0
+        }
+        ToolsSubcommand::Verify => verify_tools(&tools, &target)?,
+    };
+    Ok(exit_code)
+}
+
+/// Prints the pinned and installed versions of each tool, and which one will
+/// be used.
+fn list_tools(tools: &[Tool], target: &Path) -> Result<(), InstallError> {
+    for tool in tools {
+        println!("{}:", tool.name);
+        match &tool.user_given_version {
+            Some(version) => println!("  pinned: {}", version),
+            None => println!("  pinned: none (the latest version will be used)"),
+        }
+        let installed = tool.get_installed_versions(target)?;
+        if installed.is_empty() {
+            println!("  installed: none");
+        } else {
+            println!("  installed: {}", installed.join(", "));
+        }
+        match tool.get_status(target)? {
+            ToolStatus::Available(path) => println!("  using: {}", path),
+            ToolStatus::NeedsInstall { version, .. } => {
+                println!("  using: {} (will be installed on the next build)", version)
+            }
+            ToolStatus::NeedsLatestInstall => {
+                println!("  using: latest (will be installed on the next build)")
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Installs the latest version of each tool and pins it in `Perseus.toml`.
+/// Tools the user has given a path to, or that are vendored, will be left
+/// alone.
+async fn update_tools(dir: &Path, tools: &[Tool], target: &Path) -> Result<(), InstallError> {
+    let mut pins = Vec::new();
+    for tool in tools {
+        if tool.user_given_path.is_some() {
+            println!("Skipping '{}', since a path to it was given.", tool.name);
+            continue;
+        }
+        if tool.vendored_archive.is_some() {
+            println!(
+                "Skipping '{}', since it's vendored (replace the archive and its version in `Perseus.toml` to update it).",
+                tool.name
+            );
+            continue;
+        }
+        let latest_version = tool.get_latest_version().await?;
+        let artifact_name = match tool.get_artifact_name(&latest_version) {
+            Some(artifact_name) => artifact_name,
+            None => {
+                println!(
+                    "Skipping '{}', since there are no precompiled binaries for your platform.",
+                    tool.name
+                );
+                continue;
+            }
+        };
+        if !tool
+            .get_installed_versions(target)?
+            .contains(&latest_version)
+        {
+            tool.install_version(&latest_version, &artifact_name, target)
+                .await?;
+        }
+        println!("Pinned '{}' to {}.", tool.name, latest_version);
+        pins.push((tool.name.to_string(), latest_version));
+    }
+    write_pins(dir, &pins)
+}
+
+/// Checks that each tool is pinned, installed, and reports the pinned version,
+/// returning an exit code.
+fn verify_tools(tools: &[Tool], target: &Path) -> Result<i32, InstallError> {
+    let mut failed = false;
+    for tool in tools {
+        let version = match &tool.user_given_version {
+            Some(version) => version,
+            None => {
+                println!(
+                    "[FAIL] '{}' isn't pinned in `Perseus.toml`, so different builds may use different versions.",
+                    tool.name
+                );
+                failed = true;
+                continue;
+            }
+        };
+        let path = match tool.get_status(target)? {
+            ToolStatus::Available(path) => path,
+            _ => {
+                println!(
+                    "[FAIL] '{}' {} isn't installed (it will be installed on the next build).",
+                    tool.name, version
+                );
+                failed = true;
+                continue;
+            }
+        };
+        let output = match Command::new(&path).arg("--version").output() {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            }
+            _ => {
+                println!("[FAIL] '{}' at '{}' couldn't be run.", tool.name, path);
+                failed = true;
+                continue;
+            }
+        };
+        if reports_version(&output, version) {
+            println!("[OK] '{}' {} ({})", tool.name, version, path);
+        } else {
+            println!(
+                "[FAIL] '{}' at '{}' reports version '{}', but {} is pinned.",
+                tool.name, path, output, version
+            );
+            failed = true;
+        }
+    }
+
+    Ok(if failed { 1 } else { 0 })
+}
+
+/// Checks if the given output of a tool's `--version` flag reports exactly the
+/// given version (so `0.2.8` won't match `0.2.84`). Tools print their versions
+/// in different ways (e.g. `wasm-bindgen 0.2.84`, or `wasm-opt version 110
+/// (version_110)`), so this looks for any word in the output that's the
+/// version.
+fn reports_version(output: &str, version: &str) -> bool {
+    // Binaryen versions are tagged like `version_110`, but `wasm-opt` also reports
+    // `110`
+    let normalize = |version: &str| {
+        let version = version.trim_start_matches("version_");
+        version.strip_prefix('v').unwrap_or(version).to_string()
+    };
+    let expected = normalize(version);
+    output
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+        .any(|word| normalize(word) == expected)
+}
+
+/// Writes the given tool versions to the `[tools]` section of `Perseus.toml`,
+/// creating it if necessary. Any existing pins for those tools will be
+/// replaced, and everything else in the file will be left alone.
+fn write_pins(dir: &Path, pins: &[(String, String)]) -> Result<(), InstallError> {
+    if pins.is_empty() {
+        return Ok(());
+    }
+    let path = dir.join("Perseus.toml");
+    let contents = if path.exists() {
+        fs::read_to_string(&path)
+            .map_err(|err| InstallError::ReadToolsConfigFailed { source: err })?
+    } else {
+        String::new()
+    };
+
+    fs::write(&path, update_pins(&contents, pins)?)
+        .map_err(|err| InstallError::WriteToolsConfigFailed { source: err })
+}
+
+/// Updates the given tool versions in the given contents of `Perseus.toml`,
+/// returning the new contents.
+fn update_pins(contents: &str, pins: &[(String, String)]) -> Result<String, InstallError> {
+    let mut doc = contents
+        .parse::<Document>()
+        .map_err(|err| InstallError::ParseToolsConfigForUpdateFailed { source: err })?;
+    let tools = table_mut(&mut doc, &["tools"]).ok_or(InstallError::ToolsConfigNotEditable)?;
+    for (name, version) in pins {
+        // `wasm-opt` can also be pinned as `binaryen`, and we should update that pin if
+        // the user's used it
+        let key =
+            if name == "wasm-opt" && !tools.contains_key(name) && tools.contains_key("binaryen") {
+                "binaryen"
+            } else {
+                name
+            };
+        // Replacing the value directly keeps any comments above the key
+        match tools.get_mut(key) {
+            Some(pin) => *pin = value(version.as_str()),
+            None => {
+                tools.insert(key, value(version.as_str()));
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pins(pins: &[(&str, &str)]) -> Vec<(String, String)> {
+        pins.iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn adds_tools_section() {
+        assert_eq!(
+            update_pins(
+                "[proxy]\n\"/api\" = \"http://localhost:4000\"\n",
+                &pins(&[("wasm-bindgen", "0.2.84")])
+            )
+            .unwrap(),
+            "[proxy]\n\"/api\" = \"http://localhost:4000\"\n\n[tools]\nwasm-bindgen = \"0.2.84\"\n"
+        );
+        assert_eq!(
+            update_pins("", &pins(&[("wasm-bindgen", "0.2.84")])).unwrap(),
+            "[tools]\nwasm-bindgen = \"0.2.84\"\n"
+        );
+    }
+    #[test]
+    fn replaces_existing_pins() {
+        let contents = "[tools]\n# Keep in sync with CI\nwasm-bindgen = \"0.2.83\"\nbinaryen = \"109\"\n\n[dev]\noptimize = true\n";
+        assert_eq!(
+            update_pins(
+                contents,
+                &pins(&[("wasm-bindgen", "0.2.84"), ("wasm-opt", "110")])
+            )
+            .unwrap(),
+            "[tools]\n# Keep in sync with CI\nwasm-bindgen = \"0.2.84\"\nbinaryen = \"110\"\n\n[dev]\noptimize = true\n"
+        );
+    }
+    #[test]
+    fn matches_versions_exactly() {
+        assert!(reports_version("wasm-bindgen 0.2.84", "0.2.84"));
+        assert!(!reports_version("wasm-bindgen 0.2.84", "0.2.8"));
+        assert!(!reports_version("wasm-bindgen 0.2.8", "0.2.84"));
+        assert!(reports_version(
+            "wasm-opt version 110 (version_110)",
+            "version_110"
+        ));
+        assert!(reports_version("wasm-opt version 110", "110"));
+        assert!(!reports_version("wasm-opt version 110", "11"));
+        assert!(!reports_version("wasm-opt version 1100", "version_110"));
+    }
+    #[test]
+    fn fails_on_invalid_config() {
+        assert!(matches!(
+            update_pins("tools = 5\n", &pins(&[("wasm-opt", "110")])),
+            Err(InstallError::ToolsConfigNotEditable)
+        ));
+        assert!(matches!(
+            update_pins("[tools\n", &pins(&[("wasm-opt", "110")])),
+            Err(InstallError::ParseToolsConfigForUpdateFailed { .. })
+        ));
+    }
+}
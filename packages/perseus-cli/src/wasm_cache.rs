@@ -0,0 +1,82 @@
+use crate::cmd::{fail_spinner, run_cmd, run_stage, succeed_spinner};
+use crate::errors::ExecutionError;
+use indicatif::ProgressBar;
+use std::fs;
+use std::path::Path;
+
+/// The file (in `dist/pkg/`) that records the key of the Wasm artifact (and
+/// post-processing commands) that the current contents of `dist/pkg/` were
+/// generated from.
+static CACHE_KEY_FILE: &str = ".perseus_cache_key";
+/// The files (in `dist/pkg/`) that post-processing must have generated for a
+/// cached result to be usable.
+static CACHED_FILES: [&str; 2] = ["perseus_engine.js", "perseus_engine_bg.wasm"];
+
+/// Runs the stage of building the app to Wasm, which involves building the
+/// Wasm artifact itself with the given command, and then post-processing it
+/// with the given commands (`wasm-bindgen`, and `wasm-opt` in release mode).
+/// Post-processing is the slowest part of this, so it will be skipped if the
+/// artifact (at the given path) and the post-processing commands are the same
+/// as last time, since its results will already be in `dist/pkg/` (which
+/// should be given as `pkg`).
+///
+/// This otherwise behaves exactly like [`run_stage`].
+#[allow(clippy::too_many_arguments)]
+pub fn run_wasm_stage(
+    build_cmd: &str,
+    post_cmds: Vec<String>,
+    wasm_path: &Path,
+    pkg: &Path,
+    target: &Path,
+    spinner: &ProgressBar,
+    message: &str,
+    envs: Vec<(&str, &str)>,
+) -> Result<(String, String, i32), ExecutionError> {
+    let (stdout, stderr, exit_code) =
+        run_cmd(build_cmd.to_string(), target, envs.to_vec(), || {
+            fail_spinner(spinner, message);
+        })?;
+    if exit_code != 0 {
+        return Ok((stdout, stderr, 1));
+    }
+
+    let key = get_cache_key(wasm_path, &post_cmds);
+    let cached_key = fs::read_to_string(pkg.join(CACHE_KEY_FILE)).ok();
+    if key.is_some() && key == cached_key && CACHED_FILES.iter().all(|file| pkg.join(file).exists())
+    {
+        succeed_spinner(spinner, message);
+        return Ok((stdout, stderr, 0));
+    }
+    // If post-processing fails partway through, the old key would be wrong
+    let _ = fs::remove_file(pkg.join(CACHE_KEY_FILE));
+
+    let res = run_stage(
+        post_cmds.iter().map(|cmd| cmd.as_str()).collect(),
+        target,
+        spinner,
+        message,
+        envs,
+    )?;
+    if let (0, Some(key)) = (res.2, key) {
+        // If this fails, we'll just post-process again next time
+        let _ = fs::write(pkg.join(CACHE_KEY_FILE), key);
+    }
+
+    Ok(res)
+}
+
+/// Computes a key for the given Wasm artifact and the commands that will
+/// post-process it, which will change if either of them does. This uses the
+/// same FNV-1a hash as the render configuration's checksum. If the artifact
+/// can't be read, this will return `None`.
+fn get_cache_key(wasm_path: &Path, post_cmds: &[String]) -> Option<String> {
+    let wasm = fs::read(wasm_path).ok()?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let cmds = post_cmds.join("\n");
+    for byte in wasm.iter().chain(cmds.as_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    Some(format!("{:016x}", hash))
+}
@@ -0,0 +1,322 @@
+use perseus::i18n::TRANSLATOR_FILE_EXT;
+use perseus::plugins::{empty_control_actions_registrar, Plugin, PluginAction, PluginEnv, Plugins};
+use perseus::session::{get_session, MemorySessionStore, Sessions};
+use perseus::{
+    AppError, ErrorPages, PerseusApp, Redirect, RenderFnResultWithCause, Request,
+    StateGeneratorInfo, Template,
+};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use sycamore::prelude::*;
+
+/// The default locale of the test app.
+pub(crate) const DEFAULT_LOCALE: &str = "en-US";
+/// The other locale the test app supports.
+pub(crate) const OTHER_LOCALE: &str = "fr-FR";
+/// A locale the test app doesn't support.
+pub(crate) const UNSUPPORTED_LOCALE: &str = "de-DE";
+/// The contents of the file in the test app's static directory, which is
+/// served at `/.perseus/static/file.txt`.
+pub(crate) const STATIC_FILE: &str = "perseus-conformance-static";
+/// The URL of the test app's static alias.
+pub(crate) const STATIC_ALIAS_URL: &str = "/alias.txt";
+/// The contents of the file the test app's static alias points to.
+pub(crate) const STATIC_ALIAS_FILE: &str = "perseus-conformance-alias";
+/// The name of the template that panics while it's being rendered.
+pub(crate) const PANIC_TEMPLATE: &str = "panic";
+/// The name of the template whose request state takes longer than its
+/// timeout.
+pub(crate) const TIMEOUT_TEMPLATE: &str = "timeout";
+/// The name of the template whose request state fails with an app-defined
+/// error.
+pub(crate) const APP_ERROR_TEMPLATE: &str = "app-error";
+/// The kind of app-defined error that template fails with.
+pub(crate) const APP_ERROR_KIND: &str = "perseus-conformance-kind";
+/// The status code that template's error is sent with.
+pub(crate) const APP_ERROR_STATUS: u16 = 429;
+/// The name of the template whose rendering makes the test app's plugin panic.
+pub(crate) const PLUGIN_PANIC_TEMPLATE: &str = "plugin-panic";
+/// The name of the template whose request state reads the user's session.
+pub(crate) const SESSION_TEMPLATE: &str = "session";
+/// The name of the template whose request state redirects elsewhere.
+pub(crate) const REDIRECT_TEMPLATE: &str = "redirect";
+/// The name of the template whose request state logs the user in and then
+/// redirects elsewhere.
+pub(crate) const LOGIN_REDIRECT_TEMPLATE: &str = "login-redirect";
+/// The name of the template whose request state logs the user in and then
+/// fails with an app-defined error.
+pub(crate) const LOGIN_ERROR_TEMPLATE: &str = "login-error";
+/// The name of the template whose build state redirects elsewhere.
+pub(crate) const BUILD_REDIRECT_TEMPLATE: &str = "build-redirect";
+/// The name of the template whose request state redirects with a status
+/// that isn't a redirect.
+pub(crate) const INVALID_REDIRECT_TEMPLATE: &str = "invalid-redirect";
+/// Where the test app's redirecting templates send the user.
+pub(crate) const REDIRECT_LOCATION: &str = "/en-US/about";
+/// The contents of the placeholder JS bundle.
+pub(crate) const JS_BUNDLE: &str = "// perseus-conformance-bundle";
+
+/// Gets the text a page of the test app renders, which will be in both its
+/// HTML and its page data.
+pub(crate) fn page_marker(template: &str) -> String {
+    format!("perseus-conformance-page-{}", template)
+}
+
+/// Gets the text an error page of the test app renders for the given status.
+pub(crate) fn error_marker(status: u16) -> String {
+    format!("perseus-conformance-error-{}", status)
+}
+
+/// Gets the text the error page for the test app's app-defined error kind
+/// renders.
+pub(crate) fn app_error_marker() -> String {
+    format!("perseus-conformance-app-error-{}", APP_ERROR_KIND)
+}
+
+/// Creates the app the conformance suite is run against. This has two
+/// templates (`index` and `about`), a template that panics, a template that
+/// times out, a template that fails with an app-defined error, a
+/// template that reads the user's session, a template that makes a plugin
+/// panic, templates that redirect elsewhere (including one that logs the user
+/// in first, and one that does so invalidly), a template that logs the user in
+/// and then fails, two locales, a static directory, a static alias, CSRF
+/// protection, and error pages that make their status codes (and the
+/// app-defined error's kind) obvious.
+pub(crate) fn get_test_app() -> PerseusApp<SsrNode> {
+    PerseusApp::new()
+        .template(|| get_template("index"))
+        .template(|| get_template("about"))
+        .template(|| {
+            Template::new(PANIC_TEMPLATE)
+                .request_state_fn(panicking_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(TIMEOUT_TEMPLATE)
+                .request_state_fn(slow_request_state)
+                .state_fn_timeout(Duration::from_millis(100))
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(APP_ERROR_TEMPLATE)
+                .request_state_fn(failing_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| get_template(PLUGIN_PANIC_TEMPLATE))
+        .plugins(Plugins::new().plugin(get_panicking_plugin, ()))
+        .template(|| {
+            Template::new(SESSION_TEMPLATE)
+                .request_state_fn(session_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(REDIRECT_TEMPLATE)
+                .request_state_fn(redirecting_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(LOGIN_REDIRECT_TEMPLATE)
+                .request_state_fn(login_redirecting_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(LOGIN_ERROR_TEMPLATE)
+                .request_state_fn(login_failing_request_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(BUILD_REDIRECT_TEMPLATE)
+                .build_state_fn(redirecting_build_state)
+                .template(|_, _| View::empty())
+        })
+        .template(|| {
+            Template::new(INVALID_REDIRECT_TEMPLATE)
+                .request_state_fn(invalid_redirecting_request_state)
+                .template(|_, _| View::empty())
+        })
+        .error_pages(|| {
+            let mut error_pages = ErrorPages::new(|cx, _, status, _, _| {
+                view! { cx,
+                    p { (error_marker(status)) }
+                }
+            });
+            error_pages.add_app_page(APP_ERROR_KIND, |cx, _, _, _| {
+                view! { cx,
+                    p { (app_error_marker()) }
+                }
+            });
+            error_pages
+        })
+        .locales_and_translations_manager(DEFAULT_LOCALE, &[OTHER_LOCALE])
+        .static_dir("static")
+        .static_alias(STATIC_ALIAS_URL, "alias.txt")
+        .sessions(Sessions::new(MemorySessionStore::default()))
+        .csrf_protection(true)
+}
+
+/// Creates a template that just renders its marker.
+fn get_template(name: &str) -> Template<SsrNode> {
+    let marker = page_marker(name);
+    Template::new(name).template(move |cx, _| {
+        let marker = marker.clone();
+        view! { cx,
+            p { (marker) }
+        }
+    })
+}
+
+/// Creates a plugin that panics before the plugin panic template is
+/// rendered, which shouldn't stop any other pages from being rendered
+/// afterward.
+fn get_panicking_plugin() -> Plugin<SsrNode, ()> {
+    Plugin::new(
+        "perseus-conformance-plugin",
+        |mut actions| {
+            actions
+                .server_actions
+                .before_page_render
+                .register_plugin("perseus-conformance-plugin", |info, _| {
+                    if info.template_name == PLUGIN_PANIC_TEMPLATE {
+                        panic!("perseus-conformance-plugin-panic");
+                    }
+                });
+            actions
+        },
+        empty_control_actions_registrar,
+        PluginEnv::Server,
+    )
+}
+
+/// A request state function that always panics, which should be turned into
+/// a `500` by the server.
+async fn panicking_request_state(
+    _info: StateGeneratorInfo,
+    _req: Request,
+) -> RenderFnResultWithCause<String> {
+    panic!("perseus-conformance-panic")
+}
+
+/// A request state function that takes far longer than its template's
+/// timeout, and so should be cancelled.
+async fn slow_request_state(
+    _info: StateGeneratorInfo,
+    _req: Request,
+) -> RenderFnResultWithCause<String> {
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    Ok(String::new())
+}
+
+/// A request state function that always fails with a retryable app-defined
+/// error.
+async fn failing_request_state(
+    _info: StateGeneratorInfo,
+    _req: Request,
+) -> RenderFnResultWithCause<String> {
+    Err(
+        AppError::new(APP_ERROR_KIND, APP_ERROR_STATUS, "perseus-conformance-app-error")
+            .retryable(true)
+            .into(),
+    )
+}
+
+/// A request state function that reads the user's session, which should stop
+/// the page from being cached for other users.
+async fn session_request_state(
+    _info: StateGeneratorInfo,
+    req: Request,
+) -> RenderFnResultWithCause<String> {
+    let session = get_session(&req).expect("sessions should be enabled");
+    let user = session.lock().get::<String>("user");
+    Ok(user.unwrap_or_default())
+}
+
+/// A request state function that always redirects to the about page.
+async fn redirecting_request_state(
+    _info: StateGeneratorInfo,
+    _req: Request,
+) -> RenderFnResultWithCause<String> {
+    Err(Redirect::to(REDIRECT_LOCATION, 307))
+}
+
+/// A request state function that logs the user in by modifying their session
+/// and then redirects to the about page, which should still save the session.
+async fn login_redirecting_request_state(
+    _info: StateGeneratorInfo,
+    req: Request,
+) -> RenderFnResultWithCause<String> {
+    let session = get_session(&req).expect("sessions should be enabled");
+    {
+        let mut session = session.lock();
+        session.insert("user", "perseus-conformance-user")?;
+        session.regenerate_id();
+    }
+    Err(Redirect::to(REDIRECT_LOCATION, 303))
+}
+
+/// A request state function that logs the user in by modifying their session
+/// and then fails, which should still save the session.
+async fn login_failing_request_state(
+    _info: StateGeneratorInfo,
+    req: Request,
+) -> RenderFnResultWithCause<String> {
+    let session = get_session(&req).expect("sessions should be enabled");
+    {
+        let mut session = session.lock();
+        session.insert("user", "perseus-conformance-user")?;
+        session.regenerate_id();
+    }
+    Err(
+        AppError::new(APP_ERROR_KIND, APP_ERROR_STATUS, "perseus-conformance-app-error")
+            .into(),
+    )
+}
+
+/// A build state function that always redirects to the about page, which the
+/// server should keep doing without calling it again.
+async fn redirecting_build_state(_info: StateGeneratorInfo) -> RenderFnResultWithCause<String> {
+    Err(Redirect::to(REDIRECT_LOCATION, 301))
+}
+
+/// A request state function that redirects with a success status, which
+/// should be turned into a `500`.
+async fn invalid_redirecting_request_state(
+    _info: StateGeneratorInfo,
+    _req: Request,
+) -> RenderFnResultWithCause<String> {
+    Err(Redirect::to(REDIRECT_LOCATION, 200))
+}
+
+/// Writes the files the test app needs (other than its build artifacts) into
+/// the given directory, which the app should be run from.
+pub(crate) fn write_app_files(dir: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(dir.join("translations"))?;
+    // These don't need to contain anything, but they do need to be valid for the
+    // translator in use
+    let translations = match TRANSLATOR_FILE_EXT {
+        "json" => "{}",
+        _ => "",
+    };
+    for locale in [DEFAULT_LOCALE, OTHER_LOCALE] {
+        fs::write(
+            dir.join(format!("translations/{}.{}", locale, TRANSLATOR_FILE_EXT)),
+            translations,
+        )?;
+    }
+    fs::create_dir_all(dir.join("static"))?;
+    fs::write(dir.join("static/file.txt"), STATIC_FILE)?;
+    fs::write(dir.join("alias.txt"), STATIC_ALIAS_FILE)?;
+
+    Ok(())
+}
+
+/// Writes placeholder bundles into the given distribution directory, since the
+/// suite never runs the app in a browser.
+pub(crate) fn write_bundles(dist_dir: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(dist_dir.join("pkg/snippets"))?;
+    fs::write(dist_dir.join("pkg/perseus_engine.js"), JS_BUNDLE)?;
+    fs::write(dist_dir.join("pkg/perseus_engine_bg.wasm"), "")?;
+
+    Ok(())
+}
@@ -0,0 +1,583 @@
+use crate::app::*;
+use crate::errors::SuiteError;
+use perseus::csrf::CSRF_COOKIE;
+use perseus::error_pages::ErrorPageData;
+use perseus::session::SESSION_COOKIE;
+use reqwest::header::{
+    CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LOCATION, SET_COOKIE, VARY,
+};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Response, StatusCode};
+use std::time::Duration;
+
+/// How many times the server will be polled to see if it's started, and how
+/// long will be waited between each attempt.
+const STARTUP_ATTEMPTS: usize = 100;
+const STARTUP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The result of a single check, which will describe the problem if it failed.
+type CheckResult = Result<(), String>;
+
+/// Runs all the checks against the server for the test app at the given base
+/// URL (without a trailing `/`), once it's started. Every check will be run,
+/// even if some fail.
+pub(crate) async fn run_checks(base: &str) -> Result<(), SuiteError> {
+    // Integrations should leave redirects to the browser, so we need to see them
+    let client = Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .expect("couldn't create http client");
+    wait_for_server(&client, base).await?;
+
+    let mut failures = Vec::new();
+    let mut check = |name: &str, res: CheckResult| {
+        if let Err(problem) = res {
+            failures.push(format!("- {}: {}", name, problem));
+        }
+    };
+    check("initial load", initial_load(&client, base).await);
+    check(
+        "initial load in another locale",
+        initial_load_other_locale(&client, base).await,
+    );
+    check("subsequent load", subsequent_load(&client, base).await);
+    check("locale redirect", locale_redirect(&client, base).await);
+    check("translations", translations(&client, base).await);
+    check("bundles", bundles(&client, base).await);
+    check("static directory", static_dir(&client, base).await);
+    check("static alias", static_alias(&client, base).await);
+    check("page not found", page_not_found(&client, base).await);
+    check("render panic", render_panic(&client, base).await);
+    check("plugin panic", plugin_panic(&client, base).await);
+    check("state function timeout", state_fn_timeout(&client, base).await);
+    check("app-defined error", app_error(&client, base).await);
+    check("unsupported locale", unsupported_locale(&client, base).await);
+    check("state redirects", state_redirects(&client, base).await);
+    check(
+        "redirects with session changes",
+        session_redirects(&client, base).await,
+    );
+    check(
+        "errors with session changes",
+        session_errors(&client, base).await,
+    );
+    check("private session pages", session_pages(&client, base).await);
+    check("private csrf tokens", csrf_tokens(&client, base).await);
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(SuiteError::ChecksFailed { failures })
+    }
+}
+
+/// Waits until the server starts responding to requests.
+async fn wait_for_server(client: &Client, base: &str) -> Result<(), SuiteError> {
+    let url = format!("{}/.perseus/bundle.js", base);
+    for _ in 0..STARTUP_ATTEMPTS {
+        if client.get(&url).send().await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(STARTUP_INTERVAL).await;
+    }
+
+    Err(SuiteError::ServerNotStarted { url })
+}
+
+/// Makes a `GET` request to the given path on the server.
+async fn get(client: &Client, base: &str, path: &str) -> Result<Response, String> {
+    let url = format!("{}{}", base, path);
+    client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|err| format!("request to '{}' failed: {}", url, err))
+}
+
+/// Checks that the given response has the given status code, and returns its
+/// body.
+async fn expect_status(res: Response, status: u16) -> Result<String, String> {
+    let url = res.url().to_string();
+    let found = res.status();
+    let body = res
+        .text()
+        .await
+        .map_err(|err| format!("couldn't read body of response from '{}': {}", url, err))?;
+    if found != StatusCode::from_u16(status).unwrap() {
+        return Err(format!(
+            "'{}' returned status {}, expected {} (body: '{}')",
+            url,
+            found.as_u16(),
+            status,
+            body
+        ));
+    }
+
+    Ok(body)
+}
+
+/// Checks that the given body contains the given text.
+fn expect_contains(body: &str, expected: &str, what: &str) -> CheckResult {
+    if body.contains(expected) {
+        Ok(())
+    } else {
+        Err(format!("{} didn't contain '{}'", what, expected))
+    }
+}
+
+/// Pages should be fully rendered into the app shell on initial loads.
+async fn initial_load(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, &format!("/{}/about", DEFAULT_LOCALE)).await?;
+    let body = expect_status(res, 200).await?;
+    expect_contains(&body, &page_marker("about"), "initial load html")?;
+    expect_contains(&body, "__PERSEUS_INITIAL_STATE", "initial load html")
+}
+
+/// Every supported locale should be served, not just the default one.
+async fn initial_load_other_locale(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, &format!("/{}", OTHER_LOCALE)).await?;
+    let body = expect_status(res, 200).await?;
+    expect_contains(&body, &page_marker("index"), "initial load html")
+}
+
+/// The router fetches page data for subsequent loads from a dedicated
+/// endpoint.
+async fn subsequent_load(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/about.json?template_name=about&was_incremental_match=false",
+            DEFAULT_LOCALE
+        ),
+    )
+    .await?;
+    let body = expect_status(res, 200).await?;
+    expect_contains(&body, &page_marker("about"), "page data")?;
+    expect_contains(&body, "\"content\"", "page data")
+}
+
+/// Pages requested without a locale should be sent to the locale detection
+/// system, which is done by the app shell (with a fallback to the default
+/// locale for users without JS).
+async fn locale_redirect(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, "/about").await?;
+    let body = expect_status(res, 302).await?;
+    expect_contains(
+        &body,
+        &format!("/{}/about", DEFAULT_LOCALE),
+        "locale redirection html",
+    )
+}
+
+/// Translations should be served with ETags, and only sent again if they've
+/// changed.
+async fn translations(client: &Client, base: &str) -> CheckResult {
+    let path = format!("/.perseus/translations/{}", OTHER_LOCALE);
+    let res = get(client, base, &path).await?;
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| etag.to_string())
+        .ok_or_else(|| format!("'{}' had no `ETag` header", path))?;
+    expect_status(res, 200).await?;
+
+    let url = format!("{}{}", base, path);
+    let res = client
+        .get(&url)
+        .header(IF_NONE_MATCH, &etag)
+        .send()
+        .await
+        .map_err(|err| format!("request to '{}' failed: {}", url, err))?;
+    expect_status(res, 304).await?;
+
+    let res = get(
+        client,
+        base,
+        &format!("/.perseus/translations/{}", UNSUPPORTED_LOCALE),
+    )
+    .await?;
+    expect_status(res, 404).await?;
+
+    Ok(())
+}
+
+/// The app's bundles should be served from the locations in the server
+/// options.
+async fn bundles(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, "/.perseus/bundle.js").await?;
+    let body = expect_status(res, 200).await?;
+    if body != JS_BUNDLE {
+        return Err("'/.perseus/bundle.js' wasn't the js bundle".to_string());
+    }
+    let res = get(client, base, "/.perseus/bundle.wasm").await?;
+    expect_status(res, 200).await?;
+
+    Ok(())
+}
+
+/// The static directory should be served under `/.perseus/static`.
+async fn static_dir(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, "/.perseus/static/file.txt").await?;
+    let body = expect_status(res, 200).await?;
+    if body != STATIC_FILE {
+        return Err("'/.perseus/static/file.txt' wasn't the static file".to_string());
+    }
+
+    Ok(())
+}
+
+/// Static aliases should be served at their own URLs.
+async fn static_alias(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, STATIC_ALIAS_URL).await?;
+    let body = expect_status(res, 200).await?;
+    if body != STATIC_ALIAS_FILE {
+        return Err(format!(
+            "'{}' wasn't the file it aliases",
+            STATIC_ALIAS_URL
+        ));
+    }
+
+    Ok(())
+}
+
+/// Pages that don't exist should get the app's 404 error page.
+async fn page_not_found(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, &format!("/{}/nonexistent", DEFAULT_LOCALE)).await?;
+    let body = expect_status(res, 404).await?;
+    expect_contains(&body, &error_marker(404), "error page html")
+}
+
+/// Panics while rendering a page should give the user the app's 500 error
+/// page, rather than dropping their connection.
+async fn render_panic(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, PANIC_TEMPLATE),
+    )
+    .await?;
+    let body = expect_status(res, 500).await?;
+    expect_contains(&body, &error_marker(500), "error page html")?;
+
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+            DEFAULT_LOCALE, PANIC_TEMPLATE, PANIC_TEMPLATE
+        ),
+    )
+    .await?;
+    expect_status(res, 500).await?;
+
+    Ok(())
+}
+
+/// Plugins that panic while a page is being rendered should only affect that
+/// request, not any others that come after it.
+async fn plugin_panic(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, PLUGIN_PANIC_TEMPLATE),
+    )
+    .await?;
+    expect_status(res, 500).await?;
+
+    let res = get(client, base, &format!("/{}/about", DEFAULT_LOCALE)).await?;
+    let body = expect_status(res, 200).await?;
+    expect_contains(&body, &page_marker("about"), "initial load html")
+}
+
+/// State functions that take too long should be cancelled, and the user
+/// should get the app's 504 error page.
+async fn state_fn_timeout(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, TIMEOUT_TEMPLATE),
+    )
+    .await?;
+    let body = expect_status(res, 504).await?;
+    expect_contains(&body, &error_marker(504), "error page html")
+}
+
+/// App-defined errors should be sent with their own status codes, and they
+/// should reach the app's error pages on both initial and subsequent loads (on
+/// subsequent loads, as JSON error page data).
+async fn app_error(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, APP_ERROR_TEMPLATE),
+    )
+    .await?;
+    let body = expect_status(res, APP_ERROR_STATUS).await?;
+    expect_contains(&body, &app_error_marker(), "error page html")?;
+
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+            DEFAULT_LOCALE, APP_ERROR_TEMPLATE, APP_ERROR_TEMPLATE
+        ),
+    )
+    .await?;
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_string());
+    let body = expect_status(res, APP_ERROR_STATUS).await?;
+    if content_type.as_deref() != Some("application/json") {
+        return Err(format!(
+            "expected page data error to be json, found content type {:?}",
+            content_type
+        ));
+    }
+    let app_error = serde_json::from_str::<ErrorPageData>(&body)
+        .ok()
+        .and_then(|data| data.app_error);
+    match app_error {
+        Some(app_error) if app_error.kind() == APP_ERROR_KIND && app_error.is_retryable() => Ok(()),
+        app_error => Err(format!(
+            "expected page data to have retryable error kind '{}', found {:?}",
+            APP_ERROR_KIND, app_error
+        )),
+    }
+}
+
+/// Page data shouldn't be served for locales the app doesn't support (on
+/// initial loads, these will just be treated as paths without a locale).
+async fn unsupported_locale(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/about.json?template_name=about&was_incremental_match=false",
+            UNSUPPORTED_LOCALE
+        ),
+    )
+    .await?;
+    expect_status(res, 404).await?;
+
+    Ok(())
+}
+
+/// Pages whose state functions redirect elsewhere should get real redirects on
+/// initial loads, and page data telling the app to navigate on subsequent
+/// loads. Build-time redirects should keep being served, and redirects with
+/// invalid statuses should become server errors.
+async fn state_redirects(client: &Client, base: &str) -> CheckResult {
+    for (template, status) in [(REDIRECT_TEMPLATE, 307), (BUILD_REDIRECT_TEMPLATE, 301)] {
+        let res = get(client, base, &format!("/{}/{}", DEFAULT_LOCALE, template)).await?;
+        let location = header(&res, LOCATION).map(|location| location.to_string());
+        expect_status(res, status).await?;
+        if location.as_deref() != Some(REDIRECT_LOCATION) {
+            return Err(format!(
+                "expected '{}' to redirect to '{}', found {:?}",
+                template, REDIRECT_LOCATION, location
+            ));
+        }
+
+        let res = get(
+            client,
+            base,
+            &format!(
+                "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+                DEFAULT_LOCALE, template, template
+            ),
+        )
+        .await?;
+        let body = expect_status(res, 200).await?;
+        expect_contains(
+            &body,
+            &format!("\"redirect\":\"{}\"", REDIRECT_LOCATION),
+            "page data",
+        )?;
+    }
+
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, INVALID_REDIRECT_TEMPLATE),
+    )
+    .await?;
+    let body = expect_status(res, 500).await?;
+    expect_contains(&body, &error_marker(500), "error page html")
+}
+
+/// Checks that the given response sets the session cookie.
+fn expect_session_cookie(res: &Response) -> CheckResult {
+    let set_cookie = res
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .any(|header| header.starts_with(&format!("{}=", SESSION_COOKIE)));
+    if set_cookie {
+        Ok(())
+    } else {
+        Err(format!("'{}' didn't set a session cookie", res.url()))
+    }
+}
+
+/// State functions that change the user's session and then redirect (e.g. to
+/// log a user in) should still have their changes saved, and the redirects
+/// mustn't be cached for other users.
+async fn session_redirects(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, LOGIN_REDIRECT_TEMPLATE),
+    )
+    .await?;
+    expect_session_cookie(&res)?;
+    expect_private(&res, true)?;
+    let location = header(&res, LOCATION).map(|location| location.to_string());
+    expect_status(res, 303).await?;
+    if location.as_deref() != Some(REDIRECT_LOCATION) {
+        return Err(format!(
+            "expected '{}' to redirect to '{}', found {:?}",
+            LOGIN_REDIRECT_TEMPLATE, REDIRECT_LOCATION, location
+        ));
+    }
+
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+            DEFAULT_LOCALE, LOGIN_REDIRECT_TEMPLATE, LOGIN_REDIRECT_TEMPLATE
+        ),
+    )
+    .await?;
+    expect_session_cookie(&res)?;
+    expect_private(&res, true)?;
+    let body = expect_status(res, 200).await?;
+    expect_contains(
+        &body,
+        &format!("\"redirect\":\"{}\"", REDIRECT_LOCATION),
+        "page data",
+    )
+}
+
+/// State functions that change the user's session and then fail should still
+/// have their changes saved, and the error pages should still give users
+/// without a CSRF token a new one.
+async fn session_errors(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!("/{}/{}", DEFAULT_LOCALE, LOGIN_ERROR_TEMPLATE),
+    )
+    .await?;
+    expect_session_cookie(&res)?;
+    expect_private(&res, true)?;
+    let set_csrf_cookie = res
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .any(|header| header.starts_with(&format!("{}=", CSRF_COOKIE)));
+    if !set_csrf_cookie {
+        return Err("initial load error didn't set a csrf cookie".to_string());
+    }
+    let body = expect_status(res, APP_ERROR_STATUS).await?;
+    expect_contains(&body, &app_error_marker(), "error page html")?;
+
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+            DEFAULT_LOCALE, LOGIN_ERROR_TEMPLATE, LOGIN_ERROR_TEMPLATE
+        ),
+    )
+    .await?;
+    expect_session_cookie(&res)?;
+    expect_private(&res, true)?;
+    expect_status(res, APP_ERROR_STATUS).await?;
+    Ok(())
+}
+
+/// Gets the given header from the given response as a string, if it's there.
+fn header(res: &Response, name: reqwest::header::HeaderName) -> Option<&str> {
+    res.headers()
+        .get(name)
+        .and_then(|header| header.to_str().ok())
+}
+
+/// Checks whether or not the given response is marked as depending on the
+/// user's cookies, which stops shared caches from serving it to other users.
+fn expect_private(res: &Response, private: bool) -> CheckResult {
+    let cache_control = header(res, CACHE_CONTROL);
+    let vary = header(res, VARY);
+    let is_private = cache_control == Some("private, no-store") && vary == Some("Cookie");
+    if is_private == private {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' should{} have been private, but had `Cache-Control: {:?}` and `Vary: {:?}`",
+            res.url(),
+            if private { "" } else { "n't" },
+            cache_control,
+            vary
+        ))
+    }
+}
+
+/// Pages that read the user's session mustn't be cached for other users, but
+/// pages that don't should be left alone.
+async fn session_pages(client: &Client, base: &str) -> CheckResult {
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/{}.json?template_name={}&was_incremental_match=false",
+            DEFAULT_LOCALE, SESSION_TEMPLATE, SESSION_TEMPLATE
+        ),
+    )
+    .await?;
+    expect_private(&res, true)?;
+    expect_status(res, 200).await?;
+
+    let res = get(client, base, &format!("/{}/{}", DEFAULT_LOCALE, SESSION_TEMPLATE)).await?;
+    expect_private(&res, true)?;
+    expect_status(res, 200).await?;
+
+    let res = get(
+        client,
+        base,
+        &format!(
+            "/.perseus/page/{}/about.json?template_name=about&was_incremental_match=false",
+            DEFAULT_LOCALE
+        ),
+    )
+    .await?;
+    expect_private(&res, false)?;
+    expect_status(res, 200).await?;
+
+    Ok(())
+}
+
+/// Initial loads embed the user's CSRF token, so they mustn't be cached for
+/// other users, and they should give users without a token a new one.
+async fn csrf_tokens(client: &Client, base: &str) -> CheckResult {
+    let res = get(client, base, &format!("/{}/about", DEFAULT_LOCALE)).await?;
+    expect_private(&res, true)?;
+    let set_cookie = res
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .any(|header| header.starts_with(&format!("{}=", CSRF_COOKIE)));
+    if !set_cookie {
+        return Err("initial load didn't set a csrf cookie".to_string());
+    }
+    let body = expect_status(res, 200).await?;
+    expect_contains(&body, "perseus-csrf-token", "initial load html")
+}
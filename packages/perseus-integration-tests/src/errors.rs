@@ -0,0 +1,31 @@
+#![allow(missing_docs)]
+use thiserror::Error;
+
+/// Errors that can occur while running the conformance suite against an
+/// integration.
+#[derive(Error, Debug)]
+pub enum SuiteError {
+    #[error("couldn't write the test app's files")]
+    WriteFilesFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("couldn't build the test app:\n{err}")]
+    BuildFailed { err: String },
+    #[error("couldn't set up the server for the test app")]
+    ServerOptionsFailed {
+        #[source]
+        source: perseus::errors::EngineError,
+    },
+    #[error("couldn't find a free port to run the server on")]
+    NoFreePort {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("the server never started responding at '{url}'")]
+    ServerNotStarted { url: String },
+    #[error("the server stopped before all the checks had been run")]
+    ServerStopped,
+    #[error("{} check(s) failed:\n{}", .failures.len(), .failures.join("\n"))]
+    ChecksFailed { failures: Vec<String> },
+}
@@ -0,0 +1,98 @@
+/*!
+This is the API documentation for the `perseus-integration-tests` package, which is a conformance test suite for Perseus server integrations. Note that Perseus mostly uses [the book](https://arctic-hen7.github.io/perseus/en-US) for
+documentation, and this should mostly be used as a secondary reference source. You can also find full usage examples [here](https://github.com/arctic-hen7/perseus/tree/main/examples).
+
+If you're maintaining an integration for a framework Perseus doesn't officially support, you can run [`run_suite`] against it to make sure it behaves the same way as the official ones do, without having to build an app and test it in a browser. The suite builds a small test app, starts your server with it, and checks initial loads, subsequent loads, locale redirection, redirects and errors from state functions (including ones that change the session), translations, bundles, static content, static aliases, error pages (including pages for app-defined errors), and recovery from panics and slow state functions while rendering over HTTP.
+*/
+
+#![deny(missing_docs)]
+
+mod app;
+mod checks;
+/// Errors that can occur while running the conformance suite.
+pub mod errors;
+
+use crate::app::{get_test_app, write_app_files, write_bundles};
+use crate::checks::run_checks;
+use crate::errors::SuiteError;
+use fmterr::fmt_err;
+use futures::future::{select, Either};
+use futures::pin_mut;
+use perseus::engine::engine_build;
+use perseus::i18n::FsTranslationsManager;
+use perseus::server::{ServerOptions, ServerProps};
+use perseus::stores::FsMutableStore;
+use perseus::utils::get_dist_dir;
+use std::env;
+use std::future::Future;
+use std::net::TcpListener;
+
+/// Runs the conformance suite against a server integration. This takes a
+/// function with the same signature as the `dflt_server` functions of the
+/// official integrations, which should start serving the given properties on
+/// the given host and port (and never return). All the checks will be run, and
+/// any that fail will be reported together.
+///
+/// This should be run from a test, in a runtime your server can run in (e.g.
+/// `#[tokio::test]`). Note that the test app is built in a temporary directory,
+/// which this will make the working directory until it's done, so the suite
+/// should be run in its own test binary (i.e. its own file in `tests/`).
+///
+/// # Example
+/// ```rust,ignore
+/// #[tokio::test]
+/// async fn conformance() {
+///     if let Err(err) = perseus_integration_tests::run_suite(my_integration::dflt_server).await {
+///         panic!("{}", fmterr::fmt_err(&err));
+///     }
+/// }
+/// ```
+pub async fn run_suite<F, Fut>(serve: F) -> Result<(), SuiteError>
+where
+    F: FnOnce(ServerProps<FsMutableStore, FsTranslationsManager>, (String, u16)) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let dir = tempfile::tempdir().map_err(|source| SuiteError::WriteFilesFailed { source })?;
+    let prev_dir = env::current_dir().map_err(|source| SuiteError::WriteFilesFailed { source })?;
+    write_app_files(dir.path()).map_err(|source| SuiteError::WriteFilesFailed { source })?;
+    env::set_current_dir(dir.path()).map_err(|source| SuiteError::WriteFilesFailed { source })?;
+
+    let res = run_in_dir(serve).await;
+    // The temporary directory can't be deleted while we're still in it on some
+    // platforms
+    let _ = env::set_current_dir(prev_dir);
+    res
+}
+
+/// Builds the test app in the current directory, and then runs the checks
+/// against the server once it's started.
+async fn run_in_dir<F, Fut>(serve: F) -> Result<(), SuiteError>
+where
+    F: FnOnce(ServerProps<FsMutableStore, FsTranslationsManager>, (String, u16)) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    engine_build(get_test_app())
+        .await
+        .map_err(|err| SuiteError::BuildFailed { err: fmt_err(&*err) })?;
+    write_bundles(get_dist_dir().as_ref())
+        .map_err(|source| SuiteError::WriteFilesFailed { source })?;
+    let props = ServerOptions::builder(get_test_app())
+        .standalone(false)
+        .build()
+        .map_err(|source| SuiteError::ServerOptionsFailed { source })?;
+
+    // We let the OS pick a port that's free, and then hand it to the server
+    let port = TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map_err(|source| SuiteError::NoFreePort { source })?
+        .port();
+    let base = format!("http://127.0.0.1:{}", port);
+    let server = serve(props, ("127.0.0.1".to_string(), port));
+    let checks = run_checks(&base);
+    pin_mut!(server, checks);
+    // The server should run forever, so the checks should always finish first
+    match select(server, checks).await {
+        Either::Left(_) => Err(SuiteError::ServerStopped),
+        Either::Right((res, _)) => res,
+    }
+}
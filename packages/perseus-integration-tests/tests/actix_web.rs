@@ -0,0 +1,8 @@
+use fmterr::fmt_err;
+
+#[actix_web::test]
+async fn actix_web_conforms() {
+    if let Err(err) = perseus_integration_tests::run_suite(perseus_actix_web::dflt_server).await {
+        panic!("{}", fmt_err(&err));
+    }
+}
@@ -0,0 +1,8 @@
+use fmterr::fmt_err;
+
+#[tokio::test]
+async fn axum_conforms() {
+    if let Err(err) = perseus_integration_tests::run_suite(perseus_axum::dflt_server).await {
+        panic!("{}", fmt_err(&err));
+    }
+}
@@ -0,0 +1,8 @@
+use fmterr::fmt_err;
+
+#[tokio::test]
+async fn warp_conforms() {
+    if let Err(err) = perseus_integration_tests::run_suite(perseus_warp::dflt_server).await {
+        panic!("{}", fmt_err(&err));
+    }
+}
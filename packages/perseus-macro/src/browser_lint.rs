@@ -0,0 +1,217 @@
+// This file contains the lint that catches engine-only code in code that will
+// be compiled for the browser
+
+use crate::warning::warning;
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use syn::{Lit, Meta, NestedMeta};
+
+/// Crates and modules that only work on the engine-side. Using anything in
+/// these from browser code will either fail to compile for Wasm, or fail at
+/// runtime.
+const ENGINE_ONLY_PATHS: [&str; 20] = [
+    "actix_web",
+    "axum",
+    "diesel",
+    "hyper",
+    "lettre",
+    "mongodb",
+    "native_tls",
+    "openssl",
+    "postgres",
+    "redis",
+    "rocket",
+    "rusqlite",
+    "sqlx",
+    "tokio",
+    "tokio_postgres",
+    "warp",
+    "std::fs",
+    "std::net",
+    "std::process",
+    "std::thread",
+];
+/// Exceptions to the above, which work perfectly well in the browser.
+const BROWSER_SAFE_PATHS: [&str; 1] = ["tokio::sync"];
+/// Functions that compile for Wasm, but panic when they're called in the
+/// browser. These are matched against the end of each path, so `Instant::now`
+/// will be caught, but `instant::Instant::now` (which works in the browser)
+/// won't be.
+const PANICKING_FNS: [&str; 2] = ["std::time::Instant::now", "std::time::SystemTime::now"];
+
+/// Checks the given code, which will be compiled for the browser as part of the
+/// given kind of item (e.g. `template`), for references to things that only
+/// work on the engine-side, returning a tailored warning for each one (or
+/// nothing if there aren't any).
+///
+/// Anything annotated with `#[engine]`, or with a `#[cfg(..)]` that can't be
+/// true when compiling for Wasm, will be ignored.
+pub fn lint_browser_code(tokens: TokenStream, context: &str) -> TokenStream {
+    let mut warnings = TokenStream::new();
+    for (path, span) in find_engine_only_paths(tokens) {
+        let msg = if is_panicking_fn(&path) {
+            format!(
+                "`{}` panics in the browser, and this {} is compiled for the browser too (if you only need it on the engine-side, move it into a state function like `#[build_state]`, or gate it with `#[engine]`)",
+                path, context
+            )
+        } else {
+            format!(
+                "`{}` can only be used on the engine-side, but this {} is compiled for the browser too (move it into a state function like `#[build_state]`, which will only be compiled for the engine, or gate it with `#[engine]`)",
+                path, context
+            )
+        };
+        warnings.extend(warning("engine_only_code", span, &msg));
+    }
+
+    warnings
+}
+
+/// Finds all the paths in the given code that refer to engine-only things,
+/// along with where they are.
+fn find_engine_only_paths(tokens: TokenStream) -> Vec<(String, Span)> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut paths = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            // Skip over anything that's been gated to the engine
+            TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(attr)) = tokens.get(i + 1) {
+                    if attr.delimiter() == Delimiter::Bracket && is_engine_attr(&attr.stream()) {
+                        i = skip_item(&tokens, i + 2);
+                        continue;
+                    }
+                }
+                i += 1;
+            }
+            TokenTree::Group(group) => {
+                paths.extend(find_engine_only_paths(group.stream()));
+                i += 1;
+            }
+            TokenTree::Ident(ident) => {
+                let span = ident.span();
+                let mut path = ident.to_string();
+                let mut is_path = false;
+                i += 1;
+                // Follow the path for as long as it goes (`use` statements might end in a
+                // group or a glob)
+                while is_path_separator(&tokens, i) {
+                    is_path = true;
+                    i += 2;
+                    match tokens.get(i) {
+                        Some(TokenTree::Ident(segment)) => {
+                            path.push_str("::");
+                            path.push_str(&segment.to_string());
+                            i += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                if is_path && is_engine_only(&path) {
+                    paths.push((path, span));
+                }
+            }
+            TokenTree::Punct(_) | TokenTree::Literal(_) => i += 1,
+        }
+    }
+
+    paths
+}
+
+/// Checks if the given path refers to something that only works on the
+/// engine-side.
+fn is_engine_only(path: &str) -> bool {
+    let starts_with = |prefix: &str| path == prefix || path.starts_with(&format!("{}::", prefix));
+    (ENGINE_ONLY_PATHS.iter().any(|prefix| starts_with(prefix))
+        && !BROWSER_SAFE_PATHS.iter().any(|prefix| starts_with(prefix)))
+        || is_panicking_fn(path)
+}
+
+/// Checks if the given path refers to a function that will panic in the
+/// browser. Paths can leave off any number of leading segments (since they'll
+/// usually be imported), but they can't have different ones.
+fn is_panicking_fn(path: &str) -> bool {
+    PANICKING_FNS
+        .iter()
+        .any(|f| *f == path || f.ends_with(&format!("::{}", path)))
+}
+
+/// Checks if there's a `::` at the given position in the given tokens.
+fn is_path_separator(tokens: &[TokenTree], i: usize) -> bool {
+    matches!(
+        (tokens.get(i), tokens.get(i + 1)),
+        (Some(TokenTree::Punct(first)), Some(TokenTree::Punct(second)))
+            if first.as_char() == ':' && second.as_char() == ':'
+    )
+}
+
+/// Checks if the given attribute contents gate something to the engine. That's
+/// either `#[engine]`, or a `#[cfg(..)]` whose predicate is known to be false
+/// when compiling for Wasm.
+fn is_engine_attr(attr: &TokenStream) -> bool {
+    match syn::parse2::<Meta>(attr.clone()) {
+        Ok(Meta::Path(path)) => path
+            .segments
+            .last()
+            .map_or(false, |seg| seg.ident == "engine"),
+        Ok(Meta::List(list)) if list.path.is_ident("cfg") && list.nested.len() == 1 => {
+            eval_cfg_on_wasm(&list.nested[0]) == Some(false)
+        }
+        _ => false,
+    }
+}
+
+/// Evaluates the given `cfg` predicate as it would be when compiling for Wasm,
+/// returning `None` if that can't be known (e.g. for features).
+fn eval_cfg_on_wasm(pred: &NestedMeta) -> Option<bool> {
+    let meta = match pred {
+        NestedMeta::Meta(meta) => meta,
+        NestedMeta::Lit(_) => return None,
+    };
+    match meta {
+        Meta::NameValue(nv) => match &nv.lit {
+            Lit::Str(value) if nv.path.is_ident("target_arch") => Some(value.value() == "wasm32"),
+            Lit::Str(value) if nv.path.is_ident("target_family") => Some(value.value() == "wasm"),
+            _ => None,
+        },
+        Meta::List(list) if list.path.is_ident("not") && list.nested.len() == 1 => {
+            eval_cfg_on_wasm(&list.nested[0]).map(|val| !val)
+        }
+        Meta::List(list) if list.path.is_ident("all") => {
+            let vals = list.nested.iter().map(eval_cfg_on_wasm).collect::<Vec<_>>();
+            if vals.contains(&Some(false)) {
+                Some(false)
+            } else if vals.iter().all(|val| *val == Some(true)) {
+                Some(true)
+            } else {
+                None
+            }
+        }
+        Meta::List(list) if list.path.is_ident("any") => {
+            let vals = list.nested.iter().map(eval_cfg_on_wasm).collect::<Vec<_>>();
+            if vals.contains(&Some(true)) {
+                Some(true)
+            } else if vals.iter().all(|val| *val == Some(false)) {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Skips over the item (or statement) starting at the given position in the
+/// given tokens, returning the position after it. Items end with either a block
+/// or a semicolon.
+fn skip_item(tokens: &[TokenTree], mut i: usize) -> usize {
+    while let Some(token) = tokens.get(i) {
+        i += 1;
+        match token {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => break,
+            TokenTree::Punct(punct) if punct.as_char() == ';' => break,
+            _ => (),
+        }
+    }
+
+    i
+}
@@ -11,9 +11,9 @@ pub struct HeadFn {
     pub block: Box<Block>,
     /// The argument for the reactive scope.
     pub cx_arg: FnArg,
-    /// The possible single argument for custom properties, or there might be no
-    /// arguments.
-    pub arg: Option<FnArg>,
+    /// The other arguments, which may be one for custom properties and/or one
+    /// for information about the page (in either order).
+    pub args: Vec<FnArg>,
     /// The visibility of the function.
     pub vis: Visibility,
     /// Any attributes the function uses.
@@ -82,28 +82,29 @@ impl Parse for HeadFn {
                         ));
                     }
                 };
-                let arg = inputs.next();
-                // We don't care what the type is, as long as it's not `self`
-                if let Some(FnArg::Receiver(arg)) = arg {
+                let args = inputs.collect::<Vec<_>>();
+                // We don't care what the types are, as long as they're not `self`
+                if let Some(FnArg::Receiver(arg)) = args.first() {
                     return Err(syn::Error::new_spanned(
                         arg,
                         "head functions can't take `self`",
                     ));
                 }
-
-                // This operates on what's left over after calling `.next()`
-                if inputs.len() > 0 {
-                    let params: TokenStream = inputs.map(|it| it.to_token_stream()).collect();
+                // There can be at most one argument for custom properties and one for the page
+                // information
+                let info_args = args.iter().filter(|arg| is_page_info(arg)).count();
+                if args.len() > 2 || info_args > 1 || args.len() - info_args > 1 {
+                    let params: TokenStream = args.iter().map(|it| it.to_token_stream()).collect();
                     return Err(syn::Error::new_spanned(
                         params,
-                        "head functions must accept either two arguments for scope, and custom properties or just one argument for scope",
+                        "head functions must accept an argument for scope, and then optionally one for custom properties and/or one for `perseus::template::PageInfo`",
                     ));
                 }
 
                 Ok(Self {
                     block,
                     cx_arg,
-                    arg,
+                    args,
                     vis,
                     attrs,
                     name: sig.ident,
@@ -119,11 +120,42 @@ impl Parse for HeadFn {
     }
 }
 
+/// Checks if the given function argument is for the information about the
+/// page being rendered. This is used for both head functions and
+/// header-setting functions. The type has to be written out in full (as
+/// `perseus::template::PageInfo`, with or without a leading `::`), since we
+/// can't resolve imports here, and we don't want to mistake the user's own
+/// state types for it.
+pub fn is_page_info(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Typed(pat_type) => match &*pat_type.ty {
+            Type::Path(ty) if ty.qself.is_none() => {
+                let segments = ty
+                    .path
+                    .segments
+                    .iter()
+                    .map(|segment| {
+                        segment
+                            .arguments
+                            .is_empty()
+                            .then(|| segment.ident.to_string())
+                    })
+                    .collect::<Option<Vec<_>>>();
+                segments.map_or(false, |segments| {
+                    segments == ["perseus", "template", "PageInfo"]
+                })
+            }
+            _ => false,
+        },
+        FnArg::Receiver(_) => false,
+    }
+}
+
 pub fn head_impl(input: HeadFn) -> TokenStream {
     let HeadFn {
         block,
         cx_arg,
-        arg,
+        args,
         generics,
         vis,
         attrs,
@@ -132,46 +164,36 @@ pub fn head_impl(input: HeadFn) -> TokenStream {
     } = input;
 
     // We create a wrapper function that can be easily provided to `.head()` that
-    // does deserialization automatically if needed This is dependent on what
-    // arguments the template takes
-    if arg.is_some() {
-        // There's an argument that will be provided as a `String`, so the wrapper will
-        // deserialize it
-        quote! {
-            // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
-            #[cfg(target_arch = "wasm32")]
-            #vis fn #name() {}
-            #[cfg(not(target_arch = "wasm32"))]
-            #vis fn #name(cx: ::sycamore::prelude::Scope, props: ::perseus::template::PageProps) -> ::sycamore::prelude::View<::sycamore::prelude::SsrNode> {
-                // The user's function, with Sycamore component annotations and the like preserved
-                // We know this won't be async because Sycamore doesn't allow that
-                #(#attrs)*
-                fn #name #generics(#cx_arg, #arg) -> #return_type {
-                    #block
-                }
-                #name(
-                    cx,
-                    // If there are props, they will always be provided, the compiler just doesn't know that
-                    ::serde_json::from_str(&props.state.unwrap()).unwrap()
-                )
-            }
+    // does deserialization automatically if needed, and that passes through the
+    // page information if the user's function takes it (in which case it has to
+    // be provided to `.head_with_info()` instead, since it takes an extra argument)
+    let info_param = args
+        .iter()
+        .any(is_page_info)
+        .then(|| quote!(, info: ::perseus::template::PageInfo));
+    let call_args = args.iter().map(|arg| {
+        if is_page_info(arg) {
+            quote!(info)
+        } else {
+            // If there are props, they will always be provided, the compiler just doesn't
+            // know that
+            quote!(::serde_json::from_str(&props.state.unwrap()).unwrap())
         }
-    } else {
-        // There are no arguments
-        quote! {
-            // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
-            #[cfg(target_arch = "wasm32")]
-            #vis fn #name() {}
-            #[cfg(not(target_arch = "wasm32"))]
-            #vis fn #name(cx: ::sycamore::prelude::Scope, props: ::perseus::template::PageProps) -> ::sycamore::prelude::View<::sycamore::prelude::SsrNode> {
-                // The user's function, with Sycamore component annotations and the like preserved
-                // We know this won't be async because Sycamore doesn't allow that
-                #(#attrs)*
-                fn #name #generics(#cx_arg) -> #return_type {
-                    #block
-                }
-                #name(cx)
+    });
+    quote! {
+        // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
+        #[cfg(target_arch = "wasm32")]
+        #vis fn #name() {}
+        #[cfg(not(target_arch = "wasm32"))]
+        #[allow(unused_variables)]
+        #vis fn #name(cx: ::sycamore::prelude::Scope, props: ::perseus::template::PageProps #info_param) -> ::sycamore::prelude::View<::sycamore::prelude::SsrNode> {
+            // The user's function, with Sycamore component annotations and the like preserved
+            // We know this won't be async because Sycamore doesn't allow that
+            #(#attrs)*
+            fn #name #generics(#cx_arg, #(#args),*) -> #return_type {
+                #block
             }
+            #name(cx, #(#call_args),*)
         }
     }
 }
@@ -11,19 +11,23 @@ This is the API documentation for the `perseus-macro` package, which manages Per
 documentation, and this should mostly be used as a secondary reference source. You can also find full usage examples [here](https://github.com/arctic-hen7/perseus/tree/main/examples).
 */
 
+mod browser_lint;
 mod entrypoint;
 mod head;
+mod reactive_state;
 mod rx_state;
 mod state_fns;
 mod template;
 mod template_rx;
 mod test;
+mod translations;
+mod warning;
 
 use darling::FromMeta;
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, ToTokens};
 use state_fns::StateFnType;
-use syn::{ItemStruct, Path};
+use syn::{DeriveInput, ItemStruct, Path};
 
 /// Annotates functions used for generating state at build time to support
 /// automatic serialization/deserialization of app state and client/server
@@ -69,10 +73,12 @@ pub fn request_state(_args: TokenStream, input: TokenStream) -> TokenStream {
     state_fns::state_fn_impl(parsed, StateFnType::RequestState).into()
 }
 
-/// Annotates functions used for generating state at build time to support
-/// automatic serialization/deserialization of app state and client/server
-/// division. This supersedes the old `autoserde` macro for build state
-/// functions.
+/// Annotates functions used for setting HTTP headers to support automatic
+/// deserialization of app state and client/server division. These functions
+/// can take the page's state and/or a `perseus::template::PageInfo` (in either
+/// order, and written out in full), which has the path, locale, and query
+/// string of the request. Functions that take a `PageInfo` should be given to
+/// `.set_headers_fn_with_info()`, rather than `.set_headers_fn()`.
 #[proc_macro_attribute]
 pub fn set_headers(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as state_fns::StateFn);
@@ -110,8 +116,10 @@ pub fn should_revalidate(_args: TokenStream, input: TokenStream) -> TokenStream
 #[proc_macro_attribute]
 pub fn template(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as template::TemplateFn);
+    let lint = browser_lint::lint_browser_code(parsed.block.to_token_stream(), "template");
+    let output = template::template_impl(parsed);
 
-    template::template_impl(parsed).into()
+    quote!(#lint #output).into()
 }
 
 /// The new version of `#[template]` designed for reactive state. This can
@@ -132,11 +140,18 @@ pub fn template(_args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn template_rx(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as template_rx::TemplateFn);
-    template_rx::template_impl(parsed).into()
+    let lint = browser_lint::lint_browser_code(parsed.block.to_token_stream(), "template");
+    let output = template_rx::template_impl(parsed);
+
+    quote!(#lint #output).into()
 }
 
 /// Labels a function as a Perseus head function, which is very similar to a
-/// template, but for the HTML metadata in the document `<head>`.
+/// template, but for the HTML metadata in the document `<head>`. After the
+/// reactive scope, these functions can take the page's state and/or a
+/// `perseus::template::PageInfo` (in either order, and written out in full),
+/// which has the path and locale of the page. Functions that take a
+/// `PageInfo` should be given to `.head_with_info()`, rather than `.head()`.
 #[proc_macro_attribute]
 pub fn head(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as head::HeadFn);
@@ -181,8 +196,10 @@ pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn main(args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as entrypoint::MainFn);
     let args = syn::parse_macro_input!(args as Path);
+    let lint = browser_lint::lint_browser_code(parsed.block.to_token_stream(), "function");
+    let output = entrypoint::main_impl(parsed, args);
 
-    entrypoint::main_impl(parsed, args).into()
+    quote!(#lint #output).into()
 }
 
 /// This is identical to `#[main]`, except it doesn't require a server
@@ -193,8 +210,10 @@ pub fn main(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn main_export(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as entrypoint::MainFn);
+    let lint = browser_lint::lint_browser_code(parsed.block.to_token_stream(), "function");
+    let output = entrypoint::main_export_impl(parsed);
 
-    entrypoint::main_export_impl(parsed).into()
+    quote!(#lint #output).into()
 }
 
 /// Marks the given function as the browser entrypoint into your app. This is
@@ -214,8 +233,10 @@ pub fn main_export(_args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro_attribute]
 pub fn browser_main(_args: TokenStream, input: TokenStream) -> TokenStream {
     let parsed = syn::parse_macro_input!(input as entrypoint::MainFn);
+    let lint = browser_lint::lint_browser_code(parsed.block.to_token_stream(), "function");
+    let output = entrypoint::browser_main_impl(parsed);
 
-    entrypoint::browser_main_impl(parsed).into()
+    quote!(#lint #output).into()
 }
 
 /// Marks the given function as the engine entrypoint into your app. This is
@@ -252,6 +273,10 @@ pub fn engine_main(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// = "testField")]`) as usual. `Clone` will also be derived on both the
 /// original and the new `struct`, so do NOT try to derive it yourself.
 ///
+/// Generic `struct`s are supported, as long as their type parameters are
+/// `Clone + Serialize + DeserializeOwned + 'static` (you don't need to write
+/// these bounds yourself, they'll be added wherever they're needed).
+///
 /// If one of your fields is itself a `struct`, by default it will just be
 /// wrapped in a `Signal`, but you can also enable nested fine-grained
 /// reactivity by adding the `#[rx::nested("field_name", FieldTypeRx)]` helper
@@ -327,6 +352,69 @@ pub fn make_rx(args: TokenStream, input: TokenStream) -> TokenStream {
     rx_state::make_rx_impl(parsed, name).into()
 }
 
+/// Derives a reactive version of the annotated `struct`, which can then be
+/// used as page or global state. This is a more flexible alternative to
+/// `#[make_rx]`, which lets you control how each field is made reactive.
+///
+/// By default, the reactive type will be called the name of the `struct`, with
+/// `Rx` on the end (e.g. `IndexPageState` becomes `IndexPageStateRx`), but you
+/// can change this with `#[rx(alias = "MyRxName")]` on the `struct`. Every
+/// field will be wrapped in an `RcSignal`, unless it's annotated with one of
+/// the following:
+///
+/// - `#[rx(nested)]`, which will use the field's own reactive version (so its
+///   type must also derive `ReactiveState`, or use `#[make_rx]`), giving you
+///   fine-grained reactivity on its fields;
+/// - `#[rx(skip)]`, which will leave the field as it is (useful for things that
+///   should be shared with the template, but never changed, like connection
+///   pools).
+///
+/// Unlike `#[make_rx]`, this won't derive anything on the original `struct`,
+/// so you'll need to derive `Serialize`, `Deserialize`, and `Clone` yourself
+/// (and skipped fields that can't be serialized will need
+/// `#[serde(skip)]`). Any type parameters must also implement these traits.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use perseus::ReactiveState;
+/// use serde::{Serialize, Deserialize};
+///
+/// #[derive(Serialize, Deserialize, Clone, ReactiveState)]
+/// #[rx(alias = "PostRx")]
+/// struct Post<T> {
+///     title: String,
+///     // This will be `&RcSignal<T>` in the reactive version
+///     extra: T,
+///     #[rx(nested)]
+///     author: Author,
+///     #[serde(skip)]
+///     #[rx(skip)]
+///     pool: MyPool,
+/// }
+/// #[derive(Serialize, Deserialize, Clone, ReactiveState)]
+/// struct Author {
+///     name: String,
+/// }
+/// ```
+#[proc_macro_derive(ReactiveState, attributes(rx))]
+pub fn derive_reactive_state(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+
+    reactive_state::reactive_state_impl(input).into()
+}
+
+/// Checks a call to the `t!` macro against the app's Fluent translations at
+/// compile-time, if they can be found. This is used internally by `t!`, and
+/// shouldn't be called directly.
+#[doc(hidden)]
+#[proc_macro]
+pub fn check_translation(input: TokenStream) -> TokenStream {
+    let parsed = syn::parse_macro_input!(input as translations::TranslationCheck);
+
+    translations::translation_check_impl(parsed).into()
+}
+
 /// Marks the annotated code as only to be run as part of the engine (the
 /// server, the builder, the exporter, etc.). This resolves to a target-gate
 /// that makes the annotated code run only on targets that are not `wasm32`.
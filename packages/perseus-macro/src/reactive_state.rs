@@ -0,0 +1,265 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Ident, Lifetime, LifetimeDef,
+    Lit, Meta, NestedMeta, Result,
+};
+
+/// The ways a single field can be made reactive.
+enum FieldKind {
+    /// The field will be wrapped in an `RcSignal` (the default).
+    Signal,
+    /// The field's own reactive version will be used (`#[rx(nested)]`).
+    Nested,
+    /// The field will be left as it is (`#[rx(skip)]`).
+    Skip,
+}
+
+/// Gets the arguments to all the `#[rx(..)]` helper attributes in the given
+/// attributes.
+fn get_rx_args(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("rx")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => args.extend(list.nested),
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[rx(..)]`")),
+        }
+    }
+
+    Ok(args)
+}
+
+pub fn reactive_state_impl(input: DeriveInput) -> TokenStream {
+    match derive(input) {
+        Ok(output) => output,
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let DeriveInput {
+        attrs,
+        vis,
+        ident: orig_name,
+        generics,
+        data,
+    } = input;
+
+    // The reactive type is called `{Name}Rx` unless the user tells us otherwise
+    let mut ref_name = Ident::new(&(orig_name.to_string() + "Rx"), orig_name.span());
+    for arg in get_rx_args(&attrs)? {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("alias") => {
+                match name_value.lit {
+                    Lit::Str(s) => ref_name = s.parse()?,
+                    lit => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "expected the name of the reactive type as a string literal",
+                        ))
+                    }
+                }
+            }
+            arg => {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    "unknown argument (expected `#[rx(alias = \"..\")]`)",
+                ))
+            }
+        }
+    }
+    // This has to follow the same naming convention as `#[make_rx]`, so that
+    // `#[template_rx]` can find it
+    let mid_name = Ident::new(
+        &(ref_name.to_string() + "PerseusRxIntermediary"),
+        ref_name.span(),
+    );
+
+    let fields = match data {
+        Data::Struct(data) => match data.fields {
+            Fields::Named(fields) => fields.named.into_iter().collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "tuple structs can't be made reactive (try using named fields instead)",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            return Err(syn::Error::new_spanned(
+                data.enum_token,
+                "only structs can be made reactive",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "only structs can be made reactive",
+            ))
+        }
+    };
+
+    // Each of these is spanned to the field it comes from, so that any errors
+    // (e.g. a field that isn't `Clone`) point there (which also means Clippy
+    // will treat them as user code, so we use fully qualified calls)
+    let mut mid_fields = quote!();
+    let mut ref_fields = quote!();
+    let mut make_rx_fields = quote!();
+    let mut make_unrx_fields = quote!();
+    let mut make_ref_fields = quote!();
+    for field in fields.iter() {
+        let mut kind = FieldKind::Signal;
+        for arg in get_rx_args(&field.attrs)? {
+            match arg {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => {
+                    kind = FieldKind::Nested
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    kind = FieldKind::Skip
+                }
+                arg => {
+                    return Err(syn::Error::new_spanned(
+                        arg,
+                        "unknown argument (expected `#[rx(nested)]` or `#[rx(skip)]`)",
+                    ))
+                }
+            }
+        }
+
+        let span = field.span();
+        let field_vis = &field.vis;
+        // We know it has an identifier because it's a named field
+        let field_name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        match kind {
+            FieldKind::Signal => {
+                mid_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: ::sycamore::prelude::RcSignal<#ty>,
+                });
+                ref_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: &'rx ::sycamore::prelude::RcSignal<#ty>,
+                });
+                make_rx_fields.extend(quote_spanned! {span=>
+                    #field_name: ::sycamore::prelude::create_rc_signal(self.#field_name),
+                });
+                make_unrx_fields.extend(quote_spanned! {span=>
+                    #field_name: ::std::clone::Clone::clone(&*self.#field_name.get_untracked()),
+                });
+                make_ref_fields.extend(quote_spanned! {span=>
+                    #field_name: ::sycamore::prelude::create_ref(cx, self.#field_name),
+                });
+            }
+            FieldKind::Nested => {
+                mid_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: <#ty as ::perseus::state::MakeRx>::Rx,
+                });
+                ref_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: &'rx <<#ty as ::perseus::state::MakeRx>::Rx as ::perseus::state::MakeRxRef<'rx>>::RxRef,
+                });
+                make_rx_fields.extend(quote_spanned! {span=>
+                    #field_name: ::perseus::state::MakeRx::make_rx(self.#field_name),
+                });
+                make_unrx_fields.extend(quote_spanned! {span=>
+                    #field_name: ::perseus::state::MakeUnrx::make_unrx(::std::clone::Clone::clone(&self.#field_name)),
+                });
+                make_ref_fields.extend(quote_spanned! {span=>
+                    #field_name: ::sycamore::prelude::create_ref(cx, ::perseus::state::MakeRxRef::to_ref_struct(self.#field_name, cx)),
+                });
+            }
+            FieldKind::Skip => {
+                mid_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: #ty,
+                });
+                ref_fields.extend(quote_spanned! {span=>
+                    #field_vis #field_name: &'rx #ty,
+                });
+                make_rx_fields.extend(quote_spanned! {span=>
+                    #field_name: self.#field_name,
+                });
+                make_unrx_fields.extend(quote_spanned! {span=>
+                    #field_name: ::std::clone::Clone::clone(&self.#field_name),
+                });
+                make_ref_fields.extend(quote_spanned! {span=>
+                    #field_name: ::sycamore::prelude::create_ref(cx, self.#field_name),
+                });
+            }
+        }
+    }
+
+    // The reactive versions get the same generics as the original, but the
+    // implementations need some extra bounds on them (these are the same as what
+    // Perseus needs for any state)
+    let where_clause = &generics.where_clause;
+    let mut bounded_generics = generics.clone();
+    for param in generics.type_params() {
+        let param = &param.ident;
+        bounded_generics.make_where_clause().predicates.push(parse_quote! {
+            #param: ::std::clone::Clone + ::serde::Serialize + ::serde::de::DeserializeOwned + 'static
+        });
+    }
+    let (impl_generics, ty_generics, bounded_where_clause) = bounded_generics.split_for_impl();
+    // The version with references also needs a lifetime for them (unless there
+    // aren't any fields, in which case it would be unused)
+    let rx_lifetime =
+        GenericParam::Lifetime(LifetimeDef::new(Lifetime::new("'rx", Span::call_site())));
+    let mut ref_generics = generics.clone();
+    if !fields.is_empty() {
+        ref_generics.params.insert(0, rx_lifetime.clone());
+    }
+    let (_, ref_ty_generics, _) = ref_generics.split_for_impl();
+    let mut ref_impl_generics = bounded_generics.clone();
+    ref_impl_generics.params.insert(0, rx_lifetime);
+    let (ref_impl_generics, _, _) = ref_impl_generics.split_for_impl();
+
+    Ok(quote! {
+        #[derive(::std::clone::Clone)]
+        #vis struct #mid_name #generics #where_clause {
+            #mid_fields
+        }
+        #[derive(::std::clone::Clone)]
+        #vis struct #ref_name #ref_generics #where_clause {
+            #ref_fields
+        }
+        impl #impl_generics ::perseus::state::MakeRx for #orig_name #ty_generics #bounded_where_clause {
+            type Rx = #mid_name #ty_generics;
+            fn make_rx(self) -> Self::Rx {
+                #mid_name {
+                    #make_rx_fields
+                }
+            }
+        }
+        impl #impl_generics ::perseus::state::MakeUnrx for #mid_name #ty_generics #bounded_where_clause {
+            type Unrx = #orig_name #ty_generics;
+            fn make_unrx(self) -> Self::Unrx {
+                #orig_name {
+                    #make_unrx_fields
+                }
+            }
+        }
+        impl #impl_generics ::perseus::state::Freeze for #mid_name #ty_generics #bounded_where_clause {
+            fn freeze(&self) -> ::std::string::String {
+                let unrx = #orig_name {
+                    #make_unrx_fields
+                };
+                // The original `struct` has to be serializable to be used as state at all
+                ::serde_json::to_string(&unrx).unwrap()
+            }
+        }
+        impl #ref_impl_generics ::perseus::state::MakeRxRef<'rx> for #mid_name #ty_generics #bounded_where_clause {
+            type RxRef = #ref_name #ref_ty_generics;
+            fn to_ref_struct(self, cx: ::sycamore::prelude::Scope<'rx>) -> Self::RxRef {
+                #ref_name {
+                    #make_ref_fields
+                }
+            }
+        }
+        impl #impl_generics #mid_name #ty_generics #bounded_where_clause {
+            // Templates use this to get the version of the state they actually take
+            pub fn to_ref_struct<'rx>(self, cx: ::sycamore::prelude::Scope<'rx>) -> #ref_name #ref_ty_generics {
+                ::perseus::state::MakeRxRef::to_ref_struct(self, cx)
+            }
+        }
+    })
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
 use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use syn::{GenericParam, Ident, ItemStruct, Lifetime, LifetimeDef, Lit, Meta, NestedMeta, Result};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, GenericParam, Ident, ItemStruct, Lifetime, LifetimeDef, Lit, Meta, NestedMeta,
+    Result,
+};
 
 pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream {
     // Note: we create three `struct`s with this macro: the original, the new one
@@ -38,19 +42,23 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 && attr.path.segments.first().unwrap().ident == "rx"
                 && attr.path.segments.last().unwrap().ident == "nested"
         })
-        // Remove any attributes that can't be parsed as a `MetaList`, returning the internal list
-        // of what can (the 'arguments' to the attribute) We need them to be two elements
-        // long (a field name and a wrapper type)
-        .filter_map(|attr| match attr.parse_meta() {
-            Ok(Meta::List(list)) if list.nested.len() == 2 => Some(list.nested),
-            _ => None,
+        // Parse these as a `MetaList`, returning the internal list (the 'arguments' to the
+        // attribute) We need them to be two elements long (a field name and a wrapper type)
+        .map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) if list.nested.len() == 2 => Ok(list.nested),
+            _ => Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[rx::nested(\"field_name\", FieldTypeRx)]`",
+            )),
         })
         // Now parse the tokens within these to an `(Ident, Ident)`, the first being the name of the
         // field and the second being the wrapper type to use
         .map(|meta_list| {
+            let meta_list = meta_list?;
             // Extract field name and wrapper type (we know this only has two elements)
+            // These keep the spans of what the user gave us, so errors about them point there
             let field_name = match meta_list.first().unwrap() {
-                NestedMeta::Lit(Lit::Str(s)) => Ident::new(s.value().as_str(), Span::call_site()),
+                NestedMeta::Lit(Lit::Str(s)) => Ident::new(s.value().as_str(), s.span()),
                 NestedMeta::Lit(val) => {
                     return Err(syn::Error::new_spanned(
                         val,
@@ -65,8 +73,15 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 }
             };
             let wrapper_ty = match meta_list.last().unwrap() {
-                // TODO Is this `.unwrap()` actually safe to use?
-                NestedMeta::Meta(meta) => &meta.path().segments.first().unwrap().ident,
+                NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                    path.get_ident().unwrap()
+                }
+                NestedMeta::Meta(meta) => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "second argument must be reactive wrapper type (without any generics or lifetimes)",
+                    ))
+                }
                 NestedMeta::Lit(val) => {
                     return Err(syn::Error::new_spanned(
                         val,
@@ -86,6 +101,27 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
             Err(err) => return err.to_compile_error(),
         };
     }
+    // Make sure all the nested fields actually exist (otherwise they'd just be
+    // silently ignored)
+    for field_name in nested_fields_map.keys() {
+        let exists = match &orig_struct.fields {
+            syn::Fields::Named(fields) => fields
+                .named
+                .iter()
+                .any(|field| field.ident.as_ref() == Some(field_name)),
+            _ => false,
+        };
+        if !exists {
+            return syn::Error::new(
+                field_name.span(),
+                format!(
+                    "no field named `{}` to use nested reactivity on in `{}`",
+                    field_name, orig_name
+                ),
+            )
+            .to_compile_error();
+        }
+    }
     // Now remove our attributes from all the `struct`s
     let mut filtered_attrs = Vec::new();
     for attr in orig_struct.attrs.iter() {
@@ -109,6 +145,25 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
         0,
         GenericParam::Lifetime(LifetimeDef::new(Lifetime::new("'rx", Span::call_site()))),
     );
+    // Any bounds have to go in the `impl` headers, not on the types themselves,
+    // and the implementations need some extra ones (these are the same as what
+    // Perseus needs for any state)
+    let mut bounded_generics = generics.clone();
+    for param in generics.type_params() {
+        let param = &param.ident;
+        bounded_generics.make_where_clause().predicates.push(parse_quote! {
+            #param: ::std::clone::Clone + ::serde::Serialize + ::serde::de::DeserializeOwned + 'static
+        });
+    }
+    let (impl_generics, ty_generics, where_clause) = bounded_generics.split_for_impl();
+    let ref_generics = ref_struct.generics.clone();
+    let (_, ref_ty_generics, _) = ref_generics.split_for_impl();
+    let mut ref_impl_generics = bounded_generics.clone();
+    ref_impl_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeDef::new(Lifetime::new("'rx", Span::call_site()))),
+    );
+    let (ref_impl_generics, _, _) = ref_impl_generics.split_for_impl();
 
     match mid_struct.fields {
         syn::Fields::Named(ref mut fields) => {
@@ -119,11 +174,13 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 field.ty = if let Some(wrapper_ty) = wrapper_ty {
                     let mid_wrapper_ty = Ident::new(
                         &(wrapper_ty.to_string() + "PerseusRxIntermediary"),
-                        Span::call_site(),
+                        wrapper_ty.span(),
                     );
                     syn::Type::Verbatim(quote!(#mid_wrapper_ty))
                 } else {
-                    syn::Type::Verbatim(quote!(::sycamore::prelude::RcSignal<#orig_ty>))
+                    syn::Type::Verbatim(
+                        quote_spanned!(orig_ty.span()=> ::sycamore::prelude::RcSignal<#orig_ty>),
+                    )
                 };
                 // Remove any `serde` attributes (Serde can't be used with the reactive version)
                 let mut new_attrs = Vec::new();
@@ -137,8 +194,8 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 field.attrs = new_attrs;
             }
         }
-        syn::Fields::Unnamed(_) => return syn::Error::new_spanned(
-            mid_struct,
+        syn::Fields::Unnamed(ref fields) => return syn::Error::new_spanned(
+            fields,
             "tuple structs can't be made reactive with this macro (try using named fields instead)",
         )
         .to_compile_error(),
@@ -160,7 +217,9 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 } else {
                     // This is the only difference from the intermediate `struct` (this lifetime is
                     // declared above)
-                    syn::Type::Verbatim(quote!(&'rx ::sycamore::prelude::RcSignal<#orig_ty>))
+                    syn::Type::Verbatim(
+                        quote_spanned!(orig_ty.span()=> &'rx ::sycamore::prelude::RcSignal<#orig_ty>),
+                    )
                 };
                 // Remove any `serde` attributes (Serde can't be used with the reactive version)
                 let mut new_attrs = Vec::new();
@@ -174,8 +233,8 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
                 field.attrs = new_attrs;
             }
         }
-        syn::Fields::Unnamed(_) => return syn::Error::new_spanned(
-            mid_struct,
+        syn::Fields::Unnamed(ref fields) => return syn::Error::new_spanned(
+            fields,
             "tuple structs can't be made reactive with this macro (try using named fields instead)",
         )
         .to_compile_error(),
@@ -191,13 +250,14 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
             for field in fields.named.iter_mut() {
                 // We know it has an identifier because it's a named field
                 let field_name = field.ident.as_ref().unwrap();
+                let span = field.span();
                 // Check if this field was registered as one to use nested reactivity
                 if nested_fields_map.contains_key(field.ident.as_ref().unwrap()) {
-                    field_assignments.extend(quote! {
+                    field_assignments.extend(quote_spanned! {span=>
                         #field_name: self.#field_name.make_rx(),
                     })
                 } else {
-                    field_assignments.extend(quote! {
+                    field_assignments.extend(quote_spanned! {span=>
                         #field_name: ::sycamore::prelude::create_rc_signal(self.#field_name),
                     });
                 }
@@ -220,9 +280,10 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
             for field in fields.named.iter_mut() {
                 // We know it has an identifier because it's a named field
                 let field_name = field.ident.as_ref().unwrap();
+                let span = field.span();
                 // Check if this field was registered as one to use nested reactivity
                 if nested_fields_map.contains_key(field.ident.as_ref().unwrap()) {
-                    field_assignments.extend(quote! {
+                    field_assignments.extend(quote_spanned! {span=>
                         #field_name: ::sycamore::prelude::create_ref(cx, self.#field_name.to_ref_struct(cx)),
                     })
                 } else {
@@ -249,16 +310,19 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
             for field in fields.named.iter_mut() {
                 // We know it has an identifier because it's a named field
                 let field_name = field.ident.as_ref().unwrap();
+                let span = field.span();
                 // Check if this field was registered as one to use nested reactivity
                 if nested_fields_map.contains_key(field.ident.as_ref().unwrap()) {
-                    field_assignments.extend(quote! {
+                    field_assignments.extend(quote_spanned! {span=>
                         #field_name: self.#field_name.clone().make_unrx(),
                     })
                 } else {
                     // We can `.clone()` the field because we implement `Clone` on both the new and
-                    // the original `struct`s, meaning all fields must also be `Clone`
-                    field_assignments.extend(quote! {
-                        #field_name: (*self.#field_name.get_untracked()).clone(),
+                    // the original `struct`s, meaning all fields must also be `Clone` (this is
+                    // fully qualified so Clippy doesn't complain about `Copy` fields, since the
+                    // span makes this look like user code)
+                    field_assignments.extend(quote_spanned! {span=>
+                        #field_name: ::std::clone::Clone::clone(&*self.#field_name.get_untracked()),
                     });
                 }
             }
@@ -277,23 +341,23 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
         // We add a Serde derivation because it will always be necessary for Perseus on the original `struct`, and it's really difficult and brittle to filter it out
         #[derive(::serde::Serialize, ::serde::Deserialize, ::std::clone::Clone)]
         #orig_struct
-        impl #generics ::perseus::state::MakeRx for #orig_name #generics {
-            type Rx = #mid_name #generics;
-            fn make_rx(self) -> #mid_name #generics {
+        impl #impl_generics ::perseus::state::MakeRx for #orig_name #ty_generics #where_clause {
+            type Rx = #mid_name #ty_generics;
+            fn make_rx(self) -> #mid_name #ty_generics {
                 use ::perseus::state::MakeRx;
                 #make_rx_fields
             }
         }
         #[derive(::std::clone::Clone)]
         #mid_struct
-        impl #generics ::perseus::state::MakeUnrx for #mid_name #generics {
-            type Unrx = #orig_name #generics;
-            fn make_unrx(self) -> #orig_name #generics {
+        impl #impl_generics ::perseus::state::MakeUnrx for #mid_name #ty_generics #where_clause {
+            type Unrx = #orig_name #ty_generics;
+            fn make_unrx(self) -> #orig_name #ty_generics {
                 use ::perseus::state::MakeUnrx;
                 #make_unrx_fields
             }
         }
-        impl #generics ::perseus::state::Freeze for #mid_name #generics {
+        impl #impl_generics ::perseus::state::Freeze for #mid_name #ty_generics #where_clause {
             fn freeze(&self) -> ::std::string::String {
                 use ::perseus::state::MakeUnrx;
                 let unrx = #make_unrx_fields;
@@ -303,10 +367,16 @@ pub fn make_rx_impl(mut orig_struct: ItemStruct, name_raw: Ident) -> TokenStream
         }
         #[derive(::std::clone::Clone)]
         #ref_struct
-        impl #generics #mid_name #generics {
-            pub fn to_ref_struct(self, cx: ::sycamore::prelude::Scope) -> #ref_name #generics {
+        impl #impl_generics #mid_name #ty_generics #where_clause {
+            pub fn to_ref_struct(self, cx: ::sycamore::prelude::Scope) -> #ref_name #ty_generics {
                 #make_ref_fields
             }
         }
+        impl #ref_impl_generics ::perseus::state::MakeRxRef<'rx> for #mid_name #ty_generics #where_clause {
+            type RxRef = #ref_name #ref_ty_generics;
+            fn to_ref_struct(self, cx: ::sycamore::prelude::Scope<'rx>) -> #ref_name #ref_ty_generics {
+                #mid_name::to_ref_struct(self, cx)
+            }
+        }
     }
 }
@@ -1,5 +1,6 @@
 // This file contains all the macros that supersede `autoserde`
 
+use crate::head::is_page_info;
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
@@ -113,7 +114,7 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
             #[cfg(target_arch = "wasm32")]
             #vis fn #name() {}
             #[cfg(not(target_arch = "wasm32"))]
-            #vis async fn #name(path: ::std::string::String, locale: ::std::string::String) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
+            #vis async fn #name(info: ::perseus::template::StateGeneratorInfo) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
                 // The user's function
                 // We can assume the return type to be `RenderFnResultWithCause<CustomTemplatePropsType>`
                 #(#attrs)*
@@ -123,29 +124,43 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
                 // Call the user's function with the usual arguments and then serialize the result to a string
                 // We only serialize the `Ok` outcome, errors are left as-is
                 // We also assume that this will serialize correctly
-                let build_state = #name(path, locale).await;
+                let build_state = #name(info).await;
                 let build_state_with_str = build_state.map(|val| ::serde_json::to_string(&val).unwrap());
                 build_state_with_str
             }
         },
         // This one only exists to appease the server-side/client-side division
-        StateFnType::BuildPaths => quote! {
-            // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
-            #[cfg(target_arch = "wasm32")]
-            #vis fn #name() {}
-            // This normal version is identical to the user's (we know it won't have any arguments, and we know its return type)
-            // We use the user's return type to prevent unused imports warnings in their code
-            #[cfg(not(target_arch = "wasm32"))]
-            #vis async fn #name() -> #return_type {
-                #block
+        StateFnType::BuildPaths => {
+            // The user's function can optionally take the locale (if it generates different
+            // paths for each locale)
+            let call = if args.is_empty() {
+                quote!(#name())
+            } else {
+                quote!(#name(locale))
+            };
+            quote! {
+                // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
+                #[cfg(target_arch = "wasm32")]
+                #vis fn #name() {}
+                // The user can return either a `Vec<String>` or `BuildPaths`, so we convert it
+                // We use the user's return type on their function to prevent unused imports warnings in their code
+                #[cfg(not(target_arch = "wasm32"))]
+                #[allow(unused_variables)]
+                #vis async fn #name(locale: ::std::string::String) -> ::perseus::RenderFnResult<::perseus::template::BuildPaths> {
+                    #(#attrs)*
+                    async fn #name #generics(#args) -> #return_type {
+                        #block
+                    }
+                    #call.await.map(::perseus::template::BuildPaths::from)
+                }
             }
-        },
+        }
         StateFnType::RequestState => quote! {
             // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
             #[cfg(target_arch = "wasm32")]
             #vis fn #name() {}
             #[cfg(not(target_arch = "wasm32"))]
-            #vis async fn #name(path: ::std::string::String, locale: ::std::string::String, req: ::perseus::Request) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
+            #vis async fn #name(info: ::perseus::template::StateGeneratorInfo, req: ::perseus::Request) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
                 // The user's function
                 // We can assume the return type to be `RenderFnResultWithCause<CustomTemplatePropsType>`
                 #(#attrs)*
@@ -155,47 +170,43 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
                 // Call the user's function with the usual arguments and then serialize the result to a string
                 // We only serialize the `Ok` outcome, errors are left as-is
                 // We also assume that this will serialize correctly
-                let req_state = #name(path, locale, req).await;
+                let req_state = #name(info, req).await;
                 let req_state_with_str = req_state.map(|val| ::serde_json::to_string(&val).unwrap());
                 req_state_with_str
             }
         },
         // Always synchronous
         StateFnType::SetHeaders => {
-            if args.len() == 1 {
-                quote! {
-                    // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
-                    #[cfg(target_arch = "wasm32")]
-                    #vis fn #name() {}
-                    #[cfg(not(target_arch = "wasm32"))]
-                    #vis fn #name(props: ::std::option::Option<::std::string::String>) -> ::perseus::http::header::HeaderMap {
-                        // The user's function
-                        // We can assume the return type to be `HeaderMap`
-                        #(#attrs)*
-                        fn #name #generics(#args) -> #return_type {
-                            #block
-                        }
-                        // Deserialize the props and then call the user's function
-                        // Their function is taking state, so this must be defined
-                        let props_de = ::serde_json::from_str(&props.unwrap()).unwrap();
-                        #name(props_de)
-                    }
+            // The user's function can take the state and/or the page information (in
+            // either order), and we only take the page information if it does, so that
+            // this can be given to `.set_headers_fn()` otherwise
+            let info_param = args
+                .iter()
+                .any(is_page_info)
+                .then(|| quote!(, info: ::perseus::template::PageInfo));
+            let call_args = args.iter().map(|arg| {
+                if is_page_info(arg) {
+                    quote!(info)
+                } else {
+                    // Their function is taking state, so this must be defined
+                    quote!(::serde_json::from_str(&props.unwrap()).unwrap())
                 }
-            } else {
-                quote! {
-                    // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
-                    #[cfg(target_arch = "wasm32")]
-                    #vis fn #name() {}
-                    #[cfg(not(target_arch = "wasm32"))]
-                    #vis fn #name(props: ::std::option::Option<::std::string::String>) -> ::perseus::http::header::HeaderMap {
-                        // The user's function
-                        // We can assume the return type to be `HeaderMap`
-                        #(#attrs)*
-                        fn #name #generics() -> #return_type {
-                            #block
-                        }
-                        #name()
+            });
+            quote! {
+                // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
+                #[cfg(target_arch = "wasm32")]
+                #vis fn #name() {}
+                #[cfg(not(target_arch = "wasm32"))]
+                #[allow(unused_variables)]
+                #vis fn #name(props: ::std::option::Option<::std::string::String> #info_param) -> ::perseus::http::header::HeaderMap {
+                    // The user's function
+                    // We can assume the return type to be `HeaderMap`
+                    #(#attrs)*
+                    fn #name #generics(#args) -> #return_type {
+                        #block
                     }
+                    // Deserialize the props (if they're being used) and then call the user's function
+                    #name(#(#call_args),*)
                 }
             }
         }
@@ -205,7 +216,7 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
             #[cfg(target_arch = "wasm32")]
             #vis fn #name() {}
             #[cfg(not(target_arch = "wasm32"))]
-            #vis async fn #name(path: ::std::string::String, locale: ::std::string::String, build_state: ::std::string::String, request_state: ::std::string::String) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
+            #vis async fn #name(info: ::perseus::template::StateGeneratorInfo, build_state: ::std::string::String, request_state: ::std::string::String, req: ::perseus::Request) -> ::perseus::RenderFnResultWithCause<::std::string::String> {
                 // The user's function
                 // We can assume the return type to be `RenderFnResultWithCause<Option<CustomTemplatePropsType>>`
                 #(#attrs)*
@@ -218,7 +229,7 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
                 // Call the user's function with the usual arguments and then serialize the result to a string
                 // We only serialize the `Ok(Some(_))` outcome, errors are left as-is
                 // We also assume that this will serialize correctly
-                let amalgamated_state = #name(path, locale, build_state_de, request_state_de).await;
+                let amalgamated_state = #name(info, build_state_de, request_state_de, req).await;
                 let amalgamated_state_with_str = amalgamated_state.map(|val| ::serde_json::to_string(&val).unwrap());
                 amalgamated_state_with_str
             }
@@ -248,11 +259,15 @@ pub fn state_fn_impl(input: StateFn, fn_type: StateFnType) -> TokenStream {
             // We create a normal version of the function and one to appease the handlers in Wasm (which expect functions that take no arguments, etc.)
             #[cfg(target_arch = "wasm32")]
             #vis fn #name() {}
-            // This normal version is identical to the user's (we know it won't have any arguments, and we know its return type)
+            // This normal version just calls the user's (we know its arguments, and we know its return type)
             // We use the user's return type to prevent unused imports warnings in their code
             #[cfg(not(target_arch = "wasm32"))]
-            #vis async fn #name(path: ::std::string::String, locale: ::std::string::String, req: ::perseus::Request) -> #return_type {
-                #block
+            #vis async fn #name(info: ::perseus::template::StateGeneratorInfo, req: ::perseus::Request) -> #return_type {
+                #(#attrs)*
+                async fn #name #generics(#args) -> #return_type {
+                    #block
+                }
+                #name(info, req).await
             }
         },
     }
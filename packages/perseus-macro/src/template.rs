@@ -77,9 +77,15 @@ impl Parse for TemplateFn {
                     }
                     args.push(arg.clone())
                 }
-                // We can have anywhere between 1 and 3 arguments (scope, ?state, ?global state)
-                if args.len() > 3 || args.is_empty() {
-                    return Err(syn::Error::new_spanned(&sig.inputs, "template functions accept between one and two arguments (reactive scope; then one optional for custom properties)"));
+                // We can have one or two arguments (scope, ?state)
+                if args.len() > 2 || args.is_empty() {
+                    let msg = "template functions accept between one and two arguments (reactive scope; then one optional for custom properties)";
+                    // If there are no arguments, point to the empty parentheses
+                    return Err(if args.is_empty() {
+                        syn::Error::new(sig.paren_token.span, msg)
+                    } else {
+                        syn::Error::new_spanned(&sig.inputs, msg)
+                    });
                 }
 
                 Ok(Self {
@@ -1,13 +1,9 @@
-use std::str::FromStr;
-
-use darling::ToTokens;
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use regex::Regex;
 use syn::parse::{Parse, ParseStream};
 use syn::{
-    Attribute, Block, FnArg, Generics, Ident, Item, ItemFn, PatType, Result, ReturnType, Type,
-    TypeTuple, Visibility,
+    Attribute, Block, FnArg, GenericArgument, Generics, Ident, Item, ItemFn, PatType,
+    PathArguments, Result, ReturnType, Type, TypePath, TypeTuple, Visibility,
 };
 
 /// A function that can be wrapped in the Perseus test sub-harness.
@@ -84,7 +80,13 @@ impl Parse for TemplateFn {
                 }
                 // We can have anywhere between 1 and 3 arguments (scope, ?state, ?global state)
                 if args.len() > 3 || args.is_empty() {
-                    return Err(syn::Error::new_spanned(&sig.inputs, "template functions accept between one and three arguments (reactive scope; then one for custom properties and another for global state, both optional)"));
+                    let msg = "template functions accept between one and three arguments (reactive scope; then one for custom properties and another for global state, both optional)";
+                    // If there are no arguments, point to the empty parentheses
+                    return Err(if args.is_empty() {
+                        syn::Error::new(sig.paren_token.span, msg)
+                    } else {
+                        syn::Error::new_spanned(&sig.inputs, msg)
+                    });
                 }
 
                 Ok(Self {
@@ -108,23 +110,40 @@ impl Parse for TemplateFn {
 /// Converts the user-given name of a final reactive `struct` into the
 /// intermediary name used for the one we'll interface with. This will remove
 /// any associated lifetimes because we want just the type name. This will leave
-/// generics intact though.
-fn make_mid(ty: &Type) -> Type {
-    // Don't run any transformation if this is the unit type
+/// generics intact though. The new type keeps the span of the original, so any
+/// errors about it will point to the user's type, not the whole macro.
+fn make_mid(ty: &Type) -> Result<Type> {
     match ty {
-        Type::Tuple(TypeTuple { elems, .. }) if elems.is_empty() => ty.clone(),
-        _ => {
-            let ty_str = ty.to_token_stream().to_string();
-            // Remove any lifetimes from the type (anything in angular brackets beginning
-            // with `'`) This regex just removes any lifetimes next to generics
-            // or on their own, allowing for the whitespace Syn seems to insert
-            let ty_str = Regex::new(r#"(('.*?) |<\s*('[^, ]*?)\s*>)"#)
-                .unwrap()
-                .replace_all(&ty_str, "");
-            // And now actually make the replacement we need (ref to intermediate)
-            let ty_str = ty_str.trim().to_string() + "PerseusRxIntermediary";
-            Type::Verbatim(TokenStream::from_str(&ty_str).unwrap())
+        // Don't run any transformation if this is the unit type
+        Type::Tuple(TypeTuple { elems, .. }) if elems.is_empty() => Ok(ty.clone()),
+        Type::Group(group) => make_mid(&group.elem),
+        Type::Path(TypePath { qself: None, path }) => {
+            let mut path = path.clone();
+            // Paths always have at least one segment
+            let last = path.segments.last_mut().unwrap();
+            last.ident = Ident::new(
+                &(last.ident.to_string() + "PerseusRxIntermediary"),
+                last.ident.span(),
+            );
+            // Remove any lifetimes, leaving the other generics intact
+            if let PathArguments::AngleBracketed(args) = &mut last.arguments {
+                args.args = args
+                    .args
+                    .iter()
+                    .filter(|arg| !matches!(arg, GenericArgument::Lifetime(_)))
+                    .cloned()
+                    .collect();
+                if args.args.is_empty() {
+                    last.arguments = PathArguments::None;
+                }
+            }
+
+            Ok(Type::Path(TypePath { qself: None, path }))
         }
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "template state must be a reactive state type (the name given to `#[make_rx(..)]`), or `()` if the template only takes global state",
+        )),
     }
 }
 
@@ -156,6 +175,10 @@ pub fn template_impl(input: TemplateFn) -> TokenStream {
             FnArg::Typed(PatType { ty, .. }) => make_mid(&**ty),
             FnArg::Receiver(_) => unreachable!(),
         };
+        let rx_props_ty = match rx_props_ty {
+            Ok(ty) => ty,
+            Err(err) => return err.to_compile_error(),
+        };
         // There's also a second argument for the global state, which we'll deserialize
         // and make global if it's not already (aka. if any other pages have loaded
         // before this one) Sycamore won't let us have more than one argument to
@@ -166,6 +189,10 @@ pub fn template_impl(input: TemplateFn) -> TokenStream {
             FnArg::Typed(PatType { pat, ty, .. }) => (pat, make_mid(&**ty)),
             FnArg::Receiver(_) => unreachable!(),
         };
+        let global_state_rx = match global_state_rx {
+            Ok(ty) => ty,
+            Err(err) => return err.to_compile_error(),
+        };
         let name_string = name.to_string();
         // Handle the case in which the template is just using global state and the
         // first argument is the unit type That's represented for Syn as a typle
@@ -267,6 +294,10 @@ pub fn template_impl(input: TemplateFn) -> TokenStream {
             FnArg::Typed(PatType { ty, .. }) => make_mid(&**ty),
             FnArg::Receiver(_) => unreachable!(),
         };
+        let rx_props_ty = match rx_props_ty {
+            Ok(ty) => ty,
+            Err(err) => return err.to_compile_error(),
+        };
         let name_string = name.to_string();
         quote! {
             #vis fn #name<G: ::sycamore::prelude::Html>(cx: ::sycamore::prelude::Scope, props: ::perseus::template::PageProps) -> ::sycamore::prelude::View<G> {
@@ -0,0 +1,405 @@
+// This file contains the compile-time checks that the `t!` macro runs against
+// an app's Fluent translations, if it can find them
+
+use crate::warning::warning;
+use fluent_syntax::ast::{
+    CallArguments, Entry, Expression, InlineExpression, Message, Pattern, PatternElement, Resource,
+    VariantKey,
+};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{braced, Expr, Lit, LitStr, Token};
+
+/// The directory translations are expected to be in, relative to the root of
+/// the crate being compiled. This is where `FsTranslationsManager` looks by
+/// default.
+const TRANSLATIONS_DIR: &str = "translations";
+/// The file extension of Fluent translations files.
+const FLUENT_FILE_EXT: &str = "ftl";
+/// The plural categories that a number can be matched against. A variable used
+/// to select between variants with any of these as keys (or with numbers as
+/// keys) is assumed to be numeric. (`other` is left out, since it's often the
+/// default variant when selecting on strings too.)
+const PLURAL_CATEGORIES: [&str; 5] = ["zero", "one", "two", "few", "many"];
+
+/// The translations files for each locale, with their paths and their parsed
+/// contents (if they could be parsed).
+type TranslationsFiles = BTreeMap<String, (PathBuf, Option<Arc<Resource<String>>>)>;
+
+/// A call to the `t!` macro that should be checked.
+pub struct TranslationCheck {
+    /// The path to the Perseus crate (this is given to us as `$crate`).
+    krate: TokenTree,
+    /// The translation ID, which will only be checked if it's a string
+    /// literal.
+    id: Expr,
+    /// The arguments given for interpolation.
+    args: Punctuated<TranslationArg, Token![,]>,
+}
+impl Parse for TranslationCheck {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let krate = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let id = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let content;
+        braced!(content in input);
+        let args = content.parse_terminated(TranslationArg::parse)?;
+
+        Ok(Self { krate, id, args })
+    }
+}
+
+/// An argument given to the `t!` macro.
+pub struct TranslationArg {
+    key: LitStr,
+    value: Expr,
+}
+impl Parse for TranslationArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+
+        Ok(Self { key, value })
+    }
+}
+
+/// The variables a translation uses in one locale.
+#[derive(Default)]
+struct Variables {
+    /// All the variables used.
+    all: HashSet<String>,
+    /// The variables that are used as numbers.
+    numeric: HashSet<String>,
+}
+
+/// A translations file that's already been parsed, along with when it was last
+/// modified. If it couldn't be parsed, this will hold `None`.
+struct ParsedFile {
+    modified: SystemTime,
+    resource: Option<Arc<Resource<String>>>,
+}
+
+/// The translations files that have already been parsed in this compilation,
+/// by path, so that they're only parsed once, rather than for every call to
+/// `t!`. Procedural macros can stay loaded across compilations (e.g. in IDEs),
+/// so files are parsed again if they've been modified since.
+static PARSED_FILES: Mutex<Option<HashMap<PathBuf, ParsedFile>>> = Mutex::new(None);
+
+pub fn translation_check_impl(input: TranslationCheck) -> TokenStream {
+    let TranslationCheck { krate, id, args } = input;
+    let id = match strip_groups(&id) {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(id) => id.clone(),
+            _ => return TokenStream::new(),
+        },
+        // We can't check IDs that are only known at runtime
+        _ => return TokenStream::new(),
+    };
+    let files = match get_translations_files() {
+        Some(files) if !files.is_empty() => files,
+        _ => return TokenStream::new(),
+    };
+
+    let id_str = id.value();
+    let (msg_id, attr_id) = match id_str.split_once('.') {
+        Some((msg_id, attr_id)) => (msg_id, Some(attr_id)),
+        None => (id_str.as_str(), None),
+    };
+    let provided = args
+        .iter()
+        .map(|arg| arg.key.value())
+        .collect::<HashSet<_>>();
+    // These are all warnings rather than errors, since the translations can be
+    // changed without recompiling the app
+    let mut warnings = TokenStream::new();
+    let mut warn = |span, msg: String| {
+        warnings.extend(warning("translation_mismatch", span, &msg));
+    };
+    let mut used = HashSet::new();
+    let mut numeric = HashSet::new();
+    for (locale, (_, resource)) in &files {
+        // Translations that don't parse will fail when the app loads them anyway, so we
+        // won't try to check against them
+        let resource = match resource {
+            Some(resource) => resource,
+            None => continue,
+        };
+        let vars = match get_variables(resource, msg_id, attr_id) {
+            Ok(vars) => vars,
+            Err(msg) => {
+                warn(
+                    id.span(),
+                    format!("translation id `{}` {} in locale `{}`", id_str, msg, locale),
+                );
+                continue;
+            }
+        };
+        let mut missing = vars.all.difference(&provided).collect::<Vec<_>>();
+        missing.sort();
+        for var in missing {
+            warn(
+                id.span(),
+                format!(
+                    "translation `{}` uses the variable `${}` in locale `{}`, but no argument was given for it",
+                    id_str, var, locale
+                ),
+            );
+        }
+        used.extend(vars.all);
+        numeric.extend(vars.numeric);
+    }
+    for arg in args.iter() {
+        if !used.contains(&arg.key.value()) {
+            warn(
+                arg.key.span(),
+                format!(
+                    "argument `{}` isn't used by translation `{}` in any locale",
+                    arg.key.value(),
+                    id_str
+                ),
+            );
+        }
+    }
+
+    // Numeric variables should be given numbers, which we check without evaluating
+    // the arguments by putting them in a closure that's never called
+    let numeric_checks = args
+        .iter()
+        .filter(|arg| numeric.contains(&arg.key.value()))
+        .map(|TranslationArg { value, .. }| {
+            quote_spanned! {strip_groups(value).span()=>
+                let _ = || #krate::i18n::check_numeric_arg(&#value);
+            }
+        });
+    let file_paths = files
+        .values()
+        .map(|(path, _)| path.to_string_lossy().to_string());
+    quote! {
+        // This makes sure the crate is recompiled when the translations change, so these checks
+        // are rerun
+        #(const _: &str = include_str!(#file_paths);)*
+        #warnings
+        #(#numeric_checks)*
+    }
+}
+
+/// Removes any invisible groups around the given expression, which it will be
+/// wrapped in if it came from a `macro_rules!` macro.
+fn strip_groups(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Group(group) => strip_groups(&group.expr),
+        Expr::Paren(paren) => strip_groups(&paren.expr),
+        expr => expr,
+    }
+}
+
+/// Gets all the Fluent translations files in the translations directory of the
+/// crate being compiled, returning their paths and their parsed contents (if
+/// they could be parsed) by locale. Files that can't be read are skipped. If
+/// the directory can't be found, this will return `None`.
+///
+/// The directory is `translations/` by default, but this can be changed by
+/// setting the `PERSEUS_TRANSLATIONS_DIR` environment variable (relative to
+/// the root of the crate) at compile-time.
+fn get_translations_files() -> Option<TranslationsFiles> {
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").ok()?);
+    let dir = match std::env::var("PERSEUS_TRANSLATIONS_DIR") {
+        Ok(dir) => root.join(dir),
+        Err(_) => root.join(TRANSLATIONS_DIR),
+    };
+    let mut parsed_files = PARSED_FILES.lock().unwrap_or_else(|err| err.into_inner());
+    let parsed_files = parsed_files.get_or_insert_with(HashMap::new);
+    let mut files = BTreeMap::new();
+    for entry in std::fs::read_dir(dir).ok()? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(_) => continue,
+        };
+        if path.extension().and_then(|ext| ext.to_str()) != Some(FLUENT_FILE_EXT) {
+            continue;
+        }
+        let locale = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(locale) => locale.to_string(),
+            None => continue,
+        };
+        let modified = match std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(_) => continue,
+        };
+        let resource = match parsed_files.get(&path) {
+            Some(parsed) if parsed.modified == modified => parsed.resource.clone(),
+            _ => {
+                let source = match std::fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(_) => continue,
+                };
+                let resource = fluent_syntax::parser::parse(source).ok().map(Arc::new);
+                parsed_files.insert(
+                    path.clone(),
+                    ParsedFile {
+                        modified,
+                        resource: resource.clone(),
+                    },
+                );
+                resource
+            }
+        };
+        files.insert(locale, (path, resource));
+    }
+
+    Some(files)
+}
+
+/// Gets the variables used by the given message (or the given attribute of it)
+/// in the given translations, returning a description of the problem if it
+/// can't be translated.
+fn get_variables<'a>(
+    resource: &'a Resource<String>,
+    msg_id: &'a str,
+    attr_id: Option<&str>,
+) -> Result<Variables, &'static str> {
+    let msg = find_message(resource, msg_id).ok_or("not found")?;
+    let pattern = match attr_id {
+        Some(attr_id) => msg
+            .attributes
+            .iter()
+            .find(|attr| attr.id.name == attr_id)
+            .map(|attr| &attr.value)
+            .ok_or("has no such variant")?,
+        None => msg
+            .value
+            .as_ref()
+            .ok_or("is a compound message, and needs a variant (e.g. `id.variant`)")?,
+    };
+    let mut vars = Variables::default();
+    let mut visited = HashSet::new();
+    visited.insert(msg_id);
+    collect_pattern(resource, pattern, &mut vars, &mut visited);
+
+    Ok(vars)
+}
+
+/// Finds the message with the given ID in the given translations.
+fn find_message<'a>(resource: &'a Resource<String>, id: &str) -> Option<&'a Message<String>> {
+    resource.body.iter().find_map(|entry| match entry {
+        Entry::Message(msg) if msg.id.name == id => Some(msg),
+        _ => None,
+    })
+}
+
+/// Collects the variables used in the given pattern. Messages referenced by it
+/// get the same arguments, so their variables are collected too (the messages
+/// in `visited` are skipped, which stops cycles).
+fn collect_pattern<'a>(
+    resource: &'a Resource<String>,
+    pattern: &'a Pattern<String>,
+    vars: &mut Variables,
+    visited: &mut HashSet<&'a str>,
+) {
+    for elem in &pattern.elements {
+        if let PatternElement::Placeable { expression } = elem {
+            collect_expression(resource, expression, vars, visited);
+        }
+    }
+}
+
+/// Collects the variables used in the given expression.
+fn collect_expression<'a>(
+    resource: &'a Resource<String>,
+    expr: &'a Expression<String>,
+    vars: &mut Variables,
+    visited: &mut HashSet<&'a str>,
+) {
+    match expr {
+        Expression::Select { selector, variants } => {
+            let is_numeric = variants.iter().any(|variant| match &variant.key {
+                VariantKey::NumberLiteral { .. } => true,
+                VariantKey::Identifier { name } => PLURAL_CATEGORIES.contains(&name.as_str()),
+            });
+            if let (true, InlineExpression::VariableReference { id }) = (is_numeric, selector) {
+                vars.numeric.insert(id.name.to_string());
+            }
+            collect_inline(resource, selector, vars, visited);
+            for variant in variants {
+                collect_pattern(resource, &variant.value, vars, visited);
+            }
+        }
+        Expression::Inline(inline) => collect_inline(resource, inline, vars, visited),
+    }
+}
+
+/// Collects the variables used in the given inline expression.
+fn collect_inline<'a>(
+    resource: &'a Resource<String>,
+    expr: &'a InlineExpression<String>,
+    vars: &mut Variables,
+    visited: &mut HashSet<&'a str>,
+) {
+    match expr {
+        InlineExpression::VariableReference { id } => {
+            vars.all.insert(id.name.to_string());
+        }
+        InlineExpression::FunctionReference { id, arguments } => {
+            if id.name == "NUMBER" {
+                for arg in &arguments.positional {
+                    if let InlineExpression::VariableReference { id } = arg {
+                        vars.numeric.insert(id.name.to_string());
+                    }
+                }
+            }
+            collect_arguments(resource, arguments, vars, visited);
+        }
+        // Terms only get the arguments they're explicitly given, so we don't look
+        // inside them
+        InlineExpression::TermReference {
+            arguments: Some(arguments),
+            ..
+        } => collect_arguments(resource, arguments, vars, visited),
+        InlineExpression::MessageReference { id, attribute } => {
+            if !visited.insert(id.name.as_str()) {
+                return;
+            }
+            if let Some(msg) = find_message(resource, &id.name) {
+                let pattern = match attribute {
+                    Some(attribute) => msg
+                        .attributes
+                        .iter()
+                        .find(|attr| attr.id.name == attribute.name)
+                        .map(|attr| &attr.value),
+                    None => msg.value.as_ref(),
+                };
+                if let Some(pattern) = pattern {
+                    collect_pattern(resource, pattern, vars, visited);
+                }
+            }
+        }
+        InlineExpression::Placeable { expression } => {
+            collect_expression(resource, expression, vars, visited)
+        }
+        _ => (),
+    }
+}
+
+/// Collects the variables used in the given arguments to a function or term.
+fn collect_arguments<'a>(
+    resource: &'a Resource<String>,
+    arguments: &'a CallArguments<String>,
+    vars: &mut Variables,
+    visited: &mut HashSet<&'a str>,
+) {
+    for arg in &arguments.positional {
+        collect_inline(resource, arg, vars, visited);
+    }
+    for arg in &arguments.named {
+        collect_inline(resource, &arg.value, vars, visited);
+    }
+}
@@ -0,0 +1,21 @@
+// This file contains the workaround the macros use to emit warnings, since
+// there's no way for procedural macros to do that on stable Rust
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+/// Produces a warning with the given message at the given place. This is done
+/// by using a deprecated constant with the given name, so the warning will be
+/// shown as `use of deprecated constant `_::<name>`: <msg>`.
+pub fn warning(name: &str, span: Span, msg: &str) -> TokenStream {
+    let usage = Ident::new(name, span);
+    let name = Ident::new(name, Span::call_site());
+    quote! {
+        const _: () = {
+            #[deprecated(note = #msg)]
+            #[allow(non_upper_case_globals)]
+            const #name: () = ();
+            #usage
+        };
+    }
+}
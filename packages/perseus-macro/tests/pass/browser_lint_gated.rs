@@ -0,0 +1,38 @@
+#![deny(deprecated)]
+
+use perseus_macro::template;
+
+// The template macro expands to code using `::perseus`, so we stand in for it
+// here
+extern crate self as perseus;
+pub mod template {
+    pub struct PageProps {
+        pub state: Option<String>,
+    }
+}
+mod instant {
+    pub struct Instant;
+    impl Instant {
+        pub fn now() -> Self {
+            Self
+        }
+    }
+}
+
+#[template]
+fn index_page<G: sycamore::prelude::Html>(
+    cx: sycamore::prelude::Scope,
+) -> sycamore::prelude::View<G> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = std::fs::read_to_string("foo.txt");
+    #[cfg(all(feature = "foo", not(target_arch = "wasm32")))]
+    let _ = std::time::SystemTime::now();
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        let _ = std::time::SystemTime::now();
+    }
+    let _ = instant::Instant::now();
+    sycamore::view! { cx, p { "Hello World!" } }
+}
+
+fn main() {}
@@ -0,0 +1,62 @@
+use perseus_macro::make_rx;
+use std::fmt::Display;
+
+// The generated code uses these traits from `::perseus`, so we stand in for
+// them here
+extern crate self as perseus;
+pub mod state {
+    pub trait MakeRx {
+        type Rx: MakeUnrx;
+        fn make_rx(self) -> Self::Rx;
+    }
+    pub trait MakeUnrx {
+        type Unrx: serde::Serialize + for<'de> serde::Deserialize<'de> + MakeRx;
+        fn make_unrx(self) -> Self::Unrx;
+    }
+    pub trait MakeRxRef<'rx> {
+        type RxRef;
+        fn to_ref_struct(self, cx: sycamore::prelude::Scope<'rx>) -> Self::RxRef;
+    }
+    pub trait Freeze {
+        fn freeze(&self) -> String;
+    }
+}
+
+#[make_rx(GenericRx)]
+struct Generic<T> {
+    value: T,
+    label: String,
+}
+
+#[make_rx(BoundedRx)]
+struct Bounded<T: Display, U>
+where
+    U: Default,
+{
+    first: T,
+    second: Vec<U>,
+}
+
+fn main() {
+    use perseus::state::{Freeze, MakeRx, MakeRxRef, MakeUnrx};
+
+    let generic = Generic {
+        value: 5u8,
+        label: "five".to_string(),
+    }
+    .make_rx();
+    assert_eq!(generic.freeze(), r#"{"value":5,"label":"five"}"#);
+    let bounded = Bounded {
+        first: true,
+        second: vec![1i32, 2],
+    }
+    .make_rx();
+    let _ = sycamore::prelude::create_scope_immediate(|cx| {
+        let rx: GenericRx<'_, u8> = MakeRxRef::to_ref_struct(generic.clone(), cx);
+        rx.value.set(6);
+        let rx: BoundedRx<'_, bool, i32> = bounded.clone().to_ref_struct(cx);
+        assert!(*rx.first.get());
+    });
+    assert_eq!(generic.make_unrx().value, 6);
+    assert_eq!(bounded.make_unrx().second, vec![1, 2]);
+}
@@ -0,0 +1,63 @@
+use sycamore::prelude::{view, Scope, SsrNode, View};
+
+// The generated code uses these types from `::perseus`, so we stand in for
+// them here
+extern crate self as perseus;
+pub mod template {
+    pub struct PageProps {
+        pub path: String,
+        pub state: Option<String>,
+    }
+    pub struct PageInfo {
+        pub path: String,
+        pub locale: String,
+        pub query: Option<String>,
+    }
+}
+pub mod http {
+    pub mod header {
+        pub struct HeaderMap;
+    }
+}
+
+// The user's own state can be called `PageInfo` without being mistaken for
+// Perseus' one
+#[derive(serde::Deserialize)]
+struct PageInfo {
+    title: String,
+}
+
+#[perseus_macro::head]
+fn head(cx: Scope, state: PageInfo) -> View<SsrNode> {
+    view! { cx, title { (state.title) } }
+}
+#[perseus_macro::head]
+fn head_with_info(
+    cx: Scope,
+    info: perseus::template::PageInfo,
+    state: PageInfo,
+) -> View<SsrNode> {
+    let title = format!("{} ({})", state.title, info.path);
+    view! { cx, title { (title) } }
+}
+
+#[perseus_macro::set_headers]
+fn set_headers(_state: PageInfo) -> perseus::http::header::HeaderMap {
+    perseus::http::header::HeaderMap
+}
+#[perseus_macro::set_headers]
+fn set_headers_with_info(
+    _info: ::perseus::template::PageInfo,
+) -> perseus::http::header::HeaderMap {
+    perseus::http::header::HeaderMap
+}
+
+fn main() {
+    // Functions that don't take the page information keep the signatures
+    // `.head()` and `.set_headers_fn()` expect
+    let _: fn(Scope, template::PageProps) -> View<SsrNode> = head;
+    let _: fn(Scope, template::PageProps, template::PageInfo) -> View<SsrNode> = head_with_info;
+    let _: fn(Option<String>) -> http::header::HeaderMap = set_headers;
+    let _: fn(Option<String>, template::PageInfo) -> http::header::HeaderMap =
+        set_headers_with_info;
+}
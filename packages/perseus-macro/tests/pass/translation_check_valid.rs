@@ -0,0 +1,19 @@
+#![deny(deprecated)]
+
+use perseus_macro::check_translation;
+
+// The checks generate code that uses `$crate::i18n`, so we stand in for it here
+mod i18n {
+    pub trait NumericArg {}
+    impl NumericArg for i32 {}
+    impl<T: NumericArg + ?Sized> NumericArg for &T {}
+    pub fn check_numeric_arg<T: NumericArg>(_arg: &T) {}
+}
+
+fn main() {
+    let name = "Alice".to_string();
+    check_translation!(crate, "greeting", { "name" = name });
+    check_translation!(crate, "greeting", { "name" = "Bob" });
+    // IDs that are only known at runtime can't be checked
+    check_translation!(crate, &format!("gr{}", "eeting"), {});
+}
@@ -0,0 +1,14 @@
+// These tests make sure the macros give useful errors (pointing to the right
+// code) when they're used incorrectly
+
+#[test]
+fn ui() {
+    // The `t!` checks will use these translations
+    std::env::set_var(
+        "PERSEUS_TRANSLATIONS_DIR",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/translations"),
+    );
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/pass/*.rs");
+}
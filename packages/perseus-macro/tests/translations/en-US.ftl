@@ -0,0 +1,5 @@
+greeting = Hello, { $name }!
+emails = { $count ->
+    [one] You have one email.
+   *[other] You have { $count } emails.
+}
@@ -0,0 +1,24 @@
+#![deny(deprecated)]
+
+use perseus_macro::template;
+
+// The template macro expands to code using `::perseus`, so we stand in for it
+// here
+extern crate self as perseus;
+pub mod template {
+    pub struct PageProps {
+        pub state: Option<String>,
+    }
+}
+
+#[template]
+fn index_page<G: sycamore::prelude::Html>(
+    cx: sycamore::prelude::Scope,
+) -> sycamore::prelude::View<G> {
+    // This will still be compiled for Wasm if the feature is enabled
+    #[cfg(any(not(target_arch = "wasm32"), feature = "foo"))]
+    let _ = std::fs::read_to_string("foo.txt");
+    sycamore::view! { cx, p { "Hello World!" } }
+}
+
+fn main() {}
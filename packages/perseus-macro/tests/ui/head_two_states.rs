@@ -0,0 +1,11 @@
+use sycamore::prelude::{view, Scope, SsrNode, View};
+
+// Without its full path, this is taken to be the user's own state
+struct PageInfo;
+
+#[perseus_macro::head]
+fn head(cx: Scope, state: String, info: PageInfo) -> View<SsrNode> {
+    view! { cx, title { (state) } }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use perseus_macro::make_rx;
+
+#[make_rx(TestRx)]
+#[rx::nested("nested", NestedRx)]
+struct Test {
+    foo: String,
+    nestd: Nested,
+}
+
+struct Nested {
+    bar: String,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use perseus_macro::make_rx;
+
+#[make_rx(TestRx)]
+struct Test(String, u16);
+
+fn main() {}
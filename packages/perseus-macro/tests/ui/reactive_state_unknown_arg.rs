@@ -0,0 +1,14 @@
+use perseus_macro::ReactiveState;
+
+#[derive(ReactiveState)]
+struct Test {
+    foo: String,
+    #[rx(nestd)]
+    bar: Bar,
+}
+
+struct Bar {
+    baz: String,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use perseus_macro::template;
+
+#[template]
+fn index_page<G: sycamore::prelude::Html>() -> sycamore::prelude::View<G> {
+    todo!()
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use perseus_macro::template_rx;
+
+#[template_rx]
+async fn index_page<G: sycamore::prelude::Html>(
+    cx: sycamore::prelude::Scope,
+) -> sycamore::prelude::View<G> {
+    sycamore::view! { cx, p { "Hello World!" } }
+}
+
+fn main() {}
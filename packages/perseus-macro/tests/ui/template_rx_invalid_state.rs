@@ -0,0 +1,11 @@
+use perseus_macro::template_rx;
+
+#[template_rx]
+fn index_page<'a, G: sycamore::prelude::Html>(
+    cx: sycamore::prelude::Scope<'a>,
+    state: &'a str,
+) -> sycamore::prelude::View<G> {
+    sycamore::view! { cx, p { (state) } }
+}
+
+fn main() {}
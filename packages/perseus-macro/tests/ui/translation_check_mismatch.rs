@@ -0,0 +1,18 @@
+#![deny(deprecated)]
+
+use perseus_macro::check_translation;
+
+// The checks generate code that uses `$crate::i18n`, so we stand in for it here
+mod i18n {
+    pub trait NumericArg {}
+    impl NumericArg for i32 {}
+    impl<T: NumericArg + ?Sized> NumericArg for &T {}
+    pub fn check_numeric_arg<T: NumericArg>(_arg: &T) {}
+}
+
+fn main() {
+    check_translation!(crate, "nonexistent", {});
+    check_translation!(crate, "emails", { "count" = 5 });
+    check_translation!(crate, "greeting", {});
+    check_translation!(crate, "greeting", { "name" = "Alice", "extra" = 5 });
+}
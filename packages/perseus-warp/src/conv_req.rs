@@ -1,4 +1,5 @@
-use perseus::http;
+use perseus::{http, server::RemoteAddr};
+use std::net::SocketAddr;
 use warp::{path::FullPath, Filter, Rejection};
 
 /// A Warp filter for extracting an HTTP request directly, which is slightly different to how the Actix Web integration handles this. Modified from [here](https://github.com/seanmonstar/warp/issues/139#issuecomment-853153712).
@@ -12,20 +13,26 @@ pub fn get_http_req() -> impl Filter<Extract = (http::Request<()>,), Error = Rej
                 .or_else(|_| async move { Ok::<_, Rejection>((String::new(),)) }),
         )
         .and(warp::header::headers_cloned())
-        .and_then(|method, path: FullPath, query, headers| async move {
-            let uri = http::uri::Builder::new()
-                .path_and_query(format!("{}?{}", path.as_str(), query))
-                .build()
-                .unwrap();
+        .and(warp::addr::remote())
+        .and_then(
+            |method, path: FullPath, query, headers, remote_addr: Option<SocketAddr>| async move {
+                let uri = http::uri::Builder::new()
+                    .path_and_query(format!("{}?{}", path.as_str(), query))
+                    .build()
+                    .unwrap();
 
-            let mut request = http::Request::builder()
-                .method(method)
-                .uri(uri)
-                .body(()) // We don't do anything with the body in Perseus, so this is irrelevant
-                .unwrap();
+                let mut request = http::Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .body(()) // We don't do anything with the body in Perseus, so this is irrelevant
+                    .unwrap();
 
-            *request.headers_mut() = headers;
+                *request.headers_mut() = headers;
+                if let Some(remote_addr) = remote_addr {
+                    request.extensions_mut().insert(RemoteAddr(remote_addr));
+                }
 
-            Ok::<http::Request<()>, Rejection>(request)
-        })
+                Ok::<http::Request<()>, Rejection>(request)
+            },
+        )
 }
@@ -0,0 +1,34 @@
+use perseus::{
+    csrf::{verify_csrf, CSRF_HEADER},
+    errors::CsrfError,
+    http::{header::COOKIE, HeaderMap, Method},
+};
+use warp::{reject::Reject, Filter, Rejection};
+
+/// The rejection produced by [`csrf_protection`] when a request fails CSRF
+/// validation. You'll probably want to turn this into a `403 Forbidden` with
+/// `.recover()`.
+#[derive(Debug)]
+pub struct CsrfRejection(pub CsrfError);
+impl Reject for CsrfRejection {}
+
+/// A Warp filter that rejects any requests with unsafe methods (e.g. `POST`)
+/// that don't have a valid CSRF token. This should be added to any mutating
+/// routes you add to your server, and it requires CSRF protection to have been
+/// enabled on your `PerseusApp`, so that users are actually given tokens.
+pub fn csrf_protection() -> impl Filter<Extract = (), Error = Rejection> + Copy {
+    warp::method()
+        .and(warp::header::headers_cloned())
+        .and_then(|method: Method, headers: HeaderMap| async move {
+            let cookie_headers = headers
+                .get_all(COOKIE)
+                .iter()
+                .filter_map(|header| header.to_str().ok());
+            let token = headers
+                .get(CSRF_HEADER)
+                .and_then(|header| header.to_str().ok());
+            verify_csrf(method.as_str(), cookie_headers, token)
+                .map_err(|err| warp::reject::custom(CsrfRejection(err)))
+        })
+        .untuple_one()
+}
@@ -1,18 +1,26 @@
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    csrf::get_or_mint_csrf_token,
+    errors::{err_to_redirect, err_to_status_code, ServerError},
+    experiments::{assign_variants, get_variants},
     i18n::{TranslationsManager, Translator},
-    router::{match_route_atomic, RouteInfoAtomic, RouteVerdictAtomic},
+    router::{
+        get_index_redirect, match_route_atomic, RenderCfg, RouteInfoAtomic, RouteVerdictAtomic,
+    },
     server::{
-        build_error_page, get_page_for_template, get_path_slice, GetPageProps, HtmlShell,
-        ServerOptions,
+        attach_request_context, build_error_page, build_server_error_page, get_page_for_template,
+        get_path_slice, GetPageProps, HtmlShell, ServerOptions,
     },
     stores::{ImmutableStore, MutableStore},
-    utils::get_path_prefix_server,
+    template::PageInfo,
+    utils::{get_path_prefix_server, is_private_response, make_private},
     ErrorPages, SsrNode,
 };
-use std::{collections::HashMap, rc::Rc, sync::Arc};
-use warp::{http::Response, path::FullPath};
+use std::{rc::Rc, sync::Arc};
+use warp::{
+    http::{header::SET_COOKIE, HeaderValue, Response, StatusCode},
+    path::FullPath,
+};
 
 /// Builds on the internal Perseus primitives to provide a utility function that
 /// returns a `Response` automatically.
@@ -32,26 +40,69 @@ fn return_error_page(
 /// The handler for calls to any actual pages (first-time visits), which will
 /// render the appropriate HTML and then interpolate it into the app shell.
 #[allow(clippy::too_many_arguments)] // As for `page_data_handler`, we don't have a choice
-pub async fn initial_load_handler<M: MutableStore, T: TranslationsManager>(
+pub async fn initial_load_handler<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     path: FullPath,
-    req: perseus::http::Request<()>,
+    mut req: perseus::http::Request<()>,
     opts: Arc<ServerOptions>,
     html_shell: Arc<HtmlShell>,
-    render_cfg: Arc<HashMap<String, String>>,
+    render_cfg: Arc<RenderCfg>,
     immutable_store: Arc<ImmutableStore>,
     mutable_store: Arc<M>,
     translations_manager: Arc<T>,
     global_state: Arc<Option<String>>,
 ) -> Response<String> {
+    // In maintenance mode, every page is replaced by the maintenance page
+    if let Some(maintenance_page) = opts.maintenance.get_page() {
+        return Response::builder()
+            .status(503)
+            .header("Retry-After", opts.maintenance.retry_after())
+            .body(maintenance_page)
+            .unwrap();
+    }
     let path = path.as_str();
     let templates = &opts.templates_map;
     let error_pages = &opts.error_pages;
     let path_slice = get_path_slice(path);
+    // In development, error pages should use the latest translations
+    opts.error_translations
+        .refresh(path, translations_manager.as_ref())
+        .await;
     // Create a closure to make returning error pages easier (most have the same
-    // data)
+    // data, and they should be in the locale the user requested, if possible)
     let html_err = |status: u16, err: &str| {
-        return return_error_page(path, status, err, None, error_pages, html_shell.as_ref());
+        let translator = opts.error_translations.get_translator(path);
+        return return_error_page(
+            path,
+            status,
+            err,
+            translator,
+            error_pages,
+            html_shell.as_ref(),
+        );
     };
+    // Errors from rendering pages might have error pages of their own
+    let server_err = |err: &ServerError| {
+        let translator = opts.error_translations.get_translator(path);
+        let html = build_server_error_page(path, err, translator, error_pages, html_shell.as_ref());
+        Response::builder()
+            .status(err_to_status_code(err))
+            .body(html)
+            .unwrap()
+    };
+
+    // If this is the root of the app and it redirects elsewhere, we can send the
+    // user straight there
+    if let Some(to) = opts
+        .index_redirect
+        .as_ref()
+        .and_then(|index_redirect| get_index_redirect(&path_slice, &opts.locales, index_redirect))
+    {
+        return Response::builder()
+            .status(302)
+            .header("Location", format!("{}/{}", get_path_prefix_server(), to))
+            .body(String::new())
+            .unwrap();
+    }
 
     // Run the routing algorithms on the path to figure out which template we need
     let verdict = match_route_atomic(&path_slice, render_cfg.as_ref(), templates, &opts.locales);
@@ -64,43 +115,126 @@ pub async fn initial_load_handler<M: MutableStore, T: TranslationsManager>(
             locale,
             was_incremental_match,
         }) => {
-            // Actually render the page as we would if this weren't an initial load
-            let page_data = get_page_for_template(
-                GetPageProps::<M, T> {
-                    raw_path: &path,
-                    locale: &locale,
-                    was_incremental_match,
-                    req,
-                    global_state: &global_state,
-                    immutable_store: &immutable_store,
-                    mutable_store: &mutable_store,
-                    translations_manager: &translations_manager,
-                },
-                template,
-            )
-            .await;
-            let page_data = match page_data {
-                Ok(page_data) => page_data,
-                // We parse the error to return an appropriate status code
-                Err(err) => {
-                    return html_err(err_to_status_code(&err), &fmt_err(&err));
-                }
+            attach_request_context(&mut req, &opts);
+            // Work out which A/B experiment variants this user should get
+            let set_cookie = assign_variants(&opts.experiments, &mut req);
+            let variants = get_variants(&req);
+            // Give the user a CSRF token if they don't have one (and it's enabled)
+            let csrf = if opts.csrf_protection {
+                Some(get_or_mint_csrf_token(&req))
+            } else {
+                None
+            };
+            // Load the user's session, if sessions are enabled (if this fails, we still
+            // need to set the other cookies, so the error page is sent below)
+            let session = match &opts.sessions {
+                Some(sessions) => sessions.attach(&mut req).await.map(Some),
+                None => Ok(None),
             };
+            let info = PageInfo::for_request(&path, &locale, &req);
+            // Every outcome of rendering (including errors and redirects) needs the session
+            // saved and cookies set (e.g. after logging a user in), so this block only
+            // produces the response, and that's all done once it's finished
+            let mut http_res = async {
+                let session = match &session {
+                    Ok(session) => session,
+                    Err(err) => return html_err(500, &fmt_err(err)),
+                };
+                // Actually render the page as we would if this weren't an initial load
+                let page_data = get_page_for_template(
+                    GetPageProps::<M, T> {
+                        raw_path: &path,
+                        locale: &locale,
+                        was_incremental_match,
+                        req,
+                        global_state: &global_state,
+                        immutable_store: &immutable_store,
+                        mutable_store: &mutable_store,
+                        translations_manager: &translations_manager,
+                        plugins: &opts.plugins,
+                        incremental_warmup: &opts.incremental_warmup,
+                        templates: &opts.templates_map,
+                        error_reporter: &opts.error_reporter,
+                        state_fn_timeout: opts.state_fn_timeout,
+                        build_redirects: &opts.build_redirects,
+                    },
+                    template,
+                )
+                .await;
+                match page_data {
+                    Ok(page_data) => {
+                        let mut final_html = html_shell
+                            .as_ref()
+                            .clone()
+                            .page_data(&page_data, &global_state)
+                            .variants(&variants);
+                        if let Some(session) = session {
+                            final_html = final_html.session(&session.snapshot());
+                        }
+                        if let Some((token, _)) = &csrf {
+                            final_html = final_html.csrf_token(token);
+                        }
+
+                        let mut http_res = Response::builder().status(200);
+                        // Generate and add HTTP headers
+                        for (key, val) in template.get_headers(page_data.state, info) {
+                            http_res = http_res.header(key.unwrap(), val);
+                        }
 
-            let final_html = html_shell
-                .as_ref()
-                .clone()
-                .page_data(&page_data, &global_state)
-                .to_string();
+                        http_res.body(final_html.to_string()).unwrap()
+                    }
+                    // We parse the error to return an appropriate status code (or redirect)
+                    Err(err) => match err_to_redirect(&err) {
+                        // `Redirect::to` validates these, but a bad one still shouldn't panic
+                        Some(redirect) => match (
+                            StatusCode::from_u16(redirect.status()),
+                            HeaderValue::from_str(redirect.location()),
+                        ) {
+                            (Ok(status), Ok(location)) => Response::builder()
+                                .status(status)
+                                .header("Location", location)
+                                .body(String::new())
+                                .unwrap(),
+                            _ => html_err(500, "invalid redirect"),
+                        },
+                        None => server_err(&err),
+                    },
+                }
+            }
+            .await;
 
-            let mut http_res = Response::builder().status(200);
-            // http_res.content_type("text/html");
-            // Generate and add HTTP headers
-            for (key, val) in template.get_headers(page_data.state) {
-                http_res = http_res.header(key.unwrap(), val);
+            // Save any changes made to the session while rendering
+            let session = session.ok().flatten();
+            let session_cookie = match (&opts.sessions, &session) {
+                (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                    Ok(set_cookie) => set_cookie,
+                    Err(err) => {
+                        http_res = html_err(500, &fmt_err(&err));
+                        None
+                    }
+                },
+                _ => None,
+            };
+            // Anything that depends on the session, embeds a CSRF token, or embeds the user's
+            // experiment variants mustn't be cached for other users
+            let private = is_private_response(
+                session.as_ref(),
+                session_cookie.is_some(),
+                &variants,
+                csrf.is_some(),
+            );
+
+            let csrf_cookie = csrf.and_then(|(_, csrf_cookie)| csrf_cookie);
+            let cookies = [set_cookie, csrf_cookie, session_cookie];
+            let headers = http_res.headers_mut();
+            for cookie in cookies.into_iter().flatten() {
+                headers.append(SET_COOKIE, cookie.parse().unwrap());
+            }
+            if private {
+                make_private(headers);
             }
 
-            http_res.body(final_html).unwrap()
+            http_res
         }
         // For locale detection, we don't know the user's locale, so there's not much we can do
         // except send down the app shell, which will do the rest and fetch from `.perseus/page/...`
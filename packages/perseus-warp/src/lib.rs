@@ -9,6 +9,7 @@ documentation, and this should mostly be used as a secondary reference source. Y
 #![deny(missing_docs)]
 
 mod conv_req;
+mod csrf;
 #[cfg(feature = "dflt-server")]
 mod dflt_server;
 mod initial_load;
@@ -17,6 +18,7 @@ mod perseus_routes;
 mod static_content;
 mod translations;
 
+pub use crate::csrf::{csrf_protection, CsrfRejection};
 pub use crate::perseus_routes::perseus_routes;
 #[cfg(feature = "dflt-server")]
 pub use dflt_server::dflt_server;
@@ -1,13 +1,19 @@
 use fmterr::fmt_err;
 use perseus::{
-    errors::err_to_status_code,
+    errors::{err_to_body, err_to_redirect, err_to_status_code},
+    experiments::{assign_variants, get_variants},
     i18n::TranslationsManager,
-    server::{get_page_for_template, GetPageProps, ServerOptions},
+    server::{
+        attach_request_context, get_page_for_template, get_redirect_page_data, set_req_query,
+        GetPageProps, ServerOptions,
+    },
     stores::{ImmutableStore, MutableStore},
+    template::PageInfo,
+    utils::{is_private_response, make_private},
 };
 use serde::Deserialize;
 use std::sync::Arc;
-use warp::http::Response;
+use warp::http::{header::SET_COOKIE, Response};
 use warp::path::Tail;
 
 // Note: this is the same as for the Actix Web integration, but other frameworks
@@ -17,23 +23,34 @@ use warp::path::Tail;
 pub struct PageDataReq {
     pub template_name: String,
     pub was_incremental_match: bool,
+    #[serde(default)]
+    pub query: String,
 }
 
 #[allow(clippy::too_many_arguments)] // Because of how Warp filters work, we don't exactly have a choice
-pub async fn page_handler<M: MutableStore, T: TranslationsManager>(
+pub async fn page_handler<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     locale: String,
     path: Tail, // This is the path after the locale that was sent
     PageDataReq {
         template_name,
         was_incremental_match,
+        query,
     }: PageDataReq,
-    http_req: perseus::http::Request<()>,
+    mut http_req: perseus::http::Request<()>,
     opts: Arc<ServerOptions>,
     immutable_store: Arc<ImmutableStore>,
     mutable_store: Arc<M>,
     translations_manager: Arc<T>,
     global_state: Arc<Option<String>>,
 ) -> Response<String> {
+    // Nothing can be loaded in maintenance mode
+    if opts.maintenance.is_enabled() {
+        return Response::builder()
+            .status(503)
+            .header("Retry-After", opts.maintenance.retry_after())
+            .body("the server is down for maintenance".to_string())
+            .unwrap();
+    }
     let templates = &opts.templates_map;
     // Check if the locale is supported
     if opts.locales.is_supported(&locale) {
@@ -53,38 +70,100 @@ pub async fn page_handler<M: MutableStore, T: TranslationsManager>(
                     .unwrap();
             }
         };
-        let page_data = get_page_for_template(
-            GetPageProps::<M, T> {
-                raw_path: path,
-                locale: &locale,
-                was_incremental_match,
-                req: http_req,
-                global_state: &global_state,
-                immutable_store: &immutable_store,
-                mutable_store: &mutable_store,
-                translations_manager: &translations_manager,
-            },
-            template,
-        )
+        attach_request_context(&mut http_req, &opts);
+        let set_cookie = assign_variants(&opts.experiments, &mut http_req);
+        let variants = get_variants(&http_req);
+        // Load the user's session, if sessions are enabled (if this fails, we still need
+        // to set the variants cookie, so the error is sent below)
+        let session = match &opts.sessions {
+            Some(sessions) => sessions.attach(&mut http_req).await.map(Some),
+            None => Ok(None),
+        };
+        let http_req = set_req_query(http_req, &query);
+        let info = PageInfo::for_request(path, &locale, &http_req);
+        // Every outcome of rendering (including errors and redirects) needs the session
+        // saved and cookies set (e.g. after logging a user in), so this block only
+        // produces the response, and that's all done once it's finished
+        let mut http_res = async {
+            if let Err(err) = &session {
+                return Response::builder().status(500).body(fmt_err(err)).unwrap();
+            }
+            let page_data = get_page_for_template(
+                GetPageProps::<M, T> {
+                    raw_path: path,
+                    locale: &locale,
+                    was_incremental_match,
+                    req: http_req,
+                    global_state: &global_state,
+                    immutable_store: &immutable_store,
+                    mutable_store: &mutable_store,
+                    translations_manager: &translations_manager,
+                    plugins: &opts.plugins,
+                    incremental_warmup: &opts.incremental_warmup,
+                    templates: &opts.templates_map,
+                    error_reporter: &opts.error_reporter,
+                    state_fn_timeout: opts.state_fn_timeout,
+                    build_redirects: &opts.build_redirects,
+                },
+                template,
+            )
+            .await;
+            match page_data {
+                Ok(page_data) => {
+                    let mut http_res = Response::builder().status(200);
+                    // Generate and add HTTP headers
+                    for (key, val) in template.get_headers(page_data.state.clone(), info) {
+                        http_res = http_res.header(key.unwrap(), val);
+                    }
+                    http_res
+                        .body(serde_json::to_string(&page_data).unwrap())
+                        .unwrap()
+                }
+                // The app will navigate to wherever the page redirects to
+                Err(err) if err_to_redirect(&err).is_some() => Response::builder()
+                    .status(200)
+                    .body(get_redirect_page_data(err_to_redirect(&err).unwrap()))
+                    .unwrap(),
+                // We parse the error to return an appropriate status code
+                Err(err) => {
+                    // The body tells the app what kind of error this is
+                    let (content_type, body) = err_to_body(path, &err);
+                    Response::builder()
+                        .status(err_to_status_code(&err))
+                        .header("Content-Type", content_type)
+                        .body(body)
+                        .unwrap()
+                }
+            }
+        }
         .await;
-        match page_data {
-            Ok(page_data) => {
-                let mut http_res = Response::builder().status(200);
-                // http_res.content_type("text/html");
-                // Generate and add HTTP headers
-                for (key, val) in template.get_headers(page_data.state.clone()) {
-                    http_res = http_res.header(key.unwrap(), val);
+
+        // Save any changes made to the session while rendering
+        let session = session.ok().flatten();
+        let session_cookie = match (&opts.sessions, &session) {
+            (Some(sessions), Some(session)) => match sessions.commit(session).await {
+                Ok(set_cookie) => set_cookie,
+                Err(err) => {
+                    http_res = Response::builder().status(500).body(fmt_err(&err)).unwrap();
+                    None
                 }
+            },
+            _ => None,
+        };
+        // Anything that depends on the session or the user's experiment variants mustn't be
+        // cached for other users
+        let private =
+            is_private_response(session.as_ref(), session_cookie.is_some(), &variants, false);
 
-                let page_data_str = serde_json::to_string(&page_data).unwrap();
-                http_res.body(page_data_str).unwrap()
-            }
-            // We parse the error to return an appropriate status code
-            Err(err) => Response::builder()
-                .status(err_to_status_code(&err))
-                .body(fmt_err(&err))
-                .unwrap(),
+        let headers = http_res.headers_mut();
+        for cookie in [set_cookie, session_cookie].into_iter().flatten() {
+            headers.append(SET_COOKIE, cookie.parse().unwrap());
+        }
+        if private {
+            make_private(headers);
         }
+
+        http_res
     } else {
         Response::builder()
             .status(404)
@@ -3,10 +3,10 @@ use crate::page_data::page_handler;
 use crate::{
     conv_req::get_http_req,
     page_data::PageDataReq,
-    static_content::{serve_file, static_aliases_filter},
+    static_content::{serve_file, static_content_filter},
     translations::translations_handler,
 };
-use perseus::server::{get_render_cfg, ServerProps};
+use perseus::server::{get_render_cfg, prepare_maintenance_page, warm_up_incremental, ServerProps};
 use perseus::{i18n::TranslationsManager, stores::MutableStore};
 use std::sync::Arc;
 use warp::Filter;
@@ -32,6 +32,25 @@ pub async fn perseus_routes<M: MutableStore + 'static, T: TranslationsManager +
         .get_build_state()
         .await
         .expect("Couldn't generate global state.");
+    // Pre-render any incrementally generated pages the user wants ready immediately
+    warm_up_incremental(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
+    prepare_maintenance_page(
+        &opts,
+        &render_cfg,
+        &immutable_store,
+        &mutable_store,
+        &translations_manager,
+        &global_state,
+    )
+    .await;
 
     // Handle static files
     let js_bundle = warp::path!(".perseus" / "bundle.js")
@@ -46,29 +65,45 @@ pub async fn perseus_routes<M: MutableStore + 'static, T: TranslationsManager +
     // Handle JS interop snippets (which need to be served as separate files)
     let snippets =
         warp::path!(".perseus" / "snippets" / ..).and(warp::fs::dir(opts.snippets.clone()));
-    // Handle static content in the user-set directories (this will all be under
-    // `/.perseus/static`) We only set this if the user is using a static
-    // content directory
-    let static_dir_path = Arc::new(opts.static_dir.clone());
-    let static_dir_path_filter = warp::any().map(move || static_dir_path.clone());
-    let static_dir = warp::path!(".perseus" / "static" / ..)
-        .and(static_dir_path_filter)
-        .and_then(|static_dir_path: Arc<Option<String>>| async move {
-            if static_dir_path.is_some() {
-                Ok(())
-            } else {
-                Err(warp::reject::not_found())
-            }
-        })
-        .untuple_one() // We need this to avoid a ((), File) (which makes the return type fail)
-        // This alternative will never be served, but if we don't have it we'll get a runtime panic
-        .and(warp::fs::dir(
-            opts.static_dir.clone().unwrap_or_else(|| "".to_string()),
-        ));
-    // Handle static aliases
-    let static_aliases = warp::any()
-        .and(static_aliases_filter(opts.static_aliases.clone()))
+    // Handle static content in the user-set directory (this will all be under
+    // `/.perseus/static`) and static aliases, which are both served according to
+    // the user's static content options
+    let static_opts = Arc::new(opts.clone());
+    let static_content = warp::any()
+        .and(static_content_filter(static_opts.clone()))
+        .and(warp::fs::conditionals())
+        .and(warp::header::headers_cloned())
+        .and(warp::any().map(move || static_opts.clone()))
         .and_then(serve_file);
+    // Let maintenance mode be toggled while the server is running (this is
+    // `MAINTENANCE_ENDPOINT`)
+    let maintenance_state = opts.maintenance.clone();
+    let maintenance = warp::post()
+        .and(warp::path!(".perseus" / "maintenance"))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::bytes())
+        .map(
+            move |authorization: Option<String>, body: warp::hyper::body::Bytes| {
+                let body = String::from_utf8_lossy(&body);
+                let status = maintenance_state.handle_toggle(authorization.as_deref(), &body);
+                warp::http::StatusCode::from_u16(status).unwrap()
+            },
+        );
+    // Handle the generated `robots.txt` file (if there is one)
+    let robots_txt_contents = Arc::new(opts.robots_txt.clone());
+    let robots_txt = warp::path!("robots.txt")
+        .and(warp::path::end())
+        .and(warp::any().map(move || robots_txt_contents.clone()))
+        .and_then(|robots_txt: Arc<Option<String>>| async move {
+            match robots_txt.as_ref() {
+                Some(robots_txt) => Ok(warp::reply::with_header(
+                    robots_txt.to_string(),
+                    "Content-Type",
+                    "text/plain",
+                )),
+                None => Err(warp::reject::not_found()),
+            }
+        });
 
     // Define some filters to handle all the data we want to pass through
     let opts = Arc::new(opts);
@@ -88,6 +123,7 @@ pub async fn perseus_routes<M: MutableStore + 'static, T: TranslationsManager +
 
     // Handle getting translations
     let translations = warp::path!(".perseus" / "translations" / String)
+        .and(warp::header::optional::<String>("if-none-match"))
         .and(opts.clone())
         .and(translations_manager.clone())
         .then(translations_handler);
@@ -121,8 +157,9 @@ pub async fn perseus_routes<M: MutableStore + 'static, T: TranslationsManager +
         .or(wasm_bundle)
         .or(wasm_js_bundle)
         .or(snippets)
-        .or(static_dir)
-        .or(static_aliases)
+        .or(robots_txt)
+        .or(maintenance)
+        .or(static_content)
         .or(translations)
         .or(page_data)
         .or(initial_loads)
@@ -1,38 +1,46 @@
-use std::collections::HashMap;
+use perseus::server::{get_static_file, ServerOptions};
+use std::path::PathBuf;
 use std::sync::Arc;
-use warp::fs::{file_reply, ArcPath, Conditionals, File};
+use warp::fs::{file_reply, ArcPath, Conditionals};
+use warp::http::HeaderMap;
+use warp::reply::{with_header, Reply, Response};
 use warp::{path::FullPath, Filter, Rejection};
 
-/// A filter for static aliases that determines which file to serve.
-pub fn static_aliases_filter(
-    paths: HashMap<String, String>,
-) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+/// A filter for the static directory and static aliases that determines which
+/// file to serve, and what its `Content-Type` should be.
+pub fn static_content_filter(
+    opts: Arc<ServerOptions>,
+) -> impl Filter<Extract = (PathBuf, String), Error = Rejection> + Clone {
     warp::any()
         .and(warp::path::full())
-        .and(warp::any().map(move || paths.clone()))
-        .and_then(
-            |path: FullPath, paths: HashMap<String, String>| async move {
-                // Match a specific static alias and break on the first match
-                let mut file_to_serve = String::new();
-                for (url, static_dir) in paths.iter() {
-                    if path.as_str() == url {
-                        file_to_serve = static_dir.to_string();
-                        break;
-                    }
+        .and(warp::any().map(move || opts.clone()))
+        .and_then(|path: FullPath, opts: Arc<ServerOptions>| async move {
+            // This handles the static directory, single files, and mounted directories
+            match get_static_file(&opts, path.as_str()) {
+                Some(file) => {
+                    let content_type = opts.static_content.get_content_type(&file);
+                    Ok((file, content_type))
                 }
-
-                if file_to_serve.is_empty() {
-                    Err(warp::reject::not_found())
-                } else {
-                    Ok(file_to_serve)
-                }
-            },
-        )
+                None => Err(warp::reject::not_found()),
+            }
+        })
+        .untuple_one()
 }
 
-/// Serves the file provided through the filter.
-pub async fn serve_file(path: String) -> Result<File, Rejection> {
-    let arc_path = ArcPath(Arc::new(path.into()));
-    let conds = Conditionals::default();
-    file_reply(arc_path, conds).await
+/// Serves the file provided through the filter with the given `Content-Type`.
+pub async fn serve_file(
+    path: PathBuf,
+    content_type: String,
+    conds: Conditionals,
+    headers: HeaderMap,
+    opts: Arc<ServerOptions>,
+) -> Result<Response, Rejection> {
+    let req_headers = headers.keys().map(|name| name.as_str());
+    if let Some(contents) = opts.static_content.read_small_file(&path, req_headers) {
+        return Ok(with_header(contents, "Content-Type", content_type).into_response());
+    }
+    // This streams the file, handling conditional and range requests for us
+    let arc_path = ArcPath(Arc::new(path));
+    let file = file_reply(arc_path, conds).await?;
+    Ok(with_header(file, "Content-Type", content_type).into_response())
 }
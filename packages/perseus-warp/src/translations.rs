@@ -1,10 +1,17 @@
 use fmterr::fmt_err;
-use perseus::{i18n::TranslationsManager, server::ServerOptions};
+use perseus::{
+    i18n::TranslationsManager,
+    server::{etag_matches, get_etag, ServerOptions},
+};
 use std::sync::Arc;
-use warp::http::Response;
+use warp::http::{header, Response};
 
+/// The handler for calls to `.perseus/translations/{locale}`. Responses have
+/// ETags, so the browser will only download translations again if they've
+/// changed.
 pub async fn translations_handler<T: TranslationsManager>(
     locale: String,
+    if_none_match: Option<String>,
     opts: Arc<ServerOptions>,
     translations_manager: Arc<T>,
 ) -> Response<String> {
@@ -19,8 +26,21 @@ pub async fn translations_handler<T: TranslationsManager>(
             Ok(translations) => translations,
             Err(err) => return Response::builder().status(500).body(fmt_err(&err)).unwrap(),
         };
+        let etag = get_etag(&translations);
+        if etag_matches(if_none_match.as_deref(), &etag) {
+            return Response::builder()
+                .status(304)
+                .header(header::ETAG, etag)
+                .body(String::new())
+                .unwrap();
+        }
 
-        Response::new(translations)
+        Response::builder()
+            // The browser should always check that the translations haven't changed
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header(header::ETAG, etag)
+            .body(translations)
+            .unwrap()
     } else {
         Response::builder()
             .status(404)
@@ -2,29 +2,153 @@
 
 use crate::errors::*;
 use crate::i18n::{Locales, TranslationsManager};
+use crate::init::BuildFailurePolicy;
+use crate::router::{BuildRedirects, LocalizedPaths, RenderCfg, TemplateRenderCfg};
+use crate::server::write_revalidation_schedule;
 use crate::stores::{ImmutableStore, MutableStore};
-use crate::template::Template;
-use crate::template::{PageProps, TemplateMap};
+use crate::template::{BuildPaths, Template};
+use crate::template::{PageInfo, PageProps, TemplateMap};
 use crate::translator::Translator;
-use futures::future::try_join_all;
-use std::collections::HashMap;
+use crate::utils::{get_build_time, set_asset_urls, BuildProfile};
+use futures::future::{join_all, try_join_all};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
 use sycamore::prelude::SsrNode;
 
+/// The version of the format of the artifacts written by the build process
+/// (the render configuration and the pages themselves). This must be
+/// incremented whenever that format changes, so that old artifacts are
+/// detected before they cause strange errors.
+pub const ARTIFACTS_VERSION: u32 = 2;
+
+/// The prefix of the lines the engine prints to `stdout` to report the progress
+/// of a build to the CLI, each of which is followed by a JSON-serialized
+/// [`BuildProgress`].
+pub const BUILD_PROGRESS_PREFIX: &str = "__PERSEUS_BUILD_PROGRESS__ ";
+/// The prefix of the lines the engine prints to `stdout` to report warnings
+/// about a build to the CLI, each of which is followed by a JSON-serialized
+/// string.
+pub const BUILD_WARNING_PREFIX: &str = "__PERSEUS_BUILD_WARNING__ ";
+
+/// An update on the progress of a build (or an export), which is sent whenever
+/// a page has been generated. Because the pages of each template are only
+/// known once its build paths have been generated, `total` may grow as the
+/// build goes on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuildProgress {
+    /// The template the page belongs to.
+    pub template: String,
+    /// The path of the page (including its locale if it was built for one).
+    pub path: String,
+    /// The number of pages that have been generated so far.
+    pub done: usize,
+    /// The number of pages known to need to be generated.
+    pub total: usize,
+}
+
+/// Keeps track of the progress of a build, sending a [`BuildProgress`] update
+/// through the given channel (if there is one) every time a page has been
+/// generated. The same tracker can be used for building and then exporting an
+/// app, in which case the progress will continue across both.
+#[derive(Debug, Default)]
+pub struct BuildProgressTracker {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    sender: Option<Mutex<Sender<BuildProgress>>>,
+}
+impl BuildProgressTracker {
+    /// Creates a new progress tracker, which will send updates to the given
+    /// channel, if one is given.
+    pub fn new(sender: Option<Sender<BuildProgress>>) -> Self {
+        Self {
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            sender: sender.map(Mutex::new),
+        }
+    }
+    /// Records that the given number of pages have been found to need
+    /// generating.
+    pub(crate) fn add_pages(&self, count: usize) {
+        self.total.fetch_add(count, Ordering::SeqCst);
+    }
+    /// Records that the given page has been generated (successfully or not).
+    pub(crate) fn page_done(&self, template: &str, path: &str) {
+        let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(sender) = &self.sender {
+            // If nobody's listening anymore, that's not our problem
+            let _ = sender.lock().unwrap().send(BuildProgress {
+                template: template.to_string(),
+                path: path.to_string(),
+                done,
+                total: self.total.load(Ordering::SeqCst),
+            });
+        }
+    }
+    /// Reports a warning about the build. If the CLI is listening for updates
+    /// on the build's progress, this will be sent to it (so it can show the
+    /// warning without disrupting its progress bars), otherwise it'll just be
+    /// printed to `stderr`.
+    pub(crate) fn warn(&self, msg: &str) {
+        match &self.sender {
+            Some(_) => println!(
+                "{}{}",
+                BUILD_WARNING_PREFIX,
+                serde_json::to_string(msg).unwrap()
+            ),
+            None => eprintln!("Warning: {}", msg),
+        }
+    }
+}
+
+/// The stamp written to the immutable store alongside the build artifacts.
+#[derive(Serialize, Deserialize)]
+struct ArtifactsStamp {
+    /// The version of the artifacts' format.
+    version: u32,
+    /// The version of Perseus that built the artifacts, which is only used
+    /// for error messages.
+    perseus: String,
+}
+
+/// The result of building a single template: the pages it explicitly
+/// generated, whether or not it only generated a single page to occupy the
+/// template's root path (`true` unless using build-time path generation), any
+/// pages that failed to build (only if the failure policy isn't `FailFast`),
+/// and any pages that redirected elsewhere (by their full paths without their
+/// locales).
+type BuiltTemplate = (
+    Vec<String>,
+    bool,
+    Vec<PageBuildFailure>,
+    BTreeMap<String, Redirect>,
+);
+
 /// Builds a template, writing static data as appropriate. This should be used
 /// as part of a larger build process. This returns both a list of the extracted
 /// render options for this template (needed at request time), a list of pages
-/// that it explicitly generated, and a boolean as to whether or not it only
+/// that it explicitly generated, a boolean as to whether or not it only
 /// generated a single page to occupy the template's root path (`true` unless
-/// using using build-time path generation).
+/// using using build-time path generation), and a list of pages that failed to
+/// build, and the pages that redirected elsewhere.
+///
+/// Unless the given failure policy is `FailFast`, pages that fail to build
+/// won't make this fail. If the policy is `SkipFailed`, they'll be marked to be
+/// generated on demand by the server (or left out if we're exporting).
 pub async fn build_template(
     template: &Template<SsrNode>,
     translator: &Translator,
     (immutable_store, mutable_store): (&ImmutableStore, &impl MutableStore),
     global_state: &Option<String>,
     exporting: bool,
-) -> Result<(Vec<String>, bool), ServerError> {
+    failure_policy: BuildFailurePolicy,
+    progress: &BuildProgressTracker,
+) -> Result<BuiltTemplate, ServerError> {
     let mut single_page = false;
     let template_path = template.get_path();
+    let locale = translator.get_locale();
 
     // If we're exporting, ensure that all the template's strategies are export-safe
     // (not requiring a server)
@@ -44,65 +168,156 @@ pub async fn build_template(
     // Handle static path generation
     // Because we iterate over the paths, we need a base path if we're not
     // generating custom ones (that'll be overriden if needed)
-    let paths = match template.uses_build_paths() {
-        true => template
-            .get_build_paths()
-            .await?
+    let (mut paths, mut extra) = match template.uses_build_paths() {
+        true => {
+            let BuildPaths { paths, extra } = match template.get_build_paths(locale.clone()).await {
+                Ok(build_paths) => build_paths,
+                // If we can't get the paths, there are no pages to build
+                Err(err) if failure_policy != BuildFailurePolicy::FailFast => {
+                    let failure = PageBuildFailure {
+                        path: template_path,
+                        locale,
+                        error: err,
+                    };
+                    return Ok((Vec::new(), false, vec![failure], BTreeMap::new()));
+                }
+                Err(err) => return Err(err),
+            };
             // Trim away any trailing `/`s so we don't insert them into the render config
             // That makes rendering an index page from build paths impossible (see #39)
-            .iter()
-            .map(|p| match p.strip_suffix('/') {
+            let trim = |p: String| match p.strip_suffix('/') {
                 Some(stripped) => stripped.to_string(),
-                None => p.to_string(),
-            })
-            .collect(),
+                None => p,
+            };
+            let paths = paths.into_iter().map(trim).collect::<Vec<_>>();
+            let extra = extra
+                .into_iter()
+                .map(|(p, extra)| (trim(p), extra))
+                .collect::<HashMap<_, _>>();
+            (paths, extra)
+        }
         false => {
             single_page = true;
-            vec![String::new()]
+            (vec![String::new()], HashMap::new())
         }
     };
 
     // Iterate through the paths to generate initial states if needed
     // Note that build paths pages on incrementally generable pages will use the
     // immutable store
+    progress.add_pages(paths.len());
     let mut futs = Vec::new();
     for path in paths.iter() {
-        let fut = gen_state_for_path(
-            path,
-            template,
-            translator,
-            (immutable_store, mutable_store),
-            global_state,
-        );
+        let extra = extra.remove(path);
+        let fut = async {
+            let res = gen_state_for_path(
+                path,
+                extra,
+                template,
+                translator,
+                (immutable_store, mutable_store),
+                global_state,
+            )
+            .await;
+            // If the page redirects elsewhere, the server will keep doing that until the
+            // next build (there won't be a server to do so if we're exporting though)
+            let res = match res {
+                Ok(()) => Ok(None),
+                Err(err) if !exporting => match err_to_redirect(&err) {
+                    Some(redirect) => Ok(Some(redirect.clone())),
+                    None => Err(err),
+                },
+                Err(err) => Err(err),
+            };
+            let full_path = get_full_path_without_locale(template, path);
+            let full_path = match locale.as_str() {
+                "xx-XX" => full_path,
+                locale => format!("{}/{}", locale, full_path),
+            };
+            progress.page_done(&template_path, &full_path);
+            res
+        };
         futs.push(fut);
     }
-    try_join_all(futs).await?;
+    let mut failures = Vec::new();
+    let mut redirects = BTreeMap::new();
+    let mut record_redirect = |path: &str, redirect: Option<Redirect>| {
+        if let Some(redirect) = redirect {
+            redirects.insert(get_full_path_without_locale(template, path), redirect);
+        }
+    };
+    if failure_policy == BuildFailurePolicy::FailFast {
+        for (path, redirect) in paths.iter().zip(try_join_all(futs).await?) {
+            record_redirect(path, redirect);
+        }
+    } else {
+        let results = join_all(futs).await;
+        let mut failed_paths = Vec::new();
+        for (path, res) in paths.iter().zip(results) {
+            match res {
+                Ok(redirect) => record_redirect(path, redirect),
+                Err(err) => {
+                    failed_paths.push(path.to_string());
+                    failures.push(PageBuildFailure {
+                        path: get_full_path_without_locale(template, path),
+                        locale: locale.clone(),
+                        error: err,
+                    });
+                }
+            }
+        }
+
+        if failure_policy == BuildFailurePolicy::SkipFailed && exporting {
+            // There won't be a server to generate these later
+            paths.retain(|path| !failed_paths.contains(path));
+        } else if failure_policy == BuildFailurePolicy::SkipFailed {
+            // The server will generate these on demand when it finds this marker (old
+            // markers will be removed with the rest of the build artifacts)
+            for path in failed_paths {
+                let full_path_encoded = format!(
+                    "{}-{}",
+                    locale,
+                    urlencoding::encode(&get_full_path_without_locale(template, &path))
+                );
+                immutable_store
+                    .write(&format!("static/{}.skipped.txt", full_path_encoded), "1")
+                    .await?;
+            }
+        }
+    }
+
+    Ok((paths, single_page, failures, redirects))
+}
 
-    Ok((paths, single_page))
+/// Gets the full path of the given page generated by the given template,
+/// without its locale.
+fn get_full_path_without_locale(template: &Template<SsrNode>, path: &str) -> String {
+    let template_path = template.get_path();
+    let full_path_without_locale = match template.uses_build_paths() {
+        true => format!("{}/{}", &template_path, path),
+        // We don't want to concatenate the name twice if we don't have to
+        false => template_path,
+    };
+    // Strip trailing `/`s for the reasons described above
+    match full_path_without_locale.strip_suffix('/') {
+        Some(stripped) => stripped.to_string(),
+        None => full_path_without_locale,
+    }
 }
 
 /// Generates state for a single page within a template. This is broken out into
 /// a separate function for concurrency.
 async fn gen_state_for_path(
     path: &str,
+    extra: Option<String>,
     template: &Template<SsrNode>,
     translator: &Translator,
     (immutable_store, mutable_store): (&ImmutableStore, &impl MutableStore),
     global_state: &Option<String>,
 ) -> Result<(), ServerError> {
-    let template_path = template.get_path();
     // If needed, we'll contruct a full path that's URL encoded so we can easily
     // save it as a file
-    let full_path_without_locale = match template.uses_build_paths() {
-        true => format!("{}/{}", &template_path, path),
-        // We don't want to concatenate the name twice if we don't have to
-        false => template_path.clone(),
-    };
-    // Strip trailing `/`s for the reasons described above
-    let full_path_without_locale = match full_path_without_locale.strip_suffix('/') {
-        Some(stripped) => stripped.to_string(),
-        None => full_path_without_locale,
-    };
+    let full_path_without_locale = get_full_path_without_locale(template, path);
     // Add the current locale to the front of that and dencode it as a URL so we can
     // store a flat series of files BUG: insanely nested paths won't work
     // whatsoever if the filename is too long, maybe hash instead?
@@ -127,9 +342,14 @@ async fn gen_state_for_path(
     if template.uses_build_state() && template.revalidates() {
         // We pass in the path to get a state (including the template path for
         // consistency with the incremental logic)
-        let initial_state = template
-            .get_build_state(full_path_without_locale.clone(), translator.get_locale())
+        let (initial_state, schedule) = template
+            .get_build_state_with_schedule(
+                full_path_without_locale.clone(),
+                translator.get_locale(),
+                extra,
+            )
             .await?;
+        write_revalidation_schedule(&full_path_encoded, schedule, mutable_store).await?;
         // Write that intial state to a static JSON file
         mutable_store
             .write(
@@ -154,7 +374,11 @@ async fn gen_state_for_path(
         // Prerender the document `<head>` with that state
         // If the page also uses request state, amalgamation will be applied as for the
         // normal content
-        let head_str = template.render_head_str(page_props, translator);
+        let head_str = template.render_head_str(
+            page_props,
+            PageInfo::new(full_path_without_locale.clone(), &locale, None),
+            translator,
+        );
         mutable_store
             .write(
                 &format!("static/{}.head.html", full_path_encoded),
@@ -165,7 +389,11 @@ async fn gen_state_for_path(
         // We pass in the path to get a state (including the template path for
         // consistency with the incremental logic)
         let initial_state = template
-            .get_build_state(full_path_without_locale.clone(), translator.get_locale())
+            .get_build_state(
+                full_path_without_locale.clone(),
+                translator.get_locale(),
+                extra,
+            )
             .await?;
         // Write that intial state to a static JSON file
         immutable_store
@@ -191,7 +419,11 @@ async fn gen_state_for_path(
         // Prerender the document `<head>` with that state
         // If the page also uses request state, amalgamation will be applied as for the
         // normal content
-        let head_str = template.render_head_str(page_props, translator);
+        let head_str = template.render_head_str(
+            page_props,
+            PageInfo::new(full_path_without_locale.clone(), &locale, None),
+            translator,
+        );
         immutable_store
             .write(
                 &format!("static/{}.head.html", full_path_encoded),
@@ -203,11 +435,8 @@ async fn gen_state_for_path(
     // Handle revalidation, we need to parse any given time strings into datetimes
     // We don't need to worry about revalidation that operates by logic, that's
     // request-time only
-    if template.revalidates_with_time() {
-        let datetime_to_revalidate = template
-            .get_revalidate_interval()
-            .unwrap()
-            .compute_timestamp();
+    if let Some(interval) = template.get_revalidate_interval(&locale) {
+        let datetime_to_revalidate = interval.compute_timestamp_from(get_build_time());
         // Write that to a static file, we'll update it every time we revalidate
         // Note that this runs for every path generated, so it's fully usable with ISR
         // Yes, there's a different revalidation schedule for each locale, but that
@@ -238,7 +467,11 @@ async fn gen_state_for_path(
         let prerendered = sycamore::render_to_string(|cx| {
             template.render_for_template_server(page_props.clone(), cx, translator)
         });
-        let head_str = template.render_head_str(page_props, translator);
+        let head_str = template.render_head_str(
+            page_props,
+            PageInfo::new(full_path_without_locale.clone(), &locale, None),
+            translator,
+        );
         // Write that prerendered HTML to a static file
         immutable_store
             .write(&format!("static/{}.html", full_path_encoded), &prerendered)
@@ -255,30 +488,45 @@ async fn gen_state_for_path(
 }
 
 /// Builds all pages within a template and compiles its component of the render
-/// configuration.
+/// configuration, also returning any pages that failed to build, and any that
+/// redirected elsewhere.
 pub async fn build_template_and_get_cfg(
     template: &Template<SsrNode>,
     translator: &Translator,
     (immutable_store, mutable_store): (&ImmutableStore, &impl MutableStore),
     global_state: &Option<String>,
     exporting: bool,
-) -> Result<HashMap<String, String>, ServerError> {
+    failure_policy: BuildFailurePolicy,
+    progress: &BuildProgressTracker,
+) -> Result<
+    (
+        HashMap<String, String>,
+        Vec<PageBuildFailure>,
+        BTreeMap<String, Redirect>,
+    ),
+    ServerError,
+> {
     let mut render_cfg = HashMap::new();
     let template_root_path = template.get_path();
     let is_incremental = template.uses_incremental();
 
-    let (pages, single_page) = build_template(
+    let (pages, single_page, failures, redirects) = build_template(
         template,
         translator,
         (immutable_store, mutable_store),
         global_state,
         exporting,
+        failure_policy,
+        progress,
     )
     .await?;
     // If the template represents a single page itself, we don't need any
     // concatenation
     if single_page {
-        render_cfg.insert(template_root_path.clone(), template_root_path.clone());
+        // The page won't be there if it failed and was left out of an export
+        if !pages.is_empty() {
+            render_cfg.insert(template_root_path.clone(), template_root_path.clone());
+        }
     } else {
         // Add each page that the template explicitly generated (ignoring ISR for now)
         for page in pages {
@@ -300,23 +548,37 @@ pub async fn build_template_and_get_cfg(
         }
     }
 
-    Ok(render_cfg)
+    Ok((render_cfg, failures, redirects))
 }
 
 /// Runs the build process of building many different templates for a single
 /// locale. If you're not using i18n, provide a `Translator::empty()`
 /// for this. You should only build the most commonly used locales here (the
 /// rest should be built on demand).
+///
+/// This returns a map of the pages generated for this locale to the root paths
+/// of the templates that render them, which is part of the render
+/// configuration, any pages that failed to build, and any pages that redirected
+/// elsewhere.
 pub async fn build_templates_for_locale(
     templates: &TemplateMap<SsrNode>,
     translator: &Translator,
     (immutable_store, mutable_store): (&ImmutableStore, &impl MutableStore),
     global_state: &Option<String>,
     exporting: bool,
-) -> Result<(), ServerError> {
+    failure_policy: BuildFailurePolicy,
+    progress: &BuildProgressTracker,
+) -> Result<
+    (
+        BTreeMap<String, String>,
+        Vec<PageBuildFailure>,
+        BTreeMap<String, Redirect>,
+    ),
+    ServerError,
+> {
     // The render configuration stores a list of pages to the root paths of their
-    // templates
-    let mut render_cfg = HashMap::new();
+    // templates (this is sorted so that it's the same on every build)
+    let mut pages = BTreeMap::new();
     // Create each of the templates
     let mut futs = Vec::new();
     for template in templates.values() {
@@ -326,24 +588,26 @@ pub async fn build_templates_for_locale(
             (immutable_store, mutable_store),
             global_state,
             exporting,
+            failure_policy,
+            progress,
         ));
     }
     let template_cfgs = try_join_all(futs).await?;
-    for template_cfg in template_cfgs {
-        render_cfg.extend(template_cfg.into_iter())
+    let mut failures = Vec::new();
+    let mut redirects = BTreeMap::new();
+    for (template_cfg, template_failures, template_redirects) in template_cfgs {
+        pages.extend(template_cfg.into_iter());
+        failures.extend(template_failures);
+        redirects.extend(template_redirects);
     }
 
-    immutable_store
-        .write(
-            "render_conf.json",
-            &serde_json::to_string(&render_cfg).unwrap(),
-        )
-        .await?;
-
-    Ok(())
+    Ok((pages, failures, redirects))
 }
 
-/// Gets a translator and builds templates for a single locale.
+/// Gets a translator and builds templates for a single locale, returning the
+/// locale, the pages generated for it, any pages that failed to build, and any
+/// pages that redirected elsewhere.
+#[allow(clippy::too_many_arguments)]
 pub async fn build_templates_and_translator_for_locale(
     templates: &TemplateMap<SsrNode>,
     locale: String,
@@ -351,20 +615,32 @@ pub async fn build_templates_and_translator_for_locale(
     translations_manager: &impl TranslationsManager,
     global_state: &Option<String>,
     exporting: bool,
-) -> Result<(), ServerError> {
+    failure_policy: BuildFailurePolicy,
+    progress: &BuildProgressTracker,
+) -> Result<
+    (
+        String,
+        BTreeMap<String, String>,
+        Vec<PageBuildFailure>,
+        BTreeMap<String, Redirect>,
+    ),
+    ServerError,
+> {
     let translator = translations_manager
-        .get_translator_for_locale(locale)
+        .get_translator_for_locale(locale.clone())
         .await?;
-    build_templates_for_locale(
+    let (pages, failures, redirects) = build_templates_for_locale(
         templates,
         &translator,
         (immutable_store, mutable_store),
         global_state,
         exporting,
+        failure_policy,
+        progress,
     )
     .await?;
 
-    Ok(())
+    Ok((locale, pages, failures, redirects))
 }
 
 /// The properties needed to build an app.
@@ -385,6 +661,13 @@ pub struct BuildProps<'a, M: MutableStore, T: TranslationsManager> {
     /// Whether or not we're exporting after this build (changes behavior
     /// slightly).
     pub exporting: bool,
+    /// What to do if some pages fail to build.
+    pub failure_policy: BuildFailurePolicy,
+    /// A tracker for the progress of the build.
+    pub progress: &'a BuildProgressTracker,
+    /// The fingerprinted URLs of the app's static aliases (empty if they
+    /// aren't being fingerprinted).
+    pub asset_urls: BTreeMap<String, String>,
 }
 
 /// Runs the build process of building many templates for the given locales
@@ -399,8 +682,34 @@ pub async fn build_app<M: MutableStore, T: TranslationsManager>(
         translations_manager,
         global_state,
         exporting,
+        failure_policy,
+        progress,
+        asset_urls,
     }: BuildProps<'_, M, T>,
 ) -> Result<(), ServerError> {
+    // Catch any invalid revalidation intervals before we do any work
+    for template in templates.values() {
+        template.check_revalidate_intervals()?;
+    }
+    // Compute the shared state of any template groups up-front (each group's
+    // state will only be computed once, however many templates are in it)
+    try_join_all(
+        templates
+            .values()
+            .map(|template| template.prepare_group_state()),
+    )
+    .await?;
+
+    // Templates need these to reference static aliases while they're rendered
+    set_asset_urls(asset_urls.clone());
+
+    let using_i18n = locales.using_i18n;
+    // The app needs to know which locales were built, since they might not be all
+    // of them
+    let built_locales = match using_i18n {
+        true => locales.get_all().into_iter().cloned().collect(),
+        false => Vec::new(),
+    };
     let locales = locales.get_all();
     let mut futs = Vec::new();
 
@@ -412,10 +721,102 @@ pub async fn build_app<M: MutableStore, T: TranslationsManager>(
             translations_manager,
             global_state,
             exporting,
+            failure_policy,
+            progress,
         ));
     }
     // Build all locales in parallel
-    try_join_all(futs).await?;
+    let mut locale_pages = BTreeMap::new();
+    let mut failures = Vec::new();
+    let mut redirects = BuildRedirects::default();
+    for (locale, pages, locale_failures, locale_redirects) in try_join_all(futs).await? {
+        for (path, redirect) in locale_redirects {
+            redirects.insert(path, &locale, redirect);
+        }
+        locale_pages.insert(locale, pages);
+        failures.extend(locale_failures);
+    }
+    if !failures.is_empty() {
+        match failure_policy {
+            BuildFailurePolicy::SkipFailed => progress.warn(&format!(
+                "{} page(s) failed to build, and will be skipped:\n{}",
+                failures.len(),
+                fmt_page_failures(&failures)
+            )),
+            _ => return Err(BuildError::PagesFailed { failures }.into()),
+        }
+    }
+
+    // The render configuration also records how each template is rendered
+    let templates_cfg = templates
+        .iter()
+        .map(|(path, template)| {
+            (
+                path.to_string(),
+                TemplateRenderCfg {
+                    build_paths: template.uses_build_paths(),
+                    build_state: template.uses_build_state(),
+                    request_state: template.uses_request_state(),
+                    incremental: template.uses_incremental(),
+                    revalidates: template.revalidates(),
+                },
+            )
+        })
+        .collect();
+    let localized_paths = LocalizedPaths::from_templates(templates.values().map(|t| t.as_ref()));
+    let render_cfg = RenderCfg::new(locale_pages, templates_cfg, asset_urls, using_i18n)
+        .with_localized_paths(localized_paths)
+        .with_redirects(redirects)
+        .with_locales(built_locales)
+        .with_profile(BuildProfile::current());
+    immutable_store
+        .write("render_conf.json", &render_cfg.to_json())
+        .await?;
+
+    // Stamp the artifacts so that we can tell if they're stale later
+    let stamp = ArtifactsStamp {
+        version: ARTIFACTS_VERSION,
+        perseus: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    immutable_store
+        .write(
+            "artifacts_version.json",
+            &serde_json::to_string(&stamp).unwrap(),
+        )
+        .await?;
 
     Ok(())
 }
+
+/// Checks that the build artifacts in the given immutable store were written
+/// in the format this version of Perseus expects, returning an error if they
+/// weren't (e.g. if Perseus has been upgraded since the app was last built).
+/// Artifacts from before this stamp was introduced will be treated as
+/// incompatible.
+pub async fn check_artifacts_version(immutable_store: &ImmutableStore) -> Result<(), ServerError> {
+    let stamp = match immutable_store.read("artifacts_version.json").await {
+        Ok(stamp) => serde_json::from_str::<ArtifactsStamp>(&stamp).ok(),
+        // If there are no artifacts at all, the render configuration will be missing too, which
+        // is a clearer error
+        Err(StoreError::NotFound { .. })
+            if immutable_store.read("render_conf.json").await.is_err() =>
+        {
+            return Ok(())
+        }
+        Err(StoreError::NotFound { .. }) => None,
+        Err(err) => return Err(err.into()),
+    };
+    match stamp {
+        Some(stamp) if stamp.version == ARTIFACTS_VERSION => Ok(()),
+        Some(stamp) => Err(BuildError::ArtifactsVersionMismatch {
+            found: format!("v{} (from Perseus {})", stamp.version, stamp.perseus),
+            expected: ARTIFACTS_VERSION,
+        }
+        .into()),
+        None => Err(BuildError::ArtifactsVersionMismatch {
+            found: "unknown".to_string(),
+            expected: ARTIFACTS_VERSION,
+        }
+        .into()),
+    }
+}
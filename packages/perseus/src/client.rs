@@ -1,11 +1,20 @@
 use crate::{
     checkpoint,
+    error_pages::{ErrorContext, ErrorKind},
+    errors::ClientError,
+    i18n::get_embedded_translator,
     plugins::PluginAction,
     router::{perseus_router, PerseusRouterProps},
-    shell::get_render_cfg,
+    shell::{get_render_cfg, reload_page},
     template::TemplateNodeType,
+    utils::{source_mapped_panic_hook, source_maps_enabled},
+    ErrorPages,
 };
-use wasm_bindgen::JsValue;
+use fmterr::fmt_err;
+use std::rc::Rc;
+use std::sync::Arc;
+use sycamore::reactive::create_scope;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 
 use crate::{i18n::TranslationsManager, stores::MutableStore, PerseusAppBase};
 
@@ -24,10 +33,46 @@ pub fn run_client<M: MutableStore, T: TranslationsManager>(
 ) -> Result<(), JsValue> {
     let app = app();
     let plugins = app.get_plugins();
+    let error_reporter = app.get_error_reporter();
 
     checkpoint("begin");
-    // Panics should always go to the console
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    // Panics should always go to the console, and then to the error reporter, if
+    // there is one
+    let panic_reporter = error_reporter.clone();
+    let panic_error_pages = Arc::new(app.get_error_pages());
+    let panic_root = app.get_root();
+    // Nothing else can be relied on after a panic, so the panic page can only be
+    // translated with the app's embedded translations
+    let panic_translations = app
+        .get_embedded_translations()
+        .map(|translations| (app.get_locales().default, translations));
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // Development builds with source maps get symbolicated stack traces
+        if source_maps_enabled() {
+            source_mapped_panic_hook(panic_info);
+        } else {
+            console_error_panic_hook::hook(panic_info);
+        }
+        if let Some(reporter) = &panic_reporter {
+            reporter.report(&ClientError::Panic(panic_info.to_string()));
+        }
+        // Rendering the panic page runs the app's code again, which could panic again
+        // (or use something that was in use when this panic happened) if we did it
+        // inside the hook, so we wait until whatever panicked has unwound
+        let error_pages = Arc::clone(&panic_error_pages);
+        let root_id = panic_root.clone();
+        let panic_msg = panic_info.to_string();
+        let translations = panic_translations.clone();
+        let render = Closure::once_into_js(move || {
+            let translator = translations
+                .and_then(|(locale, translations)| get_embedded_translator(&locale, translations));
+            render_panic_page(&error_pages, &root_id, &panic_msg, translator);
+        });
+        if let Some(window) = web_sys::window() {
+            let _ = window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(render.unchecked_ref(), 0);
+        }
+    }));
 
     plugins
         .functional_actions
@@ -46,11 +91,19 @@ pub fn run_client<M: MutableStore, T: TranslationsManager>(
         .unwrap();
 
     // Set up the properties we'll pass to the router
+    let render_cfg = get_render_cfg().unwrap_or_else(|err| panic!("{}", fmt_err(&err)));
     let router_props = PerseusRouterProps {
-        locales: app.get_locales(),
+        // Only some of the app's locales might have been built
+        locales: render_cfg.built_locales(app.get_locales()),
         error_pages: app.get_error_pages(),
         templates: app.get_templates_map(),
-        render_cfg: get_render_cfg().expect("render configuration invalid or not injected"),
+        render_cfg,
+        error_reporter,
+        focus_target: app.get_focus_target(),
+        persist_translations: app.get_persist_translations(),
+        preload_locales: app.get_preload_locales(),
+        embedded_translations: app.get_embedded_translations(),
+        index_redirect: app.get_index_redirect(),
     };
 
     // This top-level context is what we use for everything, allowing page state to
@@ -60,6 +113,30 @@ pub fn run_client<M: MutableStore, T: TranslationsManager>(
     Ok(())
 }
 
+/// Replaces the app with the error page for a panic, since nothing in the app
+/// can be relied on after one.
+fn render_panic_page(
+    error_pages: &ErrorPages<TemplateNodeType>,
+    root_id: &str,
+    panic_msg: &str,
+    translator: Option<Rc<Translator>>,
+) {
+    let window = web_sys::window().unwrap();
+    let root = window
+        .document()
+        .and_then(|document| document.query_selector(&format!("#{}", root_id)).ok())
+        .flatten();
+    let root = match root {
+        Some(root) => root,
+        None => return,
+    };
+    let url = window.location().pathname().unwrap_or_default();
+    root.set_inner_html("");
+    let ctx = ErrorContext::new(ErrorKind::Panic, &url).with_retry(reload_page);
+    // This scope is never disposed of, since the app won't be doing anything else
+    let _ = create_scope(|cx| error_pages.render_page_ctx(cx, ctx, panic_msg, translator, &root));
+}
+
 /// A convenience type wrapper for the type returned by nearly all client-side
 /// entrypoints.
 pub type ClientReturn = Result<(), JsValue>;
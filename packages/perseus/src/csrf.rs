@@ -0,0 +1,209 @@
+//! Perseus can protect any mutating endpoints you add to your server (e.g. API
+//! routes or form actions) from cross-site request forgery (CSRF) with the
+//! *double-submit* pattern. Once enabled with
+//! `PerseusApp::csrf_protection(true)`, every initial load will give the user a
+//! random token in a cookie, and will also embed that token in the page. Your
+//! mutating requests then need to send the token back in the
+//! `X-Perseus-CSRF-Token` header, which a malicious site can't do, because it
+//! can't read the page.
+//!
+//! In the browser, [`csrf_token`] will get the token, and [`csrf_fetch`] will
+//! make a request with it attached automatically. On the engine-side, each
+//! server integration provides a way of validating requests to your own
+//! routes (which uses [`verify_csrf`] under the hood).
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::errors::CsrfError;
+use crate::errors::WebError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::{get_cookie, is_served_over_https, random_token};
+
+/// The name of the cookie that holds the user's CSRF token.
+pub const CSRF_COOKIE: &str = "__perseus_csrf";
+/// The name of the header mutating requests should send the CSRF token in.
+pub const CSRF_HEADER: &str = "x-perseus-csrf-token";
+/// The name of the `<meta>` tag the CSRF token is embedded in on initial
+/// loads.
+pub(crate) const CSRF_META_NAME: &str = "perseus-csrf-token";
+
+/// Gets the CSRF token from the given request's cookies, or mints a new one if
+/// it doesn't have one. This returns the token and, if a new one was minted,
+/// the value of a `Set-Cookie` header that should be sent to the user. The
+/// cookie will be marked `Secure` if the app is served over HTTPS.
+///
+/// Any response that embeds the token must be sent with
+/// [`PRIVATE_CACHE_CONTROL`](crate::utils::PRIVATE_CACHE_CONTROL), so that
+/// it's never served to another user by a shared cache.
+///
+/// This is designed for use by server integrations.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_or_mint_csrf_token(req: &crate::Request) -> (String, Option<String>) {
+    let cookie_headers = req
+        .headers()
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok());
+    match get_cookie(cookie_headers, CSRF_COOKIE) {
+        Some(token) if is_valid_token(token) => (token.to_string(), None),
+        _ => {
+            let token = random_token();
+            let set_cookie = csrf_cookie(&token, is_served_over_https());
+            (token, Some(set_cookie))
+        }
+    }
+}
+
+/// Creates the value of a `Set-Cookie` header that gives the user the given
+/// CSRF token.
+#[cfg(not(target_arch = "wasm32"))]
+fn csrf_cookie(token: &str, secure: bool) -> String {
+    let secure = if secure { "; Secure" } else { "" };
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax{}",
+        CSRF_COOKIE, token, secure
+    )
+}
+
+/// Checks that a request with the given method, `Cookie` headers, and CSRF
+/// token header is allowed. Requests with safe methods (`GET`, `HEAD`,
+/// `OPTIONS`, and `TRACE`) are always allowed, and all others need to have a
+/// CSRF token header that matches their CSRF cookie.
+///
+/// This is designed for use by server integrations.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_csrf<'a>(
+    method: &str,
+    cookie_headers: impl IntoIterator<Item = &'a str>,
+    token_header: Option<&str>,
+) -> Result<(), CsrfError> {
+    if ["GET", "HEAD", "OPTIONS", "TRACE"]
+        .iter()
+        .any(|safe| method.eq_ignore_ascii_case(safe))
+    {
+        return Ok(());
+    }
+
+    let cookie = match get_cookie(cookie_headers, CSRF_COOKIE) {
+        Some(cookie) if is_valid_token(cookie) => cookie,
+        _ => return Err(CsrfError::MissingCookie),
+    };
+    let token = token_header.ok_or(CsrfError::MissingToken)?;
+    if constant_time_eq(cookie.trim().as_bytes(), token.trim().as_bytes()) {
+        Ok(())
+    } else {
+        Err(CsrfError::Mismatch)
+    }
+}
+
+/// Checks if the given string could be a token Perseus minted.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_valid_token(token: &str) -> bool {
+    token.len() == 32 && token.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Compares two byte strings in time that doesn't depend on where they differ,
+/// so that tokens can't be guessed byte-by-byte. This should be used whenever
+/// you check a secret token a user has sent you in your own routes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Gets the CSRF token that was embedded in the page, which should be sent in
+/// the [`CSRF_HEADER`] header of any mutating requests to your server. This
+/// will fail if CSRF protection hasn't been enabled.
+pub fn csrf_token() -> Result<String, WebError> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .query_selector(&format!("meta[name=\"{}\"]", CSRF_META_NAME))
+            .ok()
+            .flatten()
+            .and_then(|meta| meta.get_attribute("content"))
+            .ok_or(WebError::Unavailable {
+                api: "csrf token".to_string(),
+            })
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    Err(WebError::NotInBrowser)
+}
+
+/// Makes a request to the given URL with the given method and body (which
+/// will be sent as-is), attaching the page's CSRF token automatically. This
+/// returns the response, whatever its status code.
+#[cfg(target_arch = "wasm32")]
+pub async fn csrf_fetch(
+    url: &str,
+    method: &str,
+    body: Option<&str>,
+) -> Result<web_sys::Response, WebError> {
+    use crate::web::js_err;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{Headers, Request, RequestCredentials, RequestInit};
+
+    let headers = Headers::new().map_err(js_err)?;
+    headers.set(CSRF_HEADER, &csrf_token()?).map_err(js_err)?;
+    let mut opts = RequestInit::new();
+    opts.method(method)
+        .headers(&headers)
+        // The cookie needs to be sent too
+        .credentials(RequestCredentials::SameOrigin);
+    if let Some(body) = body {
+        opts.body(Some(&JsValue::from_str(body)));
+    }
+    let request = Request::new_with_str_and_init(url, &opts).map_err(js_err)?;
+
+    let res = JsFuture::from(web_sys::window().unwrap().fetch_with_request(&request))
+        .await
+        .map_err(js_err)?;
+    Ok(res.unchecked_into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_double_submitted_token() {
+        let req = http::Request::builder().body(()).unwrap();
+        let (token, set_cookie) = get_or_mint_csrf_token(&req);
+        assert!(set_cookie.is_some());
+        let cookie = format!("theme=dark; {}={}", CSRF_COOKIE, token);
+        // Existing tokens are reused
+        let req = http::Request::builder()
+            .header("Cookie", &cookie)
+            .body(())
+            .unwrap();
+        assert_eq!(get_or_mint_csrf_token(&req), (token.clone(), None));
+
+        assert!(verify_csrf("GET", None::<&str>, None).is_ok());
+        assert!(verify_csrf("POST", Some(cookie.as_str()), Some(&token)).is_ok());
+        assert!(matches!(
+            verify_csrf("POST", None::<&str>, Some(&token)),
+            Err(CsrfError::MissingCookie)
+        ));
+        assert!(matches!(
+            verify_csrf("DELETE", Some(cookie.as_str()), None),
+            Err(CsrfError::MissingToken)
+        ));
+        assert!(matches!(
+            verify_csrf("POST", Some(cookie.as_str()), Some(&"0".repeat(32))),
+            Err(CsrfError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn cookie_is_secure_over_https() {
+        let token = random_token();
+        assert!(!csrf_cookie(&token, false).contains("Secure"));
+        assert!(csrf_cookie(&token, true).ends_with("; Secure"));
+        assert!(csrf_cookie(&token, true).starts_with(&format!("{}={};", CSRF_COOKIE, token)));
+    }
+}
@@ -0,0 +1,219 @@
+//! Thin adapters for fetching data from REST and GraphQL APIs in your state
+//! generation functions, which handle retries, caching, and deserializing
+//! responses into your own types. These are built on `reqwest`, so you can
+//! always drop down to that if you need more control.
+//!
+//! ```rust,ignore
+//! let posts: Vec<Post> = rest_get(
+//!     "https://example.com/api/posts",
+//!     &FetchOpts::new().cache("posts"),
+//! )
+//! .await?;
+//! ```
+
+use crate::errors::DataError;
+use crate::utils::cache_fallible_res;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+
+/// Options for fetching data.
+#[derive(Debug, Clone)]
+pub struct FetchOpts {
+    /// How many times to retry failed requests.
+    retries: u32,
+    /// How long to wait before the first retry (this doubles with each retry).
+    backoff: Duration,
+    /// The name to cache the result under, if it should be cached.
+    cache_name: Option<String>,
+    /// Whether or not to ignore any cached result.
+    force_run: bool,
+    /// Extra headers to send.
+    headers: Vec<(String, String)>,
+}
+impl Default for FetchOpts {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            cache_name: None,
+            force_run: false,
+            headers: Vec::new(),
+        }
+    }
+}
+impl FetchOpts {
+    /// Creates the default options, which will retry failed requests twice, and
+    /// won't cache anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how many times failed requests should be retried. Only network
+    /// errors and server errors (`5xx` and `429`) will be retried.
+    pub fn retries(mut self, val: u32) -> Self {
+        self.retries = val;
+        self
+    }
+    /// Sets how long to wait before the first retry, which will double with
+    /// each subsequent retry.
+    pub fn backoff(mut self, val: Duration) -> Self {
+        self.backoff = val;
+        self
+    }
+    /// Caches the result under the given name with
+    /// [`cache_fallible_res`](crate::utils::cache_fallible_res), so that it
+    /// won't be fetched again in development.
+    pub fn cache(mut self, name: &str) -> Self {
+        self.cache_name = Some(name.to_string());
+        self
+    }
+    /// Sets whether or not to ignore any cached result and fetch the data
+    /// again (see [`cache_res`](crate::utils::cache_res)).
+    pub fn force_run(mut self, val: bool) -> Self {
+        self.force_run = val;
+        self
+    }
+    /// Adds a header to send with the request (e.g. `Authorization`).
+    pub fn header(mut self, name: &str, val: &str) -> Self {
+        self.headers.push((name.to_string(), val.to_string()));
+        self
+    }
+}
+
+/// Fetches the given URL with a `GET` request, deserializing the JSON response
+/// into the given type.
+pub async fn rest_get<T>(url: &str, opts: &FetchOpts) -> Result<T, DataError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let fetch = || async {
+        let client = reqwest::Client::new();
+        send_with_retries(url, opts, || {
+            opts.headers
+                .iter()
+                .fold(client.get(url), |req, (name, val)| req.header(name, val))
+        })
+        .await?
+        .json::<T>()
+        .await
+        .map_err(|err| DataError::RequestFailed {
+            url: url.to_string(),
+            source: err,
+        })
+    };
+
+    match &opts.cache_name {
+        Some(name) => cache_fallible_res(name, fetch, opts.force_run).await,
+        None => fetch().await,
+    }
+}
+
+/// The body of a GraphQL request.
+#[derive(Serialize)]
+struct GraphqlRequest<'a, V: Serialize> {
+    query: &'a str,
+    variables: V,
+}
+/// The body of a GraphQL response.
+#[derive(Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+/// An error in a GraphQL response.
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+/// Runs the given GraphQL query against the given endpoint with the given
+/// variables (use `()` if there aren't any), deserializing the `data` of the
+/// response into the given type. If the response has any errors, this will
+/// fail, even if some data was returned.
+pub async fn graphql_query<T, V>(
+    endpoint: &str,
+    query: &str,
+    variables: V,
+    opts: &FetchOpts,
+) -> Result<T, DataError>
+where
+    T: Serialize + DeserializeOwned,
+    V: Serialize,
+{
+    let body = GraphqlRequest { query, variables };
+    let fetch = || async {
+        let client = reqwest::Client::new();
+        let res = send_with_retries(endpoint, opts, || {
+            opts.headers
+                .iter()
+                .fold(client.post(endpoint).json(&body), |req, (name, val)| {
+                    req.header(name, val)
+                })
+        })
+        .await?
+        .json::<GraphqlResponse<T>>()
+        .await
+        .map_err(|err| DataError::RequestFailed {
+            url: endpoint.to_string(),
+            source: err,
+        })?;
+
+        if !res.errors.is_empty() {
+            return Err(DataError::GraphqlErrors {
+                url: endpoint.to_string(),
+                errors: res.errors.into_iter().map(|err| err.message).collect(),
+            });
+        }
+        res.data.ok_or(DataError::GraphqlNoData {
+            url: endpoint.to_string(),
+        })
+    };
+
+    match &opts.cache_name {
+        Some(name) => cache_fallible_res(name, fetch, opts.force_run).await,
+        None => fetch().await,
+    }
+}
+
+/// Sends the request created by the given function, retrying it as the given
+/// options specify. This returns the response once it has a successful status
+/// code.
+async fn send_with_retries(
+    url: &str,
+    opts: &FetchOpts,
+    make_req: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, DataError> {
+    let mut backoff = opts.backoff;
+    let mut attempt = 0;
+    loop {
+        let res = make_req()
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+        match res {
+            Ok(res) => return Ok(res),
+            Err(err) if attempt < opts.retries && is_retryable(&err) => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(DataError::RequestFailed {
+                    url: url.to_string(),
+                    source: err,
+                })
+            }
+        }
+    }
+}
+
+/// Checks if the given error is worth retrying (i.e. it might not happen
+/// again).
+fn is_retryable(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error() || status.as_u16() == 429,
+        // Anything that didn't get a response is a network error
+        None => !err.is_builder() && !err.is_decode(),
+    }
+}
@@ -1,4 +1,6 @@
-use crate::build::{build_app, BuildProps};
+use super::reachability::check_reachability;
+use crate::build::{build_app, BuildProgressTracker, BuildProps, BUILD_PROGRESS_PREFIX};
+use crate::utils::fingerprint_static_aliases;
 use crate::{
     errors::{EngineError, ServerError},
     i18n::TranslationsManager,
@@ -6,7 +8,10 @@ use crate::{
     stores::MutableStore,
     PerseusAppBase, SsrNode,
 };
+use std::collections::BTreeMap;
+use std::env;
 use std::rc::Rc;
+use std::sync::mpsc::channel;
 
 /// Builds the app, calling all necessary plugin opportunities. This works
 /// solely with the properties provided in the given
@@ -26,7 +31,7 @@ pub async fn build<M: MutableStore, T: TranslationsManager>(
 
     let immutable_store = app.get_immutable_store();
     let mutable_store = app.get_mutable_store();
-    let locales = app.get_locales();
+    let locales = app.get_locales_to_build();
     // Generate the global state
     let gsc = app.get_global_state_creator();
     let global_state = match gsc.get_build_state().await {
@@ -46,20 +51,47 @@ pub async fn build<M: MutableStore, T: TranslationsManager>(
     // All these parameters can be modified by `PerseusApp` and plugins, so there's
     // no point in having a plugin opportunity here
     let templates_map = app.get_templates_map();
+    let robots_txt = app.get_robots_txt();
+    let failure_policy = app.get_build_failure_policy();
+    let static_aliases = app.get_static_aliases();
+    let asset_urls = if app.get_fingerprint_static_aliases() {
+        fingerprint_static_aliases(&static_aliases)
+    } else {
+        Ok(BTreeMap::new())
+    };
+    let index_view_str = app.get_index_view_str();
+    let index_redirect = app.get_index_redirect();
+    let progress = get_progress_tracker();
 
     // We have to get the translations manager last, because it consumes everything
     let translations_manager = app.get_translations_manager().await;
 
-    let res = build_app(BuildProps {
-        templates: &templates_map,
-        locales: &locales,
-        immutable_store: &immutable_store,
-        mutable_store: &mutable_store,
-        translations_manager: &translations_manager,
-        global_state: &global_state,
-        exporting: false,
-    })
-    .await;
+    let res = match asset_urls {
+        Ok(asset_urls) => {
+            build_app(BuildProps {
+                templates: &templates_map,
+                locales: &locales,
+                immutable_store: &immutable_store,
+                mutable_store: &mutable_store,
+                translations_manager: &translations_manager,
+                global_state: &global_state,
+                exporting: false,
+                failure_policy,
+                progress: &progress,
+                asset_urls,
+            })
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+    // The server will serve this from the immutable store
+    let res = match (res, robots_txt) {
+        (Ok(()), Some(robots_txt)) => immutable_store
+            .write("robots.txt", &robots_txt.render())
+            .await
+            .map_err(|err| err.into()),
+        (res, _) => res,
+    };
     if let Err(err) = res {
         let err: Rc<EngineError> = Rc::new(err.into());
         plugins
@@ -70,6 +102,16 @@ pub async fn build<M: MutableStore, T: TranslationsManager>(
 
         Err(err)
     } else {
+        for warning in check_reachability(
+            &templates_map,
+            &static_aliases,
+            &locales,
+            &index_view_str,
+            index_redirect.as_deref(),
+            &immutable_store,
+        ) {
+            progress.warn(&warning);
+        }
         plugins
             .functional_actions
             .build_actions
@@ -79,3 +121,25 @@ pub async fn build<M: MutableStore, T: TranslationsManager>(
         Ok(())
     }
 }
+
+/// Gets a tracker for the progress of building (and maybe exporting) the app.
+/// If the CLI has asked for updates on this (by setting the
+/// `PERSEUS_BUILD_PROGRESS` environment variable), they'll be printed to
+/// `stdout` as they come in, each on its own line, prefixed with
+/// [`BUILD_PROGRESS_PREFIX`].
+pub(super) fn get_progress_tracker() -> BuildProgressTracker {
+    if !matches!(env::var("PERSEUS_BUILD_PROGRESS").as_deref(), Ok("1")) {
+        return BuildProgressTracker::default();
+    }
+
+    let (sender, receiver) = channel();
+    // This will finish once the tracker has been dropped
+    std::thread::spawn(move || {
+        for progress in receiver {
+            if let Ok(progress) = serde_json::to_string(&progress) {
+                println!("{}{}", BUILD_PROGRESS_PREFIX, progress);
+            }
+        }
+    });
+    BuildProgressTracker::new(Some(sender))
+}
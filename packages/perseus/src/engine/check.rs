@@ -0,0 +1,101 @@
+use super::serve::{detect_standalone, set_standalone_dir};
+use crate::errors::EngineError;
+use crate::server::{get_render_cfg, get_static_alias_mount};
+use crate::{i18n::TranslationsManager, stores::MutableStore};
+use crate::{utils::get_dist_dir, PerseusAppBase, SsrNode};
+use fmterr::fmt_err;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Checks that everything the server needs is present and consistent, without
+/// actually starting it. This is designed to catch incomplete deployments
+/// (e.g. ones that are missing their translations) before any users hit them,
+/// and it's run by `perseus deploy --check`. It can also be run manually on a
+/// deployed binary by setting the `PERSEUS_ENGINE_OPERATION` environment
+/// variable to `check`.
+///
+/// All the problems found will be reported together, rather than stopping at
+/// the first one.
+///
+/// Like the server, this will check relative to the location of the binary in
+/// standalone deployments (which all production builds are), and relative to
+/// the root of the project otherwise.
+pub async fn check(
+    app: PerseusAppBase<SsrNode, impl MutableStore, impl TranslationsManager>,
+) -> Result<(), Rc<EngineError>> {
+    set_standalone_dir(detect_standalone());
+
+    let mut problems = Vec::new();
+
+    // This will also make sure the artifacts are from a compatible version of
+    // Perseus
+    let immutable_store = app.get_immutable_store();
+    match get_render_cfg(&immutable_store).await {
+        Ok(render_cfg) => {
+            // If the render configuration references templates the binary doesn't
+            // know about, they were probably built from different versions of the app
+            let templates = app.get_templates_map();
+            let mut unknown = render_cfg
+                .all_templates()
+                .filter(|template| !templates.contains_key(*template))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            unknown.dedup();
+            for template in unknown {
+                problems.push(format!("the render configuration references the template '{}', which this binary doesn't have (was the app rebuilt after the server?)", template));
+            }
+        }
+        Err(err) => problems.push(fmt_err(&err)),
+    }
+    if app.get_robots_txt().is_some() && immutable_store.read("robots.txt").await.is_err() {
+        problems.push("couldn't read the generated `robots.txt`".to_string());
+    }
+    // These are the same paths the server will use
+    let dist_dir = get_dist_dir();
+    for bundle in [
+        format!("{}/pkg/perseus_engine.js", dist_dir),
+        format!("{}/pkg/perseus_engine_bg.wasm", dist_dir),
+    ] {
+        if !Path::new(&bundle).is_file() {
+            problems.push(format!("the app bundle file '{}' is missing", bundle));
+        }
+    }
+    for (url, path) in app.get_static_aliases() {
+        if get_static_alias_mount(&url).is_some() && !Path::new(&path).is_dir() {
+            problems.push(format!(
+                "the static alias '{}' mounts '{}', which isn't a directory",
+                url, path
+            ));
+        } else if !Path::new(&path).exists() {
+            problems.push(format!(
+                "the static alias '{}' points to '{}', which doesn't exist",
+                url, path
+            ));
+        }
+    }
+
+    let locales = app.get_locales();
+    if locales.using_i18n {
+        let translations_manager = app.get_translations_manager().await;
+        for locale in locales.get_all() {
+            if let Err(err) = translations_manager
+                .get_translations_str_for_locale(locale.to_string())
+                .await
+            {
+                problems.push(fmt_err(&err));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Rc::new(EngineError::CheckFailed {
+            problems: problems
+                .iter()
+                .map(|problem| format!("- {}", problem))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }))
+    }
+}
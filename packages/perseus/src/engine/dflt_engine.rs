@@ -1,12 +1,19 @@
 // This file contains functions exclusive to the default engine systems
 
-use super::serve::{get_host_and_port, get_props};
-use super::EngineOperation;
+use super::serve::get_host_and_port;
+use super::{EngineHookStage, EngineOperation};
+use crate::build::check_artifacts_version;
+use crate::errors::EngineError;
 use crate::{
-    i18n::TranslationsManager, server::ServerProps, stores::MutableStore, PerseusAppBase, SsrNode,
+    i18n::TranslationsManager,
+    server::{ServerOptions, ServerProps},
+    stores::MutableStore,
+    PerseusAppBase, SsrNode,
 };
 use fmterr::fmt_err;
-use futures::Future;
+use futures::channel::oneshot;
+use futures::future::{join, select};
+use futures::{pin_mut, Future};
 use std::env;
 
 /// A wrapper around `run_dflt_engine` for apps that only use exporting, and so
@@ -42,6 +49,25 @@ where
 /// arguments to the binary invocation. If this is not the desired behavior, you
 /// should handle the `EngineOperation::ExportErrorPage` case manually.
 ///
+/// Before anything else, the app's configuration will be validated (see
+/// [`PerseusAppBase::validate`]), and any problems will be reported, failing
+/// the operation. The exception is a production server (or a check of one),
+/// which will have been validated when it was built, and which may not be
+/// running from the project directory.
+///
+/// Any engine hooks the app has registered will be run before and after the
+/// operation.
+///
+/// While serving, any jobs the app has scheduled will be run alongside the
+/// server. If there are any, the server will stop when the process receives
+/// `SIGINT` or `SIGTERM`, and no more jobs will be started once it has (or once
+/// the server stops by itself). Any job that's running will be given up to 30
+/// seconds to finish.
+///
+/// Before serving in development, this will check that the app's build
+/// artifacts were created by a compatible version of Perseus, rebuilding the
+/// app if it's been configured to with `.rebuild_stale_artifacts()`.
+///
 /// This returns an exit code, which should be returned from the process. Any
 /// handled errors will be printed to the console.
 pub async fn run_dflt_engine<M, T, F, A>(
@@ -55,7 +81,27 @@ where
     F: Future<Output = ()>,
     A: Fn() -> PerseusAppBase<SsrNode, M, T> + 'static + Send + Sync + Clone,
 {
-    match op {
+    if cfg!(debug_assertions) || !matches!(op, EngineOperation::Serve | EngineOperation::Check) {
+        let errors = app().validate();
+        if !errors.is_empty() {
+            let err = EngineError::InvalidConfig {
+                problems: errors
+                    .iter()
+                    .map(|err| format!("- {}", err))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            };
+            eprintln!("{}", fmt_err(&err));
+            return 1;
+        }
+    }
+
+    if let Err(err) = app().run_engine_hooks(op, EngineHookStage::Before).await {
+        eprintln!("{}", fmt_err(&err));
+        return 1;
+    }
+
+    let exit_code = match op {
         EngineOperation::Build => match super::engine_build(app()).await {
             Ok(_) => 0,
             Err(err) => {
@@ -107,9 +153,38 @@ where
             }
         }
         EngineOperation::Serve => {
+            // In production, the working directory won't be set up until we get the server
+            // properties, so this can only be checked in development (otherwise that will
+            // fail with a less friendly error)
+            if cfg!(debug_assertions) {
+                let app_inst = app();
+                if let Err(err) = check_artifacts_version(&app_inst.get_immutable_store()).await {
+                    if !app_inst.get_rebuild_stale_artifacts() {
+                        eprintln!("{}", fmt_err(&err));
+                        return 1;
+                    }
+                    eprintln!("[WARNING]: {}\nRebuilding...", fmt_err(&err));
+                    if let Err(err) = super::engine_build(app_inst).await {
+                        eprintln!("{}", fmt_err(&*err));
+                        return 1;
+                    }
+                }
+            }
             // To reduce friction for default servers and user-made servers, we
             // automatically do the boilerplate that all servers would have to do
-            let props = get_props(app());
+            let props = match ServerOptions::builder(app()).build() {
+                Ok(props) => props,
+                Err(err) => {
+                    eprintln!("{}", fmt_err(&err));
+                    return 1;
+                }
+            };
+            // The server won't return, so we run the hooks for after it's been set up
+            // now
+            if let Err(err) = app().run_engine_hooks(op, EngineHookStage::After).await {
+                eprintln!("{}", fmt_err(&err));
+                return 1;
+            }
             // This returns a `(String, u16)` of the host and port for maximum compatibility
             let addr = get_host_and_port();
             // In production, give the user a heads up that something's actually happening
@@ -120,13 +195,81 @@ where
                 port = &addr.1
             );
 
-            serve_fn(props, addr).await;
+            let app_inst = app();
+            if app_inst.has_scheduled_jobs() {
+                let (stop_tx, stop_rx) = oneshot::channel::<()>();
+                let server = async {
+                    let server = serve_fn(props, addr);
+                    let shutdown = shutdown_signal();
+                    pin_mut!(server, shutdown);
+                    // Some servers handle shutdown signals themselves, so they might stop
+                    // before we're told to
+                    select(server, shutdown).await;
+                    // Either way, the jobs should stop too (the server can just be dropped)
+                    let _ = stop_tx.send(());
+                };
+                let jobs = app_inst.run_scheduled_jobs(async {
+                    let _ = stop_rx.await;
+                });
+                // This will finish once the jobs have stopped, which gives any job that's
+                // running a chance to finish
+                join(server, jobs).await;
+            } else {
+                serve_fn(props, addr).await;
+            }
             0
         }
-        EngineOperation::Tinker => {
-            // This is infallible (though plugins could panic)
-            super::engine_tinker(app());
+        EngineOperation::Tinker => match super::engine_tinker(app()) {
+            Ok(_) => 0,
+            Err(err) => {
+                eprintln!("{}", fmt_err(&*err));
+                1
+            }
+        },
+        // The configuration has already been validated
+        EngineOperation::Validate => {
+            println!("Your app's configuration is valid!");
             0
         }
+        EngineOperation::Check => match super::engine_check(app()).await {
+            Ok(_) => {
+                println!("Everything the server needs is present!");
+                0
+            }
+            Err(err) => {
+                eprintln!("{}", fmt_err(&*err));
+                1
+            }
+        },
+    };
+
+    if exit_code == 0 && op != EngineOperation::Serve {
+        if let Err(err) = app().run_engine_hooks(op, EngineHookStage::After).await {
+            eprintln!("{}", fmt_err(&err));
+            return 1;
+        }
+    }
+
+    exit_code
+}
+
+/// Waits until the process is told to shut down, with either `SIGINT` (i.e.
+/// Ctrl+C) or, on Unix, `SIGTERM` (which is what most deployment platforms
+/// send).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
+            let sigterm = sigterm.recv();
+            pin_mut!(ctrl_c, sigterm);
+            select(ctrl_c, sigterm).await;
+            return;
+        }
     }
+    ctrl_c.await;
 }
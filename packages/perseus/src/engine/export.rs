@@ -1,18 +1,24 @@
+use super::build::get_progress_tracker;
+use super::reachability::check_reachability;
 use crate::build::{build_app, BuildProps};
 use crate::errors::ServerError;
 use crate::export::{export_app, ExportProps};
 use crate::{
     plugins::{PluginAction, Plugins},
+    server::get_static_alias_mount,
     utils::get_path_prefix_server,
     PerseusApp, SsrNode,
 };
-use fs_extra::dir::{copy as copy_dir, CopyOptions};
-use std::collections::HashMap;
+use fs_extra::dir::{copy as copy_dir, get_dir_content, CopyOptions};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crate::errors::*;
+use crate::server::get_render_cfg;
+use crate::stores::get_asset_path;
+use crate::utils::{add_fingerprinted_aliases, fingerprint_static_aliases};
 use crate::{i18n::TranslationsManager, stores::MutableStore, PerseusAppBase};
 
 /// Exports the app to static files, given a [`PerseusApp`]. This is
@@ -25,17 +31,43 @@ pub async fn export<M: MutableStore, T: TranslationsManager>(
     app: PerseusAppBase<SsrNode, M, T>,
 ) -> Result<(), Rc<EngineError>> {
     let plugins = app.get_plugins();
-    let static_aliases = app.get_static_aliases();
-    // This won't have any trailing slashes (they're stripped by the immutable store
-    // initializer)
-    let dest = format!("{}/exported", app.get_immutable_store().get_path());
+    let mut static_aliases = app.get_static_aliases();
+    let immutable_store = app.get_immutable_store();
+    let dest = Path::new(immutable_store.get_path()).join("exported");
     let static_dir = app.get_static_dir();
 
     build_and_export(app).await?;
+    // Fingerprinted static aliases are exported at their hashed URLs as well
+    let render_cfg = get_render_cfg(&immutable_store)
+        .await
+        .map_err(|err| Rc::new(err.into()))?;
+    add_fingerprinted_aliases(&mut static_aliases, &render_cfg.assets);
     // After that's done, we can do two copy operations in parallel at least
     copy_static_aliases(&plugins, &static_aliases, &dest)?;
     copy_static_dir(&plugins, &static_dir, &dest)?;
 
+    // Let plugins post-process everything that was just exported
+    let exported_files = match get_dir_content(&dest) {
+        Ok(content) => content.files.into_iter().map(PathBuf::from).collect(),
+        Err(err) => {
+            let err = Rc::new(EngineError::ListExportedFilesError {
+                source: err,
+                dest: dest.to_string_lossy().to_string(),
+            });
+            plugins
+                .functional_actions
+                .export_actions
+                .after_failed_export
+                .run(err.clone(), plugins.get_plugin_data());
+            return Err(err);
+        }
+    };
+    plugins
+        .functional_actions
+        .export_actions
+        .after_export
+        .run((dest, exported_files), plugins.get_plugin_data());
+
     plugins
         .functional_actions
         .export_actions
@@ -62,7 +94,7 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
     let immutable_store = app.get_immutable_store();
     // We don't need this in exporting, but the build process does
     let mutable_store = app.get_mutable_store();
-    let locales = app.get_locales();
+    let locales = app.get_locales_to_build();
     // Generate the global state
     let gsc = app.get_global_state_creator();
     let global_state = match gsc.get_build_state().await {
@@ -79,23 +111,46 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
     };
     let templates_map = app.get_templates_map();
     let index_view_str = app.get_index_view_str();
+    let boot_indicator = app.get_boot_indicator();
+    let wasm_loading = app.get_wasm_loading();
+    let index_redirect = app.get_index_redirect();
     let root_id = app.get_root();
+    let robots_txt = app.get_robots_txt();
+    let experiments = app.get_experiments();
+    let failure_policy = app.get_build_failure_policy();
+    let static_aliases = app.get_static_aliases();
+    let asset_urls = if app.get_fingerprint_static_aliases() {
+        fingerprint_static_aliases(&static_aliases)
+    } else {
+        Ok(BTreeMap::new())
+    };
+    // This is shared between building and exporting, so the progress continues
+    // across both
+    let progress = get_progress_tracker();
     // This consumes `self`, so we get it finally
     let translations_manager = app.get_translations_manager().await;
 
     // Build the site for all the common locales (done in parallel), denying any
     // non-exportable features We need to build and generate those artifacts
     // before we can proceed on to exporting
-    let build_res = build_app(BuildProps {
-        templates: &templates_map,
-        locales: &locales,
-        immutable_store: &immutable_store,
-        mutable_store: &mutable_store,
-        translations_manager: &translations_manager,
-        global_state: &global_state,
-        exporting: true,
-    })
-    .await;
+    let build_res = match asset_urls {
+        Ok(asset_urls) => {
+            build_app(BuildProps {
+                templates: &templates_map,
+                locales: &locales,
+                immutable_store: &immutable_store,
+                mutable_store: &mutable_store,
+                translations_manager: &translations_manager,
+                global_state: &global_state,
+                exporting: true,
+                failure_policy,
+                progress: &progress,
+                asset_urls,
+            })
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
     if let Err(err) = build_res {
         let err: Rc<EngineError> = Rc::new(err.into());
         plugins
@@ -105,6 +160,16 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
             .run(err.clone(), plugins.get_plugin_data());
         return Err(err);
     }
+    for warning in check_reachability(
+        &templates_map,
+        &static_aliases,
+        &locales,
+        &index_view_str,
+        index_redirect.as_deref(),
+        &immutable_store,
+    ) {
+        progress.warn(&warning);
+    }
     plugins
         .functional_actions
         .export_actions
@@ -114,19 +179,39 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
     // (which needs access to the render config, generated in the above build step)
     // It doesn't matter if the type parameters here are wrong, this function
     // doesn't use them
-    let index_view =
+    let mut index_view =
         PerseusApp::get_html_shell(index_view_str, &root_id, &immutable_store, &plugins).await;
+    index_view.shell = plugins
+        .functional_actions
+        .export_actions
+        .transform_html_shell
+        .run_chained(index_view.shell, plugins.get_plugin_data());
+    if boot_indicator {
+        index_view = index_view.boot_indicator();
+    }
+    index_view = index_view.wasm_loading(wasm_loading);
+    let index_view = index_view.prepare();
     // Turn the build artifacts into self-contained static files
     let export_res = export_app(ExportProps {
         templates: &templates_map,
         html_shell: index_view,
         locales: &locales,
+        index_redirect: index_redirect.as_deref(),
         immutable_store: &immutable_store,
         translations_manager: &translations_manager,
         path_prefix: get_path_prefix_server(),
         global_state: &global_state,
+        experiments: &experiments,
+        progress: &progress,
     })
     .await;
+    let export_res = match (export_res, robots_txt) {
+        (Ok(()), Some(robots_txt)) => immutable_store
+            .write("exported/robots.txt", &robots_txt.render())
+            .await
+            .map_err(|err| err.into()),
+        (res, _) => res,
+    };
     if let Err(err) = export_res {
         let err: Rc<EngineError> = Rc::new(err.into());
         plugins
@@ -140,10 +225,10 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
     Ok(())
 }
 
-/// Copies the static aliases into a distribution directory at `dest` (no
-/// trailing `/`). This should be the root of the destination directory for the
-/// exported files. Because this provides a customizable destination, it is
-/// fully engine-agnostic.
+/// Copies the static aliases into a distribution directory at `dest`. This
+/// should be the root of the destination directory for the exported files.
+/// Because this provides a customizable destination, it is fully
+/// engine-agnostic.
 ///
 /// The error type here is a tuple of the location the asset was copied from,
 /// the location it was copied to, and the error in that process (which could be
@@ -151,7 +236,7 @@ async fn build_and_export<M: MutableStore, T: TranslationsManager>(
 fn copy_static_aliases(
     plugins: &Plugins<SsrNode>,
     static_aliases: &HashMap<String, String>,
-    dest: &str,
+    dest: &Path,
 ) -> Result<(), Rc<EngineError>> {
     // Loop through any static aliases and copy them in too
     // Unlike with the server, these could override pages!
@@ -159,10 +244,41 @@ fn copy_static_aliases(
     // Remember: `alias` has a leading `/`!
     for (alias, path) in static_aliases {
         let from = PathBuf::from(path);
-        let to = format!("{}{}", dest, alias);
+        let mount = get_static_alias_mount(alias);
+        let to_path = match get_asset_path(dest, mount.unwrap_or(alias)) {
+            Ok(to_path) => to_path,
+            // Aliases that would be copied outside the export directory can't be exported
+            Err(err) => {
+                let err = EngineError::CopyStaticAliasFileError {
+                    source: std::io::Error::new(std::io::ErrorKind::InvalidInput, err),
+                    to: alias.to_string(),
+                    from: path.to_string(),
+                };
+                let err = Rc::new(err);
+                plugins
+                    .functional_actions
+                    .export_actions
+                    .after_failed_static_alias_file_copy
+                    .run(err.clone(), plugins.get_plugin_data());
+                return Err(err);
+            }
+        };
+        let to = to_path.to_string_lossy().to_string();
 
         if from.is_dir() {
-            if let Err(err) = copy_dir(&from, &to, &CopyOptions::new()) {
+            // Mounted directories have their contents copied to where they're mounted
+            let res = match mount {
+                Some(_) => fs::create_dir_all(&to_path)
+                    .map_err(Into::into)
+                    .and_then(|_| {
+                        let mut options = CopyOptions::new();
+                        options.content_only = true;
+                        options.overwrite = true;
+                        copy_dir(&from, &to_path, &options)
+                    }),
+                None => copy_dir(&from, &to_path, &CopyOptions::new()),
+            };
+            if let Err(err) = res {
                 let err = EngineError::CopyStaticAliasDirErr {
                     source: err,
                     to,
@@ -176,7 +292,11 @@ fn copy_static_aliases(
                     .run(err.clone(), plugins.get_plugin_data());
                 return Err(err);
             }
-        } else if let Err(err) = fs::copy(&from, &to) {
+        } else if let Err(err) = to_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&from, &to_path))
+        {
             let err = EngineError::CopyStaticAliasFileError {
                 source: err,
                 to,
@@ -201,22 +321,18 @@ fn copy_static_aliases(
 fn copy_static_dir(
     plugins: &Plugins<SsrNode>,
     static_dir_raw: &str,
-    dest: &str,
+    dest: &Path,
 ) -> Result<(), Rc<EngineError>> {
     // Copy the `static` directory into the export package if it exists
     // If the user wants extra, they can use static aliases, plugins are unnecessary
     // here
     let static_dir = PathBuf::from(static_dir_raw);
     if static_dir.exists() {
-        if let Err(err) = copy_dir(
-            &static_dir,
-            format!("{}/.perseus/", dest),
-            &CopyOptions::new(),
-        ) {
+        if let Err(err) = copy_dir(&static_dir, dest.join(".perseus"), &CopyOptions::new()) {
             let err = EngineError::CopyStaticDirError {
                 source: err,
                 path: static_dir_raw.to_string(),
-                dest: dest.to_string(),
+                dest: dest.to_string_lossy().to_string(),
             };
             let err = Rc::new(err);
             plugins
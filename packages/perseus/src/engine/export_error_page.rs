@@ -1,6 +1,10 @@
 use crate::{
-    errors::EngineError, i18n::TranslationsManager, plugins::PluginAction,
-    server::build_error_page, stores::MutableStore, PerseusApp, PerseusAppBase, SsrNode,
+    errors::EngineError,
+    i18n::TranslationsManager,
+    plugins::PluginAction,
+    server::{build_error_page, ErrorTranslations},
+    stores::MutableStore,
+    PerseusApp, PerseusAppBase, SsrNode,
 };
 use std::{fs, rc::Rc};
 
@@ -20,6 +24,8 @@ pub async fn export_error_page(
     let plugins = app.get_plugins();
 
     let error_pages = app.get_error_pages();
+    let locales = app.get_locales();
+    let embedded_translations = app.get_embedded_translations();
     // Prepare the HTML shell
     let index_view_str = app.get_index_view_str();
     let root_id = app.get_root();
@@ -27,8 +33,12 @@ pub async fn export_error_page(
     // We assume the app has already been built before running this (so the render
     // config must be available) It doesn't matter if the type parameters here
     // are wrong, this function doesn't use them
-    let html_shell =
+    let mut html_shell =
         PerseusApp::get_html_shell(index_view_str, &root_id, &immutable_store, &plugins).await;
+    if app.get_boot_indicator() {
+        html_shell = html_shell.boot_indicator();
+    }
+    html_shell = html_shell.wasm_loading(app.get_wasm_loading());
 
     plugins
         .functional_actions
@@ -36,8 +46,19 @@ pub async fn export_error_page(
         .before_export_error_page
         .run((code, output.to_string()), plugins.get_plugin_data());
 
-    // Build that error page as the server does
-    let err_page_str = build_error_page("", code, "", None, &error_pages, &html_shell);
+    // Build that error page as the server does (exported error pages aren't under
+    // any particular locale, so they'll be in the default one)
+    let translations_manager = app.get_translations_manager().await;
+    let error_translations =
+        ErrorTranslations::new(&locales, &translations_manager, embedded_translations).await;
+    let err_page_str = build_error_page(
+        "",
+        code,
+        "",
+        error_translations.get_translator(""),
+        &error_pages,
+        &html_shell,
+    );
 
     // Write that to the given output location
     match fs::write(&output, err_page_str) {
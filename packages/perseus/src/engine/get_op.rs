@@ -31,6 +31,8 @@ pub fn get_op() -> Option<EngineOperation> {
         "export" => Some(EngineOperation::Export),
         "export_error_page" => Some(EngineOperation::ExportErrorPage),
         "tinker" => Some(EngineOperation::Tinker),
+        "check" => Some(EngineOperation::Check),
+        "validate" => Some(EngineOperation::Validate),
         _ => {
             if cfg!(debug_assertions) {
                 None
@@ -42,6 +44,7 @@ pub fn get_op() -> Option<EngineOperation> {
 }
 
 /// A representation of the server-side engine operations that can be performed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EngineOperation {
     /// Run the server for the app. This assumes the app has already been built.
     Serve,
@@ -55,4 +58,10 @@ pub enum EngineOperation {
     ExportErrorPage,
     /// Run the tinker plugin actions.
     Tinker,
+    /// Check that everything the server needs is present and consistent,
+    /// without starting it. This assumes the app has already been built.
+    Check,
+    /// Check the app's configuration for problems, without doing anything
+    /// else (this happens before every other operation too).
+    Validate,
 }
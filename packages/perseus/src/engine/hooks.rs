@@ -0,0 +1,22 @@
+use std::future::Future;
+use std::pin::Pin;
+
+/// The stages of an engine operation at which engine hooks can be run. These
+/// are set up with `PerseusApp::engine_hook()` and friends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineHookStage {
+    /// Before the operation has begun.
+    Before,
+    /// After the operation has completed successfully. For the server, this
+    /// is once everything is ready, just before it starts listening for
+    /// requests.
+    After,
+}
+
+/// The result of an engine hook. Any errors returned will stop the engine
+/// operation.
+pub type EngineHookResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// An engine hook, which is stored as a function returning a future so that it
+/// can be run as many times as necessary.
+pub(crate) type EngineHookFn = Box<dyn Fn() -> Pin<Box<dyn Future<Output = EngineHookResult>>>>;
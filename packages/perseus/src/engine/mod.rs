@@ -1,10 +1,19 @@
 mod build;
+mod check;
 mod export;
 mod export_error_page;
+mod hooks;
+mod reachability;
+mod scheduler;
 mod tinker;
 pub use build::build as engine_build;
+pub use check::check as engine_check;
 pub use export::export as engine_export;
 pub use export_error_page::export_error_page as engine_export_error_page;
+pub(crate) use hooks::EngineHookFn;
+pub use hooks::{EngineHookResult, EngineHookStage};
+pub use scheduler::Schedule;
+pub(crate) use scheduler::{run_jobs as run_scheduled_jobs, ScheduledJob, JOB_SHUTDOWN_TIMEOUT};
 pub use tinker::tinker as engine_tinker;
 
 #[cfg(feature = "dflt-engine")]
@@ -16,3 +25,4 @@ mod get_op;
 pub use get_op::{get_op, EngineOperation};
 
 mod serve;
+pub(crate) use serve::{detect_standalone, set_standalone_dir};
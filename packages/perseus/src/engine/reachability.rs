@@ -0,0 +1,165 @@
+use crate::i18n::Locales;
+use crate::stores::ImmutableStore;
+use crate::template::TemplateMap;
+use crate::utils::get_path_prefix_server;
+use crate::SsrNode;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Checks which of the app's templates, static aliases, and locales can't be
+/// reached from any of the pages prerendered at build-time (or from the index
+/// view, which is where most navigation lives), returning a warning for each.
+/// This can only see links that are actually in the HTML, so it's a heuristic
+/// designed to catch things that were registered and then forgotten about,
+/// rather than a guarantee.
+///
+/// The app's index redirect (if it has one) counts as a link.
+pub(super) fn check_reachability(
+    templates: &TemplateMap<SsrNode>,
+    static_aliases: &HashMap<String, String>,
+    locales: &Locales,
+    index_view: &str,
+    index_redirect: Option<&str>,
+    immutable_store: &ImmutableStore,
+) -> Vec<String> {
+    let mut documents = vec![index_view.to_string()];
+    if let Ok(entries) = fs::read_dir(Path::new(immutable_store.get_path()).join("static")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().map_or(false, |ext| ext == "html") {
+                if let Ok(contents) = fs::read_to_string(&path) {
+                    documents.push(contents);
+                }
+            }
+        }
+    }
+
+    // The base path might be a full URL, but we only care about the path part
+    let path_prefix = get_path_prefix_server();
+    let path_prefix = match path_prefix.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |idx| &rest[idx..]),
+        None => &path_prefix,
+    };
+    let mut linked_paths = HashSet::new();
+    let mut linked_locales = HashSet::new();
+    if let Some(index_redirect) = index_redirect {
+        linked_paths.insert(index_redirect.to_string());
+    }
+    for link in documents.iter().flat_map(|doc| get_links(doc)) {
+        let path = match normalize_link(link, path_prefix) {
+            Some(path) => path,
+            None => continue,
+        };
+        let path = match path.split_once('/') {
+            Some((locale, rest)) if locales.using_i18n && locales.is_supported(locale) => {
+                linked_locales.insert(locale.to_string());
+                rest.to_string()
+            }
+            _ if locales.using_i18n && locales.is_supported(&path) => {
+                linked_locales.insert(path);
+                String::new()
+            }
+            _ => path,
+        };
+        linked_paths.insert(path);
+    }
+
+    let mut warnings = Vec::new();
+    let mut unreachable_templates = templates
+        .keys()
+        .filter(|template| {
+            // The index page is always reachable
+            template.as_str() != "index"
+                && !linked_paths
+                    .iter()
+                    .any(|path| path == *template || path.starts_with(&format!("{}/", template)))
+        })
+        .collect::<Vec<_>>();
+    unreachable_templates.sort();
+    for template in unreachable_templates {
+        warnings.push(format!(
+            "the template '{}' isn't linked to from the index view or any prerendered page, so it might be unreachable (if it's no longer needed, consider removing it)",
+            template
+        ));
+    }
+    let mut unreferenced_aliases = static_aliases
+        .keys()
+        .filter(|url| {
+            // For mounted directories, any reference to something inside them counts
+            let url = url.trim_start_matches('/').trim_end_matches('*');
+            !documents.iter().any(|doc| doc.contains(url))
+        })
+        .collect::<Vec<_>>();
+    unreferenced_aliases.sort();
+    for url in unreferenced_aliases {
+        warnings.push(format!(
+            "the static alias '{}' isn't referenced by the index view or any prerendered page (if it's no longer needed, consider removing it)",
+            url
+        ));
+    }
+    if locales.using_i18n {
+        for locale in &locales.other {
+            if !linked_locales.contains(locale) {
+                warnings.push(format!(
+                    "the translations for the locale '{}' aren't linked to from the index view or any prerendered page, so users can only reach them through locale detection (consider adding a locale switcher)",
+                    locale
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Gets the targets of all the links in the given HTML.
+fn get_links(html: &str) -> Vec<&str> {
+    let mut links = Vec::new();
+    for quote in ['"', '\''] {
+        let attr = format!("href={}", quote);
+        let mut rest = html;
+        while let Some(idx) = rest.find(&attr) {
+            rest = &rest[(idx + attr.len())..];
+            match rest.find(quote) {
+                Some(end) => {
+                    links.push(&rest[..end]);
+                    rest = &rest[end..];
+                }
+                None => break,
+            }
+        }
+    }
+
+    links
+}
+
+/// Turns the given link into the path of a page in the app (without any
+/// leading or trailing `/`s), or `None` if it points to another site.
+fn normalize_link(link: &str, path_prefix: &str) -> Option<String> {
+    // This covers external links, and things like `mailto:`
+    if link.contains(':') || link.starts_with("//") {
+        return None;
+    }
+    let link = link.split(&['?', '#'][..]).next().unwrap_or(link);
+    let link = match link.strip_prefix(path_prefix) {
+        Some(stripped) if !path_prefix.is_empty() => stripped,
+        _ => link,
+    };
+
+    Some(link.trim_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_links() {
+        let html = r#"<a href="/base/about?x=1">About</a><a href='post/first#top'>Post</a><a href="https://example.com">Out</a>"#;
+        let paths = get_links(html)
+            .into_iter()
+            .filter_map(|link| normalize_link(link, "/base"))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["about", "post/first"]);
+    }
+}
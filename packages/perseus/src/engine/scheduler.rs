@@ -0,0 +1,225 @@
+use super::EngineHookFn;
+use chrono::{DateTime, Duration as ChronoDuration, Timelike, Utc};
+use futures::future::{select, Either};
+use futures::{pin_mut, Future};
+use std::time::Duration;
+
+/// When a scheduled job should be run (see `PerseusApp::scheduled_job()`).
+/// All times are in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run the job repeatedly, with the given interval between the end of one
+    /// run and the start of the next. The first run will be one interval after
+    /// the server starts. Intervals shorter than a second will be treated as a
+    /// second.
+    Every(Duration),
+    /// Run the job every hour, at the given minute past the hour.
+    Hourly {
+        /// The minute past the hour (0-59).
+        minute: u32,
+    },
+    /// Run the job every day, at the given time.
+    Daily {
+        /// The hour of the day (0-23).
+        hour: u32,
+        /// The minute past the hour (0-59).
+        minute: u32,
+    },
+}
+impl Schedule {
+    /// Gets the next time the job should be run, after the given time.
+    pub(crate) fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        // This ignores any leftover seconds, so that jobs run on the minute
+        let this_minute = now
+            - ChronoDuration::seconds(now.second() as i64)
+            - ChronoDuration::nanoseconds(now.nanosecond() as i64);
+        match *self {
+            Self::Every(interval) => {
+                let interval = interval.max(Duration::from_secs(1));
+                now + ChronoDuration::from_std(interval)
+                    .unwrap_or_else(|_| ChronoDuration::weeks(52))
+            }
+            Self::Hourly { minute } => {
+                let minute = minute.min(59);
+                let next = this_minute
+                    + ChronoDuration::minutes(minute as i64 - this_minute.minute() as i64);
+                if next > now {
+                    next
+                } else {
+                    next + ChronoDuration::hours(1)
+                }
+            }
+            Self::Daily { hour, minute } => {
+                let (hour, minute) = (hour.min(23), minute.min(59));
+                let next = this_minute
+                    + ChronoDuration::hours(hour as i64 - this_minute.hour() as i64)
+                    + ChronoDuration::minutes(minute as i64 - this_minute.minute() as i64);
+                if next > now {
+                    next
+                } else {
+                    next + ChronoDuration::days(1)
+                }
+            }
+        }
+    }
+}
+
+/// A job that the server will run on a schedule.
+pub(crate) struct ScheduledJob {
+    /// The name of the job, which is used in error messages.
+    pub(crate) name: String,
+    pub(crate) schedule: Schedule,
+    pub(crate) job: EngineHookFn,
+}
+
+/// How long a job that's running when the server shuts down will be given to
+/// finish.
+pub(crate) const JOB_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs the given jobs on their schedules until the given future resolves. Jobs
+/// are run one at a time, so a job that takes a long time will delay any
+/// others that come due while it's running. If a job is running when the
+/// future resolves, it will be given up to `timeout` to finish before this
+/// returns (after which it will be dropped). Jobs that fail will be reported,
+/// and then run again at their next scheduled time.
+pub(crate) async fn run_jobs(
+    jobs: &[ScheduledJob],
+    stop: impl Future<Output = ()>,
+    timeout: Duration,
+) {
+    if jobs.is_empty() {
+        return;
+    }
+    let now = Utc::now();
+    let mut next_runs = jobs
+        .iter()
+        .map(|job| job.schedule.next_after(now))
+        .collect::<Vec<_>>();
+    pin_mut!(stop);
+    loop {
+        // There's at least one job, so this will always find one
+        let (idx, next_run) = next_runs
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, next_run)| **next_run)
+            .map(|(idx, next_run)| (idx, *next_run))
+            .unwrap();
+        let wait = (next_run - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+        let sleep = tokio::time::sleep(wait);
+        pin_mut!(sleep);
+        if let Either::Left(_) = select(stop.as_mut(), sleep).await {
+            return;
+        }
+
+        let job = &jobs[idx];
+        let run = (job.job)();
+        pin_mut!(run);
+        let (res, stopped) = match select(run, stop.as_mut()).await {
+            Either::Left((res, _)) => (res, false),
+            Either::Right((_, run)) => match tokio::time::timeout(timeout, run).await {
+                Ok(res) => (res, true),
+                Err(_) => {
+                    eprintln!(
+                        "[WARNING]: scheduled job '{}' was stopped before it finished, because the server shut down",
+                        job.name
+                    );
+                    return;
+                }
+            },
+        };
+        if let Err(err) = res {
+            eprintln!("[WARNING]: scheduled job '{}' failed: {}", job.name, err);
+        }
+        if stopped {
+            return;
+        }
+        next_runs[idx] = job.schedule.next_after(Utc::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Creates a job that runs every second, taking the given time, and that
+    /// records when it's finished.
+    fn get_slow_job(duration: Duration, finished: Rc<Cell<bool>>) -> ScheduledJob {
+        ScheduledJob {
+            name: "slow".to_string(),
+            schedule: Schedule::Every(Duration::from_secs(1)),
+            job: Box::new(move || {
+                let finished = finished.clone();
+                Box::pin(async move {
+                    tokio::time::sleep(duration).await;
+                    finished.set(true);
+                    Ok(())
+                })
+            }),
+        }
+    }
+
+    #[test]
+    fn lets_running_jobs_finish_when_stopped() {
+        let finished = Rc::new(Cell::new(false));
+        let jobs = vec![get_slow_job(Duration::from_millis(200), finished.clone())];
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(async {
+                // This stops the jobs while the first run is in progress
+                let stop = tokio::time::sleep(Duration::from_millis(1100));
+                run_jobs(&jobs, stop, Duration::from_secs(5)).await
+            });
+        assert!(finished.get());
+    }
+
+    #[test]
+    fn drops_running_jobs_after_timeout() {
+        let finished = Rc::new(Cell::new(false));
+        let jobs = vec![get_slow_job(Duration::from_secs(60), finished.clone())];
+        let started = std::time::Instant::now();
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let stop = tokio::time::sleep(Duration::from_millis(1100));
+                run_jobs(&jobs, stop, Duration::from_millis(100)).await
+            });
+        assert!(!finished.get());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn finds_next_run() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 16, 12, 30, 15).unwrap();
+        assert_eq!(
+            Schedule::Hourly { minute: 45 }.next_after(now),
+            Utc.with_ymd_and_hms(2022, 10, 16, 12, 45, 0).unwrap()
+        );
+        assert_eq!(
+            Schedule::Hourly { minute: 0 }.next_after(now),
+            Utc.with_ymd_and_hms(2022, 10, 16, 13, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Schedule::Daily { hour: 3, minute: 0 }.next_after(now),
+            Utc.with_ymd_and_hms(2022, 10, 17, 3, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Schedule::Daily {
+                hour: 18,
+                minute: 5
+            }
+            .next_after(now),
+            Utc.with_ymd_and_hms(2022, 10, 16, 18, 5, 0).unwrap()
+        );
+        assert_eq!(
+            Schedule::Every(Duration::ZERO).next_after(now),
+            now + ChronoDuration::seconds(1)
+        );
+    }
+}
@@ -1,10 +1,20 @@
+use crate::errors::EngineError;
 use crate::{i18n::TranslationsManager, stores::MutableStore};
-use crate::{plugins::PluginAction, PerseusAppBase, SsrNode};
+use crate::{plugins::PluginAction, utils::get_dist_dir, PerseusAppBase, SsrNode};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
 
-/// Runs tinker plugin actions.
+/// Runs tinker plugin actions, and then writes the structured tinker edits
+/// declared by plugins to `tinker_edits.json` in the output directory (see
+/// [`get_dist_dir`]), where the CLI will pick
+/// them up, validate them, and apply them.
 ///
 /// Note that this expects to be run in the root of the project.
-pub fn tinker(app: PerseusAppBase<SsrNode, impl MutableStore, impl TranslationsManager>) {
+pub fn tinker(
+    app: PerseusAppBase<SsrNode, impl MutableStore, impl TranslationsManager>,
+) -> Result<(), Rc<EngineError>> {
     let plugins = app.get_plugins();
     // Run all the tinker actions
     // Note: this is deliberately synchronous, tinker actions that need a
@@ -13,4 +23,25 @@ pub fn tinker(app: PerseusAppBase<SsrNode, impl MutableStore, impl TranslationsM
         .functional_actions
         .tinker
         .run((), plugins.get_plugin_data());
+
+    // Collect the structured edits, ordered by plugin name so the CLI applies them
+    // deterministically
+    let edits: BTreeMap<_, _> = plugins
+        .functional_actions
+        .tinker_edits
+        .run((), plugins.get_plugin_data())
+        .into_iter()
+        .collect();
+    // This can't fail, the edits are just strings
+    let edits = serde_json::to_string(&edits).unwrap();
+    let dist_dir = get_dist_dir();
+    let dest = Path::new(&dist_dir).join("tinker_edits.json");
+    if let Err(err) = fs::create_dir_all(&dist_dir).and_then(|_| fs::write(&dest, edits)) {
+        return Err(Rc::new(EngineError::WriteTinkerEditsError {
+            source: err,
+            dest: dest.to_string_lossy().to_string(),
+        }));
+    }
+
+    Ok(())
 }
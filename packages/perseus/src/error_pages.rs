@@ -1,4 +1,7 @@
+use crate::errors::AppError;
 use crate::translator::Translator;
+#[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+use crate::translator::{TranslationArg, TranslationArgs};
 use crate::Html;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::SsrNode;
@@ -20,6 +23,146 @@ use web_sys::Element;
 /// rely on symbols or the like in these cases.
 pub type ErrorPageTemplate<G> =
     Box<dyn Fn(Scope, String, u16, String, Option<Rc<Translator>>) -> View<G> + Send + Sync>;
+/// The callback to a template for errors of a particular [`ErrorKind`]. This is
+/// passed an [`ErrorContext`] with the details of the error, the error message,
+/// and a translator if one is available.
+pub type ErrorKindPageTemplate<G> =
+    Box<dyn Fn(Scope, ErrorContext, String, Option<Rc<Translator>>) -> View<G> + Send + Sync>;
+
+/// The broad kinds of errors that error pages might be rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// A request to the server couldn't be completed at all (e.g. because the
+    /// user is offline). Error pages for these are given the status code `503`.
+    Fetch,
+    /// A request to the server took too long to complete. Error pages for
+    /// these are given the status code `408`.
+    Timeout,
+    /// The app panicked in the browser. Error pages for these are given the
+    /// status code `500`.
+    Panic,
+    /// The server responded with a `5xx` status code.
+    Server,
+    /// The server responded with any other unsuccessful status code (e.g. `404`
+    /// if a page couldn't be found).
+    Client,
+}
+impl ErrorKind {
+    /// Gets the kind of error the server responding with the given status code
+    /// represents.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            500..=599 => Self::Server,
+            _ => Self::Client,
+        }
+    }
+    /// Gets the status code that error pages for this kind of error will be
+    /// rendered with if the server didn't give one.
+    fn default_status(&self) -> u16 {
+        match self {
+            Self::Fetch => 503,
+            Self::Timeout => 408,
+            Self::Panic | Self::Server => 500,
+            Self::Client => 400,
+        }
+    }
+    /// Whether or not errors of this kind are likely to go away by themselves
+    /// if whatever caused them is retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Fetch | Self::Timeout)
+    }
+}
+
+/// The details of an error that an error page is being rendered for.
+#[derive(Clone)]
+pub struct ErrorContext {
+    /// The kind of error this is.
+    pub kind: ErrorKind,
+    /// The URL the error originated from. This may be the URL of an asset the
+    /// app was fetching, rather than the URL of the page the user is on.
+    pub url: String,
+    /// The HTTP status code that corresponds with the error (see [`ErrorKind`]
+    /// for those given to errors that don't come from the server).
+    pub status: u16,
+    /// The app-defined error that caused this error, if it was caused by one.
+    pub app_error: Option<AppError>,
+    /// A callback that retries whatever caused the error, if that's possible.
+    retry: Option<Rc<dyn Fn()>>,
+}
+impl std::fmt::Debug for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorContext")
+            .field("kind", &self.kind)
+            .field("url", &self.url)
+            .field("status", &self.status)
+            .field("app_error", &self.app_error)
+            .field("can_retry", &self.can_retry())
+            .finish()
+    }
+}
+impl ErrorContext {
+    /// Creates the context for an error of the given kind, using the default
+    /// status code for that kind.
+    pub fn new(kind: ErrorKind, url: &str) -> Self {
+        Self {
+            kind,
+            url: url.to_string(),
+            status: kind.default_status(),
+            app_error: None,
+            retry: None,
+        }
+    }
+    /// Creates the context for an error where the server responded with the
+    /// given status code.
+    pub fn from_status(url: &str, status: u16) -> Self {
+        Self {
+            kind: ErrorKind::from_status(status),
+            url: url.to_string(),
+            status,
+            app_error: None,
+            retry: None,
+        }
+    }
+    /// Sets the app-defined error that caused this error.
+    pub fn with_app_error(mut self, app_error: Option<AppError>) -> Self {
+        self.app_error = app_error;
+        self
+    }
+    /// Whether or not this error might go away if whatever caused it were
+    /// retried.
+    pub fn is_retryable(&self) -> bool {
+        self.kind.is_transient()
+            || matches!(&self.app_error, Some(app_error) if app_error.is_retryable())
+    }
+    /// Gets the message that explains this error to the user, if the state
+    /// generation function that caused it provided the ID of one. If there's
+    /// no translator, or the translation fails, this will be the ID itself.
+    #[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+    pub fn user_message(&self, translator: Option<&Translator>) -> Option<String> {
+        let id = self.app_error.as_ref()?.get_message_id()?;
+        Some(match translator {
+            Some(translator) => translator
+                .translate_checked(id, None)
+                .unwrap_or_else(|_| id.to_string()),
+            None => id.to_string(),
+        })
+    }
+    /// Sets the callback that will retry whatever caused this error.
+    pub fn with_retry(mut self, retry: impl Fn() + 'static) -> Self {
+        self.retry = Some(Rc::new(retry));
+        self
+    }
+    /// Whether or not whatever caused this error can be retried.
+    pub fn can_retry(&self) -> bool {
+        self.retry.is_some()
+    }
+    /// Retries whatever caused this error, if that's possible.
+    pub fn retry(&self) {
+        if let Some(retry) = &self.retry {
+            retry();
+        }
+    }
+}
 
 /// A representation of the views configured in an app for responding to errors.
 ///
@@ -38,13 +181,39 @@ pub type ErrorPageTemplate<G> =
 /// occurred before translations data could be fetched and processed, in which
 /// case you should try to display language-agnostic information).
 ///
-/// In development, you can get away with not defining any error pages for your
-/// app, as Perseus has a simple inbuilt default, though, when you try to go to
-/// production (e.g. with `perseus deploy`), you'll receive an error message in
-/// building. In other words, you must define your own error pages for release
-/// mode.
+/// If you need to know more about an error (e.g. whether it happened because
+/// the user is offline), you can also add pages for particular
+/// [`ErrorKind`]s with `.add_kind_page()`, which will take precedence over
+/// pages for status codes, and which are given an [`ErrorContext`]. Otherwise,
+/// Perseus will add a "Retry" button to any error page rendered for an error
+/// that's likely to be transient (i.e. a network failure or a timeout).
+/// Errors your state generation functions return as an
+/// [`AppError`](crate::AppError) can have their own pages too, which can be
+/// added with `.add_app_page()`, and which take precedence over all the
+/// others.
+///
+/// If your app uses i18n, error pages can also be defined with the IDs of
+/// translations to display (see `.new_with_message()` and
+/// `.add_message_page()`), which will be given the variables `status`, `url`,
+/// and `err`. If you embed your default locale's translations in your app
+/// with `PerseusApp::embedded_translations()`, Perseus will always provide a
+/// translator to error pages, using those translations whenever the ones for
+/// the user's locale can't be fetched (e.g. if the error was caused by a
+/// network failure).
+///
+/// If you don't define any error pages, Perseus will use a default set (see
+/// `ErrorPages::default()`), which has pages for `404`s, `500`s, network
+/// failures, and everything else, each with a link back to your app's landing
+/// page. These will use translations with the IDs `perseus-error-not-found`,
+/// `perseus-error-server`, `perseus-error-offline`, `perseus-error-generic`,
+/// `perseus-error-go-home`, and `perseus-error-retry` if your app has them,
+/// and English otherwise. To customize some of them, you can start from the
+/// defaults and replace just the pages you want (e.g. with `.add_page(404,
+/// ..)`), rather than having to write them all yourself.
 pub struct ErrorPages<G: Html> {
     status_pages: HashMap<u16, ErrorPageTemplate<G>>,
+    kind_pages: HashMap<ErrorKind, ErrorKindPageTemplate<G>>,
+    app_pages: HashMap<String, ErrorKindPageTemplate<G>>,
     fallback: ErrorPageTemplate<G>,
 }
 impl<G: Html> std::fmt::Debug for ErrorPages<G> {
@@ -64,9 +233,31 @@ impl<G: Html> ErrorPages<G> {
     ) -> Self {
         Self {
             status_pages: HashMap::default(),
+            kind_pages: HashMap::default(),
+            app_pages: HashMap::default(),
             fallback: Box::new(fallback),
         }
     }
+    /// Creates a new definition of error pages with just a fallback page that
+    /// displays the translation with the given ID (see `.new()`).
+    #[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+    pub fn new_with_message(id: &str) -> Self {
+        let id = id.to_string();
+        Self::new(move |cx, url, status, err, translator| {
+            let message = translate_message(&id, &url, status, &err, translator.as_deref());
+            view! { cx, p { (message) } }
+        })
+    }
+    /// Adds a new page for the given status code that displays the translation
+    /// with the given ID (see `.add_page()`).
+    #[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+    pub fn add_message_page(&mut self, status: u16, id: &str) {
+        let id = id.to_string();
+        self.add_page(status, move |cx, url, status, err, translator| {
+            let message = translate_message(&id, &url, status, &err, translator.as_deref());
+            view! { cx, p { (message) } }
+        });
+    }
     /// Adds a new page for the given status code. If a page was already defined
     /// for the given code, it will be updated by replacement, through the
     /// mechanics of the internal `HashMap`. While there is no requirement
@@ -89,6 +280,34 @@ impl<G: Html> ErrorPages<G> {
     pub fn add_page_rc(&mut self, status: u16, page: ErrorPageTemplate<G>) {
         self.status_pages.insert(status, page);
     }
+    /// Adds a new page for the given kind of error, which will be used instead
+    /// of any page for the error's status code, and which will be given the
+    /// full [`ErrorContext`] of the error. If a page was already defined for
+    /// the given kind, it will be replaced.
+    pub fn add_kind_page(
+        &mut self,
+        kind: ErrorKind,
+        page: impl Fn(Scope, ErrorContext, String, Option<Rc<Translator>>) -> View<G>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.kind_pages.insert(kind, Box::new(page));
+    }
+    /// Adds a new page for errors of the given app-defined kind (see
+    /// [`AppError`](crate::AppError)), which will be used instead of any other
+    /// page, and which will be given the full [`ErrorContext`] of the error.
+    /// If a page was already defined for the given kind, it will be replaced.
+    pub fn add_app_page(
+        &mut self,
+        app_kind: &str,
+        page: impl Fn(Scope, ErrorContext, String, Option<Rc<Translator>>) -> View<G>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.app_pages.insert(app_kind.to_string(), Box::new(page));
+    }
     /// Gets the internal template function to render.
     fn get_template_fn(&self, status: u16) -> &ErrorPageTemplate<G> {
         // Check if we have an explicitly defined page for this status code
@@ -98,6 +317,39 @@ impl<G: Html> ErrorPages<G> {
             false => &self.fallback,
         }
     }
+    /// Gets the view of the appropriate error page for the given error.
+    pub fn get_view(
+        &self,
+        cx: Scope,
+        ctx: ErrorContext,
+        err: &str,
+        translator: Option<Rc<Translator>>,
+    ) -> View<G> {
+        let app_page = ctx
+            .app_error
+            .as_ref()
+            .and_then(|app_error| self.app_pages.get(app_error.kind()));
+        if let Some(page) = app_page {
+            return page(cx, ctx, err.to_string(), translator);
+        }
+        if let Some(page) = self.kind_pages.get(&ctx.kind) {
+            return page(cx, ctx, err.to_string(), translator);
+        }
+        let retry_text = default_text(translator.as_deref(), "perseus-error-retry", "Retry");
+        let template_fn = self.get_template_fn(ctx.status);
+        let view = template_fn(cx, ctx.url.clone(), ctx.status, err.to_string(), translator);
+        // Pages for status codes don't know about retrying, so we give the user a way
+        // to do that for errors that might go away
+        match ctx.retry {
+            Some(retry) if ctx.is_retryable() => View::new_fragment(vec![
+                view,
+                view! { cx,
+                    button(class = "__perseus_retry", on:click = move |_| retry()) { (retry_text) }
+                },
+            ]),
+            _ => view,
+        }
+    }
 }
 #[cfg(target_arch = "wasm32")]
 impl ErrorPages<DomNode> {
@@ -111,12 +363,21 @@ impl ErrorPages<DomNode> {
         translator: Option<Rc<Translator>>,
         container: &Element,
     ) {
-        let template_fn = self.get_template_fn(status);
+        let ctx = ErrorContext::from_status(url, status);
+        self.render_page_ctx(cx, ctx, err, translator, container);
+    }
+    /// Renders the appropriate error page for the error with the given context
+    /// to the given DOM container.
+    pub fn render_page_ctx(
+        &self,
+        cx: Scope,
+        ctx: ErrorContext,
+        err: &str,
+        translator: Option<Rc<Translator>>,
+        container: &Element,
+    ) {
         // Render that to the given container
-        sycamore::render_to(
-            |_| template_fn(cx, url.to_string(), status, err.to_string(), translator),
-            container,
-        );
+        sycamore::render_to(|_| self.get_view(cx, ctx, err, translator), container);
     }
 }
 #[cfg(target_arch = "wasm32")]
@@ -133,8 +394,8 @@ impl ErrorPages<HydrateNode> {
         translator: Option<Rc<Translator>>,
         container: &Element,
     ) {
-        let template_fn = self.get_template_fn(status);
-        let hydrate_view = template_fn(cx, url.to_string(), status, err.to_string(), translator);
+        let ctx = ErrorContext::from_status(url, status);
+        let hydrate_view = self.get_view(cx, ctx, err, translator);
         // TODO Now convert that `HydrateNode` to a `DomNode`
         let dom_view = hydrate_view;
         // Render that to the given container
@@ -153,12 +414,21 @@ impl ErrorPages<HydrateNode> {
         translator: Option<Rc<Translator>>,
         container: &Element,
     ) {
-        let template_fn = self.get_template_fn(status);
+        let ctx = ErrorContext::from_status(url, status);
+        self.render_page_ctx(cx, ctx, err, translator, container);
+    }
+    /// Renders the appropriate error page for the error with the given context
+    /// to the given DOM container.
+    pub fn render_page_ctx(
+        &self,
+        cx: Scope,
+        ctx: ErrorContext,
+        err: &str,
+        translator: Option<Rc<Translator>>,
+        container: &Element,
+    ) {
         // Render that to the given container
-        sycamore::hydrate_to(
-            |_| template_fn(cx, url.to_string(), status, err.to_string(), translator),
-            container,
-        );
+        sycamore::hydrate_to(|_| self.get_view(cx, ctx, err, translator), container);
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
@@ -173,11 +443,18 @@ impl ErrorPages<SsrNode> {
         err: &str,
         translator: Option<Rc<Translator>>,
     ) -> String {
-        let template_fn = self.get_template_fn(status);
-        // Render that to the given container
-        sycamore::render_to_string(|cx| {
-            template_fn(cx, url.to_string(), status, err.to_string(), translator)
-        })
+        let ctx = ErrorContext::from_status(url, status);
+        self.render_to_string_ctx(ctx, err, translator)
+    }
+    /// Renders the error page for the error with the given context to a
+    /// string.
+    pub fn render_to_string_ctx(
+        &self,
+        ctx: ErrorContext,
+        err: &str,
+        translator: Option<Rc<Translator>>,
+    ) -> String {
+        sycamore::render_to_string(|cx| self.get_view(cx, ctx, err, translator))
     }
     /// Renders the error page to a string, using the given reactive scope. Note
     /// that this function is not used internally, and `.render_to_string()`
@@ -190,37 +467,140 @@ impl ErrorPages<SsrNode> {
         err: &str,
         translator: Option<Rc<Translator>>,
     ) -> String {
-        let template_fn = self.get_template_fn(status);
+        let ctx = ErrorContext::from_status(url, status);
         // Render that to the given container
-        sycamore::render_to_string(|_| {
-            template_fn(cx, url.to_string(), status, err.to_string(), translator)
-        })
+        sycamore::render_to_string(|_| self.get_view(cx, ctx, err, translator))
     }
 }
-// We provide default error pages to speed up development, but they have to be
-// added before moving to production (or we'll `panic!`)
+/// Translates the message with the given ID for an error page, giving it the
+/// details of the error as arguments. If there's no translator, or if the
+/// translation fails, the ID itself will be displayed (which makes missing
+/// translations easy to spot).
+#[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+fn translate_message(
+    id: &str,
+    url: &str,
+    status: u16,
+    err: &str,
+    translator: Option<&Translator>,
+) -> String {
+    let translator = match translator {
+        Some(translator) => translator,
+        None => return id.to_string(),
+    };
+    let mut args = TranslationArgs::new();
+    args.set("status", TranslationArg::from(status));
+    args.set("url", TranslationArg::from(url));
+    args.set("err", TranslationArg::from(err));
+
+    translator
+        .translate_checked(id, Some(args))
+        .unwrap_or_else(|_| id.to_string())
+}
+
+/// The styles for the default error pages, which are kept small and scoped to
+/// them so they won't clash with the app's own styles.
+const DEFAULT_ERROR_PAGE_STYLES: &str = ".__perseus_error_page{font-family:system-ui,sans-serif;max-width:32rem;margin:4rem auto;padding:0 1rem;text-align:center;color:#333}.__perseus_error_page h1{font-size:3rem;margin:0}.__perseus_error_page pre{text-align:left;white-space:pre-wrap;font-size:.8rem;color:#666}.__perseus_error_page a,.__perseus_error_page button{display:inline-block;margin:1rem .25rem 0;padding:.5rem 1rem;border:1px solid #ccc;border-radius:.25rem;background:none;color:inherit;font:inherit;text-decoration:none;cursor:pointer}";
+
+// We provide a set of default error pages, so that apps only need to replace
+// the ones they want to customize (e.g. with `.add_page(404, ..)` on these)
 impl<G: Html> Default for ErrorPages<G> {
-    #[cfg(debug_assertions)]
     fn default() -> Self {
-        let mut error_pages = Self::new(|cx, url, status, err, _| {
-            view! { cx,
-                p { (format!("An error with HTTP code {} occurred at '{}': '{}'.", status, url, err)) }
-            }
+        let mut error_pages = Self::new(|cx, _, status, err, translator| {
+            let title = default_text(
+                translator.as_deref(),
+                "perseus-error-generic",
+                "Something went wrong.",
+            );
+            default_page(cx, status, title, err, translator.as_deref(), View::empty())
         });
-        // 404 is the most common by far, so we add a little page for that too
-        error_pages.add_page(404, |cx, _, _, _, _| {
-            view! { cx,
-                p { "Page not found." }
-            }
+        // 404 is the most common by far, so it gets a page of its own
+        error_pages.add_page(404, |cx, _, status, err, translator| {
+            let title = default_text(
+                translator.as_deref(),
+                "perseus-error-not-found",
+                "This page doesn't exist.",
+            );
+            default_page(cx, status, title, err, translator.as_deref(), View::empty())
+        });
+        error_pages.add_page(500, |cx, _, status, err, translator| {
+            let title = default_text(
+                translator.as_deref(),
+                "perseus-error-server",
+                "Something went wrong on our end. Please try again later.",
+            );
+            default_page(cx, status, title, err, translator.as_deref(), View::empty())
+        });
+        // Network failures get their own page, since the user can usually fix them
+        error_pages.add_kind_page(ErrorKind::Fetch, |cx, ctx, err, translator| {
+            let title = default_text(
+                translator.as_deref(),
+                "perseus-error-offline",
+                "You seem to be offline. Check your connection and try again.",
+            );
+            let retry = match ctx.can_retry() {
+                true => {
+                    let text = default_text(translator.as_deref(), "perseus-error-retry", "Retry");
+                    view! { cx,
+                        button(class = "__perseus_retry", on:click = move |_| ctx.retry()) { (text) }
+                    }
+                }
+                false => View::empty(),
+            };
+            default_page(cx, 503, title, err, translator.as_deref(), retry)
         });
 
         error_pages
     }
-    #[cfg(not(debug_assertions))]
-    fn default() -> Self {
-        panic!("you must provide your own error pages in production")
+}
+/// Renders one of the default error pages, with the given title, a link back
+/// to the app's landing page (in the user's locale), and any extra actions.
+/// The error message itself is only shown in development.
+#[allow(unused_variables)]
+fn default_page<G: Html>(
+    cx: Scope,
+    status: u16,
+    title: String,
+    err: String,
+    translator: Option<&Translator>,
+    actions: View<G>,
+) -> View<G> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let path_prefix = crate::utils::get_path_prefix_server();
+    #[cfg(target_arch = "wasm32")]
+    let path_prefix = crate::utils::get_path_prefix_client();
+    let home_url = match translator.map(|translator| translator.get_locale()) {
+        Some(locale) if locale != "xx-XX" => format!("{}/{}", path_prefix, locale),
+        _ => format!("{}/", path_prefix),
+    };
+    let home_text = default_text(translator, "perseus-error-go-home", "Go home");
+    let details = match cfg!(debug_assertions) {
+        true => view! { cx, pre { (err) } },
+        false => View::empty(),
+    };
+
+    view! { cx,
+        div(class = "__perseus_error_page") {
+            style { (DEFAULT_ERROR_PAGE_STYLES) }
+            h1 { (status.to_string()) }
+            p { (title) }
+            (details)
+            a(href = home_url) { (home_text) }
+            (actions)
+        }
     }
 }
+/// Gets the translation with the given ID for one of the default error pages,
+/// falling back to the given English text if there's no translator, or if the
+/// app doesn't have a translation for it.
+#[allow(unused_variables)]
+fn default_text(translator: Option<&Translator>, id: &str, fallback: &str) -> String {
+    #[cfg(any(feature = "translator-fluent", feature = "translator-lightweight"))]
+    if let Some(Ok(text)) = translator.map(|translator| translator.translate_checked(id, None)) {
+        return text;
+    }
+    fallback.to_string()
+}
 
 /// A representation of an error page, particularly for storage in transit so
 /// that server-side rendered error pages can be hydrated on the client-side.
@@ -232,4 +612,47 @@ pub struct ErrorPageData {
     pub status: u16,
     /// The actual error message as a string.
     pub err: String,
+    /// The app-defined error that caused the error, if any.
+    #[serde(default)]
+    pub app_error: Option<AppError>,
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_pages_can_be_replaced_individually() {
+        let mut error_pages = ErrorPages::<SsrNode>::default();
+        let html = error_pages.render_to_string("/foo", 404, "not found", None);
+        assert!(html.contains("This page doesn't exist."));
+        assert!(html.contains("href=\"/\""));
+
+        error_pages.add_page(404, |cx, _, _, _, _| view! { cx, p { "custom" } });
+        let html = error_pages.render_to_string("/foo", 404, "not found", None);
+        assert!(html.contains("custom"));
+        // The other defaults should still be there
+        let html = error_pages.render_to_string("/foo", 500, "oops", None);
+        assert!(html.contains("Something went wrong on our end."));
+    }
+    #[test]
+    #[cfg(feature = "translator-fluent")]
+    fn retry_buttons_are_translated() {
+        let error_pages = ErrorPages::<SsrNode>::new(|cx, _, _, _, _| view! { cx, p { "error" } });
+        let ctx = ErrorContext::new(ErrorKind::Timeout, "/foo").with_retry(|| {});
+        let html = error_pages.render_to_string_ctx(ctx.clone(), "timed out", None);
+        assert!(html.contains(">Retry</button>"));
+
+        let translator = Translator::new(
+            "fr-FR".to_string(),
+            "perseus-error-retry = Réessayer".to_string(),
+        )
+        .unwrap();
+        let html = error_pages.render_to_string_ctx(ctx, "timed out", Some(Rc::new(translator)));
+        assert!(html.contains(">Réessayer</button>"));
+        // Errors that won't go away by themselves don't get the button
+        let ctx = ErrorContext::new(ErrorKind::Client, "/foo").with_retry(|| {});
+        let html = error_pages.render_to_string_ctx(ctx, "not found", None);
+        assert!(!html.contains("button"));
+    }
 }
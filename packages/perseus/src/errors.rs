@@ -2,10 +2,20 @@
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::i18n::TranslationsManagerError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::InvalidDuration;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// All errors that can be returned from this crate.
+///
+/// All of Perseus' error enums are `#[non_exhaustive]`, so that new variants
+/// can be added without breaking code that matches on them. Errors that come
+/// from somewhere else (e.g. your state generation functions) are always kept
+/// as sources, rather than being converted to strings, so the full chain can be
+/// logged (e.g. with `fmterr::fmt_err()`).
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error(transparent)]
     ClientError(#[from] ClientError),
@@ -21,6 +31,7 @@ pub enum Error {
 /// building the app).
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum EngineError {
     // Many of the build/export processes return these more generic errors
     #[error(transparent)]
@@ -46,16 +57,43 @@ pub enum EngineError {
         from: String,
         to: String,
     },
+    #[error("couldn't list the exported files in '{dest}'")]
+    ListExportedFilesError {
+        #[source]
+        source: fs_extra::error::Error,
+        dest: String,
+    },
+    #[error("engine hook for operation '{op}' failed")]
+    EngineHookFailed {
+        op: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("couldn't write tinker edits to '{dest}'")]
+    WriteTinkerEditsError {
+        #[source]
+        source: std::io::Error,
+        dest: String,
+    },
     #[error("couldn't write the generated error page to '{dest}'")]
     WriteErrorPageError {
         #[source]
         source: std::io::Error,
         dest: String,
     },
+    #[error("the app isn't ready to be served:\n{problems}")]
+    CheckFailed { problems: String },
+    #[error("the app's configuration is invalid:\n{problems}")]
+    InvalidConfig { problems: String },
+    #[error("the server couldn't be set up (try rebuilding your app):\n{problems}")]
+    InvalidServerOptions { problems: String },
+    #[error("the app's plugins were still in use after it was consumed, so the server couldn't take ownership of them (are you holding onto the result of `.get_plugins()`?)")]
+    PluginsStillReferenced,
 }
 
 /// Errors that can occur in the browser.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ClientError {
     #[error("locale '{locale}' is not supported")]
     LocaleNotSupported { locale: String },
@@ -69,6 +107,8 @@ pub enum ClientError {
         #[source]
         source: serde_json::Error,
     },
+    #[error("frozen state is in format v{found}, but this version of Perseus expects format v{expected} (it was probably frozen by an older version of your app)")]
+    FrozenStateVersionMismatch { found: u32, expected: u32 },
     // If the user is using the template macros, this should never be emitted because we can
     // ensure that the generated state is valid
     #[error("tried to deserialize invalid state")]
@@ -76,11 +116,151 @@ pub enum ClientError {
         #[source]
         source: serde_json::Error,
     },
+    /// This is never returned, it's only used to pass panics (in the browser,
+    /// or while rendering a page on the server) through to an
+    /// [`ErrorReporter`].
+    #[error("a panic occurred: {0}")]
+    Panic(String),
+    /// This is never returned, it's only used to pass hydration mismatches
+    /// (where the HTML the server prerendered for a page differs from the
+    /// browser's first render of it) through to an [`ErrorReporter`]. These
+    /// are only checked for in development.
+    #[error("hydration mismatch in template '{template_name}' (at page '{path}'): {}", .mismatches.join("; "))]
+    HydrationMismatch {
+        template_name: String,
+        path: String,
+        /// Descriptions of where the server and the browser disagree (only
+        /// the first few are included).
+        mismatches: Vec<String>,
+    },
+    #[error("render configuration wasn't injected by the server")]
+    RenderCfgNotInjected,
+    #[error(transparent)]
+    RenderCfgError(#[from] RenderCfgError),
+}
+
+/// Errors that can occur while loading the render configuration, in either the
+/// engine or the browser.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RenderCfgError {
+    #[error("render configuration invalid or corrupted (try cleaning all assets)")]
+    Invalid {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("render configuration is in format v{found}, but this version of Perseus expects format v{expected} (the app's server and Wasm bundle were probably built by different versions of Perseus, try running `perseus clean --dist` and rebuilding)")]
+    VersionMismatch { found: u64, expected: u32 },
+    #[error("render configuration failed its integrity check (checksum was '{found}', but its contents hash to '{expected}'), it may have been corrupted (try rebuilding the app)")]
+    ChecksumMismatch { found: String, expected: String },
+}
+
+/// A function that will be called with every client-side error that Perseus
+/// handles internally (e.g. failed fetches of page data or translations), as
+/// well as with any panics that occur in the browser (and, in development, any
+/// hydration mismatches). This is designed for
+/// reporting errors to an external service, and it can't change how Perseus
+/// handles the error itself.
+///
+/// This can be cheaply cloned, and it can be set with
+/// `PerseusApp::error_reporter()`.
+#[derive(Clone)]
+pub struct ErrorReporter(Arc<dyn Fn(&ClientError) + Send + Sync>);
+impl ErrorReporter {
+    /// Creates a new error reporter from the given function.
+    pub fn new(f: impl Fn(&ClientError) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+    /// Reports the given error.
+    pub fn report(&self, err: &ClientError) {
+        (self.0)(err)
+    }
+}
+impl std::fmt::Debug for ErrorReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorReporter").finish()
+    }
+}
+
+/// Errors that can occur while using the SSR-safe browser APIs in
+/// [`crate::web`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum WebError {
+    #[error("browser apis can't be used on the engine-side (try calling this in an effect or event handler)")]
+    NotInBrowser,
+    #[error("the browser api '{api}' isn't available (it may be unsupported, disabled, or only allowed in secure contexts)")]
+    Unavailable { api: String },
+    /// This converts from a `JsValue` or the like.
+    #[error("the following error occurred while interfacing with JavaScript: {0}")]
+    Js(String),
+    #[error("couldn't get the current position (error code {code}): {message}")]
+    GeolocationFailed { code: u16, message: String },
+}
+
+/// Errors that can occur while validating a request's CSRF token (see
+/// [`crate::csrf`]).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CsrfError {
+    #[error("request had no csrf cookie (the page it came from should have set one)")]
+    MissingCookie,
+    #[error("request had no csrf token (it should be sent in the 'x-perseus-csrf-token' header)")]
+    MissingToken,
+    #[error("request's csrf token didn't match its csrf cookie")]
+    Mismatch,
+}
+
+/// Errors that can occur while fetching data with the adapters in
+/// [`crate::data`].
+#[cfg(all(feature = "data-sources", not(target_arch = "wasm32")))]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DataError {
+    #[error("request to '{url}' failed")]
+    RequestFailed {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("graphql query to '{url}' returned errors: {}", .errors.join("; "))]
+    GraphqlErrors { url: String, errors: Vec<String> },
+    #[error("graphql query to '{url}' returned no data")]
+    GraphqlNoData { url: String },
+}
+
+/// Errors that can occur while parsing the front matter of some content with
+/// [`FrontMatter`](crate::utils::FrontMatter).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum FrontMatterError {
+    #[error("front matter field '{field}' has invalid value '{value}'")]
+    InvalidField { field: String, value: String },
+    #[error("front matter was opened with `---`, but never closed")]
+    Unclosed,
+}
+
+/// Errors that can occur while highlighting code with
+/// [`crate::highlight`].
+#[cfg(all(feature = "syntax-highlighting", not(target_arch = "wasm32")))]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum HighlightError {
+    #[error("there's no built-in highlighting theme called '{name}'")]
+    UnknownTheme { name: String },
+    #[error("couldn't highlight code")]
+    HighlightFailed {
+        #[source]
+        source: syntect::Error,
+    },
 }
 
 /// Errors that can occur in the build process or while the server is running.
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ServerError {
     #[error("render function '{fn_name}' in template '{template_name}' failed (cause: {cause:?})")]
     RenderFnFailed {
@@ -105,24 +285,79 @@ pub enum ServerError {
     ExportError(#[from] ExportError),
     #[error(transparent)]
     ServeError(#[from] ServeError),
+    #[error("state function '{fn_name}' in template '{template_name}' didn't finish within {timeout:?}, so it was cancelled")]
+    StateFnTimedOut {
+        fn_name: String,
+        template_name: String,
+        timeout: std::time::Duration,
+    },
+    #[error("the circuit breaker for the request state function of template '{template_name}' is open after too many failures, and there's no fallback state")]
+    CircuitOpen { template_name: String },
+    #[error("gave up waiting for another server instance to finish generating page '{path}' after {timeout:?}")]
+    RegenerationLockTimedOut {
+        path: String,
+        timeout: std::time::Duration,
+    },
+    #[error("rendering page '{path}' with template '{template_name}' panicked: {message}")]
+    RenderPanicked {
+        path: String,
+        template_name: String,
+        message: String,
+    },
 }
 /// Converts a server error into an HTTP status code.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn err_to_status_code(err: &ServerError) -> u16 {
+    // App-defined errors know their own status codes
+    if let Some(app_err) = err_to_app_error(err) {
+        return app_err.status();
+    }
     match err {
         ServerError::ServeError(ServeError::PageNotFound { .. }) => 404,
+        ServerError::StateFnTimedOut { .. } => 504,
+        ServerError::CircuitOpen { .. } => 503,
+        ServerError::RegenerationLockTimedOut { .. } => 503,
         // Ambiguous (user-generated error), we'll rely on the given cause
-        ServerError::RenderFnFailed { cause, .. } => match cause {
-            ErrorCause::Client(code) => code.unwrap_or(400),
-            ErrorCause::Server(code) => code.unwrap_or(500),
-        },
+        ServerError::RenderFnFailed { cause, .. } => cause.status(),
         // Any other errors go to a 500, they'll be misconfigurations or internal server errors
         _ => 500,
     }
 }
 
+/// Problems with how an app has been configured through `PerseusApp`, which
+/// are found before the engine does anything else. Each of these explains how
+/// to fix it.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AppConfigError {
+    #[error("the template path '{path}' is used by more than one template (only one of them will ever be used, so give each template a unique path)")]
+    DuplicateTemplatePath { path: String },
+    #[error("the locale '{locale}' is listed more than once (make sure it's only given once, either as the default locale or as one of the others)")]
+    DuplicateLocale { locale: String },
+    #[error("the build profile '{profile}' doesn't exist (set `--profile` or the `PERSEUS_PROFILE` environment variable to either `preview` or `production`)")]
+    UnknownProfile { profile: String },
+    #[error("the locale '{locale}' was selected to be built, but the app doesn't support it (check `--locales`, or the `PERSEUS_LOCALES` environment variable)")]
+    UnknownFilteredLocale { locale: String },
+    #[error("i18n is enabled, but there's no translations manager to provide translations (use `.locales_and_translations_manager()` instead of `.locales()`, or set one with `.translations_manager()`)")]
+    NoTranslationsManager,
+    #[error("i18n is enabled, but the translations directory '{dir}' doesn't exist (create it with a translations file for each locale, or use `.disable_i18n()` if you don't need i18n)")]
+    NoTranslationsDir { dir: String },
+    #[error("this is a release build, but the app is using the default error pages, which are only meant for development (set your own with `.error_pages()`)")]
+    NoErrorPages,
+    #[error("there's no `index` template, so the root of your app (`/`) would be a 404 (add a template called `index`, or use `.index_redirect()` to send visitors somewhere else)")]
+    NoIndexTemplate,
+    #[error("the index view doesn't contain `<div id=\"{root}\">`, which is where your app will be rendered (use `PerseusRoot` in your index view, or change the id with `.root()`)")]
+    NoRootElement { root: String },
+    #[error("the static alias '{url}' points to '{path}', which is outside the project directory (this is a security risk, so make the path relative to the project directory, without any `../`)")]
+    UnsafeStaticAlias { url: String, path: String },
+    #[error("the template '{template}' is in the template group '{group}', which hasn't been declared (declare it with `.template_group()`)")]
+    UnknownTemplateGroup { template: String, group: String },
+}
+
 /// Errors that can occur with regards to global state.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum GlobalStateError {
     #[error("couldn't generate global state at build time")]
     BuildGenerationFailed {
@@ -135,6 +370,7 @@ pub enum GlobalStateError {
 /// immutable store.
 // We do need this on the client to complete some things
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum StoreError {
     #[error("asset '{name}' not found in store")]
     NotFound { name: String },
@@ -150,10 +386,19 @@ pub enum StoreError {
         #[source]
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+    #[error("asset name '{name}' isn't a valid path in the store")]
+    InvalidName { name: String },
+    #[error("lock '{name}' couldn't be taken or released in store")]
+    LockFailed {
+        name: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
 
 /// Errors that can occur while fetching a resource from the server.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum FetchError {
     #[error("asset fetched from '{url}' wasn't a string")]
     NotString { url: String },
@@ -164,17 +409,31 @@ pub enum FetchError {
         // The underlying body of the HTTP error response
         err: String,
     },
+    #[error("asset fetched from '{url}' returned status code '{status}' because of an app-defined error of kind '{}'", .app_error.kind())]
+    AppError {
+        url: String,
+        status: u16,
+        // The error message the server sent
+        err: String,
+        /// The error the server said caused this.
+        app_error: AppError,
+    },
     #[error("asset fetched from '{url}' couldn't be serialized")]
     SerFailed {
         url: String,
         #[source]
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+    #[error("asset couldn't be fetched from '{url}' (the network request failed): {err}")]
+    Network { url: String, err: String },
+    #[error("asset fetch from '{url}' timed out")]
+    Timeout { url: String },
 }
 
 /// Errors that can occur while building an app.
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum BuildError {
     #[error("template '{template_name}' is missing feature '{feature_name}' (required due to its properties)")]
     TemplateFeatureNotEnabled {
@@ -187,20 +446,72 @@ pub enum BuildError {
         #[source]
         source: std::io::Error,
     },
-    #[error(
-        "invalid indicator '{indicator}' in time string (must be one of: s, m, h, d, w, M, y)"
-    )]
-    InvalidDatetimeIntervalIndicator { indicator: String },
+    #[error("template '{template_name}' has an invalid revalidation interval")]
+    InvalidRevalidationInterval {
+        template_name: String,
+        #[source]
+        source: InvalidDuration,
+    },
     #[error("asset 'render_cfg.json' invalid or corrupted (try cleaning all assets)")]
     RenderCfgInvalid {
         #[from]
         source: serde_json::Error,
     },
+    #[error(transparent)]
+    RenderCfgError(#[from] RenderCfgError),
+    #[error("build artifacts are in format {found}, but this version of Perseus expects format v{expected} (this usually happens after upgrading Perseus, try running `perseus clean --dist` and rebuilding)")]
+    ArtifactsVersionMismatch { found: String, expected: u32 },
+    #[error("{} page(s) failed to build:\n{}", .failures.len(), fmt_page_failures(.failures))]
+    PagesFailed { failures: Vec<PageBuildFailure> },
+    #[error("couldn't fingerprint the static alias '{url}', because '{path}' couldn't be read")]
+    FingerprintStaticAliasFailed {
+        url: String,
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// A page that couldn't be built, which will be collected into a report if the
+/// app's [`BuildFailurePolicy`](crate::BuildFailurePolicy) isn't `FailFast`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct PageBuildFailure {
+    /// The path of the page, without its locale. If a template's build paths
+    /// couldn't be generated, this will be the template's path.
+    pub path: String,
+    /// The locale the page was being built for.
+    pub locale: String,
+    /// The error that occurred.
+    pub error: ServerError,
+}
+
+/// Formats a list of pages that couldn't be built, one per line, with the full
+/// chain of each error.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn fmt_page_failures(failures: &[PageBuildFailure]) -> String {
+    failures
+        .iter()
+        .map(|failure| {
+            let mut line = format!(
+                "  - '{}' ({}): {}",
+                failure.path, failure.locale, failure.error
+            );
+            let mut source = std::error::Error::source(&failure.error);
+            while let Some(err) = source {
+                line.push_str(&format!(": {}", err));
+                source = err.source();
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Errors that can occur while exporting an app to static files.
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ExportError {
     #[error("template '{template_name}' can't be exported because it depends on strategies that can't be run at build-time (only build state and build paths can be use din exportable templates)")]
     TemplateNotExportable { template_name: String },
@@ -210,6 +521,7 @@ pub enum ExportError {
 
 /// Errors that can occur while serving an app. These are integration-agnostic.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ServeError {
     #[error("page at '{path}' not found")]
     PageNotFound { path: String },
@@ -225,12 +537,24 @@ pub enum ServeError {
 
 /// Defines who caused an ambiguous error message so we can reliably create an
 /// HTTP status code. Specific status codes may be provided in either case, or
-/// the defaults (400 for client, 500 for server) will be used.
+/// the defaults (400 for client, 500 for server) will be used. If the app
+/// needs to know more about the error than who caused it, you should return
+/// an [`AppError`] instead.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ErrorCause {
     Client(Option<u16>),
     Server(Option<u16>),
 }
+impl ErrorCause {
+    /// Gets the HTTP status code the error should be sent with.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Client(code) => code.unwrap_or(400),
+            Self::Server(code) => code.unwrap_or(500),
+        }
+    }
+}
 
 /// An error that has an attached cause that blames either the client or the
 /// server for its occurrence. You can convert any error into this with
@@ -255,6 +579,218 @@ impl<E: std::error::Error + Send + Sync + 'static> From<E> for GenericErrorWithC
     }
 }
 
+/// A redirect away from a page, which can be returned from its state
+/// generation functions with [`Redirect::to`] to send the user somewhere else
+/// (e.g. to a login page, or to the new location of some moved content). On
+/// initial loads, the server will respond with a real HTTP redirect, and, on
+/// subsequent loads, the app will navigate to the new location (replacing the
+/// page in the user's history).
+///
+/// If a build state function returns a redirect at build-time, the redirect
+/// will be recorded in the render configuration and served for that page until
+/// the next build (this doesn't work when exporting, since there's no server to
+/// serve it, so it will fail the page's build).
+#[derive(Error, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[error("page redirects to '{location}' (status {status})")]
+pub struct Redirect {
+    location: String,
+    status: u16,
+}
+impl Redirect {
+    /// Creates an error that will redirect the user to the given location with
+    /// the given HTTP status code, which must be in the 3xx range (e.g. 302
+    /// for a temporary redirect, or 301 for a permanent one). The location can
+    /// be a path within the app (which should include any path prefix, and a
+    /// locale if it's needed), or a full URL, and it must be a valid HTTP
+    /// header value (so any non-ASCII characters should be percent-encoded).
+    /// This can be returned directly from any state generation function with
+    /// `Err(Redirect::to("/login", 302))`.
+    ///
+    /// If the status or the location is invalid, this will instead create an
+    /// error that leads to a 500 error page, since it's a bug in the app.
+    pub fn to(location: impl Into<String>, status: u16) -> GenericErrorWithCause {
+        let location = location.into();
+        let valid_location = !location.is_empty()
+            && location
+                .bytes()
+                .all(|byte| (byte >= 0x20 && byte != 0x7f) || byte == b'\t');
+        if !(300..400).contains(&status) || !valid_location {
+            return GenericErrorWithCause {
+                error: Box::new(InvalidRedirect { location, status }),
+                cause: ErrorCause::Server(None),
+            };
+        }
+
+        GenericErrorWithCause {
+            error: Box::new(Self { location, status }),
+            cause: ErrorCause::Client(Some(status)),
+        }
+    }
+    /// Gets the location to redirect to.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+    /// Gets the HTTP status code to redirect with (this will always be in the
+    /// 3xx range).
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+/// The error a state generation function returns if it calls [`Redirect::to`]
+/// with an invalid status or location.
+#[derive(Error, Debug)]
+#[error("invalid redirect to '{location}' with status {status} (redirects need a 3xx status and a location that's a valid header value)")]
+struct InvalidRedirect {
+    location: String,
+    status: u16,
+}
+/// Gets the redirect the given server error represents, if it came from a
+/// state generation function returning [`Redirect::to`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn err_to_redirect(err: &ServerError) -> Option<&Redirect> {
+    match err {
+        ServerError::RenderFnFailed { source, .. } => source.downcast_ref::<Redirect>(),
+        _ => None,
+    }
+}
+
+/// An error of a kind defined by your app, which can be returned from any
+/// state generation function (with `?`, or `Err(AppError::new(..).into())`)
+/// to show the user an error page made specifically for it, or to tell
+/// anything else reading your app's page data what went wrong without it
+/// having to parse the error message. The kind is an arbitrary string (e.g.
+/// `quota-exceeded`), and error pages for it can be added with
+/// `ErrorPages::add_app_page()`, which will be used instead of the page for
+/// the error's status code.
+///
+/// Errors can also have the ID of a translation that explains them to the
+/// user (set with `.message_id()`), and can be marked as retryable (with
+/// `.retryable(true)`) if the same request might succeed if it were made
+/// again (e.g. if a database was briefly unavailable), which will give their
+/// error pages a way to retry them.
+///
+/// All this is sent to the browser along with the error (in the error page
+/// data on initial loads, and as the JSON body of the response on subsequent
+/// loads), so it should never contain anything secret. Any underlying error
+/// can be attached with `.with_source()`, and it will be kept for logging on
+/// the server (it's never sent to the browser).
+#[derive(Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[error("{message}")]
+pub struct AppError {
+    kind: String,
+    status: u16,
+    message: String,
+    #[serde(default)]
+    message_id: Option<String>,
+    #[serde(default)]
+    retryable: bool,
+    #[source]
+    #[serde(skip)]
+    source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+}
+impl AppError {
+    /// Creates a new error of the given kind, which will be sent to the user
+    /// with the given HTTP status code and message. If the status code isn't
+    /// one for an error (i.e. it isn't from 400 to 599), 500 will be used
+    /// instead.
+    pub fn new(kind: impl Into<String>, status: u16, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            status: match status {
+                400..=599 => status,
+                _ => 500,
+            },
+            message: message.into(),
+            message_id: None,
+            retryable: false,
+            source: None,
+        }
+    }
+    /// Attaches the error that caused this one.
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        self.source = Some(Arc::from(source.into()));
+        self
+    }
+    /// Sets the ID of the translation that explains this error to the user.
+    pub fn message_id(mut self, id: impl Into<String>) -> Self {
+        self.message_id = Some(id.into());
+        self
+    }
+    /// Sets whether or not the request that caused this error might succeed
+    /// if it were retried.
+    pub fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+    /// Gets the kind of this error.
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+    /// Gets the HTTP status code this error will be sent with.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+    /// Gets the ID of the translation that explains this error to the user, if
+    /// there is one.
+    pub fn get_message_id(&self) -> Option<&str> {
+        self.message_id.as_deref()
+    }
+    /// Checks if the request that caused this error might succeed if it were
+    /// retried.
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+}
+/// Gets the app-defined error the given server error represents, if it came
+/// from a state generation function returning an [`AppError`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn err_to_app_error(err: &ServerError) -> Option<&AppError> {
+    match err {
+        ServerError::RenderFnFailed { source, .. } => source.downcast_ref::<AppError>(),
+        _ => None,
+    }
+}
+/// Gets the body of the response for the given server error on a subsequent
+/// load of the page at the given URL, along with its content type. If the
+/// error came from an [`AppError`], this will be the JSON error page data
+/// (the same as what's sent with the error page on an initial load), so that
+/// the app can tell what kind of error it is. Otherwise, it'll just be the
+/// error message.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn err_to_body(url: &str, err: &ServerError) -> (&'static str, String) {
+    let err_str = err_to_message(err);
+    match err_to_app_error(err) {
+        Some(app_err) => {
+            let data = crate::error_pages::ErrorPageData {
+                url: url.to_string(),
+                status: app_err.status(),
+                err: err_str,
+                app_error: Some(app_err.clone()),
+            };
+            // This can't fail, since the error page data is plain data
+            ("application/json", serde_json::to_string(&data).unwrap())
+        }
+        None => ("text/plain; charset=utf-8", err_str),
+    }
+}
+/// Formats the given server error to be sent to the browser. This is the
+/// error with its full chain of sources, unless it came from an [`AppError`],
+/// in which case it's just the app's message (since any source of that is only
+/// for the server's logs).
+#[cfg(not(target_arch = "wasm32"))]
+pub fn err_to_message(err: &ServerError) -> String {
+    match err_to_app_error(err) {
+        Some(app_err) => fmterr::fmt_err(&AppError {
+            source: None,
+            ..app_err.clone()
+        }),
+        None => fmterr::fmt_err(err),
+    }
+}
+
 /// Creates a new [`GenericErrorWithCause` (the error type behind
 /// [`RenderFnResultWithCause`](crate::RenderFnResultWithCause)) efficiently.
 /// This allows you to explicitly return errors from any state-generation
@@ -300,3 +836,81 @@ macro_rules! blame_err {
         })
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirects_must_be_valid() {
+        let redirect = Redirect::to("/login?next=%2Faccount", 303);
+        assert_eq!(redirect.cause.status(), 303);
+        assert_eq!(
+            redirect
+                .error
+                .downcast_ref::<Redirect>()
+                .unwrap()
+                .location(),
+            "/login?next=%2Faccount"
+        );
+
+        for (location, status) in [
+            ("/login", 200),
+            ("/login", 400),
+            ("/login\r\nX-Evil: 1", 302),
+            ("", 302),
+        ] {
+            let redirect = Redirect::to(location, status);
+            assert!(!redirect.error.is::<Redirect>());
+            assert_eq!(redirect.cause.status(), 500);
+        }
+    }
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn app_errors_have_error_statuses() {
+        let to_server_err = |app_err: AppError| ServerError::RenderFnFailed {
+            fn_name: "build_state".to_string(),
+            template_name: "index".to_string(),
+            cause: ErrorCause::Server(None),
+            source: app_err.into(),
+        };
+        let err = to_server_err(AppError::new("quota-exceeded", 429, "Too many requests"));
+        assert_eq!(err_to_status_code(&err), 429);
+        assert_eq!(err_to_app_error(&err).unwrap().kind(), "quota-exceeded");
+        for status in [0, 200, 302, 600, 1000] {
+            let err = to_server_err(AppError::new("odd", status, "Odd status"));
+            assert_eq!(err_to_status_code(&err), 500);
+        }
+    }
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn app_errors_are_sent_as_json() {
+        let err = ServerError::RenderFnFailed {
+            fn_name: "request_state".to_string(),
+            template_name: "index".to_string(),
+            cause: ErrorCause::Server(None),
+            source: AppError::new("db-unavailable", 503, "Database unavailable")
+                .message_id("error-db-unavailable")
+                .retryable(true)
+                .with_source("connection refused")
+                .into(),
+        };
+        let (content_type, body) = err_to_body("index", &err);
+        assert_eq!(content_type, "application/json");
+        let data: crate::error_pages::ErrorPageData = serde_json::from_str(&body).unwrap();
+        let app_err = data.app_error.unwrap();
+        assert_eq!(data.status, 503);
+        assert_eq!(app_err.kind(), "db-unavailable");
+        assert_eq!(app_err.get_message_id(), Some("error-db-unavailable"));
+        assert!(app_err.is_retryable());
+        // The source should never leave the server
+        assert!(!body.contains("connection refused"));
+
+        let err = ServerError::ServeError(ServeError::PageNotFound {
+            path: "index".to_string(),
+        });
+        let (content_type, body) = err_to_body("index", &err);
+        assert!(content_type.starts_with("text/plain"));
+        assert!(body.contains("not found"));
+    }
+}
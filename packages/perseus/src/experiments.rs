@@ -0,0 +1,282 @@
+//! Perseus supports A/B testing natively, without the need for an external tag
+//! manager. Experiments are defined on your `PerseusApp`, each with a set of
+//! weighted variants, and the server will assign every user a variant of each
+//! experiment on their first request, which it will remember with a cookie.
+//!
+//! The variants a user has been assigned are available to request-state
+//! functions (through [`get_variants`]), and to templates through
+//! [`RenderCtx::variant`](crate::template::RenderCtx::variant). Note that
+//! prerendered pages are shared between all users, so they're always rendered
+//! without any variants, and the variants will take effect reactively once the
+//! page has been hydrated. Pages rendered for a request do depend on the
+//! user's variants though (they're embedded in initial loads, and they're
+//! given to request-state functions), so both initial loads and subsequent
+//! loads will be sent with `Cache-Control: private, no-store` and `Vary:
+//! Cookie` whenever the user has been assigned any variants.
+//!
+//! When an app is exported, there's no server to assign variants, so, in
+//! addition to the usual pages, the exporter will emit a copy of every page for
+//! each variant of each experiment under
+//! `.perseus/experiments/<experiment>/<variant>/`, which your hosting provider
+//! can serve based on the `__perseus_variants` cookie (or however else you'd
+//! like to assign them).
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::{get_cookie, random_u64};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The name of the cookie the server uses to remember which variants a user has
+/// been assigned.
+pub const VARIANTS_COOKIE: &str = "__perseus_variants";
+/// How long the variants cookie should last for (a year, in seconds).
+#[cfg(not(target_arch = "wasm32"))]
+const VARIANTS_COOKIE_MAX_AGE: u64 = 60 * 60 * 24 * 365;
+
+/// An A/B experiment (or feature flag), which users will be randomly assigned a
+/// variant of.
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    /// The name of the experiment.
+    name: String,
+    /// The variants of this experiment, with their weights.
+    variants: Vec<(String, u32)>,
+}
+impl Experiment {
+    /// Creates a new experiment with the given name, which should be unique
+    /// within your app. You'll need to add at least one variant with
+    /// `.variant()`, or the experiment will be ignored.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            variants: Vec::new(),
+        }
+    }
+    /// Adds a variant to this experiment with the given weight. Users will be
+    /// assigned each variant with a probability of its weight divided by the
+    /// sum of the weights of all the variants (so `.variant("a",
+    /// 1).variant("b", 1)` would be an even split).
+    pub fn variant(mut self, name: &str, weight: u32) -> Self {
+        self.variants.push((name.to_string(), weight));
+        self
+    }
+    /// Gets the name of this experiment.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// Gets the names of the variants of this experiment.
+    pub fn get_variants(&self) -> impl Iterator<Item = &str> {
+        self.variants.iter().map(|(name, _)| name.as_str())
+    }
+    /// Checks if this experiment has a variant with the given name.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn has_variant(&self, name: &str) -> bool {
+        self.variants.iter().any(|(variant, _)| variant == name)
+    }
+    /// Picks a variant of this experiment using the given random number.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_variant(&self, random: u64) -> Option<&str> {
+        let total: u64 = self.variants.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            // We'll still assign a variant, even if the user has set all the weights to
+            // zero
+            return self.variants.first().map(|(name, _)| name.as_str());
+        }
+        let mut target = random % total;
+        for (name, weight) in self.variants.iter() {
+            let weight = *weight as u64;
+            if target < weight {
+                return Some(name);
+            }
+            target -= weight;
+        }
+        unreachable!()
+    }
+}
+
+/// All the experiments defined for an app.
+#[derive(Debug, Clone, Default)]
+pub struct Experiments(Vec<Experiment>);
+impl Experiments {
+    /// Adds the given experiment, replacing any existing one with the same
+    /// name.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn add(&mut self, experiment: Experiment) {
+        self.0
+            .retain(|existing| existing.get_name() != experiment.get_name());
+        self.0.push(experiment);
+    }
+    /// Checks if there are no experiments.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Gets an iterator over all the experiments.
+    pub fn iter(&self) -> impl Iterator<Item = &Experiment> {
+        self.0.iter()
+    }
+    /// Assigns variants of every experiment, keeping any of the given
+    /// previous assignments that are still valid (so that users see the same
+    /// variants every time). Any assignments for experiments or variants that
+    /// no longer exist will be dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn assign(&self, prev: &Variants) -> Variants {
+        let mut variants = BTreeMap::new();
+        for experiment in self.0.iter() {
+            let variant = match prev.get(experiment.get_name()) {
+                Some(variant) if experiment.has_variant(variant) => Some(variant),
+                _ => experiment.pick_variant(random_u64()),
+            };
+            if let Some(variant) = variant {
+                variants.insert(experiment.get_name().to_string(), variant.to_string());
+            }
+        }
+
+        Variants(variants)
+    }
+}
+
+/// The variants a user has been assigned, as a map of experiment names to
+/// variant names.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variants(BTreeMap<String, String>);
+impl Variants {
+    /// Gets the variant of the given experiment, if one has been assigned.
+    pub fn get(&self, experiment: &str) -> Option<&str> {
+        self.0.get(experiment).map(|variant| variant.as_str())
+    }
+    /// Checks if no variants have been assigned.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Creates a set of variants with only the given assignment.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn single(experiment: &str, variant: &str) -> Self {
+        let mut variants = BTreeMap::new();
+        variants.insert(experiment.to_string(), variant.to_string());
+        Self(variants)
+    }
+    /// Parses variants from the value of the variants cookie. Anything that
+    /// can't be parsed will be ignored.
+    pub fn from_cookie_value(value: &str) -> Self {
+        let variants = value
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .filter_map(|(experiment, variant)| {
+                let experiment = urlencoding::decode(experiment).ok()?;
+                let variant = urlencoding::decode(variant).ok()?;
+                Some((experiment.to_string(), variant.to_string()))
+            })
+            .collect();
+        Self(variants)
+    }
+    /// Serializes these variants into a value for the variants cookie.
+    pub fn to_cookie_value(&self) -> String {
+        self.0
+            .iter()
+            .map(|(experiment, variant)| {
+                format!(
+                    "{}={}",
+                    urlencoding::encode(experiment),
+                    urlencoding::encode(variant)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+    /// Gets the variants that have been injected into the page by the server,
+    /// if there are any.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn from_window() -> Self {
+        use sycamore::rt::{JsValue, Reflect};
+
+        let val = Reflect::get(
+            &JsValue::from(web_sys::window().unwrap()),
+            &JsValue::from("__PERSEUS_VARIANTS"),
+        )
+        .ok()
+        .and_then(|val| val.as_string());
+        match val {
+            Some(val) => serde_json::from_str(&val).unwrap_or_default(),
+            None => Self::default(),
+        }
+    }
+}
+
+/// Gets the variants the given request has been assigned. This is designed to
+/// be used in request-state functions, and it will return no variants if the
+/// app has no experiments.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_variants(req: &crate::Request) -> Variants {
+    req.extensions()
+        .get::<Variants>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Assigns variants of the given experiments to the given request, respecting
+/// any assignments in its variants cookie, and stores them in the request so
+/// they can be accessed with [`get_variants`]. If the user's assignments have
+/// changed, this will return the value of a `Set-Cookie` header that should
+/// be sent back to them.
+///
+/// This is designed for use by server integrations.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn assign_variants(experiments: &Experiments, req: &mut crate::Request) -> Option<String> {
+    if experiments.is_empty() {
+        return None;
+    }
+
+    let cookie_headers = req
+        .headers()
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok());
+    let prev = get_cookie(cookie_headers, VARIANTS_COOKIE)
+        .map(Variants::from_cookie_value)
+        .unwrap_or_default();
+    let variants = experiments.assign(&prev);
+    let set_cookie = if variants != prev {
+        Some(format!(
+            "{}={}; Path=/; Max-Age={}; SameSite=Lax",
+            VARIANTS_COOKIE,
+            variants.to_cookie_value(),
+            VARIANTS_COOKIE_MAX_AGE
+        ))
+    } else {
+        None
+    };
+    req.extensions_mut().insert(variants);
+
+    set_cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_cookie_round_trips() {
+        let mut variants = BTreeMap::new();
+        variants.insert("new homepage".to_string(), "b=1&c".to_string());
+        variants.insert("checkout".to_string(), "control".to_string());
+        let variants = Variants(variants);
+
+        assert_eq!(
+            Variants::from_cookie_value(&variants.to_cookie_value()),
+            variants
+        );
+    }
+
+    #[test]
+    fn assignments_are_sticky() {
+        let mut experiments = Experiments::default();
+        experiments.add(Experiment::new("test").variant("a", 1).variant("b", 1));
+        let first = experiments.assign(&Variants::default());
+        for _ in 0..10 {
+            assert_eq!(experiments.assign(&first), first);
+        }
+        // Stale assignments are replaced
+        let stale = Variants::single("test", "c");
+        assert_ne!(experiments.assign(&stale).get("test"), Some("c"));
+    }
+}
@@ -1,10 +1,21 @@
+use crate::build::BuildProgressTracker;
 use crate::errors::*;
+use crate::experiments::{Experiments, Variants};
 use crate::i18n::{Locales, TranslationsManager};
+use crate::router::LocalizedPaths;
 use crate::server::{get_render_cfg, HtmlShell};
 use crate::stores::ImmutableStore;
 use crate::template::TemplateMap;
 use crate::{page_data::PageData, SsrNode};
 use futures::future::{try_join, try_join_all};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::BTreeMap;
+
+/// The maximum number of pages that will be exported at once. Each page being
+/// exported is held in memory in full, so this keeps memory usage bounded for
+/// sites with very large numbers of pages (exporting is mostly limited by the
+/// filesystem anyway).
+const EXPORT_CONCURRENCY: usize = 64;
 
 /// Gets the static page data.
 pub async fn get_static_page_data(
@@ -32,6 +43,7 @@ pub async fn get_static_page_data(
         content,
         state,
         head,
+        redirect: None,
     })
 }
 
@@ -44,6 +56,8 @@ pub struct ExportProps<'a, T: TranslationsManager> {
     pub html_shell: HtmlShell,
     /// The locales data for the app.
     pub locales: &'a Locales,
+    /// The path the root of the app should redirect to, if there is one.
+    pub index_redirect: Option<&'a str>,
     /// An immutable store.
     pub immutable_store: &'a ImmutableStore,
     /// A translations manager.
@@ -52,6 +66,11 @@ pub struct ExportProps<'a, T: TranslationsManager> {
     pub path_prefix: String,
     /// A stringified global state.
     pub global_state: &'a Option<String>,
+    /// The app's A/B experiments, for each variant of which a copy of every
+    /// page will be exported.
+    pub experiments: &'a Experiments,
+    /// A tracker for the progress of the export.
+    pub progress: &'a BuildProgressTracker,
 }
 
 /// Exports your app to static files, which can be served from anywhere, without
@@ -63,29 +82,54 @@ pub async fn export_app<T: TranslationsManager>(
         templates,
         html_shell,
         locales,
+        index_redirect,
         immutable_store,
         translations_manager,
         path_prefix,
         global_state,
+        experiments,
+        progress,
     }: ExportProps<'_, T>,
 ) -> Result<(), ServerError> {
     // The render configuration acts as a guide here, it tells us exactly what we
     // need to iterate over (no request-side pages!)
     let render_cfg = get_render_cfg(immutable_store).await?;
 
-    // We can do literally everything concurrently here
+    // We can do literally everything concurrently here (though we only export a
+    // limited number of pages at a time)
     let mut export_futs = Vec::new();
+    // Pages may only exist in some locales, so we work out which locales to export
+    // each one for
+    let mut pages: BTreeMap<&String, (&String, Vec<&String>)> = BTreeMap::new();
+    for locale in locales.get_all() {
+        for (path, template_path) in render_cfg.pages_for_locale(locale) {
+            pages
+                .entry(path)
+                .or_insert_with(|| (template_path, Vec::new()))
+                .1
+                .push(locale);
+        }
+    }
     // Loop over every partial
-    for (path, template_path) in render_cfg {
-        let fut = export_path(
-            (path.to_string(), template_path.to_string()),
-            templates,
-            locales,
-            &html_shell,
-            immutable_store,
-            path_prefix.to_string(),
-            global_state,
-        );
+    progress.add_pages(pages.len());
+    for (path, (template_path, page_locales)) in pages {
+        let fut = async {
+            let res = export_path(
+                (path.to_string(), template_path.to_string()),
+                page_locales,
+                templates,
+                locales,
+                &render_cfg.localized_paths,
+                &html_shell,
+                immutable_store,
+                path_prefix.to_string(),
+                global_state,
+                experiments,
+            )
+            .await;
+            progress.page_done(template_path, path);
+            res
+        };
         export_futs.push(fut);
     }
     // If we're using i18n, loop through the locales to create translations files
@@ -97,7 +141,22 @@ pub async fn export_app<T: TranslationsManager>(
         }
     }
 
-    try_join(try_join_all(export_futs), try_join_all(translations_futs)).await?;
+    let export_fut = stream::iter(export_futs)
+        .buffer_unordered(EXPORT_CONCURRENCY)
+        .try_collect::<()>();
+    try_join(export_fut, try_join_all(translations_futs)).await?;
+    // This has to be done after everything else, because it overrides any `index`
+    // pages
+    if let Some(index_redirect) = index_redirect {
+        write_index_redirects(
+            index_redirect,
+            locales,
+            &html_shell,
+            immutable_store,
+            &path_prefix,
+        )
+        .await?;
+    }
 
     // Copying in bundles from the filesystem is left to the CLI command for
     // exporting, so we're done!
@@ -127,30 +186,82 @@ pub async fn create_translation_file(
     Ok(())
 }
 
+/// Writes pages that redirect the root of the app (and of each locale, if
+/// we're using i18n) to the given path. Static hosts can't send HTTP redirects,
+/// so these use `<meta>` refreshes.
+async fn write_index_redirects(
+    index_redirect: &str,
+    locales: &Locales,
+    html_shell: &HtmlShell,
+    immutable_store: &ImmutableStore,
+    path_prefix: &str,
+) -> Result<(), ServerError> {
+    let redirect_page = |url: &str| {
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+    <head>
+        <meta http-equiv="refresh" content="0; url={url}" />
+        <link rel="canonical" href="{url}" />
+    </head>
+    <body></body>
+</html>"#,
+            url = url
+        )
+    };
+
+    if locales.using_i18n {
+        for locale in locales.get_all() {
+            immutable_store
+                .write(
+                    &format!("exported/{}/index.html", locale),
+                    &redirect_page(&format!("{}/{}/{}", path_prefix, locale, index_redirect)),
+                )
+                .await?;
+        }
+        // The root of the app still needs to detect the user's locale, after which
+        // they'll be redirected by the router (or by the above if they don't have
+        // Wasm)
+        immutable_store
+            .write(
+                "exported/index.html",
+                &html_shell
+                    .clone()
+                    .locale_redirection_fallback(&format!("{}/{}", path_prefix, locales.default))
+                    .to_string(),
+            )
+            .await?;
+    } else {
+        immutable_store
+            .write(
+                "exported/index.html",
+                &redirect_page(&format!("{}/{}", path_prefix, index_redirect)),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
 /// Exports a single path within a template.
+#[allow(clippy::too_many_arguments)]
 pub async fn export_path(
     (path, template_path): (String, String),
+    page_locales: Vec<&String>,
     templates: &TemplateMap<SsrNode>,
     locales: &Locales,
+    localized_paths: &LocalizedPaths,
     html_shell: &HtmlShell,
     immutable_store: &ImmutableStore,
     path_prefix: String,
     global_state: &Option<String>,
+    experiments: &Experiments,
 ) -> Result<(), ServerError> {
     // We need the encoded path to reference flattened build artifacts
     // But we don't create a flattened system with exporting, everything is properly
     // created in a directory structure
     let path_encoded = urlencoding::encode(&path).to_string();
-    // All initial load pages should be written into their own folders, which
-    // prevents a situation of a template root page outside the directory for the
-    // rest of that template's pages (see #73) The `.html` file extension is
-    // added when this variable is used (for contrast to the `.json`s)
-    let initial_load_path = if path.ends_with("index") {
-        // However, if it's already an index page, we dont want `index/index.html`
-        path.to_string()
-    } else {
-        format!("{}/index", &path)
-    };
+    let initial_load_path = get_initial_load_path(&path);
 
     // Get the template itself
     let template = templates.get(&template_path);
@@ -166,8 +277,12 @@ pub async fn export_path(
     // Create a locale detection file for it if we're using i18n
     // These just send the app shell, which will perform a redirect as necessary
     // Notably, these also include fallback redirectors if either Wasm or JS is
-    // disabled (or both)
+    // disabled (or both), which go to the default locale if the page exists in it
     if locales.using_i18n {
+        let fallback_locale = match page_locales.contains(&&locales.default) {
+            true => &locales.default,
+            false => page_locales[0],
+        };
         immutable_store
             .write(
                 &format!("exported/{}.html", &initial_load_path),
@@ -175,7 +290,9 @@ pub async fn export_path(
                     .clone()
                     .locale_redirection_fallback(&format!(
                         "{}/{}/{}",
-                        path_prefix, locales.default, &path
+                        path_prefix,
+                        fallback_locale,
+                        localized_paths.localize(&path, fallback_locale)
                     ))
                     .to_string(),
             )
@@ -185,8 +302,8 @@ pub async fn export_path(
     // file)
     let has_state = template.uses_build_state();
     if locales.using_i18n {
-        // Loop through all the app's locales
-        for locale in locales.get_all() {
+        // Loop through all the locales this page exists in
+        for locale in page_locales {
             let page_data = get_static_page_data(
                 &format!("{}-{}", locale, &path_encoded),
                 has_state,
@@ -196,16 +313,19 @@ pub async fn export_path(
             // Create a full HTML file from those that can be served for initial loads
             // The build process writes these with a dummy default locale even though we're
             // not using i18n
-            let full_html = html_shell
-                .clone()
-                .page_data(&page_data, global_state)
-                .to_string();
-            immutable_store
-                .write(
-                    &format!("exported/{}/{}.html", locale, initial_load_path),
-                    &full_html,
-                )
-                .await?;
+            let full_html = html_shell.clone().page_data(&page_data, global_state);
+            // Initial loads are served from the path users will see in this locale, but
+            // partials stay under the page's actual path, since that's what the app
+            // shell will ask for
+            let localized_initial_load_path =
+                get_initial_load_path(&localized_paths.localize(&path, locale));
+            write_initial_load(
+                full_html,
+                &format!("{}/{}.html", locale, localized_initial_load_path),
+                experiments,
+                immutable_store,
+            )
+            .await?;
 
             // Serialize the page data to JSON and write it as a partial (fetched by the app
             // shell for subsequent loads)
@@ -227,15 +347,14 @@ pub async fn export_path(
         // Create a full HTML file from those that can be served for initial loads
         // The build process writes these with a dummy default locale even though we're
         // not using i18n
-        let full_html = html_shell
-            .clone()
-            .page_data(&page_data, global_state)
-            .to_string();
-        // We don't add an extension because this will be queried directly by the
-        // browser
-        immutable_store
-            .write(&format!("exported/{}.html", initial_load_path), &full_html)
-            .await?;
+        let full_html = html_shell.clone().page_data(&page_data, global_state);
+        write_initial_load(
+            full_html,
+            &format!("{}.html", initial_load_path),
+            experiments,
+            immutable_store,
+        )
+        .await?;
 
         // Serialize the page data to JSON and write it as a partial (fetched by the app
         // shell for subsequent loads)
@@ -250,3 +369,54 @@ pub async fn export_path(
 
     Ok(())
 }
+
+/// Gets the path (without the `.html` extension) that the initial load page
+/// for the given path should be exported to. All initial load pages are
+/// written into their own folders, which prevents a situation of a template
+/// root page outside the directory for the rest of that template's pages (see
+/// #73). The `.html` file extension should be added to this by the caller.
+fn get_initial_load_path(path: &str) -> String {
+    if path.ends_with("index") {
+        // However, if it's already an index page, we dont want `index/index.html`
+        path.to_string()
+    } else {
+        format!("{}/index", path)
+    }
+}
+
+/// Writes the given HTML shell (which should already have page data
+/// interpolated) to the given path in the export directory, along with a copy
+/// for each variant of each of the app's A/B experiments under
+/// `.perseus/experiments/<experiment>/<variant>/`. The pages themselves are the
+/// same, but the copies will tell the app which variant it should use.
+async fn write_initial_load(
+    html_shell: HtmlShell,
+    path: &str,
+    experiments: &Experiments,
+    immutable_store: &ImmutableStore,
+) -> Result<(), ServerError> {
+    for experiment in experiments.iter() {
+        for variant in experiment.get_variants() {
+            let variant_html = html_shell
+                .clone()
+                .variants(&Variants::single(experiment.get_name(), variant))
+                .to_string();
+            immutable_store
+                .write(
+                    &format!(
+                        "exported/.perseus/experiments/{}/{}/{}",
+                        urlencoding::encode(experiment.get_name()),
+                        urlencoding::encode(variant),
+                        path
+                    ),
+                    &variant_html,
+                )
+                .await?;
+        }
+    }
+    immutable_store
+        .write(&format!("exported/{}", path), &html_shell.to_string())
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Build-time syntax highlighting, so that code blocks can be sent to the
+//! browser already highlighted, without shipping a highlighter to it. This is
+//! built on [`syntect`](https://docs.rs/syntect), which supports the same
+//! languages and themes as Sublime Text.
+//!
+//! Highlighted code is marked up with classes rather than inline styles, so
+//! the theme's CSS (from [`Highlighter::css`]) only needs to be included once
+//! (e.g. in your index view, or in a file in your `static/` directory), rather
+//! than being repeated in every page.
+//!
+//! Loading the syntax definitions is fairly expensive, so you should create
+//! one [`Highlighter`] and share it between your state generation functions,
+//! e.g. with `PerseusApp::state_dependency()`:
+//!
+//! ```rust,ignore
+//! async fn get_build_state(info: StateGeneratorInfo) -> RenderFnResultWithCause<PostState> {
+//!     let highlighter = info.dependency::<Highlighter>().unwrap();
+//!     let html = render_markdown(&info.path).await?;
+//!     Ok(PostState {
+//!         content: highlighter.highlight_html(&html)?,
+//!     })
+//! }
+//! ```
+
+use crate::errors::HighlightError;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// The prefix of every class the highlighter produces, which avoids clashes
+/// with your own styles.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+/// The theme that will be used by default.
+const DEFAULT_THEME: &str = "InspiredGitHub";
+
+/// A syntax highlighter, which holds the loaded syntax definitions and a
+/// theme.
+#[derive(Debug)]
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Highlighter {
+    /// Creates a new highlighter with the default syntaxes and the default
+    /// theme (`InspiredGitHub`).
+    pub fn new() -> Self {
+        Self::with_theme(DEFAULT_THEME).unwrap()
+    }
+    /// Creates a new highlighter with the default syntaxes and the given
+    /// theme, which must be one of the ones built into `syntect` (e.g.
+    /// `base16-ocean.dark` or `Solarized (light)`).
+    pub fn with_theme(name: &str) -> Result<Self, HighlightError> {
+        let mut themes = ThemeSet::load_defaults().themes;
+        let theme = themes
+            .remove(name)
+            .ok_or_else(|| HighlightError::UnknownTheme {
+                name: name.to_string(),
+            })?;
+
+        Ok(Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        })
+    }
+    /// Gets the CSS for the highlighter's theme, which should be included once
+    /// on any page that has highlighted code on it.
+    pub fn css(&self) -> Result<String, HighlightError> {
+        css_for_theme_with_class_style(&self.theme, CLASS_STYLE)
+            .map_err(|err| HighlightError::HighlightFailed { source: err })
+    }
+    /// Highlights the given code in the given language, which can be a name
+    /// (e.g. `Rust`) or a file extension (e.g. `rs`), producing a
+    /// `<pre class="hl-code">` element. If the language isn't known, the code
+    /// will be left as plain text.
+    pub fn highlight(&self, code: &str, lang: &str) -> Result<String, HighlightError> {
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+        for line in LinesWithEndings::from(code) {
+            generator
+                .parse_html_for_line_which_includes_newline(line)
+                .map_err(|err| HighlightError::HighlightFailed { source: err })?;
+        }
+
+        Ok(format!(
+            "<pre class=\"hl-code\"><code>{}</code></pre>",
+            generator.finalize()
+        ))
+    }
+    /// Highlights all the code blocks in the given HTML, as produced by most
+    /// Markdown renderers (i.e. `<pre><code class="language-rust">`). Code
+    /// blocks without a language will be left alone.
+    pub fn highlight_html(&self, html: &str) -> Result<String, HighlightError> {
+        const OPENING: &str = "<pre><code class=\"language-";
+        const CLOSING: &str = "</code></pre>";
+
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html;
+        while let Some(start) = rest.find(OPENING) {
+            let after_opening = &rest[start + OPENING.len()..];
+            let block = after_opening
+                .split_once("\">")
+                .and_then(|(lang, after_tag)| {
+                    let end = after_tag.find(CLOSING)?;
+                    Some((lang, &after_tag[..end], &after_tag[end + CLOSING.len()..]))
+                });
+            let (lang, code, after_block) = match block {
+                Some(block) => block,
+                None => break,
+            };
+
+            out.push_str(&rest[..start]);
+            out.push_str(&self.highlight(&decode_entities(code), lang)?);
+            rest = after_block;
+        }
+        out.push_str(rest);
+
+        Ok(out)
+    }
+}
+
+/// Decodes the HTML entities Markdown renderers produce when escaping code.
+fn decode_entities(code: &str) -> String {
+    code.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_code_blocks() {
+        let highlighter = Highlighter::new();
+        let html = highlighter
+            .highlight_html(
+                "<p>Hi</p><pre><code class=\"language-rust\">let x = &amp;1;\n</code></pre><pre><code>plain</code></pre>",
+            )
+            .unwrap();
+
+        assert!(html.starts_with(
+            "<p>Hi</p><pre class=\"hl-code\"><code><span class=\"hl-source hl-rust\">"
+        ));
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-rust\">let</span>"));
+        assert!(html.ends_with("<pre><code>plain</code></pre>"));
+        assert!(highlighter.css().unwrap().contains(".hl-code {"));
+        assert!(matches!(
+            Highlighter::with_theme("nonexistent"),
+            Err(HighlightError::UnknownTheme { .. })
+        ));
+    }
+}
@@ -1,103 +1,282 @@
-use super::Locales;
+use super::{get_preferred_langs, Locales};
 use crate::errors::*;
 use crate::i18n::Translator;
-use crate::shell::fetch;
+use crate::shell::{fetch_if_modified, Revalidated};
 use crate::utils::get_path_prefix_client;
+use crate::web::WebStorage;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
+/// The prefix of the `localStorage` keys that translations are persisted
+/// under (the locale is appended to this).
+const PERSISTED_TRANSLATIONS_KEY_PREFIX: &str = "__perseus_translations_";
+
+thread_local! {
+    /// The translator for the app's embedded translations, which is only
+    /// created the first time an error page needs it.
+    static EMBEDDED_TRANSLATOR: RefCell<Option<Translator>> = RefCell::new(None);
+}
+
+/// Gets a translator for the given embedded translations of the given default
+/// locale, only parsing them the first time. This is shared by every error
+/// page, including the one rendered after a panic.
+pub(crate) fn get_embedded_translator(locale: &str, embedded: &str) -> Option<Rc<Translator>> {
+    EMBEDDED_TRANSLATOR.with(|cached| {
+        // If we panicked while this was borrowed, it'll stay that way, so we just won't
+        // cache anything
+        let mut cached = match cached.try_borrow_mut() {
+            Ok(cached) => cached,
+            Err(_) => {
+                return Translator::new(locale.to_string(), embedded.to_string())
+                    .ok()
+                    .map(Rc::new)
+            }
+        };
+        if cached.is_none() {
+            *cached = Translator::new(locale.to_string(), embedded.to_string()).ok();
+        }
+        cached.clone().map(Rc::new)
+    })
+}
+
+/// Translations persisted in `localStorage`, along with the version (the ETag
+/// the server sent with them) they're at.
+#[derive(Serialize, Deserialize)]
+struct PersistedTranslations {
+    version: String,
+    translations: String,
+}
+
 /// Manages translations in the app shell. This handles fetching translations
 /// from the server as well as caching for performance. This is distinct from
 /// `TranslationsManager` in that it operates on the client-side rather than on
 /// the server. This optimizes for users viewing many pages in the same locale,
 /// which is by far the most common use of most websites in terms of i18n.
 ///
+/// If the app has enabled it, translations will also be persisted in
+/// `localStorage`, and they'll only be downloaded again in future sessions if
+/// the server says they've changed.
+///
+/// The translations for other locales can be fetched in the background with
+/// `.preload()` (this is available on the render context as
+/// `.translations_manager`), so that switching to them later is instant.
+///
 /// This holds mutability internally to avoid issues with async/await.
 #[derive(Debug, Clone)]
-pub(crate) struct ClientTranslationsManager {
-    /// The cached translator. If the same locale is requested again, this will
-    /// simply be returned.
-    cached_translator: Rc<RefCell<Option<Translator>>>,
+pub struct ClientTranslationsManager {
+    /// The cached translators for each locale. If the same locale is requested
+    /// again, its translator will simply be returned.
+    cached_translators: Rc<RefCell<HashMap<String, Translator>>>,
     locales: Locales,
+    /// Whether or not translations should be persisted in `localStorage`.
+    persist: bool,
+    /// The translations for the default locale that the app has embedded, if
+    /// it has any. These are used to render error pages when the translations
+    /// for the user's locale can't be fetched.
+    embedded: Option<&'static str>,
 }
 impl ClientTranslationsManager {
     /// Creates a new client-side translations manager that hasn't cached
     /// anything yet. This needs to know about an app's supported locales so
     /// it can avoid network requests to unsupported locales.
-    pub fn new(locales: &Locales) -> Self {
+    pub(crate) fn new(locales: &Locales, persist: bool, embedded: Option<&'static str>) -> Self {
         Self {
-            cached_translator: Rc::new(RefCell::new(None)),
+            cached_translators: Rc::new(RefCell::new(HashMap::new())),
             locales: locales.clone(),
+            persist,
+            embedded,
         }
     }
     /// Gets an `&'static Translator` for the given locale. This will use the
     /// internally cached `Translator` if possible, and will otherwise fetch
     /// the translations from the server. This manages mutability for caching
     /// internally.
-    pub async fn get_translator_for_locale<'a>(
+    pub(crate) async fn get_translator_for_locale<'a>(
         &'a self,
         locale: &'a str,
     ) -> Result<Translator, ClientError> {
         let path_prefix = get_path_prefix_client();
-        // Check if we've already cached
-        let mut cached_translator = self.cached_translator.borrow_mut();
-        if cached_translator.is_some() && cached_translator.as_ref().unwrap().get_locale() == locale
-        {
-            Ok(cached_translator.as_ref().unwrap().clone())
-        } else {
-            // Check if the locale is supported and we're actually using i18n
-            if self.locales.is_supported(locale) && self.locales.using_i18n {
-                // Get the translations data
-                let asset_url = format!("{}/.perseus/translations/{}", path_prefix, locale);
-                // If this doesn't exist, then it's a 404 (we went here by explicit navigation
-                // after checking the locale, so that's a bug)
-                let translations_str = fetch(&asset_url).await;
-                let translator = match translations_str {
-                    Ok(translations_str) => match translations_str {
-                        Some(translations_str) => {
-                            // All good, turn the translations into a translator
-                            match Translator::new(locale.to_string(), translations_str) {
-                                Ok(translator) => translator,
-                                Err(err) => {
-                                    return Err(FetchError::SerFailed {
-                                        url: asset_url,
-                                        source: err.into(),
-                                    }
-                                    .into())
-                                }
-                            }
+        // Check if we've already cached (we can't hold onto this borrow while we fetch)
+        if let Some(translator) = self.cached_translators.borrow().get(locale) {
+            return Ok(translator.clone());
+        }
+        // Check if the locale is supported and we're actually using i18n
+        let translator = if self.locales.is_supported(locale) && self.locales.using_i18n {
+            // Get the translations data
+            let asset_url = format!("{}/.perseus/translations/{}", path_prefix, locale);
+            let persisted = self.get_persisted(locale);
+            // If this doesn't exist, then it's a 404 (we went here by explicit navigation
+            // after checking the locale, so that's a bug)
+            let res = fetch_if_modified(
+                &asset_url,
+                persisted
+                    .as_ref()
+                    .map(|persisted| persisted.version.as_str()),
+            )
+            .await;
+            let translations_str =
+                match res {
+                    Ok(Some(Revalidated::Modified { body, etag })) => {
+                        self.persist(locale, &body, etag);
+                        body
+                    }
+                    Ok(Some(Revalidated::NotModified)) => match persisted {
+                        Some(persisted) => persisted.translations,
+                        // We didn't give the server a version, so it shouldn't have said we
+                        // have the current one
+                        None => return Err(FetchError::NotOk {
+                            url: asset_url,
+                            status: 304,
+                            err: "server said translations weren't modified, but none were cached"
+                                .to_string(),
                         }
-                        // If we get a 404 for a supported locale, that's an exception
-                        None => panic!(
-                            "server returned 404 for translations for known supported locale '{}'",
-                            locale
-                        ),
+                        .into()),
                     },
+                    // If we get a 404 for a supported locale, that's an exception
+                    Ok(None) => panic!(
+                        "server returned 404 for translations for known supported locale '{}'",
+                        locale
+                    ),
                     Err(err) => match err {
-                        not_ok_err @ ClientError::FetchError(FetchError::NotOk { .. }) => {
-                            return Err(not_ok_err)
-                        }
+                        fetch_err @ ClientError::FetchError(_) => return Err(fetch_err),
                         // No other errors should be returned
-                        _ => panic!("expected 'AssetNotOk' error, found other unacceptable error"),
+                        _ => panic!("expected fetch error, found other unacceptable error"),
                     },
                 };
-                // Cache that translator
-                *cached_translator = Some(translator);
-                // Now return that
-                Ok(cached_translator.as_ref().unwrap().clone())
-            } else if !self.locales.using_i18n {
-                // If we aren't even using i18n, then it would be pointless to fetch
-                // translations
-                let translator = Translator::new("xx-XX".to_string(), "".to_string()).unwrap();
-                // Cache that translator
-                *cached_translator = Some(translator);
-                // Now return that
-                Ok(cached_translator.as_ref().unwrap().clone())
-            } else {
-                Err(ClientError::LocaleNotSupported {
-                    locale: locale.to_string(),
-                })
+            // All good, turn the translations into a translator
+            match Translator::new(locale.to_string(), translations_str) {
+                Ok(translator) => translator,
+                Err(err) => {
+                    return Err(FetchError::SerFailed {
+                        url: asset_url,
+                        source: err.into(),
+                    }
+                    .into())
+                }
+            }
+        } else if !self.locales.using_i18n {
+            // If we aren't even using i18n, then it would be pointless to fetch
+            // translations
+            Translator::new("xx-XX".to_string(), "".to_string()).unwrap()
+        } else {
+            return Err(ClientError::LocaleNotSupported {
+                locale: locale.to_string(),
+            });
+        };
+        // Cache that translator
+        self.cached_translators
+            .borrow_mut()
+            .insert(locale.to_string(), translator.clone());
+
+        Ok(translator)
+    }
+    /// Gets a translator to render an error page in the given locale with,
+    /// without fetching anything. If the translations for the locale have
+    /// already been fetched, they'll be used, and otherwise the app's
+    /// embedded translations for its default locale will be (if it has none,
+    /// this will return `None`).
+    pub(crate) fn get_fallback_translator(&self, locale: &str) -> Option<Rc<Translator>> {
+        if self.locales.using_i18n {
+            if let Some(translator) = self.cached_translators.borrow().get(locale) {
+                return Some(Rc::new(translator.clone()));
             }
         }
+        get_embedded_translator(&self.locales.default, self.embedded?)
+    }
+    /// Gets a translator to render an error page in the given locale with,
+    /// fetching its translations if necessary, and falling back to the app's
+    /// embedded translations for its default locale if that fails. This should
+    /// be used for error pages the server has rendered, which will have been
+    /// rendered with the same translations.
+    pub(crate) async fn get_error_translator(&self, locale: &str) -> Option<Rc<Translator>> {
+        if self.locales.using_i18n {
+            if let Ok(translator) = self.get_translator_for_locale(locale).await {
+                return Some(Rc::new(translator));
+            }
+        }
+        self.get_fallback_translator(locale)
+    }
+    /// Gets the locale of the given URL (from its first path segment), falling
+    /// back to the default locale if it isn't under one the app supports.
+    pub(crate) fn get_url_locale(&self, url: &str) -> String {
+        let path_prefix = get_path_prefix_client();
+        let path = url.strip_prefix(&path_prefix).unwrap_or(url);
+        path.split('/')
+            .find(|segment| !segment.is_empty())
+            .filter(|locale| self.locales.is_supported(locale))
+            .unwrap_or(&self.locales.default)
+            .to_string()
+    }
+    /// Fetches the translations for the given locale in the background (after
+    /// checking that it's supported), so that switching to it later will be
+    /// instant. If they've already been fetched, this will do nothing. Any
+    /// errors will be ignored, since the translations will just be fetched
+    /// again when they're actually needed.
+    pub fn preload(&self, locale: &str) {
+        if !self.locales.using_i18n || !self.locales.is_supported(locale) {
+            return;
+        }
+        let manager = self.clone();
+        let locale = locale.to_string();
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = manager.get_translator_for_locale(&locale).await;
+        });
+    }
+    /// Preloads the translations for every supported locale the user's browser
+    /// says they understand (e.g. their secondary languages). This is run
+    /// automatically after the first page has been rendered if the app has
+    /// enabled it.
+    pub(crate) fn preload_preferred(&self) {
+        if !self.locales.using_i18n {
+            return;
+        }
+        let mut preloaded = Vec::new();
+        for lang in get_preferred_langs() {
+            if let Some(locale) = self.locales.negotiate(&[lang]) {
+                if !preloaded.contains(&locale) {
+                    self.preload(&locale);
+                    preloaded.push(locale);
+                }
+            }
+        }
+    }
+    /// Gets the translations persisted in `localStorage` for the given locale,
+    /// if persistence is enabled and there are any.
+    fn get_persisted(&self, locale: &str) -> Option<PersistedTranslations> {
+        if !self.persist {
+            return None;
+        }
+        let key = format!("{}{}", PERSISTED_TRANSLATIONS_KEY_PREFIX, locale);
+        let persisted = WebStorage::local().get(&key).ok()??;
+        // If these are in an old format, we'll just fetch them again
+        serde_json::from_str(&persisted).ok()
+    }
+    /// Persists the given translations for the given locale in `localStorage`
+    /// at the given version, if persistence is enabled. If the server didn't
+    /// give them a version, any old translations will be removed, since we
+    /// wouldn't be able to check if they're current. Failures (e.g. if the
+    /// storage quota has been exceeded) are ignored, since the translations
+    /// can always be fetched again.
+    fn persist(&self, locale: &str, translations: &str, version: Option<String>) {
+        if !self.persist {
+            return;
+        }
+        let key = format!("{}{}", PERSISTED_TRANSLATIONS_KEY_PREFIX, locale);
+        let storage = WebStorage::local();
+        let _ = match version {
+            Some(version) => {
+                let persisted = PersistedTranslations {
+                    version,
+                    translations: translations.to_string(),
+                };
+                match serde_json::to_string(&persisted) {
+                    Ok(persisted) => storage.set(&key, &persisted),
+                    Err(_) => return,
+                }
+            }
+            None => storage.remove(&key),
+        };
     }
 }
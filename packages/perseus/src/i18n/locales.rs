@@ -24,4 +24,135 @@ impl Locales {
         let locales = self.get_all();
         locales.iter().any(|l| *l == locale)
     }
+    /// Works out which supported locale best matches the given locales the
+    /// user prefers (most preferred first), if any do. As per [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647.txt),
+    /// the first of the user's locales that matches a supported locale
+    /// (either exactly or by language alone) wins.
+    pub fn negotiate<S: AsRef<str>>(&self, preferred: &[S]) -> Option<String> {
+        let locales = self.get_all();
+        preferred
+            .iter()
+            .find_map(|cmp| match compare_locale(cmp.as_ref(), &locales) {
+                LocaleMatch::Exact(matched) | LocaleMatch::Language(matched) => Some(matched),
+                LocaleMatch::None => None,
+            })
+    }
+    /// Restricts these locales to the given ones, which is how only some of an
+    /// app's locales are built (e.g. for a preview deployment for
+    /// translators). The default locale is always kept, since users are sent
+    /// to it whenever locale detection fails, and any of the given locales
+    /// that aren't supported will be ignored. If the app doesn't use i18n, this
+    /// does nothing.
+    pub fn restrict_to<S: AsRef<str>>(&self, only: &[S]) -> Self {
+        if !self.using_i18n {
+            return self.clone();
+        }
+        Self {
+            default: self.default.clone(),
+            other: self
+                .other
+                .iter()
+                .filter(|locale| only.iter().any(|l| l.as_ref() == *locale))
+                .cloned()
+                .collect(),
+            using_i18n: true,
+        }
+    }
+}
+
+/// Gets the locales that should be built, if the CLI has been told to only
+/// build some of them with `--locales`. This uses the `PERSEUS_LOCALES`
+/// environment variable, which should be a comma-separated list of locales.
+/// If this returns `None`, all of the app's locales should be built.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_locale_filter() -> Option<Vec<String>> {
+    let locales = std::env::var("PERSEUS_LOCALES").ok()?;
+    let locales = locales
+        .split(',')
+        .map(|locale| locale.trim())
+        .filter(|locale| !locale.is_empty())
+        .map(|locale| locale.to_string())
+        .collect::<Vec<_>>();
+
+    match locales.is_empty() {
+        true => None,
+        false => Some(locales),
+    }
+}
+
+/// The possible outcomes of trying to match a locale.
+#[derive(Debug, PartialEq, Eq)]
+enum LocaleMatch {
+    /// The language and region match to a supported locale.
+    Exact(String),
+    /// The language (but not the region) matches a supported locale, the first
+    /// supported locale with that language will be used.
+    Language(String),
+    /// The given locale isn't supported at all. If all the user's requested
+    /// locales return this, we should fall back to the default.
+    None,
+}
+
+/// Compares the given locale with the given vector of locales, identifying the
+/// closest match. This handles possible case discrepancies automatically (e.g.
+/// Safari before iOS 10.2 returned all locales in lower-case).
+///
+/// Exact matches with any supported locale are preferred to language-only (and
+/// not region) matches. Remember that this function only matches a single
+/// locale, not the list of the preferred locales (in which the first of either
+/// kind of match is used as per [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647.txt)).
+///
+/// This does NOT comply fully with [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647.txt) yet, as only `xx-XX` form locales are
+/// currently supported. This functionality will eventually be broken out into a
+/// separate module for ease of use.
+fn compare_locale<S: Into<String> + std::fmt::Display>(cmp: &str, locales: &[S]) -> LocaleMatch {
+    let mut outcome = LocaleMatch::None;
+    // Split into language and region (e.g. `en-US`) if possible
+    let cmp_parts: Vec<&str> = cmp.split('-').collect();
+
+    for locale in locales {
+        let locale = locale.to_string();
+        // Split into language and region (e.g. `en-US`) if possible
+        let parts: Vec<&str> = locale.split('-').collect();
+        if locale == cmp {
+            outcome = LocaleMatch::Exact(locale.to_string());
+            // Any exact match voids anything after it (it'll be further down the list or
+            // only a partial match from here on)
+            break;
+        } else if cmp_parts.first() == parts.first() {
+            // If we've already had a partial match higher up the chain, this is void
+            // But we shouldn't break in case there's an exact match coming up
+            if !matches!(outcome, LocaleMatch::Language(_)) {
+                outcome = LocaleMatch::Language(locale.to_string())
+            }
+        }
+        // If there's no match, just continue on for now
+    }
+
+    outcome
+}
+
+mod tests {
+    #[allow(unused_imports)] // For some reason this throws a warning otherwise...
+    use super::*;
+    #[test]
+    fn matches_exact() {
+        let verdict = compare_locale("en-US", &["en-US"]);
+        assert_eq!(verdict, LocaleMatch::Exact("en-US".to_string()))
+    }
+    #[test]
+    fn matches_lang() {
+        let verdict = compare_locale("en-US", &["en-GB"]);
+        assert_eq!(verdict, LocaleMatch::Language("en-GB".to_string()))
+    }
+    #[test]
+    fn fails_on_no_match() {
+        let verdict = compare_locale("en-US", &["zh-CN"]);
+        assert_eq!(verdict, LocaleMatch::None)
+    }
+    #[test]
+    fn uses_later_exact_match() {
+        let verdict = compare_locale("en-US", &["en-GB", "en-US"]);
+        assert_eq!(verdict, LocaleMatch::Exact("en-US".to_string()))
+    }
 }
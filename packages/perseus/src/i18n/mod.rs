@@ -9,9 +9,13 @@ mod locales;
 mod translations_manager;
 
 #[cfg(target_arch = "wasm32")]
-pub(crate) use client_translations_manager::ClientTranslationsManager;
+pub(crate) use client_translations_manager::get_embedded_translator;
 #[cfg(target_arch = "wasm32")]
-pub(crate) use locale_detector::detect_locale;
+pub use client_translations_manager::ClientTranslationsManager;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use locale_detector::{detect_locale, get_preferred_langs};
+#[cfg(not(target_arch = "wasm32"))]
+pub use locales::get_locale_filter;
 pub use locales::Locales;
 pub use translations_manager::{
     FsTranslationsManager, TranslationsManager, TranslationsManagerError,
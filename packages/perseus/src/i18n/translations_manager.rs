@@ -31,6 +31,10 @@ use futures::future::join_all;
 #[cfg(not(target_arch = "wasm32"))]
 use std::collections::HashMap;
 #[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::fs::File;
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::io::AsyncReadExt;
@@ -95,7 +99,10 @@ async fn get_translations_str_and_cache(
 /// supports not using i18n at all (a dummy translations manager).
 ///
 /// Note that this will cache translations upon initialization, meaning
-/// source files cannot be updated while the system is running.
+/// source files cannot be updated while the system is running, except in
+/// development when the CLI is watching for changes, in which case any
+/// translations that have changed since they were cached will be read from
+/// disk again (so you can edit them without restarting your app).
 #[derive(Clone, Debug)]
 pub struct FsTranslationsManager {
     #[cfg(not(target_arch = "wasm32"))]
@@ -109,6 +116,11 @@ pub struct FsTranslationsManager {
     /// The locales being cached for easier access.
     #[cfg(not(target_arch = "wasm32"))]
     cached_locales: Vec<String>,
+    /// When the translations were cached, if they should be invalidated when
+    /// their files are modified after that (which will happen when the CLI is
+    /// watching for changes).
+    #[cfg(not(target_arch = "wasm32"))]
+    cached_at: Option<SystemTime>,
     /// The file extension expected (e.g. JSON, FTL, etc). This allows for
     /// greater flexibility of translation engines (future).
     #[cfg(not(target_arch = "wasm32"))]
@@ -134,6 +146,7 @@ impl FsTranslationsManager {
             root_path,
             cached_translations: HashMap::new(),
             cached_locales: Vec::new(),
+            cached_at: None,
             file_ext,
             is_dummy: false,
         };
@@ -148,9 +161,35 @@ impl FsTranslationsManager {
         // We only declare the locales that are being cached after getting translations
         // becuase otherwise those getters would be using undefined caches
         manager.cached_locales = locales_to_cache;
+        // This is set by the CLI when it's watching for changes
+        if std::env::var("PERSEUS_USE_RELOAD_SERVER").is_ok() {
+            manager.cached_at = Some(SystemTime::now());
+        }
 
         manager
     }
+    /// Gets the cached translations for the given locale, if they're cached
+    /// and haven't been modified since.
+    async fn get_cached(&self, locale: &str) -> Option<String> {
+        if !self.cached_locales.iter().any(|cached| cached == locale) {
+            return None;
+        }
+        let translations = self.cached_translations.get(locale)?;
+        if let Some(cached_at) = self.cached_at {
+            let asset_path =
+                Path::new(&self.root_path).join(format!("{}.{}", locale, self.file_ext));
+            let modified = tokio::fs::metadata(&asset_path)
+                .await
+                .and_then(|metadata| metadata.modified());
+            match modified {
+                Ok(modified) if modified <= cached_at => (),
+                // If we can't tell, we'll read them again to be safe
+                _ => return None,
+            }
+        }
+
+        Some(translations.to_string())
+    }
 }
 // `FsTranslationsManager` needs to exist in the browser, but it shouldn't do
 // anything
@@ -162,6 +201,7 @@ impl TranslationsManager for FsTranslationsManager {
             root_path: String::new(),
             cached_translations: HashMap::new(),
             cached_locales: Vec::new(),
+            cached_at: None,
             file_ext: String::new(),
             is_dummy: true,
         }
@@ -178,11 +218,12 @@ impl TranslationsManager for FsTranslationsManager {
 
         // Check if the locale is cached for
         // No dynamic caching, so if it isn't cached it stays that way
-        if self.cached_locales.contains(&locale) {
-            Ok(self.cached_translations.get(&locale).unwrap().to_string())
+        if let Some(translations_str) = self.get_cached(&locale).await {
+            Ok(translations_str)
         } else {
             // The file must be named as the locale it describes
-            let asset_path = format!("{}/{}.{}", self.root_path, locale, self.file_ext);
+            let asset_path =
+                Path::new(&self.root_path).join(format!("{}.{}", locale, self.file_ext));
             let mut file = File::open(&asset_path).await.map_err(|err| {
                 TranslationsManagerError::ReadFailed {
                     locale: locale.clone(),
@@ -231,11 +272,7 @@ impl TranslationsManager for FsTranslationsManager {
 
         // Check if the locale is cached for
         // No dynamic caching, so if it isn't cached it stays that way
-        let translations_str = if self.cached_locales.contains(&locale) {
-            self.cached_translations.get(&locale).unwrap().to_string()
-        } else {
-            self.get_translations_str_for_locale(locale.clone()).await?
-        };
+        let translations_str = self.get_translations_str_for_locale(locale.clone()).await?;
         // We expect the translations defined there, but not the locale itself
         let translator = Translator::new(locale.clone(), translations_str).map_err(|err| {
             TranslationsManagerError::SerializationFailed {
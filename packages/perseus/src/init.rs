@@ -1,23 +1,50 @@
 #[cfg(not(target_arch = "wasm32"))]
-use crate::server::{get_render_cfg, HtmlShell};
+use crate::engine::{
+    EngineHookFn, EngineHookResult, EngineHookStage, EngineOperation, Schedule, ScheduledJob,
+};
+use crate::errors::ErrorReporter;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::errors::{AppConfigError, EngineError};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::experiments::Experiments;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::router::LocalizedPaths;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::server::{
+    get_render_cfg, HtmlShell, IncrementalWarmup, MaintenanceOptions, StaticContentOptions,
+    TrustedProxies,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::state::{StateDependencies, TemplateGroups};
 use crate::stores::ImmutableStore;
 #[cfg(not(target_arch = "wasm32"))]
+use crate::template::{BaseHeadFn, RenderFnResult, RouteRegistry};
+#[cfg(not(target_arch = "wasm32"))]
 use crate::utils::get_path_prefix_server;
 use crate::{
+    errors::ClientError,
+    experiments::Experiment,
     i18n::{Locales, TranslationsManager},
     plugins::{PluginAction, Plugins},
+    session::Sessions,
     state::GlobalStateCreator,
     stores::MutableStore,
     template::TemplateMap,
+    utils::RobotsTxt,
     ErrorPages, Html, SsrNode, Template,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use fmterr::fmt_err;
 use futures::Future;
 #[cfg(target_arch = "wasm32")]
 use std::marker::PhantomData;
 #[cfg(not(target_arch = "wasm32"))]
+use std::path::{Component, Path};
+#[cfg(not(target_arch = "wasm32"))]
 use std::pin::Pin;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
+use std::time::Duration;
 use std::{collections::HashMap, rc::Rc};
 use sycamore::prelude::Scope;
 use sycamore::{
@@ -54,6 +81,36 @@ impl<G: Html> std::fmt::Debug for ErrorPagesGetter<G> {
     }
 }
 
+// This is broken out for debug implementation ease
+#[cfg(not(target_arch = "wasm32"))]
+struct EngineHooks(Vec<(EngineOperation, EngineHookStage, EngineHookFn)>);
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Debug for EngineHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EngineHooks").finish()
+    }
+}
+
+// This is broken out for debug implementation ease
+#[cfg(not(target_arch = "wasm32"))]
+struct ScheduledJobs(Vec<ScheduledJob>);
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Debug for ScheduledJobs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduledJobs").finish()
+    }
+}
+
+// This is broken out for debug implementation ease
+#[cfg(not(target_arch = "wasm32"))]
+struct BaseHead(Option<BaseHeadFn>);
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Debug for BaseHead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BaseHead").finish()
+    }
+}
+
 /// The different types of translations managers that can be stored. This allows
 /// us to store dummy translations managers directly, without holding futures.
 /// If this stores a full translations manager though, it will store it as a
@@ -70,6 +127,52 @@ impl<T: TranslationsManager> std::fmt::Debug for Tm<T> {
     }
 }
 
+/// What the build process should do when generating some of an app's pages
+/// fails (e.g. because a *build state* function returned an error).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildFailurePolicy {
+    /// Stop the build as soon as any page fails. This is the default.
+    FailFast,
+    /// Try to build every page, and then fail the build with a report of
+    /// every page that failed.
+    CollectAll,
+    /// Build every page that can be built, and print a report of those that
+    /// couldn't be, without failing the build. The pages that were skipped
+    /// will be generated when they're first requested, just like pages that
+    /// use incremental generation (unless the app is being exported, in which
+    /// case they'll just be left out).
+    SkipFailed,
+}
+impl Default for BuildFailurePolicy {
+    fn default() -> Self {
+        Self::FailFast
+    }
+}
+
+/// When the browser should start loading an app's Wasm bundle on an initial
+/// load. Until the bundle has loaded, the user will see the prerendered page,
+/// but nothing on it will be interactive, so deferring this is best suited to
+/// content sites where most users never need the app to be live.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmLoadingStrategy {
+    /// Load the bundle as soon as the page has been parsed. This is the
+    /// default.
+    Immediate,
+    /// Load the bundle once the browser is idle (with
+    /// `requestIdleCallback`), so that it doesn't compete with the rest of the
+    /// page for the network and the main thread.
+    Idle,
+    /// Load the bundle when the user first interacts with the page (e.g. by
+    /// clicking, typing, or scrolling). Note that the interaction that starts
+    /// the load won't be handled by the app, since it isn't live yet.
+    Interaction,
+}
+impl Default for WasmLoadingStrategy {
+    fn default() -> Self {
+        Self::Immediate
+    }
+}
+
 /// An automatically implemented trait for asynchronous functions that return
 /// instances of `TranslationsManager`. This is needed so we can store the
 /// 'promise' of getting a translations manager in future by executing a stored
@@ -109,6 +212,10 @@ pub struct PerseusAppBase<G: Html, M: MutableStore, T: TranslationsManager> {
     template_getters: TemplateGetters<G>,
     /// The app's error pages.
     error_pages: ErrorPagesGetter<G>,
+    /// Whether or not the app has been given its own error pages, rather than
+    /// relying on the defaults (which shouldn't be used in production).
+    #[cfg(not(target_arch = "wasm32"))]
+    custom_error_pages: bool,
     /// The global state creator for the app.
     // This is wrapped in an `Arc` so we can pass it around on the engine-side (which is solely for
     // Actix's benefit...)
@@ -129,6 +236,14 @@ pub struct PerseusAppBase<G: Html, M: MutableStore, T: TranslationsManager> {
     /// contain a `<div>` with the `id` set to whatever the value of `self.root`
     /// is.
     index_view: String,
+    /// The path that the root of the app (`/`) should redirect to, if it
+    /// shouldn't be rendered by an `index` template. This has no leading or
+    /// trailing slashes.
+    index_redirect: Option<String>,
+    /// The translations for the app's default locale, embedded in the binary,
+    /// which error pages will be rendered with if the translations for the
+    /// user's locale can't be fetched.
+    embedded_translations: Option<&'static str>,
     /// The app's mutable store.
     #[cfg(not(target_arch = "wasm32"))]
     mutable_store: M,
@@ -138,12 +253,96 @@ pub struct PerseusAppBase<G: Html, M: MutableStore, T: TranslationsManager> {
     /// app initialization in Wasm.
     #[cfg(not(target_arch = "wasm32"))]
     translations_manager: Tm<T>,
+    /// The directory the default translations manager will read translations
+    /// from, if it's being used.
+    #[cfg(not(target_arch = "wasm32"))]
+    translations_dir: Option<String>,
     /// The location of the directory to use for static assets that will placed
     /// under the URL `/.perseus/static/`. By default, this is the `static/`
     /// directory at the root of your project. Note that the directory set
     /// here will only be used if it exists.
     #[cfg(not(target_arch = "wasm32"))]
     static_dir: String,
+    /// The configuration for the app's `robots.txt` file, if it should have
+    /// one.
+    #[cfg(not(target_arch = "wasm32"))]
+    robots_txt: Option<RobotsTxt>,
+    /// Custom logic to run before and after engine operations.
+    #[cfg(not(target_arch = "wasm32"))]
+    engine_hooks: EngineHooks,
+    /// Jobs to run on a schedule while the server is running.
+    #[cfg(not(target_arch = "wasm32"))]
+    scheduled_jobs: ScheduledJobs,
+    /// The incrementally generated pages to pre-render when the server starts.
+    #[cfg(not(target_arch = "wasm32"))]
+    incremental_warmup: IncrementalWarmup,
+    /// How long request-time state functions are given before they're
+    /// cancelled, unless their templates set their own timeouts.
+    #[cfg(not(target_arch = "wasm32"))]
+    state_fn_timeout: Option<Duration>,
+    /// Whether or not the HTML shell should show a boot indicator until the app
+    /// has been hydrated.
+    #[cfg(not(target_arch = "wasm32"))]
+    boot_indicator: bool,
+    /// When the browser should start loading the app's Wasm bundle.
+    #[cfg(not(target_arch = "wasm32"))]
+    wasm_loading: WasmLoadingStrategy,
+    /// Whether or not the app should be rebuilt automatically in development
+    /// if its build artifacts were created by an incompatible version of
+    /// Perseus.
+    #[cfg(not(target_arch = "wasm32"))]
+    rebuild_stale_artifacts: bool,
+    /// Whether or not the files that static aliases point to should be served
+    /// at URLs with hashes of their contents in them.
+    #[cfg(not(target_arch = "wasm32"))]
+    fingerprint_static_aliases: bool,
+    /// How static content should be served.
+    #[cfg(not(target_arch = "wasm32"))]
+    static_content: StaticContentOptions,
+    /// What should be served in maintenance mode.
+    #[cfg(not(target_arch = "wasm32"))]
+    maintenance: MaintenanceOptions,
+    /// The A/B experiments the app is running.
+    #[cfg(not(target_arch = "wasm32"))]
+    experiments: Experiments,
+    /// Whether or not CSRF tokens should be given to users on initial loads.
+    #[cfg(not(target_arch = "wasm32"))]
+    csrf_protection: bool,
+    /// The proxies trusted to report the addresses of clients.
+    #[cfg(not(target_arch = "wasm32"))]
+    trusted_proxies: TrustedProxies,
+    /// The app's session configuration, if sessions are enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    sessions: Option<Sessions>,
+    /// The values provided to the app's state generation functions.
+    #[cfg(not(target_arch = "wasm32"))]
+    state_dependencies: StateDependencies,
+    /// The function that renders the head shared by all the app's templates.
+    #[cfg(not(target_arch = "wasm32"))]
+    base_head: BaseHead,
+    /// The app's template groups, whose shared state is computed once per
+    /// build.
+    #[cfg(not(target_arch = "wasm32"))]
+    template_groups: TemplateGroups,
+    /// What the build process should do if some pages fail to build.
+    #[cfg(not(target_arch = "wasm32"))]
+    build_failure_policy: BuildFailurePolicy,
+    /// A function that will be called with any client-side errors Perseus
+    /// handles internally, and with any panics that occur while rendering
+    /// pages on the server.
+    error_reporter: Option<ErrorReporter>,
+    /// A CSS selector for the element that should be focused after client-side
+    /// navigation (if this isn't set, the first `h1` will be used).
+    #[cfg(target_arch = "wasm32")]
+    focus_target: Option<String>,
+    /// Whether or not translations should be persisted in `localStorage`
+    /// between sessions.
+    #[cfg(target_arch = "wasm32")]
+    persist_translations: bool,
+    /// Whether or not the translations for the other locales the user
+    /// understands should be preloaded after the first page has been rendered.
+    #[cfg(target_arch = "wasm32")]
+    preload_locales: bool,
     // We need this on the client-side to account for the unused type parameters
     #[cfg(target_arch = "wasm32")]
     _marker: PhantomData<(M, T)>,
@@ -169,7 +368,10 @@ impl<G: Html, T: TranslationsManager> PerseusAppBase<G, FsMutableStore, T> {
     #[cfg(not(target_arch = "wasm32"))]
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self::new_with_mutable_store(FsMutableStore::new("./dist/mutable".to_string()))
+        Self::new_with_mutable_store(FsMutableStore::new(format!(
+            "{}/mutable",
+            crate::utils::get_dist_dir()
+        )))
     }
     /// Creates a new instance of a Perseus app using the default
     /// filesystem-based mutable store (see [`FsMutableStore`]). For most apps,
@@ -224,8 +426,10 @@ impl<G: Html, M: MutableStore> PerseusAppBase<G, M, FsTranslationsManager> {
                     crate::i18n::TRANSLATOR_FILE_EXT.to_string(),
                 );
                 self.translations_manager = Tm::Full(Box::pin(tm_fut));
+                self.translations_dir = Some(crate::i18n::DFLT_TRANSLATIONS_DIR.to_string());
             } else {
                 self.translations_manager = Tm::Dummy(FsTranslationsManager::new_dummy());
+                self.translations_dir = None;
             }
         }
 
@@ -272,6 +476,8 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             // building
             error_pages: ErrorPagesGetter(Box::new(ErrorPages::default)),
             #[cfg(not(target_arch = "wasm32"))]
+            custom_error_pages: false,
+            #[cfg(not(target_arch = "wasm32"))]
             global_state_creator: Arc::new(GlobalStateCreator::default()),
             // By default, we'll disable i18n (as much as I may want more websites to support more
             // languages...)
@@ -286,15 +492,67 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             // By default, we won't use any plugins
             plugins: Rc::new(Plugins::new()),
             #[cfg(not(target_arch = "wasm32"))]
-            immutable_store: ImmutableStore::new("./dist".to_string()),
+            immutable_store: ImmutableStore::new(crate::utils::get_dist_dir()),
             #[cfg(not(target_arch = "wasm32"))]
             mutable_store,
             #[cfg(not(target_arch = "wasm32"))]
             translations_manager: Tm::Dummy(T::new_dummy()),
+            #[cfg(not(target_arch = "wasm32"))]
+            translations_dir: None,
             // Many users won't need anything fancy in the index view, so we provide a default
             index_view: DFLT_INDEX_VIEW.to_string(),
+            index_redirect: None,
+            embedded_translations: None,
             #[cfg(not(target_arch = "wasm32"))]
             static_dir: "./static".to_string(),
+            // By default, we won't generate a `robots.txt` file
+            #[cfg(not(target_arch = "wasm32"))]
+            robots_txt: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            engine_hooks: EngineHooks(Vec::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            scheduled_jobs: ScheduledJobs(Vec::new()),
+            // By default, incremental pages will only be generated when they're requested
+            #[cfg(not(target_arch = "wasm32"))]
+            incremental_warmup: IncrementalWarmup::default(),
+            // State functions can take as long as they like by default
+            #[cfg(not(target_arch = "wasm32"))]
+            state_fn_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            boot_indicator: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            wasm_loading: WasmLoadingStrategy::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            rebuild_stale_artifacts: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            fingerprint_static_aliases: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            static_content: StaticContentOptions::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            maintenance: MaintenanceOptions::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            experiments: Experiments::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            csrf_protection: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            trusted_proxies: TrustedProxies::None,
+            #[cfg(not(target_arch = "wasm32"))]
+            sessions: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            state_dependencies: StateDependencies::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            base_head: BaseHead(None),
+            #[cfg(not(target_arch = "wasm32"))]
+            template_groups: TemplateGroups::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            build_failure_policy: BuildFailurePolicy::default(),
+            error_reporter: None,
+            #[cfg(target_arch = "wasm32")]
+            focus_target: None,
+            #[cfg(target_arch = "wasm32")]
+            persist_translations: false,
+            #[cfg(target_arch = "wasm32")]
+            preload_locales: false,
             #[cfg(target_arch = "wasm32")]
             _marker: PhantomData,
         }
@@ -323,6 +581,12 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             plugins: Rc::new(Plugins::new()),
             // Many users won't need anything fancy in the index view, so we provide a default
             index_view: DFLT_INDEX_VIEW.to_string(),
+            index_redirect: None,
+            embedded_translations: None,
+            error_reporter: None,
+            focus_target: None,
+            persist_translations: false,
+            preload_locales: false,
             _marker: PhantomData,
         }
     }
@@ -370,6 +634,10 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     /// Sets the app's error pages. See [`ErrorPages`] for further details.
     pub fn error_pages(mut self, val: impl Fn() -> ErrorPages<G> + 'static) -> Self {
         self.error_pages = ErrorPagesGetter(Box::new(val));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.custom_error_pages = true;
+        }
         self
     }
     /// Sets the app's [`GlobalStateCreator`].
@@ -434,6 +702,8 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
         #[cfg(not(target_arch = "wasm32"))]
         {
             self.translations_manager = Tm::Full(Box::pin(val));
+            // We don't know where a custom translations manager gets its translations from
+            self.translations_dir = None;
         }
         self
     }
@@ -451,6 +721,7 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
         #[cfg(not(target_arch = "wasm32"))]
         {
             self.translations_manager = Tm::Dummy(T::new_dummy());
+            self.translations_dir = None;
         }
         self
     }
@@ -470,6 +741,11 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     /// Adds a single static alias. This takes a URL path (e.g. `/file`)
     /// followed by a path to a resource (which must be within the project
     /// directory, e.g. `style.css`).
+    ///
+    /// To serve a whole directory, end the URL with `/*` and give the path
+    /// to the directory (e.g. `/assets/*` and `assets/`), and everything
+    /// inside it will be served under that URL (e.g. `assets/img/logo.png`
+    /// at `/assets/img/logo.png`).
     #[allow(unused_variables)]
     #[allow(unused_mut)]
     pub fn static_alias(mut self, url: &str, resource: &str) -> Self {
@@ -485,6 +761,400 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
         self.plugins = Rc::new(val);
         self
     }
+    /// Sets the configuration for the app's `robots.txt` file, which will be
+    /// generated at build-time and served at `/robots.txt`. See [`RobotsTxt`]
+    /// for further details.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn robots_txt(mut self, val: RobotsTxt) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.robots_txt = Some(val);
+        }
+        self
+    }
+    /// Sets paths of pages generated with incremental generation that should be
+    /// pre-rendered when the server starts, so that the first visitors to them
+    /// after a restart don't have to wait for them to be generated. These
+    /// should include the locale if your app uses i18n (e.g.
+    /// `en-US/post/foo`).
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn warm_up_incremental_paths(mut self, val: &[&str]) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.incremental_warmup.paths = val.iter().map(|path| path.to_string()).collect();
+        }
+        self
+    }
+    /// Sets the number of the most recently requested pages generated with
+    /// incremental generation that should be pre-rendered when the server
+    /// starts. If this is set, the paths of these pages will be recorded in
+    /// the mutable store as they're requested.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn warm_up_recent_incremental(mut self, val: usize) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.incremental_warmup.recent = val;
+        }
+        self
+    }
+    /// Sets how long the request state, revalidation logic, and state
+    /// amalgamation functions of every template are given on each request
+    /// before they're cancelled (templates can override this with
+    /// `.state_fn_timeout()`). If one of these times out, the stale version of
+    /// the page will be served if there is one, and otherwise the user will
+    /// get the error page for a `504`. This stops one slow upstream API from
+    /// tying up the server. By default, there's no timeout.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn state_fn_timeout(mut self, val: Duration) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.state_fn_timeout = Some(val);
+        }
+        self
+    }
+    /// Sets whether or not a boot indicator should be shown between the
+    /// server-rendered HTML being painted and the app being hydrated (before
+    /// which nothing interactive will work). This adds the `__perseus_booting`
+    /// class to the `<body>` until the first page is interactive, and shows a
+    /// small progress bar at the top of the page while it's there (which you
+    /// can restyle, or build on, with that class). Pages can also check
+    /// `RenderCtx::is_hydrated()` to disable their own controls until then.
+    /// By default, there's no indicator.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn boot_indicator(mut self, val: bool) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.boot_indicator = val;
+        }
+        self
+    }
+    /// Sets when the browser should start loading the app's Wasm bundle on an
+    /// initial load. By default, this happens immediately, but sites that are
+    /// mostly static content can defer it until the browser is idle, or until
+    /// the user first interacts with the page, so that the bundle doesn't slow
+    /// down the first paint (see [`WasmLoadingStrategy`]). Subsequent loads
+    /// are unaffected, since the bundle will already be loaded by then.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn wasm_loading(mut self, strategy: WasmLoadingStrategy) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.wasm_loading = strategy;
+        }
+        self
+    }
+    /// Sets whether or not the app should be rebuilt automatically before
+    /// serving in development if its build artifacts were created by an
+    /// incompatible version of Perseus (e.g. after upgrading). By default, an
+    /// error will be printed instead. This has no effect in production, where
+    /// the artifacts are deployed alongside the server.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn rebuild_stale_artifacts(mut self, val: bool) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.rebuild_stale_artifacts = val;
+        }
+        self
+    }
+    /// Sets whether or not the files that single-file static aliases point to
+    /// should be fingerprinted, which will make them available at URLs with
+    /// hashes of their contents in them as well (e.g. `/logo.png` at
+    /// `/logo.1a2b3c4d5e6f7a8b.png`). These URLs change whenever the files do,
+    /// so browsers can cache them forever. Reference them in templates and
+    /// heads with `asset_url!("logo.png")`, which will resolve to the right URL
+    /// (including the app's path prefix) whether or not this is enabled.
+    ///
+    /// By default, static aliases are not fingerprinted.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn fingerprint_static_aliases(mut self, val: bool) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.fingerprint_static_aliases = val;
+        }
+        self
+    }
+    /// Sets how the static directory and static aliases should be served (e.g.
+    /// custom MIME types, or files that should never be served). See
+    /// [`StaticContentOptions`] for the defaults.
+    ///
+    /// Static content is only served by the engine, so this method only
+    /// exists on the engine-side, and you'll need to gate calls to it with
+    /// `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn static_content(mut self, val: StaticContentOptions) -> Self {
+        self.static_content = val;
+        self
+    }
+    /// Sets the page that should be served in place of every other page when
+    /// the server is in maintenance mode, and how long clients should wait
+    /// before trying again. See [`MaintenanceOptions`] for how to turn
+    /// maintenance mode on.
+    ///
+    /// Maintenance mode is only handled by the engine, so this method only
+    /// exists on the engine-side, and you'll need to gate calls to it with
+    /// `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn maintenance(mut self, val: MaintenanceOptions) -> Self {
+        self.maintenance = val;
+        self
+    }
+    /// Sets what the build process should do if some pages fail to build (see
+    /// [`BuildFailurePolicy`] for the options). By default, the build will
+    /// stop at the first failure, but large sites may prefer to collect every
+    /// failure into one report, or even to deploy the rest of the site and
+    /// generate the failed pages when they're requested.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn build_failure_policy(mut self, val: BuildFailurePolicy) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.build_failure_policy = val;
+        }
+        self
+    }
+    /// Adds an A/B experiment to the app. The server will assign every user a
+    /// variant of it, which will be available to request-state functions and
+    /// templates (see the [`experiments`](crate::experiments) module for
+    /// details). If an experiment with the same name has already been added,
+    /// it will be replaced.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn experiment(mut self, val: Experiment) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.experiments.add(val);
+        }
+        self
+    }
+    /// Sets whether or not users should be given CSRF tokens on initial loads,
+    /// which can be used to protect any mutating endpoints you add to your
+    /// server (see the [`csrf`](crate::csrf) module for details). By default,
+    /// this is disabled, since Perseus doesn't have any mutating endpoints of
+    /// its own.
+    ///
+    /// Note that, since every initial load will embed the user's token, they'll
+    /// all be sent with `Cache-Control: private, no-store` when this is
+    /// enabled.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn csrf_protection(mut self, val: bool) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.csrf_protection = val;
+        }
+        self
+    }
+    /// Sets which proxies in front of your server should be trusted to report
+    /// the addresses of the clients they're forwarding requests for (in
+    /// `X-Forwarded-For`). By default, none are, so the address of every
+    /// client will be the address the server received its request from, which
+    /// will be your proxy's if you have one. Only trust proxies that your
+    /// server can't be reached without going through, otherwise clients will
+    /// be able to claim any address they like.
+    ///
+    /// Requests are only handled by the engine, so this method only exists on
+    /// the engine-side, and you'll need to gate calls to it with
+    /// `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn trusted_proxies(mut self, val: TrustedProxies) -> Self {
+        self.trusted_proxies = val;
+        self
+    }
+    /// Enables cookie-based sessions with the given configuration, which will
+    /// make a session available to every request (see the
+    /// [`session`](crate::session) module for details).
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn sessions(mut self, val: Sessions) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.sessions = Some(val);
+        }
+        self
+    }
+    /// Provides the given value (e.g. a database connection pool) to all the
+    /// app's state generation functions, which can access it with
+    /// [`get_state_dependency`](crate::state::get_state_dependency). Only one
+    /// value of each type can be provided, so you may want to wrap values in
+    /// your own types to distinguish them.
+    ///
+    /// This should be used instead of global statics for anything that needs
+    /// to be shared between requests. Since these values are only relevant on
+    /// the engine-side, you should target-gate calls to this with
+    /// `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn state_dependency<D: std::any::Any + Send + Sync>(mut self, val: D) -> Self {
+        self.state_dependencies.insert(val);
+        self
+    }
+    /// Declares a template group with the given name, whose shared state (e.g.
+    /// a parsed manifest of documentation pages) will be computed by the given
+    /// function. Templates can be added to the group with
+    /// [`Template::group`], and their state generation functions can then
+    /// access this state with
+    /// [`StateGeneratorInfo::group_state`](crate::template::StateGeneratorInfo::group_state).
+    ///
+    /// The state will be computed only once per build, before any of the
+    /// group's templates are built (and, on the server, once when it's first
+    /// needed), no matter how many pages need it. This should be used
+    /// instead of caching expensive computations in global statics, which
+    /// won't work reliably when pages are built in parallel.
+    ///
+    /// Since state generation only happens on the engine-side, you should
+    /// target-gate calls to this with `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn template_group<S, F, Fut>(mut self, name: &str, get_state: F) -> Self
+    where
+        S: std::any::Any + Send + Sync,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = RenderFnResult<S>> + Send + Sync + 'static,
+    {
+        self.template_groups.insert(name, get_state);
+        self
+    }
+    /// Adds a hook that will be run before the given engine operation, which
+    /// is useful for things like running database migrations before serving.
+    /// Hooks are run in the order they were added, and, if any of them fail,
+    /// the operation will be aborted.
+    ///
+    /// Because engine operations only exist on the engine-side, this method
+    /// does too, so you'll need to gate calls to it with
+    /// `#[cfg(not(target_arch = "wasm32"))]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn engine_hook<F, Fut>(self, op: EngineOperation, hook: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = EngineHookResult> + 'static,
+    {
+        self.engine_hook_at(op, EngineHookStage::Before, hook)
+    }
+    /// Adds a hook that will be run after the given engine operation has
+    /// completed successfully, which is useful for things like warming
+    /// caches after a build. For the server, this will be run once it's
+    /// ready, just before it starts listening for requests.
+    ///
+    /// Like `.engine_hook()`, this only exists on the engine-side.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn engine_hook_after<F, Fut>(self, op: EngineOperation, hook: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = EngineHookResult> + 'static,
+    {
+        self.engine_hook_at(op, EngineHookStage::After, hook)
+    }
+    /// Adds a hook that will be run at the given stage of the given engine
+    /// operation. Usually, `.engine_hook()` or `.engine_hook_after()` will be
+    /// more convenient.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn engine_hook_at<F, Fut>(
+        mut self,
+        op: EngineOperation,
+        stage: EngineHookStage,
+        hook: F,
+    ) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = EngineHookResult> + 'static,
+    {
+        self.engine_hooks
+            .0
+            .push((op, stage, Box::new(move || Box::pin(hook()))));
+        self
+    }
+    /// Adds a job that will be run on the given schedule while the server is
+    /// running, in the same process (e.g. revalidating every page overnight,
+    /// or pruning a cache every hour). This saves small apps from needing an
+    /// external cron job to ping an endpoint. Jobs are run one at a time, and,
+    /// if any of them fail, they'll be reported and then tried again at their
+    /// next scheduled time.
+    ///
+    /// When the server is shut down (with `SIGINT` or `SIGTERM`, or if it stops
+    /// by itself), no more jobs will be started, but any job that's running
+    /// will be given up to 30 seconds to finish first. Note that, if you deploy
+    /// several instances of your server, each of them will run every job.
+    ///
+    /// Like `.engine_hook()`, this only exists on the engine-side.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn scheduled_job<F, Fut>(mut self, name: &str, schedule: Schedule, job: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = EngineHookResult> + 'static,
+    {
+        self.scheduled_jobs.0.push(ScheduledJob {
+            name: name.to_string(),
+            schedule,
+            job: Box::new(move || Box::pin(job())),
+        });
+        self
+    }
+    /// Sets a function that will be called with every client-side error that
+    /// Perseus handles internally, including failed fetches of page data
+    /// and translations, and any panics that occur in the browser (which will
+    /// still be logged to the console). This is designed for wiring up an
+    /// external error reporting service.
+    ///
+    /// On the server-side, this will be called with [`ClientError::Panic`] if
+    /// a page panics while it's being rendered (e.g. in a request state
+    /// function), in which case the user will be sent the error page for a
+    /// `500` instead. Note that this will be called on whichever thread was
+    /// handling the request.
+    pub fn error_reporter(mut self, val: impl Fn(&ClientError) + Send + Sync + 'static) -> Self {
+        self.error_reporter = Some(ErrorReporter::new(val));
+        self
+    }
+    /// Sets a CSS selector for the element that should be focused after each
+    /// client-side navigation, so that keyboard and screen reader users start
+    /// from the top of the new page, rather than wherever they were on the
+    /// old one. By default, this is the first `h1` on the page (if there isn't
+    /// one, the page's content as a whole will be focused). If the new URL has
+    /// a hash fragment, the element it targets will be focused instead.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn focus_target(mut self, val: &str) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.focus_target = Some(val.to_string());
+        }
+        self
+    }
+    /// Sets whether or not the translations for each locale should be
+    /// persisted in the browser's `localStorage`, so that they don't have to
+    /// be downloaded again in future sessions (they'll still be checked with
+    /// the server, but it will only send them again if they've changed). By
+    /// default, translations are only cached in memory for the current
+    /// session, which is enough for most apps, but this can save a lot of
+    /// bandwidth for apps with large translations files.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn persist_translations(mut self, val: bool) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.persist_translations = val;
+        }
+        self
+    }
+    /// Sets whether or not the translations for the other locales the user's
+    /// browser says they understand (e.g. their secondary languages) should be
+    /// fetched in the background once the first page has been rendered, so
+    /// that switching to them later is instant. This is disabled by default,
+    /// since most users never switch locales. You can also preload locales
+    /// manually with `.translations_manager.preload()` on the render context.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn preload_locales(mut self, val: bool) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.preload_locales = val;
+        }
+        self
+    }
     /// Sets the [`MutableStore`] for the app to use, which you would change for
     /// some production server environments if you wanted to store build
     /// artifacts that can change at runtime in a place other than on the
@@ -543,6 +1213,59 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
         self.index_view = html_str;
         self
     }
+    /// Sets the base head of the app, which will be merged into the `<head>` of
+    /// every page, so templates don't have to repeat things like default
+    /// Open Graph tags. This will be rendered on the engine-side for every
+    /// page, with the same context as the template's head (so you can use
+    /// `t!` here, for example).
+    ///
+    /// Elements from a template's head take precedence over their equivalents
+    /// in the base head: `<title>`, `<base>`, and `<meta charset>` replace
+    /// their counterparts, `<meta>` tags replace those with the same `name`,
+    /// `property`, or `http-equiv`, and `<link rel="canonical">` replaces
+    /// the base head's one. Everything else in both heads is kept, with the
+    /// base head coming first.
+    ///
+    /// Note that anything that should be in the `<head>` of every page and
+    /// never overridden, like the charset, can also just go in the index
+    /// view.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn base_head(
+        mut self,
+        val: impl Fn(Scope) -> View<SsrNode> + Send + Sync + 'static,
+    ) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.base_head = BaseHead(Some(Arc::new(val)));
+        }
+        self
+    }
+    /// Sets the path that the root of your app (`/`) should redirect to, which
+    /// is useful if you don't have an `index` template (e.g. a documentation
+    /// site that starts at `/docs`). If you're using i18n, this applies to the
+    /// root of each locale (e.g. `/en-US` will redirect to `/en-US/docs`).
+    ///
+    /// This redirect will be performed by the server, in exported apps, and by
+    /// the router for links within your app. If you set this, it will take
+    /// precedence over any `index` template.
+    pub fn index_redirect(mut self, val: &str) -> Self {
+        self.index_redirect = Some(val.trim_matches('/').to_string());
+        self
+    }
+    /// Embeds the given translations for the app's default locale in the app
+    /// itself (both the server and the Wasm bundle), usually with
+    /// `include_str!("../translations/en-US.ftl")`. Error pages will be
+    /// rendered with these whenever the translations for the user's locale
+    /// can't be fetched (e.g. if the error was caused by a network failure),
+    /// meaning they'll always have a translator. This will increase the size
+    /// of your Wasm bundle by the size of the translations, so you may want
+    /// to keep the translations you embed to just those your error pages
+    /// need.
+    pub fn embedded_translations(mut self, val: &'static str) -> Self {
+        self.embedded_translations = Some(val);
+        self
+    }
     // Setters
     /// Gets the HTML ID of the `<div>` at which to insert Perseus.
     pub fn get_root(&self) -> String {
@@ -560,6 +1283,118 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     pub fn get_static_dir(&self) -> String {
         self.static_dir.to_string()
     }
+    /// Runs all the engine hooks registered for the given stage of the given
+    /// engine operation, in the order they were added. This will stop at the
+    /// first hook that fails. The default engine calls this automatically,
+    /// but custom engines will need to call it themselves.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn run_engine_hooks(
+        &self,
+        op: EngineOperation,
+        stage: EngineHookStage,
+    ) -> Result<(), EngineError> {
+        for (hook_op, hook_stage, hook) in self.engine_hooks.0.iter() {
+            if *hook_op == op && *hook_stage == stage {
+                hook().await.map_err(|err| EngineError::EngineHookFailed {
+                    op: format!("{:?}", op),
+                    source: err,
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+    /// Checks if the app has any scheduled jobs.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn has_scheduled_jobs(&self) -> bool {
+        !self.scheduled_jobs.0.is_empty()
+    }
+    /// Runs the app's scheduled jobs until the given future resolves, giving
+    /// any job that's running at that point up to 30 seconds to finish first.
+    /// The default engine runs this alongside the server automatically, but
+    /// custom engines will need to do so themselves.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn run_scheduled_jobs(&self, stop: impl Future<Output = ()>) {
+        crate::engine::run_scheduled_jobs(
+            &self.scheduled_jobs.0,
+            stop,
+            crate::engine::JOB_SHUTDOWN_TIMEOUT,
+        )
+        .await
+    }
+    /// Gets the settings for pre-rendering incrementally generated pages when
+    /// the server starts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_incremental_warmup(&self) -> IncrementalWarmup {
+        self.incremental_warmup.clone()
+    }
+    /// Gets the default timeout for request-time state functions, if there is
+    /// one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_state_fn_timeout(&self) -> Option<Duration> {
+        self.state_fn_timeout
+    }
+    /// Gets whether or not the HTML shell should show a boot indicator.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_boot_indicator(&self) -> bool {
+        self.boot_indicator
+    }
+    /// Gets when the browser should start loading the app's Wasm bundle.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_wasm_loading(&self) -> WasmLoadingStrategy {
+        self.wasm_loading
+    }
+    /// Gets whether or not the app should be rebuilt automatically in
+    /// development if its build artifacts are stale.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_rebuild_stale_artifacts(&self) -> bool {
+        self.rebuild_stale_artifacts
+    }
+    /// Gets whether or not static aliases should be fingerprinted.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_fingerprint_static_aliases(&self) -> bool {
+        self.fingerprint_static_aliases
+    }
+    /// Gets how static content should be served.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_static_content(&self) -> StaticContentOptions {
+        self.static_content.clone()
+    }
+    /// Gets what should be served in maintenance mode.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_maintenance(&self) -> MaintenanceOptions {
+        self.maintenance.clone()
+    }
+    /// Gets what the build process should do if some pages fail to build.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_build_failure_policy(&self) -> BuildFailurePolicy {
+        self.build_failure_policy
+    }
+    /// Gets the A/B experiments the app is running.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_experiments(&self) -> Experiments {
+        self.experiments.clone()
+    }
+    /// Gets whether or not users should be given CSRF tokens.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_csrf_protection(&self) -> bool {
+        self.csrf_protection
+    }
+    /// Gets the proxies the app trusts to report the addresses of clients.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_trusted_proxies(&self) -> TrustedProxies {
+        self.trusted_proxies.clone()
+    }
+    /// Gets the app's session configuration, if sessions are enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_sessions(&self) -> Option<Sessions> {
+        self.sessions.clone()
+    }
+    /// Gets the configuration for the app's `robots.txt` file, if it has one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_robots_txt(&self) -> Option<RobotsTxt> {
+        self.robots_txt.clone()
+    }
     /// Gets the index view as a string, without generating an HTML shell (pass
     /// this into `::get_html_shell()` to do that).
     ///
@@ -592,9 +1427,9 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             root,
             // TODO Handle this properly (good enough for now because that's what we weere already
             // doing)
-            &get_render_cfg(immutable_store)
-                .await
-                .expect("Couldn't get render configuration!"),
+            &get_render_cfg(immutable_store).await.unwrap_or_else(|err| {
+                panic!("couldn't get render configuration: {}", fmt_err(&err))
+            }),
             &get_path_prefix_server(),
         );
 
@@ -671,17 +1506,21 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
                 .collect(),
         );
 
-        html_shell
+        // The shell may have been replaced by a plugin
+        html_shell.prepare()
     }
-    /// Gets the templates in an `Rc`-based `HashMap` for non-concurrent access.
-    pub fn get_templates_map(&self) -> TemplateMap<G> {
-        let mut map = HashMap::new();
-
-        // Now add the templates the user provided
-        for template_getter in self.template_getters.0.iter() {
-            let template = template_getter();
-            map.insert(template.get_path(), Rc::new(template));
-        }
+    /// Gets all the templates in the app, including those added by plugins. On
+    /// the engine-side, this will also give each template the registry of all
+    /// their metadata, so that it can be provided to them when they're
+    /// rendered.
+    fn get_templates(&self) -> Vec<Template<G>> {
+        // Add the templates the user provided
+        let mut templates: Vec<Template<G>> = self
+            .template_getters
+            .0
+            .iter()
+            .map(|template_getter| template_getter())
+            .collect();
 
         // This will return a map of plugin name to a vector of templates to add
         let extra_templates = self
@@ -691,43 +1530,45 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             .add_templates
             .run((), self.plugins.get_plugin_data());
         for (_plugin_name, plugin_templates) in extra_templates {
-            // Turn that vector into a template map by extracting the template root paths as
-            // keys
-            for template in plugin_templates {
-                map.insert(template.get_path(), Rc::new(template));
+            templates.extend(plugin_templates);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let route_registry = RouteRegistry::new(
+                templates
+                    .iter()
+                    .map(|template| (template.get_path(), template.get_meta()))
+                    .collect(),
+                LocalizedPaths::from_templates(templates.iter()),
+            );
+            for template in templates.iter_mut() {
+                template.set_route_registry(route_registry.clone());
+                template.set_dependencies(self.state_dependencies.clone());
+                template.set_template_groups(self.template_groups.clone());
+                template.set_base_head(self.base_head.0.clone());
             }
         }
 
-        map
+        templates
+    }
+    /// Gets the templates in an `Rc`-based `HashMap` for non-concurrent access.
+    pub fn get_templates_map(&self) -> TemplateMap<G> {
+        // Turn the templates into a template map by extracting the template root paths
+        // as keys
+        self.get_templates()
+            .into_iter()
+            .map(|template| (template.get_path(), Rc::new(template)))
+            .collect()
     }
     /// Gets the templates in an `Arc`-based `HashMap` for concurrent access.
     /// This should only be relevant on the server-side.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn get_atomic_templates_map(&self) -> crate::template::ArcTemplateMap<G> {
-        let mut map = HashMap::new();
-
-        // Now add the templates the user provided
-        for template_getter in self.template_getters.0.iter() {
-            let template = template_getter();
-            map.insert(template.get_path(), std::sync::Arc::new(template));
-        }
-
-        // This will return a map of plugin name to a vector of templates to add
-        let extra_templates = self
-            .plugins
-            .functional_actions
-            .settings_actions
-            .add_templates
-            .run((), self.plugins.get_plugin_data());
-        for (_plugin_name, plugin_templates) in extra_templates {
-            // Turn that vector into a template map by extracting the template root paths as
-            // keys
-            for template in plugin_templates {
-                map.insert(template.get_path(), std::sync::Arc::new(template));
-            }
-        }
-
-        map
+        self.get_templates()
+            .into_iter()
+            .map(|template| (template.get_path(), std::sync::Arc::new(template)))
+            .collect()
     }
     /// Gets the [`ErrorPages`] used in the app. This returns an `Rc`.
     pub fn get_error_pages(&self) -> ErrorPages<G> {
@@ -752,7 +1593,9 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     pub fn get_global_state_creator(&self) -> Arc<GlobalStateCreator> {
         self.global_state_creator.clone()
     }
-    /// Gets the locales information.
+    /// Gets the locales information. This is all the locales the app supports,
+    /// which might not all have been built (the render configuration records
+    /// which were).
     pub fn get_locales(&self) -> Locales {
         let locales = self.locales.clone();
         self.plugins
@@ -762,6 +1605,17 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
             .run(locales.clone(), self.plugins.get_plugin_data())
             .unwrap_or(locales)
     }
+    /// Gets the locales that should be built, which are all the app's locales,
+    /// unless only some of them are being built (see
+    /// [`get_locale_filter`](crate::i18n::get_locale_filter)).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_locales_to_build(&self) -> Locales {
+        let locales = self.get_locales();
+        match crate::i18n::get_locale_filter() {
+            Some(only) => locales.restrict_to(&only),
+            None => locales,
+        }
+    }
     /// Gets the server-side [`TranslationsManager`]. Like the mutable store,
     /// this can't be modified by plugins due to trait complexities.
     ///
@@ -792,6 +1646,38 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
     pub fn get_mutable_store(&self) -> M {
         self.mutable_store.clone()
     }
+    /// Gets the [`ErrorReporter`] for the app, if one has been set.
+    pub fn get_error_reporter(&self) -> Option<ErrorReporter> {
+        self.error_reporter.clone()
+    }
+    /// Gets the path that the root of the app should redirect to, if one has
+    /// been set (without any leading or trailing slashes).
+    pub fn get_index_redirect(&self) -> Option<String> {
+        self.index_redirect.clone()
+    }
+    /// Gets the translations for the app's default locale that it has
+    /// embedded, if it has any.
+    pub fn get_embedded_translations(&self) -> Option<&'static str> {
+        self.embedded_translations
+    }
+    /// Gets the CSS selector for the element to focus after client-side
+    /// navigation, if one has been set.
+    #[cfg(target_arch = "wasm32")]
+    pub fn get_focus_target(&self) -> Option<String> {
+        self.focus_target.clone()
+    }
+    /// Gets whether or not translations should be persisted in `localStorage`
+    /// between sessions.
+    #[cfg(target_arch = "wasm32")]
+    pub fn get_persist_translations(&self) -> bool {
+        self.persist_translations
+    }
+    /// Gets whether or not the translations for the other locales the user
+    /// understands should be preloaded.
+    #[cfg(target_arch = "wasm32")]
+    pub fn get_preload_locales(&self) -> bool {
+        self.preload_locales
+    }
     /// Gets the plugins registered for the app. These are passed around and
     /// used in a way that doesn't require them to be concurrently accessible,
     /// and so are provided in an `Rc`.
@@ -824,31 +1710,144 @@ impl<G: Html, M: MutableStore, T: TranslationsManager> PerseusAppBase<G, M, T> {
 
         let mut scoped_static_aliases = HashMap::new();
         for (url, path) in static_aliases {
-            // We need to move this from being scoped to the app to being scoped for
-            // `.perseus/` TODO Make sure this works properly on Windows (seems
-            // to..)
-            let new_path = if path.starts_with('/') {
-                // Absolute paths are a security risk and are disallowed
-                // The reason for this is that they could point somewhere completely different
-                // on a production server (like an alias to `/etc/passwd`)
-                // Allowing these would also inevitably cause head-scratching in production,
-                // it's much easier to disallow these
-                panic!(
-                    "it's a security risk to include absolute paths in `static_aliases` ('{}'), please make this relative to the project directory",
-                    path
-                );
-            } else if path.starts_with("../") {
-                // Anything outside this directory is a security risk as well
-                panic!("it's a security risk to include paths outside the current directory in `static_aliases` ('{}')", path);
-            } else {
-                path.to_string()
-            };
+            // Absolute paths are a security risk and are disallowed
+            // The reason for this is that they could point somewhere completely different
+            // on a production server (like an alias to `/etc/passwd`)
+            // Allowing these would also inevitably cause head-scratching in production,
+            // it's much easier to disallow these
+            // Anything outside this directory is a security risk as well
+            if is_unsafe_static_alias(&path) {
+                panic!("it's a security risk to include absolute paths or paths outside the current directory in `static_aliases` ('{}'), please make this relative to the project directory", path);
+            }
 
-            scoped_static_aliases.insert(url, new_path);
+            scoped_static_aliases.insert(url, path);
+        }
+        // A generated `robots.txt` file takes precedence over any alias for it
+        if self.robots_txt.is_some() {
+            scoped_static_aliases.remove("/robots.txt");
         }
 
         scoped_static_aliases
     }
+    /// Checks the app's configuration for inconsistencies that would otherwise
+    /// only cause problems later (often not until the first request),
+    /// returning all the problems found. Each of these explains how to fix it.
+    ///
+    /// This is run automatically whenever the engine starts, and by `perseus
+    /// check`. The translations directory will be looked for relative to the
+    /// current directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn validate(&self) -> Vec<AppConfigError> {
+        let mut errors = Vec::new();
+
+        let paths = self
+            .get_templates()
+            .iter()
+            .map(|template| template.get_path())
+            .collect::<Vec<_>>();
+        for path in get_duplicates(paths.clone()) {
+            errors.push(AppConfigError::DuplicateTemplatePath { path });
+        }
+
+        if let Err(profile) = crate::utils::BuildProfile::from_env() {
+            errors.push(AppConfigError::UnknownProfile { profile });
+        }
+
+        let locales = self.get_locales();
+        if locales.using_i18n {
+            for locale in get_duplicates(locales.get_all()) {
+                errors.push(AppConfigError::DuplicateLocale {
+                    locale: locale.to_string(),
+                });
+            }
+            for locale in crate::i18n::get_locale_filter().unwrap_or_default() {
+                if !locales.is_supported(&locale) {
+                    errors.push(AppConfigError::UnknownFilteredLocale { locale });
+                }
+            }
+            if let Tm::Dummy(_) = self.translations_manager {
+                errors.push(AppConfigError::NoTranslationsManager);
+            }
+            if let Some(dir) = &self.translations_dir {
+                if !std::path::Path::new(dir).is_dir() {
+                    errors.push(AppConfigError::NoTranslationsDir {
+                        dir: dir.to_string(),
+                    });
+                }
+            }
+        }
+
+        // The default error pages are only meant for development
+        if !cfg!(debug_assertions) && !self.custom_error_pages {
+            errors.push(AppConfigError::NoErrorPages);
+        }
+
+        if self.index_redirect.is_none() && !paths.iter().any(|path| path == "index") {
+            errors.push(AppConfigError::NoIndexTemplate);
+        }
+
+        // These are the same forms the HTML shell looks for
+        let index_view = self.get_index_view_str();
+        let root = self.get_root();
+        if !index_view.contains(&format!("<div id=\"{}\">", root))
+            && !index_view.contains(&format!("<div id='{}'>", root))
+        {
+            errors.push(AppConfigError::NoRootElement { root });
+        }
+
+        for template in self.get_templates() {
+            if let Some(group) = template.get_group() {
+                if !self.template_groups.contains(group) {
+                    errors.push(AppConfigError::UnknownTemplateGroup {
+                        template: template.get_path(),
+                        group: group.to_string(),
+                    });
+                }
+            }
+        }
+
+        let mut static_aliases = self.static_aliases.iter().collect::<Vec<_>>();
+        static_aliases.sort();
+        for (url, path) in static_aliases {
+            if is_unsafe_static_alias(path) {
+                errors.push(AppConfigError::UnsafeStaticAlias {
+                    url: url.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        }
+
+        errors
+    }
+}
+
+/// Checks if the given static alias path could point outside the project
+/// directory, which it would if it were absolute or if it went up a directory
+/// anywhere. Backslashes are treated as separators on every platform, so
+/// aliases are accepted or rejected the same way everywhere.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_unsafe_static_alias(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let path = Path::new(&path);
+    path.has_root()
+        || path
+            .components()
+            .any(|part| matches!(part, Component::ParentDir | Component::Prefix(_)))
+}
+
+/// Gets the items that appear more than once in the given list (each only
+/// once).
+#[cfg(not(target_arch = "wasm32"))]
+fn get_duplicates<T: Ord + Clone>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    let mut duplicates = Vec::new();
+    for pair in items.windows(2) {
+        if pair[0] == pair[1] && duplicates.last() != Some(&pair[0]) {
+            duplicates.push(pair[0].clone());
+        }
+    }
+
+    duplicates
 }
 
 /// The component that represents the entrypoint at which Perseus will inject
@@ -879,3 +1878,90 @@ pub type PerseusAppWithTranslationsManager<G, T> = PerseusAppBase<G, FsMutableSt
 /// mutable store and a custom translations manager. Alternatively, you could
 /// just use [`PerseusAppBase`] directly.
 pub type PerseusAppWithMutableStoreAndTranslationsManager<G, M, T> = PerseusAppBase<G, M, T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let app = PerseusApp::<SsrNode>::new()
+            .template(|| Template::new("about"))
+            .template(|| Template::new("about"))
+            .template(|| Template::new("index"))
+            .template(|| Template::new("docs").group("docs"))
+            .locales("en-US", &["fr-FR", "en-US"])
+            .index_view_str("<html><body><div id=\"app\"></div></body></html>");
+        let errors = app.validate();
+
+        assert!(errors.iter().any(
+            |err| matches!(err, AppConfigError::DuplicateTemplatePath { path } if path == "about")
+        ));
+        assert!(errors.iter().any(
+            |err| matches!(err, AppConfigError::DuplicateLocale { locale } if locale == "en-US")
+        ));
+        assert!(errors
+            .iter()
+            .any(|err| matches!(err, AppConfigError::NoTranslationsManager)));
+        assert!(errors
+            .iter()
+            .any(|err| matches!(err, AppConfigError::NoRootElement { .. })));
+        assert!(errors.iter().any(
+            |err| matches!(err, AppConfigError::UnknownTemplateGroup { group, .. } if group == "docs")
+        ));
+        assert!(PerseusApp::<SsrNode>::new()
+            .template(|| Template::new("index"))
+            .error_pages(ErrorPages::default)
+            .validate()
+            .is_empty());
+    }
+
+    #[test]
+    fn validate_requires_error_pages_in_release() {
+        let missing = |app: PerseusApp<SsrNode>| {
+            app.validate()
+                .iter()
+                .any(|err| matches!(err, AppConfigError::NoErrorPages))
+        };
+
+        let app = PerseusApp::<SsrNode>::new().template(|| Template::new("index"));
+        assert_eq!(missing(app), !cfg!(debug_assertions));
+        let app = PerseusApp::<SsrNode>::new()
+            .template(|| Template::new("index"))
+            .error_pages(ErrorPages::default);
+        assert!(!missing(app));
+    }
+
+    #[test]
+    fn validate_requires_index_or_redirect() {
+        let app = PerseusApp::<SsrNode>::new().template(|| Template::new("docs"));
+        assert!(app
+            .validate()
+            .iter()
+            .any(|err| matches!(err, AppConfigError::NoIndexTemplate)));
+        assert!(app.index_redirect("/docs/").validate().is_empty());
+    }
+
+    #[test]
+    fn unsafe_static_aliases() {
+        for path in [
+            "/etc/passwd",
+            "../secret",
+            "static/../../secret",
+            "..\\secret",
+            "\\secret",
+        ] {
+            assert!(is_unsafe_static_alias(path), "{}", path);
+        }
+        #[cfg(windows)]
+        assert!(is_unsafe_static_alias("C:\\secret"));
+        for path in [
+            "static/style.css",
+            "./static",
+            "static\\style.css",
+            "..style.css",
+        ] {
+            assert!(!is_unsafe_static_alias(path), "{}", path);
+        }
+    }
+}
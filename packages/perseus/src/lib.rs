@@ -11,6 +11,9 @@
 - `idb-freezing` --- enables utilities for freezing your app's state to IndexedDB in the browser (see the book)
 - `live-reload` (default) --- enables reloading the browser automatically when you make changes to your app
 - `hsr` (default) --- enables *hot state reloading*, which reloads the state of your app right before you made code changes in development, allowing you to pick up where you left off
+- `redis-store` --- enables a [`MutableStore`](crate::stores::MutableStore) that uses Redis, which lets multiple server instances share cached pages
+- `data-sources` --- enables adapters for fetching state from REST and GraphQL APIs (see the `data` module)
+- `syntax-highlighting` --- enables highlighting code at build-time, so no highlighter has to be sent to the browser (see the `highlight` module)
 
 ## Packages
 
@@ -23,6 +26,9 @@ documentation, and this should mostly be used as a secondary reference source. Y
 #![forbid(unsafe_code)]
 #![recursion_limit = "256"] // TODO Do we need this anymore?
 
+pub mod csrf;
+#[cfg(all(feature = "data-sources", not(target_arch = "wasm32")))]
+pub mod data;
 /// Utilities for working with the engine-side, particularly with regards to
 /// setting up the entrypoint for your app's build/export/server processes.
 #[cfg(not(target_arch = "wasm32"))]
@@ -30,6 +36,9 @@ pub mod engine;
 /// Utilities surrounding [`ErrorPages`] and their management.
 pub mod error_pages;
 pub mod errors;
+pub mod experiments;
+#[cfg(all(feature = "syntax-highlighting", not(target_arch = "wasm32")))]
+pub mod highlight;
 /// Utilities for internationalization, the process of making your app available
 /// in multiple languages.
 pub mod i18n;
@@ -42,6 +51,7 @@ pub mod router;
 /// are intended for use by those developing new server integrations.
 #[cfg(not(target_arch = "wasm32"))]
 pub mod server;
+pub mod session;
 /// Utilities for working with Perseus' state platform.
 pub mod state;
 /// Utilities for working with immutable and mutable stores. See
@@ -52,6 +62,7 @@ pub mod stores;
 pub mod template;
 /// General utilities that may be useful while building Perseus apps.
 pub mod utils;
+pub mod web;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod build;
@@ -81,15 +92,17 @@ pub type Request = HttpRequest<()>;
 pub use perseus_macro::{
     amalgamate_states, browser, browser_main, build_paths, build_state, engine, engine_main,
     global_build_state, head, main, main_export, make_rx, request_state, set_headers,
-    should_revalidate, template, template_rx, test,
+    should_revalidate, template, template_rx, test, ReactiveState,
 };
 pub use sycamore::prelude::{DomNode, Html, HydrateNode, SsrNode};
 pub use sycamore_router::{navigate, navigate_replace};
 
 // All the items that should be available at the top-level for convenience
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::template::StateGeneratorInfo;
 pub use crate::{
     error_pages::ErrorPages,
-    errors::{ErrorCause, GenericErrorWithCause},
+    errors::{AppError, ErrorCause, GenericErrorWithCause, Redirect},
     init::*,
     template::{RenderFnResult, RenderFnResultWithCause, Template},
 };
@@ -11,4 +11,8 @@ pub struct PageData {
     pub state: Option<String>,
     /// The string to interpolate into the document's `<head>`.
     pub head: String,
+    /// The location the page redirects to, if it does. If this is set, none of
+    /// the other properties will be, and the app should navigate there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<String>,
 }
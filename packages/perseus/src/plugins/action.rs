@@ -2,14 +2,14 @@ use std::any::Any;
 use std::collections::HashMap;
 
 /// A runner function, which takes action data and plugin data.
-pub type Runner<A, R> = Box<dyn Fn(&A, &(dyn Any + Send)) -> R + Send>;
+pub type Runner<A, R> = Box<dyn Fn(&A, &(dyn Any + Send + Sync)) -> R + Send + Sync>;
 
 /// A trait for the interface for a plugin action, which abstracts whether it's
 /// a functional or a control action.
-pub trait PluginAction<A, R, R2>: Send {
+pub trait PluginAction<A, R, R2>: Send + Sync {
     /// Runs the action. This takes data that the action should expect, along
     /// with a map of plugins to their data.
-    fn run(&self, action_data: A, plugin_data: &HashMap<String, Box<dyn Any + Send>>) -> R2;
+    fn run(&self, action_data: A, plugin_data: &HashMap<String, Box<dyn Any + Send + Sync>>) -> R2;
     /// Registers a plugin that takes this action.
     ///
     /// # Panics
@@ -21,7 +21,7 @@ pub trait PluginAction<A, R, R2>: Send {
     fn register_plugin(
         &mut self,
         name: &str,
-        runner: impl Fn(&A, &(dyn Any + Send)) -> R + Send + 'static,
+        runner: impl Fn(&A, &(dyn Any + Send + Sync)) -> R + Send + Sync + 'static,
     );
     /// Same as `.register_plugin()`, but takes a prepared runner in a `Box`.
     fn register_plugin_box(&mut self, name: &str, runner: Runner<A, R>);
@@ -15,7 +15,11 @@ pub struct ControlPluginAction<A, R> {
 }
 impl<A, R> PluginAction<A, R, Option<R>> for ControlPluginAction<A, R> {
     /// Runs the single registered runner for the action.
-    fn run(&self, action_data: A, plugin_data: &HashMap<String, Box<dyn Any + Send>>) -> Option<R> {
+    fn run(
+        &self,
+        action_data: A,
+        plugin_data: &HashMap<String, Box<dyn Any + Send + Sync>>,
+    ) -> Option<R> {
         // If no runner is defined, this won't have any effect (same as functional
         // actions with no registered runners)
         self.runner.as_ref().map(|runner| {
@@ -34,7 +38,7 @@ impl<A, R> PluginAction<A, R, Option<R>> for ControlPluginAction<A, R> {
     fn register_plugin(
         &mut self,
         name: &str,
-        runner: impl Fn(&A, &(dyn Any + Send)) -> R + Send + 'static,
+        runner: impl Fn(&A, &(dyn Any + Send + Sync)) -> R + Send + Sync + 'static,
     ) {
         self.register_plugin_box(name, Box::new(runner))
     }
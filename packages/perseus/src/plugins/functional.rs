@@ -1,10 +1,16 @@
 use super::*;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::errors::EngineError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::page_data::PageData;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::server::PageRequestInfo;
 use crate::Html;
 use std::any::Any;
 use std::collections::HashMap;
 #[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
 use std::rc::Rc;
 
 /// An action which can be taken by many plugins. When run, a functional action
@@ -17,7 +23,7 @@ impl<A, R> PluginAction<A, R, HashMap<String, R>> for FunctionalPluginAction<A,
     fn run(
         &self,
         action_data: A,
-        plugin_data: &HashMap<String, Box<dyn Any + Send>>,
+        plugin_data: &HashMap<String, Box<dyn Any + Send + Sync>>,
     ) -> HashMap<String, R> {
         let mut returns = HashMap::new();
         for (plugin_name, runner) in &self.runners {
@@ -36,7 +42,7 @@ impl<A, R> PluginAction<A, R, HashMap<String, R>> for FunctionalPluginAction<A,
     fn register_plugin(
         &mut self,
         name: &str,
-        runner: impl Fn(&A, &(dyn Any + Send)) -> R + Send + 'static,
+        runner: impl Fn(&A, &(dyn Any + Send + Sync)) -> R + Send + Sync + 'static,
     ) {
         self.register_plugin_box(name, Box::new(runner))
     }
@@ -44,6 +50,63 @@ impl<A, R> PluginAction<A, R, HashMap<String, R>> for FunctionalPluginAction<A,
         self.runners.insert(name.to_string(), runner);
     }
 }
+impl<T> FunctionalPluginAction<T, T> {
+    /// Runs this action as a pipeline, where each plugin is given the output of
+    /// the plugin before it (the first is given the provided action data), and
+    /// the output of the last plugin is returned. Plugins are run in
+    /// alphabetical order of their names, so that the output is deterministic.
+    /// This is used for actions that transform something, rather than
+    /// contributing to it.
+    pub fn run_chained(
+        &self,
+        action_data: T,
+        plugin_data: &HashMap<String, Box<dyn Any + Send + Sync>>,
+    ) -> T {
+        let mut plugin_names: Vec<&String> = self.runners.keys().collect();
+        plugin_names.sort();
+
+        let mut data = action_data;
+        for plugin_name in plugin_names {
+            let runner = &self.runners[plugin_name];
+            data = runner(
+                &data,
+                // We must have data registered for every active plugin (even if it's empty)
+                &**plugin_data.get(plugin_name).unwrap_or_else(|| {
+                    panic!("no plugin data for registered plugin {}", plugin_name)
+                }),
+            );
+        }
+
+        data
+    }
+}
+impl<C: Clone, T> FunctionalPluginAction<(C, T), T> {
+    /// The same as `.run_chained()`, except each plugin is also given some
+    /// context alongside the data it's transforming, which it can't modify.
+    pub fn run_chained_with_context(
+        &self,
+        context: C,
+        action_data: T,
+        plugin_data: &HashMap<String, Box<dyn Any + Send + Sync>>,
+    ) -> T {
+        let mut plugin_names: Vec<&String> = self.runners.keys().collect();
+        plugin_names.sort();
+
+        let mut data = action_data;
+        for plugin_name in plugin_names {
+            let runner = &self.runners[plugin_name];
+            data = runner(
+                &(context.clone(), data),
+                // We must have data registered for every active plugin (even if it's empty)
+                &**plugin_data.get(plugin_name).unwrap_or_else(|| {
+                    panic!("no plugin data for registered plugin {}", plugin_name)
+                }),
+            );
+        }
+
+        data
+    }
+}
 // Using a default implementation allows us to avoid the action data having to
 // implement `Default` as well, which is frequently infeasible
 impl<A, R> Default for FunctionalPluginAction<A, R> {
@@ -78,6 +141,13 @@ pub struct FunctionalPluginActions<G: Html> {
     /// If your plugin uses this action in a way that may confuse other plugins,
     /// you should note this in your documentation.
     pub tinker: FunctionalPluginAction<(), ()>,
+    /// Declares structured modifications to the user's project, like
+    /// dependencies to add and snippets of code to inject, which the CLI will
+    /// validate and apply after all `tinker` actions have been run. Unlike
+    /// `tinker`, these will compose cleanly with the modifications of other
+    /// plugins, so this should be preferred wherever possible. See
+    /// [`TinkerEdits`] for further details.
+    pub tinker_edits: FunctionalPluginAction<(), TinkerEdits>,
     /// Actions pertaining to the modification of settings created with
     /// `PerseusApp`.
     pub settings_actions: FunctionalPluginSettingsActions<G>,
@@ -99,6 +169,7 @@ impl<G: Html> Default for FunctionalPluginActions<G> {
     fn default() -> Self {
         Self {
             tinker: FunctionalPluginAction::default(),
+            tinker_edits: FunctionalPluginAction::default(),
             settings_actions: FunctionalPluginSettingsActions::<G>::default(),
             #[cfg(not(target_arch = "wasm32"))]
             build_actions: FunctionalPluginBuildActions::default(),
@@ -214,6 +285,19 @@ pub struct FunctionalPluginExportActions {
     /// this is a tuple of the from and to locations of the copy, along with the
     /// error.
     pub after_failed_static_alias_file_copy: FunctionalPluginAction<Rc<EngineError>, ()>,
+    /// Transforms the HTML shell that exported pages will be interpolated into,
+    /// before any interpolation takes place. This is given the shell as a
+    /// string, and must return the new shell. Each plugin will be given the
+    /// output of the one before it. This is useful for injecting things like
+    /// analytics scripts, fonts, or `<meta>` tags into every page.
+    pub transform_html_shell: FunctionalPluginAction<String, String>,
+    /// Runs after the export process has finished writing files, if it
+    /// completes successfully. This is given the path to the export directory,
+    /// and the paths of all the files in it (including static files). This is
+    /// useful for post-processing, like minification, precomputing
+    /// compressed files, or uploading to a host. This runs before
+    /// `after_successful_export`.
+    pub after_export: FunctionalPluginAction<(PathBuf, Vec<PathBuf>), ()>,
     /// Runs after the export process if it completes successfully.
     pub after_successful_export: FunctionalPluginAction<(), ()>,
     /// Runs after the export process if it failed to generate global state.
@@ -241,6 +325,24 @@ pub struct FunctionalPluginServerActions {
     /// has been appropriately set for a standalone binary vs running in the
     /// development environment (inside `.perseus/`).
     pub before_serve: FunctionalPluginAction<(), ()>,
+    /// Transforms the HTML shell that pages will be interpolated into by the
+    /// server, before any interpolation takes place. This is given the shell
+    /// as a string, and must return the new shell. Each plugin will be given
+    /// the output of the one before it. This is useful for injecting things
+    /// like analytics scripts, fonts, or `<meta>` tags into every page.
+    pub transform_html_shell: FunctionalPluginAction<String, String>,
+    /// Runs before the server renders a page (or fetches it from a store), on
+    /// both initial and subsequent loads, with information about the request.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub before_page_render: FunctionalPluginAction<PageRequestInfo, ()>,
+    /// Runs after the server has rendered a page (or fetched it from a store),
+    /// on both initial and subsequent loads, with information about the
+    /// request and the page's data. This must return the page data to
+    /// actually use, and each plugin will be given the output of the one
+    /// before it. This is useful for post-processing the HTML of every page
+    /// (e.g. rewriting images to be lazily loaded).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub after_page_render: FunctionalPluginAction<(PageRequestInfo, PageData), PageData>,
 }
 /// Functional actions that pertain to the client-side code. These in particular
 /// should be as fast as possible.
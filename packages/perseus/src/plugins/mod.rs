@@ -3,12 +3,14 @@ mod control;
 mod functional;
 mod plugin;
 mod plugins_list;
+mod tinker;
 
 pub use action::{PluginAction, Runner};
 pub use control::*;
 pub use functional::*;
 pub use plugin::{Plugin, PluginEnv};
 pub use plugins_list::Plugins;
+pub use tinker::{TinkerDependency, TinkerEdits, TinkerSnippet, TinkerTarget};
 
 /// A helper function for plugins that don't take any functional actions. This
 /// just inserts and empty registrar.
@@ -26,7 +26,7 @@ pub enum PluginEnv {
 
 /// A Perseus plugin. This must be exported by all plugin crates so the user can
 /// register the plugin easily.
-pub struct Plugin<G: Html, D: Any + Send> {
+pub struct Plugin<G: Html, D: Any + Send + Sync> {
     /// The machine name of the plugin, which will be used as a key in a HashMap
     /// with many other plugins. This should be the public crate name in all
     /// cases.
@@ -42,7 +42,7 @@ pub struct Plugin<G: Html, D: Any + Send> {
 
     plugin_data_type: PhantomData<D>,
 }
-impl<G: Html, D: Any + Send> std::fmt::Debug for Plugin<G, D> {
+impl<G: Html, D: Any + Send + Sync> std::fmt::Debug for Plugin<G, D> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Plugin")
             .field("name", &self.name)
@@ -50,7 +50,7 @@ impl<G: Html, D: Any + Send> std::fmt::Debug for Plugin<G, D> {
             .finish()
     }
 }
-impl<G: Html, D: Any + Send> Plugin<G, D> {
+impl<G: Html, D: Any + Send + Sync> Plugin<G, D> {
     /// Creates a new plugin with a name, functional actions, control actions,
     /// and whether or not the plugin is tinker-only.
     pub fn new(
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+/// The part of an app that a dependency added by a tinker plugin should be
+/// available in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TinkerTarget {
+    /// The dependency will be available on both the engine-side and the
+    /// browser-side (i.e. under `[dependencies]`).
+    Both,
+    /// The dependency will only be available on the engine-side.
+    Engine,
+    /// The dependency will only be available on the browser-side.
+    Browser,
+}
+
+/// A Cargo dependency that a tinker plugin wants added to the user's project.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TinkerDependency {
+    /// The name of the crate.
+    pub name: String,
+    /// The version requirement for the crate (e.g. `1.0`).
+    pub version: String,
+    /// Any features of the crate that should be enabled.
+    pub features: Vec<String>,
+    /// Where the dependency should be available.
+    pub target: TinkerTarget,
+}
+
+/// A snippet of code that a tinker plugin wants injected into a file in the
+/// user's project. Snippets can only be injected at explicit injection points,
+/// which are comments of the form `// perseus-tinker: <point>` that the user
+/// has placed in their code, and the snippet will be inserted on the line
+/// after the injection point.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TinkerSnippet {
+    /// The path to the file to inject the snippet into, relative to the root
+    /// of the project.
+    pub file: String,
+    /// The name of the injection point in that file.
+    pub point: String,
+    /// The code to inject.
+    pub content: String,
+}
+
+/// A structured set of modifications that a plugin wants to make to the user's
+/// project at tinker-time, returned from the `tinker_edits` functional
+/// action. Unlike the arbitrary modifications plugins can make in the `tinker`
+/// action, these are collected from every plugin and then validated and
+/// applied by the CLI, which means plugins can compose their modifications
+/// instead of overwriting each other's. Applying these is idempotent, so
+/// `perseus tinker` can safely be run many times.
+///
+/// If two plugins require the same dependency with different versions, the
+/// CLI will fail with an error, but any features they require will be merged.
+/// The CLI will also refuse to apply edits that try to inject snippets into
+/// files outside the project (e.g. with absolute paths or `..`), or that try
+/// to enable features `perseus` doesn't have.
+/// Snippets injected at the same point by multiple plugins will be inserted
+/// in alphabetical order of the plugins' names.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TinkerEdits {
+    /// Cargo dependencies to add to the project.
+    pub dependencies: Vec<TinkerDependency>,
+    /// Features of the `perseus` crate to enable.
+    pub perseus_features: Vec<String>,
+    /// Snippets of code to inject into the project.
+    pub snippets: Vec<TinkerSnippet>,
+}
+impl TinkerEdits {
+    /// Creates a new, empty set of tinker edits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a dependency that will be available on both the engine-side and
+    /// the browser-side.
+    pub fn dependency(self, name: &str, version: &str, features: &[&str]) -> Self {
+        self.dependency_for(TinkerTarget::Both, name, version, features)
+    }
+    /// Adds a dependency that will only be available in the given target.
+    pub fn dependency_for(
+        mut self,
+        target: TinkerTarget,
+        name: &str,
+        version: &str,
+        features: &[&str],
+    ) -> Self {
+        self.dependencies.push(TinkerDependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            target,
+        });
+        self
+    }
+    /// Enables the given feature of the `perseus` crate.
+    pub fn perseus_feature(mut self, feature: &str) -> Self {
+        self.perseus_features.push(feature.to_string());
+        self
+    }
+    /// Injects the given code at the given injection point in the given file
+    /// (relative to the root of the project).
+    pub fn snippet(mut self, file: &str, point: &str, content: &str) -> Self {
+        self.snippets.push(TinkerSnippet {
+            file: file.to_string(),
+            point: point.to_string(),
+            content: content.to_string(),
+        });
+        self
+    }
+}
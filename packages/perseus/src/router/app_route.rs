@@ -1,6 +1,5 @@
-use super::{match_route, RouteVerdict};
+use super::{get_index_redirect, match_route, RenderCfg, RouteVerdict};
 use crate::{i18n::Locales, template::TemplateMap, Html};
-use std::collections::HashMap;
 use sycamore_router::Route;
 
 /// The Perseus route system, which implements Sycamore `Route`, but adds
@@ -11,11 +10,13 @@ pub(crate) struct PerseusRoute<G: Html> {
     /// routing logic).
     pub verdict: RouteVerdict<G>,
     /// The app's render configuration.
-    pub render_cfg: HashMap<String, String>,
+    pub render_cfg: RenderCfg,
     /// The templates the app is using.
     pub templates: TemplateMap<G>,
     /// The app's i18n configuration.
     pub locales: Locales,
+    /// The path the root of the app should redirect to, if there is one.
+    pub index_redirect: Option<String>,
 }
 // Sycamore would only use this if we were processing dynamic routes, which
 // we're not In other words, it's fine that these values would break everything
@@ -24,13 +25,14 @@ impl<G: Html> Default for PerseusRoute<G> {
     fn default() -> Self {
         Self {
             verdict: RouteVerdict::NotFound,
-            render_cfg: HashMap::default(),
+            render_cfg: RenderCfg::default(),
             templates: TemplateMap::default(),
             locales: Locales {
                 default: String::default(),
                 other: Vec::default(),
                 using_i18n: bool::default(),
             },
+            index_redirect: None,
         }
     }
 }
@@ -42,12 +44,39 @@ impl<G: Html> PerseusRoute<G> {
 }
 impl<G: Html> Route for PerseusRoute<G> {
     fn match_route(&self, path: &[&str]) -> Self {
-        let verdict = match_route(path, &self.render_cfg, &self.templates, &self.locales);
+        // We navigate with query strings and hash fragments intact (so that they
+        // make it into the browser's URL), but Sycamore doesn't separate them from
+        // the path, so we strip them here
+        let mut stripped_path = Vec::new();
+        for segment in path {
+            match segment.find(|c| c == '?' || c == '#') {
+                Some(idx) => {
+                    if idx > 0 {
+                        stripped_path.push(&segment[..idx]);
+                    }
+                    break;
+                }
+                None => stripped_path.push(*segment),
+            }
+        }
+        let index_redirect = self.index_redirect.as_ref().and_then(|index_redirect| {
+            get_index_redirect(&stripped_path, &self.locales, index_redirect)
+        });
+        let verdict = match index_redirect {
+            Some(path) => RouteVerdict::IndexRedirect(path),
+            None => match_route(
+                &stripped_path,
+                &self.render_cfg,
+                &self.templates,
+                &self.locales,
+            ),
+        };
         Self {
             verdict,
             render_cfg: self.render_cfg.clone(),
             templates: self.templates.clone(),
             locales: self.locales.clone(),
+            index_redirect: self.index_redirect.clone(),
         }
     }
 }
@@ -0,0 +1,120 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::template::Template;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Html;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The localized versions of the root paths of an app's templates (e.g.
+/// `a-propos` for the `about` template in `fr-FR`), which are declared with
+/// `.localized_path()` on each template. Pages are always built and stored
+/// under their templates' actual root paths, and these are only used to
+/// translate between those and the paths users see in each locale.
+///
+/// This is stored in the render configuration for the router, and in the
+/// [`RouteRegistry`](crate::template::RouteRegistry) for the `link!` macro.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalizedPaths(BTreeMap<String, BTreeMap<String, String>>);
+impl LocalizedPaths {
+    /// Collects the localized paths declared by the given templates.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_templates<'a, G: Html>(
+        templates: impl Iterator<Item = &'a Template<G>>,
+    ) -> Self {
+        let mut localized_paths = Self::default();
+        for template in templates {
+            for (locale, localized_root) in template.get_localized_paths() {
+                localized_paths
+                    .0
+                    .entry(locale.to_string())
+                    .or_default()
+                    .insert(template.get_path(), localized_root.to_string());
+            }
+        }
+
+        localized_paths
+    }
+    /// Whether or not any templates have localized paths.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Converts the given path (without the locale) into the form users will
+    /// see in the given locale, replacing the root path of the template it's
+    /// under with its localized version, if it has one. A leading `/`, query
+    /// string, and hash will be left alone.
+    pub fn localize(&self, path: &str, locale: &str) -> String {
+        self.replace_root(path, locale, false)
+    }
+    /// Converts the given path (without the locale) that a user has requested
+    /// in the given locale back into the actual path of the page, undoing
+    /// [`Self::localize`].
+    pub fn delocalize(&self, path: &str, locale: &str) -> String {
+        self.replace_root(path, locale, true)
+    }
+    /// Replaces the most specific root path in the given locale that the given
+    /// path is under with its counterpart (the localized root if `reverse` is
+    /// `false`, and the template's actual root if it's `true`).
+    fn replace_root(&self, path: &str, locale: &str, reverse: bool) -> String {
+        let (slash, path) = match path.strip_prefix('/') {
+            Some(path) => ("/", path),
+            None => ("", path),
+        };
+        let replacement = self.0.get(locale).and_then(|roots| {
+            roots
+                .iter()
+                .map(|(root, localized_root)| match reverse {
+                    false => (root, localized_root),
+                    true => (localized_root, root),
+                })
+                .filter(|(from, _)| is_under_root(path, from))
+                // The most specific root wins
+                .max_by_key(|(from, _)| from.len())
+        });
+
+        match replacement {
+            Some((from, to)) => format!("{}{}{}", slash, to, &path[from.len()..]),
+            None => format!("{}{}", slash, path),
+        }
+    }
+}
+
+/// Checks if the given path is the given root path, or is under it.
+fn is_under_root(path: &str, root: &str) -> bool {
+    !root.is_empty()
+        && path.starts_with(root)
+        && matches!(
+            path[root.len()..].chars().next(),
+            None | Some('/' | '?' | '#')
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn localizes_template_roots() {
+        let mut localized_paths = LocalizedPaths::default();
+        let mut fr = BTreeMap::new();
+        fr.insert("about".to_string(), "a-propos".to_string());
+        fr.insert("docs/guide".to_string(), "documentation/guide".to_string());
+        localized_paths.0.insert("fr-FR".to_string(), fr);
+
+        assert_eq!(localized_paths.localize("about", "fr-FR"), "a-propos");
+        assert_eq!(
+            localized_paths.localize("/about/team?x=1", "fr-FR"),
+            "/a-propos/team?x=1"
+        );
+        assert_eq!(
+            localized_paths.localize("docs/guide/intro", "fr-FR"),
+            "documentation/guide/intro"
+        );
+        assert_eq!(localized_paths.localize("about", "en-US"), "about");
+        assert_eq!(localized_paths.localize("aboutus", "fr-FR"), "aboutus");
+        assert_eq!(
+            localized_paths.delocalize("a-propos/team", "fr-FR"),
+            "about/team"
+        );
+        assert_eq!(localized_paths.delocalize("about", "fr-FR"), "about");
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use sycamore::prelude::RcSignal;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+
+/// Updates the query parameter and hash fragment signals in the render context
+/// from the browser's current URL. The signals will only be set if their values
+/// have actually changed, so this can be called liberally.
+pub(crate) fn update_location_signals(
+    query_params_signal: &RcSignal<HashMap<String, String>>,
+    hash_signal: &RcSignal<String>,
+) {
+    let location = web_sys::window().unwrap().location();
+    let query_params = parse_query_string(&location.search().unwrap());
+    if *query_params_signal.get_untracked() != query_params {
+        query_params_signal.set(query_params);
+    }
+    let hash = location.hash().unwrap();
+    let hash = hash.strip_prefix('#').unwrap_or(&hash).to_string();
+    if *hash_signal.get_untracked() != hash {
+        hash_signal.set(hash);
+    }
+}
+
+/// Gets the browser's current URL, without its origin (i.e. the path, query
+/// string, and hash fragment).
+pub(crate) fn get_browser_url() -> String {
+    let location = web_sys::window().unwrap().location();
+    format!(
+        "{}{}{}",
+        location.pathname().unwrap(),
+        location.search().unwrap(),
+        location.hash().unwrap()
+    )
+}
+
+/// Scrolls to the element targeted by the hash fragment of the browser's
+/// current URL, if there is one. Browsers will try to do this themselves on the
+/// initial load, but Perseus injects page content after that, and the router
+/// moves between pages without the browser's involvement, so this has to be
+/// done manually once the page is interactive.
+pub(crate) fn scroll_to_hash() {
+    if let Some(target) = get_hash_target() {
+        target.scroll_into_view();
+    }
+}
+
+/// Moves focus to the start of a newly loaded page, so that keyboard and screen
+/// reader users aren't left at their position on the old one. If the URL has a
+/// hash fragment, the element it targets will be focused, and otherwise the
+/// element matching the given selector (by default the first `h1`). If nothing
+/// matches, the page's content as a whole will be focused.
+pub(crate) fn focus_new_page(target: Option<&str>) {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let elem = get_hash_target()
+        .or_else(|| {
+            document
+                .query_selector(target.unwrap_or("h1"))
+                .ok()
+                .flatten()
+        })
+        .or_else(|| document.get_element_by_id("__perseus_content_rx"));
+    if let Some(elem) = elem {
+        focus_element(&elem);
+    }
+}
+
+/// Focuses the given element, making it focusable first if it isn't already
+/// (without adding it to the tab order).
+pub(crate) fn focus_element(elem: &Element) {
+    let natively_focusable = matches!(
+        elem.tag_name().to_lowercase().as_str(),
+        "a" | "button" | "input" | "select" | "textarea"
+    );
+    if !natively_focusable && !elem.has_attribute("tabindex") {
+        let _ = elem.set_attribute("tabindex", "-1");
+    }
+    if let Some(elem) = elem.dyn_ref::<HtmlElement>() {
+        let _ = elem.focus();
+    }
+}
+
+/// Gets the element targeted by the hash fragment of the browser's current URL,
+/// if there is one.
+fn get_hash_target() -> Option<Element> {
+    let window = web_sys::window().unwrap();
+    let hash = window.location().hash().unwrap();
+    let id = match hash.strip_prefix('#') {
+        Some(id) if !id.is_empty() => id,
+        _ => return None,
+    };
+    let id = urlencoding::decode(id)
+        .map(|id| id.to_string())
+        .unwrap_or_else(|_| id.to_string());
+    let document = window.document().unwrap();
+    // As in the browser, we fall back to anchors with the given name
+    match document.get_element_by_id(&id) {
+        Some(elem) => Some(elem),
+        None => document
+            .get_elements_by_name(&id)
+            .get(0)
+            .map(|elem| elem.unchecked_into()),
+    }
+}
+
+/// Parses the given query string (which may have a leading `?`) into a map of
+/// keys to decoded values. Any pairs that can't be decoded will be ignored.
+fn parse_query_string(query: &str) -> HashMap<String, String> {
+    let query = query.strip_prefix('?').unwrap_or(query);
+    let mut params = HashMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, val) = pair.split_once('=').unwrap_or((pair, ""));
+        // Spaces are encoded as `+` in query strings, which `urlencoding` doesn't
+        // handle
+        let key = urlencoding::decode(&key.replace('+', " ")).map(|key| key.to_string());
+        let val = urlencoding::decode(&val.replace('+', " ")).map(|val| val.to_string());
+        if let (Ok(key), Ok(val)) = (key, val) {
+            params.insert(key, val);
+        }
+    }
+
+    params
+}
@@ -1,14 +1,13 @@
-use super::{RouteInfo, RouteInfoAtomic, RouteVerdict, RouteVerdictAtomic};
+use super::{RenderCfg, RouteInfo, RouteInfoAtomic, RouteVerdict, RouteVerdictAtomic};
 use crate::i18n::Locales;
 use crate::template::{ArcTemplateMap, Template, TemplateMap};
 use crate::Html;
-use std::collections::HashMap;
 use std::rc::Rc;
 
 /// The backend for `get_template_for_path` to avoid code duplication for the
 /// `Arc` and `Rc` versions.
 macro_rules! get_template_for_path {
-    ($raw_path:expr, $render_cfg:expr, $templates:expr) => {{
+    ($raw_path:expr, $locale:expr, $render_cfg:expr, $templates:expr) => {{
         let mut path = $raw_path;
         // If the path is empty, we're looking for the special `index` page
         if path.is_empty() {
@@ -19,7 +18,7 @@ macro_rules! get_template_for_path {
         // Match the path to one of the templates
         let mut template_name = String::new();
         // We'll try a direct match first
-        if let Some(template_root_path) = $render_cfg.get(path) {
+        if let Some(template_root_path) = $render_cfg.get(path, $locale) {
             template_name = template_root_path.to_string();
         }
         // Next, an ISR match (more complex), which we only want to run if we didn't get
@@ -34,7 +33,7 @@ macro_rules! get_template_for_path {
                 let path_to_try = path_segments[0..(idx + 1)].join("/") + "/*";
 
                 // If we find something, keep going until we don't (maximise specificity)
-                if let Some(template_root_path) = $render_cfg.get(&path_to_try) {
+                if let Some(template_root_path) = $render_cfg.get(&path_to_try, $locale) {
                     was_incremental_match = true;
                     template_name = template_root_path.to_string();
                 } else {
@@ -53,12 +52,13 @@ macro_rules! get_template_for_path {
     }};
 }
 
-/// Determines the template to use for the given path by checking against the
-/// render configuration, also returning whether we matched a simple page or an
-/// incrementally-generated one (`true` for incrementally generated). Note that
-/// simple pages include those on incrementally-generated templates that we
-/// pre-rendered with *build paths* at build-time (and are hence in an immutable
-/// store rather than a mutable store).
+/// Determines the template to use for the given path in the given locale by
+/// checking against the render configuration, also returning whether we
+/// matched a simple page or an incrementally-generated one (`true` for
+/// incrementally generated). Note that simple pages include those on
+/// incrementally-generated templates that we pre-rendered with *build paths* at
+/// build-time (and are hence in an immutable store rather than a mutable
+/// store).
 ///
 /// This houses the central routing algorithm of Perseus, which is based fully
 /// on the fact that we know about every single page except those rendered with
@@ -70,11 +70,12 @@ macro_rules! get_template_for_path {
 /// this function.
 pub fn get_template_for_path<G: Html>(
     raw_path: &str,
-    render_cfg: &HashMap<String, String>,
+    locale: &str,
+    render_cfg: &RenderCfg,
     templates: &TemplateMap<G>,
 ) -> (Option<Rc<Template<G>>>, bool) {
     let (template_name, was_incremental_match) =
-        get_template_for_path!(raw_path, render_cfg, templates);
+        get_template_for_path!(raw_path, locale, render_cfg, templates);
 
     (
         templates.get(&template_name).cloned(),
@@ -94,11 +95,12 @@ pub fn get_template_for_path<G: Html>(
 /// this function.
 pub fn get_template_for_path_atomic<'a, G: Html>(
     raw_path: &str,
-    render_cfg: &HashMap<String, String>,
+    locale: &str,
+    render_cfg: &RenderCfg,
     templates: &'a ArcTemplateMap<G>,
 ) -> (Option<&'a Template<G>>, bool) {
     let (template_name, was_incremental_match) =
-        get_template_for_path!(raw_path, render_cfg, templates);
+        get_template_for_path!(raw_path, locale, render_cfg, templates);
 
     (
         templates
@@ -108,6 +110,32 @@ pub fn get_template_for_path_atomic<'a, G: Html>(
     )
 }
 
+/// Works out where the given path should be redirected to if the app has an
+/// index redirect (set with `.index_redirect()` on `PerseusApp`), returning
+/// the path to redirect to (without the path prefix or a leading slash), or
+/// `None` if this path isn't the root of the app. The path this takes should be
+/// split into segments as for `match_route`.
+///
+/// When i18n is being used, the root of each locale will be redirected, and the
+/// root of the whole app will be left to the locale detector (which will send
+/// the user to the root of a locale, which will then be redirected).
+///
+/// *Note:* in the vast majority of cases, you should never need to use
+/// this function.
+pub fn get_index_redirect(
+    path_slice: &[&str],
+    locales: &Locales,
+    index_redirect: &str,
+) -> Option<String> {
+    match path_slice {
+        [] if !locales.using_i18n => Some(index_redirect.to_string()),
+        [locale] if locales.using_i18n && locales.is_supported(locale) => {
+            Some(format!("{}/{}", locale, index_redirect))
+        }
+        _ => None,
+    }
+}
+
 /// Matches the given path to a `RouteVerdict`. This takes a `TemplateMap` to
 /// match against, the render configuration to index, and it needs to know if
 /// i18n is being used. The path this takes should be raw, it may or may not
@@ -118,7 +146,7 @@ pub fn get_template_for_path_atomic<'a, G: Html>(
 /// this function.
 pub fn match_route<G: Html>(
     path_slice: &[&str],
-    render_cfg: &HashMap<String, String>,
+    render_cfg: &RenderCfg,
     templates: &TemplateMap<G>,
     locales: &Locales,
 ) -> RouteVerdict<G> {
@@ -135,10 +163,13 @@ pub fn match_route<G: Html>(
         if locales.is_supported(locale) {
             // We'll assume this has already been i18ned (if one of your routes has the same
             // name as a supported locale, ffs)
-            let path_without_locale = path_slice[1..].to_vec().join("/");
+            // Any localized root path is converted back into the template's actual one
+            let path_without_locale = render_cfg
+                .localized_paths
+                .delocalize(&path_slice[1..].join("/"), locale);
             // Get the template to use
             let (template, was_incremental_match) =
-                get_template_for_path(&path_without_locale, render_cfg, templates);
+                get_template_for_path(&path_without_locale, locale, render_cfg, templates);
             verdict = match template {
                 Some(template) => RouteVerdict::Found(RouteInfo {
                     locale: locale.to_string(),
@@ -164,7 +195,7 @@ pub fn match_route<G: Html>(
     } else {
         // Get the template to use
         let (template, was_incremental_match) =
-            get_template_for_path(&path_joined, render_cfg, templates);
+            get_template_for_path(&path_joined, &locales.default, render_cfg, templates);
         verdict = match template {
             Some(template) => RouteVerdict::Found(RouteInfo {
                 locale: locales.default.to_string(),
@@ -187,7 +218,7 @@ pub fn match_route<G: Html>(
 /// this function.
 pub fn match_route_atomic<'a, G: Html>(
     path_slice: &[&str],
-    render_cfg: &HashMap<String, String>,
+    render_cfg: &RenderCfg,
     templates: &'a ArcTemplateMap<G>,
     locales: &Locales,
 ) -> RouteVerdictAtomic<'a, G> {
@@ -204,10 +235,13 @@ pub fn match_route_atomic<'a, G: Html>(
         if locales.is_supported(locale) {
             // We'll assume this has already been i18ned (if one of your routes has the same
             // name as a supported locale, ffs)
-            let path_without_locale = path_slice[1..].to_vec().join("/");
+            // Any localized root path is converted back into the template's actual one
+            let path_without_locale = render_cfg
+                .localized_paths
+                .delocalize(&path_slice[1..].join("/"), locale);
             // Get the template to use
             let (template, was_incremental_match) =
-                get_template_for_path_atomic(&path_without_locale, render_cfg, templates);
+                get_template_for_path_atomic(&path_without_locale, locale, render_cfg, templates);
             verdict = match template {
                 Some(template) => RouteVerdictAtomic::Found(RouteInfoAtomic {
                     locale: locale.to_string(),
@@ -233,7 +267,7 @@ pub fn match_route_atomic<'a, G: Html>(
     } else {
         // Get the template to use
         let (template, was_incremental_match) =
-            get_template_for_path_atomic(&path_joined, render_cfg, templates);
+            get_template_for_path_atomic(&path_joined, &locales.default, render_cfg, templates);
         verdict = match template {
             Some(template) => RouteVerdictAtomic::Found(RouteInfoAtomic {
                 locale: locales.default.to_string(),
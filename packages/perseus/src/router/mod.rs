@@ -1,17 +1,39 @@
 #[cfg(target_arch = "wasm32")]
 mod app_route;
+mod localized_paths;
+#[cfg(target_arch = "wasm32")]
+mod location;
 mod match_route;
+mod navigation_guard;
+mod progress_bar;
+mod render_cfg;
+mod route_path;
 mod route_verdict;
 #[cfg(target_arch = "wasm32")]
 mod router_component;
 mod router_state;
+mod skip_link;
 
 #[cfg(target_arch = "wasm32")]
 pub(crate) use app_route::PerseusRoute;
+pub use localized_paths::LocalizedPaths;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use location::{
+    focus_element, focus_new_page, get_browser_url, scroll_to_hash, update_location_signals,
+};
 pub use match_route::{
-    get_template_for_path, get_template_for_path_atomic, match_route, match_route_atomic,
+    get_index_redirect, get_template_for_path, get_template_for_path_atomic, match_route,
+    match_route_atomic,
 };
+pub(crate) use navigation_guard::NavigationGuards;
+pub use navigation_guard::{NavigationGuardFn, NavigationGuardVerdict, PendingNavigation};
+pub use progress_bar::{RouterProgressBar, RouterProgressBarProps};
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use render_cfg::hash_bytes;
+pub use render_cfg::{BuildRedirects, RenderCfg, TemplateRenderCfg, RENDER_CFG_VERSION};
+pub use route_path::RoutePath;
 pub use route_verdict::{RouteInfo, RouteInfoAtomic, RouteVerdict, RouteVerdictAtomic};
 #[cfg(target_arch = "wasm32")]
 pub(crate) use router_component::{perseus_router, PerseusRouterProps};
 pub use router_state::{RouterLoadState, RouterState};
+pub use skip_link::{SkipLink, SkipLinkProps};
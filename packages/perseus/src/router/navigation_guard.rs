@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The type of functions that can be used as navigation guards. These are
+/// given the details of a navigation that's about to take place, and return a
+/// verdict on whether or not it should be allowed to proceed.
+pub type NavigationGuardFn = Rc<dyn Fn(&PendingNavigation) -> NavigationGuardVerdict>;
+
+/// The details of a client-side navigation that the router is about to perform,
+/// which navigation guards can use to decide whether or not it should be
+/// allowed.
+#[derive(Clone, Debug)]
+pub struct PendingNavigation {
+    /// The full path of the page being navigated away from (including the
+    /// locale, if we're using i18n).
+    pub from: String,
+    /// The full URL of the page being navigated away from, as it appears in
+    /// the browser (including the path prefix, query string, and hash
+    /// fragment).
+    pub from_url: String,
+    /// The full path of the page being navigated to (including the locale, if
+    /// we're using i18n).
+    pub to: String,
+    /// The name of the template of the page being navigated to.
+    pub template_name: String,
+}
+
+/// What a navigation guard has decided should happen to a pending navigation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NavigationGuardVerdict {
+    /// The navigation should go ahead as usual.
+    Proceed,
+    /// The navigation should be abandoned, leaving the user on the page they
+    /// were on before.
+    Cancel,
+    /// The user should be sent to the given path instead. This is relative to
+    /// the root of your app, in the same way as a path you'd give to
+    /// `navigate()`.
+    Redirect(String),
+}
+
+/// The global navigation guards that have been registered through the render
+/// context. These are identified by name so that pages, which may be
+/// rendered many times, can register their guards without duplicating them.
+/// This can be cheaply cloned.
+#[derive(Clone, Default)]
+pub(crate) struct NavigationGuards(Rc<RefCell<Vec<(String, NavigationGuardFn)>>>);
+impl std::fmt::Debug for NavigationGuards {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self
+            .0
+            .borrow()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        f.debug_tuple("NavigationGuards").field(&names).finish()
+    }
+}
+impl NavigationGuards {
+    /// Adds the given guard, replacing any existing guard with the same name.
+    pub(crate) fn add(&self, name: String, guard: NavigationGuardFn) {
+        let mut guards = self.0.borrow_mut();
+        match guards.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(entry) => entry.1 = guard,
+            None => guards.push((name, guard)),
+        }
+    }
+    /// Removes the guard with the given name, if it exists.
+    pub(crate) fn remove(&self, name: &str) {
+        self.0.borrow_mut().retain(|(existing, _)| existing != name);
+    }
+    /// Runs all the registered guards in the order they were added, returning
+    /// the verdict of the first one that doesn't allow the navigation to
+    /// proceed.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn run(&self, pending: &PendingNavigation) -> NavigationGuardVerdict {
+        // We clone the guards out so that they can add or remove guards themselves
+        // without us holding a borrow
+        let guards: Vec<NavigationGuardFn> = self
+            .0
+            .borrow()
+            .iter()
+            .map(|(_, guard)| guard.clone())
+            .collect();
+        for guard in guards {
+            match guard(pending) {
+                NavigationGuardVerdict::Proceed => continue,
+                verdict => return verdict,
+            }
+        }
+
+        NavigationGuardVerdict::Proceed
+    }
+}
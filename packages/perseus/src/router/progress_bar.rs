@@ -0,0 +1,58 @@
+use crate::router::RouterLoadState;
+use crate::template::RenderCtx;
+use crate::Html;
+use sycamore::prelude::{component, create_memo, view, Scope, View};
+use sycamore::Prop;
+
+// As with the route announcer, we don't want to bring in a styling library, so
+// these are inlined
+const PROGRESS_BAR_STYLES: &str = r#"
+    position: fixed;
+    top: 0;
+    left: 0;
+    z-index: 9999;
+    margin: 0;
+    padding: 0;
+    border: 0;
+    pointer-events: none;
+    transition: width 0.2s ease, opacity 0.4s ease;
+"#;
+
+/// The properties for [`RouterProgressBar`].
+#[derive(Prop, Debug)]
+pub struct RouterProgressBarProps {
+    /// The CSS color of the progress bar. By default, this is `#3b82f6`.
+    #[builder(default = "#3b82f6".to_string())]
+    pub color: String,
+    /// The CSS height of the progress bar. By default, this is `3px`.
+    #[builder(default = "3px".to_string())]
+    pub height: String,
+}
+
+/// A thin progress bar fixed to the top of the page, which will fill as the
+/// router loads a new page, and then fade out once that page is ready. This is
+/// driven entirely by the router's [`RouterLoadState`], and can be placed
+/// anywhere in your templates (it'll render as invisible on the server-side).
+///
+/// If you want more control over how navigation feedback is displayed, you can
+/// derive your own state from `.router.get_load_state()` on the render context.
+#[component]
+#[allow(non_snake_case)]
+pub fn RouterProgressBar<G: Html>(cx: Scope, props: RouterProgressBarProps) -> View<G> {
+    let load_state = RenderCtx::from_ctx(cx).router.get_load_state(cx);
+    let style = create_memo(cx, move || {
+        let (width, opacity) = match &*load_state.get() {
+            RouterLoadState::Loading { progress, .. } => (progress * 100.0, 1),
+            // Once the page has loaded, we fill the bar and fade it out
+            _ => (100.0, 0),
+        };
+        format!(
+            "{}width: {}%; opacity: {}; height: {}; background-color: {};",
+            PROGRESS_BAR_STYLES, width, opacity, props.height, props.color
+        )
+    });
+
+    view! { cx,
+        div(id = "__perseus_progress_bar", role = "progressbar", style = style.get()) {}
+    }
+}
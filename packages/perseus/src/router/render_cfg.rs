@@ -0,0 +1,342 @@
+use super::LocalizedPaths;
+use crate::errors::{Redirect, RenderCfgError};
+use crate::i18n::Locales;
+use crate::utils::BuildProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the format of the render configuration. This is written into
+/// every render configuration at build-time, and it's also compiled into the
+/// Wasm bundle, so that a bundle and a server built by incompatible versions of
+/// Perseus can be detected, rather than leading to strange routing errors. This
+/// must be incremented whenever the format of [`RenderCfg`] changes.
+pub const RENDER_CFG_VERSION: u32 = 7;
+
+/// The characteristics of how a single template is rendered, which are recorded
+/// in the render configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateRenderCfg {
+    /// Whether or not the template generates its pages with build paths.
+    pub build_paths: bool,
+    /// Whether or not the template generates state at build-time.
+    pub build_state: bool,
+    /// Whether or not the template generates state at request-time.
+    pub request_state: bool,
+    /// Whether or not the template can generate new pages incrementally.
+    pub incremental: bool,
+    /// Whether or not the template's pages are ever revalidated.
+    pub revalidates: bool,
+}
+
+/// The pages whose build state functions redirected elsewhere at build-time, as
+/// a map of locales to maps of the pages' paths to their redirects. The server
+/// keeps redirecting these pages until the next build.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildRedirects(BTreeMap<String, BTreeMap<String, Redirect>>);
+impl BuildRedirects {
+    /// Records that the given page redirected elsewhere at build-time.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn insert(&mut self, path: String, locale: &str, redirect: Redirect) {
+        self.0
+            .entry(locale.to_string())
+            .or_default()
+            .insert(path, redirect);
+    }
+    /// Gets the redirect the given page (without its locale) returned at
+    /// build-time in the given locale, if it did.
+    pub fn get(&self, path: &str, locale: &str) -> Option<&Redirect> {
+        self.0.get(locale).and_then(|pages| pages.get(path))
+    }
+}
+
+/// The render configuration of an app, which maps every page the app knows
+/// about to the template that renders it. This is generated at build-time and
+/// sent to the browser by the server (it's the basis of Perseus' routing
+/// algorithm).
+///
+/// This includes a checksum of its contents, which is validated whenever it's
+/// loaded.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderCfg {
+    /// The version of the format this render configuration is in.
+    pub version: u32,
+    /// A checksum of the rest of the render configuration.
+    pub checksum: String,
+    /// Whether or not the app uses i18n.
+    pub using_i18n: bool,
+    /// A map of page paths to the root paths of the templates that render them,
+    /// for every page that exists in all the app's locales. Templates that use
+    /// incremental generation will have an entry like `template/*`.
+    pub pages: BTreeMap<String, String>,
+    /// Any pages that only exist in some of the app's locales (because their
+    /// templates generate different build paths for each locale), as a map of
+    /// locales to maps like `pages`.
+    pub localized_pages: BTreeMap<String, BTreeMap<String, String>>,
+    /// The render characteristics of each template, by their root paths.
+    pub templates: BTreeMap<String, TemplateRenderCfg>,
+    /// A map of the URLs of static aliases to the fingerprinted URLs they
+    /// should be referenced by, if the app fingerprints its static aliases.
+    pub assets: BTreeMap<String, String>,
+    /// The localized versions of the root paths of the app's templates, which
+    /// the router translates requested paths through.
+    pub localized_paths: LocalizedPaths,
+    /// The pages that redirected elsewhere at build-time.
+    pub redirects: BuildRedirects,
+    /// The locales the app was built for, which might only be some of the ones
+    /// it supports (if it was built with `--locales`). This will be empty if
+    /// the app doesn't use i18n.
+    pub locales: Vec<String>,
+    /// The profile the app was built with, which the server will keep using
+    /// for state generated at request-time.
+    pub profile: BuildProfile,
+}
+impl RenderCfg {
+    /// Creates a new render configuration in the current format from the pages
+    /// generated for each locale, computing its checksum. Pages that were
+    /// generated for every locale will be stored once, and the rest will be
+    /// stored under their locales.
+    pub fn new(
+        mut locale_pages: BTreeMap<String, BTreeMap<String, String>>,
+        templates: BTreeMap<String, TemplateRenderCfg>,
+        assets: BTreeMap<String, String>,
+        using_i18n: bool,
+    ) -> Self {
+        let mut pages = BTreeMap::new();
+        if let Some(first) = locale_pages.values().next() {
+            for (path, template) in first.iter() {
+                if locale_pages
+                    .values()
+                    .all(|other| other.get(path) == Some(template))
+                {
+                    pages.insert(path.clone(), template.clone());
+                }
+            }
+        }
+        for other in locale_pages.values_mut() {
+            other.retain(|path, _| !pages.contains_key(path));
+        }
+        locale_pages.retain(|_, other| !other.is_empty());
+
+        let mut render_cfg = Self {
+            version: RENDER_CFG_VERSION,
+            checksum: String::new(),
+            using_i18n,
+            pages,
+            localized_pages: locale_pages,
+            templates,
+            assets,
+            localized_paths: LocalizedPaths::default(),
+            redirects: BuildRedirects::default(),
+            locales: Vec::new(),
+            profile: BuildProfile::default(),
+        };
+        render_cfg.checksum = render_cfg.compute_checksum();
+        render_cfg
+    }
+    /// Sets the localized versions of the root paths of the app's templates,
+    /// recomputing the checksum.
+    pub fn with_localized_paths(mut self, localized_paths: LocalizedPaths) -> Self {
+        self.localized_paths = localized_paths;
+        self.checksum = self.compute_checksum();
+        self
+    }
+    /// Sets the pages that redirected elsewhere at build-time, recomputing the
+    /// checksum.
+    pub fn with_redirects(mut self, redirects: BuildRedirects) -> Self {
+        self.redirects = redirects;
+        self.checksum = self.compute_checksum();
+        self
+    }
+    /// Sets the locales the app was built for, recomputing the checksum.
+    pub fn with_locales(mut self, locales: Vec<String>) -> Self {
+        self.locales = locales;
+        self.checksum = self.compute_checksum();
+        self
+    }
+    /// Sets the profile the app was built with, recomputing the checksum.
+    pub fn with_profile(mut self, profile: BuildProfile) -> Self {
+        self.profile = profile;
+        self.checksum = self.compute_checksum();
+        self
+    }
+    /// Gets the given locales of the app, restricted to those it was built
+    /// for. Both the server and the app should use these, so that users are
+    /// never sent to locales that weren't built.
+    pub fn built_locales(&self, locales: Locales) -> Locales {
+        match locales.using_i18n {
+            true => locales.restrict_to(&self.locales),
+            false => locales,
+        }
+    }
+    /// Parses a render configuration from JSON, making sure it's in the format
+    /// this version of Perseus expects and that it passes its integrity check.
+    pub fn from_json(json: &str) -> Result<Self, RenderCfgError> {
+        let value = serde_json::from_str::<serde_json::Value>(json)
+            .map_err(|err| RenderCfgError::Invalid { source: err })?;
+        // Render configurations from before this format was introduced were just a map
+        // of pages, so they're treated as the zeroth version
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .unwrap_or(0);
+        if version != RENDER_CFG_VERSION as u64 {
+            return Err(RenderCfgError::VersionMismatch {
+                found: version,
+                expected: RENDER_CFG_VERSION,
+            });
+        }
+        let render_cfg = serde_json::from_value::<Self>(value)
+            .map_err(|err| RenderCfgError::Invalid { source: err })?;
+        let checksum = render_cfg.compute_checksum();
+        if render_cfg.checksum != checksum {
+            return Err(RenderCfgError::ChecksumMismatch {
+                found: render_cfg.checksum,
+                expected: checksum,
+            });
+        }
+
+        Ok(render_cfg)
+    }
+    /// Serializes this render configuration to JSON.
+    pub fn to_json(&self) -> String {
+        // This is only made of strings, booleans, and numbers, so it can't fail
+        serde_json::to_string(self).unwrap()
+    }
+    /// Gets the root path of the template that renders the given page in the
+    /// given locale, if there is one. This doesn't account for incremental
+    /// generation.
+    pub fn get(&self, path: &str, locale: &str) -> Option<&String> {
+        self.pages.get(path).or_else(|| {
+            self.localized_pages
+                .get(locale)
+                .and_then(|pages| pages.get(path))
+        })
+    }
+    /// Gets an iterator over all the pages that exist in the given locale, as
+    /// pairs of their paths and the root paths of the templates that render
+    /// them.
+    pub fn pages_for_locale<'a>(
+        &'a self,
+        locale: &str,
+    ) -> impl Iterator<Item = (&'a String, &'a String)> {
+        self.pages
+            .iter()
+            .chain(self.localized_pages.get(locale).into_iter().flatten())
+    }
+    /// Gets an iterator over the root paths of the templates that render every
+    /// page in every locale (which may include duplicates).
+    pub fn all_templates(&self) -> impl Iterator<Item = &String> {
+        self.pages.values().chain(
+            self.localized_pages
+                .values()
+                .flat_map(|pages| pages.values()),
+        )
+    }
+    /// Computes the checksum of the contents of this render configuration,
+    /// which is a hex-encoded 64-bit FNV-1a hash of them (this is for
+    /// detecting corruption, not tampering). The maps are sorted, so this
+    /// will always be the same for the same contents.
+    fn compute_checksum(&self) -> String {
+        let contents = serde_json::to_string(&(
+            self.version,
+            self.using_i18n,
+            &self.pages,
+            &self.localized_pages,
+            &self.templates,
+            &self.assets,
+            &self.localized_paths,
+            &self.redirects,
+            &self.locales,
+            self.profile,
+        ))
+        .unwrap();
+        hash_bytes(contents.as_bytes())
+    }
+}
+
+/// Hashes the given bytes with 64-bit FNV-1a, returning the hash hex-encoded.
+/// This is fast and stable, but not cryptographically secure.
+pub(crate) fn hash_bytes(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_specific_pages_are_split_out() {
+        let pages = |paths: &[&str]| {
+            paths
+                .iter()
+                .map(|path| (path.to_string(), "post".to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let mut locale_pages = BTreeMap::new();
+        locale_pages.insert("en-US".to_string(), pages(&["post", "post/hello"]));
+        locale_pages.insert("fr-FR".to_string(), pages(&["post", "post/bonjour"]));
+        let render_cfg = RenderCfg::new(locale_pages, BTreeMap::new(), BTreeMap::new(), true);
+
+        assert_eq!(render_cfg.pages, pages(&["post"]));
+        assert!(render_cfg.get("post/hello", "en-US").is_some());
+        assert!(render_cfg.get("post/hello", "fr-FR").is_none());
+        assert!(render_cfg.get("post/bonjour", "fr-FR").is_some());
+        assert_eq!(render_cfg.pages_for_locale("fr-FR").count(), 2);
+        assert_eq!(
+            RenderCfg::from_json(&render_cfg.to_json()).unwrap(),
+            render_cfg
+        );
+    }
+
+    #[test]
+    fn redirects_are_recorded_per_locale() {
+        let redirect = Redirect::to("/en-US/login", 302)
+            .error
+            .downcast::<Redirect>()
+            .unwrap();
+        let mut redirects = BuildRedirects::default();
+        redirects.insert("account".to_string(), "en-US", *redirect.clone());
+        let render_cfg = RenderCfg::new(BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), true)
+            .with_redirects(redirects);
+
+        assert_eq!(
+            render_cfg.redirects.get("account", "en-US"),
+            Some(&*redirect)
+        );
+        assert!(render_cfg.redirects.get("account", "fr-FR").is_none());
+        assert_eq!(
+            RenderCfg::from_json(&render_cfg.to_json()).unwrap(),
+            render_cfg
+        );
+    }
+
+    #[test]
+    fn only_built_locales_are_supported() {
+        let locales = Locales {
+            default: "en-US".to_string(),
+            other: vec!["fr-FR".to_string(), "de-DE".to_string()],
+            using_i18n: true,
+        };
+        let render_cfg = RenderCfg::new(BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), true)
+            .with_locales(vec!["en-US".to_string(), "de-DE".to_string()]);
+        let render_cfg = RenderCfg::from_json(&render_cfg.to_json()).unwrap();
+
+        let built = render_cfg.built_locales(locales);
+        assert!(built.is_supported("de-DE"));
+        assert!(!built.is_supported("fr-FR"));
+        assert_eq!(built.negotiate(&["fr-FR"]), None);
+    }
+    #[test]
+    fn profile_is_recorded() {
+        let render_cfg = RenderCfg::new(BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), false)
+            .with_profile(BuildProfile::Preview);
+        let render_cfg = RenderCfg::from_json(&render_cfg.to_json()).unwrap();
+        assert_eq!(render_cfg.profile, BuildProfile::Preview);
+        assert!(render_cfg.to_json().contains(r#""profile":"preview""#));
+    }
+}
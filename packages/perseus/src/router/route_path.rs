@@ -0,0 +1,149 @@
+use crate::translator::Translator;
+#[cfg(target_arch = "wasm32")]
+use crate::utils::get_path_prefix_client;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::get_path_prefix_server;
+use std::fmt;
+use std::ops::Deref;
+use sycamore::prelude::{try_use_context, Scope, Signal};
+
+/// A path to a page within your app, without any locale or path prefix (e.g.
+/// `/post/my-first-post`). These are usually created by the functions that
+/// [`routes!`](crate::routes) generates, which make sure every dynamic segment
+/// is filled in. Note that these paths aren't checked against your templates
+/// though, so it's up to you to make sure they actually exist in your app.
+///
+/// This dereferences to `&str`, so it can be given directly to the `link!`
+/// macro, or you can use `.to_url()` to get a full URL that's suitable for
+/// `navigate()` or an `href` (which works whether or not you're using i18n).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RoutePath(String);
+impl RoutePath {
+    /// Creates a new route path from the given path within the app. A leading
+    /// forward slash will be added if it's not already there.
+    pub fn new(path: impl Into<String>) -> Self {
+        let path = path.into();
+        match path.starts_with('/') {
+            true => Self(path),
+            false => Self(format!("/{}", path)),
+        }
+    }
+    /// Encodes a value that will be interpolated into a dynamic segment of a
+    /// route path. Forward slashes are preserved, since templates can render
+    /// pages nested at arbitrary depths under them. Any `.` or `..` parts are
+    /// removed, since browsers resolve those even when they're URL-encoded, and
+    /// they could otherwise make the path point outside the route.
+    #[doc(hidden)]
+    pub fn encode_segment(val: impl fmt::Display) -> String {
+        val.to_string()
+            .split('/')
+            .filter(|part| !matches!(*part, "." | ".."))
+            .map(|part| urlencoding::encode(part).to_string())
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+    /// Gets the raw path, without any locale or path prefix.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// Gets the full URL for this path, including the app's path prefix and,
+    /// if the app is using i18n, the locale of the page currently being
+    /// rendered. This is suitable for both `navigate()` and `href`s.
+    pub fn to_url(&self, cx: Scope) -> String {
+        #[cfg(target_arch = "wasm32")]
+        let path_prefix = get_path_prefix_client();
+        #[cfg(not(target_arch = "wasm32"))]
+        let path_prefix = get_path_prefix_server();
+        // Error pages may not have a translator, in which case we can't localize
+        let locale = try_use_context::<Signal<Translator>>(cx)
+            .map(|translator| translator.get_untracked().get_locale());
+        match locale.as_deref() {
+            Some("xx-XX") | None => format!("{}{}", path_prefix, self.0),
+            Some(locale) => format!("{}/{}{}", path_prefix, locale, self.0),
+        }
+    }
+    /// Navigates to this path, in the locale of the current page. This can
+    /// only be used in the browser.
+    pub fn navigate(&self, cx: Scope) {
+        sycamore_router::navigate(&self.to_url(cx));
+    }
+}
+impl Deref for RoutePath {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl fmt::Display for RoutePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl From<RoutePath> for String {
+    fn from(path: RoutePath) -> Self {
+        path.0
+    }
+}
+
+/// Defines typed functions for building paths to the pages in your app, which
+/// produce [`RoutePath`]s. Each route is given a name, which becomes the name
+/// of the function, any dynamic segments it has, which become the function's
+/// arguments, and a path, into which those segments will be interpolated
+/// (URL-encoded, without any `.` or `..` parts). If a path uses a segment that
+/// isn't declared, or doesn't use one that is, you'll get a compile-time error.
+///
+/// ```rust,ignore
+/// mod routes {
+///     perseus::routes! {
+///         index => "",
+///         about => "about",
+///         /// A single blog post.
+///         post(slug) => "post/{slug}",
+///     }
+/// }
+///
+/// // Then, in a template (`link!` will localize the path)
+/// view! { cx,
+///     a(href = link!(&routes::post("hello-world"), cx)) { "Hello World!" }
+/// }
+/// // Or, to navigate imperatively
+/// routes::post("hello-world").navigate(cx);
+/// ```
+#[macro_export]
+macro_rules! routes {
+    {
+        $(
+            $(#[$meta:meta])*
+            $name:ident $(( $($arg:ident),* ))? => $path:literal
+        ),* $(,)?
+    } => {
+        $(
+            $(#[$meta])*
+            #[allow(dead_code)]
+            pub fn $name($($($arg: impl ::std::fmt::Display),*)?) -> $crate::router::RoutePath {
+                $crate::router::RoutePath::new(format!(
+                    $path
+                    $($(, $arg = $crate::router::RoutePath::encode_segment($arg))*)?
+                ))
+            }
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_segments_without_dot_segments() {
+        assert_eq!(RoutePath::encode_segment("hello world"), "hello%20world");
+        assert_eq!(RoutePath::encode_segment("2023/my-post"), "2023/my-post");
+        assert_eq!(RoutePath::encode_segment("a?b#c"), "a%3Fb%23c");
+        assert_eq!(RoutePath::encode_segment(".."), "");
+        assert_eq!(RoutePath::encode_segment("../../admin"), "admin");
+        assert_eq!(RoutePath::encode_segment("a/./b/../c"), "a/b/c");
+        assert_eq!(RoutePath::encode_segment("v1.2"), "v1.2");
+        assert_eq!(RoutePath::encode_segment("..."), "...");
+    }
+}
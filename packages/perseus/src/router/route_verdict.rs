@@ -32,6 +32,11 @@ pub enum RouteVerdict<G: Html> {
     /// The given route maps to the locale detector, which will redirect the
     /// user to the attached path (in the appropriate locale).
     LocaleDetection(String),
+    /// The given route is the root of the app (or of a locale), which the app
+    /// has set to redirect to the attached path (without the path prefix). This
+    /// is only produced by the router in the browser, the server handles index
+    /// redirects before matching routes.
+    IndexRedirect(String),
 }
 
 /// Information about a route, which, combined with error pages and a
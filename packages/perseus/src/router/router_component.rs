@@ -1,22 +1,29 @@
 use crate::{
     checkpoint,
     error_pages::ErrorPageData,
+    errors::ErrorReporter,
+    experiments::Variants,
     i18n::Locales,
     i18n::{detect_locale, ClientTranslationsManager},
-    router::{PerseusRoute, RouteInfo, RouteVerdict},
+    router::{
+        focus_new_page, get_browser_url, update_location_signals, NavigationGuardVerdict,
+        PendingNavigation, PerseusRoute, RenderCfg, RouteInfo, RouteVerdict,
+    },
     router::{RouterLoadState, RouterState},
-    shell::{app_shell, get_initial_state, InitialState, ShellProps},
-    template::{RenderCtx, TemplateMap, TemplateNodeType},
+    session::SessionSnapshot,
+    shell::{app_shell, get_initial_state, mark_hydrated, InitialState, ShellProps},
+    template::{RenderCtx, RouteRegistry, TemplateMap, TemplateNodeType},
+    utils::get_path_prefix_client,
     DomNode, ErrorPages, Html,
 };
-use std::collections::HashMap;
 use std::rc::Rc;
 use sycamore::{
     prelude::{component, create_effect, create_signal, view, NodeRef, ReadSignal, Scope, View},
     Prop,
 };
-use sycamore_router::{HistoryIntegration, RouterBase};
-use web_sys::Element;
+use sycamore_router::{navigate, navigate_replace, HistoryIntegration, RouterBase};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{Element, HtmlAnchorElement, MouseEvent};
 
 // We don't want to bring in a styling library, so we do this the old-fashioned
 // way! We're particualrly comprehensive with these because the user could
@@ -45,6 +52,7 @@ struct OnRouteChangeProps<'a, G: Html> {
     translations_manager: ClientTranslationsManager,
     error_pages: Rc<ErrorPages<TemplateNodeType>>,
     initial_container: Option<Element>,
+    error_reporter: Option<ErrorReporter>,
 }
 
 /// The function that runs when a route change takes place. This can also be run
@@ -59,6 +67,7 @@ fn on_route_change<G: Html>(
         translations_manager,
         error_pages,
         initial_container,
+        error_reporter,
     }: OnRouteChangeProps<'_, G>,
 ) {
     sycamore_futures::spawn_local_scoped(cx, async move {
@@ -66,6 +75,15 @@ fn on_route_change<G: Html>(
             .get::<DomNode>()
             .unchecked_into::<web_sys::Element>();
         checkpoint("router_entry");
+        // This is only the URL the browser moved to through its history if this
+        // navigation was started that way
+        let popped_url = router_state.popped_url.borrow_mut().take();
+        let is_history_navigation = popped_url.as_deref() == Some(get_browser_url().as_str());
+        // If we've just gone back to undo a cancelled navigation, the right page is
+        // already being displayed
+        if router_state.undoing_navigation.replace(false) {
+            return;
+        }
         match &verdict {
             // Perseus' custom routing system is tightly coupled to the template system, and returns
             // exactly what we need for the app shell! If a non-404 error occurred, it
@@ -76,6 +94,65 @@ fn on_route_change<G: Html>(
                 locale,
                 was_incremental_match,
             }) => {
+                // Before we fetch anything, run any navigation guards (these only apply to
+                // navigations within the app, not the initial load or reloading the current
+                // page)
+                if let Some(from) = router_state.get_current_path() {
+                    let to = match locale.as_str() {
+                        "xx-XX" => path.clone(),
+                        locale => format!("{}/{}", locale, &path),
+                    };
+                    if from != to {
+                        let from_url = router_state
+                            .current_url
+                            .borrow()
+                            .clone()
+                            .unwrap_or_else(|| format!("{}/{}", get_path_prefix_client(), from));
+                        let pending = PendingNavigation {
+                            from,
+                            from_url,
+                            to,
+                            template_name: template.get_path(),
+                        };
+                        // The template's own guard takes precedence over the global ones
+                        let verdict = match template.run_navigation_guard(&pending) {
+                            NavigationGuardVerdict::Proceed => {
+                                RenderCtx::from_ctx(cx).navigation_guards.run(&pending)
+                            }
+                            verdict => verdict,
+                        };
+                        match verdict {
+                            NavigationGuardVerdict::Proceed => (),
+                            NavigationGuardVerdict::Cancel => {
+                                // The browser's URL has already been changed, so we put it back
+                                // (the page being displayed hasn't been touched)
+                                let history = web_sys::window().unwrap().history().unwrap();
+                                if is_history_navigation {
+                                    // The entry the user left is still in their history, so we
+                                    // just need to show its URL again
+                                    history
+                                        .replace_state_with_url(
+                                            &JsValue::UNDEFINED,
+                                            "",
+                                            Some(&pending.from_url),
+                                        )
+                                        .unwrap();
+                                } else {
+                                    // A new entry was pushed for this navigation, so we go back
+                                    // to the one the user was on
+                                    router_state.undoing_navigation.set(true);
+                                    history.back().unwrap();
+                                }
+                                return;
+                            }
+                            NavigationGuardVerdict::Redirect(url) => {
+                                navigate_replace(&url);
+                                return;
+                            }
+                        }
+                    }
+                }
+                *router_state.current_url.borrow_mut() = Some(get_browser_url());
                 app_shell(ShellProps {
                     cx,
                     path: path.clone(),
@@ -88,20 +165,29 @@ fn on_route_change<G: Html>(
                     initial_container: initial_container.unwrap(),
                     container_rx_elem,
                     route_verdict: verdict,
+                    error_reporter,
                 })
-                .await
+                .await;
+                mark_hydrated(cx);
             }
             // If the user is using i18n, then they'll want to detect the locale on any paths
             // missing a locale Those all go to the same system that redirects to the
             // appropriate locale Note that `container` doesn't exist for this scenario
             RouteVerdict::LocaleDetection(path) => detect_locale(path.clone(), &locales),
+            // This replaces the current history entry, so that the back button doesn't lead
+            // straight back here
+            RouteVerdict::IndexRedirect(path) => {
+                navigate_replace(&format!("{}/{}", get_path_prefix_client(), path))
+            }
             // To get a translator here, we'd have to go async and dangerously check the URL
             // If this is an initial load, there'll already be an error message, so we should only
             // proceed if the declaration is not `error` BUG If we have an error in a
             // subsequent load, the error message appears below the current page...
             RouteVerdict::NotFound => {
                 checkpoint("not_found");
-                if let InitialState::Error(ErrorPageData { url, status, err }) = get_initial_state()
+                if let InitialState::Error(ErrorPageData {
+                    url, status, err, ..
+                }) = get_initial_state()
                 {
                     let initial_container = initial_container.unwrap();
                     // We need to move the server-rendered content from its current container to the
@@ -118,21 +204,90 @@ fn on_route_change<G: Html>(
                     initial_container
                         .set_attribute("style", "display: none;")
                         .unwrap();
-                    // Hydrate the error pages
-                    // Right now, we don't provide translators to any error pages that have come
-                    // from the server
-                    error_pages.render_page(cx, &url, status, &err, None, &container_rx_elem);
+                    // Hydrate the error pages, with the same translations the server used
+                    let locale = translations_manager.get_url_locale(&url);
+                    let translator = translations_manager.get_error_translator(&locale).await;
+                    error_pages.render_page(cx, &url, status, &err, translator, &container_rx_elem);
                 } else {
                     // This is an error from navigating within the app (probably the dev mistyped a
                     // link...), so we'll clear the page
                     container_rx_elem.set_inner_html("");
-                    error_pages.render_page(cx, "", 404, "not found", None, &container_rx_elem);
+                    let url = web_sys::window()
+                        .unwrap()
+                        .location()
+                        .pathname()
+                        .unwrap_or_default();
+                    let locale = translations_manager.get_url_locale(&url);
+                    let translator = translations_manager.get_fallback_translator(&locale);
+                    error_pages.render_page(
+                        cx,
+                        "",
+                        404,
+                        "not found",
+                        translator,
+                        &container_rx_elem,
+                    );
                 }
+                mark_hydrated(cx);
             }
         };
     });
 }
 
+/// Handles clicks on links that Sycamore's router would get wrong. Sycamore
+/// ignores query strings and hash fragments entirely (dropping them from the
+/// browser's URL, and ignoring links that only change the query string), and
+/// it resolves hash-only links against the `<base>` element, which would
+/// navigate to the root of the app. We intercept these links before Sycamore
+/// can see them and handle them ourselves, leaving all other links to it.
+fn handle_link_click(ev: web_sys::Event) {
+    let a = match ev
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .and_then(|elem| elem.closest("a[href]").ok().flatten())
+    {
+        Some(a) => a.unchecked_into::<HtmlAnchorElement>(),
+        None => return,
+    };
+    let location = web_sys::window().unwrap().location();
+    // External links and links opened with modifier keys (e.g. in a new tab)
+    // should use the browser's default behavior
+    let ev_mouse = ev.unchecked_ref::<MouseEvent>();
+    if a.rel() == "external"
+        || ev_mouse.meta_key()
+        || ev_mouse.ctrl_key()
+        || ev_mouse.shift_key()
+        || ev_mouse.alt_key()
+        || location.origin().as_ref() != Ok(&a.origin())
+    {
+        return;
+    }
+
+    // Links to anchors on the current page should just change the hash (the
+    // browser will scroll to the anchor for us, since the content is already
+    // there)
+    let raw_href = a.get_attribute("href").unwrap_or_default();
+    if let Some(hash) = raw_href.strip_prefix('#') {
+        ev.prevent_default();
+        ev.stop_immediate_propagation();
+        location.set_hash(hash).unwrap();
+        return;
+    }
+
+    let search = a.search();
+    let hash = a.hash();
+    let curr_search = location.search().unwrap();
+    let is_new_path = location.pathname().unwrap() != a.pathname();
+    if search != curr_search || (is_new_path && !(search.is_empty() && hash.is_empty())) {
+        ev.prevent_default();
+        // Make sure Sycamore doesn't handle this as well
+        ev.stop_immediate_propagation();
+        // The app shell will scroll to the hash (if there is one) once the new page is
+        // interactive
+        navigate(&format!("{}{}{}", a.pathname(), search, hash));
+    }
+}
+
 /// The properties that the router takes.
 #[derive(Debug, Prop)]
 pub(crate) struct PerseusRouterProps {
@@ -144,7 +299,23 @@ pub(crate) struct PerseusRouterProps {
     pub templates: TemplateMap<TemplateNodeType>,
     /// The render configuration of the app (which lays out routing information,
     /// among other things).
-    pub render_cfg: HashMap<String, String>,
+    pub render_cfg: RenderCfg,
+    /// The function to report client-side errors to, if the app has one.
+    pub error_reporter: Option<ErrorReporter>,
+    /// A CSS selector for the element to focus after client-side navigation,
+    /// if the app has set one.
+    pub focus_target: Option<String>,
+    /// The path the root of the app should redirect to, if the app has set
+    /// one.
+    pub index_redirect: Option<String>,
+    /// Whether or not translations should be persisted in `localStorage`
+    /// between sessions.
+    pub persist_translations: bool,
+    /// Whether or not the translations for the other locales the user
+    /// understands should be preloaded after the first page has been rendered.
+    pub preload_locales: bool,
+    /// The translations for the default locale the app has embedded, if any.
+    pub embedded_translations: Option<&'static str>,
 }
 
 /// The Perseus router. This is used internally in the Perseus engine, and you
@@ -164,14 +335,30 @@ pub(crate) fn perseus_router<G: Html>(
         locales,
         templates,
         render_cfg,
+        error_reporter,
+        focus_target,
+        index_redirect,
+        persist_translations,
+        preload_locales,
+        embedded_translations,
     }: PerseusRouterProps,
 ) -> View<G> {
+    // Collect the metadata of all the templates for the render context before we
+    // give them to the router
+    let route_registry = RouteRegistry::new(
+        templates
+            .iter()
+            .map(|(path, template)| (path.to_string(), template.get_meta()))
+            .collect(),
+        render_cfg.localized_paths.clone(),
+    );
     // Create a `Route` to pass through Sycamore with the information we need
     let route = PerseusRoute {
         verdict: RouteVerdict::NotFound,
         templates,
         render_cfg,
         locales: locales.clone(),
+        index_redirect,
     };
 
     // Get the root that the server will have injected initial load content into
@@ -188,7 +375,8 @@ pub(crate) fn perseus_router<G: Html>(
     // verison
     let container_rx = NodeRef::new();
 
-    let translations_manager = ClientTranslationsManager::new(&locales);
+    let translations_manager =
+        ClientTranslationsManager::new(&locales, persist_translations, embedded_translations);
     // Now that we've used the reference, put the locales in an `Rc`
     let locales = Rc::new(locales);
     // Get the error pages in an `Rc` so we aren't creating hundreds of them
@@ -196,7 +384,12 @@ pub(crate) fn perseus_router<G: Html>(
 
     // Now create an instance of `RenderCtx`, which we'll insert into context and
     // use everywhere throughout the app
-    let render_ctx = RenderCtx::default().set_ctx(cx);
+    let render_ctx = RenderCtx {
+        route_registry,
+        translations_manager: translations_manager.clone(),
+        ..Default::default()
+    }
+    .set_ctx(cx);
 
     // TODO Replace passing a router state around with getting it out of context
     // instead in the shell
@@ -218,6 +411,17 @@ pub(crate) fn perseus_router<G: Html>(
                 // least something that we should announce, if this page reloads then the
                 // content will change, that would be from thawing)
                 is_first_page = false;
+                // The first page has been rendered with the server's media query defaults
+                // (which hydration needs), so we can use the real values now
+                render_ctx.media.connect();
+                // The same goes for A/B experiment variants
+                render_ctx.variants.set(Variants::from_window());
+                render_ctx.session.set(SessionSnapshot::from_window());
+                // Now that the user can see something, we can fetch the translations they
+                // might switch to in the background
+                if preload_locales {
+                    render_ctx.translations_manager.preload_preferred();
+                }
             } else {
                 // TODO Validate approach with reloading
                 // A new page has just been loaded and is interactive (this event only fires
@@ -255,6 +459,9 @@ pub(crate) fn perseus_router<G: Html>(
                 };
 
                 route_announcement.set(announcement);
+                // Keyboard and screen reader users shouldn't be left wherever they were on
+                // the old page
+                focus_new_page(focus_target.as_deref());
             }
         }
     });
@@ -269,8 +476,39 @@ pub(crate) fn perseus_router<G: Html>(
         translations_manager,
         error_pages,
         initial_container,
+        error_reporter,
     };
 
+    // Keep the query parameters and hash fragment in the render context up-to-date
+    // when only the hash changes (which the router won't tell us about)
+    {
+        let query_params = render_ctx.query_params.clone();
+        let hash = render_ctx.hash.clone();
+        let current_url = router_state.current_url.clone();
+        let on_hash_change = Closure::wrap(Box::new(move || {
+            update_location_signals(&query_params, &hash);
+            *current_url.borrow_mut() = Some(get_browser_url());
+        }) as Box<dyn FnMut()>);
+        web_sys::window()
+            .unwrap()
+            .add_event_listener_with_callback("hashchange", on_hash_change.as_ref().unchecked_ref())
+            .unwrap();
+        on_hash_change.forget();
+    }
+    // Keep track of where the browser moves to through its history, so that
+    // cancelled navigations can be undone properly
+    {
+        let popped_url = router_state.popped_url.clone();
+        let on_pop_state = Closure::wrap(Box::new(move || {
+            *popped_url.borrow_mut() = Some(get_browser_url());
+        }) as Box<dyn FnMut()>);
+        web_sys::window()
+            .unwrap()
+            .add_event_listener_with_callback("popstate", on_pop_state.as_ref().unchecked_ref())
+            .unwrap();
+        on_pop_state.forget();
+    }
+
     // Listen for changes to the reload commander and reload as appropriate
     let orcp_clone = on_route_change_props.clone();
     create_effect(cx, move || {
@@ -347,6 +585,7 @@ pub(crate) fn perseus_router<G: Html>(
                 // We do need the future though (otherwise `container_rx` doesn't link to anything until it's too late)
                 create_effect(cx, move || {
                     let route = route.get();
+                    update_location_signals(&render_ctx.query_params, &render_ctx.hash);
                     let verdict = route.get_verdict();
                     on_route_change(verdict.clone(), on_route_change_props.clone());
                 });
@@ -355,8 +594,9 @@ pub(crate) fn perseus_router<G: Html>(
                 // However, the server has already rendered initial load content elsewhere, so we move that into here as well in the app shell
                 // The main reason for this is that the router only intercepts click events from its children
                 view! { cx,
-                    div {
-                        div(id="__perseus_content_rx", class="__perseus_content", ref=container_rx) {}
+                    div(on:click = handle_link_click) {
+                        // This can be focused programmatically (e.g. by a skip link), but it won't be in the tab order
+                        div(id="__perseus_content_rx", class="__perseus_content", tabindex = "-1", ref=container_rx) {}
                         p(id = "__perseus_route_announcer", aria_live = "assertive", role = "alert", style = ROUTE_ANNOUNCER_STYLES) { (route_announcement.get()) }
                     }
                 }
@@ -1,5 +1,7 @@
 use super::RouteVerdict;
 use crate::template::TemplateNodeType;
+#[cfg(target_arch = "wasm32")]
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::rc::Rc;
 use sycamore::prelude::{create_rc_signal, create_ref, RcSignal, Scope};
@@ -19,6 +21,19 @@ pub struct RouterState {
     /// should rarely ever need to do this, but it's used internally in the
     /// thawing process.
     pub(crate) reload_commander: RcSignal<bool>,
+    /// The full URL of the page the router is currently on, as it appears in
+    /// the browser (including the path prefix, query string, and hash
+    /// fragment). Navigation guards might need to put this back.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) current_url: Rc<RefCell<Option<String>>>,
+    /// The URL the browser last moved to through its history (e.g. with the
+    /// back button), which tells us how a navigation was started.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) popped_url: Rc<RefCell<Option<String>>>,
+    /// Whether or not the next navigation is the router going back to undo a
+    /// cancelled one, in which case the current page should be left alone.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) undoing_navigation: Rc<Cell<bool>>,
 }
 impl Default for RouterState {
     /// Creates a default instance of the router state intended for server-side
@@ -29,6 +44,12 @@ impl Default for RouterState {
             last_verdict: Rc::new(RefCell::new(None)),
             // It doesn't matter what we initialize this as, it's just for signalling
             reload_commander: create_rc_signal(true),
+            #[cfg(target_arch = "wasm32")]
+            current_url: Rc::new(RefCell::new(None)),
+            #[cfg(target_arch = "wasm32")]
+            popped_url: Rc::new(RefCell::new(None)),
+            #[cfg(target_arch = "wasm32")]
+            undoing_navigation: Rc::new(Cell::new(false)),
         }
     }
 }
@@ -52,6 +73,18 @@ impl RouterState {
     pub fn set_load_state(&self, new: RouterLoadState) {
         self.load_state.set(new);
     }
+    /// Gets the full path of the page the router is currently on (including
+    /// the locale, if we're using i18n). If a new page is being loaded, this
+    /// will be the page being navigated away from. On the server, or before
+    /// the first page has been loaded, this will be `None`.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn get_current_path(&self) -> Option<String> {
+        match &*self.load_state.get_untracked() {
+            RouterLoadState::Loaded { path, .. } => Some(path.to_string()),
+            RouterLoadState::Loading { from, .. } => from.clone(),
+            RouterLoadState::Server => None,
+        }
+    }
     /// Gets the last verdict.
     pub fn get_last_verdict(&self) -> Option<RouteVerdict<TemplateNodeType>> {
         (*self.last_verdict.borrow()).clone()
@@ -94,6 +127,16 @@ pub enum RouterLoadState {
         /// The full path to the new page being loaded (including the locale, if
         /// we're using i18n).
         path: String,
+        /// The full path to the page we're navigating away from (including the
+        /// locale, if we're using i18n). This will be `None` on the initial
+        /// load.
+        from: Option<String>,
+        /// A rough estimate of how far through loading the new page we are,
+        /// from `0.0` to `1.0`. This is updated as the page's data and
+        /// translations are fetched, and it's mostly useful for displaying a
+        /// progress bar (see
+        /// [`RouterProgressBar`](crate::router::RouterProgressBar)).
+        progress: f32,
     },
     /// We're on the server, and there is no router. Whatever you render based
     /// on this state will appear when the user first loads the page, before
@@ -0,0 +1,88 @@
+use crate::Html;
+use sycamore::prelude::{component, create_memo, create_signal, view, Scope, View};
+use sycamore::Prop;
+
+// As with the route announcer, we don't want to bring in a styling library, so
+// these are inlined (this is the usual 'visually hidden' pattern)
+const SKIP_LINK_HIDDEN_STYLES: &str = r#"
+    position: absolute;
+    width: 1px;
+    height: 1px;
+    padding: 0;
+    margin: -1px;
+    overflow: hidden;
+    clip: rect(0, 0, 0, 0);
+    white-space: nowrap;
+    border: 0;
+"#;
+const SKIP_LINK_VISIBLE_STYLES: &str = r#"
+    position: fixed;
+    top: 0.5rem;
+    left: 0.5rem;
+    z-index: 10000;
+    padding: 0.5rem 1rem;
+    background-color: white;
+    color: black;
+"#;
+
+/// The properties for [`SkipLink`].
+#[derive(Prop, Debug)]
+pub struct SkipLinkProps {
+    /// The ID of the element to skip to. By default, this is the element
+    /// Perseus renders your pages into.
+    #[builder(default = "__perseus_content_rx".to_string())]
+    pub target: String,
+    /// The text of the link. By default, this is `Skip to content`.
+    #[builder(default = "Skip to content".to_string())]
+    pub text: String,
+}
+
+/// A 'skip to content' link, which lets keyboard and screen reader users jump
+/// past repeated navigation straight to the content of the page. This is hidden
+/// until it's focused, so it should be placed before everything else on the
+/// page (e.g. at the start of a layout template), where it'll be the first
+/// thing reached with the `Tab` key.
+///
+/// Activating this will move focus to the target element, not just scroll to
+/// it.
+#[component]
+#[allow(non_snake_case)]
+pub fn SkipLink<G: Html>(cx: Scope, props: SkipLinkProps) -> View<G> {
+    let focused = create_signal(cx, false);
+    let style = create_memo(cx, move || {
+        if *focused.get() {
+            SKIP_LINK_VISIBLE_STYLES
+        } else {
+            SKIP_LINK_HIDDEN_STYLES
+        }
+    });
+    let href = format!("#{}", props.target);
+    #[allow(unused_variables)]
+    let target = props.target;
+
+    view! { cx,
+        a(
+            href = href,
+            class = "__perseus_skip_link",
+            style = style.get(),
+            on:focus = move |_| focused.set(true),
+            on:blur = move |_| focused.set(false),
+            on:click = move |_ev| {
+                #[cfg(target_arch = "wasm32")]
+                skip_to(_ev, &target);
+            }
+        ) { (props.text) }
+    }
+}
+
+/// Moves focus to the element with the given ID, if it exists. The router would
+/// otherwise just change the hash, which doesn't move focus.
+#[cfg(target_arch = "wasm32")]
+fn skip_to(ev: web_sys::Event, target: &str) {
+    let document = web_sys::window().unwrap().document().unwrap();
+    if let Some(elem) = document.get_element_by_id(target) {
+        ev.prevent_default();
+        ev.stop_propagation();
+        crate::router::focus_element(&elem);
+    }
+}
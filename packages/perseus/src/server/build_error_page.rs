@@ -1,5 +1,6 @@
 use super::HtmlShell;
-use crate::error_pages::{ErrorPageData, ErrorPages};
+use crate::error_pages::{ErrorContext, ErrorPageData, ErrorPages};
+use crate::errors::{err_to_app_error, err_to_message, err_to_status_code, ServerError};
 use crate::translator::Translator;
 use crate::SsrNode;
 use std::rc::Rc;
@@ -19,15 +20,51 @@ pub fn build_error_page(
     error_pages: &ErrorPages<SsrNode>,
     html_shell: &HtmlShell,
 ) -> String {
-    let error_html = error_pages.render_to_string(url, status, err, translator);
+    let ctx = ErrorContext::from_status(url, status);
+    build_error_page_with_ctx(ctx, err, translator, error_pages, html_shell)
+}
+
+/// Prepares an HTML error page for the client for the given error that occurred
+/// while rendering a page (see [`build_error_page`]). This works out the status
+/// code and message itself, makes sure any page for the kind of
+/// [`AppError`](crate::AppError) that caused the error is used, and sends that
+/// error to the client.
+pub fn build_server_error_page(
+    url: &str,
+    err: &ServerError,
+    translator: Option<Rc<Translator>>,
+    error_pages: &ErrorPages<SsrNode>,
+    html_shell: &HtmlShell,
+) -> String {
+    let ctx = ErrorContext::from_status(url, err_to_status_code(err))
+        .with_app_error(err_to_app_error(err).cloned());
+    build_error_page_with_ctx(
+        ctx,
+        &err_to_message(err),
+        translator,
+        error_pages,
+        html_shell,
+    )
+}
+
+/// Prepares an HTML error page for the error with the given context.
+fn build_error_page_with_ctx(
+    ctx: ErrorContext,
+    err: &str,
+    translator: Option<Rc<Translator>>,
+    error_pages: &ErrorPages<SsrNode>,
+    html_shell: &HtmlShell,
+) -> String {
     // We create a JSON representation of the data necessary to hydrate the error
     // page on the client-side Right now, translators are never included in
     // transmitted error pages
     let error_page_data = ErrorPageData {
-        url: url.to_string(),
-        status,
+        url: ctx.url.clone(),
+        status: ctx.status,
         err: err.to_string(),
+        app_error: ctx.app_error.clone(),
     };
+    let error_html = error_pages.render_to_string_ctx(ctx, err, translator);
 
     html_shell
         .clone()
@@ -0,0 +1,180 @@
+use super::get_path_slice;
+use crate::i18n::{Locales, TranslationsManager};
+use crate::translator::Translator;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+/// The translations that error pages for initial loads are rendered with.
+/// These are fetched for every locale when the server starts, so that error
+/// pages can always be rendered in the right language without having to wait
+/// on the translations manager (which might be what failed). If the
+/// translations for the default locale can't be fetched, the ones the app has
+/// embedded (see `PerseusApp::embedded_translations()`) will be used instead.
+///
+/// Each locale's translations are only parsed once, unless they're changed
+/// in development (see [`ErrorTranslations::refresh`]).
+#[derive(Debug, Clone)]
+pub struct ErrorTranslations {
+    /// The translator for each locale, along with the translations it was
+    /// created from.
+    translators: Arc<RwLock<HashMap<String, (String, Translator)>>>,
+    locales: Locales,
+    /// Whether or not the translations should be fetched again when they
+    /// might have changed, which is only the case when the CLI is watching
+    /// for changes.
+    hot_reload: bool,
+}
+impl ErrorTranslations {
+    /// Fetches the translations for every locale the app supports from the
+    /// given translations manager, falling back to the given embedded
+    /// translations for the default locale.
+    pub async fn new<T: TranslationsManager>(
+        locales: &Locales,
+        translations_manager: &T,
+        embedded: Option<&str>,
+    ) -> Self {
+        let mut translators = HashMap::new();
+        if locales.using_i18n {
+            let all_locales = locales
+                .get_all()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<String>>();
+            for locale in all_locales {
+                if let Ok(translations_str) = translations_manager
+                    .get_translations_str_for_locale(locale.clone())
+                    .await
+                {
+                    if let Ok(translator) =
+                        Translator::new(locale.clone(), translations_str.clone())
+                    {
+                        translators.insert(locale, (translations_str, translator));
+                    }
+                }
+            }
+        }
+        if let Some(embedded) = embedded {
+            if !translators.contains_key(&locales.default) {
+                if let Ok(translator) =
+                    Translator::new(locales.default.clone(), embedded.to_string())
+                {
+                    translators.insert(locales.default.clone(), (embedded.to_string(), translator));
+                }
+            }
+        }
+
+        Self {
+            translators: Arc::new(RwLock::new(translators)),
+            locales: locales.clone(),
+            // This is set by the CLI when it's watching for changes
+            hot_reload: std::env::var("PERSEUS_USE_RELOAD_SERVER").is_ok(),
+        }
+    }
+    /// Fetches the translations for the locale of the given path from the
+    /// given translations manager again, parsing them if they've changed. This
+    /// only does anything in development, when the CLI is watching for changes,
+    /// so that error pages can use translations that have been edited since
+    /// the server started. This should be called before
+    /// [`ErrorTranslations::get_translator`] while there's a translations
+    /// manager to hand.
+    pub async fn refresh<T: TranslationsManager>(&self, path: &str, translations_manager: &T) {
+        if !self.hot_reload || !self.locales.using_i18n {
+            return;
+        }
+        let locale = self
+            .get_path_locale(path)
+            .unwrap_or_else(|| self.locales.default.to_string());
+        let translations_str = match translations_manager
+            .get_translations_str_for_locale(locale.clone())
+            .await
+        {
+            Ok(translations_str) => translations_str,
+            Err(_) => return,
+        };
+        let is_current = self
+            .translators
+            .read()
+            .unwrap()
+            .get(&locale)
+            .map_or(false, |(current, _)| *current == translations_str);
+        if !is_current {
+            if let Ok(translator) = Translator::new(locale.clone(), translations_str.clone()) {
+                self.translators
+                    .write()
+                    .unwrap()
+                    .insert(locale, (translations_str, translator));
+            }
+        }
+    }
+    /// Gets a translator to render an error page for the given path with. The
+    /// locale will be taken from the path, falling back to the default locale
+    /// if there isn't one (or if its translations couldn't be fetched). If
+    /// there are no translations for the default locale either, this will
+    /// return `None`.
+    pub fn get_translator(&self, path: &str) -> Option<Rc<Translator>> {
+        let translators = self.translators.read().unwrap();
+        self.get_path_locale(path)
+            .and_then(|locale| translators.get(&locale))
+            .or_else(|| translators.get(&self.locales.default))
+            .map(|(_, translator)| Rc::new(translator.clone()))
+    }
+    /// Gets the locale the given path is under, if it's one the app supports.
+    fn get_path_locale(&self, path: &str) -> Option<String> {
+        get_path_slice(path)
+            .first()
+            .filter(|locale| self.locales.is_supported(locale) && self.locales.using_i18n)
+            .map(|locale| locale.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::FsTranslationsManager;
+    use futures::executor::block_on;
+
+    #[test]
+    #[cfg(feature = "translator-fluent")]
+    fn translators_are_parsed_once_and_refreshed() {
+        let dir =
+            std::env::temp_dir().join(format!("perseus-error-translations-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("fr-FR.ftl"), "greeting = Bonjour").unwrap();
+        let manager = block_on(FsTranslationsManager::new(
+            dir.to_string_lossy().to_string(),
+            Vec::new(),
+            "ftl".to_string(),
+        ));
+        let locales = Locales {
+            default: "en-US".to_string(),
+            other: vec!["fr-FR".to_string()],
+            using_i18n: true,
+        };
+        let mut error_translations = block_on(ErrorTranslations::new(
+            &locales,
+            &manager,
+            Some("greeting = Hello"),
+        ));
+        let translator = error_translations.get_translator("/fr-FR/foo").unwrap();
+        assert_eq!(translator.translate("greeting", None), "Bonjour");
+        // There are no English translations, so the embedded ones are used
+        let translator = error_translations.get_translator("/en-US/foo").unwrap();
+        assert_eq!(translator.translate("greeting", None), "Hello");
+        let translator = error_translations.get_translator("/foo").unwrap();
+        assert_eq!(translator.get_locale(), "en-US");
+        assert_eq!(error_translations.translators.read().unwrap().len(), 2);
+
+        // Changes are only picked up in development
+        std::fs::write(dir.join("fr-FR.ftl"), "greeting = Salut").unwrap();
+        block_on(error_translations.refresh("/fr-FR/foo", &manager));
+        let translator = error_translations.get_translator("/fr-FR/foo").unwrap();
+        assert_eq!(translator.translate("greeting", None), "Bonjour");
+        error_translations.hot_reload = true;
+        block_on(error_translations.refresh("/fr-FR/foo", &manager));
+        let translator = error_translations.get_translator("/fr-FR/foo").unwrap();
+        assert_eq!(translator.translate("greeting", None), "Salut");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
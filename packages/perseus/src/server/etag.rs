@@ -0,0 +1,39 @@
+use crate::router::hash_bytes;
+
+/// Gets an ETag for the given response body, which integrations should send
+/// with it so that clients can check if their cached versions are still
+/// current (this is used for translations, which the browser will then only
+/// download again when they change).
+pub fn get_etag(body: &str) -> String {
+    format!("\"{}\"", hash_bytes(body.as_bytes()))
+}
+
+/// Checks if the given `If-None-Match` header (if the request had one) matches
+/// the given ETag, in which case integrations should respond with a `304 Not
+/// Modified` rather than sending the body again. Since this is for `GET`
+/// requests, weak ETags are compared as if they were strong ones.
+pub fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    match if_none_match {
+        Some(if_none_match) => if_none_match
+            .split(',')
+            .any(|tag| tag.trim() == "*" || strip_weak(tag) == strip_weak(etag)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn etags_match_if_none_match_headers() {
+        let etag = get_etag("hello = Hello!");
+        assert_ne!(etag, get_etag("hello = Bonjour !"));
+        assert!(etag_matches(Some(&etag), &etag));
+        assert!(etag_matches(Some(&format!("\"abc\", W/{}", etag)), &etag));
+        assert!(etag_matches(Some("*"), &etag));
+        assert!(!etag_matches(Some("\"abc\""), &etag));
+        assert!(!etag_matches(None, &etag));
+    }
+}
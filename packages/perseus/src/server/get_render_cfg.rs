@@ -1,21 +1,30 @@
+use crate::build::check_artifacts_version;
 use crate::errors::*;
+use crate::router::RenderCfg;
 use crate::stores::ImmutableStore;
-use std::collections::HashMap;
+use crate::utils::{set_asset_urls, BuildProfile};
 
 /// Gets the configuration of how to render each page using an immutable store.
 ///
 /// The render configuration is an internal build artifact stored somewhere like
 /// `dist/`, generated automatically by the build process. The server provides
 /// it automatically to the client to optimize routing.
-pub async fn get_render_cfg(
-    immutable_store: &ImmutableStore,
-) -> Result<HashMap<String, String>, ServerError> {
+///
+/// This will return an error if the build artifacts were created by an
+/// incompatible version of Perseus, or if the render configuration fails its
+/// integrity check.
+pub async fn get_render_cfg(immutable_store: &ImmutableStore) -> Result<RenderCfg, ServerError> {
+    check_artifacts_version(immutable_store).await?;
     let content = immutable_store.read("render_conf.json").await?;
-    let cfg = serde_json::from_str::<HashMap<String, String>>(&content).map_err(|e| {
+    let cfg = RenderCfg::from_json(&content).map_err(|e| {
         // We have to convert it into a build error and then into a server error
         let build_err: BuildError = e.into();
         build_err
     })?;
+    // Templates need these to reference static aliases
+    set_asset_urls(cfg.assets.clone());
+    // State generated from now on should use the same profile as the build did
+    BuildProfile::set_built(cfg.profile);
 
     Ok(cfg)
 }
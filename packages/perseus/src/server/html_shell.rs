@@ -1,7 +1,12 @@
+use crate::csrf::CSRF_META_NAME;
 use crate::error_pages::ErrorPageData;
+use crate::experiments::Variants;
+use crate::init::WasmLoadingStrategy;
 use crate::page_data::PageData;
-use std::collections::HashMap;
-use std::{env, fmt};
+use crate::router::RenderCfg;
+use crate::session::SessionSnapshot;
+use crate::template::BOOT_INDICATOR_CLASS;
+use std::{env, fmt, ops::Range, sync::Arc};
 
 /// Escapes special characters in page data that might interfere with JavaScript
 /// processing.
@@ -15,6 +20,107 @@ fn escape_page_data(data: &str) -> String {
         .replace(r#"${"#, r#"\${"#)
 }
 
+/// Escapes JSON that will be interpolated into a JS raw string in a `<script>`
+/// with [`escape_page_data`], and also escapes any characters that could let
+/// it close the script early (e.g. a `</script>` in user-controlled data). The
+/// JS string will turn these escapes back into the original characters.
+fn escape_script_json(json: &str) -> String {
+    escape_page_data(json)
+        .replace('<', r#"\u003c"#)
+        .replace('>', r#"\u003e"#)
+        .replace('&', r#"\u0026"#)
+}
+
+/// The part of the script that loads the Wasm bundle that decides when to
+/// call `main()`, based on the strategy set with [`HtmlShell::wasm_loading`].
+/// Browsers without `requestIdleCallback` will load the bundle shortly after
+/// the page has loaded instead.
+const SCHEDULE_MAIN: &str = r#"
+        const loading = window.__PERSEUS_WASM_LOADING;
+        if (loading === "idle") {
+            if ("requestIdleCallback" in window) {
+                requestIdleCallback(() => main(), { timeout: 5000 });
+            } else {
+                setTimeout(() => main(), 200);
+            }
+        } else if (loading === "interaction") {
+            const events = ["pointerdown", "keydown", "touchstart", "focusin", "scroll"];
+            const onInteraction = () => {
+                events.forEach((ev) => window.removeEventListener(ev, onInteraction, true));
+                main();
+            };
+            events.forEach((ev) => window.addEventListener(ev, onInteraction, { capture: true, passive: true }));
+        } else {
+            main();
+        }
+"#;
+
+/// The places in an HTML shell that things are interpolated into.
+#[derive(Clone, Copy, Debug)]
+enum Slot {
+    /// Just after `<head>`.
+    HeadStart,
+    /// Just before `</head>`.
+    HeadEnd,
+    /// Just after `<body>`.
+    BodyStart,
+    /// Just before `</body>`.
+    BodyEnd,
+    /// The root `<div>` of the app, which the page's content goes inside.
+    Content,
+}
+
+/// An HTML shell that's been parsed into a series of segments of text, each
+/// followed by the slot that comes after it (the last one has no slot). This
+/// lets us interpolate into the shell by concatenation, without scanning it
+/// again for every page.
+#[derive(Debug)]
+struct PreparedShell {
+    /// The shell this was parsed from.
+    shell: String,
+    segments: Vec<(Range<usize>, Option<Slot>)>,
+}
+impl PreparedShell {
+    /// Parses the given shell, which should have a `<div>` with the given ID
+    /// for the app to be rendered into.
+    fn parse(shell: &str, root_id: &str) -> Self {
+        // The user MUST place have a `<div>` of this exact form (documented explicitly)
+        // We permit either double or single quotes
+        let root_double = format!("<div id=\"{}\">", root_id);
+        let root_single = format!("<div id='{}'>", root_id);
+        let boundaries = [
+            ("<head>", Slot::HeadStart),
+            ("</head>", Slot::HeadEnd),
+            ("<body>", Slot::BodyStart),
+            ("</body>", Slot::BodyEnd),
+            (root_double.as_str(), Slot::Content),
+            (root_single.as_str(), Slot::Content),
+        ];
+        let mut matches = boundaries
+            .iter()
+            .flat_map(|(boundary, slot)| {
+                shell
+                    .match_indices(boundary)
+                    .map(move |(idx, boundary)| (idx, idx + boundary.len(), *slot))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_unstable_by_key(|(start, _, _)| *start);
+
+        let mut segments = Vec::new();
+        let mut last = 0;
+        for (start, end, slot) in matches {
+            segments.push((last..start, Some(slot)));
+            last = end;
+        }
+        segments.push((last..shell.len(), None));
+
+        Self {
+            shell: shell.to_string(),
+            segments,
+        }
+    }
+}
+
 /// The shell used to interpolate the Perseus app into, including associated
 /// scripts and content defined by the user, components of the Perseus core, and
 /// plugins.
@@ -45,25 +151,21 @@ pub struct HtmlShell {
     /// The path prefix to use.
     #[cfg_attr(not(feature = "preload-wasm-on-redirect"), allow(dead_code))]
     path_prefix: String,
+    /// The shell, parsed ahead of time (see [`HtmlShell::prepare`]).
+    prepared: Option<Arc<PreparedShell>>,
 }
 impl HtmlShell {
     /// Initializes the HTML shell by interpolating necessary scripts into it
     /// and adding the render configuration.
-    pub fn new(
-        shell: String,
-        root_id: &str,
-        render_cfg: &HashMap<String, String>,
-        path_prefix: &str,
-    ) -> Self {
+    pub fn new(shell: String, root_id: &str, render_cfg: &RenderCfg, path_prefix: &str) -> Self {
         let mut head_before_boundary = Vec::new();
         let mut scripts_before_boundary = Vec::new();
 
         // Define the render config as a global variable
         let render_cfg = format!(
             "window.__PERSEUS_RENDER_CFG = '{render_cfg}';",
-            // It's safe to assume that something we just deserialized will serialize again in this
-            // case
-            render_cfg = serde_json::to_string(render_cfg).unwrap()
+            // This is sorted, so the same app will always produce the same HTML
+            render_cfg = render_cfg.to_json()
         );
         scripts_before_boundary.push(render_cfg);
 
@@ -80,6 +182,10 @@ impl HtmlShell {
         //
         // Note: because we're using binary bundles, we don't need to import
         // a `main` function or the like, `init()` just works
+        //
+        // If the app has deferred loading the bundle (see
+        // `HtmlShell::wasm_loading`), `main()` will only be called once the browser
+        // is idle, or once the user first interacts with the page
         #[cfg(not(feature = "wasm2js"))]
         let load_wasm_bundle = format!(
             r#"
@@ -87,9 +193,10 @@ impl HtmlShell {
         async function main() {{
             await init("{path_prefix}/.perseus/bundle.wasm");
         }}
-        main();
+        {schedule_main}
         "#,
-            path_prefix = path_prefix
+            path_prefix = path_prefix,
+            schedule_main = SCHEDULE_MAIN
         );
         #[cfg(feature = "wasm2js")]
         let load_wasm_bundle = format!(
@@ -98,9 +205,10 @@ impl HtmlShell {
         async function main() {{
             await init("{path_prefix}/.perseus/bundle.wasm.js");
         }}
-        main();
+        {schedule_main}
         "#,
-            path_prefix = path_prefix
+            path_prefix = path_prefix,
+            schedule_main = SCHEDULE_MAIN
         );
         scripts_before_boundary.push(load_wasm_bundle);
 
@@ -109,10 +217,13 @@ impl HtmlShell {
         // environment variable here, which is set by the CLI's controller process, not
         // the user That way, we won't do this if the reload server doesn't
         // exist
+        // If browsers reach us at a different host (e.g. other devices on a local
+        // network), they'll need to reach the reload server there too
         #[cfg(debug_assertions)]
         if env::var("PERSEUS_USE_RELOAD_SERVER").is_ok() {
-            let host =
-                env::var("PERSEUS_RELOAD_SERVER_HOST").unwrap_or_else(|_| "localhost".to_string());
+            let host = crate::utils::get_public_host()
+                .or_else(|| env::var("PERSEUS_RELOAD_SERVER_HOST").ok())
+                .unwrap_or_else(|| "localhost".to_string());
             let port =
                 env::var("PERSEUS_RELOAD_SERVER_PORT").unwrap_or_else(|_| "3100".to_string());
             scripts_before_boundary
@@ -139,7 +250,18 @@ impl HtmlShell {
             content: "".into(),
             root_id: root_id.into(),
             path_prefix: path_prefix.into(),
+            prepared: None,
         }
+        .prepare()
+    }
+
+    /// Parses the shell ahead of time, so that interpolating into it for each
+    /// page can be done quickly. This should be called again if `.shell` is
+    /// modified (e.g. by plugins), otherwise the shell will have to be parsed
+    /// again every time it's used.
+    pub fn prepare(mut self) -> Self {
+        self.prepared = Some(Arc::new(PreparedShell::parse(&self.shell, &self.root_id)));
+        self
     }
 
     /// Interpolates page data and global state into the shell.
@@ -177,6 +299,100 @@ impl HtmlShell {
         self
     }
 
+    /// Interpolates the given page into the shell as static HTML, without any
+    /// of the scripts that would load the app, so that it won't be hydrated.
+    /// This is used for pages that could be served at any URL, like the
+    /// maintenance page.
+    pub fn static_page(mut self, page_data: &PageData) -> Self {
+        self.scripts_before_boundary.clear();
+        self.head_after_boundary.push((&page_data.head).into());
+        self.content = (&page_data.content).into();
+
+        self
+    }
+
+    /// Adds a boot indicator to the shell, which marks the `<body>` with the
+    /// `__perseus_booting` class (and shows a small progress bar) until the
+    /// app has been hydrated, at which point the app will remove it.
+    pub fn boot_indicator(mut self) -> Self {
+        self.head_before_boundary.push(format!(
+            "<style>body.{class}{{cursor:progress}}body.{class}::before{{content:\"\";position:fixed;top:0;left:0;z-index:2147483647;width:30%;height:3px;background:#3b82f6;animation:{class} 1s ease-in-out infinite alternate}}@keyframes {class}{{to{{left:70%}}}}</style>",
+            class = BOOT_INDICATOR_CLASS
+        ));
+        // This runs as soon as the `<body>` exists, before anything is painted
+        self.before_content.push(format!(
+            r#"<script>document.body.classList.add("{}");</script>"#,
+            BOOT_INDICATOR_CLASS
+        ));
+
+        self
+    }
+
+    /// Sets when the browser should start loading the app's Wasm bundle. Until
+    /// it's loaded, the prerendered page will be shown, but it won't be
+    /// interactive.
+    pub fn wasm_loading(mut self, strategy: WasmLoadingStrategy) -> Self {
+        let strategy = match strategy {
+            WasmLoadingStrategy::Immediate => return self,
+            WasmLoadingStrategy::Idle => "idle",
+            WasmLoadingStrategy::Interaction => "interaction",
+        };
+        // This has to be a classic script so that it runs before the module that loads
+        // the bundle
+        self.head_before_boundary.push(format!(
+            r#"<script>window.__PERSEUS_WASM_LOADING = "{}";</script>"#,
+            strategy
+        ));
+
+        self
+    }
+
+    /// Embeds the given CSRF token into the shell (see [`crate::csrf`]).
+    pub fn csrf_token(mut self, token: &str) -> Self {
+        // Tokens minted by Perseus are hex, so this needs no escaping
+        self.head_before_boundary.push(format!(
+            r#"<meta name="{}" content="{}">"#,
+            CSRF_META_NAME, token
+        ));
+
+        self
+    }
+
+    /// Interpolates the A/B experiment variants the user has been assigned
+    /// into the shell, so that they can be used in the browser. If there
+    /// aren't any, this will do nothing.
+    pub fn variants(mut self, variants: &Variants) -> Self {
+        if !variants.is_empty() {
+            // This is made only of strings, so it can't fail to serialize, and JSON
+            // escaping handles any quotes (but not backslashes or anything that could
+            // close the script, which we escape for the JS string)
+            let variants = serde_json::to_string(variants).unwrap();
+            self.scripts_before_boundary.push(format!(
+                "window.__PERSEUS_VARIANTS = `{}`;",
+                escape_script_json(&variants)
+            ));
+        }
+
+        self
+    }
+
+    /// Interpolates the public data of the user's session into the shell, so
+    /// that it can be used in the browser. If there isn't any, this will do
+    /// nothing.
+    pub fn session(mut self, snapshot: &SessionSnapshot) -> Self {
+        if !snapshot.is_empty() {
+            // As for variants, JSON escaping handles quotes, but this data is often
+            // user-controlled, so anything that could close the script must be escaped
+            let snapshot = serde_json::to_string(snapshot).unwrap();
+            self.scripts_before_boundary.push(format!(
+                "window.__PERSEUS_SESSION = `{}`;",
+                escape_script_json(&snapshot)
+            ));
+        }
+
+        self
+    }
+
     /// Interpolates a fallback for locale redirection pages such that, even if
     /// JavaScript is disabled, the user will still be redirected to the default
     /// locale. From there, Perseus' inbuilt progressive enhancement can
@@ -265,47 +481,126 @@ impl HtmlShell {
 // unexpected ways
 impl fmt::Display for HtmlShell {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let head_start = self.head_before_boundary.join("\n");
-        // We also inject a delimiter comment that will be used to wall off the constant
-        // document head from the interpolated document head
-        let head_end = format!(
-            r#"
+        // If the shell has changed since it was prepared, we have to parse it again
+        let parsed;
+        let prepared = match &self.prepared {
+            Some(prepared) if prepared.shell == self.shell => prepared.as_ref(),
+            _ => {
+                parsed = PreparedShell::parse(&self.shell, &self.root_id);
+                &parsed
+            }
+        };
+
+        for (text, slot) in prepared.segments.iter() {
+            f.write_str(&prepared.shell[text.clone()])?;
+            match slot {
+                Some(Slot::HeadStart) => {
+                    f.write_str("<head>")?;
+                    f.write_str(&self.head_before_boundary.join("\n"))?;
+                }
+                // We also inject a delimiter comment that will be used to wall off the
+                // constant document head from the interpolated document head
+                Some(Slot::HeadEnd) => write!(
+                    f,
+                    r#"
             <script type="module">{scripts_before_boundary}</script>
             <!--PERSEUS_INTERPOLATED_HEAD_BEGINS-->
             {head_after_boundary}
             <script>{scripts_after_boundary}</script>
-            "#,
-            scripts_before_boundary = self.scripts_before_boundary.join("\n"),
-            head_after_boundary = self.head_after_boundary.join("\n"),
-            scripts_after_boundary = self.scripts_after_boundary.join("\n"),
+            </head>"#,
+                    scripts_before_boundary = self.scripts_before_boundary.join("\n"),
+                    head_after_boundary = self.head_after_boundary.join("\n"),
+                    scripts_after_boundary = self.scripts_after_boundary.join("\n"),
+                )?,
+                Some(Slot::BodyStart) => {
+                    f.write_str("<body>")?;
+                    f.write_str(&self.before_content.join("\n"))?;
+                }
+                Some(Slot::BodyEnd) => {
+                    f.write_str(&self.after_content.join("\n"))?;
+                    f.write_str("</body>")?;
+                }
+                // We give the content a specific ID so that it can be deleted if an error page
+                // needs to be rendered on the client-side
+                Some(Slot::Content) => write!(
+                    f,
+                    r#"<div id="{}"><div id="__perseus_content_initial" class="__perseus_content">{}</div>"#,
+                    self.root_id, self.content,
+                )?,
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn interpolates_into_prepared_shell() {
+        let render_cfg = RenderCfg::new(BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), false);
+        let shell = HtmlShell::new(
+            "<html><head><title>Test</title></head><body><div id=\"root\"></div></body></html>"
+                .to_string(),
+            "root",
+            &render_cfg,
+            "",
         );
+        let page_data = PageData {
+            content: "<p>Hello</body></p>".to_string(),
+            state: None,
+            head: "<meta name=\"test\" />".to_string(),
+            redirect: None,
+        };
+        let html = shell.clone().page_data(&page_data, &None).to_string();
 
-        let shell_with_head = self
-            .shell
-            .replace("<head>", &format!("<head>{}", head_start))
-            .replace("</head>", &format!("{}</head>", head_end));
+        assert!(html.starts_with("<html><head><base href=\"/\" /><title>Test</title>"));
+        assert!(html.contains("<meta name=\"test\" />"));
+        assert!(html.contains(
+            "<div id=\"root\"><div id=\"__perseus_content_initial\" class=\"__perseus_content\"><p>Hello</body></p></div></div></body></html>"
+        ));
+        // Boundaries in the interpolated content shouldn't be touched
+        assert_eq!(html.matches("</head>").count(), 1);
 
-        let body_start = self.before_content.join("\n");
-        let body_end = self.after_content.join("\n");
-        let shell_with_body = shell_with_head
-            .replace("<body>", &format!("<body>{}", body_start))
-            .replace("</body>", &format!("{}</body>", body_end));
+        // The bundle should be loaded straight away unless the app says otherwise
+        assert!(!html.contains("__PERSEUS_WASM_LOADING = "));
+        let deferred = shell
+            .clone()
+            .wasm_loading(WasmLoadingStrategy::Interaction)
+            .page_data(&page_data, &None)
+            .to_string();
+        assert!(deferred.contains(r#"window.__PERSEUS_WASM_LOADING = "interaction";"#));
 
-        // The user MUST place have a `<div>` of this exact form (documented explicitly)
-        // We permit either double or single quotes
-        let html_to_replace_double = format!("<div id=\"{}\">", self.root_id);
-        let html_to_replace_single = format!("<div id='{}'>", self.root_id);
-        let html_replacement = format!(
-            // We give the content a specific ID so that it can be deleted if an error page needs
-            // to be rendered on the client-side
-            r#"{}<div id="__perseus_content_initial" class="__perseus_content">{}</div>"#,
-            &html_to_replace_double, self.content,
+        // Changes to the shell after it was prepared should still be picked up
+        let mut shell = shell;
+        shell.shell = "<body><div id='root'></div></body>".to_string();
+        assert!(shell
+            .to_string()
+            .contains(r#"<div id="root"><div id="__perseus_content_initial""#));
+    }
+
+    #[test]
+    fn escapes_script_breakouts_in_session_and_variants() {
+        let render_cfg = RenderCfg::new(BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), false);
+        let shell = HtmlShell::new(
+            "<html><head></head><body></body></html>".to_string(),
+            "root",
+            &render_cfg,
+            "",
         );
-        // Now interpolate that HTML into the HTML shell
-        let new_shell = shell_with_body
-            .replace(&html_to_replace_double, &html_replacement)
-            .replace(&html_to_replace_single, &html_replacement);
+        let payload = "</script><script>alert('pwned')</script>";
+        let snapshot: SessionSnapshot =
+            serde_json::from_value(serde_json::json!({ "name": payload })).unwrap();
+        let variants = Variants::single("experiment", payload);
+        let html = shell.session(&snapshot).variants(&variants).to_string();
 
-        f.write_str(&new_shell)
+        // The only script closers should be Perseus' own
+        assert!(!html.contains(payload));
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains(r#"\u003c/script\u003e\u003cscript\u003ealert('pwned')"#));
     }
 }
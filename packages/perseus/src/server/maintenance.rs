@@ -0,0 +1,218 @@
+use super::{build_error_page, get_page_for_template, get_path_slice, GetPageProps, ServerOptions};
+use crate::csrf::constant_time_eq;
+use crate::i18n::TranslationsManager;
+use crate::router::{match_route_atomic, RenderCfg, RouteInfoAtomic, RouteVerdictAtomic};
+use crate::stores::{ImmutableStore, MutableStore};
+use crate::Request;
+use fmterr::fmt_err;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// The path of the endpoint that maintenance mode can be turned on and off
+/// through while the server is running.
+pub const MAINTENANCE_ENDPOINT: &str = "/.perseus/maintenance";
+
+/// Settings for the app's maintenance mode, in which every page will be
+/// replaced by a maintenance page, served with `503 Service Unavailable` and a
+/// `Retry-After` header. Static content and bundles are still served as
+/// normal, so the maintenance page can use them.
+///
+/// Maintenance mode is turned on when the server starts if the
+/// `PERSEUS_MAINTENANCE` environment variable is set to `true`. It can also be
+/// turned on and off while the server is running by sending a `POST` request
+/// to `/.perseus/maintenance` with a body of `on` or `off`, authenticated with
+/// the token in the `PERSEUS_MAINTENANCE_TOKEN` environment variable as a
+/// bearer token (if that isn't set, this endpoint is disabled).
+#[derive(Clone, Debug)]
+pub struct MaintenanceOptions {
+    /// The path of the page to serve in maintenance mode (without a locale,
+    /// the app's default locale will be used), which should be one that's
+    /// prerendered at build-time. The page will be served without the app's
+    /// Wasm bundle, so it won't be interactive. If this isn't set, the error
+    /// page for `503` will be served instead.
+    pub page: Option<String>,
+    /// The number of seconds clients should be told to wait before trying
+    /// again. By default, this is five minutes.
+    pub retry_after: u64,
+}
+impl Default for MaintenanceOptions {
+    fn default() -> Self {
+        Self {
+            page: None,
+            retry_after: 300,
+        }
+    }
+}
+
+/// The current state of the server's maintenance mode, which integrations
+/// should check before serving pages (see [`MaintenanceOptions`]). Clones of
+/// this share the same state.
+#[derive(Clone, Debug)]
+pub struct Maintenance {
+    opts: MaintenanceOptions,
+    enabled: Arc<AtomicBool>,
+    token: Option<String>,
+    /// The fully rendered maintenance page.
+    html: Arc<RwLock<Option<String>>>,
+}
+impl Maintenance {
+    /// Sets up maintenance mode with the given options, turning it on if the
+    /// `PERSEUS_MAINTENANCE` environment variable is set to `true`.
+    pub fn new(opts: MaintenanceOptions) -> Self {
+        let enabled = matches!(env::var("PERSEUS_MAINTENANCE").as_deref(), Ok("true" | "1"));
+        let token = env::var("PERSEUS_MAINTENANCE_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+
+        Self {
+            opts,
+            enabled: Arc::new(AtomicBool::new(enabled)),
+            token,
+            html: Arc::new(RwLock::new(None)),
+        }
+    }
+    /// Whether or not the server is currently in maintenance mode.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+    /// Turns maintenance mode on or off.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+    /// Gets the value of the `Retry-After` header that responses in
+    /// maintenance mode should have.
+    pub fn retry_after(&self) -> String {
+        self.opts.retry_after.to_string()
+    }
+    /// Gets the HTML of the maintenance page if the server is in maintenance
+    /// mode. If this returns something, integrations should serve it in place
+    /// of any page with `503 Service Unavailable` and a `Retry-After` header.
+    pub fn get_page(&self) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let html = self.html.read().unwrap_or_else(|err| err.into_inner());
+        // If the integration didn't prepare a page, there's still no point in sending
+        // down the app
+        Some(
+            html.clone()
+                .unwrap_or_else(|| "<h1>Down for maintenance</h1>".to_string()),
+        )
+    }
+    /// Handles a request to [`MAINTENANCE_ENDPOINT`] with the given
+    /// `Authorization` header and body, returning the status code that should
+    /// be responded with.
+    pub fn handle_toggle(&self, authorization: Option<&str>, body: &str) -> u16 {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return 404,
+        };
+        let provided = authorization.and_then(|header| header.strip_prefix("Bearer "));
+        if !provided.map_or(false, |provided| {
+            constant_time_eq(provided.as_bytes(), token.as_bytes())
+        }) {
+            return 401;
+        }
+        match body.trim() {
+            "on" => self.set_enabled(true),
+            "off" => self.set_enabled(false),
+            _ => return 400,
+        }
+
+        204
+    }
+}
+
+/// Renders the app's maintenance page ahead of time, so that it can be served
+/// without touching any stores if the server goes into maintenance mode (e.g.
+/// while a deploy is replacing them). This should be called by server
+/// integrations once everything else has been set up, before they start
+/// listening for requests. If the maintenance page fails to render, the error
+/// page for `503` will be used instead.
+pub async fn prepare_maintenance_page<
+    M: MutableStore + 'static,
+    T: TranslationsManager + 'static,
+>(
+    opts: &ServerOptions,
+    render_cfg: &RenderCfg,
+    immutable_store: &ImmutableStore,
+    mutable_store: &M,
+    translations_manager: &T,
+    global_state: &Option<String>,
+) {
+    let mut html = None;
+    if let Some(page) = &opts.maintenance.opts.page {
+        let path = match opts.locales.using_i18n {
+            true => format!("{}/{}", opts.locales.default, page.trim_start_matches('/')),
+            false => page.to_string(),
+        };
+        let path_slice = get_path_slice(&path);
+        let verdict =
+            match_route_atomic(&path_slice, render_cfg, &opts.templates_map, &opts.locales);
+        match verdict {
+            RouteVerdictAtomic::Found(RouteInfoAtomic {
+                path: raw_path,
+                template,
+                locale,
+                was_incremental_match,
+            }) => {
+                // There's no real request, so we make a blank one for this page
+                let req = Request::builder()
+                    .uri(format!("/{}", path.trim_start_matches('/')))
+                    .body(())
+                    .unwrap_or_default();
+                let res = get_page_for_template(
+                    GetPageProps::<M, T> {
+                        raw_path: &raw_path,
+                        locale: &locale,
+                        was_incremental_match,
+                        req,
+                        global_state,
+                        immutable_store,
+                        mutable_store,
+                        translations_manager,
+                        plugins: &opts.plugins,
+                        incremental_warmup: &opts.incremental_warmup,
+                        templates: &opts.templates_map,
+                        error_reporter: &opts.error_reporter,
+                        state_fn_timeout: opts.state_fn_timeout,
+                        build_redirects: &opts.build_redirects,
+                    },
+                    template,
+                )
+                .await;
+                match res {
+                    Ok(page_data) => {
+                        html = Some(opts.html_shell.clone().static_page(&page_data).to_string())
+                    }
+                    Err(err) => eprintln!(
+                        "[WARNING]: couldn't render maintenance page '{}': {}",
+                        page,
+                        fmt_err(&err)
+                    ),
+                }
+            }
+            _ => eprintln!(
+                "[WARNING]: maintenance page '{}' doesn't match any template",
+                page
+            ),
+        }
+    }
+    let html = html.unwrap_or_else(|| {
+        build_error_page(
+            MAINTENANCE_ENDPOINT,
+            503,
+            "the server is down for maintenance",
+            opts.error_translations.get_translator(MAINTENANCE_ENDPOINT),
+            &opts.error_pages,
+            &opts.html_shell,
+        )
+    });
+
+    *opts
+        .maintenance
+        .html
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some(html);
+}
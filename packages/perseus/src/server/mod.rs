@@ -5,16 +5,42 @@
 //! need to use this module (though some plugins may need types in here).
 
 mod build_error_page;
+mod error_translations;
+mod etag;
 mod get_render_cfg;
 mod html_shell;
+mod maintenance;
 mod options;
 mod render;
+mod request_context;
+mod request_log;
+mod static_alias;
+mod static_content;
+mod warmup;
 
-pub use build_error_page::build_error_page;
+pub use build_error_page::{build_error_page, build_server_error_page};
+pub use error_translations::ErrorTranslations;
+pub use etag::{etag_matches, get_etag};
 pub use get_render_cfg::get_render_cfg;
 pub use html_shell::HtmlShell;
-pub use options::{ServerOptions, ServerProps};
-pub use render::{get_page, get_page_for_template, GetPageProps};
+pub use maintenance::{
+    prepare_maintenance_page, Maintenance, MaintenanceOptions, MAINTENANCE_ENDPOINT,
+};
+pub use options::{ServerOptions, ServerOptionsBuilder, ServerProps};
+pub(crate) use render::write_revalidation_schedule;
+pub use render::{
+    get_page, get_page_for_template, get_redirect_page_data, mark_for_revalidation, GetPageProps,
+    PageRequestInfo,
+};
+pub use request_context::{
+    attach_request_context, ClientIp, DeviceKind, RemoteAddr, RequestContext, TrustedProxies,
+};
+pub use static_alias::{get_static_alias, get_static_alias_mount};
+pub use static_content::{get_static_file, StaticContentOptions};
+pub(crate) use warmup::record_incremental_request;
+pub use warmup::{warm_up_incremental, IncrementalWarmup};
+
+use crate::Request;
 
 /// Removes empty elements from a path, which is important due to double
 /// slashes. This returns a vector of the path's components;
@@ -23,3 +49,27 @@ pub fn get_path_slice(path: &str) -> Vec<&str> {
 
     path_slice
 }
+
+/// Replaces the query string of the given request with the given one (which
+/// should not have a leading `?`). On subsequent loads, the browser requests
+/// page data from a dedicated endpoint, sending the query string of the page
+/// the user is actually on as a parameter, so integrations should use this to
+/// make that query string available to request-state functions. If the given
+/// query string would produce an invalid URI, the request will be returned
+/// unchanged.
+pub fn set_req_query(req: Request, query: &str) -> Request {
+    let (mut parts, body) = req.into_parts();
+    let path_and_query = match query.is_empty() {
+        true => parts.uri.path().to_string(),
+        false => format!("{}?{}", parts.uri.path(), query),
+    };
+    let mut uri_parts = parts.uri.clone().into_parts();
+    if let Ok(path_and_query) = path_and_query.parse() {
+        uri_parts.path_and_query = Some(path_and_query);
+        if let Ok(uri) = http::Uri::from_parts(uri_parts) {
+            parts.uri = uri;
+        }
+    }
+
+    Request::from_parts(parts, body)
+}
@@ -1,18 +1,35 @@
+use crate::engine::{detect_standalone, set_standalone_dir};
 use crate::error_pages::ErrorPages;
+use crate::errors::{EngineError, ErrorReporter};
+use crate::experiments::Experiments;
 use crate::i18n::Locales;
 use crate::i18n::TranslationsManager;
+use crate::plugins::{PluginAction, Plugins};
+use crate::router::BuildRedirects;
+use crate::session::Sessions;
 use crate::state::GlobalStateCreator;
 use crate::stores::{ImmutableStore, MutableStore};
 use crate::template::ArcTemplateMap;
-use crate::SsrNode;
+use crate::utils::{add_fingerprinted_aliases, get_dist_dir, get_public_host};
+use crate::{PerseusAppBase, SsrNode};
+use fmterr::fmt_err;
+use futures::executor::block_on;
 use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::HtmlShell;
+use super::{
+    get_render_cfg, ErrorTranslations, HtmlShell, IncrementalWarmup, Maintenance,
+    StaticContentOptions, TrustedProxies,
+};
 
-/// The options for setting up all server integrations. This should be literally
-/// constructed, as nothing is optional. If integrations need further
-/// properties, they should expose their own options in addition to these.
+/// The options for setting up all server integrations. These should be created
+/// with [`ServerOptions::builder`], which derives them from your app and the
+/// engine's environment in the same way for every integration. If integrations
+/// need further properties, they should expose their own options in addition
+/// to these.
 #[derive(Debug, Clone)]
 pub struct ServerOptions {
     /// The location on the filesystem of your JavaScript bundle.
@@ -29,6 +46,11 @@ pub struct ServerOptions {
     pub templates_map: ArcTemplateMap<SsrNode>,
     /// The locales information for the app.
     pub locales: Locales,
+    /// The path that the root of the app should redirect to, if there is one.
+    /// Integrations should check for this with
+    /// [`get_index_redirect`](crate::router::get_index_redirect) before
+    /// matching routes for initial loads.
+    pub index_redirect: Option<String>,
     /// The HTML `id` of the element at which to render Perseus. On the
     /// server-side, interpolation will be done here in a highly
     /// efficient manner by not parsing the HTML, so this MUST be of the form
@@ -40,6 +62,11 @@ pub struct ServerOptions {
     /// The error pages for the app. These will be server-rendered if an initial
     /// load fails.
     pub error_pages: Arc<ErrorPages<SsrNode>>,
+    /// The translations error pages for initial loads should be rendered with.
+    /// Integrations should get the translator to pass to
+    /// [`build_error_page`](super::build_error_page) with
+    /// [`ErrorTranslations::get_translator`].
+    pub error_translations: ErrorTranslations,
     /// The directory to serve static content from, which will be mapped to
     /// `/.perseus/static`in the browser.
     pub static_dir: Option<String>,
@@ -48,6 +75,62 @@ pub struct ServerOptions {
     /// favicons, which should be stored in a static directory, but need to be
     /// aliased at a path like `/favicon.ico`.
     pub static_aliases: HashMap<String, String>,
+    /// How the static directory and static aliases should be served.
+    /// Integrations should get the file to serve for any of these with
+    /// [`get_static_file`](super::get_static_file), rather than serving them
+    /// directly.
+    pub static_content: StaticContentOptions,
+    /// The state of the server's maintenance mode. Integrations should serve
+    /// [`Maintenance::get_page`] in place of any page if it returns something,
+    /// and handle requests to
+    /// [`MAINTENANCE_ENDPOINT`](super::MAINTENANCE_ENDPOINT) with
+    /// [`Maintenance::handle_toggle`].
+    pub maintenance: Maintenance,
+    /// The contents of the app's `robots.txt` file, if it has one, which will
+    /// be served at `/robots.txt`.
+    pub robots_txt: Option<String>,
+    /// The incrementally generated pages to pre-render when the server starts.
+    pub incremental_warmup: IncrementalWarmup,
+    /// The host browsers will reach the server at, if that's different from
+    /// the one it's listening on (e.g. when testing on other devices on a
+    /// local network). This will already have been applied to the HTML shell,
+    /// but integrations should use it if they need to construct any absolute
+    /// URLs themselves.
+    pub public_host: Option<String>,
+    /// The A/B experiments the app is running. Integrations should assign
+    /// variants of these to every page request with
+    /// [`assign_variants`](crate::experiments::assign_variants).
+    pub experiments: Experiments,
+    /// Whether or not users should be given CSRF tokens on initial loads.
+    /// Integrations should do this with
+    /// [`get_or_mint_csrf_token`](crate::csrf::get_or_mint_csrf_token) and
+    /// [`HtmlShell::csrf_token`] (marking the response as private with
+    /// [`make_private`](crate::utils::make_private)), and should provide a way
+    /// of validating requests to users' own routes.
+    pub csrf_protection: bool,
+    /// The proxies that should be trusted to report the addresses of clients.
+    /// Integrations should pass these options to
+    /// [`attach_request_context`](super::attach_request_context), which will
+    /// take care of this.
+    pub trusted_proxies: TrustedProxies,
+    /// The app's session configuration, if sessions are enabled. Integrations
+    /// should attach a session to every request with [`Sessions::attach`],
+    /// commit it with [`Sessions::commit`] after the page has been rendered,
+    /// and interpolate its public data with [`HtmlShell::session`] on initial
+    /// loads.
+    pub sessions: Option<Sessions>,
+    /// The app's plugins, which can be shared freely between threads.
+    pub plugins: Arc<Plugins<SsrNode>>,
+    /// The function panics while rendering pages should be reported to, if
+    /// the app has one.
+    pub error_reporter: Option<ErrorReporter>,
+    /// How long request-time state functions are given before they're
+    /// cancelled, for templates that don't set their own timeouts.
+    pub state_fn_timeout: Option<Duration>,
+    /// The pages that redirected elsewhere at build-time, from the render
+    /// configuration. These should be passed through to
+    /// [`get_page_for_template`](super::get_page_for_template).
+    pub build_redirects: BuildRedirects,
 }
 
 /// The full set of properties that all server integrations take.
@@ -65,3 +148,248 @@ pub struct ServerProps<M: MutableStore, T: TranslationsManager> {
     /// cloning in Actix Web.
     pub global_state_creator: Arc<GlobalStateCreator>,
 }
+
+impl ServerOptions {
+    /// Creates a builder for the options (and the rest of the properties) to
+    /// serve the given app with. Everything will be derived from the app and
+    /// the build artifacts the CLI produces, so this will usually be used as
+    /// `ServerOptions::builder(app).build()`.
+    pub fn builder<M: MutableStore, T: TranslationsManager>(
+        app: PerseusAppBase<SsrNode, M, T>,
+    ) -> ServerOptionsBuilder<M, T> {
+        ServerOptionsBuilder {
+            app,
+            standalone: None,
+            js_bundle: None,
+            wasm_bundle: None,
+            wasm_js_bundle: None,
+            snippets: None,
+        }
+    }
+}
+
+/// A builder for the properties to pass to a server integration, created with
+/// [`ServerOptions::builder`]. This is where the server's working directory is
+/// settled, so all the paths it produces (and any given to it) are relative to
+/// the same place: the parent of the binary in standalone deployments, and the
+/// root of the project otherwise.
+///
+/// Before anything is served, this will make sure the render configuration,
+/// the app's bundles, and any generated `robots.txt` can be found, reporting
+/// all the problems together if they can't.
+#[derive(Debug)]
+pub struct ServerOptionsBuilder<M: MutableStore, T: TranslationsManager> {
+    app: PerseusAppBase<SsrNode, M, T>,
+    standalone: Option<bool>,
+    js_bundle: Option<String>,
+    wasm_bundle: Option<String>,
+    wasm_js_bundle: Option<String>,
+    snippets: Option<String>,
+}
+impl<M: MutableStore, T: TranslationsManager> ServerOptionsBuilder<M, T> {
+    /// Sets whether or not the server is running as a standalone deployment,
+    /// in which case its working directory will be changed to the parent of
+    /// the binary. By default, this is assumed for release builds, and for any
+    /// binary that's run from somewhere other than the root of the project
+    /// while there's a distribution directory next to it.
+    pub fn standalone(mut self, val: bool) -> Self {
+        self.standalone = Some(val);
+        self
+    }
+    /// Sets the location of the JS bundle, which is `pkg/perseus_engine.js` in
+    /// the distribution directory by default.
+    pub fn js_bundle(mut self, val: impl Into<String>) -> Self {
+        self.js_bundle = Some(val.into());
+        self
+    }
+    /// Sets the location of the Wasm bundle, which is
+    /// `pkg/perseus_engine_bg.wasm` in the distribution directory by default.
+    pub fn wasm_bundle(mut self, val: impl Into<String>) -> Self {
+        self.wasm_bundle = Some(val.into());
+        self
+    }
+    /// Sets the location of the JS bundle converted from the Wasm bundle for
+    /// older browsers, which is `pkg/perseus_engine_bg.wasm.js` in the
+    /// distribution directory by default. Unlike the other bundles, this
+    /// doesn't have to exist.
+    pub fn wasm_js_bundle(mut self, val: impl Into<String>) -> Self {
+        self.wasm_js_bundle = Some(val.into());
+        self
+    }
+    /// Sets the location of the JS interop snippets, which is `pkg/snippets`
+    /// in the distribution directory by default.
+    pub fn snippets(mut self, val: impl Into<String>) -> Self {
+        self.snippets = Some(val.into());
+        self
+    }
+    /// Builds the properties for the server, invoking plugin opportunities as
+    /// necessary. This consumes the app.
+    ///
+    /// WARNING: in standalone deployments, this will set the working directory
+    /// to be the parent of the actual binary!
+    pub fn build(self) -> Result<ServerProps<M, T>, EngineError> {
+        let Self {
+            app,
+            standalone,
+            js_bundle,
+            wasm_bundle,
+            wasm_js_bundle,
+            snippets,
+        } = self;
+        set_standalone_dir(standalone.unwrap_or_else(detect_standalone));
+
+        let plugins = app.get_plugins();
+        plugins
+            .functional_actions
+            .server_actions
+            .before_serve
+            .run((), plugins.get_plugin_data());
+
+        // We don't support setting some attributes from `wasm-pack` through
+        // plugins/`PerseusApp` because that would require CLI changes as well
+        // (a job for an alternative engine)
+        let dist_dir = get_dist_dir();
+        let js_bundle = js_bundle.unwrap_or_else(|| format!("{}/pkg/perseus_engine.js", dist_dir));
+        // Our crate has the same name, so this will be predictable
+        let wasm_bundle =
+            wasm_bundle.unwrap_or_else(|| format!("{}/pkg/perseus_engine_bg.wasm", dist_dir));
+        // This probably won't exist, but on the off chance that the user needs to
+        // support older browsers, we'll provide it anyway
+        let wasm_js_bundle =
+            wasm_js_bundle.unwrap_or_else(|| format!("{}/pkg/perseus_engine_bg.wasm.js", dist_dir));
+        let snippets = snippets.unwrap_or_else(|| format!("{}/pkg/snippets", dist_dir));
+
+        // By the time this binary is being run, the app should already have been built
+        // by the CLI, so we make sure of that before going any further
+        let mut problems = Vec::new();
+        let immutable_store = app.get_immutable_store();
+        let render_cfg = match block_on(get_render_cfg(&immutable_store)) {
+            Ok(render_cfg) => Some(render_cfg),
+            Err(err) => {
+                problems.push(fmt_err(&err));
+                None
+            }
+        };
+        for bundle in [&js_bundle, &wasm_bundle] {
+            if !Path::new(bundle).is_file() {
+                problems.push(format!("the app bundle file '{}' is missing", bundle));
+            }
+        }
+        // This will have been generated by the build process
+        let robots_txt = match app.get_robots_txt() {
+            Some(_) => match block_on(immutable_store.read("robots.txt")) {
+                Ok(robots_txt) => Some(robots_txt),
+                Err(_) => {
+                    problems.push("couldn't read the generated `robots.txt`".to_string());
+                    None
+                }
+            },
+            None => None,
+        };
+        let render_cfg = match render_cfg {
+            Some(render_cfg) if problems.is_empty() => render_cfg,
+            _ => {
+                return Err(EngineError::InvalidServerOptions {
+                    problems: problems
+                        .iter()
+                        .map(|problem| format!("- {}", problem))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                })
+            }
+        };
+
+        let static_dir_path = app.get_static_dir();
+        let app_root = app.get_root();
+        let index_view_str = app.get_index_view_str();
+        let mut index_view = block_on(PerseusAppBase::<SsrNode, M, T>::get_html_shell(
+            index_view_str,
+            &app_root,
+            &immutable_store,
+            &plugins,
+        ));
+        index_view.shell = plugins
+            .functional_actions
+            .server_actions
+            .transform_html_shell
+            .run_chained(index_view.shell, plugins.get_plugin_data());
+        if app.get_boot_indicator() {
+            index_view = index_view.boot_indicator();
+        }
+        index_view = index_view.wasm_loading(app.get_wasm_loading());
+        let index_view = index_view.prepare();
+
+        let templates_map = app.get_atomic_templates_map();
+        let locales = render_cfg.built_locales(app.get_locales());
+        let index_redirect = app.get_index_redirect();
+        let error_pages = Arc::new(app.get_error_pages());
+        let mut static_aliases = app.get_static_aliases();
+        // Fingerprinted static aliases are served at their hashed URLs as well
+        add_fingerprinted_aliases(&mut static_aliases, &render_cfg.assets);
+        let static_content = app.get_static_content();
+        let maintenance = Maintenance::new(app.get_maintenance());
+        let incremental_warmup = app.get_incremental_warmup();
+        let experiments = app.get_experiments();
+        let csrf_protection = app.get_csrf_protection();
+        let trusted_proxies = app.get_trusted_proxies();
+        let sessions = app.get_sessions();
+        let mutable_store = app.get_mutable_store();
+        let global_state_creator = app.get_global_state_creator();
+        let embedded_translations = app.get_embedded_translations();
+        let error_reporter = app.get_error_reporter();
+        let state_fn_timeout = app.get_state_fn_timeout();
+        // This consumes the app, which means we'll have the only reference to the
+        // plugins after it
+        let translations_manager = block_on(app.get_translations_manager());
+        let error_translations = block_on(ErrorTranslations::new(
+            &locales,
+            &translations_manager,
+            embedded_translations,
+        ));
+        // The server needs to run plugin actions on every request, so it needs to
+        // own them in a way that can be shared between threads
+        let plugins = Rc::try_unwrap(plugins).map_err(|_| EngineError::PluginsStillReferenced)?;
+
+        let opts = ServerOptions {
+            html_shell: index_view,
+            js_bundle,
+            wasm_bundle,
+            wasm_js_bundle,
+            templates_map,
+            locales,
+            index_redirect,
+            root_id: app_root,
+            snippets,
+            error_pages,
+            error_translations,
+            // This will be available directly at `/.perseus/static`
+            static_dir: if Path::new(&static_dir_path).exists() {
+                Some(static_dir_path)
+            } else {
+                None
+            },
+            static_aliases,
+            static_content,
+            maintenance,
+            robots_txt,
+            incremental_warmup,
+            public_host: get_public_host(),
+            experiments,
+            csrf_protection,
+            trusted_proxies,
+            sessions,
+            plugins: Arc::new(plugins),
+            error_reporter,
+            state_fn_timeout,
+            build_redirects: render_cfg.redirects,
+        };
+
+        Ok(ServerProps {
+            opts,
+            immutable_store,
+            mutable_store,
+            global_state_creator,
+            translations_manager,
+        })
+    }
+}
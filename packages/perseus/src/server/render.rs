@@ -1,20 +1,85 @@
 use crate::errors::*;
+use crate::experiments::Variants;
 use crate::i18n::TranslationsManager;
 use crate::page_data::PageData;
+use crate::plugins::{PluginAction, Plugins};
+use crate::router::BuildRedirects;
+use crate::server::request_log::RequestLog;
+use crate::server::{
+    record_incremental_request, ClientIp, IncrementalWarmup, RemoteAddr, RequestContext,
+};
+use crate::session::SessionHandle;
 use crate::stores::{ImmutableStore, MutableStore};
-use crate::template::{PageProps, States, Template, TemplateMap};
+use crate::template::{ArcTemplateMap, PageInfo, PageProps, States, Template, TemplateMap};
 use crate::translator::Translator;
 use crate::Request;
 use crate::SsrNode;
 use chrono::{DateTime, Utc};
+use fmterr::fmt_err;
+use futures::{Future, FutureExt};
+use http::{Extensions, HeaderMap, Uri};
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-/// Clones a `Request` from its internal parts.
-fn clone_req(raw: &Request) -> Request {
+/// How long a lock on regenerating a page will be held for before it expires,
+/// in case the server instance holding it crashes.
+const REGENERATION_LOCK_TTL: Duration = Duration::from_secs(30);
+/// How often to check if another server instance has finished generating a
+/// page we're waiting for.
+const REGENERATION_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for another server instance to finish generating a page
+/// before giving up. This is a little longer than the lock lasts, so that a
+/// lock left behind by an instance that crashed can be taken over.
+const REGENERATION_LOCK_WAIT: Duration = Duration::from_secs(35);
+
+/// The encoded paths of the pages currently being revalidated in the
+/// background, which prevents a flood of requests for a stale page from all
+/// revalidating it at once.
+static REVALIDATING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Marks a page as being revalidated in the background for as long as it's
+/// held, so that the page will be unmarked even if revalidation panics.
+struct RevalidatingGuard(String);
+impl RevalidatingGuard {
+    /// Marks the page with the given encoded path as being revalidated, unless
+    /// it already is, in which case this will return `None`.
+    fn new(path_encoded: &str) -> Option<Self> {
+        // The list is always left consistent, so it's fine to use after a panic
+        let mut revalidating = REVALIDATING.lock().unwrap_or_else(PoisonError::into_inner);
+        if revalidating.iter().any(|p| p == path_encoded) {
+            return None;
+        }
+        revalidating.push(path_encoded.to_string());
+
+        Some(Self(path_encoded.to_string()))
+    }
+}
+impl Drop for RevalidatingGuard {
+    fn drop(&mut self) {
+        REVALIDATING
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|p| p != &self.0);
+    }
+}
+
+/// Clones a `Request` from its internal parts. Extensions can't be cloned in
+/// general, so only those that Perseus itself attaches will be carried over.
+pub(crate) fn clone_req(raw: &Request) -> Request {
     let mut builder = Request::builder();
 
     for (name, val) in raw.headers() {
         builder = builder.header(name, val);
     }
+    if let Some(extensions) = builder.extensions_mut() {
+        clone_extension::<RemoteAddr>(raw, extensions);
+        clone_extension::<ClientIp>(raw, extensions);
+        clone_extension::<RequestContext>(raw, extensions);
+        clone_extension::<Variants>(raw, extensions);
+        clone_extension::<SessionHandle>(raw, extensions);
+    }
 
     builder
         .uri(raw.uri())
@@ -26,6 +91,13 @@ fn clone_req(raw: &Request) -> Request {
         .body(())
         .unwrap() // This should never fail...
 }
+/// Copies the extension of the given type from the given request into the given
+/// extensions, if it's there.
+fn clone_extension<T: Clone + Send + Sync + 'static>(raw: &Request, extensions: &mut Extensions) {
+    if let Some(val) = raw.extensions().get::<T>() {
+        extensions.insert(val.clone());
+    }
+}
 
 /// Gets the path with the locale, returning it without if i18n isn't being
 /// used.
@@ -102,13 +174,18 @@ async fn get_request_state(
     translator: &Translator,
     path: &str,
     req: Request,
+    timeout: Option<Duration>,
 ) -> Result<Option<String>, ServerError> {
     // Generate the initial state (this may generate an error, but there's no file
     // that can't exist)
     let state = Some(
-        template
-            .get_request_state(path.to_string(), translator.get_locale(), req)
-            .await?,
+        run_with_timeout(
+            template.get_request_state(path.to_string(), translator.get_locale(), req),
+            timeout,
+            "get_request_state",
+            template,
+        )
+        .await?,
     );
 
     Ok(state)
@@ -119,6 +196,7 @@ async fn get_request_state(
 ///
 /// As this is always the final item, this returns a body and head along with
 /// the state.
+#[allow(clippy::too_many_arguments)]
 async fn render_amalgamated_state(
     template: &Template<SsrNode>,
     translator: &Translator,
@@ -126,19 +204,27 @@ async fn render_amalgamated_state(
     global_state: &Option<String>,
     build_state: String,
     request_state: String,
+    req: Request,
+    timeout: Option<Duration>,
 ) -> Result<(String, String, Option<String>), ServerError> {
     let path_with_locale = get_path_with_locale(path, translator);
+    let info = PageInfo::for_request(path, translator.get_locale(), &req);
     // Generate the initial state (this may generate an error, but there's no file
     // that can't exist)
     let state = Some(
-        template
-            .amalgamate_states(
+        run_with_timeout(
+            template.amalgamate_states(
                 path.to_string(),
                 translator.get_locale(),
                 build_state,
                 request_state,
-            )
-            .await?,
+                req,
+            ),
+            timeout,
+            "amalgamate_states",
+            template,
+        )
+        .await?,
     );
 
     // Assemble the page properties
@@ -150,7 +236,7 @@ async fn render_amalgamated_state(
     let html = sycamore::render_to_string(|cx| {
         template.render_for_template_server(page_props.clone(), cx, translator)
     });
-    let head = template.render_head_str(page_props, translator);
+    let head = template.render_head_str(page_props, info, translator);
 
     Ok((html, head, state))
 }
@@ -183,8 +269,10 @@ async fn get_incremental_cached(
         Ok(_) | Err(_) => None,
     }
 }
-/// Checks if a template should revalidate by time. All revalidation timestamps
-/// are stored in a mutable store, so that's what this function uses.
+/// Checks if a template should revalidate, either because it's been marked for
+/// revalidation on demand, or by time, or by the user's custom logic. All
+/// revalidation timestamps are stored in a mutable store, so that's what this
+/// function uses.
 async fn should_revalidate(
     template: &Template<SsrNode>,
     path_encoded: &str,
@@ -192,11 +280,37 @@ async fn should_revalidate(
     translator: &Translator,
     path: &str,
     req: Request,
+    timeout: Option<Duration>,
 ) -> Result<bool, ServerError> {
+    if !template.revalidates() {
+        return Ok(false);
+    }
+    // Pages marked for revalidation on demand are always revalidated
+    if matches!(
+        mutable_store.read(&format!("static/{}.stale.txt", path_encoded)).await,
+        Ok(marker) if marker == "1"
+    ) {
+        return Ok(true);
+    }
+    // As are pages that scheduled their own revalidation for a time that's now
+    // passed
+    if let Ok(scheduled) = mutable_store
+        .read(&format!("static/{}.sched.txt", path_encoded))
+        .await
+    {
+        if matches!(DateTime::parse_from_rfc3339(&scheduled), Ok(scheduled) if scheduled <= Utc::now())
+        {
+            return Ok(true);
+        }
+    }
+
     let mut should_revalidate = false;
     // If it revalidates after a certain period of time, we needd to check that
     // BEFORE the custom logic
-    if template.revalidates_with_time() {
+    if template
+        .get_revalidate_interval(&translator.get_locale())
+        .is_some()
+    {
         // Get the time when it should revalidate (RFC 3339)
         // This will be updated, so it's in a mutable store
         let datetime_to_revalidate_str = mutable_store
@@ -219,18 +333,58 @@ async fn should_revalidate(
 
     // Now run the user's custom revalidation logic
     if template.revalidates_with_logic() {
-        should_revalidate = template
-            .should_revalidate(path.to_string(), translator.get_locale(), req)
-            .await?;
+        should_revalidate = match run_with_timeout(
+            template.should_revalidate(path.to_string(), translator.get_locale(), req),
+            timeout,
+            "should_revalidate",
+            template,
+        )
+        .await
+        {
+            Ok(should_revalidate) => should_revalidate,
+            // If we can't tell whether or not the page is stale in time, we'll serve what we've
+            // got
+            Err(ServerError::StateFnTimedOut { .. }) => false,
+            Err(err) => return Err(err),
+        };
     }
     Ok(should_revalidate)
 }
+/// Checks if the given error from a request-time state function was the
+/// server's fault, and so should count against its circuit breaker.
+fn is_server_failure(err: &ServerError) -> bool {
+    err_to_status_code(err) >= 500
+}
+/// Runs the given call to one of a template's request-time state functions,
+/// cancelling it if it doesn't finish within the given timeout (if there is
+/// one).
+async fn run_with_timeout<T>(
+    fut: impl Future<Output = Result<T, ServerError>>,
+    timeout: Option<Duration>,
+    fn_name: &str,
+    template: &Template<SsrNode>,
+) -> Result<T, ServerError> {
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, fut).await {
+            Ok(res) => res,
+            Err(_) => Err(ServerError::StateFnTimedOut {
+                fn_name: fn_name.to_string(),
+                template_name: template.get_path(),
+                timeout,
+            }),
+        },
+        None => fut.await,
+    }
+}
 /// Revalidates a template. All information about templates that revalidate
 /// (timestamp, content, head, and state) is stored in a mutable store, so
 /// that's what this function uses.
 ///
 /// Despite this involving state computation, it needs to write a body and
 /// head to the mutable store, so it returns those along with the state.
+///
+/// If another server instance sharing the mutable store is already
+/// revalidating this page, this will just return the stale page.
 async fn revalidate(
     template: &Template<SsrNode>,
     translator: &Translator,
@@ -238,18 +392,47 @@ async fn revalidate(
     path_encoded: &str,
     global_state: &Option<String>,
     mutable_store: &impl MutableStore,
+) -> Result<(String, String, Option<String>), ServerError> {
+    let lock = format!("static/{}", path_encoded);
+    let token = match mutable_store.try_lock(&lock, REGENERATION_LOCK_TTL).await? {
+        Some(token) => token,
+        None => return render_build_state_for_mutable(path_encoded, mutable_store).await,
+    };
+    let res = revalidate_locked(
+        template,
+        translator,
+        path,
+        path_encoded,
+        global_state,
+        mutable_store,
+    )
+    .await;
+    let unlock_res = mutable_store.unlock(&lock, &token).await;
+    let res = res?;
+    unlock_res?;
+
+    Ok(res)
+}
+/// Revalidates a template once we hold the lock on regenerating it.
+async fn revalidate_locked(
+    template: &Template<SsrNode>,
+    translator: &Translator,
+    path: &str,
+    path_encoded: &str,
+    global_state: &Option<String>,
+    mutable_store: &impl MutableStore,
 ) -> Result<(String, String, Option<String>), ServerError> {
     let path_with_locale = get_path_with_locale(path, translator);
     // We need to regenerate and cache this page for future usage (until the next
     // revalidation)
-    let state = Some(
-        template
-            .get_build_state(
-                format!("{}/{}", template.get_path(), path),
-                translator.get_locale(),
-            )
-            .await?,
-    );
+    let (state, schedule) = template
+        .get_build_state_with_schedule(
+            format!("{}/{}", template.get_path(), path),
+            translator.get_locale(),
+            None,
+        )
+        .await?;
+    let state = Some(state);
     // Assemble the page properties
     let page_props = PageProps {
         path: path_with_locale,
@@ -259,18 +442,16 @@ async fn revalidate(
     let html = sycamore::render_to_string(|cx| {
         template.render_for_template_server(page_props.clone(), cx, translator)
     });
-    let head = template.render_head_str(page_props, translator);
+    let info = PageInfo::new(path, translator.get_locale(), None);
+    let head = template.render_head_str(page_props, info, translator);
     // Handle revalidation, we need to parse any given time strings into datetimes
     // We don't need to worry about revalidation that operates by logic, that's
     // request-time only
-    if template.revalidates_with_time() {
+    if let Some(interval) = template.get_revalidate_interval(&translator.get_locale()) {
         // IMPORTANT: we set the new revalidation datetime to the interval from NOW, not
         // from the previous one So if you're revalidating many pages weekly,
         // they will NOT revalidate simultaneously, even if they're all queried thus
-        let datetime_to_revalidate = template
-            .get_revalidate_interval()
-            .unwrap()
-            .compute_timestamp();
+        let datetime_to_revalidate = interval.compute_timestamp();
         mutable_store
             .write(
                 &format!("static/{}.revld.txt", path_encoded),
@@ -278,6 +459,7 @@ async fn revalidate(
             )
             .await?;
     }
+    write_revalidation_schedule(path_encoded, schedule, mutable_store).await?;
     mutable_store
         .write(
             &format!("static/{}.json", path_encoded),
@@ -290,10 +472,199 @@ async fn revalidate(
     mutable_store
         .write(&format!("static/{}.head.html", path_encoded), &head)
         .await?;
+    // If this page was marked for revalidation on demand, that's been done now
+    let stale_marker = format!("static/{}.stale.txt", path_encoded);
+    if mutable_store.read(&stale_marker).await.is_ok() {
+        mutable_store.write(&stale_marker, "0").await?;
+    }
 
     Ok((html, head, state))
 }
 
+/// Records the time at which the page with the given encoded path has
+/// scheduled its own revalidation (see
+/// [`StateGeneratorInfo::schedule_revalidation`](crate::template::StateGeneratorInfo::schedule_revalidation)),
+/// or clears any previous schedule if it hasn't scheduled one.
+pub(crate) async fn write_revalidation_schedule(
+    path_encoded: &str,
+    schedule: Option<DateTime<Utc>>,
+    mutable_store: &impl MutableStore,
+) -> Result<(), StoreError> {
+    let marker = format!("static/{}.sched.txt", path_encoded);
+    match schedule {
+        Some(schedule) => mutable_store.write(&marker, &schedule.to_rfc3339()).await,
+        None if mutable_store.read(&marker).await.is_ok() => mutable_store.write(&marker, "").await,
+        None => Ok(()),
+    }
+}
+
+/// Encodes the given page path (without its locale or surrounding slashes)
+/// into the form its artifacts are stored under in the given locale. An empty
+/// path is the special `index` page.
+fn encode_page_path(path: &str, locale: &str) -> String {
+    let path = if path.is_empty() { "index" } else { path };
+    // Remove `/` from the path by encoding it as a URL (that's what we store) and
+    // add the locale
+    format!("{}-{}", locale, urlencoding::encode(path))
+}
+
+/// Marks the given page (without its locale) for revalidation in the given
+/// locale, so that it will be revalidated on the next request for it,
+/// regardless of its template's revalidation interval or logic. This is useful
+/// for revalidating pages on demand, e.g. from a webhook that your CMS calls
+/// when some content in a particular language changes. If you're not using
+/// i18n, the locale should be `xx-XX`.
+///
+/// This will only have an effect for pages whose templates use revalidation,
+/// since the pages of other templates are never regenerated after the app is
+/// built.
+pub async fn mark_for_revalidation(
+    path: &str,
+    locale: &str,
+    mutable_store: &impl MutableStore,
+) -> Result<(), StoreError> {
+    let path_encoded = encode_page_path(path.trim_matches('/'), locale);
+    mutable_store
+        .write(&format!("static/{}.stale.txt", path_encoded), "1")
+        .await
+}
+
+/// Generates a page for a template that uses incremental generation, caching
+/// it in the mutable store. This should only be called once we hold the lock
+/// on generating it.
+#[allow(clippy::too_many_arguments)]
+async fn generate_incremental(
+    template: &Template<SsrNode>,
+    translator: &Translator,
+    path: &str,
+    locale: &str,
+    path_encoded: &str,
+    path_with_locale: &str,
+    global_state: &Option<String>,
+    mutable_store: &impl MutableStore,
+) -> Result<(String, String, Option<String>), ServerError> {
+    // We need to generate and cache this page for future usage
+    // Even if we're going to amalgamate later, we still have to perform incremental
+    // caching, which means a potentially unnecessary page build
+    let (state, schedule) = template
+        .get_build_state_with_schedule(path.to_string(), locale.to_string(), None)
+        .await?;
+    let state = Some(state);
+    // Assemble the page properties
+    let page_props = PageProps {
+        path: path_with_locale.to_string(),
+        state: state.clone(),
+        global_state: global_state.clone(),
+    };
+    let html_val = sycamore::render_to_string(|cx| {
+        template.render_for_template_server(page_props.clone(), cx, translator)
+    });
+    let head_val =
+        template.render_head_str(page_props, PageInfo::new(path, locale, None), translator);
+    // Handle revalidation, we need to parse any given time strings into datetimes
+    // We don't need to worry about revalidation that operates by logic, that's
+    // request-time only Obviously we don't need to revalidate now, we just created
+    // it
+    if let Some(interval) = template.get_revalidate_interval(locale) {
+        let datetime_to_revalidate = interval.compute_timestamp();
+        // Write that to a static file, we'll update it every time we revalidate
+        // Note that this runs for every path generated, so it's fully usable with ISR
+        mutable_store
+            .write(
+                &format!("static/{}.revld.txt", path_encoded),
+                &datetime_to_revalidate,
+            )
+            .await?;
+    }
+    write_revalidation_schedule(path_encoded, schedule, mutable_store).await?;
+    // Cache all that
+    mutable_store
+        .write(
+            &format!("static/{}.json", path_encoded),
+            &state.clone().unwrap(),
+        )
+        .await?;
+    // Write that prerendered HTML to a static file
+    mutable_store
+        .write(&format!("static/{}.html", path_encoded), &html_val)
+        .await?;
+    mutable_store
+        .write(&format!("static/{}.head.html", path_encoded), &head_val)
+        .await?;
+
+    Ok((html_val, head_val, state))
+}
+
+/// Revalidates a page in a background task, if it isn't already being
+/// revalidated. Any errors will be printed, since there's no request left to
+/// return them to (the page will just be revalidated again on the next
+/// request).
+fn revalidate_in_background<M: MutableStore + 'static, T: TranslationsManager + 'static>(
+    template: Arc<Template<SsrNode>>,
+    path: &str,
+    locale: &str,
+    path_encoded: &str,
+    global_state: &Option<String>,
+    mutable_store: &M,
+    translations_manager: &T,
+) {
+    let guard = match RevalidatingGuard::new(path_encoded) {
+        Some(guard) => guard,
+        None => return,
+    };
+
+    let path = path.to_string();
+    let locale = locale.to_string();
+    let path_encoded = path_encoded.to_string();
+    let global_state = global_state.clone();
+    let mutable_store = mutable_store.clone();
+    let translations_manager = translations_manager.clone();
+    tokio::spawn(async move {
+        // This will be dropped when the task finishes, or if it panics
+        let _guard = guard;
+        let res: Result<_, ServerError> = async {
+            let translator = translations_manager
+                .get_translator_for_locale(locale)
+                .await?;
+            revalidate(
+                &template,
+                &translator,
+                &path,
+                &path_encoded,
+                &global_state,
+                &mutable_store,
+            )
+            .await
+        }
+        .await;
+        if let Err(err) = res {
+            eprintln!(
+                "[WARNING]: couldn't revalidate page '{}' in the background: {}",
+                path,
+                fmt_err(&err)
+            );
+        }
+    });
+}
+
+/// Information about a request for a page, which is given to the plugin actions
+/// that run around the rendering of pages.
+#[derive(Clone, Debug)]
+pub struct PageRequestInfo {
+    /// The path of the page (without the locale).
+    pub path: String,
+    /// The locale the page is being rendered for.
+    pub locale: String,
+    /// The name of the template the page uses.
+    pub template_name: String,
+    /// The URI of the request. On subsequent loads, this will be for the
+    /// page data endpoint, although the query string will be that of the
+    /// page.
+    pub uri: Uri,
+    /// The headers of the request.
+    pub headers: HeaderMap,
+}
+
 /// The properties required to get data for a page.
 #[derive(Debug)]
 pub struct GetPageProps<'a, M: MutableStore, T: TranslationsManager> {
@@ -315,6 +686,24 @@ pub struct GetPageProps<'a, M: MutableStore, T: TranslationsManager> {
     pub mutable_store: &'a M,
     /// A translations manager.
     pub translations_manager: &'a T,
+    /// The app's plugins, which may want to act before and after the page is
+    /// rendered.
+    pub plugins: &'a Plugins<SsrNode>,
+    /// The settings for pre-rendering incrementally generated pages when the
+    /// server starts, which determine whether or not requests for them
+    /// should be recorded.
+    pub incremental_warmup: &'a IncrementalWarmup,
+    /// All the app's templates, which are needed for revalidating pages in the
+    /// background.
+    pub templates: &'a ArcTemplateMap<SsrNode>,
+    /// The function to report panics that occur while rendering the page to,
+    /// if the app has one.
+    pub error_reporter: &'a Option<ErrorReporter>,
+    /// How long the template's request-time state functions should be given
+    /// before they're cancelled, if it doesn't set its own timeout.
+    pub state_fn_timeout: Option<Duration>,
+    /// The pages that redirected elsewhere at build-time.
+    pub build_redirects: &'a BuildRedirects,
 }
 
 /// Internal logic behind [`get_page`]. The only differences are that this takes
@@ -323,7 +712,69 @@ pub struct GetPageProps<'a, M: MutableStore, T: TranslationsManager> {
 /// load server-side routing). Because this handles templates with potentially
 /// revalidation and incremental generation, it uses both mutable and immutable
 /// stores.
-pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
+///
+/// If the `PERSEUS_REQUEST_LOG` environment variable is set, this will print
+/// the rendering strategies used for the page and how long was spent reading
+/// from stores and rendering it.
+///
+/// If anything panics while the page is being rendered (e.g. the user's request
+/// state function), the panic will be caught and reported to the app's error
+/// reporter, and this will return an error, so that the user can be sent an
+/// error page, rather than having their connection dropped.
+pub async fn get_page_for_template<M: MutableStore + 'static, T: TranslationsManager + 'static>(
+    props: GetPageProps<'_, M, T>,
+    template: &Template<SsrNode>,
+) -> Result<PageData, ServerError> {
+    let mut log = RequestLog::new(
+        &props.req,
+        props.raw_path,
+        props.locale,
+        template.get_path(),
+    );
+    let path = props.raw_path.to_string();
+    let error_reporter = props.error_reporter;
+    // Anything shared between requests that rendering touches has to survive it
+    // panicking partway through: locks on pages being generated in the mutable
+    // store will expire, the list of pages being revalidated is managed by a drop
+    // guard, and poisoned plugin locks are recovered from (plugin actions don't
+    // mutate the plugins)
+    let res = match AssertUnwindSafe(render_page(props, template, &mut log))
+        .catch_unwind()
+        .await
+    {
+        Ok(res) => res,
+        // The panic hook will already have printed the panic
+        Err(payload) => {
+            let message = get_panic_message(payload.as_ref());
+            if let Some(reporter) = error_reporter {
+                reporter.report(&ClientError::Panic(message.clone()));
+            }
+            Err(ServerError::RenderPanicked {
+                path,
+                template_name: template.get_path(),
+                message,
+            })
+        }
+    };
+    log.finish(res.is_ok());
+
+    res
+}
+
+/// Gets the message from the payload of a panic (which will be a string,
+/// unless something other than `panic!` was used).
+fn get_panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Gets the data for a page, recording what was done in the given request log.
+async fn render_page<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     GetPageProps {
         raw_path,
         locale,
@@ -333,9 +784,30 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
         immutable_store,
         mutable_store,
         translations_manager,
+        plugins,
+        incremental_warmup,
+        templates,
+        error_reporter: _,
+        state_fn_timeout,
+        build_redirects,
     }: GetPageProps<'_, M, T>,
     template: &Template<SsrNode>,
+    log: &mut RequestLog,
 ) -> Result<PageData, ServerError> {
+    // Templates can override the app's timeout for their own state functions
+    let timeout = template.get_state_fn_timeout().or(state_fn_timeout);
+    let request_info = PageRequestInfo {
+        path: raw_path.to_string(),
+        locale: locale.to_string(),
+        template_name: template.get_path(),
+        uri: req.uri().clone(),
+        headers: req.headers().clone(),
+    };
+    plugins
+        .functional_actions
+        .server_actions
+        .before_page_render
+        .run(request_info.clone(), plugins.get_plugin_data());
     // Since `Request` is not actually `Clone`able, we hack our way around needing
     // it twice An `Rc` won't work because of future constraints, and an `Arc`
     // seems a little unnecessary
@@ -350,10 +822,10 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
     if path.is_empty() {
         path = "index";
     }
-    // Remove `/` from the path by encoding it as a URL (that's what we store) and
-    // add the locale
-    let path_encoded = format!("{}-{}", locale, urlencoding::encode(path));
+    let path_encoded = encode_page_path(path, locale);
     let path_with_locale = get_path_with_locale(path, &translator);
+    // The information about the page given to its head if it's rendered now
+    let info = PageInfo::for_request(path, locale, &req);
 
     // Only a single string of HTML is needed, and it will be overridden if
     // necessary (priorities system)
@@ -364,44 +836,98 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
     // Multiple rendering strategies may need to amalgamate different states
     let mut states = States::new();
 
+    // Keep track of which incrementally generated pages are popular, so they can be
+    // pre-rendered when the server next starts
+    if template.uses_incremental() && was_incremental_match && incremental_warmup.recent > 0 {
+        record_incremental_request(
+            &get_path_with_locale(raw_path, &translator),
+            mutable_store,
+            incremental_warmup.recent,
+        )
+        .await;
+    }
+
+    // Pages that failed to build (with `BuildFailurePolicy::SkipFailed`) are
+    // generated on demand, just like incrementally generated ones
+    let was_skipped = template.uses_build_state()
+        && !was_incremental_match
+        && log
+            .store(immutable_store.read(&format!("static/{}.skipped.txt", path_encoded)))
+            .await
+            .is_ok();
+    // Pages whose build state redirected elsewhere at build-time keep doing so
+    // until the next build
+    if template.uses_build_state() && !was_incremental_match {
+        if let Some(redirect) = build_redirects.get(raw_path, locale) {
+            return Err(ServerError::RenderFnFailed {
+                fn_name: "get_build_state".to_string(),
+                template_name: template.get_path(),
+                cause: ErrorCause::Client(Some(redirect.status())),
+                source: redirect.clone().into(),
+            });
+        }
+    }
+
     // Handle build state (which might use revalidation or incremental)
     if template.uses_build_state() || template.is_basic() {
         // If the template uses incremental generation, that is its own contained
         // process
-        if template.uses_incremental() && was_incremental_match {
+        if (template.uses_incremental() && was_incremental_match) || was_skipped {
             // This template uses incremental generation, and this page was built and cached
             // at runtime in the mutable store Get the cached content if it
             // exists (otherwise `None`)
-            let html_and_head_opt = get_incremental_cached(&path_encoded, mutable_store).await;
+            let html_and_head_opt = log
+                .store(get_incremental_cached(&path_encoded, mutable_store))
+                .await;
             match html_and_head_opt {
                 // It's cached
                 Some((html_val, head_val)) => {
                     // Check if we need to revalidate
-                    if should_revalidate(
+                    let needs_revalidation = should_revalidate(
                         template,
                         &path_encoded,
                         mutable_store,
                         &translator,
                         path,
                         req,
+                        timeout,
                     )
-                    .await?
-                    {
-                        let (html_val, head_val, state) = revalidate(
-                            template,
-                            &translator,
-                            path,
-                            &path_encoded,
-                            global_state,
-                            mutable_store,
-                        )
-                        .await?;
+                    .await?;
+                    if needs_revalidation && !template.revalidates_in_background() {
+                        log.strategy("revalidated");
+                        let (html_val, head_val, state) = log
+                            .render(revalidate(
+                                template,
+                                &translator,
+                                path,
+                                &path_encoded,
+                                global_state,
+                                mutable_store,
+                            ))
+                            .await?;
                         // That revalidation will have returned a body and head, which we can
                         // provisionally use
                         html = html_val;
                         head = head_val;
                         states.build_state = state;
                     } else {
+                        // If we're revalidating in the background, we'll serve the stale page for
+                        // now
+                        log.strategy("incremental-cached");
+                        if needs_revalidation {
+                            log.strategy("revalidating-in-background");
+                            if let Some(template) = templates.get(&template.get_path()) {
+                                revalidate_in_background(
+                                    template.clone(),
+                                    path,
+                                    locale,
+                                    &path_encoded,
+                                    global_state,
+                                    mutable_store,
+                                    translations_manager,
+                                );
+                            }
+                        }
                         // That incremental cache check will have returned a body and head, which we
                         // can provisionally use
                         html = html_val;
@@ -409,8 +935,8 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
                         // Get the static JSON (if it exists, but it should)
                         // THis wouldn't be present if the user had set up incremental generation
                         // without build state (which would be remarkably silly)
-                        states.build_state = match mutable_store
-                            .read(&format!("static/{}.json", path_encoded))
+                        states.build_state = match log
+                            .store(mutable_store.read(&format!("static/{}.json", path_encoded)))
                             .await
                         {
                             Ok(state) => Some(state),
@@ -421,57 +947,53 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
                 // It's not cached
                 // All this uses the mutable store because this will be done at runtime
                 None => {
-                    // We need to generate and cache this page for future usage
-                    // Even if we're going to amalgamate later, we still have to perform incremental
-                    // caching, which means a potentially unnecessary page build
-                    let state = Some(
-                        template
-                            .get_build_state(path.to_string(), locale.to_string())
-                            .await?,
-                    );
-                    // Assemble the page properties
-                    let page_props = PageProps {
-                        path: path_with_locale.clone(),
-                        state: state.clone(),
-                        global_state: global_state.clone(),
+                    // Make sure no other server instance is generating this page at the same
+                    // time, and wait for it to finish if it is
+                    let lock = format!("static/{}", path_encoded);
+                    log.strategy("incremental-generated");
+                    let deadline = Instant::now() + REGENERATION_LOCK_WAIT;
+                    let (html_val, head_val, state) = loop {
+                        if let Some(token) =
+                            mutable_store.try_lock(&lock, REGENERATION_LOCK_TTL).await?
+                        {
+                            // If we had to wait, the page will probably have been generated by
+                            // whoever we were waiting for
+                            let res =
+                                match get_incremental_cached(&path_encoded, mutable_store).await {
+                                    Some((html_val, head_val)) => {
+                                        let state = mutable_store
+                                            .read(&format!("static/{}.json", path_encoded))
+                                            .await
+                                            .ok();
+                                        Ok((html_val, head_val, state))
+                                    }
+                                    None => {
+                                        log.render(generate_incremental(
+                                            template,
+                                            &translator,
+                                            path,
+                                            locale,
+                                            &path_encoded,
+                                            &path_with_locale,
+                                            global_state,
+                                            mutable_store,
+                                        ))
+                                        .await
+                                    }
+                                };
+                            let unlock_res = mutable_store.unlock(&lock, &token).await;
+                            let res = res?;
+                            unlock_res?;
+                            break res;
+                        }
+                        if Instant::now() >= deadline {
+                            return Err(ServerError::RegenerationLockTimedOut {
+                                path: path_with_locale,
+                                timeout: REGENERATION_LOCK_WAIT,
+                            });
+                        }
+                        tokio::time::sleep(REGENERATION_LOCK_POLL_INTERVAL).await;
                     };
-                    let html_val = sycamore::render_to_string(|cx| {
-                        template.render_for_template_server(page_props.clone(), cx, &translator)
-                    });
-                    let head_val = template.render_head_str(page_props, &translator);
-                    // Handle revalidation, we need to parse any given time strings into datetimes
-                    // We don't need to worry about revalidation that operates by logic, that's
-                    // request-time only Obviously we don't need to revalidate
-                    // now, we just created it
-                    if template.revalidates_with_time() {
-                        let datetime_to_revalidate = template
-                            .get_revalidate_interval()
-                            .unwrap()
-                            .compute_timestamp();
-                        // Write that to a static file, we'll update it every time we revalidate
-                        // Note that this runs for every path generated, so it's fully usable with
-                        // ISR
-                        mutable_store
-                            .write(
-                                &format!("static/{}.revld.txt", path_encoded),
-                                &datetime_to_revalidate,
-                            )
-                            .await?;
-                    }
-                    // Cache all that
-                    mutable_store
-                        .write(
-                            &format!("static/{}.json", path_encoded),
-                            &state.clone().unwrap(),
-                        )
-                        .await?;
-                    // Write that prerendered HTML to a static file
-                    mutable_store
-                        .write(&format!("static/{}.html", path_encoded), &html_val)
-                        .await?;
-                    mutable_store
-                        .write(&format!("static/{}.head.html", path_encoded), &head_val)
-                        .await?;
 
                     states.build_state = state;
                     html = html_val;
@@ -484,36 +1006,56 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
 
             // Handle if we need to revalidate
             // It'll be in the mutable store if we do
-            if should_revalidate(
+            let needs_revalidation = should_revalidate(
                 template,
                 &path_encoded,
                 mutable_store,
                 &translator,
                 path,
                 req,
+                timeout,
             )
-            .await?
-            {
-                let (html_val, head_val, state) = revalidate(
-                    template,
-                    &translator,
-                    path,
-                    &path_encoded,
-                    global_state,
-                    mutable_store,
-                )
-                .await?;
+            .await?;
+            if needs_revalidation && !template.revalidates_in_background() {
+                log.strategy("revalidated");
+                let (html_val, head_val, state) = log
+                    .render(revalidate(
+                        template,
+                        &translator,
+                        path,
+                        &path_encoded,
+                        global_state,
+                        mutable_store,
+                    ))
+                    .await?;
                 // That revalidation will have produced a head and body, which we can
                 // provisionally use
                 html = html_val;
                 head = head_val;
                 states.build_state = state;
             } else if template.revalidates() {
-                // The template does revalidate, but it doesn't need to revalidate now
-                // Nonetheless, its data will be the mutable store
+                // The template does revalidate, but either it doesn't need to revalidate now,
+                // or we're doing that in the background and serving the stale page for now
+                // Either way, its data will be the mutable store
                 // This is just fetching, not computing
-                let (html_val, head_val, state) =
-                    render_build_state_for_mutable(&path_encoded, mutable_store).await?;
+                log.strategy("revalidation-cached");
+                if needs_revalidation {
+                    log.strategy("revalidating-in-background");
+                    if let Some(template) = templates.get(&template.get_path()) {
+                        revalidate_in_background(
+                            template.clone(),
+                            path,
+                            locale,
+                            &path_encoded,
+                            global_state,
+                            mutable_store,
+                            translations_manager,
+                        );
+                    }
+                }
+                let (html_val, head_val, state) = log
+                    .store(render_build_state_for_mutable(&path_encoded, mutable_store))
+                    .await?;
                 html = html_val;
                 head = head_val;
                 states.build_state = state;
@@ -521,8 +1063,10 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
                 // If we don't need to revalidate and this isn't an incrementally generated
                 // template, everything is immutable
                 // Again, this just fetches
-                let (html_val, head_val, state) =
-                    render_build_state(&path_encoded, immutable_store).await?;
+                log.strategy("build");
+                let (html_val, head_val, state) = log
+                    .store(render_build_state(&path_encoded, immutable_store))
+                    .await?;
                 html = html_val;
                 head = head_val;
                 states.build_state = state;
@@ -537,8 +1081,59 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
         // page will be built soon If we're not, and there's no build state,
         // then we still need to build, which we'll do after we've checked for
         // amalgamation
-        let state = get_request_state(template, &translator, path, req_2).await?;
-        states.request_state = state;
+        log.strategy("request");
+        let breaker = template.get_circuit_breaker();
+        let state = match breaker {
+            // While the breaker is open, the function isn't called at all
+            Some(breaker) if breaker.is_open() => Err(ServerError::CircuitOpen {
+                template_name: template.get_path(),
+            }),
+            _ => {
+                let state = log
+                    .render(get_request_state(
+                        template,
+                        &translator,
+                        path,
+                        clone_req(&req_2),
+                        timeout,
+                    ))
+                    .await;
+                if let Some(breaker) = breaker {
+                    match &state {
+                        Ok(_) => breaker.record_success(),
+                        Err(err) if is_server_failure(err) => {
+                            // If this opens the breaker, this request can use the fallback too
+                            if breaker.record_failure() {
+                                log.strategy("circuit-opened");
+                            }
+                        }
+                        Err(_) => (),
+                    }
+                }
+                state
+            }
+        };
+        let breaker_open = breaker.map(|breaker| breaker.is_open()).unwrap_or(false);
+        match state {
+            Ok(state) => states.request_state = state,
+            // If there's build state, the (possibly stale) page can be served with just that
+            Err(ServerError::StateFnTimedOut { .. }) if states.build_state.is_some() => {
+                log.strategy("request-timed-out")
+            }
+            Err(_) if breaker_open && states.build_state.is_some() => {
+                log.strategy("request-fallback")
+            }
+            Err(err) if breaker_open => {
+                match breaker.and_then(|breaker| breaker.get_degraded_state()) {
+                    Some(degraded_state) => {
+                        log.strategy("request-degraded");
+                        states.request_state = Some(degraded_state.to_string());
+                    }
+                    None => return Err(err),
+                }
+            }
+            Err(err) => return Err(err),
+        }
     }
 
     // Amalgamate the states
@@ -559,10 +1154,13 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
             state: state.clone(),
             global_state: global_state.clone(),
         };
-        let html_val = sycamore::render_to_string(|cx| {
-            template.render_for_template_server(page_props.clone(), cx, &translator)
+        let (html_val, head_val) = log.render_sync(|| {
+            let html_val = sycamore::render_to_string(|cx| {
+                template.render_for_template_server(page_props.clone(), cx, &translator)
+            });
+            let head_val = template.render_head_str(page_props, info, &translator);
+            (html_val, head_val)
         });
-        let head_val = template.render_head_str(page_props, &translator);
         html = html_val;
         head = head_val;
         state
@@ -574,18 +1172,33 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
     } else if template.can_amalgamate_states() {
         // We know that both the states are defined
         // The HTML is currently built with the wrong state, so we have to update it
-        let (html_val, head_val, state) = render_amalgamated_state(
-            template,
-            &translator,
-            path,
-            global_state,
-            states.build_state.unwrap(),
-            states.request_state.unwrap(),
-        )
-        .await?;
-        html = html_val;
-        head = head_val;
-        state
+        log.strategy("amalgamated");
+        let build_state = states.build_state.unwrap();
+        let res = log
+            .render(render_amalgamated_state(
+                template,
+                &translator,
+                path,
+                global_state,
+                build_state.clone(),
+                states.request_state.unwrap(),
+                req_2,
+                timeout,
+            ))
+            .await;
+        match res {
+            Ok((html_val, head_val, state)) => {
+                html = html_val;
+                head = head_val;
+                state
+            }
+            // The page has already been built with the build state, so we can serve that
+            Err(ServerError::StateFnTimedOut { .. }) => {
+                log.strategy("amalgamation-timed-out");
+                Some(build_state)
+            }
+            Err(err) => return Err(err),
+        }
     } else {
         // We do have multiple states, but there's no resolution function, so we have to
         // prefer request state That means we have to build the page for it,
@@ -597,10 +1210,13 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
             state: state.clone(),
             global_state: global_state.clone(),
         };
-        let html_val = sycamore::render_to_string(|cx| {
-            template.render_for_template_server(page_props.clone(), cx, &translator)
+        let (html_val, head_val) = log.render_sync(|| {
+            let html_val = sycamore::render_to_string(|cx| {
+                template.render_for_template_server(page_props.clone(), cx, &translator)
+            });
+            let head_val = template.render_head_str(page_props, info, &translator);
+            (html_val, head_val)
         });
-        let head_val = template.render_head_str(page_props, &translator);
         html = html_val;
         head = head_val;
         state
@@ -611,16 +1227,37 @@ pub async fn get_page_for_template<M: MutableStore, T: TranslationsManager>(
         content: html,
         state,
         head,
+        redirect: None,
     };
+    // Let plugins post-process the page
+    let res = plugins
+        .functional_actions
+        .server_actions
+        .after_page_render
+        .run_chained_with_context(request_info, res, plugins.get_plugin_data());
 
     Ok(res)
 }
 
+/// Gets the page data to send on subsequent loads of a page that redirects
+/// elsewhere (see [`Redirect`]). A real HTTP redirect would just be followed
+/// by the browser when fetching the page data, so this tells the app to
+/// navigate to the new location instead.
+pub fn get_redirect_page_data(redirect: &Redirect) -> String {
+    let page_data = PageData {
+        content: String::new(),
+        state: None,
+        head: String::new(),
+        redirect: Some(redirect.location().to_string()),
+    };
+    serde_json::to_string(&page_data).unwrap()
+}
+
 /// Gets the HTML/JSON data for the given page path. This will call
 /// SSG/SSR/etc., whatever is needed for that page. Note that HTML generated at
 /// request-time will **always** replace anything generated at build-time,
 /// incrementally, revalidated, etc.
-pub async fn get_page<M: MutableStore, T: TranslationsManager>(
+pub async fn get_page<M: MutableStore + 'static, T: TranslationsManager + 'static>(
     props: GetPageProps<'_, M, T>,
     template_name: &str,
     templates: &TemplateMap<SsrNode>,
@@ -647,3 +1284,48 @@ pub async fn get_page<M: MutableStore, T: TranslationsManager>(
     let res = get_page_for_template(props, template).await?;
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stores::FsMutableStore;
+
+    #[test]
+    fn marks_index_page_for_revalidation() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let root =
+                std::env::temp_dir().join(format!("perseus-revalidation-{}", std::process::id()));
+            let store = FsMutableStore::new(root.to_string_lossy().to_string());
+
+            for path in ["/", ""] {
+                mark_for_revalidation(path, "en-US", &store).await.unwrap();
+                assert_eq!(
+                    store.read("static/en-US-index.stale.txt").await.unwrap(),
+                    "1"
+                );
+                store
+                    .write("static/en-US-index.stale.txt", "0")
+                    .await
+                    .unwrap();
+            }
+            mark_for_revalidation("/posts/first/", "en-US", &store)
+                .await
+                .unwrap();
+            assert_eq!(
+                store
+                    .read(&format!(
+                        "static/{}.stale.txt",
+                        encode_page_path("posts/first", "en-US")
+                    ))
+                    .await
+                    .unwrap(),
+                "1"
+            );
+
+            let _ = std::fs::remove_dir_all(&root);
+        });
+    }
+}
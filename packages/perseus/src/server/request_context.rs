@@ -0,0 +1,346 @@
+use super::ServerOptions;
+use crate::i18n::Locales;
+use crate::Request;
+use std::net::{IpAddr, SocketAddr};
+
+/// The address of the client that made a request, as seen by the server (i.e.
+/// before accounting for any proxies). Integrations should insert this into the
+/// extensions of every request they pass to Perseus, if they can get it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteAddr(pub SocketAddr);
+
+/// The IP address of the client that made a request, after accounting for any
+/// trusted proxies (see [`TrustedProxies`]). This is stored in the extensions
+/// of every request Perseus' integrations pass to your functions, so you can
+/// get it with `req.extensions().get::<ClientIp>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Which proxies in front of the server should be trusted to report the
+/// address of the client they're forwarding requests for in
+/// `X-Forwarded-For`. Anyone can set that header, so trusting it when the
+/// server can be reached directly would let any client pretend to have any
+/// address it likes (which would make it useless for things like rate
+/// limiting). By default, no proxies are trusted, and the client's address
+/// will be the one the server received the request from.
+///
+/// Each proxy appends the address it received the request from to
+/// `X-Forwarded-For`, so Perseus reads the header from right to left, and the
+/// client's address will be the first one that wasn't added by a trusted
+/// proxy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TrustedProxies {
+    /// Don't trust any proxies.
+    #[default]
+    None,
+    /// Trust the given number of proxies in front of the server, whatever
+    /// their addresses. For example, if your server is only reachable
+    /// through a single load balancer, this should be `1`.
+    Hops(usize),
+    /// Trust the proxies with the given addresses, wherever they are in the
+    /// chain.
+    Addrs(Vec<IpAddr>),
+}
+impl TrustedProxies {
+    /// Works out the IP address of the client, given the address the server
+    /// received the request from (if it's known), and the value of the
+    /// `X-Forwarded-For` header (if there was one).
+    pub fn client_ip(
+        &self,
+        remote_addr: Option<IpAddr>,
+        forwarded_for: Option<&str>,
+    ) -> Option<IpAddr> {
+        // Each address in this is one hop further from the client than the one
+        // before it (an address we can't parse becomes a `None`, which will
+        // never be trusted)
+        let mut chain = forwarded_for
+            .map(|header| {
+                header
+                    .split(',')
+                    .map(|addr| addr.trim().parse::<IpAddr>().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        chain.push(remote_addr);
+
+        match self {
+            // The server must be able to see the address it received the request from for us
+            // to trust anything
+            Self::None => remote_addr,
+            Self::Hops(_) | Self::Addrs(_) if remote_addr.is_none() => None,
+            // The last `hops` addresses were all added by trusted proxies, so the one before
+            // them is the client (if there are fewer addresses than that, the request came
+            // through fewer proxies than expected, and the first is the best we have)
+            Self::Hops(hops) => chain[chain.len().saturating_sub(hops + 1)],
+            Self::Addrs(trusted) => {
+                let is_trusted = |addr: &Option<IpAddr>| match addr {
+                    Some(addr) => trusted.contains(addr),
+                    None => false,
+                };
+                // If every address is trusted, the first is the best we have
+                let idx = chain
+                    .iter()
+                    .rposition(|addr| !is_trusted(addr))
+                    .unwrap_or(0);
+                chain[idx]
+            }
+        }
+    }
+}
+
+/// A broad classification of the kind of device that made a request, based on
+/// its user agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    /// A desktop or laptop browser.
+    Desktop,
+    /// A phone.
+    Mobile,
+    /// A tablet.
+    Tablet,
+    /// A search engine crawler, link previewer, or other automated client.
+    Bot,
+    /// The request had no user agent.
+    Unknown,
+}
+
+/// Information about the client that made a request, parsed from it in the
+/// same way regardless of which server integration is being used. This can be
+/// used in request-state functions for personalization, without having to
+/// parse headers manually. Get this from the request passed to your function
+/// with [`RequestContext::from_req`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    /// The IP address of the client. By default, this is the address the
+    /// server received the request from, but, if the app trusts any proxies
+    /// (see [`TrustedProxies`]), this will be the original client's address
+    /// as reported by them. This is the same as the [`ClientIp`] in the
+    /// request's extensions.
+    pub ip: Option<IpAddr>,
+    /// The raw user agent of the client.
+    pub user_agent: Option<String>,
+    /// The kind of device the client is, based on its user agent.
+    pub device: DeviceKind,
+    /// The languages the client prefers (from `Accept-Language`), most
+    /// preferred first.
+    pub languages: Vec<String>,
+    /// The supported locale of the app that best matches the languages the
+    /// client prefers, if the app uses i18n and any of them match.
+    pub language: Option<String>,
+}
+impl RequestContext {
+    /// Gets the context of the given request. Perseus' integrations compute
+    /// this before passing requests to your functions, but, if that hasn't been
+    /// done, it'll be computed now (without trusting any proxies, or
+    /// negotiating a language).
+    pub fn from_req(req: &Request) -> Self {
+        match req.extensions().get::<Self>() {
+            Some(ctx) => ctx.clone(),
+            None => Self::parse(req, None, &TrustedProxies::None),
+        }
+    }
+    /// Parses the context of the given request, negotiating a language with
+    /// the given locales if they're provided.
+    fn parse(req: &Request, locales: Option<&Locales>, trusted_proxies: &TrustedProxies) -> Self {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|val| val.to_str().ok())
+                .map(|val| val.trim())
+                .filter(|val| !val.is_empty())
+        };
+
+        // Proxies can add their own `X-Forwarded-For` lines rather than appending to the
+        // client's, so every line has to be read, in order, as if they were one (a line that
+        // isn't valid UTF-8 will still count as an address, just one that's never trusted)
+        let forwarded_for = req
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .map(|val| String::from_utf8_lossy(val.as_bytes()).trim().to_string())
+            .filter(|val| !val.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+        let ip = trusted_proxies.client_ip(
+            req.extensions().get::<RemoteAddr>().map(|addr| addr.0.ip()),
+            Some(forwarded_for.as_str()).filter(|val| !val.is_empty()),
+        );
+        let user_agent = header("user-agent").map(|ua| ua.to_string());
+        let device = match &user_agent {
+            Some(ua) => classify_user_agent(ua),
+            None => DeviceKind::Unknown,
+        };
+        let languages = header("accept-language")
+            .map(parse_accept_language)
+            .unwrap_or_default();
+        let language = match locales {
+            Some(locales) if locales.using_i18n => locales.negotiate(&languages),
+            _ => None,
+        };
+
+        Self {
+            ip,
+            user_agent,
+            device,
+            languages,
+            language,
+        }
+    }
+}
+
+/// Computes the [`RequestContext`] of the given request and stores it in the
+/// request (along with the [`ClientIp`], if there is one), so that it's
+/// available to state functions. Integrations should call this on every
+/// request they pass to Perseus, after inserting the [`RemoteAddr`] (if they
+/// can get it).
+pub fn attach_request_context(req: &mut Request, opts: &ServerOptions) {
+    let ctx = RequestContext::parse(req, Some(&opts.locales), &opts.trusted_proxies);
+    if let Some(ip) = ctx.ip {
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    req.extensions_mut().insert(ctx);
+}
+
+/// Classifies a user agent into a broad kind of device. This is a heuristic,
+/// and it's deliberately simple.
+fn classify_user_agent(ua: &str) -> DeviceKind {
+    let ua = ua.to_lowercase();
+    if ["bot", "crawler", "spider", "slurp", "preview", "headless"]
+        .iter()
+        .any(|marker| ua.contains(marker))
+    {
+        DeviceKind::Bot
+    } else if ua.contains("ipad")
+        || ua.contains("tablet")
+        // Android tablets don't include `Mobile`
+        || (ua.contains("android") && !ua.contains("mobile"))
+    {
+        DeviceKind::Tablet
+    } else if ["mobi", "iphone", "ipod", "android", "windows phone"]
+        .iter()
+        .any(|marker| ua.contains(marker))
+    {
+        DeviceKind::Mobile
+    } else {
+        DeviceKind::Desktop
+    }
+}
+
+/// Parses an `Accept-Language` header into a list of languages, most preferred
+/// first. Wildcards and languages with a quality of zero are ignored.
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut langs = header
+        .split(',')
+        .filter_map(|part| {
+            let mut parts = part.split(';');
+            let lang = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            if lang.is_empty() || lang == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((lang.to_string(), quality))
+            }
+        })
+        .collect::<Vec<_>>();
+    // This is a stable sort, so languages with the same quality stay in order
+    langs.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    langs.into_iter().map(|(lang, _)| lang).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(addr: &str) -> Option<IpAddr> {
+        Some(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn ignores_forwarded_for_by_default() {
+        let proxies = TrustedProxies::None;
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), Some("1.2.3.4")),
+            ip("10.0.0.1")
+        );
+        assert_eq!(proxies.client_ip(None, Some("1.2.3.4")), None);
+    }
+    #[test]
+    fn trusts_hops_from_the_right() {
+        let proxies = TrustedProxies::Hops(1);
+        // A client could have put anything at the start of the header
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), Some("6.6.6.6, 1.2.3.4")),
+            ip("1.2.3.4")
+        );
+        assert_eq!(
+            TrustedProxies::Hops(2).client_ip(ip("10.0.0.1"), Some("1.2.3.4, 10.0.0.2")),
+            ip("1.2.3.4")
+        );
+        // Fewer proxies than expected
+        assert_eq!(
+            TrustedProxies::Hops(3).client_ip(ip("10.0.0.1"), Some("1.2.3.4")),
+            ip("1.2.3.4")
+        );
+        assert_eq!(proxies.client_ip(ip("10.0.0.1"), None), ip("10.0.0.1"));
+        assert_eq!(proxies.client_ip(None, Some("1.2.3.4")), None);
+    }
+    #[test]
+    fn trusts_allowlisted_proxies() {
+        let proxies = TrustedProxies::Addrs(vec![
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+        ]);
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), Some("6.6.6.6, 1.2.3.4, 10.0.0.2")),
+            ip("1.2.3.4")
+        );
+        // The request didn't come from a trusted proxy, so the header is ignored
+        assert_eq!(
+            proxies.client_ip(ip("5.5.5.5"), Some("1.2.3.4")),
+            ip("5.5.5.5")
+        );
+        // Garbage is never trusted
+        assert_eq!(
+            proxies.client_ip(ip("10.0.0.1"), Some("1.2.3.4, garbage")),
+            None
+        );
+    }
+    #[test]
+    fn reads_every_forwarded_for_line() {
+        // The client sent its own header, and the trusted proxy added another line
+        let mut req = Request::builder()
+            .header("x-forwarded-for", "6.6.6.6")
+            .header("x-forwarded-for", "1.2.3.4")
+            .body(())
+            .unwrap();
+        req.extensions_mut()
+            .insert(RemoteAddr("10.0.0.1:1234".parse().unwrap()));
+        let ctx = RequestContext::parse(&req, None, &TrustedProxies::Hops(1));
+        assert_eq!(ctx.ip, ip("1.2.3.4"));
+        let ctx = RequestContext::parse(&req, None, &TrustedProxies::Hops(2));
+        assert_eq!(ctx.ip, ip("6.6.6.6"));
+    }
+    #[test]
+    fn attached_client_ip_survives_clone() {
+        let mut req = Request::builder()
+            .header("x-forwarded-for", "1.2.3.4")
+            .body(())
+            .unwrap();
+        req.extensions_mut()
+            .insert(RemoteAddr("10.0.0.1:1234".parse().unwrap()));
+        let ctx = RequestContext::parse(&req, None, &TrustedProxies::Hops(1));
+        req.extensions_mut().insert(ClientIp(ctx.ip.unwrap()));
+        req.extensions_mut().insert(ctx);
+
+        let cloned = crate::server::render::clone_req(&req);
+        assert_eq!(
+            cloned.extensions().get::<ClientIp>(),
+            Some(&ClientIp("1.2.3.4".parse().unwrap()))
+        );
+        assert_eq!(RequestContext::from_req(&cloned).ip, ip("1.2.3.4"));
+    }
+}
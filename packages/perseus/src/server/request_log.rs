@@ -0,0 +1,156 @@
+use crate::Request;
+use serde::Serialize;
+use std::env;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// The format request logs are printed in, which is set with the
+/// `PERSEUS_REQUEST_LOG` environment variable.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RequestLogFormat {
+    /// A line of text for a human to read, which is the default in
+    /// development.
+    Pretty,
+    /// A line of JSON for a log collector to read, which is the default in
+    /// production (and can be forced with `PERSEUS_REQUEST_LOG=json`).
+    Json,
+}
+impl RequestLogFormat {
+    /// Gets the format that requests should be logged in, returning `None` if
+    /// they shouldn't be logged at all.
+    fn from_env() -> Option<Self> {
+        match env::var("PERSEUS_REQUEST_LOG").as_deref() {
+            Ok("json") => Some(Self::Json),
+            Ok("pretty") => Some(Self::Pretty),
+            Ok("true" | "1") if cfg!(debug_assertions) => Some(Self::Pretty),
+            Ok("true" | "1") => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A record of where the time was spent rendering a single page, which is
+/// printed once the page has been rendered if `PERSEUS_REQUEST_LOG` is set
+/// (the CLI sets this for `perseus snoop serve --request-log`). This makes it
+/// possible to work out why a page is slow without adding prints to the
+/// engine.
+pub(crate) struct RequestLog {
+    /// The format to print the log in, or `None` if it shouldn't be printed.
+    format: Option<RequestLogFormat>,
+    start: Instant,
+    method: String,
+    path: String,
+    template_name: String,
+    /// The rendering strategies that were used, in the order they were used
+    /// (e.g. `build` and then `request`).
+    strategies: Vec<&'static str>,
+    /// The time spent reading from the immutable and mutable stores.
+    store_time: Duration,
+    /// The time spent generating state and rendering the page.
+    render_time: Duration,
+}
+impl RequestLog {
+    /// Starts timing the rendering of the page at the given path (without the
+    /// locale), in the given locale.
+    pub(crate) fn new(req: &Request, path: &str, locale: &str, template_name: String) -> Self {
+        let path = match locale {
+            "xx-XX" => format!("/{}", path),
+            locale => format!("/{}/{}", locale, path),
+        };
+        Self {
+            format: RequestLogFormat::from_env(),
+            start: Instant::now(),
+            method: req.method().to_string(),
+            path,
+            template_name,
+            strategies: Vec::new(),
+            store_time: Duration::ZERO,
+            render_time: Duration::ZERO,
+        }
+    }
+    /// Records that the given rendering strategy was used.
+    pub(crate) fn strategy(&mut self, strategy: &'static str) {
+        self.strategies.push(strategy);
+    }
+    /// Runs the given future, counting the time it takes as time spent reading
+    /// from stores.
+    pub(crate) async fn store<F: Future>(&mut self, fut: F) -> F::Output {
+        let start = Instant::now();
+        let res = fut.await;
+        self.store_time += start.elapsed();
+        res
+    }
+    /// Runs the given future, counting the time it takes as time spent
+    /// rendering.
+    pub(crate) async fn render<F: Future>(&mut self, fut: F) -> F::Output {
+        let start = Instant::now();
+        let res = fut.await;
+        self.render_time += start.elapsed();
+        res
+    }
+    /// Runs the given function, counting the time it takes as time spent
+    /// rendering.
+    pub(crate) fn render_sync<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.render_time += start.elapsed();
+        res
+    }
+    /// Prints the log, if request logging is enabled.
+    pub(crate) fn finish(self, ok: bool) {
+        let format = match self.format {
+            Some(format) => format,
+            None => return,
+        };
+        let total = self.start.elapsed();
+        let strategy = match self.strategies.is_empty() {
+            true => "none".to_string(),
+            false => self.strategies.join("+"),
+        };
+        match format {
+            RequestLogFormat::Pretty => println!(
+                "[REQUEST]: {} {} -> template '{}' via {}{}: store {:.2}ms, render {:.2}ms, total {:.2}ms",
+                self.method,
+                self.path,
+                self.template_name,
+                strategy,
+                if ok { "" } else { " (failed)" },
+                as_ms(self.store_time),
+                as_ms(self.render_time),
+                as_ms(total)
+            ),
+            RequestLogFormat::Json => {
+                let entry = RequestLogEntry {
+                    method: &self.method,
+                    path: &self.path,
+                    template: &self.template_name,
+                    strategy: &strategy,
+                    ok,
+                    store_ms: as_ms(self.store_time),
+                    render_ms: as_ms(self.render_time),
+                    total_ms: as_ms(total),
+                };
+                // This can't fail, since everything in the entry is plain data
+                println!("{}", serde_json::to_string(&entry).unwrap());
+            }
+        }
+    }
+}
+
+/// A request log as it's printed in JSON.
+#[derive(Serialize)]
+struct RequestLogEntry<'a> {
+    method: &'a str,
+    path: &'a str,
+    template: &'a str,
+    strategy: &'a str,
+    ok: bool,
+    store_ms: f64,
+    render_ms: f64,
+    total_ms: f64,
+}
+
+/// Converts the given duration to fractional milliseconds.
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,87 @@
+use super::static_content::get_path_in_dir;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Gets the directory prefix that the given static alias URL mounts, if it
+/// mounts a whole directory (i.e. if it ends in `/*`). For `/assets/*`, this
+/// would be `/assets`.
+pub fn get_static_alias_mount(url: &str) -> Option<&str> {
+    url.strip_suffix("/*")
+}
+
+/// Works out which file should be served for the given (percent-encoded)
+/// request path from the given static aliases, if any. Aliases for single files
+/// have to match exactly, while aliases that mount directories (like
+/// `/assets/*` => `assets/`) will match anything inside them, with the longest
+/// matching mount taking precedence.
+///
+/// Paths that try to escape their mounted directory (e.g. with `..`) will never
+/// match. Note that this may return a directory, so integrations should use
+/// [`get_static_file`](super::get_static_file) instead, which accounts for the
+/// app's static content options.
+pub fn get_static_alias(static_aliases: &HashMap<String, String>, path: &str) -> Option<PathBuf> {
+    find_static_alias(static_aliases, path).map(|(file, _)| file)
+}
+
+/// Works out which file should be served for the given request path from the
+/// given static aliases (see [`get_static_alias`]), along with its decoded
+/// path relative to the directory it's served from (for aliases of single
+/// files, this is just the file's name).
+pub(crate) fn find_static_alias(
+    static_aliases: &HashMap<String, String>,
+    path: &str,
+) -> Option<(PathBuf, String)> {
+    if let Some(file) = static_aliases.get(path) {
+        let file = PathBuf::from(file);
+        let name = file.file_name()?.to_string_lossy().to_string();
+        return Some((file, name));
+    }
+
+    let (prefix, dir) = static_aliases
+        .iter()
+        .filter_map(|(url, dir)| {
+            let prefix = get_static_alias_mount(url)?;
+            let rest = path.strip_prefix(prefix)?;
+            rest.starts_with('/').then_some((prefix, dir))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())?;
+
+    get_path_in_dir(dir, &path[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_alias_mounts() {
+        let aliases = HashMap::from([
+            ("/file".to_string(), "style.css".to_string()),
+            ("/assets/*".to_string(), "assets".to_string()),
+            ("/assets/fonts/*".to_string(), "fonts/".to_string()),
+        ]);
+        let get = |path| get_static_alias(&aliases, path);
+
+        assert_eq!(get("/file"), Some(PathBuf::from("style.css")));
+        assert_eq!(get("/file/extra"), None);
+        assert_eq!(
+            get("/assets/img/logo%20dark.png"),
+            Some(PathBuf::from("assets").join("img").join("logo dark.png"))
+        );
+        assert_eq!(
+            get("/assets/fonts/mono.woff2"),
+            Some(PathBuf::from("fonts").join("mono.woff2"))
+        );
+        assert_eq!(get("/assets/"), Some(PathBuf::from("assets")));
+        assert_eq!(get("/assetsfoo/bar"), None);
+        assert_eq!(get("/assets/../secret"), None);
+        assert_eq!(get("/assets/%2E%2E/secret"), None);
+        assert_eq!(get("/assets/..%5Csecret"), None);
+        assert_eq!(get("/assets/%2E%2E%2Fsecret"), None);
+        assert_eq!(get("/assets/./logo.png"), None);
+        // Windows drives would replace the whole path when joined onto it
+        assert_eq!(get("/assets/C:/Windows/win.ini"), None);
+        assert_eq!(get("/assets/C%3A%5CWindows%5Cwin.ini"), None);
+        assert_eq!(get("/assets/img/logo.png:stream"), None);
+    }
+}
@@ -0,0 +1,244 @@
+use super::static_alias::find_static_alias;
+use super::ServerOptions;
+use crate::stores::get_asset_path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Options for how static content (i.e. the static directory and static
+/// aliases) is served, which all integrations respect.
+#[derive(Debug, Clone)]
+pub struct StaticContentOptions {
+    /// A map of file extensions (without the leading `.`, e.g. `wasm`) to the
+    /// MIME types files with them should be served with, which will override
+    /// the ones that would otherwise be guessed.
+    pub mime_types: HashMap<String, String>,
+    /// The charset that textual files (e.g. `text/*` or JSON) should be
+    /// declared to be in, unless their MIME type already declares one. By
+    /// default, this is `utf-8`.
+    pub charset: Option<String>,
+    /// The files that should be served when a directory is requested (e.g.
+    /// `index.html`), in order of preference. By default, there are none, and
+    /// directories can't be requested.
+    pub index_files: Vec<String>,
+    /// Patterns for files that should never be served, which may contain `*`
+    /// as a wildcard. Requests for these will be treated as if the files
+    /// didn't exist.
+    ///
+    /// Patterns containing a `/` are matched against the whole path of a file
+    /// relative to the directory it's served from (e.g. `drafts/*` hides
+    /// everything in the `drafts` directory), while patterns without one are
+    /// matched against each part of that path (e.g. `*.map` hides source maps
+    /// anywhere, and `.git` hides a whole directory).
+    pub deny: Vec<String>,
+    /// The size (in bytes) at and above which files will be streamed to
+    /// clients, rather than being read into memory in one go. Streamed files
+    /// support `Range` requests (so videos can be seeked through, and
+    /// downloads resumed) and conditional requests, so requests like those will
+    /// always be streamed regardless of this. By default, this is `0`, so
+    /// every file is streamed.
+    pub stream_threshold: u64,
+}
+impl Default for StaticContentOptions {
+    fn default() -> Self {
+        Self {
+            mime_types: HashMap::new(),
+            charset: Some("utf-8".to_string()),
+            index_files: Vec::new(),
+            deny: Vec::new(),
+            stream_threshold: 0,
+        }
+    }
+}
+impl StaticContentOptions {
+    /// Works out which file should actually be served for a request for the
+    /// given path, which might be a directory (in which case its first index
+    /// file that exists will be used). The path relative to the directory
+    /// it's being served from (with `/` separators) is needed to check it
+    /// against the deny patterns. If there's no such file, or if it's been
+    /// denied, this will return `None`.
+    fn get_file(&self, path: PathBuf, rel_path: &str) -> Option<PathBuf> {
+        let (file, rel_path) = if path.is_dir() {
+            self.index_files
+                .iter()
+                .map(|index_file| {
+                    let rel_path = format!("{}/{}", rel_path.trim_end_matches('/'), index_file);
+                    (path.join(index_file), rel_path)
+                })
+                .find(|(file, _)| file.is_file())?
+        } else {
+            (path, rel_path.to_string())
+        };
+        if !file.is_file() || self.is_denied(&rel_path) {
+            return None;
+        }
+
+        Some(file)
+    }
+    /// Checks if the file at the given path (relative to the directory it's
+    /// served from, with `/` separators) matches any of the deny patterns.
+    fn is_denied(&self, rel_path: &str) -> bool {
+        let rel_path = rel_path.trim_matches('/');
+        self.deny.iter().any(|pattern| {
+            if pattern.contains('/') {
+                matches_pattern(pattern.trim_start_matches('/'), rel_path)
+            } else {
+                rel_path
+                    .split('/')
+                    .any(|segment| matches_pattern(pattern, segment))
+            }
+        })
+    }
+    /// Reads the given file into memory if it's smaller than the stream
+    /// threshold and the request for it (whose header names should be
+    /// provided) isn't a range or conditional request. If this returns `None`,
+    /// the file should be streamed by the integration's file server instead.
+    pub fn read_small_file<'a>(
+        &self,
+        file: &Path,
+        req_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Vec<u8>> {
+        let needs_streaming = req_headers.into_iter().any(|name| {
+            let name = name.to_lowercase();
+            name == "range" || name.starts_with("if-")
+        });
+        if needs_streaming || std::fs::metadata(file).ok()?.len() >= self.stream_threshold {
+            return None;
+        }
+
+        std::fs::read(file).ok()
+    }
+    /// Gets the `Content-Type` the given file should be served with.
+    pub fn get_content_type(&self, file: &Path) -> String {
+        let overridden = file
+            .extension()
+            .and_then(|ext| self.mime_types.get(&ext.to_string_lossy().to_lowercase()));
+        let mime_type = match overridden {
+            Some(mime_type) => mime_type.to_string(),
+            None => mime_guess::from_path(file)
+                .first_or_octet_stream()
+                .to_string(),
+        };
+        let is_textual = mime_type.starts_with("text/")
+            || mime_type.starts_with("application/javascript")
+            || mime_type.starts_with("application/json")
+            || mime_type.starts_with("image/svg+xml");
+        match &self.charset {
+            Some(charset) if is_textual && !mime_type.contains("charset=") => {
+                format!("{}; charset={}", mime_type, charset)
+            }
+            _ => mime_type,
+        }
+    }
+}
+
+/// Gets the file that should be served for the given (percent-encoded) request
+/// path from the app's static content (the static directory at
+/// `/.perseus/static` and any static aliases), taking the static content
+/// options into account. Integrations should serve this with the `Content-Type`
+/// from [`StaticContentOptions::get_content_type`], and treat `None` as a 404.
+pub fn get_static_file(opts: &ServerOptions, path: &str) -> Option<PathBuf> {
+    let (file, rel_path) = match (&opts.static_dir, path.strip_prefix("/.perseus/static/")) {
+        (Some(static_dir), Some(rest)) => get_path_in_dir(static_dir, rest)?,
+        _ => find_static_alias(&opts.static_aliases, path)?,
+    };
+
+    opts.static_content.get_file(file, &rel_path)
+}
+
+/// Gets the path of the file at the given (percent-encoded) path relative to
+/// the given directory, making sure it's actually inside that directory (i.e.
+/// that every segment of it is a plain name, with nothing like `..` or `C:`
+/// that could escape it). This also returns the decoded relative path.
+pub(crate) fn get_path_in_dir(dir: &str, path: &str) -> Option<(PathBuf, String)> {
+    let path = urlencoding::decode(path).ok()?;
+    let file = get_asset_path(dir, &path).ok()?;
+    Some((file, path.into_owned()))
+}
+
+/// Checks if the given name matches the given pattern, in which `*` will match
+/// any number of characters.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            name.starts_with(prefix)
+                && (0..=name.len() - prefix.len()).any(|idx| {
+                    name.is_char_boundary(prefix.len() + idx)
+                        && matches_pattern(rest, &name[prefix.len() + idx..])
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_and_content_types() {
+        assert!(matches_pattern("*.map", "bundle.js.map"));
+        assert!(matches_pattern("secret*", "secret.txt"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("*.map", "bundle.js"));
+        assert!(!matches_pattern("index.html", "index.htm"));
+
+        let mut opts = StaticContentOptions::default();
+        assert_eq!(
+            opts.get_content_type(Path::new("style.css")),
+            "text/css; charset=utf-8"
+        );
+        assert_eq!(opts.get_content_type(Path::new("logo.png")), "image/png");
+        opts.mime_types.insert(
+            "webmanifest".to_string(),
+            "application/manifest+json".to_string(),
+        );
+        opts.charset = None;
+        assert_eq!(
+            opts.get_content_type(Path::new("site.webmanifest")),
+            "application/manifest+json"
+        );
+        assert_eq!(opts.get_content_type(Path::new("style.css")), "text/css");
+    }
+
+    #[test]
+    fn deny_patterns_match_relative_paths() {
+        let opts = StaticContentOptions {
+            deny: vec![
+                "*.map".to_string(),
+                ".git".to_string(),
+                "drafts/*".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert!(opts.is_denied("bundle.js.map"));
+        assert!(opts.is_denied("js/vendor/bundle.js.map"));
+        assert!(opts.is_denied(".git/config"));
+        assert!(opts.is_denied("sub/.git/HEAD"));
+        assert!(opts.is_denied("drafts/post.md"));
+        assert!(opts.is_denied("/drafts/nested/post.md"));
+        assert!(!opts.is_denied("posts/drafts.md"));
+        assert!(!opts.is_denied("published/drafts-list.md"));
+        assert!(!opts.is_denied("bundle.js"));
+    }
+
+    #[test]
+    fn paths_stay_in_dir() {
+        assert_eq!(
+            get_path_in_dir("static", "img/logo%20dark.png"),
+            Some((
+                PathBuf::from("static").join("img").join("logo dark.png"),
+                "img/logo dark.png".to_string()
+            ))
+        );
+        for path in [
+            "../Cargo.toml",
+            "%2E%2E/Cargo.toml",
+            "img/..%2F..%2FCargo.toml",
+            "..%5CCargo.toml",
+            "C:/Windows/win.ini",
+            "C%3A%5CWindows%5Cwin.ini",
+        ] {
+            assert_eq!(get_path_in_dir("static", path), None, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::{get_page_for_template, get_path_slice, GetPageProps, ServerOptions};
+use crate::i18n::TranslationsManager;
+use crate::router::{match_route_atomic, RenderCfg, RouteInfoAtomic, RouteVerdictAtomic};
+use crate::stores::{ImmutableStore, MutableStore};
+use crate::Request;
+use fmterr::fmt_err;
+use std::collections::HashSet;
+
+/// The name of the asset in the mutable store that holds the most recently
+/// requested incrementally generated pages.
+const RECENT_INCREMENTAL_ASSET: &str = "recent_incremental.json";
+
+/// Settings for pre-rendering pages generated with incremental generation when
+/// the server starts, so that the first visitor to each of them after a
+/// restart doesn't have to wait for it to be generated. Note that this does
+/// nothing in development, since incremental generation doesn't cache pages
+/// there.
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalWarmup {
+    /// Paths to pre-render (including the locale, if the app uses i18n). Any
+    /// paths that don't resolve to incrementally generated pages will be
+    /// ignored.
+    pub paths: Vec<String>,
+    /// The number of the most recently requested incrementally generated
+    /// pages to pre-render. If this is non-zero, the paths of these pages will
+    /// be recorded in the mutable store as they're requested.
+    pub recent: usize,
+}
+
+/// Records that the given incrementally generated page (with its locale, if
+/// i18n is being used) was just requested, keeping only the given number of
+/// the most recent pages. Concurrent requests may race each other here, but
+/// this is only a heuristic, so that doesn't matter much.
+pub(crate) async fn record_incremental_request(
+    path: &str,
+    mutable_store: &impl MutableStore,
+    limit: usize,
+) {
+    let mut recent = get_recent_incremental(mutable_store).await;
+    // Don't bother writing if nothing would change
+    if recent.first().map(|s| s.as_str()) == Some(path) {
+        return;
+    }
+    recent.retain(|recent_path| recent_path != path);
+    recent.insert(0, path.to_string());
+    recent.truncate(limit);
+    // This is only a cache, so failures don't matter
+    let _ = mutable_store
+        .write(
+            RECENT_INCREMENTAL_ASSET,
+            &serde_json::to_string(&recent).unwrap(),
+        )
+        .await;
+}
+
+/// Gets the most recently requested incrementally generated pages, most recent
+/// first.
+async fn get_recent_incremental(mutable_store: &impl MutableStore) -> Vec<String> {
+    match mutable_store.read(RECENT_INCREMENTAL_ASSET).await {
+        Ok(recent) => serde_json::from_str(&recent).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Pre-renders the incrementally generated pages declared in the given
+/// options, so that they'll be cached before any users request them. This
+/// should be called by server integrations once everything else has been set
+/// up, before they start listening for requests. Any pages that fail to render
+/// will be skipped with a warning, since they'll just be rendered when they're
+/// requested instead.
+pub async fn warm_up_incremental<M: MutableStore + 'static, T: TranslationsManager + 'static>(
+    opts: &ServerOptions,
+    render_cfg: &RenderCfg,
+    immutable_store: &ImmutableStore,
+    mutable_store: &M,
+    translations_manager: &T,
+    global_state: &Option<String>,
+) {
+    // Incremental generation doesn't cache anything in development
+    if cfg!(debug_assertions) {
+        return;
+    }
+    let warmup = &opts.incremental_warmup;
+    let mut paths = warmup.paths.clone();
+    if warmup.recent > 0 {
+        let mut recent = get_recent_incremental(mutable_store).await;
+        recent.truncate(warmup.recent);
+        paths.extend(recent);
+    }
+    // Recently requested pages might also have been declared explicitly
+    let mut seen = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+
+    for path in paths {
+        let path_slice = get_path_slice(&path);
+        let verdict =
+            match_route_atomic(&path_slice, render_cfg, &opts.templates_map, &opts.locales);
+        // Anything that isn't incrementally generated is either already built or
+        // can't be pre-rendered
+        if let RouteVerdictAtomic::Found(RouteInfoAtomic {
+            path: raw_path,
+            template,
+            locale,
+            was_incremental_match: true,
+        }) = verdict
+        {
+            // There's no real request, so we make a blank one for this page
+            let req = Request::builder()
+                .uri(format!("/{}", path.trim_start_matches('/')))
+                .body(())
+                .unwrap_or_default();
+            let res = get_page_for_template(
+                GetPageProps::<M, T> {
+                    raw_path: &raw_path,
+                    locale: &locale,
+                    was_incremental_match: true,
+                    req,
+                    global_state,
+                    immutable_store,
+                    mutable_store,
+                    translations_manager,
+                    plugins: &opts.plugins,
+                    incremental_warmup: warmup,
+                    templates: &opts.templates_map,
+                    error_reporter: &opts.error_reporter,
+                    state_fn_timeout: opts.state_fn_timeout,
+                    build_redirects: &opts.build_redirects,
+                },
+                template,
+            )
+            .await;
+            if let Err(err) = res {
+                eprintln!(
+                    "[WARNING]: couldn't pre-render incrementally generated page '{}': {}",
+                    path,
+                    fmt_err(&err)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,790 @@
+//! Perseus can manage cookie-based sessions for you, so that logged-in
+//! experiences don't need an entire external framework. Sessions are enabled
+//! by passing [`Sessions`] to `PerseusApp::sessions()`, after which every
+//! request will have a session available through [`get_session`], both in
+//! request-state functions and in your own routes (with [`Sessions::load`] and
+//! [`Sessions::commit`]).
+//!
+//! Only a random, unguessable session ID is ever stored in the user's cookie,
+//! and all the session's data is kept on the server in a [`SessionStore`]
+//! (Perseus provides [`MemorySessionStore`], [`MutableSessionStore`], which
+//! uses any [`MutableStore`], and `RedisSessionStore` with the `redis-store`
+//! feature). This means there's nothing in the cookie that a user could read
+//! or tamper with, other than the ID itself.
+//!
+//! The ID isn't signed, because there's nothing to gain from that: it's 128
+//! bits from the operating system's secure random number generator, so it
+//! can't be guessed, and tampering with it can only ever produce an ID that
+//! doesn't exist in the store (which just gives the user a fresh session).
+//! Signing would only matter if the cookie carried data the server trusted.
+//!
+//! Session data is private by default, but values inserted with
+//! [`Session::insert_public`] will be sent to the browser on initial loads
+//! (e.g. the user's display name), where they can be accessed with
+//! [`RenderCtx::session`](crate::template::RenderCtx::session). Never make
+//! anything sensitive public!
+//!
+//! Any response that reads the session (or that has public session data
+//! embedded in it) will be sent with `Cache-Control: private, no-store` and
+//! `Vary: Cookie`, overriding the page's own headers, so that it can never be
+//! served to another user by a shared cache.
+
+use crate::errors::StoreError;
+use crate::stores::MutableStore;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::{get_cookie, is_served_over_https, random_token};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the cookie that holds the user's session ID.
+pub const SESSION_COOKIE: &str = "__perseus_session";
+
+/// The data stored in a session, which is what [`SessionStore`]s persist.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionData {
+    /// Data that will only ever be available on the server.
+    private: HashMap<String, Value>,
+    /// Data that will be sent to the browser on initial loads.
+    public: HashMap<String, Value>,
+}
+
+/// A user's session. This is accessed through a [`SessionHandle`], which can be
+/// obtained with [`get_session`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct Session {
+    /// The session's ID.
+    id: String,
+    /// The session's data.
+    data: SessionData,
+    /// Whether or not this session has been saved before.
+    is_new: bool,
+    /// Whether or not this session's data has been changed.
+    modified: bool,
+    /// Whether or not this session has been destroyed.
+    destroyed: bool,
+    /// The ID this session was saved under before it was given a new one,
+    /// which will be removed from the store when the session is committed.
+    stale_id: Option<String>,
+    /// Whether or not this session has been accessed while handling the
+    /// current request.
+    accessed: bool,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl Session {
+    /// Creates a new, empty session.
+    fn new() -> Self {
+        Self {
+            id: random_token(),
+            data: SessionData::default(),
+            is_new: true,
+            modified: false,
+            destroyed: false,
+            stale_id: None,
+            accessed: false,
+        }
+    }
+    /// Gives this session a new ID, keeping its data. The old ID will stop
+    /// working once the request has been handled.
+    ///
+    /// You should do this whenever a user's privileges change (most
+    /// importantly when they log in), so that an attacker who managed to plant
+    /// a session ID in their browser beforehand can't use it to act as them
+    /// (this is called *session fixation*).
+    pub fn regenerate_id(&mut self) {
+        self.retire_id();
+        self.id = random_token();
+        self.modified = true;
+    }
+    /// Marks the current ID of this session to be removed from the store, if
+    /// it was ever saved there.
+    fn retire_id(&mut self) {
+        if !self.is_new && self.stale_id.is_none() {
+            self.stale_id = Some(self.id.clone());
+        }
+        self.is_new = true;
+    }
+    /// Starts a fresh session if this one has been destroyed, so that data
+    /// inserted after `.destroy()` doesn't end up under the old ID.
+    fn revive(&mut self) {
+        if self.destroyed {
+            self.retire_id();
+            self.id = random_token();
+            self.destroyed = false;
+        }
+    }
+    /// Gets the value of the given key in this session, if it's there and can
+    /// be deserialized into the given type. Public values are included.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let val = self
+            .data
+            .private
+            .get(key)
+            .or_else(|| self.data.public.get(key))?;
+        serde_json::from_value(val.clone()).ok()
+    }
+    /// Sets the given key in this session to the given value, which will only
+    /// ever be available on the server.
+    pub fn insert<T: Serialize>(&mut self, key: &str, val: T) -> Result<(), serde_json::Error> {
+        let val = serde_json::to_value(val)?;
+        self.revive();
+        self.data.public.remove(key);
+        self.data.private.insert(key.to_string(), val);
+        self.modified = true;
+
+        Ok(())
+    }
+    /// Sets the given key in this session to the given value, which will be
+    /// sent to the browser on initial loads.
+    pub fn insert_public<T: Serialize>(
+        &mut self,
+        key: &str,
+        val: T,
+    ) -> Result<(), serde_json::Error> {
+        let val = serde_json::to_value(val)?;
+        self.revive();
+        self.data.private.remove(key);
+        self.data.public.insert(key.to_string(), val);
+        self.modified = true;
+
+        Ok(())
+    }
+    /// Removes the given key from this session.
+    pub fn remove(&mut self, key: &str) {
+        let removed_private = self.data.private.remove(key).is_some();
+        let removed_public = self.data.public.remove(key).is_some();
+        self.modified = self.modified || removed_private || removed_public;
+    }
+    /// Destroys this session (e.g. when a user logs out). Its data will be
+    /// removed from the store, and the user will get a new session on their
+    /// next request (or immediately, if anything is inserted into this one
+    /// afterward).
+    pub fn destroy(&mut self) {
+        self.data = SessionData::default();
+        self.destroyed = true;
+    }
+    /// Gets a snapshot of the public data in this session.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot(self.data.public.clone())
+    }
+}
+
+/// A shared handle to a [`Session`], which can be cloned freely. Changes made
+/// through any handle will be saved once the request has been handled.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct SessionHandle(Arc<Mutex<Session>>);
+#[cfg(not(target_arch = "wasm32"))]
+impl SessionHandle {
+    /// Locks the session so that it can be read or modified. Don't hold this
+    /// across `.await` points!
+    pub fn lock(&self) -> MutexGuard<Session> {
+        let mut session = self.lock_quietly();
+        session.accessed = true;
+        session
+    }
+    /// Locks the session without marking it as accessed, for Perseus' own
+    /// bookkeeping.
+    fn lock_quietly(&self) -> MutexGuard<Session> {
+        // The lock can only be poisoned if a user's function panicked, in which case
+        // the data is still perfectly usable
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+    /// Gets a snapshot of the public data in this session, without marking it
+    /// as accessed.
+    ///
+    /// This is designed for use by server integrations.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        self.lock_quietly().snapshot()
+    }
+    /// Checks whether or not a response to the current request depends on
+    /// this session, which is the case if it was accessed while handling the
+    /// request, or if it has public data that will be sent to the browser. Such
+    /// responses must never be stored in shared caches (see
+    /// [`make_private`](crate::utils::make_private)).
+    ///
+    /// This is designed for use by server integrations.
+    pub fn is_private(&self) -> bool {
+        let session = self.lock_quietly();
+        session.accessed || !session.data.public.is_empty()
+    }
+}
+
+/// Gets the session of the given request. This is designed to be used in
+/// request-state functions, and it will return `None` if sessions haven't been
+/// enabled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_session(req: &crate::Request) -> Option<SessionHandle> {
+    req.extensions().get::<SessionHandle>().cloned()
+}
+
+/// The public data of a user's session, as seen by the browser. Note that
+/// prerendered pages are shared between all users, so this will be empty on
+/// the server-side.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSnapshot(HashMap<String, Value>);
+impl SessionSnapshot {
+    /// Gets the value of the given key, if it's there and can be deserialized
+    /// into the given type.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_value(self.0.get(key)?.clone()).ok()
+    }
+    /// Checks if there's no public session data.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Gets the session snapshot that has been injected into the page by the
+    /// server, if there is one.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn from_window() -> Self {
+        use sycamore::rt::{JsValue, Reflect};
+
+        let val = Reflect::get(
+            &JsValue::from(web_sys::window().unwrap()),
+            &JsValue::from("__PERSEUS_SESSION"),
+        )
+        .ok()
+        .and_then(|val| val.as_string());
+        match val {
+            Some(val) => serde_json::from_str(&val).unwrap_or_default(),
+            None => Self::default(),
+        }
+    }
+}
+
+/// A backend that session data can be kept in. Implementations should make
+/// sure that sessions expire after the TTL given when they're saved.
+#[async_trait::async_trait]
+pub trait SessionStore: std::fmt::Debug + Send + Sync {
+    /// Loads the data of the session with the given ID, if it exists and
+    /// hasn't expired.
+    async fn load(&self, id: &str) -> Result<Option<SessionData>, StoreError>;
+    /// Saves the data of the session with the given ID, which should expire
+    /// after the given duration.
+    async fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), StoreError>;
+    /// Removes the session with the given ID.
+    async fn remove(&self, id: &str) -> Result<(), StoreError>;
+}
+
+/// A [`SessionStore`] that keeps sessions in memory. This is simple and fast,
+/// but sessions will be lost whenever the server restarts, and they can't be
+/// shared between several server instances.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore {
+    /// The sessions, with the times at which they expire.
+    #[cfg(not(target_arch = "wasm32"))]
+    sessions: Arc<Mutex<HashMap<String, (SessionData, SystemTime)>>>,
+}
+#[async_trait::async_trait]
+impl SessionStore for MemorySessionStore {
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn load(&self, id: &str) -> Result<Option<SessionData>, StoreError> {
+        let sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+        Ok(sessions
+            .get(id)
+            .filter(|(_, expiry)| *expiry > SystemTime::now())
+            .map(|(data, _)| data.clone()))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+        // Clean up any expired sessions while we're here
+        let now = SystemTime::now();
+        sessions.retain(|_, (_, expiry)| *expiry > now);
+        sessions.insert(id.to_string(), (data.clone(), now + ttl));
+
+        Ok(())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn remove(&self, id: &str) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+        sessions.remove(id);
+
+        Ok(())
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn load(&self, _id: &str) -> Result<Option<SessionData>, StoreError> {
+        Ok(None)
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn save(&self, _id: &str, _data: &SessionData, _ttl: Duration) -> Result<(), StoreError> {
+        Ok(())
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn remove(&self, _id: &str) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// A [`SessionStore`] that keeps sessions in a [`MutableStore`] (under
+/// `sessions/`), which can be shared between several server instances if the
+/// mutable store is. Note that mutable stores have no way of deleting assets,
+/// so expired sessions will be ignored, but never cleaned up.
+#[derive(Debug, Clone)]
+pub struct MutableSessionStore<M: MutableStore> {
+    /// The underlying mutable store.
+    #[cfg(not(target_arch = "wasm32"))]
+    store: M,
+    #[cfg(target_arch = "wasm32")]
+    _marker: std::marker::PhantomData<M>,
+}
+impl<M: MutableStore> MutableSessionStore<M> {
+    /// Creates a new session store that uses the given mutable store.
+    #[allow(unused_variables)]
+    pub fn new(store: M) -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            store,
+            #[cfg(target_arch = "wasm32")]
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+/// A session as it's kept in a [`MutableSessionStore`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    /// The UNIX timestamp at which the session expires.
+    expires: u64,
+    /// The session's data.
+    data: SessionData,
+}
+#[async_trait::async_trait]
+impl<M: MutableStore> SessionStore for MutableSessionStore<M> {
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn load(&self, id: &str) -> Result<Option<SessionData>, StoreError> {
+        let raw = match self.store.read(&format!("sessions/{}.json", id)).await {
+            Ok(raw) => raw,
+            Err(StoreError::NotFound { .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        // Anything we can't parse is treated as if it weren't there
+        Ok(serde_json::from_str::<StoredSession>(&raw)
+            .ok()
+            .filter(|stored| stored.expires > unix_now())
+            .map(|stored| stored.data))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), StoreError> {
+        let stored = StoredSession {
+            expires: unix_now() + ttl.as_secs(),
+            data: data.clone(),
+        };
+        // This is made of strings and JSON values, so it can't fail to serialize
+        let raw = serde_json::to_string(&stored).unwrap();
+        self.store
+            .write(&format!("sessions/{}.json", id), &raw)
+            .await
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn remove(&self, id: &str) -> Result<(), StoreError> {
+        // We can't delete the asset, so we'll overwrite it with an expired session
+        let raw = serde_json::to_string(&StoredSession {
+            expires: 0,
+            data: SessionData::default(),
+        })
+        .unwrap();
+        self.store
+            .write(&format!("sessions/{}.json", id), &raw)
+            .await
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn load(&self, _id: &str) -> Result<Option<SessionData>, StoreError> {
+        Ok(None)
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn save(&self, _id: &str, _data: &SessionData, _ttl: Duration) -> Result<(), StoreError> {
+        Ok(())
+    }
+    #[cfg(target_arch = "wasm32")]
+    async fn remove(&self, _id: &str) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// A [`SessionStore`] that keeps sessions in Redis, which allows many server
+/// instances to share them. Sessions are expired by Redis itself.
+///
+/// All keys will be prefixed with the given prefix (followed by `sessions:`),
+/// so that a single Redis database can be shared by several apps.
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+#[derive(Clone)]
+pub struct RedisSessionStore {
+    conn: redis::aio::ConnectionManager,
+    prefix: String,
+}
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+impl std::fmt::Debug for RedisSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisSessionStore")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+impl RedisSessionStore {
+    /// Connects to the Redis server at the given URL (e.g.
+    /// `redis://127.0.0.1/`), prefixing all keys with the given prefix (e.g.
+    /// `my-app:`). The connection will automatically be re-established if it
+    /// drops.
+    pub async fn new(url: &str, prefix: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let conn = redis::aio::ConnectionManager::new(client).await?;
+
+        Ok(Self {
+            conn,
+            prefix: prefix.to_string(),
+        })
+    }
+    /// Gets the key for the session with the given ID.
+    fn session_key(&self, id: &str) -> String {
+        format!("{}sessions:{}", self.prefix, id)
+    }
+}
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn load(&self, id: &str) -> Result<Option<SessionData>, StoreError> {
+        use redis::AsyncCommands;
+
+        let key = self.session_key(id);
+        let mut conn = self.conn.clone();
+        let raw: Option<String> = conn.get(&key).await.map_err(|err| StoreError::ReadFailed {
+            name: key,
+            source: err.into(),
+        })?;
+
+        Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+    }
+    async fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), StoreError> {
+        use redis::AsyncCommands;
+
+        let key = self.session_key(id);
+        let mut conn = self.conn.clone();
+        let raw = serde_json::to_string(data).unwrap();
+        conn.set_ex(&key, raw, ttl.as_secs() as usize)
+            .await
+            .map_err(|err| StoreError::WriteFailed {
+                name: key,
+                source: err.into(),
+            })
+    }
+    async fn remove(&self, id: &str) -> Result<(), StoreError> {
+        use redis::AsyncCommands;
+
+        let key = self.session_key(id);
+        let mut conn = self.conn.clone();
+        conn.del(&key).await.map_err(|err| StoreError::WriteFailed {
+            name: key,
+            source: err.into(),
+        })
+    }
+}
+
+/// The configuration of an app's sessions. This can be cloned freely, and
+/// you should share it between your `PerseusApp` and any of your own routes
+/// that need to access sessions.
+#[derive(Debug, Clone)]
+pub struct Sessions {
+    /// Where sessions are kept.
+    #[cfg(not(target_arch = "wasm32"))]
+    store: Arc<dyn SessionStore>,
+    /// How long sessions last after they were last modified.
+    #[cfg(not(target_arch = "wasm32"))]
+    ttl: Duration,
+    /// Whether or not the session cookie should only be sent over HTTPS. If
+    /// this isn't set, the cookie will be secure if the app is served over
+    /// HTTPS, just like the CSRF cookie.
+    #[cfg(not(target_arch = "wasm32"))]
+    secure: Option<bool>,
+}
+impl Sessions {
+    /// Creates a new session configuration that keeps sessions in the given
+    /// store. By default, sessions will last for a week after they were last
+    /// modified.
+    #[allow(unused_variables)]
+    pub fn new(store: impl SessionStore + 'static) -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            store: Arc::new(store),
+            #[cfg(not(target_arch = "wasm32"))]
+            ttl: Duration::from_secs(60 * 60 * 24 * 7),
+            #[cfg(not(target_arch = "wasm32"))]
+            secure: None,
+        }
+    }
+    /// Sets how long sessions should last for after they were last modified.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn ttl(mut self, val: Duration) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.ttl = val;
+        }
+        self
+    }
+    /// Sets whether or not the session cookie should only be sent over HTTPS.
+    /// By default, this will be the case if the app's base path is an
+    /// `https://` URL (the same as for the CSRF cookie), so you should only
+    /// need this if your app is behind a proxy that changes the scheme.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn secure(mut self, val: bool) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.secure = Some(val);
+        }
+        self
+    }
+    /// Loads the session referenced by the given `Cookie` headers, or creates
+    /// a new one if there isn't one (or if it's expired). New sessions won't be
+    /// saved unless they're modified.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn load<'a>(
+        &self,
+        cookie_headers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<SessionHandle, StoreError> {
+        let id = get_cookie(cookie_headers, SESSION_COOKIE)
+            .filter(|id| id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        let session = match id {
+            Some(id) => match self.store.load(id).await? {
+                Some(data) => Session {
+                    id: id.to_string(),
+                    data,
+                    is_new: false,
+                    modified: false,
+                    destroyed: false,
+                    stale_id: None,
+                    accessed: false,
+                },
+                None => Session::new(),
+            },
+            None => Session::new(),
+        };
+
+        Ok(SessionHandle(Arc::new(Mutex::new(session))))
+    }
+    /// Loads the session of the given request (see `.load()`) and stores it in
+    /// the request, so that it can be accessed with [`get_session`].
+    ///
+    /// This is designed for use by server integrations.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn attach(&self, req: &mut crate::Request) -> Result<SessionHandle, StoreError> {
+        let cookie_headers = req
+            .headers()
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|header| header.to_str().ok());
+        let handle = self.load(cookie_headers).await?;
+        req.extensions_mut().insert(handle.clone());
+
+        Ok(handle)
+    }
+    /// Saves any changes made to the given session, returning the value of a
+    /// `Set-Cookie` header that should be sent to the user, if one is needed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn commit(&self, handle: &SessionHandle) -> Result<Option<String>, StoreError> {
+        // We can't hold the lock across `.await`s
+        let (id, data, is_new, modified, destroyed, stale_id) = {
+            let mut session = handle.lock_quietly();
+            (
+                session.id.clone(),
+                session.data.clone(),
+                session.is_new,
+                session.modified,
+                session.destroyed,
+                session.stale_id.take(),
+            )
+        };
+        let secure = match self.secure.unwrap_or_else(is_served_over_https) {
+            true => "; Secure",
+            false => "",
+        };
+
+        // Any ID the session had before it was regenerated must stop working
+        let had_stale_id = stale_id.is_some();
+        if let Some(stale_id) = stale_id {
+            self.store.remove(&stale_id).await?;
+        }
+
+        if destroyed {
+            if is_new && !had_stale_id {
+                return Ok(None);
+            }
+            if !is_new {
+                self.store.remove(&id).await?;
+            }
+            Ok(Some(format!(
+                "{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax{}",
+                SESSION_COOKIE, secure
+            )))
+        } else if modified {
+            self.store.save(&id, &data, self.ttl).await?;
+            handle.lock_quietly().is_new = false;
+            Ok(Some(format!(
+                "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+                SESSION_COOKIE,
+                id,
+                self.ttl.as_secs(),
+                secure
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Gets the current UNIX timestamp, in seconds.
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_are_only_saved_when_modified() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let session = sessions.load(None::<&str>).await.unwrap();
+            assert_eq!(sessions.commit(&session).await.unwrap(), None);
+
+            session.lock().insert("user", "alice").unwrap();
+            let set_cookie = sessions.commit(&session).await.unwrap().unwrap();
+            let cookie = set_cookie.split(';').next().unwrap();
+            let loaded = sessions.load(Some(cookie)).await.unwrap();
+            assert_eq!(
+                loaded.lock().get::<String>("user").as_deref(),
+                Some("alice")
+            );
+            assert!(loaded.lock().snapshot().is_empty());
+
+            loaded.lock().destroy();
+            let set_cookie = sessions.commit(&loaded).await.unwrap().unwrap();
+            assert!(set_cookie.contains("Max-Age=0"));
+            let loaded = sessions.load(Some(cookie)).await.unwrap();
+            assert_eq!(loaded.lock().get::<String>("user"), None);
+        });
+    }
+
+    #[test]
+    fn cookies_are_secure_like_csrf_cookie_by_default() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let set_cookie = |sessions: Sessions| async move {
+                let session = sessions.load(None::<&str>).await.unwrap();
+                session.lock().insert("user", "alice").unwrap();
+                sessions.commit(&session).await.unwrap().unwrap()
+            };
+
+            let default = set_cookie(Sessions::new(MemorySessionStore::default())).await;
+            assert_eq!(default.ends_with("; Secure"), is_served_over_https());
+            let secure =
+                set_cookie(Sessions::new(MemorySessionStore::default()).secure(true)).await;
+            assert!(secure.ends_with("; Secure"));
+            let insecure =
+                set_cookie(Sessions::new(MemorySessionStore::default()).secure(false)).await;
+            assert!(!insecure.contains("Secure"));
+        });
+    }
+
+    /// Saves a session with a single value in it, returning the cookie that
+    /// references it.
+    async fn saved_session(sessions: &Sessions) -> String {
+        let session = sessions.load(None::<&str>).await.unwrap();
+        session.lock().insert("user", "alice").unwrap();
+        let set_cookie = sessions.commit(&session).await.unwrap().unwrap();
+        set_cookie.split(';').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn regenerating_id_invalidates_old_one() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let old_cookie = saved_session(&sessions).await;
+
+            let session = sessions.load(Some(old_cookie.as_str())).await.unwrap();
+            session.lock().regenerate_id();
+            let set_cookie = sessions.commit(&session).await.unwrap().unwrap();
+            let new_cookie = set_cookie.split(';').next().unwrap();
+            assert_ne!(new_cookie, old_cookie);
+
+            let old = sessions.load(Some(old_cookie.as_str())).await.unwrap();
+            assert_eq!(old.lock().get::<String>("user"), None);
+            let new = sessions.load(Some(new_cookie)).await.unwrap();
+            assert_eq!(new.lock().get::<String>("user").as_deref(), Some("alice"));
+        });
+    }
+
+    #[test]
+    fn inserting_after_destroy_starts_fresh_session() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let old_cookie = saved_session(&sessions).await;
+
+            let session = sessions.load(Some(old_cookie.as_str())).await.unwrap();
+            session.lock().destroy();
+            session.lock().insert("flash", "logged out").unwrap();
+            let set_cookie = sessions.commit(&session).await.unwrap().unwrap();
+            let new_cookie = set_cookie.split(';').next().unwrap();
+            assert_ne!(new_cookie, old_cookie);
+            assert!(!set_cookie.contains("Max-Age=0"));
+
+            let old = sessions.load(Some(old_cookie.as_str())).await.unwrap();
+            assert_eq!(old.lock().get::<String>("flash"), None);
+            let new = sessions.load(Some(new_cookie)).await.unwrap();
+            assert_eq!(new.lock().get::<String>("user"), None);
+            assert_eq!(
+                new.lock().get::<String>("flash").as_deref(),
+                Some("logged out")
+            );
+        });
+    }
+
+    #[test]
+    fn sessions_are_private_once_accessed() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let session = sessions.load(None::<&str>).await.unwrap();
+            assert!(session.snapshot().is_empty());
+            assert!(!session.is_private());
+            let _ = session.lock().get::<String>("user");
+            assert!(session.is_private());
+
+            // Public data ends up in the page, even if nothing reads it
+            let cookie = {
+                let session = sessions.load(None::<&str>).await.unwrap();
+                session.lock().insert_public("name", "Alice").unwrap();
+                let set_cookie = sessions.commit(&session).await.unwrap().unwrap();
+                set_cookie.split(';').next().unwrap().to_string()
+            };
+            let session = sessions.load(Some(cookie.as_str())).await.unwrap();
+            assert!(session.is_private());
+        });
+    }
+}
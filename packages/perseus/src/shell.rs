@@ -1,36 +1,116 @@
-use crate::error_pages::ErrorPageData;
+use crate::error_pages::{ErrorContext, ErrorKind, ErrorPageData};
 use crate::errors::*;
 use crate::i18n::ClientTranslationsManager;
 use crate::page_data::PageData;
-use crate::router::{RouteVerdict, RouterLoadState, RouterState};
-use crate::template::{PageProps, Template, TemplateNodeType};
+use crate::router::{scroll_to_hash, RenderCfg, RouteVerdict, RouterLoadState, RouterState};
+use crate::template::{PageProps, RenderCtx, Template, TemplateNodeType, BOOT_INDICATOR_CLASS};
+use crate::translator::Translator;
 use crate::utils::get_path_prefix_client;
+#[cfg(all(feature = "hydrate", debug_assertions))]
+use crate::utils::HydrationSnapshot;
 use crate::ErrorPages;
 use fmterr::fmt_err;
-use std::collections::HashMap;
+use futures::channel::oneshot;
+use std::cell::RefCell;
 use std::rc::Rc;
 use sycamore::prelude::*;
 use sycamore::rt::Reflect; // We can piggyback off Sycamore to avoid bringing in `js_sys`
-use wasm_bindgen::{JsCast, JsValue};
+#[cfg(feature = "hydrate")]
+use sycamore::utils::hydrate::with_no_hydration_context;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Element, Request, RequestInit, RequestMode, Response};
+use web_sys::{AbortController, Element, Headers, Request, RequestInit, RequestMode, Response};
+
+/// The number of milliseconds after which a fetch will be aborted and treated
+/// as having timed out.
+const FETCH_TIMEOUT_MS: i32 = 30_000;
 
 /// Fetches the given resource. This should NOT be used by end users, but it's
 /// required by the CLI.
 pub(crate) async fn fetch(url: &str) -> Result<Option<String>, ClientError> {
+    let res = send_request(url, None).await?;
+    read_body(url, res).await
+}
+
+/// The result of fetching a resource that may already be cached (see
+/// [`fetch_if_modified`]).
+pub(crate) enum Revalidated {
+    /// The cached version of the resource is still current.
+    NotModified,
+    /// The resource has changed (or there was no cached version), so it was
+    /// sent again, along with its new ETag, if it has one.
+    Modified { body: String, etag: Option<String> },
+}
+
+/// Fetches the given resource, unless the version of it with the given ETag is
+/// still current, in which case the server won't send it again. Like
+/// [`fetch`], this will return `None` if the resource doesn't exist.
+pub(crate) async fn fetch_if_modified(
+    url: &str,
+    etag: Option<&str>,
+) -> Result<Option<Revalidated>, ClientError> {
+    let res = send_request(url, etag).await?;
+    if res.status() == 304 {
+        return Ok(Some(Revalidated::NotModified));
+    }
+    let new_etag = res.headers().get("ETag").ok().flatten();
+    let body = read_body(url, res).await?;
+
+    Ok(body.map(|body| Revalidated::Modified {
+        body,
+        etag: new_etag,
+    }))
+}
+
+/// Sends a `GET` request for the given resource, aborting it if it takes too
+/// long. If an ETag is given, the server will be asked to only send the
+/// resource if it's changed since that version.
+async fn send_request(url: &str, etag: Option<&str>) -> Result<Response, ClientError> {
     let js_err_handler = |err: JsValue| ClientError::Js(format!("{:?}", err));
+    // We abort the request if it takes too long
+    let controller = AbortController::new().map_err(js_err_handler)?;
     let mut opts = RequestInit::new();
-    opts.method("GET").mode(RequestMode::Cors);
+    opts.method("GET")
+        .mode(RequestMode::Cors)
+        .signal(Some(&controller.signal()));
+    if let Some(etag) = etag {
+        let headers = Headers::new().map_err(js_err_handler)?;
+        headers.set("If-None-Match", etag).map_err(js_err_handler)?;
+        opts.headers(&headers);
+    }
 
     let request = Request::new_with_str_and_init(url, &opts).map_err(js_err_handler)?;
 
     let window = web_sys::window().unwrap();
-    // Get the response as a future and await it
-    let res_value = JsFuture::from(window.fetch_with_request(&request))
-        .await
+    let abort = Closure::once_into_js(move || controller.abort());
+    let timeout = window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            abort.unchecked_ref(),
+            FETCH_TIMEOUT_MS,
+        )
         .map_err(js_err_handler)?;
+    // Get the response as a future and await it (this only fails if the request
+    // couldn't be made at all, or if we aborted it)
+    let res_value = JsFuture::from(window.fetch_with_request(&request)).await;
+    window.clear_timeout_with_handle(timeout);
+    let res_value = res_value.map_err(|err| match request.signal().aborted() {
+        true => FetchError::Timeout {
+            url: url.to_string(),
+        },
+        false => FetchError::Network {
+            url: url.to_string(),
+            err: format!("{:?}", err),
+        },
+    })?;
     // Turn that into a proper response object
-    let res: Response = res_value.dyn_into().unwrap();
+    Ok(res_value.dyn_into().unwrap())
+}
+
+/// Reads the body of the given response to a request for the given resource,
+/// returning `None` if the server said it doesn't exist, and an error for any
+/// other unsuccessful status.
+async fn read_body(url: &str, res: Response) -> Result<Option<String>, ClientError> {
+    let js_err_handler = |err: JsValue| ClientError::Js(format!("{:?}", err));
     // If the status is 404, we should return that the request worked but no file
     // existed
     if res.status() == 404 {
@@ -54,38 +134,130 @@ pub(crate) async fn fetch(url: &str) -> Result<Option<String>, ClientError> {
     };
     // Handle non-200 error codes
     if res.status() == 200 {
-        Ok(Some(body_str))
-    } else {
-        Err(FetchError::NotOk {
+        return Ok(Some(body_str));
+    }
+    // Errors from the app itself are sent as JSON error page data
+    let is_json = matches!(
+        res.headers().get("Content-Type"),
+        Ok(Some(content_type)) if content_type.starts_with("application/json")
+    );
+    let app_err_data = match is_json {
+        true => serde_json::from_str::<ErrorPageData>(&body_str).ok(),
+        false => None,
+    };
+    match app_err_data {
+        Some(ErrorPageData {
+            status,
+            err,
+            app_error: Some(app_error),
+            ..
+        }) => Err(FetchError::AppError {
+            url: url.to_string(),
+            status,
+            err,
+            app_error,
+        }
+        .into()),
+        _ => Err(FetchError::NotOk {
             url: url.to_string(),
             status: res.status(),
             err: body_str,
         }
-        .into())
+        .into()),
     }
 }
 
 /// Gets the render configuration from the JS global variable
 /// `__PERSEUS_RENDER_CFG`, which should be inlined by the server. This will
-/// return `None` on any error (not found, serialization failed, etc.), which
-/// should reasonably lead to a `panic!` in the caller.
-pub(crate) fn get_render_cfg() -> Option<HashMap<String, String>> {
+/// make sure the render configuration is in the format this bundle expects and
+/// that it passes its integrity check, returning an error otherwise (which
+/// should reasonably lead to a `panic!` in the caller).
+pub(crate) fn get_render_cfg() -> Result<RenderCfg, ClientError> {
     let val_opt = web_sys::window().unwrap().get("__PERSEUS_RENDER_CFG");
-    let js_obj = match val_opt {
-        Some(js_obj) => js_obj,
-        None => return None,
-    };
     // The object should only actually contain the string value that was injected
-    let cfg_str = match js_obj.as_string() {
+    let cfg_str = match val_opt.and_then(|js_obj| js_obj.as_string()) {
         Some(cfg_str) => cfg_str,
-        None => return None,
-    };
-    let render_cfg = match serde_json::from_str::<HashMap<String, String>>(&cfg_str) {
-        Ok(render_cfg) => render_cfg,
-        Err(_) => return None,
+        None => return Err(ClientError::RenderCfgNotInjected),
     };
+    let render_cfg = RenderCfg::from_json(&cfg_str)?;
+
+    Ok(render_cfg)
+}
+
+/// Marks the app as hydrated once the first page the user loaded (or the error
+/// page shown instead of it) has been rendered, which removes the boot
+/// indicator (if the app has one) and updates `RenderCtx::is_hydrated()`.
+pub(crate) fn mark_hydrated(cx: Scope) {
+    let hydrated = &RenderCtx::from_ctx(cx).hydrated;
+    if *hydrated.get_untracked() {
+        return;
+    }
+    if let Some(body) = web_sys::window().unwrap().document().unwrap().body() {
+        let classes = body
+            .class_name()
+            .split_whitespace()
+            .filter(|class| *class != BOOT_INDICATOR_CLASS)
+            .collect::<Vec<_>>()
+            .join(" ");
+        body.set_class_name(&classes);
+    }
+    hydrated.set(true);
+}
+
+/// Reloads the current page, which is how errors in the shell are retried.
+pub(crate) fn reload_page() {
+    // If this fails, there's nothing more we can do
+    let _ = web_sys::window().unwrap().location().reload();
+}
+
+/// Follows a redirect from a page's state generation functions, replacing the
+/// page in the user's history (as a real HTTP redirect would). Locations
+/// within the app are navigated to with the router, and anything else is
+/// loaded normally.
+fn follow_redirect(location: &str) {
+    if location.starts_with('/') && !location.starts_with("//") {
+        sycamore_router::navigate_replace(location);
+    } else {
+        // If this fails, there's nothing more we can do
+        let _ = web_sys::window().unwrap().location().replace(location);
+    }
+}
 
-    Some(render_cfg)
+/// Gets the context for rendering an error page for the given error, which
+/// occurred while fetching something from the server. Errors that might go
+/// away by themselves can be retried by reloading the page.
+fn get_fetch_error_ctx(err: &FetchError) -> ErrorContext {
+    match err {
+        FetchError::NotOk { url, status, .. } => {
+            let ctx = ErrorContext::from_status(url, *status);
+            match ctx.is_retryable() {
+                true => ctx.with_retry(reload_page),
+                false => ctx,
+            }
+        }
+        FetchError::AppError {
+            url,
+            status,
+            app_error,
+            ..
+        } => {
+            let ctx =
+                ErrorContext::from_status(url, *status).with_app_error(Some(app_error.clone()));
+            match ctx.is_retryable() {
+                true => ctx.with_retry(reload_page),
+                false => ctx,
+            }
+        }
+        FetchError::NotString { url } | FetchError::SerFailed { url, .. } => {
+            ErrorContext::new(ErrorKind::Server, url)
+        }
+        FetchError::Network { url, .. } => {
+            ErrorContext::new(ErrorKind::Fetch, url).with_retry(reload_page)
+        }
+        FetchError::Timeout { url } => {
+            ErrorContext::new(ErrorKind::Timeout, url).with_retry(reload_page)
+        }
+    }
 }
 
 /// Gets the initial state injected by the server, if there was any. This is
@@ -122,6 +294,7 @@ pub(crate) fn get_initial_state() -> InitialState {
                 url: "[current]".to_string(),
                 status: 500,
                 err: format!("couldn't serialize error from server: '{}'", err),
+                app_error: None,
             },
         };
         InitialState::Error(err_page_data)
@@ -248,6 +421,113 @@ pub(crate) enum InitialState {
     NotPresent,
 }
 
+/// The events that count as the user interacting with a page that isn't
+/// hydrated.
+const INTERACTION_EVENTS: [&str; 4] = ["pointerdown", "touchstart", "keydown", "focusin"];
+
+/// Renders the given template into the given container from scratch, throwing
+/// away whatever's already in there.
+fn render_fresh(
+    cx: Scope,
+    template: &Template<TemplateNodeType>,
+    page_props: PageProps,
+    translator: Translator,
+    container: &Element,
+) {
+    container.set_inner_html("");
+    #[cfg(not(feature = "hydrate"))]
+    sycamore::render_to(
+        move |_| template.render_for_template_client(page_props, cx, translator),
+        container,
+    );
+    // Without a hydration context, hydration nodes will just be created from
+    // scratch
+    #[cfg(feature = "hydrate")]
+    sycamore::hydrate_to(
+        move |_| {
+            with_no_hydration_context(|| {
+                template.render_for_template_client(page_props, cx, translator)
+            })
+        },
+        container,
+    );
+}
+
+/// Leaves the static content of a page that isn't hydrated in the given
+/// container as it is until the browser is idle (or until the user first
+/// interacts with it, if its template waits for that), and then renders it
+/// fresh.
+fn render_deferred(
+    cx: Scope,
+    template: Rc<Template<TemplateNodeType>>,
+    page_props: PageProps,
+    translator: Translator,
+    container: Element,
+) {
+    let content = container.first_child();
+    crate::spawn_local_scoped(cx, async move {
+        // The interaction that triggers this won't reach the page's own event handlers,
+        // since they don't exist yet (see `Template::no_hydration_until_interaction`)
+        if template.waits_for_interaction() {
+            wait_for_interaction(&container).await;
+        } else {
+            wait_for_idle().await;
+        }
+        // If the user has navigated elsewhere in the meantime, the content we were
+        // waiting on will have been replaced, and we shouldn't render over the new page
+        let still_present = match (&content, container.first_child()) {
+            (Some(content), Some(first_child)) => content.is_same_node(Some(&first_child)),
+            (None, None) => true,
+            _ => false,
+        };
+        if still_present {
+            render_fresh(cx, &template, page_props, translator, &container);
+        }
+    });
+}
+
+/// Waits until the browser is idle. Browsers without `requestIdleCallback` will
+/// just wait a little while instead (like the script that loads the Wasm
+/// bundle).
+async fn wait_for_idle() {
+    let (tx, rx) = oneshot::channel();
+    let callback = Closure::once_into_js(move || {
+        let _ = tx.send(());
+    });
+    let window = web_sys::window().unwrap();
+    if window
+        .request_idle_callback(callback.unchecked_ref())
+        .is_err()
+    {
+        // If this fails too, we'll never render the page, but there's nothing more we
+        // can do
+        let _ = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), 200);
+    }
+    let _ = rx.await;
+}
+
+/// Waits until the user interacts with something in the given element.
+async fn wait_for_interaction(elem: &Element) {
+    let (tx, rx) = oneshot::channel();
+    let tx = RefCell::new(Some(tx));
+    let listener = Closure::wrap(Box::new(move || {
+        if let Some(tx) = tx.borrow_mut().take() {
+            let _ = tx.send(());
+        }
+    }) as Box<dyn FnMut()>);
+    for event in INTERACTION_EVENTS {
+        elem.add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+            .unwrap();
+    }
+    // The sender is owned by the listener, so this can't be cancelled
+    let _ = rx.await;
+    for event in INTERACTION_EVENTS {
+        elem.remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+            .unwrap();
+    }
+}
+
 /// Properties for the app shell. These should be constructed literally when
 /// working with the app shell.
 // #[derive(Debug)]
@@ -281,6 +561,8 @@ pub(crate) struct ShellProps<'a> {
     /// be used for possible reloads. Eventually, this will be made obsolete
     /// when Sycamore supports this natively.
     pub route_verdict: RouteVerdict<TemplateNodeType>,
+    /// The function to report any errors we handle to, if the app has one.
+    pub error_reporter: Option<ErrorReporter>,
 }
 
 /// Fetches the information for the given page and renders it. This should be
@@ -301,19 +583,36 @@ pub(crate) async fn app_shell(
         initial_container,
         container_rx_elem,
         route_verdict,
+        error_reporter,
     }: ShellProps<'_>,
 ) {
     checkpoint("app_shell_entry");
+    // Any errors that we handle here should be passed through to the error reporter
+    let report_err = |err: &ClientError| {
+        if let Some(reporter) = &error_reporter {
+            reporter.report(err);
+        }
+    };
     let path_with_locale = match locale.as_str() {
         "xx-XX" => path.clone(),
         locale => format!("{}/{}", locale, &path),
     };
-    // Update the router state
-    router_state.set_load_state(RouterLoadState::Loading {
-        template_name: template.get_path(),
-        path: path_with_locale.clone(),
-    });
+    // Work out where we're navigating from (if we're interrupting another
+    // navigation, we'll keep its origin)
+    let from = router_state.get_current_path();
     router_state.set_last_verdict(route_verdict.clone());
+    // Update the router state (we'll do this several times as we progress through
+    // loading the page)
+    let loading_template_name = template.get_path();
+    let set_loading_progress = |progress: f32| {
+        router_state.set_load_state(RouterLoadState::Loading {
+            template_name: loading_template_name.clone(),
+            path: path_with_locale.clone(),
+            from: from.clone(),
+            progress,
+        })
+    };
+    set_loading_progress(0.0);
     // Get the global state if possible (we'll want this in all cases except errors)
     // If this is a subsequent load, the template macro will have already set up the
     // global state, and it will ignore whatever we naively give it (so we'll give
@@ -348,6 +647,7 @@ pub(crate) async fn app_shell(
                 .set_attribute("style", "display: none;")
                 .unwrap();
             checkpoint("page_visible");
+            set_loading_progress(0.5);
 
             // Now that the user can see something, we can get the translator
             let translator = translations_manager
@@ -356,19 +656,20 @@ pub(crate) async fn app_shell(
             let translator = match translator {
                 Ok(translator) => translator,
                 Err(err) => {
+                    report_err(&err);
                     // Directly eliminate the HTML sent in from the server before we render an error
                     // page
                     container_rx_elem.set_inner_html("");
                     match &err {
-                        // These errors happen because we couldn't get a translator, so they certainly don't get one
-                        ClientError::FetchError(FetchError::NotOk { url, status, .. }) => return error_pages.render_page(cx, url, *status, &fmt_err(&err), None, &container_rx_elem),
-                        ClientError::FetchError(FetchError::SerFailed { url, .. }) => return error_pages.render_page(cx, url, 500, &fmt_err(&err), None, &container_rx_elem),
-                        ClientError::LocaleNotSupported { .. } => return error_pages.render_page(cx, &format!("/{}/...", locale), 404, &fmt_err(&err), None, &container_rx_elem),
+                        // These errors happen because we couldn't get a translator, so they can only get a fallback one
+                        ClientError::FetchError(fetch_err) => return error_pages.render_page_ctx(cx, get_fetch_error_ctx(fetch_err), &fmt_err(&err), translations_manager.get_fallback_translator(&locale), &container_rx_elem),
+                        ClientError::LocaleNotSupported { .. } => return error_pages.render_page(cx, &format!("/{}/...", locale), 404, &fmt_err(&err), translations_manager.get_fallback_translator(&locale), &container_rx_elem),
                         // No other errors should be returned
-                        _ => panic!("expected 'AssetNotOk'/'AssetSerFailed'/'LocaleNotSupported' error, found other unacceptable error")
+                        _ => panic!("expected fetch or 'LocaleNotSupported' error, found other unacceptable error")
                     }
                 }
             };
+            set_loading_progress(0.8);
 
             let path = template.get_path();
             // Hydrate that static code using the acquired state
@@ -379,27 +680,45 @@ pub(crate) async fn app_shell(
                 state,
                 global_state,
             };
-            #[cfg(not(feature = "hydrate"))]
-            {
+            if template.is_client_only() {
+                // The server only sent a placeholder, so we render the page from scratch
+                render_fresh(cx, &template, page_props, translator, &container_rx_elem);
+            } else if template.hydrates() {
                 // If we aren't hydrating, we'll have to delete everything and re-render
-                container_rx_elem.set_inner_html("");
-                sycamore::render_to(
-                    move |_| template.render_for_template_client(page_props, cx, translator),
+                #[cfg(not(feature = "hydrate"))]
+                render_fresh(cx, &template, page_props, translator, &container_rx_elem);
+                // In development, we'll check that hydration doesn't change anything
+                #[cfg(all(feature = "hydrate", debug_assertions))]
+                let hydration_snapshot = HydrationSnapshot::take(&container_rx_elem);
+                #[cfg(feature = "hydrate")]
+                sycamore::hydrate_to(
+                    // This function provides translator context as needed
+                    |_| template.render_for_template_client(page_props, cx, translator),
                     &container_rx_elem,
                 );
+                #[cfg(all(feature = "hydrate", debug_assertions))]
+                if let Some(err) =
+                    hydration_snapshot.check(&container_rx_elem, &path, &path_with_locale)
+                {
+                    report_err(&err);
+                }
+            } else {
+                render_deferred(
+                    cx,
+                    template.clone(),
+                    page_props,
+                    translator,
+                    container_rx_elem.clone(),
+                );
             }
-            #[cfg(feature = "hydrate")]
-            sycamore::hydrate_to(
-                // This function provides translator context as needed
-                |_| template.render_for_template_client(page_props, cx, translator),
-                &container_rx_elem,
-            );
             checkpoint("page_interactive");
             // Update the router state
             router_state.set_load_state(RouterLoadState::Loaded {
                 template_name: path,
                 path: path_with_locale,
             });
+            // Now that the content is actually there, scroll to any anchor in the URL
+            scroll_to_hash();
         }
         // If we have no initial state, we should proceed as usual, fetching the content and state
         // from the server
@@ -412,14 +731,19 @@ pub(crate) async fn app_shell(
                 true => "index".to_string(),
                 false => path,
             };
+            // Get the query string of the page the user actually requested, so that the
+            // server can pass it through to request-state functions
+            let query = web_sys::window().unwrap().location().search().unwrap();
+            let query = query.strip_prefix('?').unwrap_or(&query);
             // Get the static page data
             let asset_url = format!(
-                "{}/.perseus/page/{}/{}.json?template_name={}&was_incremental_match={}",
+                "{}/.perseus/page/{}/{}.json?template_name={}&was_incremental_match={}&query={}",
                 get_path_prefix_client(),
                 locale,
                 path,
                 template.get_path(),
-                was_incremental_match
+                was_incremental_match,
+                urlencoding::encode(query)
             );
             // If this doesn't exist, then it's a 404 (we went here by explicit navigation,
             // but it may be an unservable ISR page or the like)
@@ -430,6 +754,11 @@ pub(crate) async fn app_shell(
                         // All good, deserialize the page data
                         let page_data = serde_json::from_str::<PageData>(&page_data_str);
                         match page_data {
+                            // The page redirects elsewhere, so we go there instead of rendering it
+                            Ok(PageData {
+                                redirect: Some(location),
+                                ..
+                            }) => follow_redirect(&location),
                             Ok(page_data) => {
                                 // We have the page data ready, render everything
                                 // Interpolate the HTML directly into the document (we'll hydrate it
@@ -459,6 +788,7 @@ pub(crate) async fn app_shell(
                                 );
                                 head_elem.set_inner_html(&new_head);
                                 checkpoint("page_visible");
+                                set_loading_progress(0.5);
 
                                 // Now that the user can see something, we can get the translator
                                 let translator = translations_manager
@@ -466,15 +796,18 @@ pub(crate) async fn app_shell(
                                     .await;
                                 let translator = match translator {
                                     Ok(translator) => translator,
-                                    Err(err) => match &err {
-                                        // These errors happen because we couldn't get a translator, so they certainly don't get one
-                                        ClientError::FetchError(FetchError::NotOk { url, status, .. }) => return error_pages.render_page(cx, url, *status, &fmt_err(&err), None, &container_rx_elem),
-                                        ClientError::FetchError(FetchError::SerFailed { url, .. }) => return error_pages.render_page(cx, url, 500, &fmt_err(&err), None, &container_rx_elem),
-                                        ClientError::LocaleNotSupported { locale } => return error_pages.render_page(cx, &format!("/{}/...", locale), 404, &fmt_err(&err), None, &container_rx_elem),
-                                        // No other errors should be returned
-                                        _ => panic!("expected 'AssetNotOk'/'AssetSerFailed'/'LocaleNotSupported' error, found other unacceptable error")
+                                    Err(err) => {
+                                        report_err(&err);
+                                        match &err {
+                                            // These errors happen because we couldn't get a translator, so they can only get a fallback one
+                                            ClientError::FetchError(fetch_err) => return error_pages.render_page_ctx(cx, get_fetch_error_ctx(fetch_err), &fmt_err(&err), translations_manager.get_fallback_translator(&locale), &container_rx_elem),
+                                            ClientError::LocaleNotSupported { locale } => return error_pages.render_page(cx, &format!("/{}/...", locale), 404, &fmt_err(&err), translations_manager.get_fallback_translator(locale), &container_rx_elem),
+                                            // No other errors should be returned
+                                            _ => panic!("expected fetch or 'LocaleNotSupported' error, found other unacceptable error")
+                                        }
                                     }
                                 };
+                                set_loading_progress(0.8);
 
                                 // Hydrate that static code using the acquired state
                                 // BUG (Sycamore): this will double-render if the component is just
@@ -485,35 +818,68 @@ pub(crate) async fn app_shell(
                                     global_state,
                                 };
                                 let template_name = template.get_path();
-                                #[cfg(not(feature = "hydrate"))]
-                                {
+                                if template.is_client_only() {
+                                    // The server only sent a placeholder, so we render the page
+                                    // from scratch
+                                    render_fresh(
+                                        cx,
+                                        &template,
+                                        page_props,
+                                        translator,
+                                        &container_rx_elem,
+                                    );
+                                } else if template.hydrates() {
                                     // If we aren't hydrating, we'll have to delete everything and
                                     // re-render
-                                    container_rx_elem.set_inner_html("");
-                                    sycamore::render_to(
-                                        move |_| {
+                                    #[cfg(not(feature = "hydrate"))]
+                                    render_fresh(
+                                        cx,
+                                        &template,
+                                        page_props,
+                                        translator,
+                                        &container_rx_elem,
+                                    );
+                                    // In development, we'll check that hydration doesn't change
+                                    // anything
+                                    #[cfg(all(feature = "hydrate", debug_assertions))]
+                                    let hydration_snapshot =
+                                        HydrationSnapshot::take(&container_rx_elem);
+                                    #[cfg(feature = "hydrate")]
+                                    sycamore::hydrate_to(
+                                        // This function provides translator context as needed
+                                        |_| {
                                             template.render_for_template_client(
                                                 page_props, cx, translator,
                                             )
                                         },
                                         &container_rx_elem,
                                     );
+                                    #[cfg(all(feature = "hydrate", debug_assertions))]
+                                    if let Some(err) = hydration_snapshot.check(
+                                        &container_rx_elem,
+                                        &template_name,
+                                        &path_with_locale,
+                                    ) {
+                                        report_err(&err);
+                                    }
+                                } else {
+                                    render_deferred(
+                                        cx,
+                                        template.clone(),
+                                        page_props,
+                                        translator,
+                                        container_rx_elem.clone(),
+                                    );
                                 }
-                                #[cfg(feature = "hydrate")]
-                                sycamore::hydrate_to(
-                                    // This function provides translator context as needed
-                                    move |_| {
-                                        template
-                                            .render_for_template_client(page_props, cx, translator)
-                                    },
-                                    &container_rx_elem,
-                                );
                                 checkpoint("page_interactive");
                                 // Update the router state
                                 router_state.set_load_state(RouterLoadState::Loaded {
                                     template_name,
                                     path: path_with_locale,
                                 });
+                                // Now that the content is actually there, scroll to any anchor in
+                                // the URL
+                                scroll_to_hash();
                             }
                             // If the page failed to serialize, an exception has occurred
                             Err(err) => panic!("page data couldn't be serialized: '{}'", err),
@@ -525,21 +891,34 @@ pub(crate) async fn app_shell(
                         &asset_url,
                         404,
                         "page not found",
-                        None,
+                        translations_manager.get_fallback_translator(&locale),
                         &container_rx_elem,
                     ),
                 },
-                Err(err) => match &err {
-                    // No translators ready yet
-                    ClientError::FetchError(FetchError::NotOk { url, status, .. }) => error_pages
-                        .render_page(cx, url, *status, &fmt_err(&err), None, &container_rx_elem),
-                    // No other errors should be returned
-                    _ => panic!("expected 'AssetNotOk' error, found other unacceptable error"),
-                },
+                Err(err) => {
+                    report_err(&err);
+                    match &err {
+                        // No translators ready yet
+                        ClientError::FetchError(fetch_err) => error_pages.render_page_ctx(
+                            cx,
+                            get_fetch_error_ctx(fetch_err),
+                            &fmt_err(&err),
+                            translations_manager.get_fallback_translator(&locale),
+                            &container_rx_elem,
+                        ),
+                        // No other errors should be returned
+                        _ => panic!("expected fetch error, found other unacceptable error"),
+                    }
+                }
             };
         }
         // Nothing should be done if an error was sent down
-        InitialState::Error(ErrorPageData { url, status, err }) => {
+        InitialState::Error(ErrorPageData {
+            url,
+            status,
+            err,
+            app_error,
+        }) => {
             checkpoint("initial_state_error");
             // We need to move the server-rendered content from its current container to the
             // reactive container (otherwise Sycamore can't work with it properly)
@@ -555,14 +934,14 @@ pub(crate) async fn app_shell(
             initial_container
                 .set_attribute("style", "display: none;")
                 .unwrap();
-            // Hydrate the currently static error page
-            // Right now, we don't provide translators to any error pages that have come
-            // from the server We render this rather than hydrating because
-            // otherwise we'd need a `HydrateNode` at the plugins level, which is way too
-            // inefficient
+            // Hydrate the currently static error page, with the same translations the
+            // server used We render this rather than hydrating because otherwise we'd need
+            // a `HydrateNode` at the plugins level, which is way too inefficient
+            let translator = translations_manager.get_error_translator(&locale).await;
             #[cfg(not(feature = "hydrate"))]
             container_rx_elem.set_inner_html("");
-            error_pages.render_page(cx, &url, status, &err, None, &container_rx_elem);
+            let ctx = ErrorContext::from_status(&url, status).with_app_error(app_error);
+            error_pages.render_page_ctx(cx, ctx, &err, translator, &container_rx_elem);
         }
     };
 }
@@ -0,0 +1,74 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+tokio::task_local! {
+    /// The dependencies available to the state function currently being run.
+    static DEPENDENCIES: StateDependencies;
+}
+
+/// A container of server-side values (e.g. database connection pools) that
+/// are shared between all the state generation functions of an app, keyed by
+/// their types. Values are added with `PerseusApp::state_dependency()`, and
+/// they can be accessed from inside any state generation function with
+/// [`get_state_dependency`].
+#[derive(Clone, Default)]
+pub struct StateDependencies(Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>);
+impl std::fmt::Debug for StateDependencies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateDependencies")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+impl StateDependencies {
+    /// Adds the given value, replacing any existing value of the same type.
+    pub(crate) fn insert<T: Any + Send + Sync>(&mut self, val: T) {
+        Arc::make_mut(&mut self.0).insert(TypeId::of::<T>(), Arc::new(val));
+    }
+    /// Gets the value of the given type, if there is one.
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.0.get(&TypeId::of::<T>())?.clone().downcast::<T>().ok()
+    }
+    /// Runs the given future (a state generation function) with these
+    /// dependencies available to it.
+    pub(crate) async fn provide<F: std::future::Future>(&self, fut: F) -> F::Output {
+        DEPENDENCIES.scope(self.clone(), fut).await
+    }
+}
+
+/// Gets the dependency of the given type that was provided to your app with
+/// `PerseusApp::state_dependency()`. This can be called from inside any state
+/// generation function (e.g. `get_build_state` or `get_request_state`), and
+/// will return `None` if there's no dependency of the given type, or if it's
+/// called from anywhere else.
+///
+/// ```rust,ignore
+/// let pool = get_state_dependency::<PgPool>().expect("no database pool");
+/// ```
+pub fn get_state_dependency<T: Any + Send + Sync>() -> Option<Arc<T>> {
+    DEPENDENCIES.try_with(|deps| deps.get::<T>()).ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies_are_only_available_in_scope() {
+        let mut deps = StateDependencies::default();
+        deps.insert(42_u32);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let val = deps.provide(async { get_state_dependency::<u32>() }).await;
+            assert_eq!(val.as_deref(), Some(&42));
+            assert!(deps
+                .provide(async { get_state_dependency::<String>() })
+                .await
+                .is_none());
+        });
+        assert!(get_state_dependency::<u32>().is_none());
+    }
+}
@@ -1,12 +1,22 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// The version of the format of frozen apps. This must be incremented whenever
+/// [`FrozenApp`] changes, so that state frozen by an older version of Perseus
+/// can be rejected with a clear error.
+pub const FROZEN_APP_VERSION: u32 = 1;
+
 /// A representation of a frozen app.
 ///
 /// This is only `Clone` for fault tolerance. Do NOT ever clone this unless you
 /// seriously know what you're doing!
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FrozenApp {
+    /// The version of the format this app was frozen in (see
+    /// [`FROZEN_APP_VERSION`]). State frozen before this was introduced will
+    /// have version `0`.
+    #[serde(default)]
+    pub version: u32,
     /// The frozen global state. If it was never initialized, this will be
     /// `None`.
     pub global_state: String,
@@ -1,12 +1,20 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod dependencies;
 mod freeze;
 mod global_state;
 mod page_state_store;
 mod rx_state;
+#[cfg(not(target_arch = "wasm32"))]
+mod template_groups;
 
-pub use freeze::{FrozenApp, PageThawPrefs, ThawPrefs};
+#[cfg(not(target_arch = "wasm32"))]
+pub use dependencies::{get_state_dependency, StateDependencies};
+pub use freeze::{FrozenApp, PageThawPrefs, ThawPrefs, FROZEN_APP_VERSION};
 pub use global_state::{GlobalState, GlobalStateCreator};
 pub use page_state_store::PageStateStore;
-pub use rx_state::{AnyFreeze, Freeze, MakeRx, MakeUnrx};
+pub use rx_state::{AnyFreeze, Freeze, MakeRx, MakeRxRef, MakeUnrx};
+#[cfg(not(target_arch = "wasm32"))]
+pub use template_groups::TemplateGroups;
 
 #[cfg(all(feature = "idb-freezing", target_arch = "wasm32"))]
 mod freeze_idb;
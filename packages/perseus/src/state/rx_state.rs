@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::any::Any;
+use sycamore::prelude::Scope;
 
 /// A trait for `struct`s that can be made reactive. Typically, this will be
 /// derived with the `#[make_rx]` macro, though it can be implemented manually
@@ -28,6 +29,19 @@ pub trait MakeUnrx {
     fn make_unrx(self) -> Self::Unrx;
 }
 
+/// A trait for reactive `struct`s that can be turned into versions of
+/// themselves that use references scoped to a reactive scope, which is what
+/// templates actually receive. Like `MakeRx`, this will usually be derived
+/// automatically, and it's what allows nested reactive fields to refer to the
+/// final types of their own reactive versions.
+pub trait MakeRxRef<'rx> {
+    /// The type of the version of this `struct` that uses scoped references.
+    type RxRef;
+    /// Transforms an instance of the `struct` into its version using scoped
+    /// references.
+    fn to_ref_struct(self, cx: Scope<'rx>) -> Self::RxRef;
+}
+
 /// A trait for reactive `struct`s that can be made unreactive and serialized to
 /// a `String`. `struct`s that implement this should implement `MakeUnrx` for
 /// simplicity, but they technically don't have to (they always do in Perseus
@@ -0,0 +1,100 @@
+use crate::template::RenderFnResult;
+use crate::utils::AsyncFnReturn;
+use futures::Future;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// The shared state of a template group, which can be of any type.
+type GroupState = Arc<dyn Any + Send + Sync>;
+/// A function that computes the shared state of a template group.
+type GetGroupStateFn = Box<dyn Fn() -> AsyncFnReturn<RenderFnResult<GroupState>> + Send + Sync>;
+
+/// A single template group.
+struct TemplateGroup {
+    get_state: GetGroupStateFn,
+    /// The group's state, once it's been computed.
+    state: OnceCell<GroupState>,
+}
+
+/// The template groups of an app. Each of these has a single piece of state
+/// that's shared between all the templates in it (e.g. a parsed manifest of
+/// documentation pages), which is only computed once, the first time one of
+/// the group's templates needs it (during the build process, this will be
+/// before any of them are built).
+///
+/// Groups are declared with `PerseusApp::template_group()`, templates are
+/// added to them with `Template::group()`, and their state generation
+/// functions can access the shared state with
+/// [`StateGeneratorInfo::group_state`](crate::template::StateGeneratorInfo::group_state).
+#[derive(Clone, Default)]
+pub struct TemplateGroups(Arc<HashMap<String, Arc<TemplateGroup>>>);
+impl std::fmt::Debug for TemplateGroups {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+impl TemplateGroups {
+    /// Declares a group with the given name, whose state will be computed by
+    /// the given function, replacing any existing group with the same name.
+    pub(crate) fn insert<S, F, Fut>(&mut self, name: &str, get_state: F)
+    where
+        S: Any + Send + Sync,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = RenderFnResult<S>> + Send + Sync + 'static,
+    {
+        let get_state: GetGroupStateFn = Box::new(move || {
+            let fut = get_state();
+            Box::pin(async move { fut.await.map(|state| Arc::new(state) as GroupState) })
+        });
+        Arc::make_mut(&mut self.0).insert(
+            name.to_string(),
+            Arc::new(TemplateGroup {
+                get_state,
+                state: OnceCell::new(),
+            }),
+        );
+    }
+    /// Checks if a group with the given name has been declared.
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+    /// Gets the state of the given group, computing it if this is the first
+    /// time it's been needed. If several templates need it at once, they'll
+    /// all wait for the same computation.
+    pub(crate) async fn get(&self, name: &str) -> RenderFnResult<GroupState> {
+        let group = self
+            .0
+            .get(name)
+            .ok_or_else(|| format!("no template group called '{}' has been declared", name))?;
+        let state = group.state.get_or_try_init(|| (group.get_state)()).await?;
+
+        Ok(state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn group_state_is_computed_once() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let mut groups = TemplateGroups::default();
+        groups.insert("docs", || async {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(vec!["intro".to_string()])
+        });
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (a, b) = futures::join!(groups.get("docs"), groups.get("docs"));
+            assert!(Arc::ptr_eq(&a.unwrap(), &b.unwrap()));
+            assert!(groups.get("blog").await.is_err());
+        });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,4 +1,6 @@
 #[cfg(not(target_arch = "wasm32"))]
+use super::get_asset_path;
+#[cfg(not(target_arch = "wasm32"))]
 use crate::errors::*;
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::{
@@ -24,13 +26,15 @@ pub struct ImmutableStore {
 }
 impl ImmutableStore {
     /// Creates a new immutable store. You should provide a path like `dist`
-    /// here. Note that any trailing slashes will be automatically stripped.
+    /// here. Note that any trailing slashes (or backslashes) will be
+    /// automatically stripped.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn new(root_path: String) -> Self {
-        let root_path = root_path
-            .strip_prefix('/')
-            .unwrap_or(&root_path)
-            .to_string();
+        let root_path = match root_path.trim_end_matches(|c| c == '/' || c == '\\') {
+            // This would be the root of the filesystem
+            "" if !root_path.is_empty() => root_path[..1].to_string(),
+            trimmed => trimmed.to_string(),
+        };
         Self { root_path }
     }
     /// Gets the filesystem path used for this immutable store.
@@ -44,8 +48,9 @@ impl ImmutableStore {
     /// Reads the given asset from the filesystem asynchronously.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn read(&self, name: &str) -> Result<String, StoreError> {
-        let asset_path = format!("{}/{}", self.root_path, name);
-        let mut file = File::open(&asset_path)
+        let path = get_asset_path(&self.root_path, name)?;
+        let asset_path = path.to_string_lossy().to_string();
+        let mut file = File::open(&path)
             .await
             .map_err(|err| StoreError::ReadFailed {
                 name: asset_path.clone(),
@@ -78,19 +83,19 @@ impl ImmutableStore {
     /// will automatically create any missing parent directories.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn write(&self, name: &str, content: &str) -> Result<(), StoreError> {
-        let asset_path = format!("{}/{}", self.root_path, name);
-        let mut dir_tree: Vec<&str> = asset_path.split('/').collect();
-        dir_tree.pop();
-
-        create_dir_all(dir_tree.join("/"))
-            .await
-            .map_err(|err| StoreError::WriteFailed {
-                name: asset_path.clone(),
-                source: err.into(),
-            })?;
+        let path = get_asset_path(&self.root_path, name)?;
+        let asset_path = path.to_string_lossy().to_string();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|err| StoreError::WriteFailed {
+                    name: asset_path.clone(),
+                    source: err.into(),
+                })?;
+        }
 
         // This will either create the file or truncate it if it already exists
-        let mut file = File::create(&asset_path)
+        let mut file = File::create(&path)
             .await
             .map_err(|err| StoreError::WriteFailed {
                 name: asset_path.clone(),
@@ -2,6 +2,80 @@
 pub mod immutable;
 /// Utilities for working with mutable stores.
 pub mod mutable;
+/// A mutable store that uses Redis, for apps running on many server instances.
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+pub mod redis_store;
+
+/// Gets the filesystem path of the named asset in the store at the given root
+/// path. Asset names always use `/` as a separator (they're often derived from
+/// URLs), so these are converted to whatever the current platform uses.
+///
+/// Names with segments that could escape the root path (see
+/// [`is_safe_segment`]) are rejected.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn get_asset_path(
+    root_path: impl AsRef<std::path::Path>,
+    name: &str,
+) -> Result<std::path::PathBuf, crate::errors::StoreError> {
+    let mut path = root_path.as_ref().to_path_buf();
+    for segment in name.split('/').filter(|segment| !segment.is_empty()) {
+        if !is_safe_segment(segment) {
+            return Err(crate::errors::StoreError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+        path.push(segment);
+    }
+
+    Ok(path)
+}
+
+/// Checks if the given segment of a `/`-separated path is a plain file or
+/// directory name, which can be safely joined onto another path. Anything
+/// else (e.g. `..`, a Windows drive like `C:`, or something with a `\` in it)
+/// could escape the path it's joined onto on some platform, and isn't safe.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_safe_segment(segment: &str) -> bool {
+    use std::path::{Component, Path};
+
+    let mut components = Path::new(segment).components();
+    !segment.contains(['\\', ':'])
+        && matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+}
 
 pub use immutable::ImmutableStore;
-pub use mutable::{FsMutableStore, MutableStore};
+pub use mutable::{FsMutableStore, LockToken, MutableStore};
+#[cfg(all(feature = "redis-store", not(target_arch = "wasm32")))]
+pub use redis_store::RedisMutableStore;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn asset_paths_stay_in_root() {
+        assert_eq!(
+            get_asset_path("dist", "static/en-US-about.json").unwrap(),
+            PathBuf::from("dist")
+                .join("static")
+                .join("en-US-about.json")
+        );
+        assert_eq!(
+            get_asset_path("dist", "/locks//build.lock").unwrap(),
+            PathBuf::from("dist").join("locks").join("build.lock")
+        );
+        for name in [
+            "../secret",
+            "static/../../secret",
+            "./static",
+            "C:/Windows",
+            "C:",
+            "static\\..\\secret",
+            "file.txt:stream",
+        ] {
+            assert!(get_asset_path("dist", name).is_err(), "{}", name);
+        }
+    }
+}
@@ -1,10 +1,32 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::get_asset_path;
 use crate::errors::*;
 #[cfg(not(target_arch = "wasm32"))]
+use crate::utils::random_token;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::{
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, hard_link, remove_file, File},
     io::{AsyncReadExt, AsyncWriteExt},
 };
 
+/// A counter that makes the temporary files locks are written to unique, even
+/// if the same store tries to take the same lock twice at once.
+#[cfg(not(target_arch = "wasm32"))]
+static LOCK_ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+/// How long an instance can hold the guard it needs to take over or release a
+/// lock before that guard is considered abandoned (e.g. because the instance
+/// crashed). Taking over or releasing a lock should only take milliseconds.
+#[cfg(not(target_arch = "wasm32"))]
+const LOCK_GUARD_TTL: Duration = Duration::from_secs(10);
+
 /// A trait for implementations of stores that the Perseus engine can use for
 /// mutable data, which may need to be altered while the server is running. In
 /// exported apps, this is irrelevant, since there is no server process to speak
@@ -20,6 +42,18 @@ use tokio::{
 /// a database, though this should be as low-latency as possible, since reads
 /// and writes are required at extremely short-notice as new user requests
 /// arrive.
+///
+/// If several server instances share the same mutable store, they can race
+/// each other to revalidate or incrementally generate the same page. To
+/// prevent this, implementations can provide advisory locks with
+/// `.try_lock()` and `.unlock()`, which Perseus will use to make sure only one
+/// instance regenerates a page at a time. By default, these do nothing, which
+/// is fine for stores that only a single instance uses.
+///
+/// Every time a lock is taken, it should be given a new [`LockToken`], which
+/// has to be given back to release it. That way, a request whose lock expired
+/// and was taken over by another request can never release the new holder's
+/// lock, even if both requests are handled by the same instance.
 #[async_trait::async_trait]
 pub trait MutableStore: std::fmt::Debug + Clone + Send + Sync {
     /// Reads data from the named asset.
@@ -27,6 +61,42 @@ pub trait MutableStore: std::fmt::Debug + Clone + Send + Sync {
     /// Writes data to the named asset. This will create a new asset if one
     /// doesn't exist already.
     async fn write(&self, name: &str, content: &str) -> Result<(), StoreError>;
+    /// Attempts to take the advisory lock with the given name, returning a
+    /// new [`LockToken`] if it was taken. If someone else already holds the
+    /// lock, this should return `None` immediately, rather than waiting for
+    /// it. The lock should be released automatically after the given
+    /// duration, so that an instance crashing while holding a lock won't
+    /// prevent the page from ever being regenerated.
+    ///
+    /// By default, this will always take the lock.
+    async fn try_lock(&self, _name: &str, _ttl: Duration) -> Result<Option<LockToken>, StoreError> {
+        Ok(Some(LockToken::default()))
+    }
+    /// Releases the advisory lock with the given name, if it's still held
+    /// with the given token (i.e. if it hasn't expired and been taken by
+    /// someone else).
+    ///
+    /// By default, this does nothing.
+    async fn unlock(&self, _name: &str, _token: &LockToken) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// A token that identifies a single time an advisory lock in a [`MutableStore`]
+/// was taken, which is needed to release it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LockToken(String);
+impl LockToken {
+    /// Generates a new random token, which should be done every time a lock is
+    /// taken.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn random() -> Self {
+        Self(random_token())
+    }
+    /// Gets the token as a string, which can be stored with the lock.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 /// The default [`MutableStore`], which simply uses the filesystem. This is
@@ -37,6 +107,14 @@ pub trait MutableStore: std::fmt::Debug + Clone + Send + Sync {
 ///
 /// Note: the `.write()` methods on this implementation will create any missing
 /// parent directories automatically.
+///
+/// Locks are implemented as files under `locks/` in the store, which will work
+/// across multiple server instances if they share the same filesystem (e.g.
+/// over NFS), as long as it supports hard links. Expired locks can only be
+/// taken over (and locks can only be released) by the instance holding a
+/// separate guard file for that lock, so that only one instance can ever
+/// replace an expired lock. If an instance crashes while holding a guard, the
+/// guard will be broken after ten seconds.
 #[derive(Clone, Debug)]
 pub struct FsMutableStore {
     #[cfg(not(target_arch = "wasm32"))]
@@ -52,13 +130,144 @@ impl FsMutableStore {
     pub fn new(root_path: String) -> Self {
         Self { root_path }
     }
+    /// Gets the path to the file for the lock with the given name.
+    fn lock_path(&self, name: &str) -> Result<String, StoreError> {
+        Ok(
+            get_asset_path(&self.root_path, &format!("locks/{}.lock", name))?
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+    /// Reads the given lock file, returning the token it was taken with and
+    /// when it expires (in milliseconds since the Unix epoch).
+    /// If the lock doesn't exist, this will return `None`.
+    ///
+    /// Locks are only ever put in place whole, so one that can't be parsed is
+    /// corrupt, and will be treated as having expired (otherwise it could
+    /// never be taken again).
+    async fn read_lock(&self, lock_path: &str) -> Result<Option<(String, u128)>, StoreError> {
+        let mut contents = String::new();
+        match File::open(lock_path).await {
+            Ok(mut file) => {
+                file.read_to_string(&mut contents)
+                    .await
+                    .map_err(|err| StoreError::LockFailed {
+                        name: lock_path.to_string(),
+                        source: err.into(),
+                    })?;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(StoreError::LockFailed {
+                    name: lock_path.to_string(),
+                    source: err.into(),
+                })
+            }
+        };
+        let (id, expiry) = contents.split_once('\n').unwrap_or((&contents, ""));
+        let expiry = expiry.trim().parse().unwrap_or(0);
+
+        Ok(Some((id.to_string(), expiry)))
+    }
+    /// Creates a lock file with the given token at the given path, which will
+    /// expire at the given time (in milliseconds since the Unix epoch). This
+    /// will return `false` if the file already exists, without touching it.
+    async fn create_lock(
+        &self,
+        lock_path: &str,
+        token: &LockToken,
+        expiry: u128,
+    ) -> Result<bool, StoreError> {
+        let lock_err = |err: std::io::Error| StoreError::LockFailed {
+            name: lock_path.to_string(),
+            source: err.into(),
+        };
+        // The lock is written to a file of our own first, so that other instances
+        // never see it half-written
+        let tmp_path = format!(
+            "{}.{}-{}.tmp",
+            lock_path,
+            token.as_str(),
+            LOCK_ATTEMPTS.fetch_add(1, Ordering::Relaxed)
+        );
+        let write_res = async {
+            let mut file = File::create(&tmp_path).await?;
+            file.write_all(format!("{}\n{}", token.as_str(), expiry).as_bytes())
+                .await?;
+            file.sync_all().await
+        }
+        .await;
+        if let Err(err) = write_res {
+            let _ = remove_file(&tmp_path).await;
+            return Err(lock_err(err));
+        }
+
+        // Linking fails if the target already exists, which makes this atomic (unlike
+        // renaming, which would replace it)
+        let link_res = hard_link(&tmp_path, lock_path).await;
+        let _ = remove_file(&tmp_path).await;
+        match link_res {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => Err(lock_err(err)),
+        }
+    }
+    /// Attempts to take the guard for the given lock, which must be held to
+    /// replace or remove a lock file that already exists. If the guard has been
+    /// abandoned, it will be broken, but this attempt will still fail.
+    async fn acquire_guard(&self, lock_path: &str, now: u128) -> Result<bool, StoreError> {
+        let guard_path = format!("{}.guard", lock_path);
+        if self
+            .create_lock(
+                &guard_path,
+                &LockToken::random(),
+                now + LOCK_GUARD_TTL.as_millis(),
+            )
+            .await?
+        {
+            return Ok(true);
+        }
+        if let Some((_, expiry)) = self.read_lock(&guard_path).await? {
+            if expiry <= now {
+                let _ = remove_file(&guard_path).await;
+            }
+        }
+
+        Ok(false)
+    }
+    /// Releases the guard for the given lock.
+    async fn release_guard(&self, lock_path: &str) {
+        // If this fails, the guard will be broken once it's been abandoned for long enough
+        let _ = remove_file(format!("{}.guard", lock_path)).await;
+    }
+    /// Removes the given lock file, if it's still there.
+    async fn remove_lock(&self, lock_path: &str) -> Result<(), StoreError> {
+        match remove_file(lock_path).await {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(StoreError::LockFailed {
+                name: lock_path.to_string(),
+                source: err.into(),
+            }),
+        }
+    }
+}
+
+/// Gets the current time, in milliseconds since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0)
 }
 #[async_trait::async_trait]
 impl MutableStore for FsMutableStore {
     #[cfg(not(target_arch = "wasm32"))]
     async fn read(&self, name: &str) -> Result<String, StoreError> {
-        let asset_path = format!("{}/{}", self.root_path, name);
-        let mut file = File::open(&asset_path)
+        let path = get_asset_path(&self.root_path, name)?;
+        let asset_path = path.to_string_lossy().to_string();
+        let mut file = File::open(&path)
             .await
             .map_err(|err| StoreError::ReadFailed {
                 name: asset_path.clone(),
@@ -89,19 +298,19 @@ impl MutableStore for FsMutableStore {
     // This creates a directory structure as necessary
     #[cfg(not(target_arch = "wasm32"))]
     async fn write(&self, name: &str, content: &str) -> Result<(), StoreError> {
-        let asset_path = format!("{}/{}", self.root_path, name);
-        let mut dir_tree: Vec<&str> = asset_path.split('/').collect();
-        dir_tree.pop();
-
-        create_dir_all(dir_tree.join("/"))
-            .await
-            .map_err(|err| StoreError::WriteFailed {
-                name: asset_path.clone(),
-                source: err.into(),
-            })?;
+        let path = get_asset_path(&self.root_path, name)?;
+        let asset_path = path.to_string_lossy().to_string();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|err| StoreError::WriteFailed {
+                    name: asset_path.clone(),
+                    source: err.into(),
+                })?;
+        }
 
         // This will either create the file or truncate it if it already exists
-        let mut file = File::create(&asset_path)
+        let mut file = File::create(&path)
             .await
             .map_err(|err| StoreError::WriteFailed {
                 name: asset_path.clone(),
@@ -123,6 +332,70 @@ impl MutableStore for FsMutableStore {
 
         Ok(())
     }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn try_lock(&self, name: &str, ttl: Duration) -> Result<Option<LockToken>, StoreError> {
+        let lock_path = self.lock_path(name)?;
+        if let Some(parent) = Path::new(&lock_path).parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|err| StoreError::LockFailed {
+                    name: lock_path.clone(),
+                    source: err.into(),
+                })?;
+        }
+
+        let now = now_millis();
+        let expiry = now + ttl.as_millis();
+        let token = LockToken::random();
+        let taken = match self.read_lock(&lock_path).await? {
+            Some((_, current_expiry)) if current_expiry > now => false,
+            // Other instances can take a free lock at the same time as us, but only one
+            // of us can create the file
+            None => self.create_lock(&lock_path, &token, expiry).await?,
+            // Any number of instances could have seen that this lock has expired, so only
+            // the one that holds the guard can replace it, and only if it's still the lock
+            // we saw (otherwise someone else has already taken it over)
+            Some(expired) => {
+                if !self.acquire_guard(&lock_path, now).await? {
+                    return Ok(None);
+                }
+                let res = async {
+                    if self.read_lock(&lock_path).await? != Some(expired) {
+                        return Ok(false);
+                    }
+                    self.remove_lock(&lock_path).await?;
+                    self.create_lock(&lock_path, &token, expiry).await
+                }
+                .await;
+                self.release_guard(&lock_path).await;
+                res?
+            }
+        };
+
+        Ok(taken.then_some(token))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn unlock(&self, name: &str, token: &LockToken) -> Result<(), StoreError> {
+        let lock_path = self.lock_path(name)?;
+        match self.read_lock(&lock_path).await? {
+            Some((id, _)) if id == token.as_str() => (),
+            // Either the lock has already been released, or it expired and someone else
+            // took it over
+            _ => return Ok(()),
+        }
+        // If we can't get the guard, someone else is taking over our lock (which must
+        // have expired), so it isn't ours to release anymore
+        if !self.acquire_guard(&lock_path, now_millis()).await? {
+            return Ok(());
+        }
+        let res = match self.read_lock(&lock_path).await {
+            Ok(Some((id, _))) if id == token.as_str() => self.remove_lock(&lock_path).await,
+            Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        };
+        self.release_guard(&lock_path).await;
+        res
+    }
     #[cfg(target_arch = "wasm32")]
     async fn read(&self, _name: &str) -> Result<String, StoreError> {
         Ok(String::new())
@@ -132,3 +405,181 @@ impl MutableStore for FsMutableStore {
         Ok(())
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("perseus-mutable-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn locks_are_exclusive_until_expiry() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let root = temp_root("exclusive");
+            let first = FsMutableStore::new(root.clone());
+            let second = FsMutableStore::new(root.clone());
+
+            let token = first
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(second
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_none());
+            // Releasing the lock without its token must leave it in place
+            second.unlock("page", &LockToken::random()).await.unwrap();
+            assert!(second
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_none());
+
+            first.unlock("page", &token).await.unwrap();
+            assert!(second
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_some());
+
+            // An expired lock can be taken over
+            assert!(second
+                .try_lock("expired", Duration::ZERO)
+                .await
+                .unwrap()
+                .is_some());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            assert!(first
+                .try_lock("expired", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_some());
+            assert!(second
+                .try_lock("expired", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_none());
+
+            let _ = std::fs::remove_dir_all(&root);
+        });
+    }
+
+    #[test]
+    fn stale_unlocks_leave_taken_over_locks() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let root = temp_root("stale");
+            // Both requests are handled by the same store (and its clones)
+            let store = FsMutableStore::new(root.clone());
+            let first = store
+                .try_lock("page", Duration::ZERO)
+                .await
+                .unwrap()
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let second = store
+                .clone()
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_ne!(first, second);
+
+            // The first request finishing late mustn't release the second's lock
+            store.unlock("page", &first).await.unwrap();
+            assert!(store
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_none());
+            store.unlock("page", &second).await.unwrap();
+            assert!(store
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .is_some());
+
+            let _ = std::fs::remove_dir_all(&root);
+        });
+    }
+
+    #[test]
+    fn expired_locks_are_taken_over_by_one_instance() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let root = temp_root("takeover");
+            let stores = (0..8)
+                .map(|_| FsMutableStore::new(root.clone()))
+                .collect::<Vec<_>>();
+            for round in 0..20 {
+                let name = format!("page-{}", round);
+                assert!(stores[0]
+                    .try_lock(&name, Duration::ZERO)
+                    .await
+                    .unwrap()
+                    .is_some());
+                tokio::time::sleep(Duration::from_millis(2)).await;
+
+                let tokens = futures::future::join_all(
+                    stores
+                        .iter()
+                        .map(|store| store.try_lock(&name, Duration::from_secs(30))),
+                )
+                .await
+                .into_iter()
+                .filter_map(|res| res.unwrap())
+                .collect::<Vec<_>>();
+                assert_eq!(tokens.len(), 1);
+                let (id, _) = stores[0]
+                    .read_lock(&stores[0].lock_path(&name).unwrap())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(id, tokens[0].as_str());
+            }
+
+            let _ = std::fs::remove_dir_all(&root);
+        });
+    }
+
+    #[test]
+    fn corrupt_locks_are_treated_as_expired() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let root = temp_root("corrupt");
+            let store = FsMutableStore::new(root.clone());
+            let lock_path = store.lock_path("page").unwrap();
+            std::fs::create_dir_all(Path::new(&lock_path).parent().unwrap()).unwrap();
+            std::fs::write(&lock_path, "someone-else").unwrap();
+
+            let token = store
+                .try_lock("page", Duration::from_secs(30))
+                .await
+                .unwrap()
+                .unwrap();
+            let (id, _) = store.read_lock(&lock_path).await.unwrap().unwrap();
+            assert_eq!(id, token.as_str());
+
+            let _ = std::fs::remove_dir_all(&root);
+        });
+    }
+}
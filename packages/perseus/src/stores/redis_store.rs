@@ -0,0 +1,111 @@
+use super::{LockToken, MutableStore};
+use crate::errors::StoreError;
+use redis::{aio::ConnectionManager, AsyncCommands, Client, Script};
+use std::time::Duration;
+
+/// A [`MutableStore`] that keeps assets in Redis, which allows many server
+/// instances to share the pages they've revalidated or incrementally
+/// generated. Locks are implemented with Redis' atomic `SET NX`, so only one
+/// instance will ever regenerate a page at a time. Each lock is set to the
+/// token it was taken with, and it's only deleted if that's still the case.
+///
+/// All keys will be prefixed with the given prefix, so that a single Redis
+/// database can be shared by several apps.
+#[derive(Clone)]
+pub struct RedisMutableStore {
+    conn: ConnectionManager,
+    prefix: String,
+}
+impl std::fmt::Debug for RedisMutableStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisMutableStore")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+impl RedisMutableStore {
+    /// Connects to the Redis server at the given URL (e.g.
+    /// `redis://127.0.0.1/`), prefixing all keys with the given prefix (e.g.
+    /// `my-app:`). The connection will automatically be re-established if it
+    /// drops.
+    pub async fn new(url: &str, prefix: &str) -> Result<Self, redis::RedisError> {
+        let client = Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+
+        Ok(Self {
+            conn,
+            prefix: prefix.to_string(),
+        })
+    }
+    /// Gets the key for the asset with the given name.
+    fn asset_key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+    /// Gets the key for the lock with the given name.
+    fn lock_key(&self, name: &str) -> String {
+        format!("{}locks/{}", self.prefix, name)
+    }
+}
+#[async_trait::async_trait]
+impl MutableStore for RedisMutableStore {
+    async fn read(&self, name: &str) -> Result<String, StoreError> {
+        let key = self.asset_key(name);
+        let mut conn = self.conn.clone();
+        let val: Option<String> = conn.get(&key).await.map_err(|err| StoreError::ReadFailed {
+            name: key.clone(),
+            source: err.into(),
+        })?;
+
+        val.ok_or(StoreError::NotFound { name: key })
+    }
+    async fn write(&self, name: &str, content: &str) -> Result<(), StoreError> {
+        let key = self.asset_key(name);
+        let mut conn = self.conn.clone();
+        conn.set(&key, content)
+            .await
+            .map_err(|err| StoreError::WriteFailed {
+                name: key,
+                source: err.into(),
+            })
+    }
+    async fn try_lock(&self, name: &str, ttl: Duration) -> Result<Option<LockToken>, StoreError> {
+        let key = self.lock_key(name);
+        let mut conn = self.conn.clone();
+        let token = LockToken::random();
+        // This will give back nothing if the lock is already held
+        let res: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(token.as_str())
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(&mut conn)
+            .await
+            .map_err(|err| StoreError::LockFailed {
+                name: key,
+                source: err.into(),
+            })?;
+
+        Ok(res.map(|_| token))
+    }
+    async fn unlock(&self, name: &str, token: &LockToken) -> Result<(), StoreError> {
+        let key = self.lock_key(name);
+        let mut conn = self.conn.clone();
+        // We should only delete the lock if we still hold it (it might have expired and
+        // been taken by someone else), which has to be done atomically
+        let script = Script::new(
+            r#"if redis.call("GET", KEYS[1]) == ARGV[1] then return redis.call("DEL", KEYS[1]) else return 0 end"#,
+        );
+        script
+            .key(&key)
+            .arg(token.as_str())
+            .invoke_async::<_, i32>(&mut conn)
+            .await
+            .map_err(|err| StoreError::LockFailed {
+                name: key,
+                source: err.into(),
+            })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use super::PageWindow;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The paths a template should generate pages for at build-time, which may
+/// each have some extra data attached to them. That data will be passed to the
+/// build state function for the same path (see
+/// [`StateGeneratorInfo::extra`](crate::template::StateGeneratorInfo::extra)),
+/// so that, if you've already fetched a list of items to generate the paths,
+/// you don't need to fetch each item again to generate its state.
+///
+/// Build paths functions can also just return a `Vec<String>` if they don't
+/// need any extra data.
+#[derive(Debug, Clone, Default)]
+pub struct BuildPaths {
+    /// The paths to generate pages for.
+    pub(crate) paths: Vec<String>,
+    /// The serialized extra data for each path that has any.
+    pub(crate) extra: HashMap<String, String>,
+}
+impl BuildPaths {
+    /// Creates an empty list of build paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a path to generate a page for.
+    pub fn add_path(&mut self, path: &str) {
+        self.paths.push(path.to_string());
+    }
+    /// Adds a path to generate a page for, along with some extra data that
+    /// will be given to the build state function when it generates that
+    /// page. This will fail if the data can't be serialized.
+    pub fn add_path_with_extra<E: Serialize>(
+        &mut self,
+        path: &str,
+        extra: &E,
+    ) -> Result<(), serde_json::Error> {
+        let extra = serde_json::to_string(extra)?;
+        self.paths.push(path.to_string());
+        self.extra.insert(path.to_string(), extra);
+        Ok(())
+    }
+    /// Adds the paths for every page of a paginated list with the given number
+    /// of items and page size, under the given base path (which may be empty,
+    /// for a list at the root of the template). The first page will be at the
+    /// base path itself, and the rest will be at `page/2`, `page/3`, etc.
+    /// under it. Each page will have its [`PageWindow`] attached as extra
+    /// data, which the build state function can get with
+    /// `StateGeneratorInfo::page_window()`.
+    pub fn add_paginated(&mut self, base: &str, total_items: usize, per_page: usize) {
+        let total_pages = PageWindow::new(1, total_items, per_page).total_pages;
+        for page in 1..=total_pages {
+            let path = PageWindow::page_path(base, page);
+            let window = PageWindow::new(page, total_items, per_page);
+            // This is a plain struct of numbers, so it will always serialize
+            self.add_path_with_extra(&path, &window).unwrap();
+        }
+    }
+    /// Gets the paths to generate pages for.
+    pub fn get_paths(&self) -> &[String] {
+        &self.paths
+    }
+}
+impl From<Vec<String>> for BuildPaths {
+    fn from(paths: Vec<String>) -> Self {
+        Self {
+            paths,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::state::StateDependencies;
+    use crate::template::StateGeneratorInfo;
+
+    #[test]
+    fn extra_data_reaches_build_state() {
+        let mut build_paths = BuildPaths::new();
+        build_paths.add_path("about");
+        build_paths
+            .add_path_with_extra("post/foo", &("Foo".to_string(), 3))
+            .unwrap();
+        assert_eq!(build_paths.get_paths(), ["about", "post/foo"]);
+
+        let info = StateGeneratorInfo::new(
+            "post/foo".to_string(),
+            "xx-XX".to_string(),
+            StateDependencies::default(),
+        )
+        .with_extra(build_paths.extra.remove("post/foo"));
+        assert_eq!(
+            info.extra::<(String, u32)>().unwrap(),
+            Some(("Foo".to_string(), 3))
+        );
+        assert!(build_paths.extra.get("about").is_none());
+
+        let mut build_paths = BuildPaths::new();
+        build_paths.add_paginated("", 25, 10);
+        assert_eq!(build_paths.get_paths(), ["", "page/2", "page/3"]);
+        let info = StateGeneratorInfo::new(
+            "blog/page/2".to_string(),
+            "xx-XX".to_string(),
+            StateDependencies::default(),
+        )
+        .with_extra(build_paths.extra.remove("page/2"));
+        assert_eq!(info.page_window(), Some(PageWindow::new(2, 25, 10)));
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A circuit breaker for a template's request state function, which stops
+/// calling it for a while after it's failed too many times in a row, so that
+/// an outage of whatever it depends on doesn't bring down every page using the
+/// template. While the breaker is open, pages will be served with their build
+/// state if the template has any, or otherwise with the degraded state set
+/// with [`CircuitBreaker::degraded_state`] (if there's neither, the user will
+/// get the error page for a `503`). Once the cool-off has passed, the next
+/// request will try the real function again, and the breaker will be closed
+/// if it succeeds.
+///
+/// Only failures caused by the server (including timeouts) count, since client
+/// errors (e.g. a user who isn't logged in) don't say anything about whether
+/// or not the function is working.
+///
+/// Each template keeps track of its own failures, across all of its pages.
+#[derive(Clone, Debug)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub struct CircuitBreaker {
+    /// The number of consecutive failures that will open the breaker.
+    failure_threshold: usize,
+    /// How long the breaker stays open before the function is tried again.
+    cool_off: Duration,
+    /// The serialized state to render pages with while the breaker is open, if
+    /// the template has no build state to use instead.
+    degraded_state: Option<String>,
+    /// The current status of the breaker, which is shared by every clone of
+    /// it.
+    status: Arc<Mutex<BreakerStatus>>,
+}
+
+/// The current status of a circuit breaker.
+#[derive(Debug, Default)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+struct BreakerStatus {
+    /// The number of times the function has failed in a row.
+    failures: usize,
+    /// When the breaker will let requests through again, if it's open.
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Creates a new circuit breaker that will open after the function has
+    /// failed the given number of times in a row, and stay open for the given
+    /// cool-off period.
+    pub fn new(failure_threshold: usize, cool_off: Duration) -> Self {
+        Self {
+            // A threshold of zero would keep the breaker open forever
+            failure_threshold: failure_threshold.max(1),
+            cool_off,
+            degraded_state: None,
+            status: Arc::default(),
+        }
+    }
+    /// Sets the state that pages should be rendered with while the breaker is
+    /// open, if the template has no build state. This should be the same type
+    /// as the template's usual state, and it should let the template show
+    /// something useful (e.g. a notice that some content is temporarily
+    /// unavailable).
+    ///
+    /// # Panics
+    /// This will panic if the state can't be serialized.
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    pub fn degraded_state<S: Serialize>(mut self, state: S) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.degraded_state =
+                Some(serde_json::to_string(&state).expect("couldn't serialize degraded state"));
+        }
+        self
+    }
+    /// Gets the serialized degraded state, if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn get_degraded_state(&self) -> Option<&String> {
+        self.degraded_state.as_ref()
+    }
+    /// Checks if the breaker is open, in which case the function shouldn't be
+    /// called.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn is_open(&self) -> bool {
+        let status = self.status.lock().unwrap();
+        matches!(status.open_until, Some(open_until) if Instant::now() < open_until)
+    }
+    /// Records that the function succeeded, closing the breaker.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn record_success(&self) {
+        let mut status = self.status.lock().unwrap();
+        status.failures = 0;
+        status.open_until = None;
+    }
+    /// Records that the function failed, opening the breaker if it's now
+    /// failed too many times in a row. This returns whether or not the breaker
+    /// is now open.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn record_failure(&self) -> bool {
+        let mut status = self.status.lock().unwrap();
+        status.failures += 1;
+        if status.failures >= self.failure_threshold {
+            status.open_until = Some(Instant::now() + self.cool_off);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        assert!(!breaker.record_failure());
+        assert!(!breaker.is_open());
+        assert!(breaker.record_failure());
+        assert!(breaker.is_open());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(!breaker.record_failure());
+    }
+
+    #[test]
+    fn retries_after_cool_off() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure());
+        // The cool-off has already passed, so the function can be tried again
+        assert!(!breaker.is_open());
+        // But it'll be opened again straight away if it fails
+        assert!(breaker.record_failure());
+    }
+}
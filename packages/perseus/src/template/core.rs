@@ -2,27 +2,45 @@
 
 #[cfg(not(target_arch = "wasm32"))]
 use super::default_headers;
+#[cfg(not(target_arch = "wasm32"))]
+use super::head::merge_heads;
+use super::CircuitBreaker;
 use super::PageProps;
 #[cfg(not(target_arch = "wasm32"))]
 use super::RenderCtx;
+use super::RouteMeta;
+#[cfg(not(target_arch = "wasm32"))]
+use super::RouteRegistry;
+#[cfg(not(target_arch = "wasm32"))]
+use super::{BuildPaths, PageInfo, StateGeneratorInfo};
 use crate::errors::*;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::make_async_trait;
+#[cfg(target_arch = "wasm32")]
+use crate::router::NavigationGuardFn;
+use crate::router::{NavigationGuardVerdict, PendingNavigation};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::state::{StateDependencies, TemplateGroups};
 use crate::translator::Translator;
 use crate::utils::provide_context_signal_replace;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::AsyncFnReturn;
 #[cfg(not(target_arch = "wasm32"))]
-use crate::utils::ComputedDuration;
+use crate::utils::{ComputedDuration, InvalidDuration};
 use crate::Html;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::Request;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::SsrNode;
 #[cfg(not(target_arch = "wasm32"))]
+use chrono::{DateTime, Utc};
+#[cfg(not(target_arch = "wasm32"))]
 use futures::Future;
 #[cfg(not(target_arch = "wasm32"))]
 use http::header::HeaderMap;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
 use std::time::Duration;
 use sycamore::prelude::{Scope, View};
 #[cfg(not(target_arch = "wasm32"))]
@@ -50,40 +68,47 @@ pub type RenderFnResultWithCause<T> = std::result::Result<T, GenericErrorWithCau
 // A series of asynchronous closure traits that prevent the user from having to
 // pin their functions
 #[cfg(not(target_arch = "wasm32"))]
-make_async_trait!(GetBuildPathsFnType, RenderFnResult<Vec<String>>);
+make_async_trait!(
+    GetBuildPathsFnType,
+    RenderFnResult<BuildPaths>,
+    locale: String
+);
 // The build state strategy needs an error cause if it's invoked from
 // incremental
 #[cfg(not(target_arch = "wasm32"))]
 make_async_trait!(
     GetBuildStateFnType,
     RenderFnResultWithCause<String>,
-    path: String,
-    locale: String
+    info: StateGeneratorInfo
 );
 #[cfg(not(target_arch = "wasm32"))]
 make_async_trait!(
     GetRequestStateFnType,
     RenderFnResultWithCause<String>,
-    path: String,
-    locale: String,
+    info: StateGeneratorInfo,
     req: Request
 );
 #[cfg(not(target_arch = "wasm32"))]
 make_async_trait!(
     ShouldRevalidateFnType,
     RenderFnResultWithCause<bool>,
-    path: String,
-    locale: String,
+    info: StateGeneratorInfo,
     req: Request
 );
 #[cfg(not(target_arch = "wasm32"))]
+make_async_trait!(
+    GetMockStateFnType,
+    RenderFnResultWithCause<String>,
+    info: StateGeneratorInfo
+);
+#[cfg(not(target_arch = "wasm32"))]
 make_async_trait!(
     AmalgamateStatesFnType,
     RenderFnResultWithCause<String>,
-    path: String,
-    locale: String,
+    info: StateGeneratorInfo,
     build_state: String,
-    request_state: String
+    request_state: String,
+    req: Request
 );
 
 // A series of closure types that should not be typed out more than once
@@ -98,9 +123,25 @@ pub type TemplateFn<G> = Box<dyn Fn(Scope, PageProps) -> View<G> + Send + Sync>;
 /// string, rather than a reactive template).
 #[cfg(not(target_arch = "wasm32"))]
 pub type HeadFn = TemplateFn<SsrNode>;
+/// The type of functions that modify the document head and are also given
+/// information about the page being rendered (see [`HeadFn`]).
+#[cfg(not(target_arch = "wasm32"))]
+pub type HeadWithInfoFn = Box<dyn Fn(Scope, PageProps, PageInfo) -> View<SsrNode> + Send + Sync>;
+/// The type of functions that render the placeholder that's prerendered for
+/// client-only templates.
+#[cfg(not(target_arch = "wasm32"))]
+pub type PlaceholderFn<G> = Box<dyn Fn(Scope) -> View<G> + Send + Sync>;
+/// The type of the function that renders an app's base head, which is shared
+/// between all its templates.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BaseHeadFn = Arc<dyn Fn(Scope) -> View<SsrNode> + Send + Sync>;
 #[cfg(not(target_arch = "wasm32"))]
 /// The type of functions that modify HTTP response headers.
 pub type SetHeadersFn = Box<dyn Fn(Option<String>) -> HeaderMap + Send + Sync>;
+#[cfg(not(target_arch = "wasm32"))]
+/// The type of functions that modify HTTP response headers and are also given
+/// information about the page (see [`SetHeadersFn`]).
+pub type SetHeadersWithInfoFn = Box<dyn Fn(Option<String>, PageInfo) -> HeaderMap + Send + Sync>;
 /// The type of functions that get build paths.
 #[cfg(not(target_arch = "wasm32"))]
 pub type GetBuildPathsFn = Box<dyn GetBuildPathsFnType + Send + Sync>;
@@ -116,6 +157,10 @@ pub type ShouldRevalidateFn = Box<dyn ShouldRevalidateFnType + Send + Sync>;
 /// The type of functions that amalgamate build and request states.
 #[cfg(not(target_arch = "wasm32"))]
 pub type AmalgamateStatesFn = Box<dyn AmalgamateStatesFnType + Send + Sync>;
+/// The type of functions that generate fixture state in place of the real
+/// state functions.
+#[cfg(not(target_arch = "wasm32"))]
+pub type GetMockStateFn = Box<dyn GetMockStateFnType + Send + Sync>;
 
 /// A single template in an app. Each template is comprised of a Sycamore view,
 /// a state type, and some functions involved with generating that state. Pages
@@ -151,7 +196,7 @@ pub struct Template<G: Html> {
     /// whcih will then be interpolated directly into the `<head>`,
     /// so reactivity here will not work!
     #[cfg(not(target_arch = "wasm32"))]
-    head: TemplateFn<SsrNode>,
+    head: HeadWithInfoFn,
     /// A function to be run when the server returns an HTTP response. This
     /// should return headers for said response, given the template's state.
     /// The most common use-case of this is to add cache control that respects
@@ -159,7 +204,7 @@ pub struct Template<G: Html> {
     /// does have the power to override existing headers. By default, this will
     /// create sensible cache control headers.
     #[cfg(not(target_arch = "wasm32"))]
-    set_headers: SetHeadersFn,
+    set_headers: SetHeadersWithInfoFn,
     /// A function that gets the paths to render for at built-time. If
     /// `incremental_generation` is `true`, more paths can be rendered at
     /// request time on top of these.
@@ -201,12 +246,92 @@ pub struct Template<G: Html> {
     /// that with `should_revalidate`).
     #[cfg(not(target_arch = "wasm32"))]
     revalidate_after: Option<ComputedDuration>,
+    /// Revalidation intervals for specific locales, which override
+    /// `revalidate_after` for them.
+    #[cfg(not(target_arch = "wasm32"))]
+    locale_revalidate_after: HashMap<String, ComputedDuration>,
+    /// Any revalidation intervals that couldn't be parsed, which will make the
+    /// build fail.
+    #[cfg(not(target_arch = "wasm32"))]
+    invalid_revalidate_after: Vec<InvalidDuration>,
+    /// Whether or not pages that need to be revalidated should be served stale
+    /// while they're revalidated in the background, rather than making the
+    /// request wait for the revalidation.
+    #[cfg(not(target_arch = "wasm32"))]
+    revalidate_in_background: bool,
+    /// Whether or not pages using this template can schedule their own
+    /// revalidation from their build state function.
+    #[cfg(not(target_arch = "wasm32"))]
+    scheduled_revalidation: bool,
+    /// How long this template's request-time state functions are given before
+    /// they're cancelled, overriding the app's default.
+    #[cfg(not(target_arch = "wasm32"))]
+    state_fn_timeout: Option<Duration>,
+    /// The circuit breaker for this template's request state function, if it
+    /// has one.
+    #[cfg(not(target_arch = "wasm32"))]
+    circuit_breaker: Option<CircuitBreaker>,
     /// Custom logic to amalgamate potentially different states generated at
     /// build and request time. This is only necessary if your template uses
     /// both `build_state` and `request_state`. If not specified and both are
     /// generated, request state will be prioritized.
     #[cfg(not(target_arch = "wasm32"))]
     amalgamate_states: Option<AmalgamateStatesFn>,
+    /// A function that generates fixture state to be used in place of the
+    /// build and request state functions when the `PERSEUS_MOCK_STATE`
+    /// environment variable is set to `1` in development.
+    #[cfg(not(target_arch = "wasm32"))]
+    mock_state: Option<GetMockStateFn>,
+    /// A navigation guard that will be run before the router navigates to any
+    /// page using this template on the client-side. This can cancel the
+    /// navigation or redirect the user elsewhere (e.g. for an authentication
+    /// wall).
+    #[cfg(target_arch = "wasm32")]
+    navigation_guard: Option<NavigationGuardFn>,
+    /// Metadata about this template, which will be made available to all
+    /// templates through the route registry.
+    meta: RouteMeta,
+    /// The localized versions of this template's root path, by locale (e.g.
+    /// `a-propos` for `about` in `fr-FR`).
+    localized_paths: HashMap<String, String>,
+    /// Whether or not pages using this template should be hydrated in the
+    /// browser. If this is `false`, they'll be prerendered without hydration
+    /// keys, and they'll be left as static HTML until the browser is idle (or
+    /// until the user interacts with them, if `wait_for_interaction` is set).
+    hydrate: bool,
+    /// Whether or not pages that aren't hydrated should be left static until
+    /// the user interacts with them, rather than just until the browser is
+    /// idle.
+    wait_for_interaction: bool,
+    /// Whether or not pages using this template should only be rendered in the
+    /// browser. If this is `true`, only `placeholder` will be prerendered, and
+    /// the page will be rendered from scratch on the client-side.
+    client_only: bool,
+    /// A function that renders the skeleton of a client-only page, which will
+    /// be prerendered in its place.
+    #[cfg(not(target_arch = "wasm32"))]
+    placeholder: PlaceholderFn<G>,
+    /// The registry of metadata for all the templates in the app. On the
+    /// browser-side, this is set up by the router, but the engine needs to
+    /// be able to provide it to templates whenever they're rendered, so it's
+    /// stored here (and set when the templates map is created).
+    #[cfg(not(target_arch = "wasm32"))]
+    route_registry: RouteRegistry,
+    /// The app's state dependencies, which are provided to all the state
+    /// generation functions (and set when the templates map is created).
+    #[cfg(not(target_arch = "wasm32"))]
+    dependencies: StateDependencies,
+    /// The template group this template is in, if any, whose shared state
+    /// will be available to its state generation functions.
+    #[cfg(not(target_arch = "wasm32"))]
+    group: Option<String>,
+    /// The app's template groups (set when the templates map is created).
+    #[cfg(not(target_arch = "wasm32"))]
+    template_groups: TemplateGroups,
+    /// The app's base head, which will be merged with this template's head
+    /// (set when the templates map is created).
+    #[cfg(not(target_arch = "wasm32"))]
+    base_head: Option<BaseHeadFn>,
 }
 impl<G: Html> std::fmt::Debug for Template<G> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -228,10 +353,10 @@ impl<G: Html> Template<G> {
             template: Box::new(|cx, _| sycamore::view! { cx, }),
             // Unlike `template`, this may not be set at all (especially in very simple apps)
             #[cfg(not(target_arch = "wasm32"))]
-            head: Box::new(|cx, _| sycamore::view! { cx, }),
+            head: Box::new(|cx, _, _| sycamore::view! { cx, }),
             // We create sensible header defaults here
             #[cfg(not(target_arch = "wasm32"))]
-            set_headers: Box::new(|_| default_headers()),
+            set_headers: Box::new(|_, _| default_headers()),
             #[cfg(not(target_arch = "wasm32"))]
             get_build_paths: None,
             #[cfg(not(target_arch = "wasm32"))]
@@ -245,7 +370,40 @@ impl<G: Html> Template<G> {
             #[cfg(not(target_arch = "wasm32"))]
             revalidate_after: None,
             #[cfg(not(target_arch = "wasm32"))]
+            locale_revalidate_after: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            invalid_revalidate_after: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            revalidate_in_background: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            scheduled_revalidation: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            state_fn_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            circuit_breaker: None,
+            #[cfg(not(target_arch = "wasm32"))]
             amalgamate_states: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            mock_state: None,
+            #[cfg(target_arch = "wasm32")]
+            navigation_guard: None,
+            meta: RouteMeta::default(),
+            localized_paths: HashMap::new(),
+            hydrate: true,
+            wait_for_interaction: false,
+            client_only: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            placeholder: Box::new(|cx| sycamore::view! { cx, }),
+            #[cfg(not(target_arch = "wasm32"))]
+            route_registry: RouteRegistry::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            dependencies: StateDependencies::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            group: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            template_groups: TemplateGroups::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            base_head: None,
         }
     }
 
@@ -282,34 +440,76 @@ impl<G: Html> Template<G> {
         // The context we have here has no context elements set on it, so we set all the
         // defaults (job of the router component on the client-side)
         // We don't need the value, we just want the context instantiations
-        let _ = RenderCtx::default().set_ctx(cx);
+        let _ = RenderCtx {
+            route_registry: self.route_registry.clone(),
+            ..Default::default()
+        }
+        .set_ctx(cx);
         // And now provide a translator separately
         provide_context_signal_replace(cx, translator.clone());
 
-        (self.template)(cx, props)
+        if self.client_only {
+            // Only the placeholder is prerendered, and it'll be thrown away in the browser
+            with_no_hydration_context(|| (self.placeholder)(cx))
+        } else if self.hydrate {
+            (self.template)(cx, props)
+        } else {
+            // This page won't be hydrated, so there's no point in sending hydration keys
+            with_no_hydration_context(|| (self.template)(cx, props))
+        }
     }
     /// Executes the user-given function that renders the document `<head>`,
     /// returning a string to be interpolated manually. Reactivity in this
     /// function will not take effect due to this string rendering. Note that
     /// this function will provide a translator context.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn render_head_str(&self, props: PageProps, translator: &Translator) -> String {
+    pub fn render_head_str(
+        &self,
+        props: PageProps,
+        info: PageInfo,
+        translator: &Translator,
+    ) -> String {
+        let head = self.render_head_view_str(translator, |cx| (self.head)(cx, props, info));
+        match &self.base_head {
+            Some(base_head) => merge_heads(
+                &self.render_head_view_str(translator, |cx| base_head(cx)),
+                &head,
+            ),
+            None => head,
+        }
+    }
+    /// Renders the given part of the document `<head>` to a string, with the
+    /// same context as the page.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_head_view_str(
+        &self,
+        translator: &Translator,
+        f: impl FnOnce(Scope) -> View<SsrNode>,
+    ) -> String {
         sycamore::render_to_string(|cx| {
             // The context we have here has no context elements set on it, so we set all the
             // defaults (job of the router component on the client-side)
             // We don't need the value, we just want the context instantiations
-            let _ = RenderCtx::default().set_ctx(cx);
+            let _ = RenderCtx {
+                route_registry: self.route_registry.clone(),
+                ..Default::default()
+            }
+            .set_ctx(cx);
             // And now provide a translator separately
             provide_context_signal_replace(cx, translator.clone());
             // We don't want to generate hydration keys for the head because it is static.
-            with_no_hydration_context(|| (self.head)(cx, props))
+            with_no_hydration_context(|| f(cx))
         })
     }
-    /// Gets the list of templates that should be prerendered for at build-time.
+    /// Gets the list of templates that should be prerendered for at build-time
+    /// in the given locale, along with any extra data for each of them.
     #[cfg(not(target_arch = "wasm32"))]
-    pub async fn get_build_paths(&self) -> Result<Vec<String>, ServerError> {
+    pub async fn get_build_paths(&self, locale: String) -> Result<BuildPaths, ServerError> {
         if let Some(get_build_paths) = &self.get_build_paths {
-            let res = get_build_paths.call().await;
+            let res = self
+                .dependencies
+                .provide(get_build_paths.call(locale))
+                .await;
             match res {
                 Ok(res) => Ok(res),
                 Err(err) => Err(ServerError::RenderFnFailed {
@@ -331,17 +531,40 @@ impl<G: Html> Template<G> {
     /// path of the template, which may be one of those generated by
     /// `.get_build_paths()`. This also needs the locale being rendered to so
     /// that more compelx applications like custom documentation systems can
-    /// be enabled.
+    /// be enabled. If the build paths function attached any extra data to this
+    /// path, that should be provided too.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn get_build_state(
         &self,
         path: String,
         locale: String,
+        extra: Option<String>,
     ) -> Result<String, ServerError> {
+        self.get_build_state_with_schedule(path, locale, extra)
+            .await
+            .map(|(state, _)| state)
+    }
+    /// Gets the initial state for a template, along with the time at which the
+    /// page should next be revalidated, if the build state function scheduled
+    /// one (see [`StateGeneratorInfo::schedule_revalidation`]).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn get_build_state_with_schedule(
+        &self,
+        path: String,
+        locale: String,
+        extra: Option<String>,
+    ) -> Result<(String, Option<DateTime<Utc>>), ServerError> {
+        if self.get_build_state.is_some() && self.is_mocking_state() {
+            return Ok((self.get_mock_state(path, locale).await?, None));
+        }
         if let Some(get_build_state) = &self.get_build_state {
-            let res = get_build_state.call(path, locale).await;
+            let info = self
+                .get_state_generator_info(path, locale)
+                .with_extra(extra);
+            let schedule = info.get_revalidation_schedule();
+            let res = self.dependencies.provide(get_build_state.call(info)).await;
             match res {
-                Ok(res) => Ok(res),
+                Ok(res) => Ok((res, schedule.get())),
                 Err(GenericErrorWithCause { error, cause }) => Err(ServerError::RenderFnFailed {
                     fn_name: "get_build_state".to_string(),
                     template_name: self.get_path(),
@@ -370,8 +593,14 @@ impl<G: Html> Template<G> {
         locale: String,
         req: Request,
     ) -> Result<String, ServerError> {
+        if self.get_request_state.is_some() && self.is_mocking_state() {
+            return self.get_mock_state(path, locale).await;
+        }
         if let Some(get_request_state) = &self.get_request_state {
-            let res = get_request_state.call(path, locale, req).await;
+            let res = self
+                .dependencies
+                .provide(get_request_state.call(self.get_state_generator_info(path, locale), req))
+                .await;
             match res {
                 Ok(res) => Ok(res),
                 Err(GenericErrorWithCause { error, cause }) => Err(ServerError::RenderFnFailed {
@@ -395,7 +624,8 @@ impl<G: Html> Template<G> {
     ///
     /// This takes a separate build state and request state to ensure there are
     /// no `None`s for either of the states. This will only be called if both
-    /// states are generated.
+    /// states are generated. The user's request is also passed through, so
+    /// that amalgamation can depend on who's asking.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn amalgamate_states(
         &self,
@@ -403,10 +633,21 @@ impl<G: Html> Template<G> {
         locale: String,
         build_state: String,
         request_state: String,
+        req: Request,
     ) -> Result<String, ServerError> {
+        // Both states will have come from the same fixtures
+        if self.amalgamate_states.is_some() && self.is_mocking_state() {
+            return Ok(request_state);
+        }
         if let Some(amalgamate_states) = &self.amalgamate_states {
-            let res = amalgamate_states
-                .call(path, locale, build_state, request_state)
+            let res = self
+                .dependencies
+                .provide(amalgamate_states.call(
+                    self.get_state_generator_info(path, locale),
+                    build_state,
+                    request_state,
+                    req,
+                ))
                 .await;
             match res {
                 Ok(res) => Ok(res),
@@ -437,8 +678,15 @@ impl<G: Html> Template<G> {
         locale: String,
         req: Request,
     ) -> Result<bool, ServerError> {
+        // The fixtures won't change, and this might depend on unavailable services
+        if self.should_revalidate.is_some() && self.is_mocking_state() {
+            return Ok(false);
+        }
         if let Some(should_revalidate) = &self.should_revalidate {
-            let res = should_revalidate.call(path, locale, req).await;
+            let res = self
+                .dependencies
+                .provide(should_revalidate.call(self.get_state_generator_info(path, locale), req))
+                .await;
             match res {
                 Ok(res) => Ok(res),
                 Err(GenericErrorWithCause { error, cause }) => Err(ServerError::RenderFnFailed {
@@ -456,12 +704,130 @@ impl<G: Html> Template<G> {
             .into())
         }
     }
+    /// Creates the information passed to this template's state generation
+    /// functions for the given page.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_state_generator_info(&self, path: String, locale: String) -> StateGeneratorInfo {
+        StateGeneratorInfo::new(path, locale, self.dependencies.clone())
+            .with_group(self.group.clone(), self.template_groups.clone())
+    }
+    /// Computes the shared state of this template's group, if it's in one and
+    /// that hasn't been done yet. This is called before anything is built, so
+    /// that the state is ready before any of the group's pages need it, and
+    /// so that failures are reported against the template.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn prepare_group_state(&self) -> Result<(), ServerError> {
+        let group = match &self.group {
+            Some(group) => group,
+            None => return Ok(()),
+        };
+        match self
+            .dependencies
+            .provide(self.template_groups.get(group))
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(ServerError::RenderFnFailed {
+                fn_name: "template_group".to_string(),
+                template_name: self.get_path(),
+                cause: ErrorCause::Server(None),
+                source: err,
+            }),
+        }
+    }
+    /// Gets the template group this template is in, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn get_group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+    /// Checks if this template's state functions should be substituted with its
+    /// mock state function. This is only possible in development, when the
+    /// `PERSEUS_MOCK_STATE` environment variable is set to `1`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_mocking_state(&self) -> bool {
+        self.mock_state.is_some()
+            && cfg!(debug_assertions)
+            && std::env::var("PERSEUS_MOCK_STATE")
+                .map(|val| val == "1")
+                .unwrap_or(false)
+    }
+    /// Gets fixture state for the given page from the template's mock state
+    /// function.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get_mock_state(&self, path: String, locale: String) -> Result<String, ServerError> {
+        let mock_state = match &self.mock_state {
+            Some(mock_state) => mock_state,
+            None => {
+                return Err(BuildError::TemplateFeatureNotEnabled {
+                    template_name: self.path.clone(),
+                    feature_name: "mock_state".to_string(),
+                }
+                .into())
+            }
+        };
+        match self
+            .dependencies
+            .provide(mock_state.call(self.get_state_generator_info(path, locale)))
+            .await
+        {
+            Ok(res) => Ok(res),
+            Err(GenericErrorWithCause { error, cause }) => Err(ServerError::RenderFnFailed {
+                fn_name: "mock_state".to_string(),
+                template_name: self.get_path(),
+                cause,
+                source: error,
+            }),
+        }
+    }
     /// Gets the template's headers for the given state. These will be inserted
     /// into any successful HTTP responses for this template, and they have
     /// the power to override.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn get_headers(&self, state: Option<String>) -> HeaderMap {
-        (self.set_headers)(state)
+    pub fn get_headers(&self, state: Option<String>, info: PageInfo) -> HeaderMap {
+        (self.set_headers)(state, info)
+    }
+    /// Gets the metadata declared for this template.
+    pub fn get_meta(&self) -> RouteMeta {
+        self.meta.clone()
+    }
+    /// Gets the localized versions of this template's root path, by locale.
+    pub fn get_localized_paths(&self) -> &HashMap<String, String> {
+        &self.localized_paths
+    }
+    /// Sets the registry of metadata for all the templates in the app. This is
+    /// done automatically when the templates map is created.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_route_registry(&mut self, route_registry: RouteRegistry) {
+        self.route_registry = route_registry;
+    }
+    /// Sets the state dependencies that will be provided to this template's
+    /// state generation functions. This is done automatically when the
+    /// templates map is created.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_dependencies(&mut self, dependencies: StateDependencies) {
+        self.dependencies = dependencies;
+    }
+    /// Sets the app's template groups, which this template's group's shared
+    /// state will be taken from. This is done automatically when the templates
+    /// map is created.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_template_groups(&mut self, template_groups: TemplateGroups) {
+        self.template_groups = template_groups;
+    }
+    /// Sets the app's base head, which will be merged with this template's
+    /// head. This is done automatically when the templates map is created.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_base_head(&mut self, base_head: Option<BaseHeadFn>) {
+        self.base_head = base_head;
+    }
+    /// Runs this template's navigation guard on the given pending navigation,
+    /// if it has one. If not, this will allow the navigation to proceed.
+    #[cfg(target_arch = "wasm32")]
+    pub fn run_navigation_guard(&self, pending: &PendingNavigation) -> NavigationGuardVerdict {
+        match &self.navigation_guard {
+            Some(guard) => guard(pending),
+            None => NavigationGuardVerdict::Proceed,
+        }
     }
 
     // Value getters
@@ -471,23 +837,57 @@ impl<G: Html> Template<G> {
     pub fn get_path(&self) -> String {
         self.path.clone()
     }
-    /// Gets the interval after which the template will next revalidate.
+    /// Checks if pages using this template will be hydrated in the browser
+    /// (this is the default, unless [`Template::no_hydration`] has been used).
+    pub fn hydrates(&self) -> bool {
+        self.hydrate
+    }
+    /// Checks if pages using this template will be left static until the user
+    /// interacts with them (see [`Template::no_hydration_until_interaction`]).
+    pub fn waits_for_interaction(&self) -> bool {
+        self.wait_for_interaction
+    }
+    /// Checks if pages using this template will only be rendered in the
+    /// browser (see [`Template::client_only`]).
+    pub fn is_client_only(&self) -> bool {
+        self.client_only
+    }
+    /// Gets the interval after which pages in the given locale using this
+    /// template will next revalidate, if they revalidate by time.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_revalidate_interval(&self, locale: &str) -> Option<ComputedDuration> {
+        self.locale_revalidate_after
+            .get(locale)
+            .or(self.revalidate_after.as_ref())
+            .cloned()
+    }
+
+    /// Checks that all the revalidation intervals provided to this template
+    /// were valid, returning an error for the first one that wasn't.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn get_revalidate_interval(&self) -> Option<ComputedDuration> {
-        self.revalidate_after.clone()
+    pub fn check_revalidate_intervals(&self) -> Result<(), BuildError> {
+        match self.invalid_revalidate_after.first() {
+            Some(err) => Err(BuildError::InvalidRevalidationInterval {
+                template_name: self.path.clone(),
+                source: err.clone(),
+            }),
+            None => Ok(()),
+        }
     }
 
     // Render characteristic checkers
     /// Checks if this template can revalidate existing prerendered templates.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn revalidates(&self) -> bool {
-        self.should_revalidate.is_some() || self.revalidate_after.is_some()
+        self.should_revalidate.is_some()
+            || self.revalidates_with_time()
+            || self.scheduled_revalidation
     }
     /// Checks if this template can revalidate existing prerendered templates
-    /// after a given time.
+    /// after a given time (in at least one locale).
     #[cfg(not(target_arch = "wasm32"))]
     pub fn revalidates_with_time(&self) -> bool {
-        self.revalidate_after.is_some()
+        self.revalidate_after.is_some() || !self.locale_revalidate_after.is_empty()
     }
     /// Checks if this template can revalidate existing prerendered templates
     /// based on some given logic.
@@ -495,6 +895,25 @@ impl<G: Html> Template<G> {
     pub fn revalidates_with_logic(&self) -> bool {
         self.should_revalidate.is_some()
     }
+    /// Checks if pages using this template should be revalidated in the
+    /// background while their stale versions are served.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn revalidates_in_background(&self) -> bool {
+        self.revalidate_in_background
+    }
+    /// Gets how long this template's request-time state functions are given
+    /// before they're cancelled, if that's been set for this template
+    /// specifically.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_state_fn_timeout(&self) -> Option<Duration> {
+        self.state_fn_timeout
+    }
+    /// Gets the circuit breaker for this template's request state function, if
+    /// it has one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn get_circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.circuit_breaker.as_ref()
+    }
     /// Checks if this template can render more templates beyond those paths it
     /// explicitly defines.
     #[cfg(not(target_arch = "wasm32"))]
@@ -554,11 +973,29 @@ impl<G: Html> Template<G> {
 
     /// Sets the document `<head>` rendering function to use. The [`View`]
     /// produced by this will only be rendered on the engine-side, and will
-    /// *not* be reactive (since it only contains metadata).
+    /// *not* be reactive (since it only contains metadata). If the app has a
+    /// base head (see `PerseusApp::base_head()`), this will be merged with it,
+    /// overriding any of its elements this has its own versions of.
+    ///
+    /// If the function needs to know which page it's rendering, use
+    /// [`Template::head_with_info`] instead.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn head(
-        mut self,
+        self,
         val: impl Fn(Scope, PageProps) -> View<SsrNode> + Send + Sync + 'static,
+    ) -> Template<G> {
+        self.head_with_info(move |cx, props, _| val(cx, props))
+    }
+    /// Sets the document `<head>` rendering function to use, like
+    /// [`Template::head`], except that the function is also given the path,
+    /// locale, and (for pages rendered at request-time) the query string of
+    /// the page through a [`PageInfo`]. Functions annotated with
+    /// `#[perseus::head]` that take a `perseus::template::PageInfo` should be
+    /// given to this.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn head_with_info(
+        mut self,
+        val: impl Fn(Scope, PageProps, PageInfo) -> View<SsrNode> + Send + Sync + 'static,
     ) -> Template<G> {
         // Headers are always prerendered on the server-side
         self.head = Box::new(val);
@@ -566,18 +1003,42 @@ impl<G: Html> Template<G> {
     }
     /// Sets the document `<head>` rendering function to use. The [`View`]
     /// produced by this will only be rendered on the engine-side, and will
-    /// *not* be reactive (since it only contains metadata).
+    /// *not* be reactive (since it only contains metadata). If the app has a
+    /// base head (see `PerseusApp::base_head()`), this will be merged with it,
+    /// overriding any of its elements this has its own versions of.
     #[cfg(target_arch = "wasm32")]
     pub fn head(self, _val: impl Fn() + 'static) -> Template<G> {
         self
     }
+    /// Sets the document `<head>` rendering function to use, like
+    /// [`Template::head`], except that the function is also given information
+    /// about the page.
+    #[cfg(target_arch = "wasm32")]
+    pub fn head_with_info(self, _val: impl Fn() + 'static) -> Template<G> {
+        self
+    }
 
     /// Sets the function to set headers. This will override Perseus' inbuilt
     /// header defaults.
+    ///
+    /// If the function needs to know which page it's setting headers for, use
+    /// [`Template::set_headers_fn_with_info`] instead.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn set_headers_fn(
-        mut self,
+        self,
         val: impl Fn(Option<String>) -> HeaderMap + Send + Sync + 'static,
+    ) -> Template<G> {
+        self.set_headers_fn_with_info(move |state, _| val(state))
+    }
+    /// Sets the function to set headers, like [`Template::set_headers_fn`],
+    /// except that the function is also given the path, locale, and query
+    /// string of the request through a [`PageInfo`]. Functions annotated with
+    /// `#[perseus::set_headers]` that take a `perseus::template::PageInfo`
+    /// should be given to this.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_headers_fn_with_info(
+        mut self,
+        val: impl Fn(Option<String>, PageInfo) -> HeaderMap + Send + Sync + 'static,
     ) -> Template<G> {
         self.set_headers = Box::new(val);
         self
@@ -588,8 +1049,17 @@ impl<G: Html> Template<G> {
     pub fn set_headers_fn(self, _val: impl Fn() + 'static) -> Template<G> {
         self
     }
+    /// Sets the function to set headers, like [`Template::set_headers_fn`],
+    /// except that the function is also given information about the page.
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_headers_fn_with_info(self, _val: impl Fn() + 'static) -> Template<G> {
+        self
+    }
 
-    /// Enables the *build paths* strategy with the given function.
+    /// Enables the *build paths* strategy with the given function. This will be
+    /// called once for each of the app's locales, and it will be given that
+    /// locale, so you can generate different paths for each locale (e.g. if
+    /// your blog posts have localized slugs).
     #[cfg(not(target_arch = "wasm32"))]
     pub fn build_paths_fn(
         mut self,
@@ -616,7 +1086,8 @@ impl<G: Html> Template<G> {
         self
     }
 
-    /// Enables the *build state* strategy with the given function.
+    /// Enables the *build state* strategy with the given function, which will
+    /// be given a [`StateGeneratorInfo`] describing the page being generated.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn build_state_fn(
         mut self,
@@ -631,7 +1102,11 @@ impl<G: Html> Template<G> {
         self
     }
 
-    /// Enables the *request state* strategy with the given function.
+    /// Enables the *request state* strategy with the given function. Details
+    /// about the client that made the request (like its IP address and
+    /// preferred language) can be extracted from the request given to the
+    /// function with
+    /// [`RequestContext::from_req`](crate::template::RequestContext::from_req).
     #[cfg(not(target_arch = "wasm32"))]
     pub fn request_state_fn(
         mut self,
@@ -663,8 +1138,9 @@ impl<G: Html> Template<G> {
         self
     }
 
-    /// Enables the *revalidation* strategy (time variant). This takes a time
-    /// string of a form like `1w` for one week.
+    /// Enables the *revalidation* strategy (time variant). This takes a
+    /// [`std::time::Duration`], a [`chrono::Duration`], or a time string of a
+    /// form like `1w` for one week.
     ///
     ///    - s: second,
     ///    - m: minute,
@@ -674,13 +1150,23 @@ impl<G: Html> Template<G> {
     ///    - M: month (30 days used here, 12M ≠ 1y!),
     ///    - y: year (365 days always, leap years ignored, if you want them add
     ///      them as days)
+    ///
+    /// If the interval is invalid (e.g. a malformed time string, or a duration
+    /// that isn't positive), building your app will fail.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn revalidate_after<I: Into<Duration>>(mut self, val: I) -> Template<G> {
-        self.revalidate_after = Some(ComputedDuration::new(val));
+    pub fn revalidate_after<I>(mut self, val: I) -> Template<G>
+    where
+        I: TryInto<ComputedDuration, Error = InvalidDuration>,
+    {
+        match val.try_into() {
+            Ok(interval) => self.revalidate_after = Some(interval),
+            Err(err) => self.invalid_revalidate_after.push(err),
+        }
         self
     }
-    /// Enables the *revalidation* strategy (time variant). This takes a time
-    /// string of a form like `1w` for one week.
+    /// Enables the *revalidation* strategy (time variant). This takes a
+    /// [`std::time::Duration`], a [`chrono::Duration`], or a time string of a
+    /// form like `1w` for one week.
     ///
     ///    - s: second,
     ///    - m: minute,
@@ -691,7 +1177,132 @@ impl<G: Html> Template<G> {
     ///    - y: year (365 days always, leap years ignored, if you want them add
     ///      them as days)
     #[cfg(target_arch = "wasm32")]
-    pub fn revalidate_after<I: Into<Duration>>(self, _val: I) -> Template<G> {
+    pub fn revalidate_after<I>(self, _val: I) -> Template<G> {
+        self
+    }
+    /// Enables the *revalidation* strategy (time variant) for the given locale
+    /// only, overriding any interval set with `.revalidate_after()`. This is
+    /// useful if the content in one locale changes much more often than in the
+    /// others. Pages in locales without an interval won't revalidate by time.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn revalidate_after_for_locale<I>(mut self, locale: &str, val: I) -> Template<G>
+    where
+        I: TryInto<ComputedDuration, Error = InvalidDuration>,
+    {
+        match val.try_into() {
+            Ok(interval) => {
+                self.locale_revalidate_after
+                    .insert(locale.to_string(), interval);
+            }
+            Err(err) => self.invalid_revalidate_after.push(err),
+        }
+        self
+    }
+    /// Enables the *revalidation* strategy (time variant) for the given locale
+    /// only, overriding any interval set with `.revalidate_after()`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn revalidate_after_for_locale<I>(self, _locale: &str, _val: I) -> Template<G> {
+        self
+    }
+    /// Makes pages that need to be revalidated be served stale while they're
+    /// revalidated in the background, rather than making the request that
+    /// triggered the revalidation wait for it (i.e. stale-while-revalidate).
+    /// Only one revalidation will be run for each page at a time, no matter
+    /// how many requests arrive while it's happening. This has no effect
+    /// unless one of the revalidation strategies is also enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn revalidate_in_background(mut self) -> Template<G> {
+        self.revalidate_in_background = true;
+        self
+    }
+    /// Makes pages that need to be revalidated be served stale while they're
+    /// revalidated in the background, rather than making the request that
+    /// triggered the revalidation wait for it (i.e. stale-while-revalidate).
+    /// Only one revalidation will be run for each page at a time, no matter
+    /// how many requests arrive while it's happening. This has no effect
+    /// unless one of the revalidation strategies is also enabled.
+    #[cfg(target_arch = "wasm32")]
+    pub fn revalidate_in_background(self) -> Template<G> {
+        self
+    }
+
+    /// Lets pages using this template schedule their own revalidation from
+    /// their build state function (with
+    /// [`StateGeneratorInfo::schedule_revalidation`]), which is useful for
+    /// things like lists of posts that should change when a scheduled post is
+    /// published. This can be used alongside the other revalidation
+    /// strategies, or on its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn scheduled_revalidation(mut self) -> Template<G> {
+        self.scheduled_revalidation = true;
+        self
+    }
+    /// Lets pages using this template schedule their own revalidation from
+    /// their build state function (with
+    /// `StateGeneratorInfo::schedule_revalidation`), which is useful for
+    /// things like lists of posts that should change when a scheduled post is
+    /// published. This can be used alongside the other revalidation
+    /// strategies, or on its own.
+    #[cfg(target_arch = "wasm32")]
+    pub fn scheduled_revalidation(self) -> Template<G> {
+        self
+    }
+    /// Sets how long this template's request state, revalidation logic, and
+    /// state amalgamation functions are given on each request before they're
+    /// cancelled, overriding the default set with
+    /// `PerseusApp::state_fn_timeout()`. If one of these times out, the stale
+    /// version of the page will be served if there is one, and otherwise the
+    /// user will get the error page for a `504`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn state_fn_timeout(mut self, val: Duration) -> Template<G> {
+        self.state_fn_timeout = Some(val);
+        self
+    }
+    /// Sets how long this template's request state, revalidation logic, and
+    /// state amalgamation functions are given on each request before they're
+    /// cancelled, overriding the default set with
+    /// `PerseusApp::state_fn_timeout()`. If one of these times out, the stale
+    /// version of the page will be served if there is one, and otherwise the
+    /// user will get the error page for a `504`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn state_fn_timeout(self, _val: Duration) -> Template<G> {
+        self
+    }
+    /// Protects this template's request state function with the given circuit
+    /// breaker, which will stop calling it for a while if it fails too many
+    /// times in a row, serving pages with their build state or a degraded
+    /// state instead. See [`CircuitBreaker`] for details.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn circuit_breaker(mut self, val: CircuitBreaker) -> Template<G> {
+        self.circuit_breaker = Some(val);
+        self
+    }
+    /// Protects this template's request state function with the given circuit
+    /// breaker, which will stop calling it for a while if it fails too many
+    /// times in a row, serving pages with their build state or a degraded
+    /// state instead. See [`CircuitBreaker`] for details.
+    #[cfg(target_arch = "wasm32")]
+    pub fn circuit_breaker(self, _val: CircuitBreaker) -> Template<G> {
+        self
+    }
+
+    /// Adds this template to the template group with the given name, which
+    /// must have been declared with `PerseusApp::template_group()`. The
+    /// group's shared state will be computed once per build, and this
+    /// template's state generation functions can access it with
+    /// [`StateGeneratorInfo::group_state`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn group(mut self, name: &str) -> Template<G> {
+        self.group = Some(name.to_string());
+        self
+    }
+    /// Adds this template to the template group with the given name, which
+    /// must have been declared with `PerseusApp::template_group()`. The
+    /// group's shared state will be computed once per build, and this
+    /// template's state generation functions can access it with
+    /// `StateGeneratorInfo::group_state`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn group(self, _name: &str) -> Template<G> {
         self
     }
 
@@ -700,7 +1311,9 @@ impl<G: Html> Template<G> {
     /// and request time. The function you provide here is responsible for
     /// rationalizing the two into one single state to be sent to the client,
     /// and this will be run just after the request state function
-    /// completes. See [`States`] for further details.
+    /// completes. See [`States`] for further details. This function is also
+    /// given the user's request, so it can make per-request decisions (e.g.
+    /// only using the request state for logged-in users).
     #[cfg(not(target_arch = "wasm32"))]
     pub fn amalgamate_states_fn(
         mut self,
@@ -719,6 +1332,148 @@ impl<G: Html> Template<G> {
     pub fn amalgamate_states_fn(self, _val: impl Fn() + 'static) -> Template<G> {
         self
     }
+
+    /// Sets a function that generates fixture state for this template, which
+    /// will be used in place of its build and request state functions when
+    /// the `PERSEUS_MOCK_STATE` environment variable is set to `1`. This
+    /// lets you work on your app's frontend when the services behind its
+    /// state are unavailable (e.g. offline), without littering your
+    /// templates with `cfg` attributes. This function takes the same
+    /// arguments as a build state function, so you can annotate it with
+    /// `#[perseus::build_state]`.
+    ///
+    /// Any revalidation logic and state amalgamation will be skipped while
+    /// fixtures are being used. Note that this will be ignored entirely in
+    /// production.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mock_state_fn(
+        mut self,
+        val: impl GetMockStateFnType + Send + Sync + 'static,
+    ) -> Template<G> {
+        self.mock_state = Some(Box::new(val));
+        self
+    }
+    /// Sets a function that generates fixture state for this template, which
+    /// will be used in place of its build and request state functions when
+    /// the `PERSEUS_MOCK_STATE` environment variable is set to `1`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn mock_state_fn(self, _val: impl Fn() + 'static) -> Template<G> {
+        self
+    }
+
+    /// Sets the metadata for this template (e.g. its title pattern and parent),
+    /// which will be made available to all templates through the route
+    /// registry in the render context. This is useful for generating
+    /// breadcrumbs and navigation menus.
+    pub fn meta(mut self, val: RouteMeta) -> Template<G> {
+        self.meta = val;
+        self
+    }
+
+    /// Sets the localized version of this template's root path in the given
+    /// locale (e.g. `.localized_path("fr-FR", "a-propos")` on the `about`
+    /// template will make its pages available at `/fr-FR/a-propos/...`). Pages
+    /// are still built under the template's actual root path, and the router
+    /// will translate between the two, as will the `link!` macro, so you
+    /// should always link to pages by their actual paths. Localized paths
+    /// shouldn't clash with the root paths of any other templates.
+    pub fn localized_path(mut self, locale: &str, path: impl Into<String>) -> Template<G> {
+        self.localized_paths.insert(locale.to_string(), path.into());
+        self
+    }
+
+    /// Sets a navigation guard for this template, which will be run on the
+    /// client-side before the router navigates to any page using this
+    /// template (but not on the initial load). This can cancel the navigation
+    /// or redirect the user elsewhere, which is useful for things like
+    /// authentication walls. This will be run before any global guards
+    /// registered through the render context.
+    #[cfg(target_arch = "wasm32")]
+    pub fn navigation_guard(
+        mut self,
+        val: impl Fn(&PendingNavigation) -> NavigationGuardVerdict + 'static,
+    ) -> Template<G> {
+        self.navigation_guard = Some(std::rc::Rc::new(val));
+        self
+    }
+    /// Sets a navigation guard for this template, which will be run on the
+    /// client-side before the router navigates to any page using this
+    /// template (but not on the initial load). This can cancel the navigation
+    /// or redirect the user elsewhere, which is useful for things like
+    /// authentication walls. This will be run before any global guards
+    /// registered through the render context.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn navigation_guard(
+        self,
+        _val: impl Fn(&PendingNavigation) -> NavigationGuardVerdict + 'static,
+    ) -> Template<G> {
+        self
+    }
+
+    /// Opts pages using this template out of hydration. They'll be prerendered
+    /// without hydration keys, and, when they're loaded in the browser, they'll
+    /// be left as static HTML, rather than being hydrated, until the browser is
+    /// idle (with `requestIdleCallback`), at which point they'll be rendered
+    /// fresh, making them interactive. This is useful for content-heavy pages
+    /// that don't need interactivity, since it keeps that work out of the way
+    /// of the initial load.
+    ///
+    /// Note that, since the page will be thrown away and rendered again, any
+    /// state that's only in the DOM (e.g. text typed into an input) will be
+    /// lost at that point.
+    pub fn no_hydration(mut self) -> Template<G> {
+        self.hydrate = false;
+        self
+    }
+    /// Like [`Template::no_hydration`], but pages will be left as static HTML
+    /// until the user first interacts with them (by clicking, tapping, typing,
+    /// or focusing something in them), rather than just until the browser is
+    /// idle, so pages the user only reads will never be rendered in the
+    /// browser at all.
+    ///
+    /// Because the page's event handlers don't exist until it's been rendered,
+    /// the interaction that triggers the render **won't reach them** (e.g. the
+    /// first click on a button will be lost), although the browser's default
+    /// behavior (like following a link or focusing an input) will still happen.
+    /// Only use this for pages where that doesn't matter.
+    pub fn no_hydration_until_interaction(mut self) -> Template<G> {
+        self.hydrate = false;
+        self.wait_for_interaction = true;
+        self
+    }
+    /// Makes pages using this template render only in the browser. On the
+    /// engine-side, only a placeholder (see [`Template::placeholder`]) will be
+    /// prerendered in their place, and then, in the browser, they'll be
+    /// rendered from scratch, rather than being hydrated. Their state will
+    /// still be generated as usual.
+    ///
+    /// This is useful for pages that wouldn't benefit from being prerendered,
+    /// like heavy dashboards behind authentication walls, or pages that
+    /// depend on browser-only APIs to render at all.
+    pub fn client_only(mut self) -> Template<G> {
+        self.client_only = true;
+        self
+    }
+    /// Sets the function that renders the placeholder for a client-only
+    /// template (see [`Template::client_only`]), like a loading skeleton. This
+    /// will be prerendered in place of the page, and it won't be reactive. By
+    /// default, nothing is prerendered.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn placeholder(
+        mut self,
+        val: impl Fn(Scope) -> View<G> + Send + Sync + 'static,
+    ) -> Template<G> {
+        self.placeholder = Box::new(val);
+        self
+    }
+    /// Sets the function that renders the placeholder for a client-only
+    /// template (see [`Template::client_only`]), like a loading skeleton. This
+    /// will be prerendered in place of the page, and it won't be reactive. By
+    /// default, nothing is prerendered.
+    #[cfg(target_arch = "wasm32")]
+    pub fn placeholder(self, _val: impl Fn(Scope) -> View<G> + 'static) -> Template<G> {
+        self
+    }
 }
 
 // The engine needs to know whether or not to use hydration, this is how we pass
@@ -742,3 +1497,62 @@ macro_rules! is_server {
         render_ctx.is_server
     }};
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_state_replaces_state_only_when_enabled() {
+        let template = Template::<SsrNode>::new("mocked")
+            .build_state_fn(|_: StateGeneratorInfo| async {
+                Ok::<_, GenericErrorWithCause>("build".to_string())
+            })
+            .request_state_fn(|_: StateGeneratorInfo, _: Request| async {
+                Ok::<_, GenericErrorWithCause>("request".to_string())
+            })
+            .mock_state_fn(|_: StateGeneratorInfo| async {
+                Ok::<_, GenericErrorWithCause>("mock".to_string())
+            });
+        let states = || async {
+            let build_state = template
+                .get_build_state(String::new(), "xx-XX".to_string(), None)
+                .await
+                .unwrap();
+            let request_state = template
+                .get_request_state(
+                    String::new(),
+                    "xx-XX".to_string(),
+                    Request::builder().body(()).unwrap(),
+                )
+                .await
+                .unwrap();
+            (build_state, request_state)
+        };
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        // This is the only test that uses this variable
+        std::env::remove_var("PERSEUS_MOCK_STATE");
+        assert_eq!(
+            rt.block_on(states()),
+            ("build".to_string(), "request".to_string())
+        );
+        // Only `1` enables mocking
+        std::env::set_var("PERSEUS_MOCK_STATE", "true");
+        assert_eq!(
+            rt.block_on(states()),
+            ("build".to_string(), "request".to_string())
+        );
+        std::env::set_var("PERSEUS_MOCK_STATE", "1");
+        let mocked = rt.block_on(states());
+        std::env::remove_var("PERSEUS_MOCK_STATE");
+        // Fixtures are never used in production
+        if cfg!(debug_assertions) {
+            assert_eq!(mocked, ("mock".to_string(), "mock".to_string()));
+        } else {
+            assert_eq!(mocked, ("build".to_string(), "request".to_string()));
+        }
+    }
+}
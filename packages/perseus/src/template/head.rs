@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+/// Elements that never have any content or closing tags.
+const VOID_ELEMENTS: &[&str] = &["base", "link", "meta"];
+
+/// Merges the rendered base head of an app (see `PerseusApp::base_head()`)
+/// with the rendered head of a page. Any element in the base head that the
+/// page's head has its own version of will be left out, and everything else
+/// from the base head will come first.
+///
+/// Elements are considered versions of each other if they're both `<title>`s,
+/// `<base>`s, or `<meta charset>`s, if they're `<meta>`s with the same `name`,
+/// `property`, or `http-equiv`, or if they're both `<link rel="canonical">`s.
+/// Anything else (e.g. stylesheets and scripts) is always kept.
+pub(crate) fn merge_heads(base: &str, head: &str) -> String {
+    let overridden = split_elements(head)
+        .into_iter()
+        .filter_map(get_key)
+        .collect::<HashSet<_>>();
+    let mut merged = split_elements(base)
+        .into_iter()
+        .filter(|elem| get_key(elem).map_or(true, |key| !overridden.contains(&key)))
+        .collect::<String>();
+    merged.push_str(head);
+
+    merged
+}
+
+/// Splits the given rendered head into its top-level elements (any text
+/// between them will be kept as separate parts).
+fn split_elements(html: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let len = match rest.strip_prefix('<') {
+            Some(after) => get_element_len(after).map_or(rest.len(), |len| len + 1),
+            None => rest.find('<').unwrap_or(rest.len()),
+        };
+        parts.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    parts
+}
+
+/// Gets the length of the element that the given HTML starts with (after its
+/// opening `<`), if it's well-formed.
+fn get_element_len(html: &str) -> Option<usize> {
+    let name = get_tag_name(html).to_lowercase();
+    let mut quote = None;
+    let start_tag_len = html.char_indices().find_map(|(idx, c)| {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(idx + 1),
+            _ => (),
+        }
+        None
+    })?;
+    if name.is_empty() || VOID_ELEMENTS.contains(&name.as_str()) || name.starts_with('!') {
+        return Some(start_tag_len);
+    }
+
+    let closing = format!("</{}>", name);
+    let content = &html[start_tag_len..];
+    let content_len = content.to_lowercase().find(&closing)?;
+
+    Some(start_tag_len + content_len + closing.len())
+}
+
+/// Gets the name of the tag that the given HTML starts with (after its
+/// opening `<`).
+fn get_tag_name(html: &str) -> &str {
+    let end = html
+        .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+        .unwrap_or(html.len());
+    &html[..end]
+}
+
+/// Gets the key that identifies the given element for the purposes of
+/// overriding, if it's one that can be overridden.
+fn get_key(elem: &str) -> Option<String> {
+    let tag = elem.strip_prefix('<')?;
+    let name = get_tag_name(tag).to_lowercase();
+    match name.as_str() {
+        "title" | "base" => Some(name),
+        "meta" => {
+            if get_attr(tag, "charset").is_some() {
+                return Some("charset".to_string());
+            }
+            ["name", "property", "http-equiv"]
+                .iter()
+                .find_map(|attr| Some(format!("{}:{}", attr, get_attr(tag, attr)?)))
+        }
+        "link" => get_attr(tag, "rel")
+            .filter(|rel| rel.eq_ignore_ascii_case("canonical"))
+            .map(|_| "canonical".to_string()),
+        _ => None,
+    }
+}
+
+/// Gets the value of the given attribute from the given start tag (after its
+/// opening `<`), if it has it. Attributes without values will have empty
+/// ones.
+fn get_attr<'a>(tag: &'a str, attr: &str) -> Option<&'a str> {
+    let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+    let mut rest = &tag[get_tag_name(tag).len()..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let name_len = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        rest = &rest[name_len..];
+        let val = match rest.strip_prefix('=') {
+            Some(after) => {
+                let (val, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => after[1..].split_once(quote)?,
+                    _ => after
+                        .split_once(|c: char| c.is_ascii_whitespace())
+                        .unwrap_or((after, "")),
+                };
+                rest = after;
+                val
+            }
+            None => "",
+        };
+        if name.eq_ignore_ascii_case(attr) {
+            return Some(val);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_heads_override_base_head() {
+        let base = r#"<meta charset="utf-8"><meta name="viewport" content="width=device-width"><meta property="og:title" content="My App"><title>My App</title><link rel="stylesheet" href="/app.css">"#;
+        let head = r#"<title>About</title><meta property="og:title" content="About"><link rel="stylesheet" href="/about.css">"#;
+
+        assert_eq!(
+            merge_heads(base, head),
+            r#"<meta charset="utf-8"><meta name="viewport" content="width=device-width"><link rel="stylesheet" href="/app.css"><title>About</title><meta property="og:title" content="About"><link rel="stylesheet" href="/about.css">"#
+        );
+        assert_eq!(merge_heads(base, ""), base);
+        assert_eq!(merge_heads("", head), head);
+    }
+}
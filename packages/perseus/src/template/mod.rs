@@ -1,18 +1,41 @@
+mod build_paths;
+mod circuit_breaker;
 mod core; // So called because this contains what is essentially the core exposed logic of Perseus
 #[cfg(not(target_arch = "wasm32"))]
 mod default_headers;
+#[cfg(not(target_arch = "wasm32"))]
+mod head;
+#[cfg(not(target_arch = "wasm32"))]
+mod page_info;
 mod page_props;
+mod pagination;
 mod render_ctx;
+mod route_registry;
+#[cfg(not(target_arch = "wasm32"))]
+mod state_generator_info;
 #[cfg(not(target_arch = "wasm32"))]
 mod states;
 mod templates_map;
 
-pub use self::core::*; /* There are a lot of render function traits in here, there's no
-                        * point in spelling them all out */
+pub use self::core::*;
+pub use build_paths::BuildPaths;
+pub use circuit_breaker::CircuitBreaker; /* There are a lot of render function traits in here, there's no
+                                          * point in spelling them all out */
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) use default_headers::default_headers;
+#[cfg(not(target_arch = "wasm32"))]
+pub use page_info::PageInfo;
 pub use page_props::PageProps;
+pub use pagination::{PageWindow, Pagination, PaginationProps};
 pub use render_ctx::RenderCtx;
+pub(crate) use render_ctx::BOOT_INDICATOR_CLASS;
+pub use route_registry::{Breadcrumb, RouteMeta, RouteRegistry};
+#[cfg(not(target_arch = "wasm32"))]
+pub use state_generator_info::StateGeneratorInfo;
+// These are defined with the rest of the server code, but they're mostly useful
+// in state functions
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::server::{ClientIp, DeviceKind, RequestContext};
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) use states::States;
 pub use templates_map::{ArcTemplateMap, TemplateMap};
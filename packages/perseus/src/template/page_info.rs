@@ -0,0 +1,43 @@
+use crate::Request;
+
+/// Information about the page being rendered, which is given to head
+/// functions and header-setting functions so that they can produce different
+/// output for different routes (e.g. a title like `Search results for X`)
+/// without having to put the path into the page's state.
+///
+/// Like [`StateGeneratorInfo`](super::StateGeneratorInfo), this is a single
+/// struct so that more information can be added to it in future without
+/// having to change the signatures of these functions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageInfo {
+    /// The path of the page, without the locale (e.g. `post/foo`).
+    pub path: String,
+    /// The locale the page is being rendered for. If i18n isn't being used,
+    /// this will be `xx-XX`.
+    pub locale: String,
+    /// The query string of the request for the page, without the leading `?`,
+    /// if there was one. Since heads are prerendered whenever possible, this
+    /// will only be given to head functions for pages that are rendered at
+    /// request-time (i.e. that use request state). Header-setting functions
+    /// are run on every request, so they'll always get it.
+    pub query: Option<String>,
+}
+impl PageInfo {
+    /// Creates the information for rendering the given page.
+    pub fn new(path: impl Into<String>, locale: impl Into<String>, query: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            locale: locale.into(),
+            query,
+        }
+    }
+    /// Creates the information for rendering the given page in response to
+    /// the given request, taking the query string from it.
+    pub fn for_request(path: impl Into<String>, locale: impl Into<String>, req: &Request) -> Self {
+        Self::new(
+            path,
+            locale,
+            req.uri().query().map(|query| query.to_string()),
+        )
+    }
+}
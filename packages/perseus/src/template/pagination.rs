@@ -0,0 +1,166 @@
+use crate::router::RoutePath;
+use crate::Html;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use sycamore::prelude::{component, view, Scope, View};
+use sycamore::Prop;
+
+/// Information about one page of a paginated list (e.g. the second page of a
+/// blog's posts). These are attached to the paths generated by
+/// [`BuildPaths::add_paginated`](super::BuildPaths::add_paginated), so they
+/// can be obtained in build state functions with
+/// `StateGeneratorInfo::page_window()`.
+///
+/// Pages are numbered from 1, and the first page lives at the base path of the
+/// list (e.g. `blog`), while the rest live at `page/<number>` under it (e.g.
+/// `blog/page/2`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageWindow {
+    /// The number of this page, starting from 1.
+    pub page: usize,
+    /// The maximum number of items on each page.
+    pub per_page: usize,
+    /// The total number of items in the list.
+    pub total_items: usize,
+    /// The total number of pages. There's always at least one, even if the
+    /// list is empty.
+    pub total_pages: usize,
+}
+impl PageWindow {
+    /// Creates the window for the given page of a list with the given number
+    /// of items and page size. A page size of zero will be treated as one.
+    pub fn new(page: usize, total_items: usize, per_page: usize) -> Self {
+        let per_page = per_page.max(1);
+        let total_pages = ((total_items + per_page - 1) / per_page).max(1);
+        Self {
+            page,
+            per_page,
+            total_items,
+            total_pages,
+        }
+    }
+    /// Works out the window for the page at the given path (relative to
+    /// wherever the list is, like `StateGeneratorInfo::path`), which is
+    /// useful when the page is being generated incrementally or revalidated,
+    /// since the window won't be attached to it then. This will return `None`
+    /// if the path isn't a valid page of the list (including `page/1`, since
+    /// the first page lives at the base path).
+    pub fn from_path(path: &str, total_items: usize, per_page: usize) -> Option<Self> {
+        let mut window = Self::new(1, total_items, per_page);
+        let path = path.trim_end_matches('/');
+        let page = match path.rsplit_once("page/") {
+            Some((base, page)) if base.is_empty() || base.ends_with('/') => page,
+            _ => return Some(window),
+        };
+        window.page = page.parse().ok()?;
+        (window.page > 1 && window.page <= window.total_pages).then_some(window)
+    }
+    /// Gets the range of indices of the items on this page, which can be used
+    /// to slice the full list.
+    pub fn range(&self) -> Range<usize> {
+        let start = (self.page.saturating_sub(1) * self.per_page).min(self.total_items);
+        start..(start + self.per_page).min(self.total_items)
+    }
+    /// Whether or not there's a page before this one.
+    pub fn has_prev(&self) -> bool {
+        self.page > 1
+    }
+    /// Whether or not there's a page after this one.
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages
+    }
+    /// Gets the path of the given page of a list at the given base path (e.g.
+    /// `blog/page/2` for page 2 of `blog`).
+    pub fn page_path(base: &str, page: usize) -> String {
+        let base = base.trim_end_matches('/');
+        match (page, base.is_empty()) {
+            (0 | 1, _) => base.to_string(),
+            (_, true) => format!("page/{}", page),
+            (_, false) => format!("{}/page/{}", base, page),
+        }
+    }
+}
+
+/// The properties for [`Pagination`].
+#[derive(Prop, Debug)]
+pub struct PaginationProps {
+    /// The window of the page currently being rendered.
+    pub window: PageWindow,
+    /// The path of the first page of the list within the app, without any
+    /// locale or path prefix (e.g. `blog`).
+    pub base: String,
+    /// The text of the link to the previous page. By default, this is
+    /// `Previous`.
+    #[builder(default = "Previous".to_string())]
+    pub prev_text: String,
+    /// The text of the link to the next page. By default, this is `Next`.
+    #[builder(default = "Next".to_string())]
+    pub next_text: String,
+}
+
+/// Links to the previous and next pages of a paginated list (see
+/// [`PageWindow`]), along with the current page number. The links will
+/// include the app's path prefix and the current locale, and they'll be left
+/// out on the first and last pages.
+///
+/// This renders a `nav` with the class `__perseus_pagination`, which is
+/// otherwise unstyled.
+#[component]
+#[allow(non_snake_case)]
+pub fn Pagination<G: Html>(cx: Scope, props: PaginationProps) -> View<G> {
+    let window = props.window;
+    let url = |page| RoutePath::new(PageWindow::page_path(&props.base, page)).to_url(cx);
+    let prev = window.has_prev().then(|| url(window.page - 1));
+    let next = window.has_next().then(|| url(window.page + 1));
+    let prev_text = props.prev_text;
+    let next_text = props.next_text;
+
+    view! { cx,
+        nav(class = "__perseus_pagination", aria_label = "Pagination") {
+            (match prev.clone() {
+                Some(href) => {
+                    let text = prev_text.clone();
+                    view! { cx, a(href = href, rel = "prev") { (text) } }
+                },
+                None => View::empty(),
+            })
+            span(aria_current = "page") { (format!("{} / {}", window.page, window.total_pages)) }
+            (match next.clone() {
+                Some(href) => {
+                    let text = next_text.clone();
+                    view! { cx, a(href = href, rel = "next") { (text) } }
+                },
+                None => View::empty(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_windows() {
+        let window = PageWindow::new(3, 25, 10);
+        assert_eq!(window.total_pages, 3);
+        assert_eq!(window.range(), 20..25);
+        assert!(window.has_prev() && !window.has_next());
+        assert_eq!(PageWindow::new(1, 0, 10).total_pages, 1);
+        assert_eq!(PageWindow::new(1, 0, 10).range(), 0..0);
+
+        assert_eq!(PageWindow::from_path("blog", 25, 10).unwrap().page, 1);
+        assert_eq!(
+            PageWindow::from_path("blog/page/2", 25, 10).unwrap().page,
+            2
+        );
+        assert_eq!(PageWindow::from_path("page/3/", 25, 10).unwrap().page, 3);
+        assert_eq!(PageWindow::from_path("blog/page/1", 25, 10), None);
+        assert_eq!(PageWindow::from_path("blog/page/4", 25, 10), None);
+        assert_eq!(PageWindow::from_path("blog/page/x", 25, 10), None);
+
+        assert_eq!(PageWindow::page_path("blog/", 1), "blog");
+        assert_eq!(PageWindow::page_path("blog", 2), "blog/page/2");
+        assert_eq!(PageWindow::page_path("", 2), "page/2");
+    }
+}
@@ -1,13 +1,34 @@
+use super::RouteRegistry;
 use crate::errors::*;
-use crate::router::{RouterLoadState, RouterState};
+use crate::experiments::Variants;
+#[cfg(target_arch = "wasm32")]
+use crate::i18n::{ClientTranslationsManager, Locales};
+use crate::router::{
+    NavigationGuardVerdict, NavigationGuards, PendingNavigation, RouterLoadState, RouterState,
+};
+use crate::session::SessionSnapshot;
 use crate::state::{
     AnyFreeze, Freeze, FrozenApp, GlobalState, MakeRx, MakeUnrx, PageStateStore, ThawPrefs,
+    FROZEN_APP_VERSION,
 };
+#[cfg(target_arch = "wasm32")]
+use crate::utils::listen_to_media_query;
+use crate::utils::{Breakpoint, ColorScheme, MediaSignals};
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
-use sycamore::prelude::{provide_context, use_context, Scope};
+#[cfg(target_arch = "wasm32")]
+use sycamore::prelude::on_cleanup;
+use sycamore::prelude::{
+    create_memo, create_rc_signal, create_ref, provide_context, use_context, RcSignal, ReadSignal,
+    Scope,
+};
 use sycamore_router::navigate;
 
+/// The class the HTML shell adds to the `<body>` while the app is booting, if
+/// the app has a boot indicator.
+pub(crate) const BOOT_INDICATOR_CLASS: &str = "__perseus_booting";
+
 /// A representation of the render context of the app, constructed from
 /// references to a series of `struct`s that mirror context values. This is
 /// purely a proxy `struct` for function organization.
@@ -44,6 +65,39 @@ pub struct RenderCtx {
     /// used internally to determine whether or not we should look for
     /// stored HSR state.
     pub is_first: Rc<Cell<bool>>,
+    /// The metadata declared by all the templates in the app, which can be
+    /// used to generate breadcrumbs and navigation menus.
+    pub route_registry: RouteRegistry,
+    /// The navigation guards that have been registered globally. These will be
+    /// run before every client-side navigation. Use
+    /// `.add_navigation_guard()` and `.remove_navigation_guard()` to
+    /// manage these.
+    pub(crate) navigation_guards: NavigationGuards,
+    /// The query parameters of the current URL. These are kept up-to-date by
+    /// the router. Use `.query_params()` to access this.
+    pub(crate) query_params: RcSignal<HashMap<String, String>>,
+    /// The hash fragment of the current URL (without the leading `#`). This is
+    /// kept up-to-date by the router. Use `.hash()` to access this.
+    pub(crate) hash: RcSignal<String>,
+    /// Reactive signals for common media queries. These are kept up-to-date by
+    /// the router once the first page has been rendered.
+    pub(crate) media: MediaSignals,
+    /// The A/B experiment variants the user has been assigned. These are set
+    /// by the router once the first page has been rendered. Use `.variant()`
+    /// to access this.
+    pub(crate) variants: RcSignal<Variants>,
+    /// The public data of the user's session. This is set by the router once
+    /// the first page has been rendered. Use `.session()` to access this.
+    pub(crate) session: RcSignal<SessionSnapshot>,
+    /// Whether or not the app has been hydrated (i.e. the first page is
+    /// interactive). This is set by the router. Use `.is_hydrated()` to
+    /// access this.
+    pub(crate) hydrated: RcSignal<bool>,
+    /// The manager of the translations the browser has fetched. This can be
+    /// used to preload the translations for other locales in the background,
+    /// so that switching to them later is instant.
+    #[cfg(target_arch = "wasm32")]
+    pub translations_manager: ClientTranslationsManager,
 }
 impl Default for RenderCtx {
     fn default() -> Self {
@@ -53,6 +107,25 @@ impl Default for RenderCtx {
             global_state: GlobalState::default(),
             frozen_app: Rc::new(RefCell::new(None)),
             is_first: Rc::new(Cell::new(true)),
+            route_registry: RouteRegistry::default(),
+            navigation_guards: NavigationGuards::default(),
+            query_params: create_rc_signal(HashMap::new()),
+            hash: create_rc_signal(String::new()),
+            media: MediaSignals::default(),
+            variants: create_rc_signal(Variants::default()),
+            session: create_rc_signal(SessionSnapshot::default()),
+            hydrated: create_rc_signal(false),
+            // The router will replace this with one that knows the app's locales
+            #[cfg(target_arch = "wasm32")]
+            translations_manager: ClientTranslationsManager::new(
+                &Locales {
+                    default: "xx-XX".to_string(),
+                    other: Vec::new(),
+                    using_i18n: false,
+                },
+                false,
+                None,
+            ),
         }
     }
 }
@@ -62,6 +135,7 @@ impl Freeze for RenderCtx {
     /// state at the time of freezing.
     fn freeze(&self) -> String {
         let frozen_app = FrozenApp {
+            version: FROZEN_APP_VERSION,
             global_state: self.global_state.0.borrow().freeze(),
             route: match &*self.router.get_load_state_rc().get_untracked() {
                 RouterLoadState::Loaded { path, .. } => path,
@@ -101,13 +175,20 @@ impl RenderCtx {
     /// If the app has already been thawed from a previous frozen state, any
     /// state used from that will be considered *active* for this thawing.
     ///
-    /// This will return an error if the frozen state provided is invalid.
+    /// This will return an error if the frozen state provided is invalid, or if
+    /// it was frozen in a different format by another version of Perseus.
     /// However, if the frozen state for an individual page is invalid, it will
     /// be silently ignored in favor of either the active state or the
     /// server-provided state.
     pub fn thaw(&self, new_frozen_app: &str, thaw_prefs: ThawPrefs) -> Result<(), ClientError> {
         let new_frozen_app: FrozenApp = serde_json::from_str(new_frozen_app)
             .map_err(|err| ClientError::ThawFailed { source: err })?;
+        if new_frozen_app.version != FROZEN_APP_VERSION {
+            return Err(ClientError::FrozenStateVersionMismatch {
+                found: new_frozen_app.version,
+                expected: FROZEN_APP_VERSION,
+            });
+        }
         let route = new_frozen_app.route.clone();
         // Set everything in the render context
         let mut frozen_app = self.frozen_app.borrow_mut();
@@ -135,6 +216,159 @@ impl RenderCtx {
 
         Ok(())
     }
+    /// Gets the query parameters of the current URL, as a map of keys to
+    /// (decoded) values. This is reactive, and will be updated whenever the
+    /// router navigates, including when only the query string has changed.
+    /// If a key appears more than once, the last value will be used.
+    ///
+    /// On the server-side, this will always be empty (if you need query
+    /// parameters there, you should use request state, which has access to the
+    /// full request).
+    pub fn query_params<'a>(&self, cx: Scope<'a>) -> &'a RcSignal<HashMap<String, String>> {
+        create_ref(cx, self.query_params.clone())
+    }
+    /// Gets the hash fragment of the current URL (without the leading `#`).
+    /// This is reactive, and will be updated whenever the router navigates or
+    /// the fragment changes. If there is no fragment, this will be empty.
+    ///
+    /// Browsers never send hash fragments to the server, so this will always be
+    /// empty on the server-side.
+    pub fn hash<'a>(&self, cx: Scope<'a>) -> &'a RcSignal<String> {
+        create_ref(cx, self.hash.clone())
+    }
+    /// Gets the color scheme the user prefers (from `prefers-color-scheme`).
+    /// This is reactive, and will be updated if the user changes their
+    /// preference.
+    ///
+    /// On the server-side, and during the browser's first render (so that
+    /// hydration works), this will be [`ColorScheme::Light`].
+    pub fn color_scheme<'a>(&self, cx: Scope<'a>) -> &'a RcSignal<ColorScheme> {
+        create_ref(cx, self.media.color_scheme.clone())
+    }
+    /// Gets whether or not the user prefers reduced motion (from
+    /// `prefers-reduced-motion`), in which case you should avoid non-essential
+    /// animations. This is reactive, and will be updated if the user changes
+    /// their preference.
+    ///
+    /// On the server-side, and during the browser's first render (so that
+    /// hydration works), this will be `false`.
+    pub fn prefers_reduced_motion<'a>(&self, cx: Scope<'a>) -> &'a RcSignal<bool> {
+        create_ref(cx, self.media.reduced_motion.clone())
+    }
+    /// Gets the [`Breakpoint`] of the current viewport width. This is
+    /// reactive, and will be updated as the viewport is resized.
+    ///
+    /// On the server-side, and during the browser's first render (so that
+    /// hydration works), this will be [`Breakpoint::Large`].
+    pub fn breakpoint<'a>(&self, cx: Scope<'a>) -> &'a RcSignal<Breakpoint> {
+        create_ref(cx, self.media.breakpoint.clone())
+    }
+    /// Creates a reactive signal for whether or not the given media query
+    /// (e.g. `(orientation: portrait)`) matches, which will be kept up-to-date
+    /// for as long as the given scope exists.
+    ///
+    /// On the server-side, this will always be the given default. In the
+    /// browser, it will also be the default until the current render is
+    /// complete, so that hydration works.
+    #[allow(unused_variables)]
+    pub fn media_query<'a>(
+        &self,
+        cx: Scope<'a>,
+        query: &str,
+        server_default: bool,
+    ) -> &'a RcSignal<bool> {
+        let signal = create_rc_signal(server_default);
+        #[cfg(target_arch = "wasm32")]
+        {
+            let signal_clone = signal.clone();
+            if let Some((mql, listener)) =
+                listen_to_media_query(query, move |matches| signal_clone.set(matches))
+            {
+                // We only update the signal once this render is complete
+                let signal_clone = signal.clone();
+                let mql_clone = mql.clone();
+                crate::spawn_local_scoped(cx, async move {
+                    signal_clone.set(mql_clone.matches());
+                });
+                on_cleanup(cx, move || {
+                    use wasm_bindgen::JsCast;
+                    let _ = mql.remove_event_listener_with_callback(
+                        "change",
+                        listener.as_ref().unchecked_ref(),
+                    );
+                });
+            }
+        }
+        create_ref(cx, signal)
+    }
+    /// Gets the variant of the given A/B experiment that the user has been
+    /// assigned, if they've been assigned one (see the
+    /// [`experiments`](crate::experiments) module). This is reactive.
+    ///
+    /// On the server-side, and during the browser's first render (so that
+    /// hydration works), this will be `None`, since prerendered pages are
+    /// shared between all users. If you need the variant to generate state,
+    /// use [`get_variants`](crate::experiments::get_variants) in a
+    /// request-state function instead.
+    pub fn variant<'a>(&self, cx: Scope<'a>, experiment: &str) -> &'a ReadSignal<Option<String>> {
+        let variants = self.variants.clone();
+        let experiment = experiment.to_string();
+        create_memo(cx, move || {
+            variants
+                .get()
+                .get(&experiment)
+                .map(|variant| variant.to_string())
+        })
+    }
+    /// Gets the public data of the user's session (see the
+    /// [`session`](crate::session) module). This is reactive.
+    ///
+    /// As with `.variant()`, this will be empty on the server-side and during
+    /// the browser's first render, since prerendered pages are shared between
+    /// all users. Note also that this is only updated on initial loads, so
+    /// you should reload the page after logging a user in or out.
+    pub fn session<'a>(&self, cx: Scope<'a>) -> &'a ReadSignal<SessionSnapshot> {
+        let session: &'a RcSignal<SessionSnapshot> = create_ref(cx, self.session.clone());
+        session
+    }
+    /// Gets whether or not the app has been hydrated, which is when the first
+    /// page the user loaded becomes interactive. Until then, event handlers
+    /// won't do anything, so you can use this to disable buttons and the like
+    /// (e.g. `button(disabled = !*is_hydrated.get())`) rather than having them
+    /// silently ignore the user. This is reactive, and it will only ever change
+    /// once.
+    ///
+    /// On the server-side, this will always be `false`.
+    pub fn is_hydrated<'a>(&self, cx: Scope<'a>) -> &'a ReadSignal<bool> {
+        let hydrated: &'a RcSignal<bool> = create_ref(cx, self.hydrated.clone());
+        hydrated
+    }
+    /// Registers a navigation guard, which will be run before every
+    /// client-side navigation (but not on the initial load or when the current
+    /// page is reloaded), before the router fetches anything for the new
+    /// page. The guard can allow the navigation to proceed, cancel it, or
+    /// redirect the user somewhere else (e.g. for an 'unsaved changes' prompt
+    /// or an authentication wall). Guards are run in the order they were
+    /// added, after any guard on the destination template, and the first one
+    /// that doesn't allow the navigation to proceed wins.
+    ///
+    /// Guards are identified by name, and adding a guard with the same name as
+    /// an existing one will replace it, so it's safe to call this every time
+    /// a page is rendered. Note that guards registered by a page will persist
+    /// after the user navigates away from it, so you should remove them with
+    /// `.remove_navigation_guard()` when they're no longer needed.
+    pub fn add_navigation_guard(
+        &self,
+        name: &str,
+        guard: impl Fn(&PendingNavigation) -> NavigationGuardVerdict + 'static,
+    ) {
+        self.navigation_guards.add(name.to_string(), Rc::new(guard));
+    }
+    /// Removes the navigation guard with the given name. If no such guard has
+    /// been registered, this will do nothing.
+    pub fn remove_navigation_guard(&self, name: &str) {
+        self.navigation_guards.remove(name);
+    }
     /// An internal getter for the frozen state for the given page. When this is
     /// called, it will also add any frozen state it finds to the page state
     /// store, overriding what was already there.
@@ -0,0 +1,288 @@
+use crate::router::LocalizedPaths;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Metadata about a template that doesn't affect how it's rendered, but which
+/// can be used to generate things like breadcrumbs and navigation menus that
+/// stay consistent with the actual templates in your app. This is declared on
+/// a template with `.meta()`, and can be accessed for all templates through the
+/// [`RouteRegistry`] in the render context.
+#[derive(Clone, Debug, Default)]
+pub struct RouteMeta {
+    /// A pattern for the titles of pages using this template. Any instances of
+    /// `{page}` will be replaced with the path of the page relative to the
+    /// template's root (e.g. `hello-world` for the page `post/hello-world` in
+    /// the `post` template).
+    pub title: Option<String>,
+    /// The path of the template that should be considered this template's
+    /// parent (e.g. `index` for the index template, which can also be referred
+    /// to as `""`).
+    pub parent: Option<String>,
+    /// An icon for this template, in whatever form you like (e.g. an emoji, a
+    /// CSS class, or an image URL).
+    pub icon: Option<String>,
+}
+impl RouteMeta {
+    /// Creates a new, empty set of route metadata.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the title pattern. Any instances of `{page}` will be replaced with
+    /// the path of the page relative to the template's root.
+    pub fn title(mut self, val: impl Into<String>) -> Self {
+        self.title = Some(val.into());
+        self
+    }
+    /// Sets the path of the parent template.
+    pub fn parent(mut self, val: impl Into<String>) -> Self {
+        self.parent = Some(val.into());
+        self
+    }
+    /// Sets the icon.
+    pub fn icon(mut self, val: impl Into<String>) -> Self {
+        self.icon = Some(val.into());
+        self
+    }
+    /// Renders the title of the given page from the title pattern, if there is
+    /// one. The page path given here should be relative to the template's
+    /// root.
+    pub fn render_title(&self, page: &str) -> Option<String> {
+        self.title
+            .as_ref()
+            .map(|title| title.replace("{page}", page))
+    }
+}
+
+/// A single entry in a breadcrumb trail generated by the [`RouteRegistry`].
+#[derive(Clone, Debug)]
+pub struct Breadcrumb {
+    /// The path of the template this entry is for.
+    pub template_name: String,
+    /// The path of the page this entry is for (without the locale), which will
+    /// be empty for the root of the app.
+    pub path: String,
+    /// The title of the page, rendered from the template's title pattern. If
+    /// the template has no title pattern, this will be the path of the page.
+    pub title: String,
+    /// The template's icon, if it has one.
+    pub icon: Option<String>,
+}
+
+/// A registry of the metadata declared by every template in the app (along
+/// with the localized versions of their root paths), which is available in the
+/// render context on both the engine-side and the browser-side. This can be
+/// cheaply cloned.
+#[derive(Clone, Debug, Default)]
+pub struct RouteRegistry {
+    meta: Arc<HashMap<String, RouteMeta>>,
+    localized_paths: Arc<LocalizedPaths>,
+}
+impl RouteRegistry {
+    /// Creates a new registry from the given map of template paths to their
+    /// metadata, and the localized versions of their root paths.
+    pub(crate) fn new(map: HashMap<String, RouteMeta>, localized_paths: LocalizedPaths) -> Self {
+        let map = map
+            .into_iter()
+            .map(|(name, meta)| (normalize_template_name(&name).to_string(), meta))
+            .collect();
+        Self {
+            meta: Arc::new(map),
+            localized_paths: Arc::new(localized_paths),
+        }
+    }
+    /// Gets the metadata for the template with the given path, if it exists.
+    pub fn get(&self, template_name: &str) -> Option<&RouteMeta> {
+        self.meta.get(normalize_template_name(template_name))
+    }
+    /// Gets an iterator over all the templates in the app and their metadata,
+    /// in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &RouteMeta)> {
+        self.meta.iter()
+    }
+    /// Converts the given page path (without the locale) into the form users
+    /// will see in the given locale, using the localized root paths declared
+    /// by the templates with `.localized_path()`.
+    pub fn localize_path(&self, path: &str, locale: &str) -> String {
+        self.localized_paths.localize(path, locale)
+    }
+    /// Gets the paths of all the templates that have declared the template
+    /// with the given path as their parent, sorted alphabetically. This is
+    /// useful for building navigation menus.
+    pub fn children(&self, template_name: &str) -> Vec<String> {
+        let template_name = normalize_template_name(template_name);
+        let mut children: Vec<String> = self
+            .meta
+            .iter()
+            .filter(|(_, meta)| {
+                meta.parent.as_deref().map(normalize_template_name) == Some(template_name)
+            })
+            .map(|(name, _)| name.to_string())
+            .collect();
+        children.sort();
+
+        children
+    }
+    /// Gets the path of the template that renders the given page path (without
+    /// the locale). This assumes that pages are rendered under their
+    /// templates' roots, and it doesn't know about pages that don't exist. The
+    /// root of the app (`/`) is rendered by the `index` template.
+    pub fn template_for_path(&self, path: &str) -> Option<String> {
+        let path = path.trim_matches('/');
+        // The index template only ever renders the root of the app
+        if path.is_empty() {
+            return self.meta.get("index").map(|_| "index".to_string());
+        }
+        self.meta
+            .keys()
+            .filter(|name| {
+                *name != "index"
+                    && (*name == path
+                        || (path.starts_with(name.as_str()) && path[name.len()..].starts_with('/')))
+            })
+            // The most specific template wins
+            .max_by_key(|name| name.len())
+            .cloned()
+    }
+    /// Generates a breadcrumb trail for the page at the given path (without
+    /// the locale), by following the parents declared by each template. The
+    /// trail starts at the root and ends with the given page. If the page's
+    /// template can't be found, this will be empty.
+    pub fn breadcrumbs(&self, path: &str) -> Vec<Breadcrumb> {
+        let mut crumbs = Vec::new();
+        let mut visited = HashSet::new();
+        let mut path = path.trim_matches('/').to_string();
+        let mut template_name = self.template_for_path(&path);
+        while let Some(name) = template_name {
+            // Protect against cycles in the declared parents
+            if !visited.insert(name.clone()) {
+                break;
+            }
+            let meta = &self.meta[&name];
+            let page = path
+                .strip_prefix(template_root_path(&name))
+                .unwrap_or(&path)
+                .trim_start_matches('/');
+            crumbs.push(Breadcrumb {
+                template_name: name.clone(),
+                title: meta.render_title(page).unwrap_or_else(|| path.clone()),
+                path,
+                icon: meta.icon.clone(),
+            });
+            // Parents are always referred to by their template roots, and their crumbs
+            // point to the pages at those roots
+            template_name = meta
+                .parent
+                .as_deref()
+                .map(normalize_template_name)
+                .filter(|parent| self.meta.contains_key(*parent))
+                .map(|parent| parent.to_string());
+            path = template_name
+                .as_deref()
+                .map(template_root_path)
+                .unwrap_or_default()
+                .to_string();
+        }
+        crumbs.reverse();
+
+        crumbs
+    }
+}
+
+/// Normalizes the given template name, so that the index template can be
+/// referred to as either `index` or `""` (with or without slashes).
+fn normalize_template_name(name: &str) -> &str {
+    match name.trim_matches('/') {
+        "" => "index",
+        name => name,
+    }
+}
+
+/// Gets the path of the page at the root of the template with the given
+/// (normalized) name, which is empty for the index template.
+fn template_root_path(name: &str) -> &str {
+    match name {
+        "index" => "",
+        name => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(templates: &[(&str, RouteMeta)]) -> RouteRegistry {
+        RouteRegistry::new(
+            templates
+                .iter()
+                .map(|(name, meta)| (name.to_string(), meta.clone()))
+                .collect(),
+            LocalizedPaths::default(),
+        )
+    }
+    fn crumbs(registry: &RouteRegistry, path: &str) -> Vec<(String, String, String)> {
+        registry
+            .breadcrumbs(path)
+            .into_iter()
+            .map(|crumb| (crumb.template_name, crumb.path, crumb.title))
+            .collect()
+    }
+    fn crumb(template_name: &str, path: &str, title: &str) -> (String, String, String) {
+        (
+            template_name.to_string(),
+            path.to_string(),
+            title.to_string(),
+        )
+    }
+
+    #[test]
+    fn root_is_index_template() {
+        let registry = registry(&[
+            ("index", RouteMeta::new().title("Home")),
+            ("about", RouteMeta::new().title("About")),
+        ]);
+        assert_eq!(registry.template_for_path("/").as_deref(), Some("index"));
+        assert_eq!(registry.template_for_path("").as_deref(), Some("index"));
+        assert_eq!(crumbs(&registry, "/"), vec![crumb("index", "", "Home")]);
+        assert!(registry.get("").is_some());
+        // The index template doesn't render any other pages
+        assert_eq!(registry.template_for_path("/index/foo"), None);
+    }
+    #[test]
+    fn nested_crumbs_point_to_pages() {
+        let registry = registry(&[
+            ("index", RouteMeta::new().title("Home")),
+            ("blog", RouteMeta::new().title("Blog").parent("")),
+            (
+                "blog/post",
+                RouteMeta::new().title("Post: {page}").parent("blog"),
+            ),
+            ("docs", RouteMeta::new().title("Docs").parent("index")),
+        ]);
+        assert_eq!(
+            crumbs(&registry, "/blog/post/hello-world"),
+            vec![
+                crumb("index", "", "Home"),
+                crumb("blog", "blog", "Blog"),
+                crumb("blog/post", "blog/post/hello-world", "Post: hello-world"),
+            ]
+        );
+        assert_eq!(
+            crumbs(&registry, "docs"),
+            vec![crumb("index", "", "Home"), crumb("docs", "docs", "Docs")]
+        );
+        assert_eq!(registry.children(""), vec!["blog", "docs"]);
+        assert_eq!(registry.children("index"), vec!["blog", "docs"]);
+    }
+    #[test]
+    fn missing_parents_end_trail() {
+        let registry = registry(&[
+            ("index", RouteMeta::new().title("Home")),
+            ("about", RouteMeta::new().title("About").parent("company")),
+        ]);
+        assert_eq!(
+            crumbs(&registry, "/about"),
+            vec![crumb("about", "about", "About")]
+        );
+        assert!(crumbs(&registry, "/nonexistent").is_empty());
+    }
+}
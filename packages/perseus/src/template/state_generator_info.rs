@@ -0,0 +1,172 @@
+use crate::state::{StateDependencies, TemplateGroups};
+use crate::template::{PageWindow, RenderFnResult};
+use crate::utils::BuildProfile;
+use chrono::{DateTime, Utc};
+use http::Extensions;
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+/// Information about the page whose state is being generated, which is passed
+/// to all state generation functions (except those for build paths). This is a
+/// single struct so that more information can be added to it in future without
+/// having to change the signatures of every state generation function.
+#[derive(Debug)]
+pub struct StateGeneratorInfo {
+    /// The path of the page whose state is being generated, without the locale
+    /// (e.g. `post/foo`). If you're using build paths, this will be one of the
+    /// paths you generated.
+    pub path: String,
+    /// The locale the page is being generated for. If i18n isn't being used,
+    /// this will be `xx-XX`.
+    pub locale: String,
+    /// The serialized extra data attached to this path by the build paths
+    /// function, if there is any.
+    extra: Option<String>,
+    /// The values provided to the app with `PerseusApp::state_dependency()`.
+    dependencies: StateDependencies,
+    /// The template group this page's template is in, if any.
+    group: Option<String>,
+    /// The app's template groups, from which the shared state of this page's
+    /// group can be obtained.
+    groups: TemplateGroups,
+    /// The time at which the page should next be revalidated, if the state
+    /// generation function has scheduled that.
+    revalidation_schedule: RevalidationSchedule,
+    /// Arbitrary extra data attached to this generation.
+    extensions: Extensions,
+}
+impl StateGeneratorInfo {
+    /// Creates the information for generating the state of the given page.
+    pub(crate) fn new(path: String, locale: String, dependencies: StateDependencies) -> Self {
+        Self {
+            path,
+            locale,
+            extra: None,
+            dependencies,
+            group: None,
+            groups: TemplateGroups::default(),
+            revalidation_schedule: RevalidationSchedule::default(),
+            extensions: Extensions::new(),
+        }
+    }
+    /// Attaches the given serialized extra data from the build paths function.
+    pub(crate) fn with_extra(mut self, extra: Option<String>) -> Self {
+        self.extra = extra;
+        self
+    }
+    /// Gets a handle to the time at which the page should next be revalidated,
+    /// which can be read once the state generation function has finished.
+    pub(crate) fn get_revalidation_schedule(&self) -> RevalidationSchedule {
+        self.revalidation_schedule.clone()
+    }
+    /// Attaches the template group this page's template is in, if any.
+    pub(crate) fn with_group(mut self, group: Option<String>, groups: TemplateGroups) -> Self {
+        self.group = group;
+        self.groups = groups;
+        self
+    }
+    /// Gets the extra data that the template's build paths function attached
+    /// to this path (see [`BuildPaths`](crate::template::BuildPaths)), if
+    /// there is any. This will only be available when the page is generated at
+    /// build-time, not when it's revalidated or incrementally generated (since
+    /// then you'll want fresh data anyway), so you should always be prepared
+    /// to fetch the data yourself if this is `None`.
+    ///
+    /// This will fail if the extra data can't be deserialized into the given
+    /// type.
+    pub fn extra<E: DeserializeOwned>(&self) -> Result<Option<E>, serde_json::Error> {
+        self.extra
+            .as_ref()
+            .map(|extra| serde_json::from_str(extra))
+            .transpose()
+    }
+    /// Gets the window of the paginated list this page is part of, if its
+    /// path was generated with
+    /// [`BuildPaths::add_paginated`](crate::template::BuildPaths::add_paginated).
+    /// As with [`Self::extra`], this is only available at build-time, so
+    /// [`PageWindow::from_path`] should be used as a fallback for pages
+    /// generated later.
+    pub fn page_window(&self) -> Option<PageWindow> {
+        self.extra::<PageWindow>().ok().flatten()
+    }
+    /// Schedules the page to be revalidated at the given time (e.g. when a
+    /// scheduled post that it lists will be published). If this is called
+    /// several times, the earliest time will be used.
+    ///
+    /// This only has an effect if the page's template uses
+    /// `.scheduled_revalidation()`, and only for the build state function
+    /// (since request state is generated fresh anyway). Once the page has
+    /// been revalidated, it will need to be scheduled again.
+    pub fn schedule_revalidation(&self, at: DateTime<Utc>) {
+        let mut scheduled = self
+            .revalidation_schedule
+            .0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if scheduled.map_or(true, |scheduled| at < scheduled) {
+            *scheduled = Some(at);
+        }
+    }
+    /// Gets the dependency of the given type that was provided to the app with
+    /// `PerseusApp::state_dependency()` (e.g. a database connection pool), if
+    /// there is one.
+    pub fn dependency<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.dependencies.get::<T>()
+    }
+    /// Gets the shared state of the template group this page's template is in
+    /// (see `PerseusApp::template_group()`), which will be computed once per
+    /// build, no matter how many pages need it.
+    ///
+    /// This will fail if the template isn't in a group, if computing the
+    /// group's state failed, or if that state isn't of the given type.
+    pub async fn group_state<S: Any + Send + Sync>(&self) -> RenderFnResult<Arc<S>> {
+        let group = self
+            .group
+            .as_ref()
+            .ok_or("this page's template isn't in a template group")?;
+        let state = self.groups.get(group).await?;
+        state.downcast::<S>().map_err(|_| {
+            format!(
+                "the state of template group '{}' isn't of the requested type",
+                group
+            )
+            .into()
+        })
+    }
+    /// Gets the profile the app is being built with, which can be used to
+    /// fetch data from different places for preview and production builds
+    /// (e.g. `info.profile().select(STAGING_API, LIVE_API)`).
+    pub fn profile(&self) -> BuildProfile {
+        BuildProfile::current()
+    }
+    /// Checks if the app is using i18n (i.e. if the locale is a real one).
+    pub fn is_localized(&self) -> bool {
+        self.locale != "xx-XX"
+    }
+    /// Gets the segments of the page's path (e.g. `["post", "foo"]` for
+    /// `post/foo`), ignoring any leading or trailing slashes.
+    pub fn path_segments(&self) -> impl Iterator<Item = &str> {
+        self.path.split('/').filter(|segment| !segment.is_empty())
+    }
+    /// Gets a reference to the extension map, which can be used to attach
+    /// arbitrary data to this generation (e.g. when wrapping state generation
+    /// functions).
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+    /// Gets a mutable reference to the extension map.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+/// A shared handle to the time at which a page has asked to be revalidated.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RevalidationSchedule(Arc<Mutex<Option<DateTime<Utc>>>>);
+impl RevalidationSchedule {
+    /// Gets the scheduled time, if there is one.
+    pub(crate) fn get(&self) -> Option<DateTime<Utc>> {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,230 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A typed argument that can be interpolated into a translation with the
+/// [`t!`](crate::t) macro. Strings, numbers, and dates (as
+/// [`SystemTime`]s) can all be converted into this automatically.
+///
+/// Numbers are kept as numbers (rather than being converted to strings
+/// straight away), so that Fluent can select the right plural form for them,
+/// and dates will be interpolated in RFC 3339 format (in UTC). Integers too
+/// large to be represented exactly as an `f64` are kept as strings instead, so
+/// they aren't rounded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranslationArg {
+    /// A string argument.
+    String(String),
+    /// A numeric argument.
+    Number(f64),
+    /// A date argument.
+    Date(SystemTime),
+}
+impl fmt::Display for TranslationArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(val) => f.write_str(val),
+            Self::Number(val) => write!(f, "{}", val),
+            Self::Date(val) => f.write_str(&format_date(*val)),
+        }
+    }
+}
+impl From<&str> for TranslationArg {
+    fn from(val: &str) -> Self {
+        Self::String(val.to_string())
+    }
+}
+impl From<String> for TranslationArg {
+    fn from(val: String) -> Self {
+        Self::String(val)
+    }
+}
+impl From<&String> for TranslationArg {
+    fn from(val: &String) -> Self {
+        Self::String(val.to_string())
+    }
+}
+impl From<Cow<'_, str>> for TranslationArg {
+    fn from(val: Cow<'_, str>) -> Self {
+        Self::String(val.into_owned())
+    }
+}
+impl From<SystemTime> for TranslationArg {
+    fn from(val: SystemTime) -> Self {
+        Self::Date(val)
+    }
+}
+/// The smallest magnitude at which integers can no longer all be represented
+/// exactly as `f64`s (2^53).
+const MAX_EXACT_INT: f64 = 9_007_199_254_740_992.0;
+macro_rules! impl_int_arg {
+    ($($ty:ty),+) => {
+        $(
+            impl From<$ty> for TranslationArg {
+                fn from(val: $ty) -> Self {
+                    let float = val as f64;
+                    // Anything that rounds to less than 2^53 must have been exact
+                    if float.abs() < MAX_EXACT_INT {
+                        Self::Number(float)
+                    } else {
+                        Self::String(val.to_string())
+                    }
+                }
+            }
+            impl NumericArg for $ty {}
+        )+
+    };
+}
+impl_int_arg!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+macro_rules! impl_float_arg {
+    ($($ty:ty),+) => {
+        $(
+            impl From<$ty> for TranslationArg {
+                fn from(val: $ty) -> Self {
+                    Self::Number(val as f64)
+                }
+            }
+            impl NumericArg for $ty {}
+        )+
+    };
+}
+impl_float_arg!(f32, f64);
+
+/// A CLDR plural category, as produced by the `.plural_category()` and
+/// `.ordinal_category()` methods on the Fluent translator. This can be given
+/// to the [`t!`](crate::t) macro wherever a number can, so that translations
+/// can select variants with it (which is the only way to handle ordinals in
+/// Fluent).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PluralCategory(&'static str);
+impl PluralCategory {
+    /// The `zero` category.
+    pub const ZERO: Self = Self("zero");
+    /// The `one` category.
+    pub const ONE: Self = Self("one");
+    /// The `two` category.
+    pub const TWO: Self = Self("two");
+    /// The `few` category.
+    pub const FEW: Self = Self("few");
+    /// The `many` category.
+    pub const MANY: Self = Self("many");
+    /// The `other` category.
+    pub const OTHER: Self = Self("other");
+
+    /// Gets the name of this category, which is what translations should use
+    /// as the key of the variant for it.
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+impl fmt::Display for PluralCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+impl From<PluralCategory> for TranslationArg {
+    fn from(val: PluralCategory) -> Self {
+        Self::String(val.0.to_string())
+    }
+}
+
+/// A marker trait for types that can be given to the [`t!`](crate::t) macro
+/// for arguments that translations use as numbers (e.g. to select a plural
+/// form). When your translations can be found at compile-time, the macro will
+/// check this for you.
+pub trait NumericArg {}
+impl<T: NumericArg + ?Sized> NumericArg for &T {}
+impl NumericArg for PluralCategory {}
+// We can't know what sort of value this is until runtime
+impl NumericArg for TranslationArg {}
+
+/// Asserts that the given argument is numeric. This is used by the checks the
+/// [`t!`](crate::t) macro generates, and it will never actually be called.
+#[doc(hidden)]
+pub fn check_numeric_arg<T: NumericArg>(_arg: &T) {}
+
+/// Formats the given time as an RFC 3339 timestamp in UTC, to the second.
+fn format_date(time: SystemTime) -> String {
+    let parts = DateParts::from(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        parts.year, parts.month, parts.day, parts.hour, parts.minute, parts.second
+    )
+}
+
+/// The parts of a time in UTC.
+#[derive(Debug)]
+pub(crate) struct DateParts {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+impl From<SystemTime> for DateParts {
+    fn from(time: SystemTime) -> Self {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(dur) => dur.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+        let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+        // This converts days since the epoch into a civil date (see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_idx = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_idx + 2) / 5 + 1;
+        let month = if month_idx < 10 {
+            month_idx + 3
+        } else {
+            month_idx - 9
+        };
+
+        Self {
+            year: year_of_era + era * 400 + i64::from(month <= 2),
+            month: month as u32,
+            day: day as u32,
+            hour: (secs_of_day / 3600) as u32,
+            minute: (secs_of_day % 3600 / 60) as u32,
+            second: (secs_of_day % 60) as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn converts_typed_args() {
+        assert_eq!(TranslationArg::from("hi").to_string(), "hi");
+        assert_eq!(TranslationArg::from(5).to_string(), "5");
+        assert_eq!(TranslationArg::from(1.5).to_string(), "1.5");
+        assert_eq!(
+            TranslationArg::from(UNIX_EPOCH).to_string(),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            TranslationArg::from(UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661)).to_string(),
+            "2000-02-29T01:01:01Z"
+        );
+        assert_eq!(
+            TranslationArg::from(9_007_199_254_740_991_u64),
+            TranslationArg::Number(9_007_199_254_740_991.0)
+        );
+        assert_eq!(
+            TranslationArg::from(u64::MAX),
+            TranslationArg::String("18446744073709551615".to_string())
+        );
+        assert_eq!(
+            TranslationArg::from(i64::MIN).to_string(),
+            "-9223372036854775808"
+        );
+    }
+}
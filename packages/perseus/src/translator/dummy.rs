@@ -1,4 +1,5 @@
 use crate::translator::errors::*;
+use crate::translator::TranslationArg;
 
 /// An empty file extension, because all translators must provide one.
 pub const DUMMY_TRANSLATOR_FILE_EXT: &str = "";
@@ -56,7 +57,7 @@ impl TranslationArgs {
     /// A filler function to conform to the typical argument-setting interface.
     /// Again, this will be entirele unnecessary once conditional expression
     /// compilation is supported.
-    pub fn set(&self, _key: &str, _val: &str) {}
+    pub fn set(&self, _key: &str, _val: impl Into<TranslationArg>) {}
 }
 
 /// The internal dummy backend for the `t!` macro. This
@@ -29,4 +29,12 @@ pub enum TranslatorError {
     /// This could be caused by an invalid variant for a compound message.
     #[error("no translation could be derived for message '{id}' in locale '{locale}'")]
     NoTranslationDerived { id: String, locale: String },
+    /// This will usually be caused by the locale data given to the formatter
+    /// not having the locale.
+    #[error("couldn't create formatter for locale '{locale}'")]
+    FormatterCreationFailed {
+        locale: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
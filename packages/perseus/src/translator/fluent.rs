@@ -1,6 +1,10 @@
 use crate::translator::errors::*;
-use fluent_bundle::{bundle::FluentBundle, FluentArgs, FluentResource};
+#[cfg(feature = "formatting")]
+use crate::translator::Formatter;
+use crate::translator::{NumericArg, PluralCategory, TranslationArg};
+use fluent_bundle::{bundle::FluentBundle, FluentArgs, FluentResource, FluentValue};
 use intl_memoizer::concurrent::IntlLangMemoizer;
+use intl_pluralrules::{PluralCategory as CldrCategory, PluralRuleType, PluralRules};
 use std::sync::Arc;
 use sycamore::prelude::{use_context, Scope, Signal};
 use unic_langid::{LanguageIdentifier, LanguageIdentifierError};
@@ -82,6 +86,16 @@ impl FluentTranslator {
     pub fn get_locale(&self) -> String {
         self.locale.clone()
     }
+    /// Gets a formatter for numbers and dates in the locale for which this
+    /// instance is configured, using locale data from the given provider (see
+    /// [`Formatter`] for details).
+    #[cfg(feature = "formatting")]
+    pub fn formatter(
+        &self,
+        provider: &(impl icu_provider::BufferProvider + ?Sized),
+    ) -> Result<Formatter, TranslatorError> {
+        Formatter::new(&self.locale, provider)
+    }
     /// Translates the given ID. This additionally takes any arguments that
     /// should be interpolated. If your i18n system also has variants,
     /// they should be specified somehow in the ID.
@@ -183,7 +197,64 @@ impl FluentTranslator {
     pub fn get_bundle(&self) -> &FluentBundle<FluentResource, IntlLangMemoizer> {
         &self.bundle
     }
+    /// Gets the CLDR plural category (`zero`, `one`, `two`, `few`, `many`, or
+    /// `other`) of the given number in this translator's locale (e.g. `one`
+    /// for `1` in `en-US`). Fluent does this automatically when a number is
+    /// used to select a variant, so this is mostly useful for picking
+    /// variants of compound messages (e.g. `t!(&format!("emails.{}",
+    /// translator.plural_category(n)), cx)`).
+    pub fn plural_category(&self, n: f64) -> PluralCategory {
+        self.get_category(n, PluralRuleType::CARDINAL)
+    }
+    /// Gets the CLDR ordinal category (`zero`, `one`, `two`, `few`, `many`, or
+    /// `other`) of the given number in this translator's locale (e.g. `two`
+    /// for `22` in `en-US`, as in *22nd*). Fluent can't work this out itself,
+    /// so you can pass this as an argument to a translation and select a
+    /// variant with it instead:
+    ///
+    /// ```text
+    /// place = You came { $place }{ $category ->
+    ///     [one] st
+    ///     [two] nd
+    ///     [few] rd
+    ///    *[other] th
+    /// }!
+    /// ```
+    pub fn ordinal_category(&self, n: f64) -> PluralCategory {
+        self.get_category(n, PluralRuleType::ORDINAL)
+    }
+    /// Gets the category of the given number under the given type of plural
+    /// rules, falling back to `other` if this locale doesn't have any.
+    fn get_category(&self, n: f64, rule_type: PluralRuleType) -> PluralCategory {
+        let category = PluralRules::create(self.bundle.locales[0].clone(), rule_type)
+            .and_then(|rules| rules.select(n));
+        match category {
+            Ok(CldrCategory::ZERO) => PluralCategory::ZERO,
+            Ok(CldrCategory::ONE) => PluralCategory::ONE,
+            Ok(CldrCategory::TWO) => PluralCategory::TWO,
+            Ok(CldrCategory::FEW) => PluralCategory::FEW,
+            Ok(CldrCategory::MANY) => PluralCategory::MANY,
+            Ok(CldrCategory::OTHER) | Err(_) => PluralCategory::OTHER,
+        }
+    }
+}
+
+impl<'args> From<TranslationArg> for FluentValue<'args> {
+    fn from(arg: TranslationArg) -> Self {
+        match arg {
+            TranslationArg::Number(val) => val.into(),
+            // Fluent doesn't support dates natively, so they're formatted by us
+            arg => arg.to_string().into(),
+        }
+    }
+}
+impl<'args> From<PluralCategory> for FluentValue<'args> {
+    fn from(category: PluralCategory) -> Self {
+        category.as_str().into()
+    }
 }
+// We can't know what sort of value this is until runtime
+impl NumericArg for FluentValue<'_> {}
 
 /// An alias for `FluentArgs`. This is a workaround until conditional
 /// compilation of expressions is supported, which will simplify this
@@ -209,5 +280,9 @@ pub fn t_macro_backend_with_args(id: &str, args: FluentArgs, cx: Scope) -> Strin
 #[doc(hidden)]
 pub fn link_macro_backend(url: &str, cx: Scope) -> String {
     let translator = use_context::<Signal<super::Translator>>(cx).get_untracked();
-    translator.url(url)
+    // Use the localized version of the path, if the template it's under has one
+    let url = crate::template::RenderCtx::from_ctx(cx)
+        .route_registry
+        .localize_path(url, &translator.get_locale());
+    translator.url(&url)
 }
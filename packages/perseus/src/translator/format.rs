@@ -0,0 +1,290 @@
+use super::args::DateParts;
+use crate::translator::errors::*;
+use fixed_decimal::{DoublePrecision, FixedDecimal};
+use icu_calendar::{DateTime, Gregorian};
+use icu_datetime::options::length;
+use icu_datetime::{TimeFormatter, TypedDateFormatter, TypedDateTimeFormatter};
+use icu_decimal::FixedDecimalFormatter;
+use icu_experimental::dimension::currency::formatter::{CurrencyCode, CurrencyFormatter};
+use icu_locid::Locale;
+use icu_provider::serde::AsDeserializingBufferProvider;
+use icu_provider::{BufferProvider, DataLocale};
+use std::time::SystemTime;
+use sycamore::prelude::{use_context, Scope, Signal};
+use writeable::Writeable;
+
+/// Formats numbers, currencies, and dates according to the conventions of a
+/// locale (e.g. `1,234.5` in `en-US`, but `1.234,5` in `de-DE`), using
+/// [ICU4X](https://github.com/unicode-org/icu4x). You can get one of these for
+/// the current locale with [`Formatter::from_ctx`], or from the translator
+/// with `.formatter(&provider)`.
+///
+/// ICU4X needs locale data to do this, which you'll need to provide as a
+/// `BufferProvider`. Usually, this will be a `BlobDataProvider` created from
+/// a blob generated with `icu4x-datagen --format blob` for just the locales
+/// and keys your app needs (decimal symbols, currency essentials, and Gregorian
+/// dates and times),
+/// which can be embedded in your app with `include_bytes!`. (The
+/// `icu_testdata` crate also has a provider that's useful for trying things
+/// out.) If your data doesn't have every locale your app supports, you should
+/// wrap it in a `LocaleFallbackProvider` so that, for instance, `en-US` can
+/// use the data for `en`.
+///
+/// Since the same data is used on the engine-side and in the browser, pages
+/// will be formatted in exactly the same way in both (which matters for
+/// hydration). Dates are always formatted in UTC for the same reason.
+///
+/// Creating one of these loads all the data it needs up-front, so you should
+/// create one per page and reuse it, rather than creating one for every value
+/// you want to format.
+#[derive(Debug)]
+pub struct Formatter {
+    /// The locale this formats for.
+    locale: String,
+    decimal: FixedDecimalFormatter,
+    currency: CurrencyFormatter,
+    short_date: TypedDateFormatter<Gregorian>,
+    long_date: TypedDateFormatter<Gregorian>,
+    time: TimeFormatter,
+    short_date_time: TypedDateTimeFormatter<Gregorian>,
+    long_date_time: TypedDateTimeFormatter<Gregorian>,
+}
+
+/// How much detail to include when formatting a date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateStyle {
+    /// An all-numeric date, like `1/5/23` in `en-US`.
+    Short,
+    /// A date with the month written out, like `January 5, 2023` in `en-US`.
+    Long,
+}
+
+impl Formatter {
+    /// Gets a formatter for the locale of the page currently being rendered,
+    /// from the translator in Sycamore's context system.
+    pub fn from_ctx(
+        cx: Scope,
+        provider: &(impl BufferProvider + ?Sized),
+    ) -> Result<Self, TranslatorError> {
+        let locale = use_context::<Signal<super::Translator>>(cx)
+            .get_untracked()
+            .get_locale();
+        Self::new(&locale, provider)
+    }
+    /// Creates a new formatter for the given locale, with data from the given
+    /// provider.
+    pub fn new(
+        locale: &str,
+        provider: &(impl BufferProvider + ?Sized),
+    ) -> Result<Self, TranslatorError> {
+        let make_err = |err: Box<dyn std::error::Error + Send + Sync>| {
+            TranslatorError::FormatterCreationFailed {
+                locale: locale.to_string(),
+                source: err,
+            }
+        };
+        let data_locale: DataLocale = locale
+            .parse::<Locale>()
+            .map_err(|err| TranslatorError::InvalidLocale {
+                locale: locale.to_string(),
+                source: Box::new(err),
+            })?
+            .into();
+        let date = |style| {
+            TypedDateFormatter::try_new_with_length_with_buffer_provider(
+                provider,
+                &data_locale,
+                style,
+            )
+            .map_err(|err| make_err(Box::new(err)))
+        };
+        let date_time = |style| {
+            TypedDateTimeFormatter::try_new_with_buffer_provider(
+                provider,
+                &data_locale,
+                length::Bag::from_date_time_style(style, length::Time::Short).into(),
+            )
+            .map_err(|err| make_err(Box::new(err)))
+        };
+
+        Ok(Self {
+            locale: locale.to_string(),
+            decimal: FixedDecimalFormatter::try_new_with_buffer_provider(
+                provider,
+                &data_locale,
+                Default::default(),
+            )
+            .map_err(|err| make_err(Box::new(err)))?,
+            // Currency formatting is experimental, so it doesn't have a constructor for buffer
+            // providers yet, but this is what those constructors do internally
+            currency: CurrencyFormatter::try_new_unstable(
+                &provider.as_deserializing(),
+                &data_locale,
+                Default::default(),
+            )
+            .map_err(|err| make_err(Box::new(err)))?,
+            short_date: date(length::Date::Short)?,
+            long_date: date(length::Date::Long)?,
+            time: TimeFormatter::try_new_with_length_with_buffer_provider(
+                provider,
+                &data_locale,
+                length::Time::Short,
+            )
+            .map_err(|err| make_err(Box::new(err)))?,
+            short_date_time: date_time(length::Date::Short)?,
+            long_date_time: date_time(length::Date::Long)?,
+        })
+    }
+    /// Gets the locale this formatter is for.
+    pub fn get_locale(&self) -> &str {
+        &self.locale
+    }
+    /// Formats the given number, with as many digits after the decimal point
+    /// as it needs (up to the precision of an `f64`).
+    pub fn number(&self, n: f64) -> String {
+        match FixedDecimal::try_from_f64(n, DoublePrecision::Floating) {
+            Ok(decimal) => self.decimal.format_to_string(&decimal),
+            // This only happens for infinities and `NaN`
+            Err(_) => n.to_string(),
+        }
+    }
+    /// Formats the given number with exactly the given number of digits after
+    /// the decimal point (rounding half to even).
+    pub fn decimal(&self, n: f64, fraction_digits: i16) -> String {
+        match FixedDecimal::try_from_f64(n, DoublePrecision::Floating) {
+            Ok(mut decimal) => {
+                decimal.half_even(-fraction_digits);
+                decimal.pad_end(-fraction_digits);
+                self.decimal.format_to_string(&decimal)
+            }
+            Err(_) => n.to_string(),
+        }
+    }
+    /// Formats the given amount of the given currency, which should be an ISO
+    /// 4217 code (e.g. `EUR`), with the currency's symbol in this locale (e.g.
+    /// `-$1,234.50` in `en-US`, but `-1 234,50 $US` in `fr-FR`). Amounts are
+    /// rounded to the currency's minor unit (e.g. cents for `USD`, or whole
+    /// yen for `JPY`). Currencies this locale doesn't have a symbol for will
+    /// be shown with their codes.
+    pub fn currency(&self, amount: f64, currency: &str) -> String {
+        let mut decimal = match FixedDecimal::try_from_f64(amount.abs(), DoublePrecision::Floating)
+        {
+            Ok(decimal) => decimal,
+            Err(_) => return amount.to_string(),
+        };
+        let fraction_digits = currency_fraction_digits(currency);
+        decimal.half_even(-fraction_digits);
+        decimal.pad_end(-fraction_digits);
+        let number = self.decimal.format_to_string(&decimal);
+
+        // ICU4X's currency patterns don't group digits yet, so we use them to place the
+        // symbol around a zero (which no currency symbol contains), and then put the
+        // number formatted for this locale in its place
+        let formatted = currency
+            .parse()
+            .ok()
+            .map(|code| {
+                self.currency
+                    .format_fixed_decimal(&FixedDecimal::from(0u8), CurrencyCode(code))
+                    .write_to_string()
+                    .into_owned()
+            })
+            .filter(|pattern| pattern.contains('0'))
+            .map(|pattern| pattern.replacen('0', &number, 1))
+            .unwrap_or_else(|| format!("{}\u{a0}{}", number, currency));
+        // Most locales put the sign before the symbol
+        if amount < 0.0 {
+            format!("-{}", formatted)
+        } else {
+            formatted
+        }
+    }
+    /// Formats the date of the given time (in UTC) in the given style.
+    pub fn date(&self, time: SystemTime, style: DateStyle) -> String {
+        let date_time = to_date_time(time);
+        match style {
+            DateStyle::Short => self.short_date.format_to_string(&date_time.date),
+            DateStyle::Long => self.long_date.format_to_string(&date_time.date),
+        }
+    }
+    /// Formats the time of day of the given time (in UTC), to the minute.
+    pub fn time(&self, time: SystemTime) -> String {
+        self.time.format_to_string(&to_date_time(time))
+    }
+    /// Formats the date (in the given style) and time of day of the given time
+    /// (in UTC).
+    pub fn date_time(&self, time: SystemTime, style: DateStyle) -> String {
+        let date_time = to_date_time(time);
+        match style {
+            DateStyle::Short => self.short_date_time.format_to_string(&date_time),
+            DateStyle::Long => self.long_date_time.format_to_string(&date_time),
+        }
+    }
+}
+
+/// Gets the number of digits after the decimal point amounts of the given
+/// currency have (its minor unit in ISO 4217), which is two for most.
+fn currency_fraction_digits(currency: &str) -> i16 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        "CLF" | "UYW" => 4,
+        _ => 2,
+    }
+}
+
+/// Converts the given time into a Gregorian date and time in UTC. Times too far
+/// from the present to be represented will be clamped to the Unix epoch.
+fn to_date_time(time: SystemTime) -> DateTime<Gregorian> {
+    let parts = DateParts::from(time);
+    i32::try_from(parts.year)
+        .ok()
+        .and_then(|year| {
+            DateTime::try_new_gregorian_datetime(
+                year,
+                parts.month as u8,
+                parts.day as u8,
+                parts.hour as u8,
+                parts.minute as u8,
+                parts.second as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_else(|| DateTime::try_new_gregorian_datetime(1970, 1, 1, 0, 0, 0).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    // The test data is deprecated in favour of compiled data, but apps provide their own
+    // buffer providers
+    #[allow(deprecated)]
+    fn formats_per_locale() {
+        let provider = icu_testdata::buffer();
+        let en = Formatter::new("en-US", &provider).unwrap();
+        let fr = Formatter::new("fr-FR", &provider).unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1_672_937_400); // 2023-01-05T16:50:00Z
+
+        assert_eq!(en.number(1234567.125), "1,234,567.125");
+        assert_eq!(en.number(-0.5), "-0.5");
+        assert_eq!(en.decimal(1234.5, 2), "1,234.50");
+        assert_eq!(fr.decimal(1234.5, 2), "1\u{202f}234,50");
+        assert_eq!(en.date(time, DateStyle::Long), "January 5, 2023");
+        assert_eq!(fr.date(time, DateStyle::Long), "5 janvier 2023");
+        assert_eq!(en.time(time), "4:50\u{202f}PM");
+        assert_eq!(fr.time(time), "16:50");
+        assert_eq!(en.currency(-1234.5, "USD"), "-$1,234.50");
+        assert_eq!(en.currency(1000.4, "JPY"), "¥1,000");
+        assert_eq!(fr.currency(1234.5, "EUR"), "1\u{202f}234,50\u{a0}€");
+        assert_eq!(fr.currency(1234.5, "USD"), "1\u{202f}234,50\u{a0}$US");
+        assert_eq!(
+            en.currency(3.0, "not a currency"),
+            "3.00\u{a0}not a currency"
+        );
+        assert!(Formatter::new("not a locale!", &provider).is_err());
+    }
+}
@@ -1,4 +1,7 @@
 use crate::translator::errors::*;
+#[cfg(feature = "formatting")]
+use crate::translator::Formatter;
+use crate::translator::TranslationArg;
 use std::collections::HashMap;
 use sycamore::prelude::{use_context, Scope, Signal};
 
@@ -68,6 +71,16 @@ impl LightweightTranslator {
     pub fn get_locale(&self) -> String {
         self.locale.clone()
     }
+    /// Gets a formatter for numbers and dates in the locale for which this
+    /// instance is configured, using locale data from the given provider (see
+    /// [`Formatter`] for details).
+    #[cfg(feature = "formatting")]
+    pub fn formatter(
+        &self,
+        provider: &(impl icu_provider::BufferProvider + ?Sized),
+    ) -> Result<Formatter, TranslatorError> {
+        Formatter::new(&self.locale, provider)
+    }
     /// Translates the given ID. This additionally takes any arguments that
     /// should be interpolated. If your i18n system also has variants,
     /// they should be specified somehow in the ID.
@@ -126,9 +139,10 @@ impl LightweightTranslator {
 #[allow(missing_debug_implementations)]
 pub struct TranslationArgs(pub HashMap<String, String>);
 impl TranslationArgs {
-    /// Alias for `.insert()` (needed for Fluent compat).
-    pub fn set(&mut self, k: &str, v: &str) -> Option<String> {
-        self.0.insert(k.to_string(), v.to_string())
+    /// Alias for `.insert()` (needed for Fluent compat). Typed arguments will
+    /// be converted to strings.
+    pub fn set(&mut self, k: &str, v: impl Into<TranslationArg>) -> Option<String> {
+        self.0.insert(k.to_string(), v.into().to_string())
     }
     /// Alias for `.get()` (needed for Fluent compat).
     pub fn get(&self, k: &str) -> Option<&String> {
@@ -157,5 +171,9 @@ pub fn t_macro_backend_with_args(id: &str, args: TranslationArgs, cx: Scope) ->
 #[doc(hidden)]
 pub fn link_macro_backend(url: &str, cx: Scope) -> String {
     let translator = use_context::<Signal<super::Translator>>(cx).get_untracked();
-    translator.url(url)
+    // Use the localized version of the path, if the template it's under has one
+    let url = crate::template::RenderCtx::from_ctx(cx)
+        .route_registry
+        .localize_path(url, &translator.get_locale());
+    translator.url(&url)
 }
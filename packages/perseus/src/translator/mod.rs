@@ -1,6 +1,16 @@
 /// Errors for translators. These are separate so new translators can easily be
 /// created in a modular fashion.
 pub mod errors;
+// Typed arguments are shared by all translators
+mod args;
+#[doc(hidden)]
+pub use args::check_numeric_arg;
+pub use args::{NumericArg, PluralCategory, TranslationArg};
+// As is locale-aware formatting
+#[cfg(feature = "formatting")]
+mod format;
+#[cfg(feature = "formatting")]
+pub use format::{DateStyle, Formatter};
 
 // We export each translator by name
 #[cfg(feature = "translator-fluent")]
@@ -96,26 +106,60 @@ pub use DummyTranslator as Translator;
 ))]
 pub use DUMMY_TRANSLATOR_FILE_EXT as TRANSLATOR_FILE_EXT;
 
+// The compile-time checks for the `t!` macro need the procedural macros, so
+// they're skipped without them
+#[cfg(not(feature = "macros"))]
+#[doc(hidden)]
+pub use crate::check_translation;
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub use perseus_macro::check_translation;
+#[cfg(not(feature = "macros"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! check_translation {
+    ($($tokens:tt)*) => {};
+}
+
 /// Translates the given ID conveniently, taking arguments for interpolation as
 /// required. The final argument to any call of this macro must be a Sycamore
 /// reactive scope provided to the relevant Perseus template.
+///
+/// Arguments can be anything the translator accepts (with Fluent, anything
+/// that converts into a `FluentValue`, like strings and numbers). Dates can be
+/// given as a [`TranslationArg`].
+///
+/// If the ID is a string literal and your app's Fluent translations are in
+/// `translations/` (the default, which can be changed by setting
+/// `PERSEUS_TRANSLATIONS_DIR` at compile-time), this will check them at
+/// compile-time, warning if the ID doesn't exist in any locale, if any of the
+/// variables the translation uses haven't been given, or if any of the given
+/// arguments aren't used by the translation in any locale. It will fail if a
+/// variable used as a number (e.g. to select a plural form) has been given
+/// something that isn't a number (see [`NumericArg`]). The translations files
+/// are tracked as inputs to the crate that calls this, so these checks will be
+/// rerun whenever they change.
 #[macro_export]
 macro_rules! t {
-    // When there are no arguments to interpolate
-    ($id:expr, $cx:expr) => {
-        $crate::i18n::t_macro_backend($id, $cx)
-    };
-    // When there are arguments to interpolate
+    // When there are arguments to interpolate (this has to come first, or the
+    // arguments would be parsed as a block for the scope, which fails for more
+    // than one argument)
     ($id:expr, {
         // NOTE Using a colon here leads to literally impossible to solve cast errors based on compiler misinterpretations
         $($key:literal = $value:expr),+
     }, $cx:expr) => {{
+        $crate::i18n::check_translation!($crate, $id, { $($key = $value),+ });
         let mut args = $crate::i18n::TranslationArgs::new();
         $(
             args.set($key, $value);
         )+
         $crate::i18n::t_macro_backend_with_args($id, args, $cx)
     }};
+    // When there are no arguments to interpolate
+    ($id:expr, $cx:expr) => {{
+        $crate::i18n::check_translation!($crate, $id, {});
+        $crate::i18n::t_macro_backend($id, $cx)
+    }};
 }
 /// Gets the link to the given resource in internationalized form conveniently.
 /// The final argument to any call of this macro must be a Sycamore reactive
@@ -0,0 +1,125 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::errors::BuildError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::router::hash_bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::server::get_static_alias_mount;
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::RwLock;
+
+/// The fingerprinted URLs of the app's static aliases, which the engine sets
+/// whenever it generates or loads the render configuration.
+#[cfg(not(target_arch = "wasm32"))]
+static ASSET_URLS: RwLock<Option<BTreeMap<String, String>>> = RwLock::new(None);
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// The fingerprinted URLs of the app's static aliases, which are read from
+    /// the render configuration the first time they're needed.
+    static ASSET_URLS: RefCell<Option<BTreeMap<String, String>>> = RefCell::new(None);
+}
+
+/// Gets the URL that the static alias at the given URL (e.g. `"logo.png"`)
+/// should be referenced by, which will include a hash of the file's contents if
+/// the app fingerprints its static aliases, as well as the app's path prefix.
+/// This can be used anywhere in templates and heads.
+#[macro_export]
+macro_rules! asset_url {
+    ($url:expr) => {
+        $crate::utils::get_asset_url($url)
+    };
+}
+
+/// Gets the URL that the static alias at the given URL (e.g. `/logo.png`, the
+/// leading slash is optional) should be referenced by, including the app's
+/// path prefix. If static aliases are being fingerprinted (see
+/// `PerseusApp::fingerprint_static_aliases`), this will be the URL with the
+/// hash of the file's contents in it, otherwise it'll be the URL of the alias
+/// as it is.
+///
+/// This works in templates (on both the engine-side and in the browser) and in
+/// heads, and is usually used through the `asset_url!` macro.
+pub fn get_asset_url(url: &str) -> String {
+    let url = format!("/{}", url.trim_start_matches('/'));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let (path_prefix, asset_url) = {
+        // If the lock has been poisoned, the URLs are still perfectly usable
+        let asset_urls = ASSET_URLS.read().unwrap_or_else(|err| err.into_inner());
+        let asset_url = asset_urls.as_ref().and_then(|urls| urls.get(&url)).cloned();
+        (super::get_path_prefix_server(), asset_url)
+    };
+    #[cfg(target_arch = "wasm32")]
+    let (path_prefix, asset_url) = ASSET_URLS.with(|asset_urls| {
+        let mut asset_urls = asset_urls.borrow_mut();
+        let asset_urls = asset_urls.get_or_insert_with(|| {
+            crate::shell::get_render_cfg()
+                .map(|render_cfg| render_cfg.assets)
+                .unwrap_or_default()
+        });
+        (
+            super::get_path_prefix_client(),
+            asset_urls.get(&url).cloned(),
+        )
+    });
+
+    format!("{}{}", path_prefix, asset_url.unwrap_or(url))
+}
+
+/// Sets the fingerprinted URLs of the app's static aliases, which will be used
+/// by [`get_asset_url`] from then on.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_asset_urls(asset_urls: BTreeMap<String, String>) {
+    *ASSET_URLS.write().unwrap_or_else(|err| err.into_inner()) = Some(asset_urls);
+}
+
+/// Fingerprints the given static aliases by hashing the contents of the files
+/// they point to, returning a map of their URLs to URLs with those hashes in
+/// them (e.g. `/logo.png` to `/logo.1a2b3c4d5e6f7a8b.png`). Aliases that mount
+/// whole directories are left as they are.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn fingerprint_static_aliases(
+    static_aliases: &HashMap<String, String>,
+) -> Result<BTreeMap<String, String>, BuildError> {
+    let mut asset_urls = BTreeMap::new();
+    for (url, path) in static_aliases {
+        if get_static_alias_mount(url).is_some() {
+            continue;
+        }
+        let contents =
+            std::fs::read(path).map_err(|err| BuildError::FingerprintStaticAliasFailed {
+                url: url.to_string(),
+                path: path.to_string(),
+                source: err,
+            })?;
+        let hash = hash_bytes(&contents);
+        // The hash goes before the extension, so the right content type is still
+        // inferred
+        let (dir, filename) = url.rsplit_once('/').unwrap_or(("", url));
+        let hashed_filename = match filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => format!("{}.{}.{}", stem, hash, ext),
+            _ => format!("{}.{}", filename, hash),
+        };
+        asset_urls.insert(url.to_string(), format!("{}/{}", dir, hashed_filename));
+    }
+
+    Ok(asset_urls)
+}
+
+/// Adds the fingerprinted URLs of the given static aliases to them, so that
+/// each file can be served at both.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn add_fingerprinted_aliases(
+    static_aliases: &mut HashMap<String, String>,
+    asset_urls: &BTreeMap<String, String>,
+) {
+    for (url, asset_url) in asset_urls {
+        if let Some(path) = static_aliases.get(url).cloned() {
+            static_aliases.insert(asset_url.to_string(), path);
+        }
+    }
+}
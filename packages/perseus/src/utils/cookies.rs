@@ -0,0 +1,91 @@
+use crate::experiments::Variants;
+use crate::session::SessionHandle;
+
+/// Finds the value of the cookie with the given name in the given `Cookie`
+/// headers, if it's there.
+pub(crate) fn get_cookie<'a>(
+    cookie_headers: impl IntoIterator<Item = &'a str>,
+    name: &str,
+) -> Option<&'a str> {
+    cookie_headers
+        .into_iter()
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value)
+}
+
+/// The `Cache-Control` header that should be sent with any response that
+/// depends on the user's cookies (e.g. their session or CSRF token), so that
+/// it's never served to anyone else by a shared cache.
+pub const PRIVATE_CACHE_CONTROL: &str = "private, no-store";
+
+/// Marks the given response headers as depending on the user's cookies (see
+/// [`PRIVATE_CACHE_CONTROL`]), replacing any `Cache-Control` header set by the
+/// page.
+///
+/// This is designed for use by server integrations.
+pub fn make_private(headers: &mut http::HeaderMap) {
+    headers.insert(
+        http::header::CACHE_CONTROL,
+        http::HeaderValue::from_static(PRIVATE_CACHE_CONTROL),
+    );
+    headers.append(http::header::VARY, http::HeaderValue::from_static("Cookie"));
+}
+
+/// Checks whether or not a response to a request for a page has to be kept out
+/// of shared caches (see [`make_private`]). This is the case if it depends on
+/// the user's session (or if it changes it), if it embeds a CSRF token, or if
+/// the user has been assigned any experiment variants, since those are given
+/// to request-state functions and embedded in initial loads.
+///
+/// This is designed for use by server integrations.
+pub fn is_private_response(
+    session: Option<&SessionHandle>,
+    sets_session_cookie: bool,
+    variants: &Variants,
+    has_csrf_token: bool,
+) -> bool {
+    has_csrf_token
+        || !variants.is_empty()
+        || sets_session_cookie
+        || session.map_or(false, |session| session.is_private())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{MemorySessionStore, Sessions};
+
+    #[test]
+    fn responses_are_private_when_they_depend_on_cookies() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let sessions = Sessions::new(MemorySessionStore::default());
+            let session = sessions.load(None::<&str>).await.unwrap();
+            let no_variants = Variants::default();
+            let variants = Variants::single("header", "blue");
+
+            assert!(!is_private_response(None, false, &no_variants, false));
+            assert!(!is_private_response(
+                Some(&session),
+                false,
+                &no_variants,
+                false
+            ));
+            assert!(is_private_response(None, false, &variants, false));
+            assert!(is_private_response(None, false, &no_variants, true));
+            assert!(is_private_response(None, true, &no_variants, false));
+
+            let _ = session.lock().get::<String>("user");
+            assert!(is_private_response(
+                Some(&session),
+                false,
+                &no_variants,
+                false
+            ));
+        });
+    }
+}
@@ -1,7 +1,12 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::{convert::TryFrom, time};
+use thiserror::Error;
 
 /// Represents a duration that can be computed relative to the current time.
+///
+/// This can be created from a [`std::time::Duration`], a [`chrono::Duration`],
+/// a [`Duration`], or a time string of a form like `1w` (see
+/// [`Duration`]'s implementation of `TryFrom<&str>`).
 #[derive(Debug, Clone)]
 pub struct ComputedDuration(chrono::Duration);
 
@@ -14,7 +19,11 @@ impl ComputedDuration {
 
     /// Get the timestamp of the duration added to the current time.
     pub fn compute_timestamp(&self) -> String {
-        let current = Utc::now();
+        self.compute_timestamp_from(Utc::now())
+    }
+
+    /// Get the timestamp of the duration added to the given time.
+    pub fn compute_timestamp_from(&self, current: DateTime<Utc>) -> String {
         let datetime = current + self.0;
         datetime.to_rfc3339()
     }
@@ -34,8 +43,54 @@ pub struct Duration {
     seconds: i64,
 }
 
-/// An error type for invalid `String` durations.
-pub struct InvalidDuration;
+/// An error type for invalid durations, which records the value that was
+/// provided.
+#[derive(Error, Debug, Clone)]
+#[error("invalid duration '{value}' (durations must be positive, and time strings must be of a form like `1w` or `1d12h`, using the units s, m, h, d, w, M, and y)")]
+pub struct InvalidDuration {
+    /// The duration that was provided.
+    pub value: String,
+}
+
+impl TryFrom<time::Duration> for ComputedDuration {
+    type Error = InvalidDuration;
+
+    fn try_from(value: time::Duration) -> Result<Self, Self::Error> {
+        match chrono::Duration::from_std(value) {
+            Ok(duration) if duration > chrono::Duration::zero() => Ok(Self(duration)),
+            _ => Err(InvalidDuration {
+                value: format!("{:?}", value),
+            }),
+        }
+    }
+}
+impl TryFrom<chrono::Duration> for ComputedDuration {
+    type Error = InvalidDuration;
+
+    fn try_from(value: chrono::Duration) -> Result<Self, Self::Error> {
+        if value > chrono::Duration::zero() {
+            Ok(Self(value))
+        } else {
+            Err(InvalidDuration {
+                value: value.to_string(),
+            })
+        }
+    }
+}
+impl TryFrom<Duration> for ComputedDuration {
+    type Error = InvalidDuration;
+
+    fn try_from(value: Duration) -> Result<Self, Self::Error> {
+        Self::try_from(time::Duration::from(value))
+    }
+}
+impl TryFrom<&str> for ComputedDuration {
+    type Error = InvalidDuration;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::try_from(Duration::try_from(value)?)
+    }
+}
 
 impl From<Duration> for time::Duration {
     fn from(duration: Duration) -> Self {
@@ -55,6 +110,9 @@ impl TryFrom<&str> for Duration {
     type Error = InvalidDuration;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || InvalidDuration {
+            value: value.to_string(),
+        };
         let mut duration = Self::default();
 
         // A working variable to store the '123' part of an interval until we reach the
@@ -67,9 +125,10 @@ impl TryFrom<&str> for Duration {
             if c.is_numeric() {
                 curr_duration_length.push(c);
             } else {
-                let interval_length: i64 = curr_duration_length.parse().unwrap(); // It's just a string of numbers, we know more than the compiler
+                // This will fail if there wasn't a number before the indicator
+                let interval_length: i64 = curr_duration_length.parse().map_err(|_| invalid())?;
                 if interval_length <= 0 {
-                    return Err(InvalidDuration);
+                    return Err(invalid());
                 }
 
                 match c {
@@ -80,13 +139,39 @@ impl TryFrom<&str> for Duration {
                     'w' if duration.weeks == 0 => duration.weeks = interval_length,
                     'M' if duration.months == 0 => duration.months = interval_length,
                     'y' if duration.years == 0 => duration.years = interval_length,
-                    _ => return Err(InvalidDuration),
+                    _ => return Err(invalid()),
                 };
 
                 curr_duration_length = String::new();
             }
         }
+        // A number without an indicator (or an empty string) is meaningless
+        if !curr_duration_length.is_empty() || value.is_empty() {
+            return Err(invalid());
+        }
 
         Ok(duration)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_durations() {
+        assert!(ComputedDuration::try_from("1w").is_ok());
+        assert!(ComputedDuration::try_from("1d12h").is_ok());
+        assert!(ComputedDuration::try_from(time::Duration::from_secs(5)).is_ok());
+        assert!(ComputedDuration::try_from(chrono::Duration::days(1)).is_ok());
+
+        for invalid in ["", "w", "10", "1d1d", "1x", "1.5h"] {
+            assert_eq!(
+                ComputedDuration::try_from(invalid).unwrap_err().value,
+                invalid
+            );
+        }
+        assert!(ComputedDuration::try_from(time::Duration::ZERO).is_err());
+        assert!(ComputedDuration::try_from(chrono::Duration::days(-1)).is_err());
+    }
+}
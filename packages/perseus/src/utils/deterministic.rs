@@ -0,0 +1,43 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::env;
+
+/// Checks if the app is being built deterministically (i.e. with `perseus
+/// build --deterministic`), in which case everything generated at build-time
+/// should be the same across builds of the same code. If your state generation
+/// uses any randomness, you should seed it with [`get_build_seed`] when this is
+/// `true`.
+pub fn is_deterministic_build() -> bool {
+    matches!(
+        env::var("PERSEUS_DETERMINISTIC").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// Gets the time that should be used as 'now' when generating build artifacts.
+/// In deterministic builds, this is `SOURCE_DATE_EPOCH` (which the CLI will
+/// set for you), and otherwise it's the actual current time.
+pub(crate) fn get_build_time() -> DateTime<Utc> {
+    if is_deterministic_build() {
+        let epoch = env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+            .unwrap_or(0);
+        Utc.timestamp_opt(epoch, 0)
+            .single()
+            .unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap())
+    } else {
+        Utc::now()
+    }
+}
+
+/// Gets a seed that can be used for any randomness at build-time. In
+/// deterministic builds, this will be derived from `SOURCE_DATE_EPOCH`, so it
+/// will be the same for every build of the same commit, and otherwise it will
+/// be different every time.
+pub fn get_build_seed() -> u64 {
+    let time = get_build_time();
+    // The nanoseconds are always zero in deterministic builds
+    (time.timestamp() as u64)
+        .wrapping_mul(1_000_000_000)
+        .wrapping_add(time.timestamp_subsec_nanos() as u64)
+}
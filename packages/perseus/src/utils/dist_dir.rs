@@ -0,0 +1,21 @@
+/// Gets the directory that the app's build artifacts are kept in. This uses
+/// the `PERSEUS_DIST_DIR` environment variable, which the CLI sets when it's
+/// been given `--out-dir`, and otherwise defaults to `dist` (relative to the
+/// root of the project). Trailing forward slashes will be trimmed
+/// automatically.
+///
+/// Note that the server reads this at runtime too, so, if you're running a
+/// server that was built with a custom output directory, you'll need to set
+/// the same variable for it (unless it's been packaged with `perseus deploy`,
+/// which always uses `dist`).
+pub fn get_dist_dir() -> String {
+    let dist_dir = std::env::var("PERSEUS_DIST_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| "dist".to_string());
+    match dist_dir.trim_end_matches('/') {
+        // This would be the root of the filesystem
+        "" => "/".to_string(),
+        dist_dir => dist_dir.to_string(),
+    }
+}
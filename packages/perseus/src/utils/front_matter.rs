@@ -0,0 +1,173 @@
+use super::BuildProfile;
+use crate::errors::FrontMatterError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashMap;
+
+/// The front matter of a piece of content (e.g. a Markdown blog post), which
+/// is a block of `key: value` lines between two `---` lines at the very start
+/// of it:
+///
+/// ```text
+/// ---
+/// title: My First Post
+/// draft: false
+/// publish_at: 2023-01-01T09:00:00Z
+/// ---
+/// The post itself...
+/// ```
+///
+/// This only supports simple values on single lines (surrounding quotes will
+/// be removed), not the whole of YAML. Two keys are understood by Perseus:
+/// `draft`, which marks content as a draft that should only be built in
+/// development or previews, and `publish_at`, which stops content from being
+/// published until the given time (in RFC 3339 format, or as a date or
+/// `YYYY-MM-DD HH:MM:SS`, both in UTC). [`FrontMatter::is_published`] takes
+/// both of these into account, so it can be used to filter your build paths.
+///
+/// Content that will be published in future still has to appear once its time
+/// comes: any page that lists it should have its template use
+/// `.scheduled_revalidation()`, and call
+/// [`StateGeneratorInfo::schedule_revalidation`](crate::template::StateGeneratorInfo::schedule_revalidation)
+/// with the publishing time, while the template for the content itself should
+/// use incremental generation (returning a 404 from its build state function
+/// while the content is unpublished).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    /// Whether or not the content is a draft.
+    pub draft: bool,
+    /// The time at which the content should be published, if it's scheduled.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// All the fields in the front matter, including `draft` and
+    /// `publish_at`.
+    fields: HashMap<String, String>,
+}
+impl FrontMatter {
+    /// Parses the front matter at the start of the given content, returning
+    /// it and the rest of the content. If there isn't any front matter, the
+    /// default (a published non-draft with no fields) will be returned with
+    /// all the content.
+    ///
+    /// This will fail if `draft` or `publish_at` have invalid values, or if
+    /// the front matter is never closed.
+    pub fn parse(content: &str) -> Result<(Self, &str), FrontMatterError> {
+        let mut lines = content.trim_start_matches('\u{feff}').split_inclusive('\n');
+        if lines.next().map(str::trim_end) != Some("---") {
+            return Ok((Self::default(), content));
+        }
+
+        let mut front_matter = Self::default();
+        for line in &mut lines {
+            let line = line.trim();
+            if line == "---" {
+                let body = lines.collect::<Vec<_>>().concat();
+                let body_start = content.len() - body.len();
+                return Ok((front_matter, &content[body_start..]));
+            }
+            let (key, val) = match line.split_once(':') {
+                Some((key, val)) if !line.starts_with('#') => (key.trim(), unquote(val.trim())),
+                _ => continue,
+            };
+            match key {
+                "draft" => front_matter.draft = val.parse().map_err(|_| invalid_field(key, val))?,
+                "publish_at" => {
+                    front_matter.publish_at =
+                        Some(parse_time(val).ok_or_else(|| invalid_field(key, val))?)
+                }
+                _ => (),
+            }
+            front_matter.fields.insert(key.to_string(), val.to_string());
+        }
+
+        Err(FrontMatterError::Unclosed)
+    }
+    /// Gets the value of the given field, if it's in the front matter.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(|val| val.as_str())
+    }
+    /// Checks if the content should be built now, which it should be unless
+    /// it's a draft (and drafts aren't being shown, see [`drafts_visible`]),
+    /// or it's scheduled to be published in future.
+    pub fn is_published(&self) -> bool {
+        (!self.draft || drafts_visible())
+            && self
+                .publish_at
+                .map_or(true, |publish_at| publish_at <= Utc::now())
+    }
+}
+
+/// Checks if content marked as a draft should be built, which it will be
+/// whenever the app is built with the preview [`BuildProfile`] (which
+/// development builds are by default).
+pub fn drafts_visible() -> bool {
+    BuildProfile::current().is_preview()
+}
+
+/// Removes the quotes around the given value, if there are any.
+fn unquote(val: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| val.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(val)
+}
+
+/// Parses a publishing time, which may be in RFC 3339 format, or a date or
+/// date and time in UTC.
+fn parse_time(val: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(val) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(val, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(val, "%Y-%m-%d %H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(val, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+
+    Some(Utc.from_utc_datetime(&naive))
+}
+
+/// Creates the error for a field with an invalid value.
+fn invalid_field(field: &str, val: &str) -> FrontMatterError {
+    FrontMatterError::InvalidField {
+        field: field.to_string(),
+        value: val.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_front_matter() {
+        let (front_matter, body) = FrontMatter::parse(
+            "---\ntitle: \"Hello: World\"\ndraft: true\npublish_at: 2001-02-03\n---\n# Hello\n",
+        )
+        .unwrap();
+        assert_eq!(body, "# Hello\n");
+        assert_eq!(front_matter.get("title"), Some("Hello: World"));
+        assert!(front_matter.draft);
+        assert_eq!(
+            front_matter.publish_at.unwrap().to_rfc3339(),
+            "2001-02-03T00:00:00+00:00"
+        );
+
+        let (front_matter, body) = FrontMatter::parse("No front matter").unwrap();
+        assert_eq!(body, "No front matter");
+        assert!(front_matter.is_published());
+        let (scheduled, _) =
+            FrontMatter::parse("---\npublish_at: 9999-01-01T00:00:00Z\n---\n").unwrap();
+        assert!(!scheduled.is_published());
+
+        assert!(matches!(
+            FrontMatter::parse("---\ndraft: maybe\n---\n"),
+            Err(FrontMatterError::InvalidField { .. })
+        ));
+        assert!(matches!(
+            FrontMatter::parse("---\ntitle: Hi\n"),
+            Err(FrontMatterError::Unclosed)
+        ));
+    }
+}
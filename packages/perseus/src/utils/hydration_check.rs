@@ -0,0 +1,183 @@
+use crate::errors::ClientError;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Node};
+
+/// The maximum number of mismatches that will be reported for a single page
+/// (after the first few, the rest are usually consequences of them).
+const MAX_REPORTED_MISMATCHES: usize = 10;
+
+/// A copy of the prerendered DOM of a page, taken before it's hydrated, so that
+/// it can be compared with what the browser's first render produced. If the two
+/// diverge, Sycamore will silently patch over the differences (or panic
+/// somewhere deep inside itself), so this is used in development to report
+/// exactly where the server and the browser disagree.
+pub(crate) struct HydrationSnapshot(Node);
+impl HydrationSnapshot {
+    /// Takes a snapshot of the prerendered content in the given container.
+    pub(crate) fn take(container: &Element) -> Self {
+        Self(container.clone_node_with_deep(true).unwrap())
+    }
+    /// Compares this snapshot with the current content of the given container
+    /// (which should have just been hydrated), logging a warning to the
+    /// console for every mismatch found. If there are any, this will return an
+    /// error for the app's error reporter.
+    pub(crate) fn check(
+        &self,
+        container: &Element,
+        template_path: &str,
+        page_path: &str,
+    ) -> Option<ClientError> {
+        let mut mismatches = Vec::new();
+        diff_children(&self.0, container, "", &mut mismatches);
+        if mismatches.is_empty() {
+            return None;
+        }
+
+        let omitted = mismatches.len().saturating_sub(MAX_REPORTED_MISMATCHES);
+        mismatches.truncate(MAX_REPORTED_MISMATCHES);
+        let mut msg = format!(
+            "Hydration mismatch in template '{}' (at page '{}'): the HTML the server prerendered differs from the browser's first render. This usually means the template renders differently depending on where it's run (e.g. by reading from the browser, the time, or randomness during rendering, rather than in an effect).\n",
+            template_path, page_path
+        );
+        for mismatch in &mismatches {
+            msg.push_str(&format!("\n  - {}", mismatch));
+        }
+        if omitted > 0 {
+            msg.push_str(&format!("\n  ...and {} more", omitted));
+        }
+        web_sys::console::warn_1(&msg.into());
+
+        Some(ClientError::HydrationMismatch {
+            template_name: template_path.to_string(),
+            path: page_path.to_string(),
+            mismatches,
+        })
+    }
+}
+
+/// Compares the children of the given server and browser nodes, recording any
+/// mismatches with the given path to their parent.
+fn diff_children(server: &Node, client: &Node, path: &str, mismatches: &mut Vec<String>) {
+    let server_children = server.child_nodes();
+    let client_children = client.child_nodes();
+    let len = server_children.length().max(client_children.length());
+    for idx in 0..len {
+        match (server_children.item(idx), client_children.item(idx)) {
+            (Some(server), Some(client)) => {
+                let child_path = if path.is_empty() {
+                    format!("{}[{}]", describe(&server), idx)
+                } else {
+                    format!("{} > {}[{}]", path, describe(&server), idx)
+                };
+                diff_nodes(&server, &client, &child_path, mismatches);
+            }
+            (Some(server), None) => mismatches.push(format!(
+                "{}: the server rendered {}, but the browser didn't",
+                or_root(path),
+                describe_with_content(&server)
+            )),
+            (None, Some(client)) => mismatches.push(format!(
+                "{}: the browser rendered {}, but the server didn't",
+                or_root(path),
+                describe_with_content(&client)
+            )),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+/// Compares the given server and browser nodes (and their descendants),
+/// recording any mismatches.
+fn diff_nodes(server: &Node, client: &Node, path: &str, mismatches: &mut Vec<String>) {
+    if server.node_type() != client.node_type() || server.node_name() != client.node_name() {
+        mismatches.push(format!(
+            "{}: the server rendered {}, but the browser rendered {}",
+            path,
+            describe_with_content(server),
+            describe_with_content(client)
+        ));
+        return;
+    }
+
+    match (server.dyn_ref::<Element>(), client.dyn_ref::<Element>()) {
+        (Some(server_elem), Some(client_elem)) => {
+            diff_attributes(server_elem, client_elem, path, mismatches);
+            diff_children(server, client, path, mismatches);
+        }
+        // Text and comment nodes
+        _ => {
+            let server_val = server.node_value().unwrap_or_default();
+            let client_val = client.node_value().unwrap_or_default();
+            if server_val != client_val {
+                mismatches.push(format!(
+                    "{}: the server rendered {:?}, but the browser rendered {:?}",
+                    path, server_val, client_val
+                ));
+            }
+        }
+    }
+}
+
+/// Compares the attributes of the given server and browser elements, recording
+/// any mismatches.
+fn diff_attributes(server: &Element, client: &Element, path: &str, mismatches: &mut Vec<String>) {
+    let server_attrs = server.attributes();
+    for idx in 0..server_attrs.length() {
+        let attr = server_attrs.item(idx).unwrap();
+        match client.get_attribute(&attr.name()) {
+            Some(val) if val == attr.value() => (),
+            Some(val) => mismatches.push(format!(
+                "{}: attribute '{}' was {:?} on the server, but {:?} in the browser",
+                path,
+                attr.name(),
+                attr.value(),
+                val
+            )),
+            None => mismatches.push(format!(
+                "{}: attribute '{}' was set on the server, but not in the browser",
+                path,
+                attr.name()
+            )),
+        }
+    }
+    let client_attrs = client.attributes();
+    for idx in 0..client_attrs.length() {
+        let attr = client_attrs.item(idx).unwrap();
+        if !server.has_attribute(&attr.name()) {
+            mismatches.push(format!(
+                "{}: attribute '{}' was set in the browser, but not on the server",
+                path,
+                attr.name()
+            ));
+        }
+    }
+}
+
+/// Describes the given node briefly, for use in a path.
+fn describe(node: &Node) -> String {
+    node.node_name().to_lowercase()
+}
+
+/// Describes the given node along with (the start of) its content.
+fn describe_with_content(node: &Node) -> String {
+    let content = node.text_content().unwrap_or_default();
+    let content = content.trim();
+    if content.is_empty() {
+        format!("a '{}' node", describe(node))
+    } else {
+        let mut preview = content.chars().take(40).collect::<String>();
+        if content.chars().count() > 40 {
+            preview.push_str("...");
+        }
+        format!("a '{}' node containing {:?}", describe(node), preview)
+    }
+}
+
+/// Gets a path to display, using a placeholder for the container itself.
+fn or_root(path: &str) -> &str {
+    if path.is_empty() {
+        "(page root)"
+    } else {
+        path
+    }
+}
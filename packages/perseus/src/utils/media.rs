@@ -0,0 +1,140 @@
+use sycamore::prelude::{create_rc_signal, RcSignal};
+
+/// The media query for users who prefer a dark color scheme.
+#[cfg(target_arch = "wasm32")]
+const DARK_QUERY: &str = "(prefers-color-scheme: dark)";
+/// The media query for users who prefer reduced motion.
+#[cfg(target_arch = "wasm32")]
+const REDUCED_MOTION_QUERY: &str = "(prefers-reduced-motion: reduce)";
+/// The media query for viewports that are at least [`Breakpoint::Medium`].
+#[cfg(target_arch = "wasm32")]
+const MEDIUM_QUERY: &str = "(min-width: 640px)";
+/// The media query for viewports that are [`Breakpoint::Large`].
+#[cfg(target_arch = "wasm32")]
+const LARGE_QUERY: &str = "(min-width: 1024px)";
+
+/// The color scheme the user has asked for (through
+/// `prefers-color-scheme`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// A light color scheme. This is the default, and it's what will be used
+    /// on the server-side.
+    Light,
+    /// A dark color scheme.
+    Dark,
+}
+
+/// A broad category of viewport width, which can be used to adapt layouts in
+/// ways CSS alone can't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Breakpoint {
+    /// A viewport narrower than 640px (e.g. a phone).
+    Small,
+    /// A viewport at least 640px wide, but narrower than 1024px (e.g. a
+    /// tablet).
+    Medium,
+    /// A viewport at least 1024px wide. This is what will be used on the
+    /// server-side.
+    Large,
+}
+
+/// The reactive media query signals stored in the render context. On the
+/// server-side, and until the first page has been rendered (and hydrated) in
+/// the browser, these hold defaults, so that the browser's first render matches
+/// what the server sent. After that, they're kept up-to-date with the browser.
+#[derive(Debug, Clone)]
+pub(crate) struct MediaSignals {
+    pub color_scheme: RcSignal<ColorScheme>,
+    pub reduced_motion: RcSignal<bool>,
+    pub breakpoint: RcSignal<Breakpoint>,
+}
+impl Default for MediaSignals {
+    fn default() -> Self {
+        Self {
+            color_scheme: create_rc_signal(ColorScheme::Light),
+            reduced_motion: create_rc_signal(false),
+            breakpoint: create_rc_signal(Breakpoint::Large),
+        }
+    }
+}
+#[cfg(target_arch = "wasm32")]
+impl MediaSignals {
+    /// Sets all the signals to the browser's actual values, and keeps them
+    /// up-to-date from then on. This should only be called once, after the
+    /// first page has been rendered.
+    pub(crate) fn connect(&self) {
+        let color_scheme = self.color_scheme.clone();
+        watch_media_query(DARK_QUERY, move |dark| {
+            color_scheme.set(if dark {
+                ColorScheme::Dark
+            } else {
+                ColorScheme::Light
+            })
+        });
+        let reduced_motion = self.reduced_motion.clone();
+        watch_media_query(REDUCED_MOTION_QUERY, move |reduce| {
+            reduced_motion.set(reduce)
+        });
+        // Each of these only tells us about one edge, so we check both whenever either
+        // changes
+        let breakpoint = self.breakpoint.clone();
+        let update_breakpoint = move |_| {
+            let window = web_sys::window().unwrap();
+            let matches = |query| {
+                window
+                    .match_media(query)
+                    .ok()
+                    .flatten()
+                    .map(|mql| mql.matches())
+                    .unwrap_or(false)
+            };
+            breakpoint.set(if matches(LARGE_QUERY) {
+                Breakpoint::Large
+            } else if matches(MEDIUM_QUERY) {
+                Breakpoint::Medium
+            } else {
+                Breakpoint::Small
+            });
+        };
+        watch_media_query(MEDIUM_QUERY, update_breakpoint.clone());
+        watch_media_query(LARGE_QUERY, update_breakpoint);
+    }
+}
+
+/// Calls the given function with whether or not the given media query matches
+/// now, and again whenever that changes, for the lifetime of the app.
+#[cfg(target_arch = "wasm32")]
+fn watch_media_query(query: &str, f: impl Fn(bool) + Clone + 'static) {
+    if let Some((mql, listener)) = listen_to_media_query(query, f.clone()) {
+        f(mql.matches());
+        // This will be needed for as long as the app is running
+        listener.forget();
+    }
+}
+
+/// Adds a listener to the given media query that will call the given function
+/// with whether or not it matches whenever that changes. This returns the
+/// query list (so the caller can check if it matches now) and the listener,
+/// which the caller must keep alive for as long as it wants updates (and
+/// remove from the query list when it's done).
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn listen_to_media_query(
+    query: &str,
+    f: impl Fn(bool) + 'static,
+) -> Option<(
+    web_sys::MediaQueryList,
+    wasm_bindgen::closure::Closure<dyn FnMut()>,
+)> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let mql = web_sys::window()
+        .unwrap()
+        .match_media(query)
+        .ok()
+        .flatten()?;
+    let mql_clone = mql.clone();
+    let listener = Closure::wrap(Box::new(move || f(mql_clone.matches())) as Box<dyn FnMut()>);
+    mql.add_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+        .ok()?;
+    Some((mql, listener))
+}
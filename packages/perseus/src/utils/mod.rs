@@ -1,17 +1,66 @@
+mod asset_url;
 mod async_fn_trait;
 #[cfg(not(target_arch = "wasm32"))]
 mod cache_res;
 mod context;
 #[cfg(not(target_arch = "wasm32"))]
+mod cookies;
+#[cfg(not(target_arch = "wasm32"))]
 mod decode_time_str;
+#[cfg(not(target_arch = "wasm32"))]
+mod deterministic;
+#[cfg(not(target_arch = "wasm32"))]
+mod dist_dir;
+#[cfg(not(target_arch = "wasm32"))]
+mod front_matter;
+#[cfg(all(feature = "hydrate", debug_assertions, target_arch = "wasm32"))]
+mod hydration_check;
 mod log;
+mod media;
+#[cfg(any(all(feature = "client-helpers", target_arch = "wasm32"), test))]
+mod panic_trace;
 mod path_prefix;
+mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+mod random;
+mod robots;
 mod test;
+mod toc;
 
+pub use asset_url::get_asset_url;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use asset_url::{add_fingerprinted_aliases, fingerprint_static_aliases, set_asset_urls};
 pub(crate) use async_fn_trait::AsyncFnReturn;
 #[cfg(not(target_arch = "wasm32"))]
 pub use cache_res::{cache_fallible_res, cache_res};
 pub(crate) use context::provide_context_signal_replace;
 #[cfg(not(target_arch = "wasm32"))]
+pub(crate) use cookies::get_cookie;
+#[cfg(not(target_arch = "wasm32"))]
+pub use cookies::{is_private_response, make_private, PRIVATE_CACHE_CONTROL};
+#[cfg(not(target_arch = "wasm32"))]
 pub use decode_time_str::{ComputedDuration, Duration, InvalidDuration};
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use deterministic::get_build_time;
+#[cfg(not(target_arch = "wasm32"))]
+pub use deterministic::{get_build_seed, is_deterministic_build};
+#[cfg(not(target_arch = "wasm32"))]
+pub use dist_dir::get_dist_dir;
+#[cfg(not(target_arch = "wasm32"))]
+pub use front_matter::{drafts_visible, FrontMatter};
+#[cfg(all(feature = "hydrate", debug_assertions, target_arch = "wasm32"))]
+pub(crate) use hydration_check::HydrationSnapshot;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use media::listen_to_media_query;
+pub(crate) use media::MediaSignals;
+pub use media::{Breakpoint, ColorScheme};
+#[cfg(all(feature = "client-helpers", target_arch = "wasm32"))]
+pub(crate) use panic_trace::{source_mapped_panic_hook, source_maps_enabled};
 pub use path_prefix::*;
+pub use profile::BuildProfile;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use random::{random_token, random_u64};
+pub use robots::{RobotsEnv, RobotsTxt};
+#[cfg(not(target_arch = "wasm32"))]
+pub use toc::extract_toc_from_view;
+pub use toc::{extract_toc, Toc, TocEntry};
@@ -0,0 +1,114 @@
+/// Frames from the panic machinery itself, which will be left out of
+/// symbolicated stack traces, since they're the same for every panic.
+const PANIC_MACHINERY: [&str; 8] = [
+    "std::panicking",
+    "core::panicking",
+    "std::sys",
+    "std::rt",
+    "rust_begin_unwind",
+    "rust_panic",
+    "__rust_",
+    "perseus::utils::panic_trace",
+];
+
+/// Checks if the app was built with source maps enabled in development (i.e.
+/// `--wasm-dev-source-maps` was given to the CLI, or `source_maps` was set in
+/// the `[dev]` section of `Perseus.toml`), in which case panics will be
+/// reported with symbolicated stack traces.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn source_maps_enabled() -> bool {
+    cfg!(debug_assertions) && matches!(option_env!("PERSEUS_WASM_SOURCE_MAPS"), Some("1"))
+}
+
+/// Reports the given panic to the console with the Rust source location it
+/// occurred at and a stack trace of the Rust functions that led to it (using
+/// the names the browser resolves from the bundle's name section), rather than
+/// the raw Wasm stack trace that ends in an `unreachable` trap. Each frame
+/// keeps its position in the bundle, which browser tools that support DWARF can
+/// resolve to a source line.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn source_mapped_panic_hook(info: &std::panic::PanicInfo) {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        type Error;
+        #[wasm_bindgen(constructor)]
+        fn new() -> Error;
+        #[wasm_bindgen(structural, method, getter)]
+        fn stack(error: &Error) -> String;
+    }
+
+    let mut msg = info.to_string();
+    let frames = symbolicate_stack(&Error::new().stack());
+    if !frames.is_empty() {
+        msg.push_str("\n\nStack trace (most recent call first):");
+        for frame in frames {
+            msg.push_str(&format!("\n    at {}", frame));
+        }
+    }
+    web_sys::console::error_1(&msg.into());
+}
+
+/// Extracts the Rust frames from the given JS stack trace (in the formats used
+/// by both Chromium and Firefox), removing the hashes `rustc` adds to symbol
+/// names and the frames from the panic machinery.
+pub(crate) fn symbolicate_stack(stack: &str) -> Vec<String> {
+    stack
+        .lines()
+        .map(|line| line.trim())
+        .filter_map(|line| {
+            // Chromium: `at name (url:wasm-function[12]:0x345)`
+            // Firefox: `name@url:wasm-function[12]:0x345`
+            let (name, location) = match line.strip_prefix("at ") {
+                Some(line) => line.split_once(" (")?,
+                None => line.split_once('@')?,
+            };
+            let location = &location[location.find("wasm-function")?..];
+            let location = location.trim_end_matches(')');
+            Some((strip_symbol_hash(name), location))
+        })
+        .skip_while(|(name, _)| {
+            name.is_empty()
+                || PANIC_MACHINERY.iter().any(|prefix| {
+                    name.starts_with(prefix) || name.starts_with(&format!("<{}", prefix))
+                })
+                || name.starts_with("console_error_panic_hook")
+        })
+        .map(|(name, location)| format!("{} ({})", name, location))
+        .collect()
+}
+
+/// Removes the hash `rustc` adds to the end of legacy symbol names (e.g.
+/// `::h0123456789abcdef`).
+fn strip_symbol_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((name, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            name
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbolicates_wasm_stack() {
+        let stack = "Error\n    at imports.wbg.__wbg_new_abda76e883ba8a5f (http://localhost:8080/.perseus/bundle.js:372:21)\n    at std::panicking::rust_panic_with_hook::h1a2b3c4d5e6f7a8b (http://localhost:8080/.perseus/bundle.wasm:wasm-function[80]:0x1a2b)\n    at app::templates::index::index_page::h0123456789abcdef (http://localhost:8080/.perseus/bundle.wasm:wasm-function[123]:0x4567)\n    at sycamore::render::h0123456789abcdef (http://localhost:8080/.perseus/bundle.wasm:wasm-function[9]:0x89)";
+        assert_eq!(
+            symbolicate_stack(stack),
+            vec![
+                "app::templates::index::index_page (wasm-function[123]:0x4567)",
+                "sycamore::render (wasm-function[9]:0x89)"
+            ]
+        );
+
+        let stack = "core::panicking::panic_fmt::h0123456789abcdef@http://localhost:8080/.perseus/bundle.wasm:wasm-function[70]:0x12\napp::main::h0123456789abcdef@http://localhost:8080/.perseus/bundle.wasm:wasm-function[5]:0x34";
+        assert_eq!(
+            symbolicate_stack(stack),
+            vec!["app::main (wasm-function[5]:0x34)"]
+        );
+    }
+}
@@ -3,17 +3,63 @@
 /// something as changeable as this into the final binary. Hence however, that
 /// variable must be the same as what's set in `<base>` (done automatically).
 /// Trailing forward slashes will be trimmed automatically.
+///
+/// If the base path is an absolute URL and a public host has been set (see
+/// [`get_public_host`]), the host in the URL will be replaced with it, so that
+/// assets can be loaded by devices that reach the server at a different
+/// address.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn get_path_prefix_server() -> String {
     use std::env;
 
     let base_path = env::var("PERSEUS_BASE_PATH").unwrap_or_else(|_| "".to_string());
+    let base_path = match get_public_host() {
+        Some(public_host) => replace_url_host(&base_path, &public_host),
+        None => base_path,
+    };
     base_path
         .strip_suffix('/')
         .unwrap_or(&base_path)
         .to_string()
 }
 
+/// Checks whether or not browsers will reach the app over HTTPS, which is the
+/// case if its base path (see [`get_path_prefix_server`]) is an `https://`
+/// URL. Cookies set by Perseus will be marked `Secure` if so.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_served_over_https() -> bool {
+    get_path_prefix_server().starts_with("https://")
+}
+
+/// Gets the host browsers will reach the server at, if that's different from
+/// the one it's listening on (e.g. when testing on other devices on a local
+/// network). This uses the `PERSEUS_PUBLIC_HOST` environment variable, which
+/// is set by `perseus serve --public-host`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_public_host() -> Option<String> {
+    std::env::var("PERSEUS_PUBLIC_HOST")
+        .ok()
+        .filter(|host| !host.is_empty())
+}
+
+/// Replaces the host in the given URL, keeping its scheme, port, and path. If
+/// the URL isn't absolute, it will be returned unchanged.
+#[cfg(not(target_arch = "wasm32"))]
+fn replace_url_host(url: &str, host: &str) -> String {
+    let (scheme, rest) = match url.split_once("://") {
+        Some(parts) => parts,
+        None => return url.to_string(),
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    // IPv6 addresses contain colons, but they'll be in square brackets
+    let port = match authority.rsplit_once(':') {
+        Some((_, port)) if !port.contains(']') => format!(":{}", port),
+        _ => String::new(),
+    };
+
+    format!("{}://{}{}{}", scheme, host, port, path)
+}
+
 /// Gets the path prefix to apply in the browser. This uses the HTML `<base>`
 /// element, which would be required anyway to make Sycamore's router co-operate
 /// with a relative path hosting.
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use std::cell::Cell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::env;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::RwLock;
+
+/// The profile the app's artifacts were built with, which the engine sets
+/// whenever it loads the render configuration.
+#[cfg(not(target_arch = "wasm32"))]
+static BUILT_PROFILE: RwLock<Option<BuildProfile>> = RwLock::new(None);
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// The profile the app was built with, which is read from the render
+    /// configuration the first time it's needed.
+    static BUILT_PROFILE: Cell<Option<BuildProfile>> = Cell::new(None);
+}
+
+/// The profile an app is being built with, which lets state generation
+/// functions behave differently for preview deployments (e.g. fetching from a
+/// staging API and including drafts) without checking environment variables
+/// themselves.
+///
+/// At build-time, this is set with the `PERSEUS_PROFILE` environment variable
+/// (which the CLI sets when it's given `--profile`). If that isn't set,
+/// development builds use the preview profile, and release builds use the
+/// production profile. The profile is then recorded in the render
+/// configuration, so the server (and the browser) will always use the same
+/// profile as the build, wherever it's deployed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildProfile {
+    /// A build for previewing content before it goes live, which includes
+    /// drafts.
+    Preview,
+    /// A build of the live app.
+    #[default]
+    Production,
+}
+impl BuildProfile {
+    /// Gets the profile the app is currently being built with, or was built
+    /// with, if its artifacts have been loaded. If `PERSEUS_PROFILE` is set to
+    /// something other than `preview` or `production`, it will be ignored
+    /// (the engine will refuse to start in that case anyway).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn current() -> Self {
+        // If the lock has been poisoned, the profile is still perfectly usable
+        if let Some(profile) = *BUILT_PROFILE.read().unwrap_or_else(|err| err.into_inner()) {
+            return profile;
+        }
+        match Self::from_env() {
+            Ok(Some(profile)) => profile,
+            _ if cfg!(debug_assertions) => Self::Preview,
+            _ => Self::Production,
+        }
+    }
+    /// Gets the profile the app was built with.
+    #[cfg(target_arch = "wasm32")]
+    pub fn current() -> Self {
+        BUILT_PROFILE.with(|profile| match profile.get() {
+            Some(profile) => profile,
+            None => {
+                let built_profile = crate::shell::get_render_cfg()
+                    .map(|render_cfg| render_cfg.profile)
+                    .unwrap_or_default();
+                profile.set(Some(built_profile));
+                built_profile
+            }
+        })
+    }
+    /// Gets the profile explicitly set with `PERSEUS_PROFILE`, if there is
+    /// one, returning the value it's been set to if that isn't a valid
+    /// profile.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_env() -> Result<Option<Self>, String> {
+        match env::var("PERSEUS_PROFILE") {
+            Ok(profile) => match profile.as_str() {
+                "preview" => Ok(Some(Self::Preview)),
+                "production" => Ok(Some(Self::Production)),
+                "" => Ok(None),
+                _ => Err(profile),
+            },
+            Err(_) => Ok(None),
+        }
+    }
+    /// Sets the profile the app's artifacts were built with, which will be
+    /// returned by [`BuildProfile::current`] from then on.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_built(profile: Self) {
+        *BUILT_PROFILE.write().unwrap_or_else(|err| err.into_inner()) = Some(profile);
+    }
+    /// Checks if this is the preview profile.
+    pub fn is_preview(&self) -> bool {
+        *self == Self::Preview
+    }
+    /// Checks if this is the production profile.
+    pub fn is_production(&self) -> bool {
+        *self == Self::Production
+    }
+    /// Picks between the given values for the preview and production profiles
+    /// (e.g. the URLs of a staging API and a live one).
+    pub fn select<T>(&self, preview: T, production: T) -> T {
+        match self {
+            Self::Preview => preview,
+            Self::Production => production,
+        }
+    }
+}
+impl fmt::Display for BuildProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preview => write!(f, "preview"),
+            Self::Production => write!(f, "production"),
+        }
+    }
+}
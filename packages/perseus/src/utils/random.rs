@@ -0,0 +1,38 @@
+/// Gets the given number of random bytes from the operating system's
+/// cryptographically secure random number generator.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    // This can only fail if the operating system has no source of randomness at
+    // all, in which case nothing that needs to be secure could work anyway
+    getrandom::getrandom(&mut bytes).expect("couldn't get randomness from the operating system");
+    bytes
+}
+
+/// Generates a random number with the operating system's cryptographically
+/// secure random number generator.
+pub(crate) fn random_u64() -> u64 {
+    u64::from_le_bytes(random_bytes())
+}
+
+/// Generates a random 128-bit token (e.g. a session ID or a CSRF token) with
+/// the operating system's cryptographically secure random number generator,
+/// encoded as hex.
+pub(crate) fn random_token() -> String {
+    random_bytes::<16>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_unique_hex() {
+        let token = random_token();
+        assert_eq!(token.len(), 32);
+        assert!(token.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_ne!(token, random_token());
+    }
+}
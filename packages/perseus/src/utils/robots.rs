@@ -0,0 +1,155 @@
+use super::BuildProfile;
+
+/// The environments that a rule in a [`RobotsTxt`] can apply to. Perseus
+/// considers anything built with debug assertions to be development, and
+/// anything built with the production [`BuildProfile`] (e.g. `perseus
+/// deploy`, unless it's given `--profile preview`) to be production.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RobotsEnv {
+    /// The rule will always be applied.
+    All,
+    /// The rule will only be applied in development.
+    Development,
+    /// The rule will only be applied when building with the preview profile
+    /// (including in development), which is useful for keeping preview
+    /// deployments out of search engines.
+    Preview,
+    /// The rule will only be applied in production.
+    Production,
+}
+impl RobotsEnv {
+    /// Checks if this environment includes the one the app is currently being
+    /// built in.
+    fn is_current(&self) -> bool {
+        match self {
+            Self::All => true,
+            Self::Development => cfg!(debug_assertions),
+            Self::Preview => BuildProfile::current().is_preview(),
+            Self::Production => BuildProfile::current().is_production(),
+        }
+    }
+}
+
+/// A single rule in a `robots.txt` file.
+#[derive(Clone, Debug)]
+struct RobotsRule {
+    user_agent: String,
+    env: RobotsEnv,
+    allow: bool,
+    path: String,
+}
+
+/// The configuration for an app's `robots.txt` file, which tells web crawlers
+/// which pages they can and can't visit. If you set this with
+/// `PerseusApp::robots_txt()`, Perseus will generate a `robots.txt` file at
+/// build-time and when exporting, and it will be served at `/robots.txt` by
+/// all the server integrations (overriding any static alias at that URL).
+///
+/// Rules apply to all crawlers by default, but you can use `.user_agent()` to
+/// target rules added after it to specific crawlers. Rules can also be
+/// limited to development or production, which is useful for keeping staging
+/// deployments out of search engines.
+#[derive(Clone, Debug)]
+pub struct RobotsTxt {
+    /// The rules, in the order they were added.
+    rules: Vec<RobotsRule>,
+    /// The user agent that newly added rules will apply to.
+    current_user_agent: String,
+    /// The URLs of the app's sitemaps.
+    sitemaps: Vec<String>,
+}
+impl Default for RobotsTxt {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            current_user_agent: "*".to_string(),
+            sitemaps: Vec::new(),
+        }
+    }
+}
+impl RobotsTxt {
+    /// Creates a new `robots.txt` configuration with no rules, which will
+    /// allow all crawlers to visit every page.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the user agent that all rules added after this will apply to. By
+    /// default, rules apply to all crawlers (`*`).
+    pub fn user_agent(mut self, val: &str) -> Self {
+        self.current_user_agent = val.to_string();
+        self
+    }
+    /// Allows crawlers to visit the given path in all environments.
+    pub fn allow(self, path: &str) -> Self {
+        self.allow_in(RobotsEnv::All, path)
+    }
+    /// Disallows crawlers from visiting the given path in all environments.
+    pub fn disallow(self, path: &str) -> Self {
+        self.disallow_in(RobotsEnv::All, path)
+    }
+    /// Allows crawlers to visit the given path in the given environment.
+    pub fn allow_in(self, env: RobotsEnv, path: &str) -> Self {
+        self.rule(env, true, path)
+    }
+    /// Disallows crawlers from visiting the given path in the given
+    /// environment.
+    pub fn disallow_in(self, env: RobotsEnv, path: &str) -> Self {
+        self.rule(env, false, path)
+    }
+    /// Adds the URL of a sitemap for crawlers to use. This should be a full
+    /// URL, including the protocol and domain.
+    pub fn sitemap(mut self, url: &str) -> Self {
+        self.sitemaps.push(url.to_string());
+        self
+    }
+    /// Adds a rule for the current user agent.
+    fn rule(mut self, env: RobotsEnv, allow: bool, path: &str) -> Self {
+        self.rules.push(RobotsRule {
+            user_agent: self.current_user_agent.clone(),
+            env,
+            allow,
+            path: path.to_string(),
+        });
+        self
+    }
+    /// Renders this configuration into the contents of a `robots.txt` file
+    /// for the environment the app is currently being built in.
+    pub fn render(&self) -> String {
+        // Group the rules by user agent, keeping the order in which each agent first
+        // appeared
+        let mut groups: Vec<(&str, Vec<&RobotsRule>)> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.env.is_current()) {
+            match groups
+                .iter_mut()
+                .find(|(user_agent, _)| *user_agent == rule.user_agent)
+            {
+                Some((_, rules)) => rules.push(rule),
+                None => groups.push((&rule.user_agent, vec![rule])),
+            }
+        }
+
+        let mut sections = Vec::new();
+        if groups.is_empty() {
+            // An empty `Disallow` directive allows everything
+            sections.push("User-agent: *\nDisallow:".to_string());
+        }
+        for (user_agent, rules) in groups {
+            let mut section = format!("User-agent: {}", user_agent);
+            for rule in rules {
+                let directive = if rule.allow { "Allow" } else { "Disallow" };
+                section.push_str(&format!("\n{}: {}", directive, rule.path));
+            }
+            sections.push(section);
+        }
+        if !self.sitemaps.is_empty() {
+            let sitemaps: Vec<String> = self
+                .sitemaps
+                .iter()
+                .map(|url| format!("Sitemap: {}", url))
+                .collect();
+            sections.push(sitemaps.join("\n"));
+        }
+
+        sections.join("\n\n") + "\n"
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+#[cfg(not(target_arch = "wasm32"))]
+use sycamore::prelude::{Scope, View};
+#[cfg(not(target_arch = "wasm32"))]
+use sycamore::web::SsrNode;
+
+/// A table of contents, made from the headings in some HTML (see
+/// [`extract_toc`]). This can be stored in a page's state, so that a layout
+/// can render a sidebar for the page without having to scrape the DOM after
+/// it's been hydrated.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Toc {
+    /// The top-level headings, each of which contains the headings nested
+    /// under it.
+    pub entries: Vec<TocEntry>,
+}
+impl Toc {
+    /// Whether or not there are any headings in this table of contents.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Adds the given heading after all the others, nesting it under the last
+    /// heading of a higher level.
+    fn push(&mut self, entry: TocEntry) {
+        let mut entries = &mut self.entries;
+        loop {
+            match entries.last() {
+                Some(last) if entry.level > last.level => {
+                    entries = &mut entries.last_mut().unwrap().children
+                }
+                _ => break,
+            }
+        }
+        entries.push(entry);
+    }
+}
+
+/// A single heading in a [`Toc`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TocEntry {
+    /// The level of the heading (e.g. `2` for an `<h2>`).
+    pub level: u8,
+    /// The ID of the heading, which can be linked to with `#id`.
+    pub id: String,
+    /// The text of the heading, without any markup.
+    pub text: String,
+    /// The headings nested under this one.
+    pub children: Vec<TocEntry>,
+}
+
+/// Extracts a table of contents from the headings (`<h1>` to `<h6>`) in the
+/// given HTML. Any headings that don't have an `id` will be given one based
+/// on their text (e.g. `getting-started`), so the HTML with those added is
+/// returned too, and that's what should be rendered.
+///
+/// This is designed for use in build state functions (e.g. after rendering
+/// Markdown to HTML), so that the table of contents can be stored in the
+/// page's state.
+pub fn extract_toc(html: &str) -> (String, Toc) {
+    let mut toc = Toc::default();
+    let mut out = String::with_capacity(html.len());
+    // The IDs of headings we've already seen, so the ones we generate are unique
+    let mut ids = HashSet::new();
+    let mut rest = html;
+
+    while let Some((start, level)) = find_heading(rest) {
+        let tag_end = match rest[start..].find('>') {
+            Some(idx) => start + idx,
+            None => break,
+        };
+        let closing = format!("</h{}>", level);
+        let content_end = match find_ignore_case(&rest[tag_end..], &closing) {
+            Some(idx) => tag_end + idx,
+            None => break,
+        };
+        let attrs = &rest[start + 3..tag_end];
+        let text = get_text(&rest[tag_end + 1..content_end]);
+
+        out.push_str(&rest[..tag_end]);
+        let id = match get_id(attrs) {
+            Some(id) => id,
+            None => {
+                let id = unique_id(&slugify(&text), &ids);
+                out.push_str(&format!(" id=\"{}\"", id));
+                id
+            }
+        };
+        out.push_str(&rest[tag_end..content_end + closing.len()]);
+        rest = &rest[content_end + closing.len()..];
+
+        ids.insert(id.clone());
+        toc.push(TocEntry {
+            level,
+            id,
+            text,
+            children: Vec::new(),
+        });
+    }
+    out.push_str(rest);
+
+    (out, toc)
+}
+
+/// Renders the given view to a string and extracts a table of contents from
+/// it, as with [`extract_toc`]. This is useful when the content of a page is
+/// written as a Sycamore view, rather than as HTML.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn extract_toc_from_view(view: impl FnOnce(Scope) -> View<SsrNode>) -> (String, Toc) {
+    extract_toc(&sycamore::render_to_string(view))
+}
+
+/// Finds the start of the next heading tag in the given HTML, along with its
+/// level.
+fn find_heading(html: &str) -> Option<(usize, u8)> {
+    let bytes = html.as_bytes();
+    let mut from = 0;
+    while let Some(idx) = html[from..].find('<') {
+        let start = from + idx;
+        if let [b'h' | b'H', level @ b'1'..=b'6', next, ..] = bytes[start + 1..] {
+            if next == b'>' || next.is_ascii_whitespace() {
+                return Some((start, level - b'0'));
+            }
+        }
+        from = start + 1;
+    }
+
+    None
+}
+
+/// Finds the given ASCII string in the given HTML, ignoring case.
+fn find_ignore_case(html: &str, needle: &str) -> Option<usize> {
+    html.as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Gets the value of the `id` attribute from the given attributes of a tag, if
+/// there is one.
+fn get_id(attrs: &str) -> Option<String> {
+    let mut rest = attrs;
+    while let Some(idx) = find_ignore_case(rest, "id=") {
+        let preceded_by_space = idx == 0 || rest.as_bytes()[idx - 1].is_ascii_whitespace();
+        rest = &rest[idx + 3..];
+        if !preceded_by_space {
+            continue;
+        }
+        let id = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next(),
+            _ => rest
+                .split(|c: char| c.is_ascii_whitespace() || c == '/')
+                .next(),
+        };
+        return id.filter(|id| !id.is_empty()).map(decode_entities);
+    }
+
+    None
+}
+
+/// Gets the text of the given HTML, without any tags, and with its whitespace
+/// collapsed.
+fn get_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decodes the HTML entities Sycamore (and most Markdown renderers) produce
+/// when escaping text.
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Turns the given heading text into an ID (e.g. `Getting Started!` becomes
+/// `getting-started`).
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    match slug.trim_matches('-') {
+        "" => "section".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+/// Makes the given ID unique among the given ones by adding a number to it if
+/// necessary.
+fn unique_id(id: &str, ids: &HashSet<String>) -> String {
+    if !ids.contains(id) {
+        return id.to_string();
+    }
+    (1..)
+        .map(|num| format!("{}-{}", id, num))
+        .find(|id| !ids.contains(id))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_nested_headings() {
+        let html = r#"<h1>Docs</h1><p>Intro</p><h2 id="setup" class="x">Set <em>up</em></h2><H3>A &amp; B</H3><h2>Set up</h2><h2>Set up</h2><h1>FAQ</h1>"#;
+        let (html, toc) = extract_toc(html);
+
+        assert_eq!(
+            html,
+            r#"<h1 id="docs">Docs</h1><p>Intro</p><h2 id="setup" class="x">Set <em>up</em></h2><H3 id="a-b">A &amp; B</H3><h2 id="set-up">Set up</h2><h2 id="set-up-1">Set up</h2><h1 id="faq">FAQ</h1>"#
+        );
+        assert_eq!(toc.entries.len(), 2);
+        let docs = &toc.entries[0];
+        assert_eq!(
+            docs.children
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<Vec<_>>(),
+            ["setup", "set-up", "set-up-1"]
+        );
+        assert_eq!(docs.children[0].text, "Set up");
+        assert_eq!(docs.children[0].children[0].text, "A & B");
+        assert!(extract_toc("<header>No headings</header>").1.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use crate::errors::WebError;
+
+/// Writes the given text to the user's clipboard. Browsers will usually only
+/// allow this in secure contexts, and often only in response to user
+/// interaction (e.g. in a click handler). On the engine-side, this will return
+/// [`WebError::NotInBrowser`].
+#[allow(unused_variables)]
+pub async fn write_clipboard_text(text: &str) -> Result<(), WebError> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let promise = get_clipboard()?.write_text(text);
+        wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map_err(super::js_err)?;
+        Ok(())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    Err(WebError::NotInBrowser)
+}
+
+/// Reads text from the user's clipboard. Browsers will usually ask the user
+/// for permission to do this, and they'll only allow it in secure contexts. On
+/// the engine-side, this will return [`WebError::NotInBrowser`].
+pub async fn read_clipboard_text() -> Result<String, WebError> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let promise = get_clipboard()?.read_text();
+        let text = wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map_err(super::js_err)?;
+        // The promise should always resolve to a string
+        Ok(text.as_string().unwrap_or_default())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    Err(WebError::NotInBrowser)
+}
+
+/// Gets the clipboard, if the browser makes it available (it won't in insecure
+/// contexts).
+#[cfg(target_arch = "wasm32")]
+fn get_clipboard() -> Result<web_sys::Clipboard, WebError> {
+    use sycamore::rt::{JsCast, Reflect};
+
+    let navigator = web_sys::window().unwrap().navigator();
+    // This would be `undefined` if the API isn't available, which `web_sys` doesn't
+    // account for
+    match Reflect::get(&navigator, &"clipboard".into()) {
+        Ok(clipboard) if !clipboard.is_undefined() => Ok(clipboard.unchecked_into()),
+        _ => Err(WebError::Unavailable {
+            api: "clipboard".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,87 @@
+use crate::errors::WebError;
+
+/// A position reported by the browser's geolocation API.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPosition {
+    /// The latitude, in decimal degrees.
+    pub latitude: f64,
+    /// The longitude, in decimal degrees.
+    pub longitude: f64,
+    /// The accuracy of the latitude and longitude, in meters.
+    pub accuracy: f64,
+    /// The altitude in meters above the WGS84 ellipsoid, if the device can
+    /// provide it.
+    pub altitude: Option<f64>,
+    /// The time at which this position was taken, in milliseconds since the
+    /// Unix epoch.
+    pub timestamp: f64,
+}
+
+/// Gets the user's current position. Browsers will ask the user for permission
+/// to do this, and they'll only allow it in secure contexts. On the
+/// engine-side, this will return [`WebError::NotInBrowser`].
+pub async fn get_current_position() -> Result<GeoPosition, WebError> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use futures::channel::oneshot;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use sycamore::rt::JsCast;
+        use wasm_bindgen::closure::Closure;
+
+        let geolocation = web_sys::window()
+            .unwrap()
+            .navigator()
+            .geolocation()
+            .map_err(|_| WebError::Unavailable {
+                api: "geolocation".to_string(),
+            })?;
+
+        // Only one of these callbacks will be called, so they share the sender
+        let (tx, rx) = oneshot::channel();
+        let tx = Rc::new(RefCell::new(Some(tx)));
+        let success_tx = tx.clone();
+        let on_success = Closure::wrap(Box::new(move |pos: web_sys::Position| {
+            let coords = pos.coords();
+            let res = Ok(GeoPosition {
+                latitude: coords.latitude(),
+                longitude: coords.longitude(),
+                accuracy: coords.accuracy(),
+                altitude: coords.altitude(),
+                timestamp: pos.timestamp(),
+            });
+            if let Some(tx) = success_tx.borrow_mut().take() {
+                let _ = tx.send(res);
+            }
+        }) as Box<dyn FnMut(web_sys::Position)>);
+        let on_error = Closure::wrap(Box::new(move |err: web_sys::PositionError| {
+            let res = Err(WebError::GeolocationFailed {
+                code: err.code(),
+                message: err.message(),
+            });
+            if let Some(tx) = tx.borrow_mut().take() {
+                let _ = tx.send(res);
+            }
+        }) as Box<dyn FnMut(web_sys::PositionError)>);
+
+        geolocation
+            .get_current_position_with_error_callback(
+                on_success.as_ref().unchecked_ref(),
+                Some(on_error.as_ref().unchecked_ref()),
+            )
+            .map_err(super::js_err)?;
+        // The callbacks have to stay alive until one of them has been called
+        let res = rx.await;
+        drop((on_success, on_error));
+        match res {
+            Ok(res) => res,
+            // The browser dropped both callbacks without calling either
+            Err(_) => Err(WebError::GeolocationFailed {
+                code: 0,
+                message: "no position was reported".to_string(),
+            }),
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    Err(WebError::NotInBrowser)
+}
@@ -0,0 +1,32 @@
+//! SSR-safe wrappers around common browser APIs. Templates are rendered on
+//! both the engine-side and the browser-side, so using `web_sys` directly
+//! means carefully gating everything to avoid panicking on
+//! `web_sys::window().unwrap()` during server-side rendering. Everything in
+//! here can be called on either side, and it will just return
+//! [`WebError::NotInBrowser`](crate::errors::WebError::NotInBrowser) on the
+//! engine-side.
+//!
+//! Note that these should still usually be called from effects or event
+//! handlers, since anything that depends on them won't be in the HTML the
+//! server generates.
+
+mod clipboard;
+mod geolocation;
+mod storage;
+
+pub use clipboard::{read_clipboard_text, write_clipboard_text};
+pub use geolocation::{get_current_position, GeoPosition};
+pub use storage::WebStorage;
+
+/// Checks if we're running in the browser, where the APIs in this module are
+/// available.
+pub fn is_browser() -> bool {
+    cfg!(target_arch = "wasm32")
+}
+
+/// Converts an error from JavaScript into a
+/// [`WebError`](crate::errors::WebError).
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn js_err(err: wasm_bindgen::JsValue) -> crate::errors::WebError {
+    crate::errors::WebError::Js(format!("{:?}", err))
+}
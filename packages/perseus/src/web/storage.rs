@@ -0,0 +1,75 @@
+use crate::errors::WebError;
+
+/// An SSR-safe handle to one of the browser's storage areas, either
+/// `localStorage` (which persists across sessions) or `sessionStorage` (which
+/// is cleared when the tab is closed). On the engine-side, every operation will
+/// return [`WebError::NotInBrowser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebStorage {
+    session: bool,
+}
+#[allow(unused_variables)]
+impl WebStorage {
+    /// Gets a handle to `localStorage`.
+    pub fn local() -> Self {
+        Self { session: false }
+    }
+    /// Gets a handle to `sessionStorage`.
+    pub fn session() -> Self {
+        Self { session: true }
+    }
+    /// Gets the value stored under the given key, if there is one.
+    pub fn get(&self, key: &str) -> Result<Option<String>, WebError> {
+        #[cfg(target_arch = "wasm32")]
+        return self.get_storage()?.get_item(key).map_err(super::js_err);
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(WebError::NotInBrowser)
+    }
+    /// Stores the given value under the given key, replacing whatever was
+    /// there before. This will fail if the browser's storage quota has been
+    /// exceeded.
+    pub fn set(&self, key: &str, value: &str) -> Result<(), WebError> {
+        #[cfg(target_arch = "wasm32")]
+        return self
+            .get_storage()?
+            .set_item(key, value)
+            .map_err(super::js_err);
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(WebError::NotInBrowser)
+    }
+    /// Removes the value stored under the given key. If there isn't one, this
+    /// will do nothing.
+    pub fn remove(&self, key: &str) -> Result<(), WebError> {
+        #[cfg(target_arch = "wasm32")]
+        return self.get_storage()?.remove_item(key).map_err(super::js_err);
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(WebError::NotInBrowser)
+    }
+    /// Removes everything in this storage area (for the current origin).
+    pub fn clear(&self) -> Result<(), WebError> {
+        #[cfg(target_arch = "wasm32")]
+        return self.get_storage()?.clear().map_err(super::js_err);
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(WebError::NotInBrowser)
+    }
+
+    /// Gets the underlying storage area. Browsers may refuse access to this
+    /// (e.g. if the user has disabled cookies).
+    #[cfg(target_arch = "wasm32")]
+    fn get_storage(&self) -> Result<web_sys::Storage, WebError> {
+        let (api, storage) = if self.session {
+            (
+                "sessionStorage",
+                web_sys::window().unwrap().session_storage(),
+            )
+        } else {
+            ("localStorage", web_sys::window().unwrap().local_storage())
+        };
+        match storage {
+            Ok(Some(storage)) => Ok(storage),
+            _ => Err(WebError::Unavailable {
+                api: api.to_string(),
+            }),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::components::comparisons::{render_lighthouse_score, Comparison};
 use crate::components::container::{Container, ContainerProps};
 use crate::components::info_svg::INFO_SVG;
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{t, ErrorCause, GenericErrorWithCause, Html, RenderFnResultWithCause, Template};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -359,8 +361,7 @@ pub fn get_template<G: Html>() -> Template<G> {
 
 #[perseus::build_state]
 pub async fn get_build_state(
-    _path: String,
-    _locale: String,
+    _info: StateGeneratorInfo,
 ) -> RenderFnResultWithCause<ComparisonsPageProps> {
     use walkdir::WalkDir;
 
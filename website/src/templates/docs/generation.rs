@@ -2,6 +2,8 @@ use crate::templates::docs::get_file_at_version::get_file_at_version;
 use crate::templates::docs::icons::{ERROR_ICON, WARNING_ICON};
 use crate::templates::docs::template::DocsPageProps;
 use lazy_static::lazy_static;
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{t, RenderFnResult, RenderFnResultWithCause};
 #[cfg(not(target_arch = "wasm32"))]
 use pulldown_cmark::{html, Options, Parser};
@@ -175,13 +177,11 @@ pub fn get_beta_versions(manifest: &DocsManifest) -> HashMap<String, VersionMani
 }
 
 #[perseus::build_state]
-pub async fn get_build_state(
-    path: String,
-    locale: String,
-) -> RenderFnResultWithCause<DocsPageProps> {
+pub async fn get_build_state(info: StateGeneratorInfo) -> RenderFnResultWithCause<DocsPageProps> {
     use perseus::utils::get_path_prefix_server;
     use regex::Regex;
 
+    let StateGeneratorInfo { path, locale, .. } = info;
     let path_vec: Vec<&str> = path.split('/').collect();
     // Localize the path again to what it'll be on the filesystem
     // TODO get Perseus to pass in props from build paths for ease of use?
@@ -4,6 +4,8 @@
 
 use crate::components::container::{Container, ContainerProps};
 use crate::components::trusted_svg::TRUSTED_SVG;
+#[cfg(not(target_arch = "wasm32"))]
+use perseus::StateGeneratorInfo;
 use perseus::{t, RenderFnResultWithCause, Template};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -131,14 +133,11 @@ pub fn get_template<G: Html>() -> Template<G> {
 }
 
 #[perseus::build_state]
-async fn get_build_state(
-    _path: String,
-    locale: String,
-) -> RenderFnResultWithCause<PluginsPageProps> {
+async fn get_build_state(info: StateGeneratorInfo) -> RenderFnResultWithCause<PluginsPageProps> {
     // This is the root page, so we want a list of plugins and a small amount of
     // information about each This directory loop is relative to `.perseus/`
     let mut plugins = Vec::new();
-    for entry in WalkDir::new(&format!("plugins/{}", locale)) {
+    for entry in WalkDir::new(&format!("plugins/{}", info.locale)) {
         let entry = entry?;
         let path = entry.path();
         // Ignore any empty directories or the like